@@ -1,11 +1,15 @@
 use ast::{Attribute, AttributeList};
+use itertools::Itertools;
 use local_impl::local_impl;
 use spade_ast as ast;
 use spade_common::location_info::{Loc, WithLocation};
-use spade_diagnostics::Diagnostic;
+use spade_common::name::Identifier;
+use spade_diagnostics::lint::LintLevel;
+use spade_diagnostics::{Diagnostic, Lint};
 use spade_hir as hir;
 
 use crate::error::Result;
+use crate::Context;
 
 #[local_impl]
 impl AttributeListExt for AttributeList {
@@ -64,3 +68,31 @@ impl LocAttributeExt for Loc<ast::Attribute> {
         .primary_label(format!("Unsupported attribute for {on}"))
     }
 }
+
+/// Sets the level of the lints listed in an `#[allow(...)]`, `#[warn(...)]` or `#[deny(...)]`
+/// attribute for the code in `scope`
+pub fn set_lint_levels(
+    level: ast::LintLevel,
+    lints: &[Loc<Identifier>],
+    attr: &Loc<ast::Attribute>,
+    scope: Loc<()>,
+    ctx: &mut Context,
+) -> Result<()> {
+    let level = match level {
+        ast::LintLevel::Allow => LintLevel::Allow,
+        ast::LintLevel::Warn => LintLevel::Warn,
+        ast::LintLevel::Deny => LintLevel::Deny,
+    };
+    for name in lints {
+        let Some(lint) = Lint::from_name(&name.inner.0) else {
+            return Err(Diagnostic::error(name, format!("Unknown lint {name}"))
+                .primary_label("Unknown lint")
+                .note(format!(
+                    "The known lints are {}",
+                    Lint::ALL.iter().map(|lint| lint.name()).join(", ")
+                )));
+        };
+        ctx.lints.set_level(scope, lint, level, attr);
+    }
+    Ok(())
+}
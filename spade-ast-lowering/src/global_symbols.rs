@@ -88,6 +88,9 @@ pub fn visit_item(item: &ast::Item, ctx: &mut Context) -> Result<()> {
                 Path(vec![def.name.clone()]).at_loc(&def.name),
                 Thing::Trait(def.name.clone()),
             )?;
+            if let Some(doc) = &def.doc {
+                ctx.item_list.docs.insert(name.clone(), doc.inner.clone());
+            }
 
             crate::create_trait_from_unit_heads(
                 hir::TraitName::Named(name.at_loc(&def.name)),
@@ -280,24 +283,23 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
             let mut hir_options = vec![];
 
             for (i, option) in e.options.iter().enumerate() {
-                if let Some(prev) = member_names.get(&option.0) {
-                    let new = &option.0;
+                let (doc, option_name, option_args) = option;
+                if let Some(prev) = member_names.get(option_name) {
+                    let new = option_name;
                     return Err(
                         Diagnostic::error(new, format!("Multiple options called {}", new))
                             .primary_label(format!("{} occurs more than once", new))
                             .secondary_label(prev, "Previously occurred here"),
                     );
                 }
-                member_names.insert(option.0.clone());
+                member_names.insert(option_name.clone());
                 // Check the parameter list
-                let parameter_list = option
-                    .1
+                let parameter_list = option_args
                     .clone()
                     .map(|l| visit_parameter_list(&l, ctx))
                     .unwrap_or_else(|| Ok(hir::ParameterList(vec![]).nowhere()))?;
 
-                let args = option
-                    .1
+                let args = option_args
                     .clone()
                     .map(|l| {
                         if let Some(self_) = l.self_ {
@@ -309,7 +311,7 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
                     .unwrap_or(Ok(vec![]))?;
 
                 // Ensure that we don't have any port types in the enum variants
-                for (_, _, ty) in args {
+                for (_, _, _, ty) in args {
                     visit_type_spec(&ty, &TypeSpecKind::EnumMember, ctx)?;
                     if ty.is_port(&ctx.symtab)? {
                         return Err(Diagnostic::error(ty, "Port in enum")
@@ -319,7 +321,7 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
                 }
 
                 let variant_thing = EnumVariant {
-                    name: option.0.clone(),
+                    name: option_name.clone(),
                     output_type: hir::TypeSpec::Declared(
                         declaration_id.clone(),
                         output_type_exprs.clone(),
@@ -333,9 +335,14 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
                 // Add option constructor to symtab at the outer scope
                 let head_id = ctx.symtab.add_thing_at_offset(
                     1,
-                    Path(vec![e.name.clone(), option.0.clone()]),
-                    Thing::EnumVariant(variant_thing.at_loc(&option.0)),
+                    Path(vec![e.name.clone(), option_name.clone()]),
+                    Thing::EnumVariant(variant_thing.at_loc(option_name)),
                 );
+                if let Some(doc) = doc {
+                    ctx.item_list
+                        .docs
+                        .insert(head_id.clone(), doc.inner.clone());
+                }
                 // Add option constructor to item list
                 ctx.item_list.executables.insert(
                     head_id.clone(),
@@ -348,7 +355,7 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
                 // NOTE: it's kind of weird to push head_id here, since that's just
                 // the constructor. In the future, if we move forward with enum members
                 // being individual types, we should push that instead
-                hir_options.push((head_id.clone().at_loc(&option.0), parameter_list))
+                hir_options.push((head_id.clone().at_loc(option_name), parameter_list))
             }
 
             hir::TypeDeclKind::Enum(
@@ -368,7 +375,7 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
             // Disallow normal arguments if the struct is a port, and port types
             // if it is not
             if s.is_port() {
-                for (_, f, ty) in &s.members.args {
+                for (_, _, f, ty) in &s.members.args {
                     visit_type_spec(ty, &TypeSpecKind::StructMember, ctx)?;
                     if !ty.is_port(&ctx.symtab)? {
                         return Err(Diagnostic::error(ty, "Non-port in port struct")
@@ -386,7 +393,7 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
                     }
                 }
             } else {
-                for (_, _, ty) in &s.members.args {
+                for (_, _, _, ty) in &s.members.args {
                     visit_type_spec(ty, &TypeSpecKind::StructMember, ctx)?;
                     if ty.is_port(&ctx.symtab)? {
                         return Err(Diagnostic::error(ty, "Port in non-port struct")
@@ -402,6 +409,14 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
             }
 
             let members = visit_parameter_list(&s.members, ctx)?;
+            for (doc, _, name, _) in &s.members.args {
+                if let Some(doc) = doc {
+                    ctx.item_list.member_docs.insert(
+                        (declaration_id.inner.clone(), name.inner.clone()),
+                        doc.inner.clone(),
+                    );
+                }
+            }
 
             let self_type =
                 hir::TypeSpec::Declared(declaration_id.clone(), output_type_exprs.clone())
@@ -451,7 +466,15 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
                 | ast::Attribute::NoMangle
                 | ast::Attribute::Fsm { .. }
                 | ast::Attribute::WalSuffix { .. }
-                | ast::Attribute::WalTrace { .. } => Err(attr.report_unused("struct")),
+                | ast::Attribute::WalTrace { .. }
+                | ast::Attribute::Reset { .. }
+                | ast::Attribute::CdcPrimitive
+                | ast::Attribute::Retime
+                | ast::Attribute::ClockEdge { .. }
+                | ast::Attribute::MemoryInit { .. }
+                | ast::Attribute::VerilogParameters { .. }
+                | ast::Attribute::VerilogAttrs { .. }
+                | ast::Attribute::LintLevel { .. } => Err(attr.report_unused("struct")),
             })?;
 
             // We don't do any special processing of structs here
@@ -476,6 +499,11 @@ pub fn re_visit_type_declaration(t: &Loc<ast::TypeDeclaration>, ctx: &mut Contex
         generic_args: type_params,
     }
     .at_loc(t);
+    if let Some(doc) = &t.doc {
+        ctx.item_list
+            .docs
+            .insert(declaration_id.inner.clone(), doc.inner.clone());
+    }
     ctx.item_list.types.insert(declaration_id.inner, decl);
 
     Ok(())
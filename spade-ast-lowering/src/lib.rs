@@ -12,11 +12,11 @@ use global_symbols::visit_meta_type;
 use itertools::{EitherOrBoth, Itertools};
 use num::{BigInt, Zero};
 use pipelines::PipelineContext;
-use spade_diagnostics::{diag_bail, Diagnostic};
+use spade_diagnostics::{diag_bail, Diagnostic, Lint, Lints};
 use spade_types::meta_types::MetaType;
 use tracing::{event, info, Level};
 
-use crate::attributes::AttributeListExt;
+use crate::attributes::{set_lint_levels, AttributeListExt};
 use crate::pipelines::maybe_perform_pipelining_tasks;
 use crate::types::{IsPort, IsSelf};
 use ast::{Binding, CallKind, ParameterList, UnitKind};
@@ -45,6 +45,10 @@ pub struct Context {
     pub impl_idtracker: ImplIdTracker,
     pub pipeline_ctx: Option<PipelineContext>,
     pub self_ctx: SelfContext,
+    /// The reset style of registers in the current unit which do not specify their own
+    pub reset_style: hir::ResetStyle,
+    /// Lints emitted during lowering, along with the levels set by attributes
+    pub lints: Lints,
 }
 
 trait LocExt<T> {
@@ -424,7 +428,7 @@ fn visit_parameter_list(
             diag = if l.args.is_empty() {
                 diag.span_suggest_replace(suggest_msg, l, "(self)")
             } else {
-                diag.span_suggest_insert_before(suggest_msg, &l.args[0].1, "self, ")
+                diag.span_suggest_insert_before(suggest_msg, &l.args[0].2, "self, ")
             };
             return Err(diag);
         }
@@ -455,7 +459,7 @@ fn visit_parameter_list(
         }
     }
 
-    for (attrs, name, input_type) in &l.args {
+    for (_, attrs, name, input_type) in &l.args {
         if let Some(prev) = arg_names.get(name) {
             return Err(
                 Diagnostic::error(name, "Multiple arguments with the same name")
@@ -526,7 +530,7 @@ pub fn unit_head(
     let mut port_error = Ok(());
 
     if let ast::UnitKind::Function = head.unit_kind.inner {
-        for (_, _, ty) in &head.inputs.args {
+        for (_, _, _, ty) in &head.inputs.args {
             if matches!(ctx.self_ctx, SelfContext::TraitDefinition(_)) && ty.is_self()? {
                 continue;
             };
@@ -821,6 +825,22 @@ pub fn visit_where_clauses(
     Ok(visited_where_clauses)
 }
 
+fn visit_verilog_value(value: &Loc<ast::VerilogParameterValue>) -> Loc<hir::VerilogParameterValue> {
+    value.map_ref(|value| match value {
+        ast::VerilogParameterValue::Int(v) => hir::VerilogParameterValue::Int(v.clone()),
+        ast::VerilogParameterValue::String(s) => hir::VerilogParameterValue::String(s.clone()),
+    })
+}
+
+fn visit_verilog_attrs(
+    attrs: &[(Loc<Identifier>, Option<Loc<ast::VerilogParameterValue>>)],
+) -> Vec<(Loc<Identifier>, Option<Loc<hir::VerilogParameterValue>>)> {
+    attrs
+        .iter()
+        .map(|(name, value)| (name.clone(), value.as_ref().map(visit_verilog_value)))
+        .collect()
+}
+
 /// The `extra_path` parameter allows specifying an extra path prepended to
 /// the name of the entity. This is used by impl blocks to append a unique namespace
 #[tracing::instrument(skip_all, fields(%unit.head.name, %unit.head.unit_kind))]
@@ -833,11 +853,12 @@ pub fn visit_unit(
     let ast::Unit {
         head:
             ast::UnitHead {
+                doc,
                 name,
                 attributes,
                 inputs: _,
                 output_type: _,
-                unit_kind: _,
+                unit_kind,
                 type_params,
                 where_clauses: _,
             },
@@ -860,6 +881,10 @@ pub fn visit_unit(
         })
         .expect("Attempting to lower an entity that has not been added to the symtab previously");
 
+    if let Some(doc) = doc {
+        ctx.item_list.docs.insert(id.clone(), doc.inner.clone());
+    }
+
     let mut unit_name = if type_params.is_some() || scope_type_params.is_some() {
         hir::UnitName::WithID(id.clone().at_loc(name))
     } else {
@@ -867,13 +892,39 @@ pub fn visit_unit(
     };
 
     let mut wal_suffix = None;
+    let mut unit_reset_style = hir::ResetStyle::default();
 
+    // Lints emitted at the attributes of the unit, like `#[retime]`, are affected by
+    // the lint levels set on the unit
+    let lint_scope = match attributes.0.first() {
+        Some(first) => ().between_locs(first, unit),
+        None => unit.loc(),
+    };
     let attributes = attributes.lower(&mut |attr: &Loc<ast::Attribute>| match &attr.inner {
         ast::Attribute::Optimize { passes } => Ok(Some(hir::Attribute::Optimize {
             passes: passes.clone(),
         })),
+        ast::Attribute::CdcPrimitive => Ok(Some(hir::Attribute::CdcPrimitive)),
+        ast::Attribute::Retime => {
+            if !unit_kind.is_pipeline() {
+                Err(
+                    Diagnostic::error(attr, "retime is only allowed on pipelines")
+                        .primary_label("Not allowed here")
+                        .secondary_label(unit_kind, "Not a pipeline"),
+                )
+            } else if body.is_none() {
+                Err(
+                    Diagnostic::error(attr, "retime is not allowed on __builtin__ units")
+                        .primary_label("Not allowed on __builtin__ units"),
+                )
+            } else {
+                Ok(Some(hir::Attribute::Retime))
+            }
+        }
         ast::Attribute::NoMangle => {
-            if let Some(generic_list) = type_params {
+            // Generic __builtin__ units are not monomorphised, so their names do not need
+            // to be mangled
+            if let (Some(generic_list), Some(_)) = (type_params, body) {
                 Err(
                     Diagnostic::error(attr, "no_mangle is not allowed on generic units")
                         .primary_label("no_mangle not allowed here")
@@ -903,12 +954,77 @@ pub fn visit_unit(
             wal_suffix = Some(suffix.clone());
             Ok(None)
         }
+        ast::Attribute::VerilogParameters { params } => {
+            if body.is_some() {
+                return Err(Diagnostic::error(
+                    attr,
+                    "verilog_parameters is only allowed on __builtin__ units",
+                )
+                .primary_label("Not allowed on units with a body"));
+            }
+
+            let type_params = head.get_type_params();
+            let mut seen: HashMap<&Identifier, &Loc<Identifier>> = HashMap::new();
+            for (name, _) in params {
+                if let Some(prev) = seen.insert(&name.inner, name) {
+                    return Err(Diagnostic::error(
+                        name,
+                        format!("Verilog parameter {name} specified more than once"),
+                    )
+                    .primary_label("Specified multiple times")
+                    .secondary_label(prev, "Previously specified here"));
+                }
+                if let Some(param) = type_params.iter().find(|p| p.ident.inner == name.inner) {
+                    return Err(Diagnostic::error(
+                        name,
+                        format!("Verilog parameter {name} is also a generic parameter"),
+                    )
+                    .primary_label("Conflicting parameter")
+                    .secondary_label(
+                        &param.ident,
+                        "Generic parameters are passed to the Verilog module",
+                    ));
+                }
+            }
+
+            Ok(Some(hir::Attribute::VerilogParameters {
+                params: params
+                    .iter()
+                    .map(|(name, value)| (name.clone(), visit_verilog_value(value)))
+                    .collect(),
+            }))
+        }
+        ast::Attribute::VerilogAttrs { attrs } => {
+            if body.is_none() {
+                return Err(Diagnostic::error(
+                    attr,
+                    "verilog_attrs is not allowed on __builtin__ units",
+                )
+                .primary_label("Not allowed on __builtin__ units"));
+            }
+
+            Ok(Some(hir::Attribute::VerilogAttrs {
+                attrs: visit_verilog_attrs(attrs),
+            }))
+        }
+        ast::Attribute::Reset {
+            synchronous,
+            active_low,
+        } => {
+            unit_reset_style = apply_reset_attribute(unit_reset_style, *synchronous, *active_low);
+            Ok(None)
+        }
+        ast::Attribute::LintLevel { level, lints } => {
+            set_lint_levels(*level, lints, attr, lint_scope, ctx)?;
+            Ok(None)
+        }
         _ => Err(attr.report_unused("a unit")),
     })?;
+    ctx.reset_style = unit_reset_style;
 
     // If this is a builtin entity
     if body.is_none() {
-        return Ok(hir::Item::Builtin(unit_name, head));
+        return Ok(hir::Item::Builtin(unit_name, head, attributes));
     }
 
     // Add the inputs to the symtab
@@ -1057,6 +1173,17 @@ pub fn create_trait_from_unit_heads(
         })
         .collect::<Result<Vec<_>>>()?;
 
+    if let TraitName::Named(trait_name) = &name {
+        for head in heads {
+            if let Some(doc) = &head.doc {
+                ctx.item_list.member_docs.insert(
+                    (trait_name.inner.clone(), head.name.inner.clone()),
+                    doc.inner.clone(),
+                );
+            }
+        }
+    }
+
     // Add the trait to the trait list
     ctx.item_list
         .add_trait(name, visited_type_params, trait_members)?;
@@ -1228,7 +1355,7 @@ pub fn visit_impl(block: &Loc<ast::ImplBlock>, ctx: &mut Context) -> Result<Vec<
                     (u.name.name_id().inner.clone(), u.loc()),
                 );
             }
-            hir::Item::Builtin(_, head) => {
+            hir::Item::Builtin(_, head, _) => {
                 return Err(Diagnostic::error(head, "Methods cannot be __builtin__")
                     .help("Consider defining a free-standing function"))
             }
@@ -2018,6 +2145,9 @@ pub fn visit_module(module: &ast::Module, ctx: &mut Context) -> Result<()> {
         })
         .expect("Attempting to lower a module that has not been added to the symtab previously");
 
+    if let Some(doc) = &module.doc {
+        ctx.item_list.docs.insert(id.clone(), doc.inner.clone());
+    }
     ctx.item_list.modules.insert(
         id.clone(),
         Module {
@@ -2037,9 +2167,9 @@ pub fn visit_module_body(body: &ast::ModuleBody, ctx: &mut Context) -> Result<()
                     .item_list
                     .add_executable(u.name.name_id().clone(), ExecutableItem::Unit(u))?,
 
-                hir::Item::Builtin(name, head) => ctx.item_list.add_executable(
+                hir::Item::Builtin(name, head, attributes) => ctx.item_list.add_executable(
                     name.name_id().clone(),
-                    ExecutableItem::BuiltinUnit(name, head),
+                    ExecutableItem::BuiltinUnit(name, head, attributes),
                 )?,
             }
         }
@@ -2048,6 +2178,64 @@ pub fn visit_module_body(body: &ast::ModuleBody, ctx: &mut Context) -> Result<()
     Ok(())
 }
 
+/// Emits a lint for each name bound by `pattern` which shadows a variable that is
+/// already in scope. Must be called before the pattern is visited, as that adds the
+/// new names to the symtab
+fn lint_shadowed_bindings(pattern: &Loc<ast::Pattern>, ctx: &mut Context) {
+    match &pattern.inner {
+        ast::Pattern::Integer(_) | ast::Pattern::Bool(_) => {}
+        ast::Pattern::Path(path) => {
+            if let [ident] = path.inner.0.as_slice() {
+                lint_shadowed_name(ident, ctx)
+            }
+        }
+        ast::Pattern::Tuple(inner) | ast::Pattern::Array(inner) => {
+            for p in inner {
+                lint_shadowed_bindings(p, ctx)
+            }
+        }
+        ast::Pattern::Type(_, args) => match &args.inner {
+            ast::ArgumentPattern::Named(patterns) => {
+                for (name, pattern) in patterns {
+                    match pattern {
+                        Some(p) => lint_shadowed_bindings(p, ctx),
+                        None => lint_shadowed_name(name, ctx),
+                    }
+                }
+            }
+            ast::ArgumentPattern::Positional(patterns) => {
+                for p in patterns {
+                    lint_shadowed_bindings(p, ctx)
+                }
+            }
+        },
+    }
+}
+
+fn lint_shadowed_name(ident: &Loc<Identifier>, ctx: &mut Context) {
+    // Declared names are defined by the binding rather than shadowed by it
+    if ident.inner.0.starts_with('_') || ctx.symtab.get_declaration(ident).is_some() {
+        return;
+    }
+
+    let previous = ctx
+        .symtab
+        .try_lookup_id(&Path::ident(ident.clone()).at_loc(ident))
+        .and_then(|id| match ctx.symtab.things.get(&id) {
+            Some(Thing::Variable(previous)) => Some(previous.clone()),
+            _ => None,
+        });
+
+    if let Some(previous) = previous {
+        ctx.lints.emit(
+            Lint::ShadowedBinding,
+            Diagnostic::warning(ident, format!("{ident} shadows an earlier binding"))
+                .primary_label(format!("{ident} is bound again here"))
+                .secondary_label(previous, format!("{ident} was previously bound here")),
+        )
+    }
+}
+
 fn try_lookup_enum_variant(path: &Loc<Path>, ctx: &mut Context) -> Result<hir::PatternKind> {
     let (name_id, variant) = ctx.symtab.lookup_enum_variant(path)?;
     if variant.inner.params.argument_num() == 0 {
@@ -2274,9 +2462,12 @@ fn visit_statement(s: &Loc<ast::Statement>, ctx: &mut Context) -> Result<Vec<Loc
 
             let value = value.try_visit(visit_expression, ctx)?;
 
+            lint_shadowed_bindings(pattern, ctx);
             let pattern = pattern.try_visit(visit_pattern, ctx)?;
 
             let mut wal_trace = None;
+            let mut memory_init = None;
+            let mut verilog_attrs = vec![];
             attrs.lower(&mut |attr| match &attr.inner {
                 ast::Attribute::WalTrace { clk, rst } => {
                     wal_trace = Some(
@@ -2307,10 +2498,40 @@ fn visit_statement(s: &Loc<ast::Statement>, ctx: &mut Context) -> Result<Vec<Loc
                     }
                     Ok(None)
                 }
+                ast::Attribute::MemoryInit { file, format } => {
+                    let format = match format.as_ref().map(|f| f.inner) {
+                        Some(ast::MemoryInitFormat::Hex) | None => hir::MemoryInitFormat::Hex,
+                        Some(ast::MemoryInitFormat::Bin) => hir::MemoryInitFormat::Bin,
+                        Some(ast::MemoryInitFormat::Raw) => hir::MemoryInitFormat::Raw,
+                    };
+                    memory_init = Some(
+                        hir::MemoryInit {
+                            file: file.clone(),
+                            format,
+                        }
+                        .at_loc(attr),
+                    );
+                    Ok(None)
+                }
+                ast::Attribute::VerilogAttrs { attrs } => {
+                    verilog_attrs.extend(visit_verilog_attrs(attrs));
+                    Ok(None)
+                }
+                ast::Attribute::LintLevel { level, lints } => {
+                    set_lint_levels(*level, lints, attr, s.loc(), ctx)?;
+                    Ok(None)
+                }
                 ast::Attribute::NoMangle
                 | ast::Attribute::Fsm { .. }
                 | ast::Attribute::Optimize { .. }
-                | ast::Attribute::WalTraceable { .. } => Err(attr.report_unused("let binding")),
+                | ast::Attribute::WalTraceable { .. }
+                | ast::Attribute::Reset { .. }
+                | ast::Attribute::CdcPrimitive
+                | ast::Attribute::Retime
+                | ast::Attribute::ClockEdge { .. }
+                | ast::Attribute::VerilogParameters { .. } => {
+                    Err(attr.report_unused("let binding"))
+                }
             })?;
 
             stmts.push(
@@ -2319,6 +2540,8 @@ fn visit_statement(s: &Loc<ast::Statement>, ctx: &mut Context) -> Result<Vec<Loc
                     ty: hir_type,
                     value,
                     wal_trace,
+                    memory_init,
+                    verilog_attrs,
                 })
                 .at_loc(s),
             );
@@ -2365,6 +2588,46 @@ fn visit_statement(s: &Loc<ast::Statement>, ctx: &mut Context) -> Result<Vec<Loc
 
             Ok(vec![hir::Statement::Assert(expr).at_loc(s)])
         }
+        ast::Statement::Property(property) => {
+            let ast::Property {
+                kind,
+                clock,
+                reset,
+                antecedent,
+                consequent,
+            } = &property.inner;
+
+            let kind = match kind {
+                ast::PropertyKind::Assert => hir::PropertyKind::Assert,
+                ast::PropertyKind::Assume => hir::PropertyKind::Assume,
+                ast::PropertyKind::Cover => hir::PropertyKind::Cover,
+            };
+            let clock = clock.try_visit(visit_expression, ctx)?;
+            let reset = reset
+                .as_ref()
+                .map(|reset| reset.try_visit(visit_expression, ctx))
+                .transpose()?;
+            let antecedent = antecedent
+                .as_ref()
+                .map(|(expr, implication)| -> Result<_> {
+                    let implication = match implication {
+                        ast::Implication::Overlapping => hir::Implication::Overlapping,
+                        ast::Implication::NonOverlapping => hir::Implication::NonOverlapping,
+                    };
+                    Ok((expr.try_visit(visit_expression, ctx)?, implication))
+                })
+                .transpose()?;
+            let consequent = consequent.try_visit(visit_expression, ctx)?;
+
+            Ok(vec![hir::Statement::Property(hir::Property {
+                kind,
+                clock,
+                reset,
+                antecedent,
+                consequent,
+            })
+            .at_loc(s)])
+        }
         ast::Statement::Comptime(condition) => {
             if let Some(ast_statements) = condition.maybe_unpack(&ctx.symtab)? {
                 Ok(ast_statements
@@ -2862,9 +3125,22 @@ fn visit_block(b: &ast::Block, ctx: &mut Context) -> Result<hir::Block> {
     Ok(hir::Block { statements, result })
 }
 
+/// Overrides the fields of `style` which are specified by a `#[reset_style]` attribute
+fn apply_reset_attribute(
+    style: hir::ResetStyle,
+    synchronous: Option<bool>,
+    active_low: Option<bool>,
+) -> hir::ResetStyle {
+    hir::ResetStyle {
+        synchronous: synchronous.unwrap_or(style.synchronous),
+        active_low: active_low.unwrap_or(style.active_low),
+    }
+}
+
 fn visit_register(reg: &Loc<ast::Register>, ctx: &mut Context) -> Result<Vec<Loc<hir::Statement>>> {
     let (reg, loc) = reg.split_loc_ref();
 
+    lint_shadowed_bindings(&reg.pattern, ctx);
     let pattern = reg.pattern.try_visit(visit_pattern, ctx)?;
 
     let clock = reg.clock.try_visit(visit_expression, ctx)?;
@@ -2897,6 +3173,8 @@ fn visit_register(reg: &Loc<ast::Register>, ctx: &mut Context) -> Result<Vec<Loc
     };
 
     let mut stmts = vec![];
+    let mut reset_style = ctx.reset_style;
+    let mut clock_edge = hir::ClockEdge::Rising;
 
     let attributes = reg.attributes.lower(&mut |attr| match &attr.inner {
         ast::Attribute::Fsm { state } => {
@@ -2933,6 +3211,35 @@ fn visit_register(reg: &Loc<ast::Register>, ctx: &mut Context) -> Result<Vec<Loc
             }
             Ok(None)
         }
+        ast::Attribute::Reset {
+            synchronous,
+            active_low,
+        } => {
+            if reset.is_none() {
+                return Err(
+                    Diagnostic::error(attr, "reset_style on a register without a reset")
+                        .primary_label("Unused reset style")
+                        .secondary_label(&pattern, "This register has no reset"),
+                );
+            }
+            reset_style = apply_reset_attribute(reset_style, *synchronous, *active_low);
+            Ok(None)
+        }
+        ast::Attribute::ClockEdge { edge } => {
+            clock_edge = match edge {
+                ast::ClockEdge::Rising => hir::ClockEdge::Rising,
+                ast::ClockEdge::Falling => hir::ClockEdge::Falling,
+                ast::ClockEdge::Both => hir::ClockEdge::Both,
+            };
+            Ok(None)
+        }
+        ast::Attribute::VerilogAttrs { attrs } => Ok(Some(hir::Attribute::VerilogAttrs {
+            attrs: visit_verilog_attrs(attrs),
+        })),
+        ast::Attribute::LintLevel { level, lints } => {
+            set_lint_levels(*level, lints, attr, loc, ctx)?;
+            Ok(None)
+        }
         _ => Err(attr.report_unused("a register")),
     })?;
 
@@ -2940,7 +3247,9 @@ fn visit_register(reg: &Loc<ast::Register>, ctx: &mut Context) -> Result<Vec<Loc
         hir::Statement::Register(hir::Register {
             pattern,
             clock,
+            clock_edge,
             reset,
+            reset_style,
             initial,
             value,
             value_type,
@@ -3079,6 +3388,7 @@ mod entity_visiting {
     fn entity_visits_work() {
         let input = ast::Unit {
             head: ast::UnitHead {
+                doc: None,
                 name: Identifier("test".to_string()).nowhere(),
                 inputs: ParameterList::without_self(vec![(
                     ast_ident("a"),
@@ -3267,6 +3577,8 @@ mod statement_visiting {
                 .with_id(0)
                 .nowhere(),
             reset: None,
+            clock_edge: hir::ClockEdge::Rising,
+            reset_style: hir::ResetStyle::default(),
             initial: None,
             value: hir::ExprKind::int_literal(0).idless().nowhere(),
             value_type: None,
@@ -3945,6 +4257,8 @@ mod register_visiting {
                     .nowhere(),
                 hir::ExprKind::int_literal(0).idless().nowhere(),
             )),
+            clock_edge: hir::ClockEdge::Rising,
+            reset_style: hir::ResetStyle::default(),
             initial: Some(hir::ExprKind::int_literal(0).idless().nowhere()),
             value: hir::ExprKind::int_literal(1).idless().nowhere(),
             value_type: Some(hir::TypeSpec::unit().nowhere()),
@@ -3986,6 +4300,7 @@ mod item_visiting {
         let input = ast::Item::Unit(
             ast::Unit {
                 head: ast::UnitHead {
+                    doc: None,
                     name: ast_ident("test"),
                     output_type: None,
                     inputs: aparams![],
@@ -4057,6 +4372,7 @@ mod module_visiting {
             members: vec![ast::Item::Unit(
                 ast::Unit {
                     head: ast::UnitHead {
+                        doc: None,
                         name: ast_ident("test"),
                         output_type: None,
                         inputs: ParameterList::without_self(vec![]).nowhere(),
@@ -4110,6 +4426,8 @@ mod module_visiting {
             modules: vec![].into_iter().collect(),
             traits: HashMap::new(),
             impls: HashMap::new(),
+            docs: HashMap::new(),
+            member_docs: HashMap::new(),
         };
 
         let mut ctx = test_context();
@@ -4123,10 +4441,12 @@ mod module_visiting {
         let input = ast::ModuleBody {
             members: vec![ast::Item::Module(
                 ast::Module {
+                    doc: None,
                     name: ast_ident("outer"),
                     body: ast::ModuleBody {
                         members: vec![ast::Item::Module(
                             ast::Module {
+                                doc: None,
                                 name: ast_ident("inner"),
                                 body: ast::ModuleBody { members: vec![] }.nowhere(),
                             }
@@ -4160,6 +4480,8 @@ mod module_visiting {
             .collect(),
             traits: HashMap::new(),
             impls: HashMap::new(),
+            docs: HashMap::new(),
+            member_docs: HashMap::new(),
         };
 
         let mut ctx = test_context();
@@ -26,6 +26,7 @@ fn visit_pipeline_statement(statement: &ast::Statement, ctx: &mut Context) -> Re
         ast::Statement::PipelineRegMarker(_, _) => {}
         ast::Statement::Register(_) => {}
         ast::Statement::Assert(_) => {}
+        ast::Statement::Property(_) => {}
         ast::Statement::Comptime(inner) => {
             if let Some(inner_stmts) = inner.maybe_unpack(&ctx.symtab)? {
                 for inner_stmt in inner_stmts {
@@ -11,5 +11,7 @@ pub fn test_context() -> Context {
         impl_idtracker: ImplIdTracker::new(),
         pipeline_ctx: None,
         self_ctx: SelfContext::FreeStanding,
+        reset_style: Default::default(),
+        lints: Default::default(),
     }
 }
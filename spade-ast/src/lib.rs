@@ -459,6 +459,8 @@ pub enum Statement {
         value: Loc<Expression>,
     },
     Assert(Loc<Expression>),
+    /// A clocked property, `assert(clk) a |=> b`, used by formal verification tools.
+    Property(Loc<Property>),
     Comptime(ComptimeCondition<Vec<Loc<Statement>>>),
 }
 impl WithLocation for Statement {}
@@ -518,6 +520,40 @@ impl GenericBound {
     }
 }
 
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ClockEdge {
+    Rising,
+    Falling,
+    /// Both edges, which is only supported in simulation
+    Both,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum MemoryInitFormat {
+    /// Whitespace separated hexadecimal words, as read by `$readmemh`
+    Hex,
+    /// Whitespace separated binary words, as read by `$readmemb`
+    Bin,
+    /// Raw little endian bytes, with each element padded to a whole number of bytes
+    Raw,
+}
+impl WithLocation for MemoryInitFormat {}
+
+/// The value of a parameter passed to an external Verilog module
+#[derive(PartialEq, Debug, Clone)]
+pub enum VerilogParameterValue {
+    Int(BigInt),
+    String(String),
+}
+impl WithLocation for VerilogParameterValue {}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum Attribute {
     Optimize {
@@ -540,6 +576,39 @@ pub enum Attribute {
     WalSuffix {
         suffix: Loc<Identifier>,
     },
+    /// Selects how register resets are applied. Fields which are None are
+    /// inherited from the enclosing unit, or the default async, active high reset
+    Reset {
+        synchronous: Option<bool>,
+        active_low: Option<bool>,
+    },
+    /// Marks a unit as a safe way to cross between clock domains
+    CdcPrimitive,
+    /// Lets the compiler place the registers of a pipeline to balance the delay of its stages
+    Retime,
+    /// Selects the clock edge on which a register samples its value
+    ClockEdge {
+        edge: ClockEdge,
+    },
+    /// Initialise the memory bound by a let binding with the content of a file
+    MemoryInit {
+        file: Loc<String>,
+        format: Option<Loc<MemoryInitFormat>>,
+    },
+    /// Constant parameters passed to the Verilog module implementing a __builtin__ unit
+    VerilogParameters {
+        params: Vec<(Loc<Identifier>, Loc<VerilogParameterValue>)>,
+    },
+    /// Synthesis attributes emitted as `(* name = value *)` on the generated Verilog
+    VerilogAttrs {
+        attrs: Vec<(Loc<Identifier>, Option<Loc<VerilogParameterValue>>)>,
+    },
+    /// Sets the level of the listed lints in the marked unit or statement,
+    /// `#[allow(...)]`, `#[warn(...)]` or `#[deny(...)]`
+    LintLevel {
+        level: LintLevel,
+        lints: Vec<Loc<Identifier>>,
+    },
 }
 
 impl Attribute {
@@ -551,6 +620,18 @@ impl Attribute {
             Attribute::WalTraceable { .. } => "wal_traceable",
             Attribute::WalTrace { .. } => "wal_trace",
             Attribute::WalSuffix { .. } => "wal_suffix",
+            Attribute::Reset { .. } => "reset_style",
+            Attribute::CdcPrimitive => "cdc_primitive",
+            Attribute::Retime => "retime",
+            Attribute::ClockEdge { .. } => "clock_edge",
+            Attribute::MemoryInit { .. } => "memory_init",
+            Attribute::VerilogParameters { .. } => "verilog_parameters",
+            Attribute::VerilogAttrs { .. } => "verilog_attrs",
+            Attribute::LintLevel { level, .. } => match level {
+                LintLevel::Allow => "allow",
+                LintLevel::Warn => "warn",
+                LintLevel::Deny => "deny",
+            },
         }
     }
 }
@@ -569,10 +650,22 @@ impl AttributeList {
     }
 }
 
+/// The text of the `///` comments in front of an item, struct field or enum variant, with
+/// one line per comment
+pub type DocComment = Loc<String>;
+
+/// A unit parameter or struct member
+pub type Parameter = (
+    Option<DocComment>,
+    AttributeList,
+    Loc<Identifier>,
+    Loc<TypeSpec>,
+);
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct ParameterList {
     pub self_: Option<Loc<()>>,
-    pub args: Vec<(AttributeList, Loc<Identifier>, Loc<TypeSpec>)>,
+    pub args: Vec<Parameter>,
 }
 impl WithLocation for ParameterList {}
 
@@ -582,7 +675,7 @@ impl ParameterList {
             self_: None,
             args: args
                 .into_iter()
-                .map(|(n, t)| (AttributeList::empty(), n, t))
+                .map(|(n, t)| (None, AttributeList::empty(), n, t))
                 .collect(),
         }
     }
@@ -592,7 +685,7 @@ impl ParameterList {
             self_: Some(self_),
             args: args
                 .into_iter()
-                .map(|(n, t)| (AttributeList::empty(), n, t))
+                .map(|(n, t)| (None, AttributeList::empty(), n, t))
                 .collect(),
         }
     }
@@ -628,6 +721,7 @@ impl std::fmt::Display for UnitKind {
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct UnitHead {
+    pub doc: Option<DocComment>,
     pub attributes: AttributeList,
     pub unit_kind: Loc<UnitKind>,
     pub name: Loc<Identifier>,
@@ -659,9 +753,36 @@ pub struct Register {
 }
 impl WithLocation for Register {}
 
+#[derive(PartialEq, Debug, Clone)]
+pub enum PropertyKind {
+    Assert,
+    Assume,
+    Cover,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Implication {
+    /// `a |-> b`: `b` must hold in the same cycle as `a`
+    Overlapping,
+    /// `a |=> b`: `b` must hold in the cycle after `a`
+    NonOverlapping,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Property {
+    pub kind: PropertyKind,
+    pub clock: Loc<Expression>,
+    /// The property is not checked while this is high
+    pub reset: Option<Loc<Expression>>,
+    pub antecedent: Option<(Loc<Expression>, Implication)>,
+    pub consequent: Loc<Expression>,
+}
+impl WithLocation for Property {}
+
 /// A definition of a trait
 #[derive(PartialEq, Debug, Clone)]
 pub struct TraitDef {
+    pub doc: Option<DocComment>,
     pub name: Loc<Identifier>,
     pub type_params: Option<Loc<Vec<Loc<TypeParam>>>>,
     pub where_clauses: Vec<WhereClause>,
@@ -679,6 +800,7 @@ impl WithLocation for TraitSpec {}
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct ImplBlock {
+    pub doc: Option<DocComment>,
     pub r#trait: Option<Loc<TraitSpec>>,
     pub type_params: Option<Loc<Vec<Loc<TypeParam>>>>,
     pub where_clauses: Vec<WhereClause>,
@@ -687,11 +809,18 @@ pub struct ImplBlock {
 }
 impl WithLocation for ImplBlock {}
 
+/// An enum variant with its members, if it has any
+pub type EnumOption = (
+    Option<DocComment>,
+    Loc<Identifier>,
+    Option<Loc<ParameterList>>,
+);
+
 /// Declaration of an enum
 #[derive(PartialEq, Debug, Clone)]
 pub struct Enum {
     pub name: Loc<Identifier>,
-    pub options: Vec<(Loc<Identifier>, Option<Loc<ParameterList>>)>,
+    pub options: Vec<EnumOption>,
 }
 impl WithLocation for Enum {}
 
@@ -719,6 +848,7 @@ pub enum TypeDeclKind {
 /// A declaration of a new type
 #[derive(PartialEq, Debug, Clone)]
 pub struct TypeDeclaration {
+    pub doc: Option<DocComment>,
     pub name: Loc<Identifier>,
     pub kind: TypeDeclKind,
     pub generic_args: Option<Loc<Vec<Loc<TypeParam>>>>,
@@ -781,6 +911,7 @@ impl Item {
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Module {
+    pub doc: Option<DocComment>,
     pub name: Loc<Identifier>,
     pub body: Loc<ModuleBody>,
 }
@@ -0,0 +1,68 @@
+use std::path::{Path, PathBuf};
+
+/// Directories, relative to this crate, with the code which decides the contents of
+/// libraries. Libraries record a hash of these, since the compiler state can change
+/// between builds with the same version number.
+const LIBRARY_SOURCES: &[&str] = &[
+    "prelude",
+    "stdlib",
+    "../spade-ast/src",
+    "../spade-ast-lowering/src",
+    "../spade-common/src",
+    "../spade-diagnostics/src",
+    "../spade-hir/src",
+    "../spade-parser/src",
+    "../spade-types/src",
+];
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// FNV-1a, which unlike the std hasher gives the same hash with every Rust version
+fn hash(bytes: impl IntoIterator<Item = u8>, hash: &mut u64) {
+    for byte in bytes {
+        *hash ^= byte as u64;
+        *hash = hash.wrapping_mul(0x100000001b3);
+    }
+}
+
+fn main() {
+    let mut files = vec![];
+    let mut missing = vec![];
+    for dir in LIBRARY_SOURCES {
+        println!("cargo:rerun-if-changed={dir}");
+        if let Err(e) = collect_files(Path::new(dir), &mut files) {
+            missing.push(format!("{dir} ({e})"));
+        }
+    }
+    files.sort();
+
+    let mut source_hash: u64 = 0xcbf29ce484222325;
+    if missing.is_empty() {
+        for file in files {
+            let content = std::fs::read(&file)
+                .unwrap_or_else(|e| panic!("Failed to read {}: {e}", file.to_string_lossy()));
+            hash(
+                file.to_string_lossy().bytes().chain(content),
+                &mut source_hash,
+            );
+        }
+    } else {
+        // Without the sources, libraries can only be told apart by the compiler version
+        println!(
+            "cargo:warning=Could not read {}. Libraries will only be checked against the compiler version",
+            missing.join(", ")
+        );
+        hash(env!("CARGO_PKG_VERSION").bytes(), &mut source_hash);
+    }
+    println!("cargo:rustc-env=SPADE_LIBRARY_SOURCE_HASH={source_hash:016x}");
+}
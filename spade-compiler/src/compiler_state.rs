@@ -14,8 +14,10 @@ use spade_hir_lowering::{
     NameSourceMap,
 };
 use spade_mir::{
+    coverage::CoveragePoint,
     renaming::{VerilogNameMap, VerilogNameSource},
     unit_name::InstanceMap,
+    ValueName, ValueNameSource,
 };
 use spade_typeinference::{equation::TypedExpression, TypeMap, TypeState};
 use spade_types::ConcreteType;
@@ -26,6 +28,10 @@ pub struct MirContext {
     pub type_map: TypeMap,
     pub reg_name_map: BTreeMap<NameID, NameID>,
     pub verilog_name_map: VerilogNameMap,
+    /// The coverage signals inserted into this unit if compiled with coverage enabled
+    pub coverage_points: Vec<CoveragePoint>,
+    /// The unescaped verilog names of the registers in this unit
+    pub registers: Vec<String>,
 }
 
 /// All the state required in order to add more things to the compilation process
@@ -60,12 +66,32 @@ impl CompilerState {
             })
             .collect::<HashMap<_, _>>();
 
-        string_map.get(mangled).map(|name| match name {
+        string_map
+            .get(mangled)
+            .map(|name| self.demangle_named_value(name))
+    }
+
+    /// Attempts to find the snippet of source code corresponding to the specified value.
+    /// Unlike the name source map, this also works for values which have been renamed during
+    /// codegen
+    pub fn demangle_value_name(&self, name: &ValueName) -> Option<String> {
+        let id = match name {
+            ValueName::Named(_, _, ValueNameSource::Name(name)) => return Some(format!("{name}")),
+            ValueName::Named(_, _, ValueNameSource::Expr(id)) | ValueName::Expr(id) => *id,
+        };
+        self.name_source_map
+            .inner
+            .get(&ValueName::Expr(id))
+            .map(|name| self.demangle_named_value(name))
+    }
+
+    fn demangle_named_value(&self, name: &NamedValue) -> String {
+        match name {
             NamedValue::Primary(source) => self.demangle_name_source(source),
             NamedValue::Secondary(source, description) => {
                 format!("{} ({description})", self.demangle_name_source(source))
             }
-        })
+        }
     }
 
     pub fn demangle_name_source(&self, source: &NameSource) -> String {
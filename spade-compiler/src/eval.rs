@@ -0,0 +1,363 @@
+//! Compile time evaluation of expressions. An expression is compiled in the context of
+//! the code in a finished compilation and evaluated with the semantics of the generated
+//! Verilog. Only combinational code can be evaluated, which in practice means calls to
+//! `fn`s with their generic parameters fully known.
+
+use std::collections::BTreeMap;
+
+use codespan_reporting::term::termcolor::Buffer;
+use logos::Logos;
+use num::{BigInt, BigUint, Zero};
+use spade_ast_lowering::id_tracker::ExprIdTracker;
+use spade_ast_lowering::{Context as AstLoweringCtx, SelfContext};
+use spade_common::id_tracker::ImplIdTracker;
+use spade_common::location_info::{Loc, WithLocation};
+use spade_common::name::{NameID, Path as SpadePath};
+use spade_diagnostics::{CodeBundle, CompilationError, DiagHandler, Diagnostic};
+use spade_hir::symbol_table::FrozenSymtab;
+use spade_hir::{Expression, ItemList};
+use spade_hir_lowering::monomorphisation::{compile_requested_items, MonoState};
+use spade_hir_lowering::pipelines::MaybePipelineContext;
+use spade_hir_lowering::substitution::Substitutions;
+use spade_hir_lowering::{expr_to_mir, expr_value_name, MirLowerable, NameSourceMap};
+use spade_mir::eval::{EvalError, UnitEvaluator, Value};
+use spade_parser::{lexer, Parser};
+use spade_typeinference::traits::TraitImplList;
+use spade_typeinference::{GenericListSource, HasType, TypeState};
+use spade_types::{ConcreteType, PrimitiveType};
+
+use crate::Artefacts;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EvalFailure {
+    /// The expression could not be compiled. The reason has been reported to the error
+    /// buffer
+    #[error("aborting due to previous error")]
+    Reported,
+    #[error(transparent)]
+    Eval(#[from] EvalError),
+}
+
+/// The value of an evaluated expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluated {
+    /// The value written as Spade code
+    pub value: String,
+    /// The type of the value written as Spade code
+    pub ty: String,
+    /// The bits of the value
+    pub bits: Value,
+}
+
+impl std::fmt::Display for Evaluated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.value, self.ty)
+    }
+}
+
+/// Evaluates expressions using the code in a finished compilation
+pub struct Evaluator {
+    code: CodeBundle,
+    /// The namespace which `lib` refers to in the evaluated expressions
+    base_namespace: SpadePath,
+    /// Taken while an expression is lowered, which requires an unfrozen symbol table
+    symtab: Option<FrozenSymtab>,
+    item_list: ItemList,
+    trait_impls: TraitImplList,
+    idtracker: ExprIdTracker,
+    impl_idtracker: ImplIdTracker,
+    name_source_map: NameSourceMap,
+    type_states: BTreeMap<NameID, TypeState>,
+    mir_entities: Vec<spade_mir::Entity>,
+    diag_handler: DiagHandler,
+}
+
+impl Evaluator {
+    pub fn new(artefacts: Artefacts, base_namespace: SpadePath, diag_handler: DiagHandler) -> Self {
+        let Artefacts {
+            code,
+            item_list,
+            bumpy_mir_entities,
+            flat_mir_entities: _,
+            module_code: _,
+            mir_code: _,
+            state,
+            type_states,
+        } = artefacts;
+
+        let trait_impls = TypeState::new()
+            .visit_impl_blocks(&item_list)
+            .expect("Impl blocks were checked during compilation");
+
+        Self {
+            code,
+            base_namespace,
+            symtab: Some(state.symtab),
+            item_list,
+            trait_impls,
+            idtracker: state.idtracker,
+            impl_idtracker: state.impl_idtracker,
+            name_source_map: state.name_source_map,
+            type_states,
+            mir_entities: bumpy_mir_entities,
+            diag_handler,
+        }
+    }
+
+    fn report<T>(
+        &mut self,
+        result: Result<T, Diagnostic>,
+        error_buffer: &mut Buffer,
+    ) -> Result<T, EvalFailure> {
+        result.map_err(|e| {
+            e.report(error_buffer, &self.code, &mut self.diag_handler);
+            EvalFailure::Reported
+        })
+    }
+
+    /// Evaluates `expr`, reporting any compilation errors to `error_buffer`
+    pub fn eval(
+        &mut self,
+        expr: &str,
+        error_buffer: &mut Buffer,
+    ) -> Result<Evaluated, EvalFailure> {
+        let file_id = self.code.add_file("<eval>".to_string(), expr.to_string());
+        let mut parser = Parser::new(lexer::TokenKind::lexer(expr), file_id);
+        let ast = parser.expression();
+        let ast = self.report(ast, error_buffer)?;
+
+        let mut symtab = self
+            .symtab
+            .take()
+            .expect("attempting to re-take the symbol table")
+            .unfreeze();
+        symtab.set_base_namespace(self.base_namespace.clone());
+        let mut ast_ctx = AstLoweringCtx {
+            symtab,
+            item_list: std::mem::replace(&mut self.item_list, ItemList::new()),
+            idtracker: std::mem::take(&mut self.idtracker),
+            impl_idtracker: std::mem::take(&mut self.impl_idtracker),
+            pipeline_ctx: None,
+            self_ctx: SelfContext::FreeStanding,
+            reset_style: Default::default(),
+            lints: Default::default(),
+        };
+        let hir = spade_ast_lowering::visit_expression(&ast, &mut ast_ctx);
+        let AstLoweringCtx {
+            mut symtab,
+            item_list,
+            idtracker,
+            impl_idtracker,
+            pipeline_ctx: _,
+            self_ctx: _,
+            reset_style: _,
+            lints: _,
+        } = ast_ctx;
+        symtab.set_base_namespace(SpadePath(vec![]));
+        self.symtab = Some(symtab.freeze());
+        self.item_list = item_list;
+        self.idtracker = idtracker;
+        self.impl_idtracker = impl_idtracker;
+        let hir = self.report(hir, error_buffer)?.at_loc(&ast);
+
+        let mut symtab = self
+            .symtab
+            .take()
+            .expect("attempting to re-take the symbol table");
+        let result = self.eval_hir(hir, &mut symtab, error_buffer);
+        self.symtab = Some(symtab);
+        result
+    }
+
+    fn eval_hir(
+        &mut self,
+        hir: Loc<Expression>,
+        symtab: &mut FrozenSymtab,
+        error_buffer: &mut Buffer,
+    ) -> Result<Evaluated, EvalFailure> {
+        let type_ctx = spade_typeinference::Context {
+            symtab: symtab.symtab(),
+            items: &self.item_list,
+            trait_impls: &self.trait_impls,
+        };
+        let mut type_state = TypeState::new();
+        type_state.trait_impls = self.trait_impls.clone();
+        let checked = type_state
+            .create_generic_list(GenericListSource::Anonymous, &[], &[], None, &[])
+            .and_then(|generic_list| type_state.visit_expression(&hir, &type_ctx, &generic_list))
+            .and_then(|_| type_state.check_requirements(&type_ctx))
+            .and_then(|_| hir.get_type(&type_state));
+        let ty = self.report(checked, error_buffer)?;
+
+        let Some(concrete) =
+            TypeState::ungenerify_type(&ty, symtab.symtab(), &self.item_list.types)
+        else {
+            let diag = Diagnostic::error(&hir, "Type of expression is not fully known")
+                .primary_label("The type of this expression is not fully known")
+                .note(format!("Found incomplete type: {ty}"))
+                .help("Specify the generic parameters of the called functions, like `f::<8>(x)`");
+            return self.report(Err(diag), error_buffer);
+        };
+
+        let mut mono_state = MonoState::new();
+        let mut hir_ctx = spade_hir_lowering::Context {
+            symtab,
+            idtracker: &mut self.idtracker,
+            types: &mut type_state,
+            item_list: &self.item_list,
+            unit_generic_list: &None,
+            mono_state: &mut mono_state,
+            subs: &mut Substitutions::new(),
+            diag_handler: &mut self.diag_handler,
+            code: &self.code,
+            pipeline_context: &mut MaybePipelineContext::NotPipeline,
+            self_mono_item: None,
+        };
+        let lowered = expr_value_name(&hir, &hir_ctx)
+            .and_then(|output| Ok((output, expr_to_mir(hir, &mut hir_ctx)?)));
+        let (output, statements) = self.report(lowered, error_buffer)?;
+
+        // Generic units called by the expression are compiled with the parameters they are
+        // called with here
+        let items = self
+            .item_list
+            .executables
+            .iter()
+            .filter_map(|(name, item)| Some((name, (item, self.type_states.get(name)?.clone()))))
+            .collect::<BTreeMap<_, _>>();
+        let mono_mir = compile_requested_items(
+            mono_state,
+            &items,
+            symtab,
+            &mut self.idtracker,
+            &mut self.name_source_map,
+            &self.item_list,
+            &mut self.diag_handler,
+            &self.code,
+            None,
+            &[],
+        );
+        let mono_mir = mono_mir
+            .into_iter()
+            .map(|mir| mir.map(|out| out.mir))
+            .collect::<Result<Vec<_>, _>>();
+        let mono_mir = self.report(mono_mir, error_buffer)?;
+
+        let evaluator = UnitEvaluator::new(self.mir_entities.iter().chain(&mono_mir));
+        let bits = evaluator.eval_statements(&statements.to_vec_no_source_map(), &output)?;
+
+        Ok(Evaluated {
+            value: format_value(&concrete, &bits.as_string()),
+            ty: ty.to_string(),
+            bits,
+        })
+    }
+}
+
+fn size(ty: &ConcreteType) -> usize {
+    ty.to_mir_type()
+        .size()
+        .try_into()
+        .expect("Evaluated values fit in a usize")
+}
+
+fn unsigned(bits: &str) -> BigUint {
+    BigUint::parse_bytes(bits.as_bytes(), 2).unwrap_or_else(BigUint::zero)
+}
+
+fn signed(bits: &str) -> BigInt {
+    let value = BigInt::from(unsigned(bits));
+    if bits.starts_with('1') {
+        value - (BigInt::from(1) << bits.len())
+    } else {
+        value
+    }
+}
+
+/// Splits `bits` into the bits of `types`, where the first type is the most significant
+fn split<'a>(bits: &'a str, types: impl IntoIterator<Item = &'a ConcreteType>) -> Vec<&'a str> {
+    let mut start = 0;
+    types
+        .into_iter()
+        .map(|ty| {
+            let end = start + size(ty);
+            let part = &bits[start..end];
+            start = end;
+            part
+        })
+        .collect()
+}
+
+/// Writes the value with the bits `bits`, most significant bit first, as Spade code
+fn format_value(ty: &ConcreteType, bits: &str) -> String {
+    match ty {
+        ConcreteType::Tuple(inner) => {
+            let members = split(bits, inner)
+                .into_iter()
+                .zip(inner)
+                .map(|(bits, ty)| format_value(ty, bits))
+                .collect::<Vec<_>>();
+            if members.len() == 1 {
+                format!("({},)", members[0])
+            } else {
+                format!("({})", members.join(", "))
+            }
+        }
+        ConcreteType::Struct { name, members } => {
+            let values = split(bits, members.iter().map(|(_, ty)| ty))
+                .into_iter()
+                .zip(members)
+                .map(|(bits, (field, ty))| format!("{field}: {}", format_value(ty, bits)))
+                .collect::<Vec<_>>();
+            format!("{}$({})", name.1.tail(), values.join(", "))
+        }
+        ConcreteType::Array { inner, .. } => {
+            let element_size = size(inner);
+            // The first element is the least significant
+            let elements = if element_size == 0 {
+                vec![]
+            } else {
+                bits.as_bytes()
+                    .rchunks(element_size)
+                    .map(|chunk| {
+                        format_value(inner, std::str::from_utf8(chunk).expect("Bits are ASCII"))
+                    })
+                    .collect::<Vec<_>>()
+            };
+            format!("[{}]", elements.join(", "))
+        }
+        ConcreteType::Enum { options } => {
+            let tag_size = spade_mir::enum_util::tag_size(options.len());
+            let tag = unsigned(&bits[0..tag_size]);
+            let Some((variant, members)) =
+                usize::try_from(tag).ok().and_then(|tag| options.get(tag))
+            else {
+                return format!("<invalid variant 0b{}>", &bits[0..tag_size]);
+            };
+            let payload = &bits[tag_size..];
+            let values = split(payload, members.iter().map(|(_, ty)| ty))
+                .into_iter()
+                .zip(members)
+                .map(|(bits, (_, ty))| format_value(ty, bits))
+                .collect::<Vec<_>>();
+            if values.is_empty() {
+                format!("{}", variant.1.tail())
+            } else {
+                format!("{}({})", variant.1.tail(), values.join(", "))
+            }
+        }
+        ConcreteType::Single { base, .. } => match base {
+            PrimitiveType::Int => signed(bits).to_string(),
+            PrimitiveType::Uint => unsigned(bits).to_string(),
+            PrimitiveType::Bool => (bits == "1").to_string(),
+            PrimitiveType::Bit => if bits == "1" { "HIGH" } else { "LOW" }.to_string(),
+            PrimitiveType::Void => "()".to_string(),
+            PrimitiveType::Clock | PrimitiveType::Memory | PrimitiveType::InOut => {
+                format!("0b{bits}")
+            }
+        },
+        ConcreteType::Integer(_) | ConcreteType::Backward(_) | ConcreteType::Wire(_) => {
+            format!("0b{bits}")
+        }
+    }
+}
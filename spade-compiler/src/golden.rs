@@ -0,0 +1,396 @@
+//! Golden tests of compiler output. Every `.spade` file in a directory is compiled on
+//! its own, and the generated Verilog, MIR, item list and diagnostics are compared to
+//! snapshot files next to the input. For `adder.spade`, those are `adder.v`,
+//! `adder.mir`, `adder.items.ron` and `adder.stderr`. An output which is not produced,
+//! like the Verilog of a file which fails to compile, or the diagnostics of one which
+//! compiles cleanly, has no snapshot file. Only the units and items defined in the input
+//! file are snapshotted, so changes to the stdlib do not change every snapshot.
+//!
+//! When blessing, the snapshot files are updated to match the current output instead.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use codespan_reporting::term::termcolor::Buffer;
+use prettydiff::basic::DiffOp;
+use ron::ser::PrettyConfig;
+use spade_common::name::NameID;
+use spade_common::name::Path as SpadePath;
+use spade_diagnostics::emitter::CodespanEmitter;
+use spade_diagnostics::{CodeBundle, DiagHandler};
+use spade_hir::{ExecutableItem, ItemList};
+
+use crate::{name_dump, ModuleNamespace, Opt};
+
+/// Number of unchanged lines to show around each change in a diff
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GoldenError {
+    #[error("{} contains no .spade files", .0.to_string_lossy())]
+    NoInputs(PathBuf),
+    #[error("Failed to encode the item list: {0}")]
+    Encode(#[from] ron::Error),
+    #[error("{}: {source}", path.to_string_lossy())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+trait WithPath<T> {
+    fn with_path(self, path: &Path) -> Result<T, GoldenError>;
+}
+
+impl<T> WithPath<T> for std::io::Result<T> {
+    fn with_path(self, path: &Path) -> Result<T, GoldenError> {
+        self.map_err(|source| GoldenError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Snapshot {
+    Verilog,
+    Mir,
+    ItemList,
+    Diagnostics,
+}
+
+impl Snapshot {
+    pub const ALL: [Snapshot; 4] = [
+        Snapshot::Verilog,
+        Snapshot::Mir,
+        Snapshot::ItemList,
+        Snapshot::Diagnostics,
+    ];
+
+    /// The extension of the snapshot file, replacing `.spade` in the input file name
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Snapshot::Verilog => "v",
+            Snapshot::Mir => "mir",
+            Snapshot::ItemList => "items.ron",
+            Snapshot::Diagnostics => "stderr",
+        }
+    }
+
+    pub fn path_for(&self, input: &Path) -> PathBuf {
+        input.with_extension(self.extension())
+    }
+}
+
+/// A snapshot file which does not match the output of the compiler
+#[derive(Debug)]
+pub struct Mismatch {
+    pub input: PathBuf,
+    pub snapshot: PathBuf,
+    /// Line diff from the snapshot to the current output
+    pub diff: String,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "--- {}", self.snapshot.to_string_lossy())?;
+        writeln!(f, "+++ output of {}", self.input.to_string_lossy())?;
+        write!(f, "{}", self.diff)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GoldenReport {
+    /// The inputs which were compiled
+    pub inputs: Vec<PathBuf>,
+    /// Snapshots which differ from the output. Empty when blessing
+    pub mismatches: Vec<Mismatch>,
+    /// Snapshot files which were written or removed while blessing
+    pub blessed: Vec<PathBuf>,
+}
+
+impl GoldenReport {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Compiles `source` with the stdlib and returns each output it produces. `filename` is
+/// the name used for the file in diagnostics.
+pub fn outputs(filename: &str, source: &str) -> Result<BTreeMap<Snapshot, String>, GoldenError> {
+    let mut buffer = Buffer::no_color();
+    let opts = Opt::new(&mut buffer);
+
+    let sources = vec![(
+        ModuleNamespace {
+            namespace: SpadePath(vec![]),
+            base_namespace: SpadePath(vec![]),
+        },
+        filename.to_string(),
+        source.to_string(),
+    )];
+    let artefacts = crate::compile(
+        sources,
+        true,
+        opts,
+        DiagHandler::new(Box::new(CodespanEmitter)),
+    );
+
+    let mut result = BTreeMap::new();
+    if let Ok(artefacts) = artefacts {
+        let input = file_id(&artefacts.code, filename);
+        let in_input =
+            |name: &NameID| input.is_some() && defined_in(&artefacts.item_list, name) == input;
+
+        let (verilog, mir): (Vec<_>, Vec<_>) = artefacts
+            .flat_mir_entities
+            .iter()
+            .zip(artefacts.module_code.iter().zip(&artefacts.mir_code))
+            .filter(|(entity, _)| in_input(&entity.0.name.source))
+            .map(|(_, (verilog, mir))| (verilog.as_str(), mir.as_str()))
+            .unzip();
+        result.insert(Snapshot::Verilog, verilog.join("\n\n"));
+        result.insert(Snapshot::Mir, mir.join("\n\n"));
+
+        let mut items = artefacts.item_list.clone();
+        items.executables.retain(|name, _| in_input(name));
+        result.insert(Snapshot::ItemList, item_list(&items)?);
+    }
+    let diagnostics = String::from_utf8_lossy(buffer.as_slice()).to_string();
+    if !diagnostics.is_empty() {
+        result.insert(Snapshot::Diagnostics, diagnostics);
+    }
+    Ok(result)
+}
+
+/// The ID of the file named `filename`
+fn file_id(code: &CodeBundle, filename: &str) -> Option<usize> {
+    (0..)
+        .map_while(|id| code.file_name(id).map(|name| (id, name)))
+        .find(|(_, name)| *name == filename)
+        .map(|(id, _)| id)
+}
+
+/// The ID of the file in which the unit, struct or enum variant `name` is defined
+fn defined_in(item_list: &ItemList, name: &NameID) -> Option<usize> {
+    match item_list.executables.get(name)? {
+        ExecutableItem::Unit(unit) => Some(unit.file_id),
+        ExecutableItem::BuiltinUnit(_, head, _) => Some(head.file_id),
+        ExecutableItem::StructInstance => item_list.types.get(name).map(|t| t.file_id),
+        ExecutableItem::EnumInstance { base_enum, .. } => {
+            item_list.types.get(base_enum).map(|t| t.file_id)
+        }
+    }
+}
+
+/// The item list, sorted and pretty printed to make changes to it readable in a diff
+fn item_list(items: &ItemList) -> Result<String, GoldenError> {
+    let list = name_dump::list_names(items)
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    Ok(ron::ser::to_string_pretty(&list, PrettyConfig::default())?)
+}
+
+/// Compiles every `.spade` file in `dir` and compares the outputs to their snapshots. If
+/// `bless` is set, the snapshots are updated instead.
+pub fn run(dir: &Path, bless: bool) -> Result<GoldenReport, GoldenError> {
+    let mut inputs = std::fs::read_dir(dir)
+        .with_path(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .with_path(dir)?
+        .into_iter()
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "spade"))
+        .collect::<Vec<_>>();
+    inputs.sort();
+
+    if inputs.is_empty() {
+        return Err(GoldenError::NoInputs(dir.to_path_buf()));
+    }
+
+    let mut report = GoldenReport::default();
+    for input in inputs {
+        let source = std::fs::read_to_string(&input).with_path(&input)?;
+        // Diagnostics only contain the file name to not depend on where the tests are run
+        let filename = input
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut outputs = outputs(&filename, &source)?;
+
+        for snapshot in Snapshot::ALL {
+            let path = snapshot.path_for(&input);
+            let output = outputs.remove(&snapshot);
+            let expected = match std::fs::read_to_string(&path) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e).with_path(&path),
+            };
+
+            if output == expected {
+                continue;
+            }
+
+            if bless {
+                match &output {
+                    Some(output) => std::fs::write(&path, output).with_path(&path)?,
+                    None => std::fs::remove_file(&path).with_path(&path)?,
+                }
+                report.blessed.push(path);
+            } else {
+                report.mismatches.push(Mismatch {
+                    input: input.clone(),
+                    diff: line_diff(
+                        expected.as_deref().unwrap_or(""),
+                        output.as_deref().unwrap_or(""),
+                    ),
+                    snapshot: path,
+                });
+            }
+        }
+        report.inputs.push(input);
+    }
+
+    Ok(report)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Renders the lines which differ between `old` and `new`, with some unchanged lines
+/// around them for context. Each hunk starts with the line numbers in the old and new
+/// text where it begins
+pub fn line_diff(old: &str, new: &str) -> String {
+    let changeset = prettydiff::diff_lines(old, new);
+
+    let mut lines = vec![];
+    for op in changeset.diff() {
+        match op {
+            DiffOp::Equal(same) => lines.extend(same.iter().map(|l| DiffLine::Same(l))),
+            DiffOp::Remove(removed) => lines.extend(removed.iter().map(|l| DiffLine::Removed(l))),
+            DiffOp::Insert(added) => lines.extend(added.iter().map(|l| DiffLine::Added(l))),
+            DiffOp::Replace(removed, added) => {
+                lines.extend(removed.iter().map(|l| DiffLine::Removed(l)));
+                lines.extend(added.iter().map(|l| DiffLine::Added(l)));
+            }
+        }
+    }
+    // Show the removed lines of each change before the added ones
+    for change in lines.split_mut(|l| matches!(l, DiffLine::Same(_))) {
+        change.sort_by_key(|l| matches!(l, DiffLine::Added(_)));
+    }
+
+    let changed = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| !matches!(l, DiffLine::Same(_)))
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+
+    let mut result = String::new();
+    let (mut old_line, mut new_line) = (1, 1);
+    let mut next_change = 0;
+    let mut in_hunk = false;
+    for (idx, line) in lines.iter().enumerate() {
+        while next_change < changed.len() && changed[next_change] + CONTEXT_LINES < idx {
+            next_change += 1;
+        }
+        let shown = changed
+            .get(next_change)
+            .is_some_and(|&c| idx + CONTEXT_LINES >= c && idx <= c + CONTEXT_LINES);
+
+        if shown && !in_hunk {
+            result += &format!("@@ -{old_line} +{new_line} @@\n");
+        }
+        in_hunk = shown;
+
+        match line {
+            DiffLine::Same(l) => {
+                if shown {
+                    result += &format!(" {l}\n");
+                }
+                old_line += 1;
+                new_line += 1;
+            }
+            DiffLine::Removed(l) => {
+                result += &format!("-{l}\n");
+                old_line += 1;
+            }
+            DiffLine::Added(l) => {
+                result += &format!("+{l}\n");
+                new_line += 1;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+
+    #[test]
+    fn identical_texts_have_no_diff() {
+        assert_eq!(line_diff("a\nb\n", "a\nb\n"), "");
+    }
+
+    #[test]
+    fn changed_line_is_shown_with_context() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = "1\n2\n3\n4\n5\nsix\n7\n8\n9\n10\n";
+
+        let expected = indoc! {"
+            @@ -3 +3 @@
+             3
+             4
+             5
+            -6
+            +six
+             7
+             8
+             9
+        "};
+        assert_eq!(line_diff(old, new), expected);
+    }
+
+    #[test]
+    fn distant_changes_are_separate_hunks() {
+        let old = (1..=20).map(|i| format!("{i}\n")).collect::<String>();
+        let new = (1..=20)
+            .filter(|i| *i != 18)
+            .map(|i| match i {
+                2 => "two\n".to_string(),
+                _ => format!("{i}\n"),
+            })
+            .collect::<String>();
+
+        let expected = indoc! {"
+            @@ -1 +1 @@
+             1
+            -2
+            +two
+             3
+             4
+             5
+            @@ -15 +15 @@
+             15
+             16
+             17
+            -18
+             19
+             20
+        "};
+        assert_eq!(line_diff(&old, &new), expected);
+    }
+
+    #[test]
+    fn added_file_is_all_additions() {
+        assert_eq!(line_diff("", "a\nb\n"), "@@ -1 +1 @@\n+a\n+b\n");
+    }
+}
@@ -1,17 +1,25 @@
 pub mod compiler_state;
+pub mod eval;
+pub mod golden;
+pub mod library;
 mod name_dump;
 pub mod namespaced_file;
 
 use codespan_reporting::term::termcolor::Buffer;
 use compiler_state::{CompilerState, MirContext};
+use library::Library;
 use logos::Logos;
 use ron::ser::PrettyConfig;
 use spade_ast_lowering::id_tracker::ExprIdTracker;
 pub use spade_common::namespace::ModuleNamespace;
+use spade_mir::cdc::clock_domain_crossings;
 use spade_mir::codegen::{prepare_codegen, Codegenable};
+use spade_mir::coverage::insert_coverage_signals;
+use spade_mir::formal::{formal_tops, sby_file, sby_file_name, DEFAULT_DEPTH};
+use spade_mir::graph::{dataflow_graph, instance_hierarchy, write_hierarchies, GraphFormat};
 use spade_mir::unit_name::InstanceMap;
 use spade_mir::verilator_wrapper::verilator_wrappers;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io::Write;
 use std::path::PathBuf;
 use std::rc::Rc;
@@ -24,10 +32,10 @@ use spade_ast_lowering::{
     ensure_unique_anonymous_traits, global_symbols, visit_module_body, Context as AstLoweringCtx,
     SelfContext,
 };
-use spade_common::id_tracker::ImplIdTracker;
+use spade_common::location_info::WithLocation;
 use spade_common::name::{NameID, Path as SpadePath};
-use spade_diagnostics::{CodeBundle, CompilationError, DiagHandler, Diagnostic};
-use spade_hir::symbol_table::SymbolTable;
+use spade_diagnostics::diagnostic::DiagnosticLevel;
+use spade_diagnostics::{CodeBundle, CompilationError, DiagHandler, Diagnostic, Lint, Lints};
 use spade_hir::{ExecutableItem, ItemList};
 use spade_hir_lowering::monomorphisation::MirOutput;
 use spade_hir_lowering::NameSourceMap;
@@ -35,6 +43,7 @@ pub use spade_parser::lexer;
 use spade_parser::Parser;
 use spade_typeinference as typeinference;
 use spade_typeinference::trace_stack::format_trace_stack;
+use spade_typeinference::unification_chain::TypeErrorVerbosity;
 
 pub fn wordlength_inference_method(
     arg: &str,
@@ -52,17 +61,113 @@ pub fn wordlength_inference_method(
     })
 }
 
+pub fn doc_format(arg: &str) -> Result<spade_doc::Format, String> {
+    match arg.to_lowercase().as_str() {
+        "html" => Ok(spade_doc::Format::Html),
+        "markdown" | "md" => Ok(spade_doc::Format::Markdown),
+        _ => Err("Expected one of: \"html\" or \"markdown\"".to_string()),
+    }
+}
+
+pub fn graph_format(arg: &str) -> Result<GraphFormat, String> {
+    match arg.to_lowercase().as_str() {
+        "dot" => Ok(GraphFormat::Dot),
+        "json" => Ok(GraphFormat::Json),
+        _ => Err("Expected one of: \"dot\" or \"json\"".to_string()),
+    }
+}
+
+pub fn type_error_verbosity(arg: &str) -> Result<TypeErrorVerbosity, String> {
+    match arg.to_lowercase().as_str() {
+        "short" => Ok(TypeErrorVerbosity::Short),
+        "chain" => Ok(TypeErrorVerbosity::Chain),
+        "full" => Ok(TypeErrorVerbosity::Full),
+        _ => Err("Expected one of: \"short\", \"chain\" or \"full\"".to_string()),
+    }
+}
+
+/// The name of the file in which to write the graph of the unit with the specified name
+fn graph_file_name(unit: &str, format: GraphFormat) -> String {
+    let name = unit
+        .replace("::", ".")
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '.' || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{name}.{}", format.extension())
+}
+
 pub struct Opt<'b> {
     pub error_buffer: &'b mut Buffer,
     pub outfile: Option<PathBuf>,
     pub mir_output: Option<PathBuf>,
     pub verilator_wrapper_output: Option<PathBuf>,
+    pub sby_output: Option<PathBuf>,
     pub state_dump_file: Option<PathBuf>,
     pub item_list_file: Option<PathBuf>,
+    /// Directory in which to write the documentation, and the format to write it in
+    pub doc_output: Option<(PathBuf, spade_doc::Format)>,
+    /// File in which to write the instance hierarchy, and the format to write it in
+    pub hierarchy_output: Option<(PathBuf, GraphFormat)>,
+    /// The unit at the top of the hierarchy. If this is not set, the hierarchies of all
+    /// units which are not instantiated are written
+    pub hierarchy_top: Option<String>,
+    /// Directory in which to write the dataflow graph of each unit, and the format to
+    /// write them in
+    pub dataflow_output: Option<(PathBuf, GraphFormat)>,
+    /// Precompiled libraries to start the compilation from. Each library must have been
+    /// built with the others before it linked
+    pub library: Vec<Library>,
+    /// Make the last linked library available under this namespace, in addition to the
+    /// namespace it was built in
+    pub library_namespace: Option<String>,
+    /// File in which to write a library containing the code in this compilation which is
+    /// not part of a linked library
+    pub library_output: Option<PathBuf>,
     pub print_type_traceback: bool,
+    /// How much to explain about where the types in type mismatches come from
+    pub type_error_verbosity: TypeErrorVerbosity,
     pub print_parse_traceback: bool,
     pub wl_infer_method: Option<spade_wordlength_inference::InferMethod>,
     pub opt_passes: Vec<String>,
+    pub coverage: bool,
+    /// Fail compilation if any warnings are emitted
+    pub warnings_as_errors: bool,
+}
+
+impl<'b> Opt<'b> {
+    /// Options which report diagnostics to `error_buffer` without writing any output files.
+    /// Other options can be set with struct update syntax
+    pub fn new(error_buffer: &'b mut Buffer) -> Self {
+        Self {
+            error_buffer,
+            outfile: None,
+            mir_output: None,
+            verilator_wrapper_output: None,
+            sby_output: None,
+            state_dump_file: None,
+            item_list_file: None,
+            doc_output: None,
+            hierarchy_output: None,
+            hierarchy_top: None,
+            dataflow_output: None,
+            library: vec![],
+            library_namespace: None,
+            library_output: None,
+            print_type_traceback: false,
+            type_error_verbosity: Default::default(),
+            print_parse_traceback: false,
+            wl_infer_method: None,
+            opt_passes: vec![],
+            coverage: false,
+            warnings_as_errors: false,
+        }
+    }
 }
 
 trait Reportable<T> {
@@ -91,6 +196,7 @@ where
 
 pub struct ErrorHandler<'a> {
     pub failed: bool,
+    pub warnings_as_errors: bool,
     pub error_buffer: &'a mut Buffer,
     pub diag_handler: DiagHandler,
     /// Using a RW lock here is just a lazy way of managing the ownership of code to
@@ -107,6 +213,40 @@ impl<'a> ErrorHandler<'a> {
             &mut self.diag_handler,
         );
     }
+
+    /// Report a diagnostic which does not cause compilation to fail, unless warnings
+    /// are treated as errors
+    fn warn(&mut self, diag: &Diagnostic) {
+        if self.warnings_as_errors {
+            self.report(
+                &diag
+                    .clone()
+                    .level(DiagnosticLevel::Error)
+                    .note("Warnings are treated as errors"),
+            )
+        } else {
+            diag.report(
+                self.error_buffer,
+                &self.code.read().unwrap(),
+                &mut self.diag_handler,
+            );
+        }
+    }
+
+    /// Report the lints emitted during compilation. Denied lints cause compilation to fail,
+    /// but all lints are reported before that happens. If compilation has already failed,
+    /// lints are not reported since they are often caused by the errors
+    fn report_lints(&mut self) {
+        if self.failed {
+            return;
+        }
+        for diag in self.diag_handler.lints.resolve() {
+            match diag.level {
+                DiagnosticLevel::Warning => self.warn(&diag),
+                _ => self.report(&diag),
+            }
+        }
+    }
 }
 
 /// Compiler output.
@@ -117,6 +257,10 @@ pub struct Artefacts {
     pub bumpy_mir_entities: Vec<spade_mir::Entity>,
     // MIR entities after flattening
     pub flat_mir_entities: Vec<Codegenable>,
+    // The Verilog of each module, as written to the output file
+    pub module_code: Vec<String>,
+    // The MIR of each unit, as written to the MIR output file
+    pub mir_code: Vec<String>,
     pub state: CompilerState,
     pub type_states: BTreeMap<NameID, TypeState>,
 }
@@ -144,33 +288,94 @@ pub fn compile(
     opts: Opt,
     diag_handler: DiagHandler,
 ) -> Result<Artefacts, UnfinishedArtefacts> {
-    let mut symtab = SymbolTable::new();
-    let mut item_list = ItemList::new();
-
-    let sources = if include_stdlib_and_prelude {
+    let mut linked = library::Linked::new();
+    let link_result = linked.link_all(opts.library);
+    // The namespace of the code in this compilation, used if it is written as a library
+    let namespace = sources
+        .first()
+        .map(|(namespace, _, _)| namespace.base_namespace.clone())
+        .or_else(|| linked.namespace.clone())
+        .unwrap_or(SpadePath(vec![]));
+
+    let compile_stdlib = include_stdlib_and_prelude && !linked.includes_stdlib;
+    let (sources, external_sources) = if compile_stdlib {
         // We want to build stdlib and prelude before building user code,
         // to give `previously defined <here>` pointing into user code, instead
         // of stdlib code
         let mut all_sources = stdlib_and_prelude();
+        let external_sources = all_sources.len();
         all_sources.append(&mut sources);
-        all_sources
+        (all_sources, external_sources)
     } else {
-        sources
+        (sources, 0)
     };
 
-    spade_ast_lowering::builtins::populate_symtab(&mut symtab, &mut item_list);
+    let first_ids = linked.first_ids();
+    let library::Linked {
+        mut symtab,
+        item_list,
+        code,
+        idtracker,
+        impl_idtracker,
+        namespace: library_namespace,
+        lints: linked_lints,
+        ..
+    } = linked;
 
-    let code = Rc::new(RwLock::new(CodeBundle::new("".to_string())));
+    let code = Rc::new(RwLock::new(code));
 
     let mut errors = ErrorHandler {
         failed: false,
+        warnings_as_errors: opts.warnings_as_errors,
         error_buffer: opts.error_buffer,
         diag_handler,
         code: Rc::clone(&code),
     };
+    errors.diag_handler.lints.append(linked_lints);
+
+    if let Err(e) = link_result {
+        errors.failed = true;
+        writeln!(errors.error_buffer, "{e}").unwrap();
+    }
+
+    if let Some(alias) = &opts.library_namespace {
+        match library_namespace {
+            Some(target) if !target.0.is_empty() => {
+                let alias_path = SpadePath::from_strs(&[alias]);
+                if symtab
+                    .add_alias(alias_path.nowhere(), target.nowhere())
+                    .is_err()
+                {
+                    errors.failed = true;
+                    writeln!(
+                        errors.error_buffer,
+                        "Can not link the library as {alias}, the name is already in use"
+                    )
+                    .unwrap();
+                }
+            }
+            Some(_) => {
+                errors.failed = true;
+                writeln!(
+                    errors.error_buffer,
+                    "Libraries built in the root namespace can not be linked under a namespace"
+                )
+                .unwrap();
+            }
+            None => {
+                errors.failed = true;
+                writeln!(
+                    errors.error_buffer,
+                    "A library namespace was specified without linking a library"
+                )
+                .unwrap();
+            }
+        }
+    }
 
     let module_asts = parse(
         sources,
+        external_sources,
         Rc::clone(&code),
         opts.print_parse_traceback,
         &mut errors,
@@ -210,10 +415,12 @@ pub fn compile(
     let mut ctx = AstLoweringCtx {
         symtab,
         item_list,
-        idtracker: ExprIdTracker::new(),
-        impl_idtracker: ImplIdTracker::new(),
+        idtracker,
+        impl_idtracker,
         pipeline_ctx: None,
         self_ctx: SelfContext::FreeStanding,
+        reset_style: Default::default(),
+        lints: Lints::new(),
     };
 
     for (namespace, module_ast) in &module_asts {
@@ -246,6 +453,17 @@ pub fn compile(
 
     lower_ast(&module_asts, &mut ctx, &mut errors);
 
+    let library = opts.library_output.map(|file| {
+        let encoded = library::encode(
+            &namespace,
+            compile_stdlib,
+            first_ids,
+            &code.read().unwrap().dump_files(),
+            &ctx,
+        );
+        (file, encoded)
+    });
+
     let AstLoweringCtx {
         symtab,
         item_list,
@@ -253,8 +471,12 @@ pub fn compile(
         impl_idtracker,
         pipeline_ctx: _,
         self_ctx: _,
+        reset_style: _,
+        lints,
     } = ctx;
 
+    errors.diag_handler.lints.append(lints);
+
     unfinished_artefacts.item_list = Some(item_list.clone());
 
     for e in ensure_unique_anonymous_traits(&item_list) {
@@ -287,7 +509,8 @@ pub fn compile(
         .filter_map(|(name, item)| match item {
             ExecutableItem::Unit(u) => {
                 let mut type_state = typeinference::TypeState::new()
-                    .set_wordlength_inferece(opts.wl_infer_method.is_some());
+                    .set_wordlength_inferece(opts.wl_infer_method.is_some())
+                    .set_type_error_verbosity(opts.type_error_verbosity);
 
                 if let Ok(()) = type_state
                     .visit_unit(u, &type_inference_ctx)
@@ -308,7 +531,7 @@ pub fn compile(
             }
             ExecutableItem::EnumInstance { .. } => None,
             ExecutableItem::StructInstance { .. } => None,
-            ExecutableItem::BuiltinUnit(_, _) => None,
+            ExecutableItem::BuiltinUnit(_, _, _) => None,
         })
         .collect::<BTreeMap<_, _>>();
 
@@ -332,6 +555,7 @@ pub fn compile(
         &mut name_source_map,
         &item_list,
         &mut errors.diag_handler,
+        &code.read().unwrap(),
         opts.wl_infer_method,
         &opt_passes,
     );
@@ -343,7 +567,13 @@ pub fn compile(
         mir_code,
         instance_map,
         mir_context,
-    } = codegen(mir_entities, Rc::clone(&code), &mut errors, &mut idtracker);
+    } = codegen(
+        mir_entities,
+        Rc::clone(&code),
+        &mut errors,
+        &mut idtracker,
+        opts.coverage,
+    );
 
     let state = CompilerState {
         code: code.read().unwrap().dump_files(),
@@ -356,13 +586,39 @@ pub fn compile(
         mir_context,
     };
 
+    errors.report_lints();
+
     if errors.failed {
         return Err(unfinished_artefacts);
     }
 
-    if let Some(outfile) = opts.outfile {
+    if let Some(outfile) = &opts.outfile {
         std::fs::write(outfile, module_code.join("\n\n")).or_report(&mut errors);
     }
+    if let Some(sby_dir) = opts.sby_output {
+        match &opts.outfile {
+            Some(outfile) => {
+                let entities = flat_mir_entities.iter().map(|e| &e.0).collect::<Vec<_>>();
+                if let Some(verilog_file) = std::fs::canonicalize(outfile).or_report(&mut errors) {
+                    for top in formal_tops(&entities) {
+                        std::fs::write(
+                            sby_dir.join(sby_file_name(top)),
+                            sby_file(top, &verilog_file, DEFAULT_DEPTH),
+                        )
+                        .or_report(&mut errors);
+                    }
+                }
+            }
+            None => {
+                errors.failed = true;
+                writeln!(
+                    errors.error_buffer,
+                    "Generating SymbiYosys jobs requires an output file for the verilog"
+                )
+                .unwrap();
+            }
+        }
+    }
     if let Some(cpp_file) = opts.verilator_wrapper_output {
         let cpp_code =
             verilator_wrappers(&flat_mir_entities.iter().map(|e| &e.0).collect::<Vec<_>>());
@@ -384,6 +640,64 @@ pub fn compile(
             }
         }
     }
+    if let Some((doc_dir, format)) = opts.doc_output {
+        if std::fs::create_dir_all(&doc_dir)
+            .or_report(&mut errors)
+            .is_some()
+        {
+            for (file, content) in spade_doc::generate(&item_list, format) {
+                std::fs::write(doc_dir.join(file), content).or_report(&mut errors);
+            }
+        }
+    }
+    if let Some((hierarchy_file, format)) = opts.hierarchy_output {
+        let entities = flat_mir_entities.iter().map(|e| &e.0).collect::<Vec<_>>();
+        match instance_hierarchy(
+            &entities,
+            &state.instance_map,
+            opts.hierarchy_top.as_deref(),
+        ) {
+            Some(roots) => {
+                std::fs::write(hierarchy_file, write_hierarchies(&roots, format))
+                    .or_report(&mut errors);
+            }
+            None => {
+                errors.failed = true;
+                writeln!(
+                    errors.error_buffer,
+                    "Found no unit named {} to use as the top of the hierarchy",
+                    opts.hierarchy_top.unwrap_or_default()
+                )
+                .unwrap();
+            }
+        }
+    }
+    if let Some((dataflow_dir, format)) = opts.dataflow_output {
+        if std::fs::create_dir_all(&dataflow_dir)
+            .or_report(&mut errors)
+            .is_some()
+        {
+            for entity in flat_mir_entities.iter().map(|e| &e.0) {
+                let graph = dataflow_graph(entity, &|name| state.demangle_value_name(name));
+                std::fs::write(
+                    dataflow_dir.join(graph_file_name(&graph.name, format)),
+                    graph.write(format),
+                )
+                .or_report(&mut errors);
+            }
+        }
+    }
+    if let Some((library_file, encoded)) = library {
+        match encoded {
+            Ok(encoded) => {
+                std::fs::write(library_file, encoded).or_report(&mut errors);
+            }
+            Err(e) => {
+                errors.failed = true;
+                writeln!(errors.error_buffer, "{e}").unwrap();
+            }
+        }
+    }
     if let Some(state_dump_file) = opts.state_dump_file {
         let ron = ron::Options::default().without_recursion_limit();
 
@@ -404,6 +718,8 @@ pub fn compile(
         Ok(Artefacts {
             bumpy_mir_entities,
             flat_mir_entities,
+            module_code,
+            mir_code,
             code: code.read().unwrap().clone(),
             item_list,
             state,
@@ -435,15 +751,21 @@ fn do_in_namespace(
 #[tracing::instrument(skip_all)]
 fn parse(
     sources: Vec<(ModuleNamespace, String, String)>,
+    // The number of sources at the start of `sources` which are not part of the
+    // user's code, and should not have lints reported
+    external_sources: usize,
     code: Rc<RwLock<CodeBundle>>,
     print_parse_traceback: bool,
     errors: &mut ErrorHandler,
 ) -> Vec<(ModuleNamespace, ModuleBody)> {
     let mut module_asts = vec![];
     // Read and parse input files
-    for (namespace, name, content) in sources {
+    for (i, (namespace, name, content)) in sources.into_iter().enumerate() {
         let _span = tracing::span!(Level::TRACE, "source", ?name).entered();
         let file_id = code.write().unwrap().add_file(name, content.clone());
+        if i < external_sources {
+            errors.diag_handler.lints.mark_external(file_id);
+        }
         let mut parser = Parser::new(lexer::TokenKind::lexer(&content), file_id);
 
         let result = parser
@@ -495,6 +817,7 @@ fn codegen(
     code: Rc<RwLock<CodeBundle>>,
     errors: &mut ErrorHandler,
     idtracker: &mut ExprIdTracker,
+    coverage: bool,
 ) -> CodegenArtefacts {
     let mut bumpy_mir_entities = vec![];
     let mut flat_mir_entities = vec![];
@@ -502,17 +825,36 @@ fn codegen(
     let mut mir_code = vec![];
     let mut instance_map = InstanceMap::new();
     let mut mir_context = HashMap::new();
+    let mut cdc_primitives = BTreeSet::new();
 
     for mir in mir_entities {
         if let Some(MirOutput {
             mir,
             type_state,
             reg_name_map,
+            warnings,
+            cdc_primitive,
         }) = mir.or_report(errors)
         {
+            for warning in warnings {
+                errors
+                    .diag_handler
+                    .lints
+                    .emit(Lint::OversizedWordlength, warning);
+            }
+
+            if cdc_primitive {
+                cdc_primitives.insert(mir.name.kind.clone());
+            }
+
             bumpy_mir_entities.push(mir.clone());
 
-            let codegenable = prepare_codegen(mir, idtracker);
+            let mut codegenable = prepare_codegen(mir, idtracker);
+            let coverage_points = if coverage {
+                insert_coverage_signals(&mut codegenable.0, &mut None)
+            } else {
+                vec![]
+            };
 
             let code = spade_mir::codegen::entity_code(
                 &codegenable,
@@ -527,6 +869,16 @@ fn codegen(
             let (code, name_map) = code;
             module_code.push(code.to_string());
 
+            let registers = codegenable
+                .0
+                .statements
+                .iter()
+                .filter_map(|stmt| match stmt {
+                    spade_mir::Statement::Register(reg) => Some(reg.name.unescaped_var_name()),
+                    _ => None,
+                })
+                .collect();
+
             mir_context.insert(
                 codegenable.0.name.source,
                 MirContext {
@@ -535,11 +887,21 @@ fn codegen(
                     // FIXME: Insert pipeline register stuff into the type map
                     type_map: type_state.into(),
                     verilog_name_map: name_map,
+                    coverage_points,
+                    registers,
                 },
             );
         }
     }
 
+    let entities = bumpy_mir_entities.iter().collect::<Vec<_>>();
+    for crossing in clock_domain_crossings(&entities, &cdc_primitives) {
+        errors
+            .diag_handler
+            .lints
+            .emit(Lint::ClockDomainCrossing, crossing.into());
+    }
+
     CodegenArtefacts {
         bumpy_mir_entities,
         flat_mir_entities,
@@ -579,6 +941,7 @@ pub fn stdlib_and_prelude() -> Vec<(ModuleNamespace, String, String)> {
         (["std"], ["std", "ports"], "../stdlib/ports.spade"),
         (["std"], ["std", "option"], "../stdlib/option.spade"),
         (["std"], ["std", "cdc"], "../stdlib/cdc.spade"),
+        (["std"], ["std", "stream"], "../stdlib/stream.spade"),
     }
 }
 
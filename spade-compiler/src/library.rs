@@ -0,0 +1,313 @@
+//! Precompiled libraries. A library is a snapshot of the names, items and trait impls
+//! created while lowering the code in it, along with the source code for diagnostics.
+//! Linking a library into a compilation starts the compilation with those added, which
+//! avoids parsing and lowering the code again.
+//!
+//! The names in a library are identified by IDs which continue from those of the
+//! libraries it was built with, so several libraries can only be linked together if each
+//! of them was built with the ones before it linked.
+
+use serde::{Deserialize, Serialize};
+use spade_ast_lowering::id_tracker::ExprIdTracker;
+use spade_ast_lowering::Context as AstLoweringCtx;
+use spade_common::id_tracker::ImplIdTracker;
+use spade_common::name::Path as SpadePath;
+use spade_diagnostics::{CodeBundle, Lints};
+use spade_hir::symbol_table::SymbolTable;
+use spade_hir::ItemList;
+
+/// The first word of every library file, to give a clean error if something else is
+/// passed as a library
+const MAGIC: &str = "spade-library";
+/// The version of the library format. Must be bumped whenever the format changes in a
+/// way which is not caught by the compiler version check
+pub const FORMAT_VERSION: u32 = 2;
+/// Libraries can only be linked by the compiler version which built them, since the
+/// compiler state changes between versions
+pub const COMPILER_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// A hash of the compiler source which decides the contents of libraries, computed by the
+/// build script. Catches changes to the compiler state between releases
+pub const SOURCE_HASH: &str = env!("SPADE_LIBRARY_SOURCE_HASH");
+
+#[derive(Debug, thiserror::Error)]
+pub enum LibraryError {
+    #[error("Not a Spade library")]
+    NotALibrary,
+    #[error("Library format {found} is not supported, expected format {FORMAT_VERSION}")]
+    FormatMismatch { found: u32 },
+    #[error(
+        "The library was built by Spade {found} but this is Spade {COMPILER_VERSION}. Rebuild the library with this compiler"
+    )]
+    CompilerMismatch { found: String },
+    #[error(
+        "The library was built by a different build of Spade {COMPILER_VERSION}. Rebuild the library with this compiler"
+    )]
+    SourceMismatch,
+    #[error("The library {library} was built with libraries which are not linked")]
+    MissingDependency { library: SpadePath },
+    #[error(
+        "The libraries {library} and {previous} were built separately. Libraries can only be linked together if each of them was built with the ones before it linked"
+    )]
+    BuiltSeparately {
+        library: SpadePath,
+        previous: SpadePath,
+    },
+    #[error("Failed to decode the library: {0}")]
+    Decode(#[from] ron::de::SpannedError),
+    #[error("Failed to encode the library: {0}")]
+    Encode(#[from] ron::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// The IDs of the first name, impl block and file which are not part of the builtins
+/// or a linked library
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FirstIds {
+    name: u64,
+    impl_block: u64,
+    file: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Library {
+    /// The namespace which `lib` refers to in the library code
+    pub namespace: SpadePath,
+    /// True if the stdlib and prelude are part of the library
+    pub includes_stdlib: bool,
+    pub(crate) first_ids: FirstIds,
+    pub(crate) code: Vec<(String, String)>,
+    pub(crate) symtab: SymbolTable,
+    pub(crate) item_list: ItemList,
+    pub(crate) idtracker: ExprIdTracker,
+    pub(crate) impl_idtracker: ImplIdTracker,
+}
+
+/// The contents of a library which is being built, borrowed from the compiler state
+#[derive(Serialize)]
+struct LibraryRef<'a> {
+    namespace: &'a SpadePath,
+    includes_stdlib: bool,
+    first_ids: FirstIds,
+    code: &'a [(String, String)],
+    symtab: &'a SymbolTable,
+    item_list: &'a ItemList,
+    idtracker: &'a ExprIdTracker,
+    impl_idtracker: &'a ImplIdTracker,
+}
+
+fn header(format_version: u32, compiler_version: &str, source_hash: &str) -> String {
+    format!("{MAGIC} {format_version} {compiler_version} {source_hash}")
+}
+
+/// The state to start a compilation from, with the builtins and all linked libraries added
+pub(crate) struct Linked {
+    pub symtab: SymbolTable,
+    pub item_list: ItemList,
+    pub code: CodeBundle,
+    pub idtracker: ExprIdTracker,
+    pub impl_idtracker: ImplIdTracker,
+    /// The namespace of the last linked library
+    pub namespace: Option<SpadePath>,
+    /// True if one of the linked libraries includes the stdlib and prelude
+    pub includes_stdlib: bool,
+    /// Marks the code of the linked libraries as external. Lints in it were reported when
+    /// the libraries were built
+    pub lints: Lints,
+    files: usize,
+}
+
+impl Linked {
+    pub fn new() -> Self {
+        let mut symtab = SymbolTable::new();
+        let mut item_list = ItemList::new();
+        spade_ast_lowering::builtins::populate_symtab(&mut symtab, &mut item_list);
+        Self {
+            symtab,
+            item_list,
+            code: CodeBundle::new("".to_string()),
+            idtracker: ExprIdTracker::new(),
+            impl_idtracker: ImplIdTracker::new(),
+            namespace: None,
+            includes_stdlib: false,
+            lints: Lints::new(),
+            files: 1,
+        }
+    }
+
+    /// The IDs at which the code compiled after linking starts
+    pub fn first_ids(&self) -> FirstIds {
+        FirstIds {
+            name: self.symtab.peek_id(),
+            impl_block: self.impl_idtracker.peek(),
+            file: self.files,
+        }
+    }
+
+    /// Links `libraries`, in the order they were built in
+    pub fn link_all(&mut self, mut libraries: Vec<Library>) -> Result<(), LibraryError> {
+        libraries.sort_by_key(|lib| lib.first_ids.name);
+        for library in libraries {
+            self.link(library)?;
+        }
+        Ok(())
+    }
+
+    fn link(&mut self, library: Library) -> Result<(), LibraryError> {
+        if library.first_ids != self.first_ids() {
+            return Err(match &self.namespace {
+                Some(previous) if library.first_ids.name <= self.symtab.peek_id() => {
+                    LibraryError::BuiltSeparately {
+                        library: library.namespace,
+                        previous: previous.clone(),
+                    }
+                }
+                _ => LibraryError::MissingDependency {
+                    library: library.namespace,
+                },
+            });
+        }
+
+        self.symtab.merge(library.symtab);
+        self.item_list.merge(library.item_list);
+        for (name, content) in library.code {
+            let file_id = self.code.add_file(name, content);
+            self.lints.mark_external(file_id);
+            self.files += 1;
+        }
+        self.idtracker = library.idtracker;
+        self.impl_idtracker = library.impl_idtracker;
+        self.namespace = Some(library.namespace);
+        self.includes_stdlib |= library.includes_stdlib;
+        Ok(())
+    }
+}
+
+/// Encodes the code lowered by `ctx` after `first_ids` as a library
+pub(crate) fn encode(
+    namespace: &SpadePath,
+    includes_stdlib: bool,
+    first_ids: FirstIds,
+    code: &[(String, String)],
+    ctx: &AstLoweringCtx,
+) -> Result<String, LibraryError> {
+    let library = LibraryRef {
+        namespace,
+        includes_stdlib,
+        first_ids,
+        code: &code[first_ids.file..],
+        symtab: &ctx.symtab.subset(first_ids.name),
+        item_list: &ctx.item_list.subset(first_ids.name, first_ids.impl_block),
+        idtracker: &ctx.idtracker,
+        impl_idtracker: &ctx.impl_idtracker,
+    };
+    let body = ron::Options::default()
+        .without_recursion_limit()
+        .to_string(&library)?;
+    Ok(format!(
+        "{}\n{body}",
+        header(FORMAT_VERSION, COMPILER_VERSION, SOURCE_HASH)
+    ))
+}
+
+impl Library {
+    /// Decodes a library, rejecting libraries from other versions of the compiler before
+    /// attempting to decode the contents
+    pub fn decode(source: &str) -> Result<Self, LibraryError> {
+        let (header, body) = source.split_once('\n').unwrap_or((source, ""));
+        let (format_version, compiler_version, source_hash) =
+            match header.split(' ').collect::<Vec<_>>()[..] {
+                [MAGIC, format_version, compiler_version, source_hash] => {
+                    (format_version, compiler_version, source_hash)
+                }
+                [MAGIC, format_version, compiler_version] => (format_version, compiler_version, ""),
+                _ => return Err(LibraryError::NotALibrary),
+            };
+        let format_version = format_version
+            .parse::<u32>()
+            .map_err(|_| LibraryError::NotALibrary)?;
+        if format_version != FORMAT_VERSION {
+            return Err(LibraryError::FormatMismatch {
+                found: format_version,
+            });
+        }
+        if compiler_version != COMPILER_VERSION {
+            return Err(LibraryError::CompilerMismatch {
+                found: compiler_version.to_string(),
+            });
+        }
+        if source_hash != SOURCE_HASH {
+            return Err(LibraryError::SourceMismatch);
+        }
+
+        Ok(ron::Options::default()
+            .without_recursion_limit()
+            .from_str(body)?)
+    }
+
+    pub fn read(file: &std::path::Path) -> Result<Self, LibraryError> {
+        Self::decode(&std::fs::read_to_string(file)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_error(source: &str) -> String {
+        match Library::decode(source) {
+            Ok(_) => panic!("Decoded an invalid library"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn files_without_a_header_are_rejected() {
+        assert_eq!(decode_error("entity x() {}"), "Not a Spade library");
+        assert_eq!(decode_error(""), "Not a Spade library");
+        assert_eq!(
+            decode_error("spade-library x 1.0.0\n()"),
+            "Not a Spade library"
+        );
+    }
+
+    #[test]
+    fn other_versions_are_rejected_before_decoding() {
+        assert_eq!(
+            decode_error(&format!(
+                "{}\n(garbage",
+                header(FORMAT_VERSION, "0.0.1", SOURCE_HASH)
+            )),
+            format!(
+                "The library was built by Spade 0.0.1 but this is Spade {COMPILER_VERSION}. Rebuild the library with this compiler"
+            )
+        );
+        assert_eq!(
+            decode_error(&format!(
+                "{}\n(garbage",
+                header(0, COMPILER_VERSION, SOURCE_HASH)
+            )),
+            format!("Library format 0 is not supported, expected format {FORMAT_VERSION}")
+        );
+    }
+
+    #[test]
+    fn other_builds_are_rejected_before_decoding() {
+        let expected = format!(
+            "The library was built by a different build of Spade {COMPILER_VERSION}. Rebuild the library with this compiler"
+        );
+        assert_eq!(
+            decode_error(&format!(
+                "{}\n(garbage",
+                header(FORMAT_VERSION, COMPILER_VERSION, "0000000000000000")
+            )),
+            expected
+        );
+        assert_eq!(
+            decode_error(&format!(
+                "{MAGIC} {FORMAT_VERSION} {COMPILER_VERSION}\n(garbage"
+            )),
+            expected
+        );
+    }
+}
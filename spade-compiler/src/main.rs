@@ -2,23 +2,57 @@ use std::fs::File;
 use std::io::{prelude::*, stderr, IsTerminal};
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use codespan_reporting::term::termcolor::Buffer;
 use color_eyre::eyre::{anyhow, bail, Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use spade_diagnostics::emitter::CodespanEmitter;
-use spade_diagnostics::DiagHandler;
+use spade_diagnostics::{CodeBundle, DiagHandler};
+use spade_mir::graph::GraphFormat;
+use spade_typeinference::unification_chain::TypeErrorVerbosity;
 use tracing_subscriber::filter::{EnvFilter, LevelFilter};
 use tracing_subscriber::prelude::*;
 use tracing_tree::HierarchicalLayer;
 
 use spade::{
+    doc_format,
+    eval::Evaluator,
+    golden, graph_format,
+    library::Library,
     namespaced_file::{dummy_file, namespaced_file, NamespacedFile},
-    wordlength_inference_method, ModuleNamespace,
+    type_error_verbosity, wordlength_inference_method, ModuleNamespace,
 };
 
+/// Compiler for the spade language
+#[derive(Parser)]
+#[command(
+    name = "spade",
+    arg_required_else_help = true,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    compile: Option<Opt>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Evaluate a call to a function in the spade code
+    Eval(EvalOpt),
+    /// Compare the output of the compiler for each .spade file in a directory to the golden
+    /// files next to it
+    Golden(GoldenOpt),
+    /// Format spade code
+    Fmt(FmtOpt),
+}
+
+/// Options for compiling spade code to Verilog, which is done when no subcommand is given.
+/// An input file with the same name as a subcommand can be passed as `./eval`, or after
+/// the other arguments
 #[derive(Deserialize, Parser)]
-#[structopt(name = "spade", about = "Compiler for the spade language")]
 pub struct Opt {
     #[serde(skip, default = "dummy_file")]
     #[arg(name = "INPUT_FILE", value_parser(namespaced_file))]
@@ -33,6 +67,10 @@ pub struct Opt {
     pub mir_output: Option<PathBuf>,
     #[structopt(long)]
     pub verilator_wrapper_output: Option<PathBuf>,
+    /// Directory in which to write a SymbiYosys job for each unit containing clocked
+    /// `assert`, `assume` or `cover` properties
+    #[structopt(long)]
+    pub sby_output: Option<PathBuf>,
 
     /// Do not include color in the error report
     #[structopt(long = "no-color")]
@@ -55,9 +93,58 @@ pub struct Opt {
     #[structopt(long)]
     pub item_list: Option<PathBuf>,
 
+    /// Directory in which to write documentation generated from the `///` comments in
+    /// the code, with one page per module
+    #[structopt(long)]
+    pub doc_output: Option<PathBuf>,
+    /// The format of the documentation written to `--doc-output`. Either "html" (the
+    /// default) or "markdown"
+    #[serde(default, deserialize_with = "deserialize_doc_format")]
+    #[structopt(long, value_parser(doc_format))]
+    pub doc_format: Option<spade_doc::Format>,
+
+    /// Write the instance hierarchy, with the monomorphised name of each instantiated
+    /// unit, to the specified file
+    #[structopt(long)]
+    pub hierarchy_output: Option<PathBuf>,
+    /// The unit at the top of the hierarchy written to `--hierarchy-output`. By default,
+    /// the hierarchies of all units which are not instantiated anywhere are written
+    #[structopt(long)]
+    pub hierarchy_top: Option<String>,
+    /// Directory in which to write the dataflow graph of each unit, with one file per unit
+    #[structopt(long)]
+    pub dataflow_output: Option<PathBuf>,
+    /// The format of the graphs written to `--hierarchy-output` and `--dataflow-output`.
+    /// Either "dot" (the default) or "json"
+    #[serde(default, deserialize_with = "deserialize_graph_format")]
+    #[structopt(long, value_parser(graph_format))]
+    pub graph_format: Option<GraphFormat>,
+
+    /// Start the compilation from a library written by `--library-output`, instead of
+    /// parsing the code in it again. The library must be built by this build of the
+    /// compiler. Can be given several times to link libraries which were each built
+    /// with the ones before them linked
+    #[serde(default)]
+    #[structopt(long)]
+    pub library: Vec<PathBuf>,
+    /// Make the code in the last `--library` available under this namespace, in addition
+    /// to the namespace it was built in
+    #[structopt(long)]
+    pub library_namespace: Option<String>,
+    /// Write a library containing the code in this compilation, including the stdlib
+    /// unless it comes from a linked library, to the specified file
+    #[structopt(long)]
+    pub library_output: Option<PathBuf>,
+
     /// Print a traceback of the type inference process if type inference or hir lowering fails
     #[structopt(long = "print-type-traceback")]
     pub print_type_traceback: bool,
+    /// How much to explain about where the types in a type mismatch come from. "short" (the
+    /// default) points out where the types were inferred, "chain" also points out the last few
+    /// values through which they were propagated and "full" points out all of those values
+    #[serde(default, deserialize_with = "deserialize_type_error_verbosity")]
+    #[structopt(long, value_parser(type_error_verbosity))]
+    pub type_error_verbosity: Option<TypeErrorVerbosity>,
     /// Print a traceback of the parser if parsing fails
     #[structopt(long = "print-parse-traceback")]
     pub print_parse_traceback: bool,
@@ -71,12 +158,259 @@ pub struct Opt {
     #[structopt(long = "optimize")]
     opt_passes: Vec<String>,
 
+    /// Insert coverage signals for match arms, if branches and enum registers. The
+    /// coverage report is generated from a VCD and the `--state-dump` by `spade-coverage`
+    #[serde(default)]
+    #[structopt(long)]
+    coverage: bool,
+
+    /// Fail compilation if any warnings are emitted, reporting them as errors. Compilation
+    /// still runs to completion to report as many warnings as possible
+    #[serde(default)]
+    #[structopt(long)]
+    warnings_as_errors: bool,
+
     /// When command_file is used, use this field to specify a list of strings that will
     /// be decoded to NamespacedFile instead of using `infile` and `extra_files`
     #[structopt(skip)]
     files: Vec<String>,
 }
 
+/// Options for `spade eval`, which evaluates an expression at compile time instead of
+/// generating Verilog
+#[derive(Parser)]
+pub struct EvalOpt {
+    /// The expression to evaluate, for example `lib::crc::crc8([1, 2, 3])`. `lib` refers
+    /// to the namespace of INPUT_FILE. Generic functions must be called with all their
+    /// generic parameters specified
+    #[arg(name = "EXPRESSION")]
+    pub expression: String,
+    #[arg(name = "INPUT_FILE", value_parser(namespaced_file))]
+    pub infile: NamespacedFile,
+    #[arg(name = "EXTRA_FILES", value_parser(namespaced_file))]
+    pub extra_files: Vec<NamespacedFile>,
+
+    /// Do not include color in the error report
+    #[structopt(long = "no-color")]
+    pub no_color: bool,
+}
+
+/// Options for `spade golden`, which checks the output of the compiler against snapshot
+/// files
+#[derive(Parser)]
+pub struct GoldenOpt {
+    #[arg(name = "DIR")]
+    pub dir: PathBuf,
+
+    /// Update the golden files to match the output instead of comparing against them
+    #[structopt(long)]
+    pub bless: bool,
+}
+
+/// Options for `spade fmt`, which formats spade code
+#[derive(Parser)]
+pub struct FmtOpt {
+    /// Files to format in place. If no files are given, code is read from stdin and the
+    /// formatted code is written to stdout
+    #[arg(name = "FILES")]
+    pub files: Vec<PathBuf>,
+    /// Do not write any files, instead fail if any of them is not formatted
+    #[structopt(long)]
+    pub check: bool,
+
+    /// Do not include color in the error report
+    #[structopt(long = "no-color")]
+    pub no_color: bool,
+}
+
+/// Deserializes an optional value from a string in a command file, using the same parser
+/// as the corresponding command line argument
+fn deserialize_parsed<'de, D, T>(
+    deserializer: D,
+    parser: fn(&str) -> std::result::Result<T, String>,
+) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|arg| parser(&arg).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn deserialize_doc_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<spade_doc::Format>, D::Error> {
+    deserialize_parsed(deserializer, doc_format)
+}
+
+fn deserialize_graph_format<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<GraphFormat>, D::Error> {
+    deserialize_parsed(deserializer, graph_format)
+}
+
+fn deserialize_type_error_verbosity<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<TypeErrorVerbosity>, D::Error> {
+    deserialize_parsed(deserializer, type_error_verbosity)
+}
+
+fn read_sources(files: Vec<NamespacedFile>) -> Result<Vec<(ModuleNamespace, String, String)>> {
+    files
+        .into_iter()
+        .map(
+            |NamespacedFile {
+                 file: infile,
+                 namespace,
+                 base_namespace,
+             }| {
+                let mut file = File::open(&infile)
+                    .with_context(|| format!("Failed to open {}", &infile.to_string_lossy()))?;
+                let mut file_content = String::new();
+                file.read_to_string(&mut file_content)?;
+                Ok((
+                    ModuleNamespace {
+                        namespace,
+                        base_namespace,
+                    },
+                    infile.to_string_lossy().to_string(),
+                    file_content,
+                ))
+            },
+        )
+        .collect()
+}
+
+fn error_buffer(no_color: bool) -> Buffer {
+    if no_color || !stderr().is_terminal() {
+        Buffer::no_color()
+    } else {
+        Buffer::ansi() // FIXME: Use `Buffer::console()` on windows?
+    }
+}
+
+fn eval(opts: EvalOpt) -> Result<()> {
+    let base_namespace = opts.infile.base_namespace.clone();
+    let mut infiles = vec![opts.infile];
+    infiles.extend(opts.extra_files);
+    let sources = read_sources(infiles)?;
+
+    let mut buffer = error_buffer(opts.no_color);
+    let spade_opts = spade::Opt::new(&mut buffer);
+
+    let artefacts = spade::compile(
+        sources,
+        true,
+        spade_opts,
+        DiagHandler::new(Box::new(CodespanEmitter)),
+    );
+    let artefacts = match artefacts {
+        Ok(artefacts) => artefacts,
+        Err(_) => {
+            std::io::stderr().write_all(buffer.as_slice())?;
+            bail!("aborting due to previous error")
+        }
+    };
+
+    let mut evaluator = Evaluator::new(
+        artefacts,
+        base_namespace,
+        DiagHandler::new(Box::new(CodespanEmitter)),
+    );
+    let result = evaluator.eval(&opts.expression, &mut buffer);
+    std::io::stderr().write_all(buffer.as_slice())?;
+    println!("{}", result?);
+    Ok(())
+}
+
+fn golden(opts: GoldenOpt) -> Result<()> {
+    let report = golden::run(&opts.dir, opts.bless)?;
+
+    for path in &report.blessed {
+        println!("Updated {}", path.to_string_lossy());
+    }
+    for mismatch in &report.mismatches {
+        println!("{mismatch}");
+    }
+
+    if !report.passed() {
+        bail!(
+            "{} of the golden files for {} inputs do not match, run with --bless to update them",
+            report.mismatches.len(),
+            report.inputs.len()
+        )
+    }
+    println!("Checked {} inputs", report.inputs.len());
+    Ok(())
+}
+
+fn fmt(opts: FmtOpt) -> Result<()> {
+    // Code read from stdin has no path
+    let sources = if opts.files.is_empty() {
+        let mut source = String::new();
+        std::io::stdin()
+            .read_to_string(&mut source)
+            .context("Failed to read stdin")?;
+        vec![(None, source)]
+    } else {
+        opts.files
+            .into_iter()
+            .map(|file| {
+                std::fs::read_to_string(&file)
+                    .with_context(|| format!("Failed to read {}", file.to_string_lossy()))
+                    .map(|source| (Some(file), source))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let mut buffer = error_buffer(opts.no_color);
+    let mut code = CodeBundle::from_files(&[]);
+    let mut diag_handler = DiagHandler::new(Box::new(CodespanEmitter));
+
+    let mut failed = false;
+    let mut unformatted = vec![];
+    for (file, source) in sources {
+        let name = file
+            .as_ref()
+            .map(|file| file.to_string_lossy().to_string())
+            .unwrap_or_else(|| "<stdin>".to_string());
+        let file_id = code.add_file(name.clone(), source.clone());
+        let formatted = match spade_fmt::format(&source, file_id) {
+            Ok(formatted) => formatted,
+            Err(diag) => {
+                diag_handler.emit(&diag, &mut buffer, &code);
+                failed = true;
+                continue;
+            }
+        };
+
+        if opts.check {
+            if formatted != source {
+                unformatted.push(name)
+            }
+        } else if let Some(file) = file {
+            if formatted != source {
+                std::fs::write(&file, formatted)
+                    .with_context(|| format!("Failed to write {name}"))?
+            }
+        } else {
+            print!("{formatted}")
+        }
+    }
+
+    stderr().write_all(buffer.as_slice())?;
+    for name in &unformatted {
+        eprintln!("{name}: not formatted")
+    }
+    if failed {
+        bail!("aborting due to previous error")
+    }
+    if !unformatted.is_empty() {
+        bail!("{} file(s) are not formatted", unformatted.len())
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::OFF.into())
@@ -88,7 +422,28 @@ fn main() -> Result<()> {
 
     tracing_subscriber::registry().with(layer).init();
 
-    let mut opts = Opt::parse();
+    let mut opts = match Cli::parse() {
+        Cli {
+            command: Some(Command::Eval(opts)),
+            ..
+        } => return eval(opts),
+        Cli {
+            command: Some(Command::Golden(opts)),
+            ..
+        } => return golden(opts),
+        Cli {
+            command: Some(Command::Fmt(opts)),
+            ..
+        } => return fmt(opts),
+        Cli {
+            command: None,
+            compile: Some(opts),
+        } => opts,
+        Cli {
+            command: None,
+            compile: None,
+        } => bail!("No input file was given"),
+    };
 
     if let Some(command_file) = opts.command_file {
         let content = std::fs::read_to_string(&command_file)
@@ -114,44 +469,42 @@ fn main() -> Result<()> {
     let mut infiles = vec![opts.infile.clone()];
     infiles.append(&mut opts.extra_files);
 
-    let sources: Result<Vec<(ModuleNamespace, String, String)>> = infiles
-        .into_iter()
-        .map(
-            |NamespacedFile {
-                 file: infile,
-                 namespace,
-                 base_namespace,
-             }| {
-                let mut file = File::open(&infile)
-                    .with_context(|| format!("Failed to open {}", &infile.to_string_lossy()))?;
-                let mut file_content = String::new();
-                file.read_to_string(&mut file_content)?;
-                Ok((
-                    ModuleNamespace {
-                        namespace,
-                        base_namespace,
-                    },
-                    infile.to_string_lossy().to_string(),
-                    file_content,
-                ))
-            },
-        )
-        .collect();
+    let sources = read_sources(infiles);
 
-    let mut buffer = if opts.no_color || !stderr().is_terminal() {
-        Buffer::no_color()
-    } else {
-        Buffer::ansi() // FIXME: Use `Buffer::console()` on windows?
-    };
+    let library = opts
+        .library
+        .iter()
+        .map(|file| {
+            Library::read(file)
+                .with_context(|| format!("Failed to link {}", file.to_string_lossy()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut buffer = error_buffer(opts.no_color);
 
     let spade_opts = spade::Opt {
         error_buffer: &mut buffer,
         outfile: Some(opts.outfile),
         mir_output: opts.mir_output,
         verilator_wrapper_output: opts.verilator_wrapper_output,
+        sby_output: opts.sby_output,
         state_dump_file: opts.state_dump,
         item_list_file: opts.item_list,
+        doc_output: opts
+            .doc_output
+            .map(|dir| (dir, opts.doc_format.unwrap_or(spade_doc::Format::Html))),
+        hierarchy_output: opts
+            .hierarchy_output
+            .map(|file| (file, opts.graph_format.unwrap_or(GraphFormat::Dot))),
+        hierarchy_top: opts.hierarchy_top,
+        dataflow_output: opts
+            .dataflow_output
+            .map(|dir| (dir, opts.graph_format.unwrap_or(GraphFormat::Dot))),
+        library,
+        library_namespace: opts.library_namespace,
+        library_output: opts.library_output,
         print_type_traceback: opts.print_type_traceback,
+        type_error_verbosity: opts.type_error_verbosity.unwrap_or_default(),
         print_parse_traceback: opts.print_parse_traceback,
         wl_infer_method: opts.wl_infer_method.or_else(|| {
             std::env::var("SPADE_INFER_METHOD")
@@ -159,11 +512,17 @@ fn main() -> Result<()> {
                 .and_then(|x| wordlength_inference_method(&x).ok())
         }),
         opt_passes: opts.opt_passes,
+        coverage: opts.coverage,
+        warnings_as_errors: opts.warnings_as_errors,
     };
 
     let diag_handler = DiagHandler::new(Box::new(CodespanEmitter));
     match spade::compile(sources?, true, spade_opts, diag_handler) {
-        Ok(_) => Ok(()),
+        Ok(_) => {
+            // Report any warnings
+            std::io::stderr().write_all(buffer.as_slice())?;
+            Ok(())
+        }
         Err(_) => {
             std::io::stderr().write_all(buffer.as_slice())?;
             Err(anyhow!("aborting due to previous error"))
@@ -147,6 +147,12 @@ impl SimulationExt {
     pub fn field_value(&mut self, field: &FieldRef, output_bits: &BitString) -> Result<String> {
         self.0.field_value(field.0.clone(), &output_bits.0)
     }
+
+    pub fn backward_port_field(&mut self, port: &str) -> Result<Box<FieldRef>> {
+        self.0
+            .backward_port_field(port)
+            .map(|field| Box::new(FieldRef(field)))
+    }
 }
 
 #[cxx::bridge(namespace = "spade")]
@@ -213,5 +219,7 @@ mod ffi {
         fn output_field(&mut self, path: &Vec<String>) -> Result<Box<FieldRef>>;
 
         pub fn field_value(&mut self, field: &FieldRef, output_bits: &BitString) -> Result<String>;
+
+        fn backward_port_field(&mut self, port: &str) -> Result<Box<FieldRef>>;
     }
 }
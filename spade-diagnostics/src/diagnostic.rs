@@ -206,6 +206,12 @@ impl Diagnostic {
         Self::new(DiagnosticLevel::Error, span, message)
     }
 
+    /// Report something that is likely a mistake in the supplied code, but which does not
+    /// prevent compilation.
+    pub fn warning(span: impl Into<FullSpan>, message: impl Into<Message>) -> Self {
+        Self::new(DiagnosticLevel::Warning, span, message)
+    }
+
     pub fn level(mut self, level: DiagnosticLevel) -> Self {
         self.level = level;
         self
@@ -284,9 +284,11 @@ use spade_common::location_info::{AsLabel, Loc};
 
 pub use diagnostic::Diagnostic;
 pub use emitter::Emitter;
+pub use lint::{Lint, Lints};
 
 pub mod diagnostic;
 pub mod emitter;
+pub mod lint;
 
 /// A bundle of all the source code included in the current compilation
 #[derive(Clone)]
@@ -330,6 +332,14 @@ impl CodeBundle {
         all_files
     }
 
+    /// The name of the file with the specified ID, as it was passed to the compiler
+    pub fn file_name(&self, file_id: usize) -> Option<&str> {
+        self.files
+            .get(file_id)
+            .ok()
+            .map(|file| file.name().as_str())
+    }
+
     pub fn source_loc<T>(&self, loc: &Loc<T>) -> String {
         let location = self
             .files
@@ -367,13 +377,18 @@ impl CompilationError for Diagnostic {
 
 pub struct DiagHandler {
     emitter: Box<dyn Emitter + Send>,
+    /// Lints emitted during compilation which are reported once compilation is done
+    pub lints: Lints,
     // Here we can add more shared state for diagnostics. For example, rustc can
     // stash diagnostics that can be retrieved in later stages, indexed by (Span, StashKey).
 }
 
 impl DiagHandler {
     pub fn new(emitter: Box<dyn Emitter + Send>) -> Self {
-        Self { emitter }
+        Self {
+            emitter,
+            lints: Lints::new(),
+        }
     }
 
     pub fn emit(&mut self, diagnostic: &Diagnostic, buffer: &mut Buffer, code: &CodeBundle) {
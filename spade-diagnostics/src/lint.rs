@@ -0,0 +1,304 @@
+//! Lints are warnings about code which compiles fine, but which is likely to
+//! be a mistake, like a variable which is never used.
+//!
+//! Lints are emitted by the compiler stages into [`Lints`] instead of being reported
+//! directly. Once compilation is done, [`Lints::resolve`] applies the levels set by
+//! `#[allow(...)]` and `#[deny(...)]` attributes, turning each lint into a warning, an
+//! error or nothing at all.
+
+use std::collections::HashSet;
+
+use spade_common::location_info::FullSpan;
+
+use crate::diagnostic::DiagnosticLevel;
+use crate::Diagnostic;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Lint {
+    /// A variable which is bound but never read
+    UnusedVariable,
+    /// A register whose value never reaches an output of the unit
+    UnreadRegister,
+    /// A let or reg binding which shadows an earlier variable
+    ShadowedBinding,
+    /// The output of an `inst` which is bound but never read
+    UnusedInstOutput,
+    /// A match arm which can never be taken because earlier arms cover all its values
+    UnreachableMatchArm,
+    /// An integer type which is wider than any value it holds, found by word length inference
+    OversizedWordlength,
+    /// A register which samples a value from another clock domain without a synchronizer
+    ClockDomainCrossing,
+    /// A pipeline marked `#[retime]` which the retiming could not be applied to
+    RetimingNotApplied,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 8] = [
+        Lint::UnusedVariable,
+        Lint::UnreadRegister,
+        Lint::ShadowedBinding,
+        Lint::UnusedInstOutput,
+        Lint::UnreachableMatchArm,
+        Lint::OversizedWordlength,
+        Lint::ClockDomainCrossing,
+        Lint::RetimingNotApplied,
+    ];
+
+    /// The name of the lint as written in `#[allow(...)]` and `#[deny(...)]`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused_variable",
+            Lint::UnreadRegister => "unread_register",
+            Lint::ShadowedBinding => "shadowed_binding",
+            Lint::UnusedInstOutput => "unused_inst_output",
+            Lint::UnreachableMatchArm => "unreachable_match_arm",
+            Lint::OversizedWordlength => "oversized_wordlength",
+            Lint::ClockDomainCrossing => "clock_domain_crossing",
+            Lint::RetimingNotApplied => "retiming_not_applied",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::Deny => "deny",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LintScope {
+    /// The code which the level applies to
+    span: FullSpan,
+    lint: Lint,
+    level: LintLevel,
+    /// The attribute which set the level
+    attribute: FullSpan,
+}
+
+impl LintScope {
+    fn contains(&self, span: &FullSpan) -> bool {
+        self.span.1 == span.1
+            && self.span.0.start() <= span.0.start()
+            && span.0.end() <= self.span.0.end()
+    }
+
+    fn len(&self) -> usize {
+        self.span.0.end().to_usize() - self.span.0.start().to_usize()
+    }
+}
+
+/// Lints emitted during compilation along with the levels set for parts of the code
+#[derive(Debug, Clone, Default)]
+pub struct Lints {
+    scopes: Vec<LintScope>,
+    pending: Vec<(Lint, Diagnostic)>,
+    /// Files in which no lints are reported, like the standard library
+    external_files: HashSet<usize>,
+}
+
+impl Lints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the level of `lint` for all code in `scope`. Levels set on smaller scopes
+    /// take precedence over levels set on the code surrounding them
+    pub fn set_level(
+        &mut self,
+        scope: impl Into<FullSpan>,
+        lint: Lint,
+        level: LintLevel,
+        attribute: impl Into<FullSpan>,
+    ) {
+        self.scopes.push(LintScope {
+            span: scope.into(),
+            lint,
+            level,
+            attribute: attribute.into(),
+        })
+    }
+
+    /// Do not report any lints in the file with the specified ID
+    pub fn mark_external(&mut self, file_id: usize) {
+        self.external_files.insert(file_id);
+    }
+
+    /// Emit a lint. The diagnostic is reported once compilation is done if the lint
+    /// is not allowed where it is emitted. The same lint is only reported once, even if
+    /// it is emitted for several instances of a generic unit
+    pub fn emit(&mut self, lint: Lint, diag: Diagnostic) {
+        if !self.pending.iter().any(|(l, d)| *l == lint && *d == diag) {
+            self.pending.push((lint, diag))
+        }
+    }
+
+    /// Move the levels and lints of `other` into `self`
+    pub fn append(&mut self, mut other: Lints) {
+        self.scopes.append(&mut other.scopes);
+        for (lint, diag) in other.pending {
+            self.emit(lint, diag)
+        }
+        self.external_files.extend(other.external_files);
+    }
+
+    fn scope_of(&self, lint: Lint, span: &FullSpan) -> Option<&LintScope> {
+        self.scopes
+            .iter()
+            .filter(|scope| scope.lint == lint && scope.contains(span))
+            // Later scopes win ties, max_by_key returns the last maximum
+            .max_by_key(|scope| std::cmp::Reverse(scope.len()))
+    }
+
+    /// Take all emitted lints, returning the ones which should be reported in the order
+    /// they appear in the code. Denied lints are returned as errors, the rest as warnings
+    pub fn resolve(&mut self) -> Vec<Diagnostic> {
+        let mut pending = std::mem::take(&mut self.pending);
+        pending.sort_by_key(|(_, diag)| {
+            let (span, file) = diag.labels.span;
+            (file, span.start(), span.end())
+        });
+
+        pending
+            .into_iter()
+            .filter(|(_, diag)| !self.external_files.contains(&diag.labels.span.1))
+            .filter_map(
+                |(lint, diag)| match self.scope_of(lint, &diag.labels.span) {
+                    None => Some(diag.level(DiagnosticLevel::Warning).note(format!(
+                        "This warning can be silenced with `#[allow({})]`",
+                        lint.name()
+                    ))),
+                    Some(scope) => match scope.level {
+                        LintLevel::Allow => None,
+                        LintLevel::Warn => Some(diag.level(DiagnosticLevel::Warning)),
+                        LintLevel::Deny => {
+                            Some(diag.level(DiagnosticLevel::Error).secondary_label(
+                                scope.attribute,
+                                format!("{} is denied here", lint.name()),
+                            ))
+                        }
+                    },
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan::Span;
+
+    use super::*;
+
+    fn span(file: usize, start: u32, end: u32) -> FullSpan {
+        (Span::new(start, end), file)
+    }
+
+    fn levels(lints: &mut Lints) -> Vec<DiagnosticLevel> {
+        lints.resolve().into_iter().map(|d| d.level).collect()
+    }
+
+    #[test]
+    fn lints_without_levels_are_warnings() {
+        let mut lints = Lints::new();
+        lints.emit(
+            Lint::UnusedVariable,
+            Diagnostic::warning(span(0, 5, 6), "x"),
+        );
+
+        assert_eq!(levels(&mut lints), vec![DiagnosticLevel::Warning]);
+    }
+
+    #[test]
+    fn innermost_level_is_used() {
+        let mut lints = Lints::new();
+        lints.set_level(
+            span(0, 0, 100),
+            Lint::UnusedVariable,
+            LintLevel::Deny,
+            span(0, 0, 1),
+        );
+        lints.set_level(
+            span(0, 10, 20),
+            Lint::UnusedVariable,
+            LintLevel::Allow,
+            span(0, 9, 10),
+        );
+        lints.emit(
+            Lint::UnusedVariable,
+            Diagnostic::warning(span(0, 12, 13), "a"),
+        );
+        lints.emit(
+            Lint::UnusedVariable,
+            Diagnostic::warning(span(0, 30, 31), "b"),
+        );
+
+        assert_eq!(levels(&mut lints), vec![DiagnosticLevel::Error]);
+    }
+
+    #[test]
+    fn levels_only_apply_to_their_lint_and_file() {
+        let mut lints = Lints::new();
+        lints.set_level(
+            span(0, 0, 100),
+            Lint::UnusedVariable,
+            LintLevel::Allow,
+            span(0, 0, 1),
+        );
+        lints.emit(
+            Lint::ShadowedBinding,
+            Diagnostic::warning(span(0, 12, 13), "a"),
+        );
+        lints.emit(
+            Lint::UnusedVariable,
+            Diagnostic::warning(span(1, 12, 13), "b"),
+        );
+
+        assert_eq!(
+            levels(&mut lints),
+            vec![DiagnosticLevel::Warning, DiagnosticLevel::Warning]
+        );
+    }
+
+    #[test]
+    fn lints_in_external_files_are_not_reported() {
+        let mut lints = Lints::new();
+        lints.mark_external(1);
+        lints.emit(
+            Lint::UnusedVariable,
+            Diagnostic::warning(span(1, 12, 13), "b"),
+        );
+
+        assert_eq!(levels(&mut lints), vec![]);
+    }
+
+    #[test]
+    fn duplicate_lints_are_reported_once() {
+        let mut lints = Lints::new();
+        lints.emit(
+            Lint::UnusedVariable,
+            Diagnostic::warning(span(0, 12, 13), "b"),
+        );
+        lints.emit(
+            Lint::UnusedVariable,
+            Diagnostic::warning(span(0, 12, 13), "b"),
+        );
+
+        assert_eq!(levels(&mut lints), vec![DiagnosticLevel::Warning]);
+    }
+}
@@ -0,0 +1,258 @@
+//! Signatures of items as they are shown in the documentation. The names of documented
+//! types and traits in a signature link to their documentation.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use spade_common::location_info::Loc;
+use spade_common::name::{NameID, Path};
+use spade_hir::{
+    Parameter, TraitName, TraitSpec, TypeExpression, TypeParam, TypeSpec, UnitHead, UnitKind,
+};
+use spade_types::meta_types::MetaType;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fragment {
+    Text(String),
+    Link {
+        text: String,
+        /// The module whose page the target is documented on
+        module: Vec<String>,
+        anchor: String,
+    },
+}
+
+/// A piece of code made up of text and links
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Code(pub Vec<Fragment>);
+
+impl Code {
+    pub fn text(text: impl Into<String>) -> Self {
+        Code(vec![Fragment::Text(text.into())])
+    }
+
+    pub fn push(&mut self, text: impl Into<String>) -> &mut Self {
+        self.0.push(Fragment::Text(text.into()));
+        self
+    }
+
+    pub fn append(&mut self, mut other: Code) -> &mut Self {
+        self.0.append(&mut other.0);
+        self
+    }
+
+    /// Appends `items` separated by `separator`
+    pub fn join(&mut self, items: impl IntoIterator<Item = Code>, separator: &str) -> &mut Self {
+        for (i, item) in items.into_iter().enumerate() {
+            if i != 0 {
+                self.push(separator);
+            }
+            self.append(item);
+        }
+        self
+    }
+
+    /// The code without links
+    pub fn plain(&self) -> String {
+        self.0
+            .iter()
+            .map(|fragment| match fragment {
+                Fragment::Text(text) | Fragment::Link { text, .. } => text.as_str(),
+            })
+            .collect()
+    }
+}
+
+/// The module an item is in and the name of the item
+pub fn split_path(path: &Path) -> (Vec<String>, String) {
+    let mut strs = path.as_strings();
+    let name = strs.pop().unwrap_or_default();
+    (strs, name)
+}
+
+/// The anchor of an item on the page of its module
+pub fn anchor(kind: &str, name: &str) -> String {
+    format!("{kind}.{name}")
+}
+
+/// Knows where the types and traits are documented, and builds signatures linking to them
+pub struct Linker {
+    targets: HashMap<NameID, (Vec<String>, String)>,
+}
+
+impl Linker {
+    pub fn new(
+        types: impl IntoIterator<Item = NameID>,
+        traits: impl IntoIterator<Item = NameID>,
+    ) -> Self {
+        let types = types.into_iter().map(|name| (name, "type"));
+        let traits = traits.into_iter().map(|name| (name, "trait"));
+        let targets = types
+            .chain(traits)
+            .map(|(name, kind)| {
+                let (module, item) = split_path(&name.1);
+                let target = (module, anchor(kind, &item));
+                (name, target)
+            })
+            .collect();
+        Linker { targets }
+    }
+
+    pub fn name(&self, name: &NameID) -> Code {
+        let (_, text) = split_path(&name.1);
+        match self.targets.get(name) {
+            Some((module, anchor)) => Code(vec![Fragment::Link {
+                text,
+                module: module.clone(),
+                anchor: anchor.clone(),
+            }]),
+            None => Code::text(text),
+        }
+    }
+
+    pub fn type_spec(&self, spec: &TypeSpec) -> Code {
+        let mut result = Code::default();
+        match spec {
+            TypeSpec::Declared(name, params) => {
+                result.append(self.name(name));
+                if !params.is_empty() {
+                    result
+                        .push("<")
+                        .join(params.iter().map(|p| self.type_expr(p)), ", ")
+                        .push(">");
+                }
+            }
+            TypeSpec::Generic(name) => {
+                result.append(self.name(name));
+            }
+            TypeSpec::Tuple(members) => {
+                result
+                    .push("(")
+                    .join(members.iter().map(|m| self.type_spec(m)), ", ")
+                    .push(")");
+            }
+            TypeSpec::Array { inner, size } => {
+                result
+                    .push("[")
+                    .append(self.type_spec(inner))
+                    .push("; ")
+                    .append(self.type_expr(size))
+                    .push("]");
+            }
+            TypeSpec::Unit(_) => {
+                result.push("()");
+            }
+            TypeSpec::Backward(inner) => {
+                result.push("&mut ").append(self.type_spec(inner));
+            }
+            TypeSpec::Inverted(inner) => {
+                result.push("~").append(self.type_spec(inner));
+            }
+            TypeSpec::Wire(inner) => {
+                result.push("&").append(self.type_spec(inner));
+            }
+            TypeSpec::TraitSelf(_) => {
+                result.push("Self");
+            }
+            TypeSpec::Wildcard => {
+                result.push("_");
+            }
+        }
+        result
+    }
+
+    pub fn type_expr(&self, expr: &TypeExpression) -> Code {
+        match expr {
+            TypeExpression::TypeSpec(spec) => self.type_spec(spec),
+            TypeExpression::Integer(_) | TypeExpression::ConstGeneric(_) => {
+                Code::text(expr.to_string())
+            }
+        }
+    }
+
+    pub fn trait_name(&self, name: &TraitName) -> Code {
+        match name {
+            TraitName::Named(name) => self.name(name),
+            TraitName::Anonymous(_) => Code::text(name.to_string()),
+        }
+    }
+
+    pub fn trait_spec(&self, spec: &TraitSpec) -> Code {
+        let mut result = self.trait_name(&spec.name);
+        if let Some(params) = &spec.type_params {
+            result
+                .push("<")
+                .join(params.iter().map(|p| self.type_expr(p)), ", ")
+                .push(">");
+        }
+        result
+    }
+
+    /// `<T: Trait, #uint N>`, or nothing if there are no type parameters
+    pub fn type_params(&self, params: &[Loc<TypeParam>]) -> Code {
+        let mut result = Code::default();
+        if params.is_empty() {
+            return result;
+        }
+        let params = params.iter().map(|param| {
+            let mut code = Code::default();
+            match param.meta {
+                MetaType::Type => {
+                    code.push(param.ident.to_string());
+                    if !param.trait_bounds.is_empty() {
+                        code.push(": ")
+                            .join(param.trait_bounds.iter().map(|t| self.trait_spec(t)), " + ");
+                    }
+                }
+                _ => {
+                    code.push(format!("{} {}", param.meta, param.ident));
+                }
+            }
+            code
+        });
+        result.push("<").join(params, ", ").push(">");
+        result
+    }
+
+    /// A struct field or unit parameter
+    pub fn parameter(&self, param: &Parameter) -> Code {
+        let mut result = Code::default();
+        if param.name.0 == "self" {
+            result.push("self");
+        } else {
+            result
+                .push(format!("{}: ", param.name))
+                .append(self.type_spec(&param.ty));
+        }
+        result
+    }
+
+    /// The signature of a unit, like `fn f<T>(a: T) -> T`
+    pub fn unit_head(&self, name: &str, head: &UnitHead) -> Code {
+        let mut result = match &head.unit_kind.inner {
+            UnitKind::Function(_) => Code::text("fn "),
+            UnitKind::Entity => Code::text("entity "),
+            UnitKind::Pipeline { depth, .. } => {
+                let mut code = Code::text("pipeline(");
+                code.append(self.type_expr(depth)).push(") ");
+                code
+            }
+        };
+        result
+            .push(name)
+            .append(self.type_params(&head.unit_type_params))
+            .push("(")
+            .join(head.inputs.0.iter().map(|p| self.parameter(p)), ", ")
+            .push(")");
+        if let Some(output) = &head.output_type {
+            result.push(" -> ").append(self.type_spec(output));
+        }
+        if !head.where_clauses.is_empty() {
+            result.push(format!(
+                " where {}",
+                head.where_clauses.iter().map(|c| c.to_string()).join(", ")
+            ));
+        }
+        result
+    }
+}
@@ -0,0 +1,260 @@
+//! Static HTML pages which can be browsed without a server
+
+use std::fmt::Write;
+
+use crate::code::{Code, Fragment};
+use crate::{module_name, page_name, Format, Impl, Item, Member, Page};
+
+const STYLE: &str = "
+body { font-family: sans-serif; max-width: 60em; margin: auto; padding: 1em; }
+pre { background: #f4f4f4; padding: 0.5em; overflow-x: auto; }
+code { background: #f4f4f4; }
+section { margin-bottom: 2em; }
+.member { margin-left: 2em; }
+";
+
+pub fn escape(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.chars() {
+        match c {
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '&' => result.push_str("&amp;"),
+            '"' => result.push_str("&quot;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// The code with links to the pages in `format`. Markdown pages use the same HTML
+/// since markdown does not support links inside code
+pub fn code(code: &Code, format: Format) -> String {
+    code.0
+        .iter()
+        .map(|fragment| match fragment {
+            Fragment::Text(text) => escape(text),
+            Fragment::Link {
+                text,
+                module,
+                anchor,
+            } => format!(
+                "<a href=\"{}\">{}</a>",
+                escape(&format!("{}#{anchor}", page_name(module, format))),
+                escape(text)
+            ),
+        })
+        .collect()
+}
+
+/// Text with `inline code` in it
+fn inline(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                format!("<code>{}</code>", escape(part))
+            } else {
+                escape(part)
+            }
+        })
+        .collect()
+}
+
+/// Documentation split into paragraphs and ``` code blocks
+pub fn doc(text: &str) -> String {
+    let mut result = String::new();
+    let mut paragraph = vec![];
+    let mut code_block: Option<Vec<&str>> = None;
+
+    let flush = |paragraph: &mut Vec<&str>, result: &mut String| {
+        if !paragraph.is_empty() {
+            writeln!(result, "<p>{}</p>", inline(&paragraph.join("\n"))).unwrap();
+            paragraph.clear();
+        }
+    };
+    for line in text.lines() {
+        match &mut code_block {
+            Some(lines) if line.trim_start().starts_with("```") => {
+                writeln!(
+                    result,
+                    "<pre><code>{}</code></pre>",
+                    escape(&lines.join("\n"))
+                )
+                .unwrap();
+                code_block = None;
+            }
+            Some(lines) => lines.push(line),
+            None if line.trim_start().starts_with("```") => {
+                flush(&mut paragraph, &mut result);
+                code_block = Some(vec![]);
+            }
+            None if line.trim().is_empty() => flush(&mut paragraph, &mut result),
+            None => paragraph.push(line),
+        }
+    }
+    if let Some(lines) = code_block {
+        writeln!(
+            result,
+            "<pre><code>{}</code></pre>",
+            escape(&lines.join("\n"))
+        )
+        .unwrap();
+    }
+    flush(&mut paragraph, &mut result);
+    result
+}
+
+fn members(out: &mut String, members: &[Member]) {
+    for member in members {
+        writeln!(
+            out,
+            "<div class=\"member\"><pre><code>{}</code></pre>",
+            code(&member.signature, Format::Html)
+        )
+        .unwrap();
+        if let Some(text) = &member.doc {
+            out.push_str(&doc(text));
+        }
+        out.push_str("</div>\n");
+    }
+}
+
+fn impls(out: &mut String, impls: &[Impl]) {
+    for block in impls {
+        writeln!(
+            out,
+            "<pre><code>{}</code></pre>",
+            code(&block.signature, Format::Html)
+        )
+        .unwrap();
+        members(out, &block.methods);
+    }
+}
+
+fn items(out: &mut String, title: &str, items: &[Item]) {
+    if items.is_empty() {
+        return;
+    }
+    writeln!(out, "<h2>{title}</h2>").unwrap();
+    for item in items {
+        writeln!(out, "<section id=\"{}\">", escape(&item.anchor)).unwrap();
+        writeln!(out, "<h3>{}</h3>", escape(&item.name)).unwrap();
+        writeln!(
+            out,
+            "<pre><code>{}</code></pre>",
+            code(&item.signature, Format::Html)
+        )
+        .unwrap();
+        if let Some(text) = &item.doc {
+            out.push_str(&doc(text));
+        }
+        members(out, &item.members);
+        if !item.impls.is_empty() {
+            out.push_str("<h4>Impls</h4>\n");
+            impls(out, &item.impls);
+        }
+        out.push_str("</section>\n");
+    }
+}
+
+pub fn page(page: &Page) -> String {
+    let title = module_name(&page.module);
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>\n<html>\n<head>").unwrap();
+    writeln!(out, "<meta charset=\"utf-8\">").unwrap();
+    writeln!(out, "<title>{}</title>", escape(&title)).unwrap();
+    writeln!(out, "<style>{STYLE}</style>").unwrap();
+    writeln!(out, "</head>\n<body>").unwrap();
+
+    // Links to the parent modules
+    let parents = (0..page.module.len())
+        .map(|i| {
+            let parent = &page.module[..i];
+            format!(
+                "<a href=\"{}\">{}</a>",
+                escape(&page_name(parent, Format::Html)),
+                escape(&module_name(parent))
+            )
+        })
+        .collect::<Vec<_>>();
+    if !parents.is_empty() {
+        writeln!(out, "<nav>{}</nav>", parents.join(" / ")).unwrap();
+    }
+
+    writeln!(out, "<h1>{}</h1>", escape(&title)).unwrap();
+    if let Some(text) = &page.doc {
+        out.push_str(&doc(text));
+    }
+
+    if !page.modules.is_empty() {
+        out.push_str("<h2>Modules</h2>\n<ul>\n");
+        for (module, module_doc) in &page.modules {
+            let summary = module_doc
+                .as_ref()
+                .and_then(|d| d.lines().next())
+                .map(|line| format!(" - {}", inline(line)))
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "<li><a href=\"{}\">{}</a>{summary}</li>",
+                escape(&page_name(module, Format::Html)),
+                escape(module.last().map(String::as_str).unwrap_or_default())
+            )
+            .unwrap();
+        }
+        out.push_str("</ul>\n");
+    }
+
+    items(&mut out, "Units", &page.units);
+    items(&mut out, "Types", &page.types);
+    items(&mut out, "Traits", &page.traits);
+    if !page.impls.is_empty() {
+        out.push_str("<h2>Impls</h2>\n");
+        impls(&mut out, &page.impls);
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn html_is_escaped() {
+        assert_eq!(escape("a<b> & \"c\""), "a&lt;b&gt; &amp; &quot;c&quot;")
+    }
+
+    #[test]
+    fn docs_are_split_into_paragraphs() {
+        assert_eq!(
+            doc("First\nline\n\nSecond `a<b`\n```\nlet x = 1;\n```"),
+            "<p>First\nline</p>\n<p>Second <code>a&lt;b</code></p>\n\
+             <pre><code>let x = 1;</code></pre>\n"
+        )
+    }
+
+    #[test]
+    fn links_point_to_the_page_of_the_module() {
+        let signature = Code(vec![
+            Fragment::Text("a: ".to_string()),
+            Fragment::Link {
+                text: "T".to_string(),
+                module: vec!["m".to_string(), "n".to_string()],
+                anchor: "type.T".to_string(),
+            },
+        ]);
+        assert_eq!(
+            code(&signature, Format::Html),
+            "a: <a href=\"m.n.html#type.T\">T</a>"
+        );
+        assert_eq!(
+            code(&signature, Format::Markdown),
+            "a: <a href=\"m.n.md#type.T\">T</a>"
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! Generates documentation from the `///` comments in Spade code. The items of an
+//! [`ItemList`] are grouped into one page per module, listing the units, types, traits and
+//! impls in the module along with their signatures and documentation.
+
+mod code;
+mod html;
+mod markdown;
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use spade_common::name::{Identifier, NameID};
+use spade_hir::{ExecutableItem, ItemList, TraitName, TypeDeclKind, TypeSpec, UnitHead};
+
+use crate::code::{anchor, split_path, Code, Linker};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Html,
+    Markdown,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+/// A unit, type or trait
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    name: String,
+    anchor: String,
+    signature: Code,
+    doc: Option<String>,
+    /// Struct fields, enum variants or trait methods
+    members: Vec<Member>,
+    /// The impls of a type, or the impls of a trait for other types
+    impls: Vec<Impl>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Member {
+    signature: Code,
+    doc: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Impl {
+    signature: Code,
+    methods: Vec<Member>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Page {
+    module: Vec<String>,
+    doc: Option<String>,
+    /// The direct submodules of the module along with their documentation
+    modules: Vec<(Vec<String>, Option<String>)>,
+    units: Vec<Item>,
+    types: Vec<Item>,
+    traits: Vec<Item>,
+    /// Impls for types which are not documented, like the builtin types
+    impls: Vec<Impl>,
+}
+
+/// The file name of the page documenting `module`
+fn page_name(module: &[String], format: Format) -> String {
+    if module.is_empty() {
+        format!("index.{}", format.extension())
+    } else {
+        format!("{}.{}", module.join("."), format.extension())
+    }
+}
+
+/// The name of a module as it is written in code
+fn module_name(module: &[String]) -> String {
+    if module.is_empty() {
+        "crate root".to_string()
+    } else {
+        module.join("::")
+    }
+}
+
+/// Generates the documentation for everything in `item_list`. Returns the name and
+/// content of each file
+pub fn generate(item_list: &ItemList, format: Format) -> Vec<(PathBuf, String)> {
+    pages(item_list)
+        .into_iter()
+        .map(|page| {
+            let content = match format {
+                Format::Html => html::page(&page),
+                Format::Markdown => markdown::page(&page),
+            };
+            (PathBuf::from(page_name(&page.module, format)), content)
+        })
+        .collect()
+}
+
+/// The page of `module`, creating it and the pages of its parents if they do not exist
+fn page(pages: &mut BTreeMap<Vec<String>, Page>, module: Vec<String>) -> &mut Page {
+    for i in 0..module.len() {
+        pages.entry(module[..i].to_vec()).or_default().module = module[..i].to_vec();
+    }
+    let page = pages.entry(module.clone()).or_default();
+    page.module = module;
+    page
+}
+
+fn pages(item_list: &ItemList) -> Vec<Page> {
+    let named_traits = item_list.traits.keys().filter_map(|name| match name {
+        TraitName::Named(name) => Some(name.inner.clone()),
+        TraitName::Anonymous(_) => None,
+    });
+    // Primitive types like `int` have nothing to document
+    let types = item_list
+        .types
+        .iter()
+        .filter(|(_, decl)| !matches!(decl.kind, TypeDeclKind::Primitive(_)))
+        .collect::<Vec<_>>();
+    let linker = Linker::new(types.iter().map(|(name, _)| (*name).clone()), named_traits);
+    let doc = |name: &NameID| item_list.docs.get(name).cloned();
+    let member_doc = |parent: &NameID, member: &Identifier| {
+        item_list
+            .member_docs
+            .get(&(parent.clone(), member.clone()))
+            .cloned()
+    };
+
+    let mut pages = BTreeMap::<Vec<String>, Page>::new();
+    page(&mut pages, vec![]);
+    for name in item_list.modules.keys() {
+        page(&mut pages, name.1.as_strings()).doc = doc(name);
+    }
+
+    let impl_methods = item_list
+        .impls
+        .values()
+        .flat_map(|impls| impls.values())
+        .flat_map(|block| block.fns.values().map(|(name, _)| name.clone()))
+        .collect::<HashSet<_>>();
+    let unit_head = |name: &NameID| -> Option<&UnitHead> {
+        match item_list.executables.get(name)? {
+            ExecutableItem::Unit(unit) => Some(&unit.head),
+            ExecutableItem::BuiltinUnit(_, head, _) => Some(head),
+            ExecutableItem::EnumInstance { .. } | ExecutableItem::StructInstance => None,
+        }
+    };
+
+    for name in item_list.executables.keys() {
+        if impl_methods.contains(name) {
+            continue;
+        }
+        if let Some(head) = unit_head(name) {
+            let (module, unit_name) = split_path(&name.1);
+            page(&mut pages, module).units.push(Item {
+                anchor: anchor("unit", &unit_name),
+                signature: linker.unit_head(&unit_name, head),
+                name: unit_name,
+                doc: doc(name),
+                members: vec![],
+                impls: vec![],
+            })
+        }
+    }
+
+    // Impls, by the type they are for
+    let mut impls = BTreeMap::<Vec<String>, Vec<(Option<NameID>, Impl)>>::new();
+    let mut trait_impls = Vec::<(NameID, Impl)>::new();
+    for blocks in item_list.impls.values() {
+        for ((trait_name, trait_params), block) in blocks {
+            let mut signature = Code::text("impl");
+            signature.append(linker.type_params(&block.type_params));
+            signature.push(" ");
+            if let TraitName::Named(name) = trait_name {
+                signature.append(linker.name(name));
+                if !trait_params.is_empty() {
+                    signature
+                        .push("<")
+                        .join(trait_params.iter().map(|p| linker.type_expr(p)), ", ")
+                        .push(">");
+                }
+                signature.push(" for ");
+            }
+            signature.append(linker.type_spec(&block.target));
+
+            let methods = block
+                .fns
+                .iter()
+                .sorted_by_key(|(method, _)| &method.0)
+                .filter_map(|(method, (name, _))| {
+                    Some(Member {
+                        signature: linker.unit_head(&method.0, unit_head(name)?),
+                        doc: doc(name),
+                    })
+                })
+                .collect();
+            let block_impl = Impl { signature, methods };
+
+            if let TraitName::Named(name) = trait_name {
+                trait_impls.push((
+                    name.inner.clone(),
+                    Impl {
+                        signature: block_impl.signature.clone(),
+                        methods: vec![],
+                    },
+                ));
+            }
+            // Impl methods are named `module::impl_n::method`, which tells us where the
+            // impl is if it is not for a documented type
+            let module = block
+                .fns
+                .values()
+                .next()
+                .map(|(name, _)| {
+                    let mut path = name.1.as_strings();
+                    path.truncate(path.len().saturating_sub(2));
+                    path
+                })
+                .unwrap_or_default();
+            let target = match &block.target.inner {
+                TypeSpec::Declared(name, _)
+                    if types.iter().any(|(type_name, _)| *type_name == &name.inner) =>
+                {
+                    Some(name.inner.clone())
+                }
+                _ => None,
+            };
+            impls.entry(module).or_default().push((target, block_impl));
+        }
+    }
+    let mut type_impls = BTreeMap::<NameID, Vec<Impl>>::new();
+    for (module, impls) in impls {
+        for (target, block) in impls {
+            match target {
+                Some(target) => type_impls.entry(target).or_default().push(block),
+                None => page(&mut pages, module.clone()).impls.push(block),
+            }
+        }
+    }
+
+    for (name, decl) in types {
+        let (module, type_name) = split_path(&name.1);
+        let generics = linker.type_params(&decl.generic_args);
+        let (signature, members) = match &decl.kind {
+            TypeDeclKind::Struct(s) => {
+                let mut signature = Code::text(if s.is_port { "struct port " } else { "struct " });
+                signature.push(type_name.clone()).append(generics);
+                let members = s
+                    .members
+                    .0
+                    .iter()
+                    .map(|field| Member {
+                        signature: linker.parameter(field),
+                        doc: member_doc(name, &field.name),
+                    })
+                    .collect();
+                (signature, members)
+            }
+            TypeDeclKind::Enum(e) => {
+                let mut signature = Code::text("enum ");
+                signature.push(type_name.clone()).append(generics);
+                let members = e
+                    .options
+                    .iter()
+                    .map(|(variant, params)| {
+                        let (_, variant_name) = split_path(&variant.1);
+                        let mut signature = Code::text(variant_name);
+                        if !params.0.is_empty() {
+                            signature
+                                .push("{")
+                                .join(params.0.iter().map(|p| linker.parameter(p)), ", ")
+                                .push("}");
+                        }
+                        Member {
+                            signature,
+                            doc: doc(variant),
+                        }
+                    })
+                    .collect();
+                (signature, members)
+            }
+            TypeDeclKind::Primitive(_) => continue,
+        };
+        let mut impls = type_impls.remove(name).unwrap_or_default();
+        impls.sort_by_key(|i| i.signature.plain());
+        page(&mut pages, module).types.push(Item {
+            anchor: anchor("type", &type_name),
+            name: type_name,
+            signature,
+            doc: doc(name),
+            members,
+            impls,
+        })
+    }
+
+    for (trait_name, def) in &item_list.traits {
+        let TraitName::Named(name) = trait_name else {
+            continue;
+        };
+        let (module, short_name) = split_path(&name.1);
+        let mut signature = Code::text("trait ");
+        signature.push(short_name.clone());
+        if let Some(params) = &def.type_params {
+            signature.append(linker.type_params(params));
+        }
+        let members = def
+            .fns
+            .iter()
+            .sorted_by_key(|(method, _)| &method.0)
+            .map(|(method, head)| Member {
+                signature: linker.unit_head(&method.0, head),
+                doc: member_doc(name, method),
+            })
+            .collect();
+        let mut impls = trait_impls
+            .iter()
+            .filter(|(t, _)| t == &name.inner)
+            .map(|(_, i)| i.clone())
+            .collect::<Vec<_>>();
+        impls.sort_by_key(|i| i.signature.plain());
+        page(&mut pages, module).traits.push(Item {
+            anchor: anchor("trait", &short_name),
+            name: short_name,
+            signature,
+            doc: doc(name),
+            members,
+            impls,
+        })
+    }
+
+    let modules = pages.keys().cloned().collect::<BTreeSet<_>>();
+    let mut pages = pages.into_values().collect::<Vec<_>>();
+    for page in &mut pages {
+        page.modules = modules
+            .iter()
+            .filter(|m| m.len() == page.module.len() + 1 && m.starts_with(&page.module))
+            .map(|m| {
+                let doc = item_list
+                    .modules
+                    .keys()
+                    .find(|name| &name.1.as_strings() == m)
+                    .and_then(&doc);
+                (m.clone(), doc)
+            })
+            .collect();
+        page.units.sort_by(|a, b| a.name.cmp(&b.name));
+        page.types.sort_by(|a, b| a.name.cmp(&b.name));
+        page.traits.sort_by(|a, b| a.name.cmp(&b.name));
+        page.impls.sort_by_key(|i| i.signature.plain());
+    }
+    pages
+}
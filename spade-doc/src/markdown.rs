@@ -0,0 +1,132 @@
+//! Markdown pages. Documentation is copied as is since it is usually written in markdown
+
+use std::fmt::Write;
+
+use crate::code::Code;
+use crate::html::{code, escape};
+use crate::{module_name, page_name, Format, Impl, Item, Member, Page};
+
+fn signature(out: &mut String, signature: &Code) {
+    writeln!(
+        out,
+        "<pre><code>{}</code></pre>\n",
+        code(signature, Format::Markdown)
+    )
+    .unwrap();
+}
+
+fn doc(out: &mut String, doc: &Option<String>) {
+    if let Some(text) = doc {
+        writeln!(out, "{text}\n").unwrap();
+    }
+}
+
+fn members(out: &mut String, members: &[Member]) {
+    for member in members {
+        write!(
+            out,
+            "- <code>{}</code>",
+            code(&member.signature, Format::Markdown)
+        )
+        .unwrap();
+        match &member.doc {
+            Some(text) => {
+                // Indent the documentation to keep it in the list item
+                let text = text
+                    .lines()
+                    .map(|line| {
+                        if line.is_empty() {
+                            String::new()
+                        } else {
+                            format!("  {line}")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                writeln!(out, "\n\n{text}\n").unwrap();
+            }
+            None => out.push('\n'),
+        }
+    }
+    if !members.is_empty() {
+        out.push('\n');
+    }
+}
+
+fn impls(out: &mut String, impls: &[Impl]) {
+    for block in impls {
+        signature(out, &block.signature);
+        members(out, &block.methods);
+    }
+}
+
+fn items(out: &mut String, title: &str, items: &[Item]) {
+    if items.is_empty() {
+        return;
+    }
+    writeln!(out, "## {title}\n").unwrap();
+    for item in items {
+        writeln!(out, "<a id=\"{}\"></a>\n", escape(&item.anchor)).unwrap();
+        writeln!(out, "### {}\n", item.name).unwrap();
+        signature(out, &item.signature);
+        doc(out, &item.doc);
+        members(out, &item.members);
+        if !item.impls.is_empty() {
+            writeln!(out, "#### Impls\n").unwrap();
+            impls(out, &item.impls);
+        }
+    }
+}
+
+pub fn page(page: &Page) -> String {
+    let mut out = String::new();
+
+    let parents = (0..page.module.len())
+        .map(|i| {
+            let parent = &page.module[..i];
+            format!(
+                "[{}]({})",
+                module_name(parent),
+                page_name(parent, Format::Markdown)
+            )
+        })
+        .collect::<Vec<_>>();
+    if !parents.is_empty() {
+        writeln!(out, "{}\n", parents.join(" / ")).unwrap();
+    }
+
+    writeln!(out, "# {}\n", module_name(&page.module)).unwrap();
+    doc(&mut out, &page.doc);
+
+    if !page.modules.is_empty() {
+        writeln!(out, "## Modules\n").unwrap();
+        for (module, module_doc) in &page.modules {
+            let summary = module_doc
+                .as_ref()
+                .and_then(|d| d.lines().next())
+                .map(|line| format!(" - {line}"))
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "- [{}]({}){summary}",
+                module.last().map(String::as_str).unwrap_or_default(),
+                page_name(module, Format::Markdown)
+            )
+            .unwrap();
+        }
+        out.push('\n');
+    }
+
+    items(&mut out, "Units", &page.units);
+    items(&mut out, "Types", &page.types);
+    items(&mut out, "Traits", &page.traits);
+    if !page.impls.is_empty() {
+        writeln!(out, "## Impls\n").unwrap();
+        impls(&mut out, &page.impls);
+    }
+
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    out.push('\n');
+    out
+}
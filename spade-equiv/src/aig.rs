@@ -0,0 +1,437 @@
+//! An and-inverter graph along with the bit vector operations used to describe the MIR
+//! operators in terms of it. Structurally identical gates are shared and gates with
+//! constant inputs are folded away, which means that logic which is written the same way in
+//! both designs usually ends up as the same node and never reaches the SAT solver.
+
+use std::collections::HashMap;
+
+use num::{BigInt, BigUint, Integer, One, Zero};
+
+/// A node in the graph, possibly negated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub const FALSE: Lit = Lit(0);
+    pub const TRUE: Lit = Lit(1);
+
+    fn new(node: usize, negated: bool) -> Self {
+        Lit(((node as u32) << 1) | negated as u32)
+    }
+
+    pub fn node(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    pub fn constant(value: bool) -> Self {
+        if value {
+            Lit::TRUE
+        } else {
+            Lit::FALSE
+        }
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+/// A bit vector with the least significant bit first
+pub type Bits = Vec<Lit>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Node {
+    False,
+    Input,
+    And(Lit, Lit),
+}
+
+pub struct Aig {
+    /// The nodes of the graph. The operands of a gate always come before the gate
+    nodes: Vec<Node>,
+    gates: HashMap<(Lit, Lit), Lit>,
+}
+
+impl Default for Aig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Aig {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::False],
+            gates: HashMap::new(),
+        }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn input(&mut self) -> Lit {
+        self.nodes.push(Node::Input);
+        Lit::new(self.nodes.len() - 1, false)
+    }
+
+    pub fn inputs(&mut self, width: usize) -> Bits {
+        (0..width).map(|_| self.input()).collect()
+    }
+
+    pub fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        if a == Lit::FALSE || b == Lit::FALSE || a == !b {
+            return Lit::FALSE;
+        }
+        if a == Lit::TRUE || a == b {
+            return b;
+        }
+        if b == Lit::TRUE {
+            return a;
+        }
+        let key = (a.min(b), a.max(b));
+        if let Some(lit) = self.gates.get(&key) {
+            return *lit;
+        }
+        self.nodes.push(Node::And(key.0, key.1));
+        let lit = Lit::new(self.nodes.len() - 1, false);
+        self.gates.insert(key, lit);
+        lit
+    }
+
+    pub fn or(&mut self, a: Lit, b: Lit) -> Lit {
+        !self.and(!a, !b)
+    }
+
+    pub fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let left = self.and(a, !b);
+        let right = self.and(!a, b);
+        self.or(left, right)
+    }
+
+    /// `on_true` if `cond` holds, otherwise `on_false`
+    pub fn mux(&mut self, cond: Lit, on_true: Lit, on_false: Lit) -> Lit {
+        if on_true == on_false {
+            return on_true;
+        }
+        let t = self.and(cond, on_true);
+        let f = self.and(!cond, on_false);
+        self.or(t, f)
+    }
+
+    pub fn and_all(&mut self, lits: impl IntoIterator<Item = Lit>) -> Lit {
+        lits.into_iter().fold(Lit::TRUE, |acc, l| self.and(acc, l))
+    }
+
+    pub fn or_all(&mut self, lits: impl IntoIterator<Item = Lit>) -> Lit {
+        lits.into_iter().fold(Lit::FALSE, |acc, l| self.or(acc, l))
+    }
+
+    pub fn xor_all(&mut self, lits: impl IntoIterator<Item = Lit>) -> Lit {
+        lits.into_iter().fold(Lit::FALSE, |acc, l| self.xor(acc, l))
+    }
+
+    /// The value of every node given the values of the inputs
+    pub fn simulate(&self, input: impl Fn(usize) -> bool) -> Vec<bool> {
+        let mut values = Vec::with_capacity(self.nodes.len());
+        for (i, node) in self.nodes.iter().enumerate() {
+            let value = match node {
+                Node::False => false,
+                Node::Input => input(i),
+                Node::And(a, b) => {
+                    (values[a.node()] ^ a.is_negated()) && (values[b.node()] ^ b.is_negated())
+                }
+            };
+            values.push(value)
+        }
+        values
+    }
+}
+
+/// The value of `lit` in the result of [`Aig::simulate`]
+pub fn lit_value(values: &[bool], lit: Lit) -> bool {
+    values[lit.node()] ^ lit.is_negated()
+}
+
+/// The unsigned value of `bits` in the result of [`Aig::simulate`]
+pub fn bits_value(values: &[bool], bits: &[Lit]) -> BigUint {
+    bits.iter().rev().fold(BigUint::zero(), |acc, bit| {
+        (acc << 1u32) + lit_value(values, *bit) as u32
+    })
+}
+
+/// The two's complement representation of `value` in `width` bits
+pub fn constant(value: &BigInt, width: usize) -> Bits {
+    let modulus = BigInt::one() << width;
+    let value = value.mod_floor(&modulus);
+    (0..width)
+        .map(|i| Lit::constant(value.bit(i as u64)))
+        .collect()
+}
+
+/// Truncates or extends `bits` to `width`, using sign extension if `signed` is set
+pub fn resize(bits: &[Lit], width: usize, signed: bool) -> Bits {
+    let fill = match bits.last() {
+        Some(msb) if signed => *msb,
+        _ => Lit::FALSE,
+    };
+    (0..width)
+        .map(|i| bits.get(i).copied().unwrap_or(fill))
+        .collect()
+}
+
+/// Builds a vector from parts given most significant part first, like a Verilog
+/// concatenation
+pub fn concat<'a>(parts: impl IntoIterator<Item = &'a [Lit]>) -> Bits {
+    let parts = parts.into_iter().collect::<Vec<_>>();
+    parts.into_iter().rev().flatten().copied().collect()
+}
+
+pub fn not(bits: &[Lit]) -> Bits {
+    bits.iter().map(|b| !*b).collect()
+}
+
+/// Applies `op` to each pair of bits in two vectors of the same width
+pub fn bitwise(aig: &mut Aig, a: &[Lit], b: &[Lit], op: fn(&mut Aig, Lit, Lit) -> Lit) -> Bits {
+    a.iter().zip(b).map(|(a, b)| op(aig, *a, *b)).collect()
+}
+
+pub fn mux(aig: &mut Aig, cond: Lit, on_true: &[Lit], on_false: &[Lit]) -> Bits {
+    on_true
+        .iter()
+        .zip(on_false)
+        .map(|(t, f)| aig.mux(cond, *t, *f))
+        .collect()
+}
+
+/// The sum of two vectors of the same width along with the carry out
+fn add_with_carry(aig: &mut Aig, a: &[Lit], b: &[Lit], carry_in: Lit) -> (Bits, Lit) {
+    let mut carry = carry_in;
+    let mut sum = Vec::with_capacity(a.len());
+    for (a, b) in a.iter().zip(b) {
+        let half = aig.xor(*a, *b);
+        sum.push(aig.xor(half, carry));
+        let both = aig.and(*a, *b);
+        let propagated = aig.and(half, carry);
+        carry = aig.or(both, propagated);
+    }
+    (sum, carry)
+}
+
+pub fn add(aig: &mut Aig, a: &[Lit], b: &[Lit]) -> Bits {
+    add_with_carry(aig, a, b, Lit::FALSE).0
+}
+
+pub fn sub(aig: &mut Aig, a: &[Lit], b: &[Lit]) -> Bits {
+    add_with_carry(aig, a, &not(b), Lit::TRUE).0
+}
+
+pub fn negate(aig: &mut Aig, a: &[Lit]) -> Bits {
+    let zero = vec![Lit::FALSE; a.len()];
+    sub(aig, &zero, a)
+}
+
+/// The product of two vectors of the same width, truncated to that width
+pub fn mul(aig: &mut Aig, a: &[Lit], b: &[Lit]) -> Bits {
+    let width = a.len();
+    let mut result = vec![Lit::FALSE; width];
+    for (shift, b) in b.iter().enumerate() {
+        let partial = (0..width)
+            .map(|i| {
+                if i < shift {
+                    Lit::FALSE
+                } else {
+                    aig.and(a[i - shift], *b)
+                }
+            })
+            .collect::<Vec<_>>();
+        result = add(aig, &result, &partial);
+    }
+    result
+}
+
+pub fn eq(aig: &mut Aig, a: &[Lit], b: &[Lit]) -> Lit {
+    let differences = bitwise(aig, a, b, Aig::xor);
+    !aig.or_all(differences)
+}
+
+/// `a < b` for unsigned vectors of the same width
+pub fn unsigned_lt(aig: &mut Aig, a: &[Lit], b: &[Lit]) -> Lit {
+    // a - b borrows exactly when a < b
+    !add_with_carry(aig, a, &not(b), Lit::TRUE).1
+}
+
+/// `a < b` for signed vectors of the same width
+pub fn signed_lt(aig: &mut Aig, a: &[Lit], b: &[Lit]) -> Lit {
+    // Flipping the sign bits maps the signed order onto the unsigned one
+    let flip = |bits: &[Lit]| {
+        let mut bits = bits.to_vec();
+        if let Some(msb) = bits.last_mut() {
+            *msb = !*msb
+        }
+        bits
+    };
+    unsigned_lt(aig, &flip(a), &flip(b))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shift {
+    Left,
+    Right,
+}
+
+/// Shifts `bits` by the unsigned amount `amount`, shifting in `fill`. Shifting by
+/// the width of `bits` or more gives a vector of only `fill`
+pub fn shift(aig: &mut Aig, bits: &[Lit], amount: &[Lit], direction: Shift, fill: Lit) -> Bits {
+    let width = bits.len();
+    let mut result = bits.to_vec();
+    let mut overflow = Lit::FALSE;
+    for (k, amount_bit) in amount.iter().enumerate() {
+        let distance = 1usize.checked_shl(k as u32).filter(|d| *d < width);
+        match distance {
+            Some(distance) => {
+                let shifted = (0..width)
+                    .map(|i| match direction {
+                        Shift::Left => i.checked_sub(distance).map(|j| result[j]),
+                        Shift::Right => result.get(i + distance).copied(),
+                    })
+                    .map(|bit| bit.unwrap_or(fill))
+                    .collect::<Vec<_>>();
+                result = mux(aig, *amount_bit, &shifted, &result);
+            }
+            None => overflow = aig.or(overflow, *amount_bit),
+        }
+    }
+    let filled = vec![fill; width];
+    mux(aig, overflow, &filled, &result)
+}
+
+/// Whether the unsigned value of `bits` is `value`. False if `value` does not fit
+pub fn is_value(aig: &mut Aig, bits: &[Lit], value: usize) -> Lit {
+    if bits.len() < usize::BITS as usize && value >> bits.len() != 0 {
+        return Lit::FALSE;
+    }
+    let expected = constant(&value.into(), bits.len());
+    eq(aig, bits, &expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluates `f` on every pair of `width` bit inputs and compares the result to
+    /// `expected`, which is given the unsigned values of the inputs
+    fn check_binary(
+        width: usize,
+        f: impl Fn(&mut Aig, &[Lit], &[Lit]) -> Bits,
+        expected: impl Fn(u64, u64) -> u64,
+    ) {
+        let mut aig = Aig::new();
+        let a = aig.inputs(width);
+        let b = aig.inputs(width);
+        let result = f(&mut aig, &a, &b);
+        let mask = (1u64 << result.len()) - 1;
+        for x in 0..(1u64 << width) {
+            for y in 0..(1u64 << width) {
+                let values = aig.simulate(|node| {
+                    let bit = |bits: &[Lit], value: u64| {
+                        bits.iter()
+                            .position(|l| l.node() == node)
+                            .map(|i| value >> i & 1 == 1)
+                    };
+                    bit(&a, x).or_else(|| bit(&b, y)).unwrap()
+                });
+                assert_eq!(
+                    bits_value(&values, &result),
+                    BigUint::from(expected(x, y) & mask),
+                    "inputs {x} and {y}"
+                );
+            }
+        }
+    }
+
+    fn signed(value: u64, width: usize) -> i64 {
+        ((value << (64 - width)) as i64) >> (64 - width)
+    }
+
+    #[test]
+    fn arithmetic_matches_integers() {
+        check_binary(4, add, |a, b| a + b);
+        check_binary(4, sub, |a, b| a.wrapping_sub(b));
+        check_binary(4, mul, |a, b| a * b);
+        check_binary(4, |aig, a, _| negate(aig, a), |a, _| a.wrapping_neg());
+    }
+
+    #[test]
+    fn comparisons_match_integers() {
+        check_binary(3, |aig, a, b| vec![eq(aig, a, b)], |a, b| (a == b) as u64);
+        check_binary(
+            3,
+            |aig, a, b| vec![unsigned_lt(aig, a, b)],
+            |a, b| (a < b) as u64,
+        );
+        check_binary(
+            3,
+            |aig, a, b| vec![signed_lt(aig, a, b)],
+            |a, b| (signed(a, 3) < signed(b, 3)) as u64,
+        );
+    }
+
+    #[test]
+    fn shifts_match_integers() {
+        check_binary(
+            4,
+            |aig, a, b| shift(aig, a, b, Shift::Left, Lit::FALSE),
+            |a, b| a.checked_shl(b as u32).unwrap_or(0),
+        );
+        check_binary(
+            4,
+            |aig, a, b| shift(aig, a, b, Shift::Right, Lit::FALSE),
+            |a, b| a.checked_shr(b as u32).unwrap_or(0),
+        );
+        check_binary(
+            4,
+            |aig, a, b| shift(aig, a, b, Shift::Right, a[3]),
+            |a, b| (signed(a, 4) >> b.min(63)) as u64,
+        );
+    }
+
+    #[test]
+    fn constants_are_twos_complement() {
+        let aig = Aig::new();
+        let values = aig.simulate(|_| false);
+        assert_eq!(
+            bits_value(&values, &constant(&(-3).into(), 4)),
+            BigUint::from(0b1101u32)
+        );
+        assert_eq!(
+            bits_value(&values, &resize(&constant(&(-3).into(), 4), 6, true)),
+            BigUint::from(0b111101u32)
+        );
+        assert_eq!(
+            bits_value(&values, &resize(&constant(&(-3).into(), 4), 6, false)),
+            BigUint::from(0b001101u32)
+        );
+    }
+
+    #[test]
+    fn identical_gates_are_shared() {
+        let mut aig = Aig::new();
+        let a = aig.input();
+        let b = aig.input();
+        let x = aig.and(a, b);
+        let y = aig.and(b, a);
+        assert_eq!(x, y);
+        assert_eq!(aig.and(a, !a), Lit::FALSE);
+        assert_eq!(aig.or(a, Lit::TRUE), Lit::TRUE);
+        assert_eq!(aig.nodes().len(), 4);
+    }
+}
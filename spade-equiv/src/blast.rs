@@ -0,0 +1,664 @@
+//! Translates a flattened MIR entity into bit vectors in an [`Aig`]. The semantics of each
+//! operator follow the Verilog generated for it, including the width extension Verilog
+//! applies to the operands of an expression, so that a difference found here is a
+//! difference in the generated hardware.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use num::{BigUint, ToPrimitive, Zero};
+use spade_mir::enum_util::tag_size;
+use spade_mir::types::Type;
+use spade_mir::unit_name::{UnitName, UnitNameKind};
+use spade_mir::{
+    Binding, ClockEdge, ConstantValue, Entity, Operator, ResetStyle, Statement, ValueName,
+};
+
+use crate::aig::{self, Aig, Bits, Lit, Shift};
+use crate::EquivalenceError;
+
+/// Identifies a register or instance across the two entities. Registers are identified by
+/// their name and instances by the unit they instantiate. Since neither is necessarily
+/// unique, they are also numbered in the order they appear in.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Key {
+    pub name: String,
+    pub index: usize,
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.index == 0 {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{}#{}", self.name, self.index)
+        }
+    }
+}
+
+/// Assigns keys to names in the order they are seen
+#[derive(Default)]
+struct KeyCounter(HashMap<String, usize>);
+
+impl KeyCounter {
+    fn next(&mut self, name: String) -> Key {
+        let count = self.0.entry(name.clone()).or_default();
+        *count += 1;
+        Key {
+            name,
+            index: *count - 1,
+        }
+    }
+}
+
+/// The name used to match units between the entities. The IDs of generic units are left
+/// out as they differ between compilations
+pub fn unit_key(name: &UnitName) -> String {
+    match &name.kind {
+        UnitNameKind::Unescaped(name) => name.clone(),
+        UnitNameKind::Escaped { path, .. } => path.join("::"),
+    }
+}
+
+fn register_name(name: &ValueName) -> String {
+    match name {
+        ValueName::Named(_, name, _) => name.clone(),
+        ValueName::Expr(_) => "e".to_string(),
+    }
+}
+
+/// The free variables which are shared between the two entities
+pub struct Context {
+    pub aig: Aig,
+    /// The bits of each input port
+    pub inputs: Vec<Bits>,
+    /// The current value of each register
+    pub registers: BTreeMap<Key, (Type, Bits)>,
+    /// The output of each instance
+    pub instances: BTreeMap<Key, (Type, Bits)>,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self {
+            aig: Aig::new(),
+            inputs: vec![],
+            registers: BTreeMap::new(),
+            instances: BTreeMap::new(),
+        }
+    }
+}
+
+/// The free variables for `key`, created the first time they are asked for
+fn shared(
+    aig: &mut Aig,
+    map: &mut BTreeMap<Key, (Type, Bits)>,
+    key: &Key,
+    ty: &Type,
+) -> Result<Result<Bits, String>, EquivalenceError> {
+    Ok(match map.get(key) {
+        Some((existing, bits)) if existing == ty => Ok(bits.clone()),
+        Some((existing, _)) => Err(format!(
+            "It is {existing} in one entity and {ty} in the other"
+        )),
+        None => {
+            let bits = aig.inputs(width(ty)?);
+            map.insert(key.clone(), (ty.clone(), bits.clone()));
+            Ok(bits)
+        }
+    })
+}
+
+pub struct RegisterLogic {
+    pub ty: Type,
+    pub clock: Bits,
+    pub clock_edge: ClockEdge,
+    /// The trigger and value of the reset
+    pub reset: Option<(Bits, Bits)>,
+    pub reset_style: ResetStyle,
+    pub initial: Option<Bits>,
+    /// The value the register takes on the next clock edge, including the effect of the reset
+    pub next: Bits,
+}
+
+pub struct InstanceLogic {
+    /// The name, type and value of each parameter
+    pub inputs: Vec<(String, Type, Bits)>,
+}
+
+/// The logic of an entity in terms of the inputs, registers and instance outputs in the
+/// [`Context`]
+pub struct Design {
+    pub output: Bits,
+    pub registers: BTreeMap<Key, RegisterLogic>,
+    pub instances: BTreeMap<Key, InstanceLogic>,
+}
+
+pub fn width(ty: &Type) -> Result<usize, EquivalenceError> {
+    if !ty.backward_size().is_zero() {
+        return Err(EquivalenceError::Unsupported("ports".to_string()));
+    }
+    match ty {
+        Type::Memory { .. } => Err(EquivalenceError::Unsupported("memories".to_string())),
+        Type::InOut(_) => Err(EquivalenceError::Unsupported("inout values".to_string())),
+        _ => ty
+            .size()
+            .to_usize()
+            .ok_or_else(|| EquivalenceError::Unsupported(format!("values as large as {ty}"))),
+    }
+}
+
+fn index(value: &BigUint) -> usize {
+    value.to_usize().expect("Index does not fit in a usize")
+}
+
+/// Computes the bits of the values defined by a list of statements
+struct Blaster<'a> {
+    ctx: &'a mut Context,
+    definitions: HashMap<ValueName, &'a Statement>,
+    instance_keys: HashMap<ValueName, Key>,
+    values: HashMap<ValueName, Bits>,
+    types: HashMap<ValueName, Type>,
+}
+
+impl<'a> Blaster<'a> {
+    fn new(ctx: &'a mut Context, statements: &'a [Statement]) -> Self {
+        let mut definitions = HashMap::new();
+        let mut types = HashMap::new();
+        let mut instances = KeyCounter::default();
+        let mut instance_keys = HashMap::new();
+        for statement in statements {
+            match statement {
+                Statement::Binding(binding) => {
+                    definitions.insert(binding.name.clone(), statement);
+                    types.insert(binding.name.clone(), binding.ty.clone());
+                    if let Operator::Instance { name, .. } = &binding.operator {
+                        instance_keys.insert(binding.name.clone(), instances.next(unit_key(name)));
+                    }
+                }
+                Statement::Constant(id, ty, _) => {
+                    definitions.insert(ValueName::Expr(*id), statement);
+                    types.insert(ValueName::Expr(*id), ty.clone());
+                }
+                Statement::Register(reg) => {
+                    types.insert(reg.name.clone(), reg.ty.clone());
+                }
+                // These have no effect on the logic
+                Statement::Assert(_) | Statement::Property(_) | Statement::WalTrace { .. } => {}
+                // Checked when blasting the entity
+                Statement::Set { .. } => {}
+            }
+        }
+        Self {
+            ctx,
+            definitions,
+            instance_keys,
+            values: HashMap::new(),
+            types,
+        }
+    }
+
+    fn operands(statement: &Statement) -> &[ValueName] {
+        match statement {
+            // Instance outputs are free variables, which means that they do not depend
+            // on the inputs to the instance
+            Statement::Binding(Binding {
+                operator: Operator::Instance { .. },
+                ..
+            }) => &[],
+            Statement::Binding(binding) => &binding.operands,
+            _ => &[],
+        }
+    }
+
+    /// The bits of `name`, computing the values it depends on first. The dependencies
+    /// are visited with an explicit stack as the statement lists of large units are too
+    /// deep to recurse through
+    fn value(&mut self, name: &ValueName) -> Result<Bits, EquivalenceError> {
+        let mut in_progress = HashSet::new();
+        let mut stack = vec![(name.clone(), false)];
+        while let Some((name, expanded)) = stack.pop() {
+            if self.values.contains_key(&name) {
+                continue;
+            }
+            let statement = *self
+                .definitions
+                .get(&name)
+                .ok_or_else(|| EquivalenceError::UndefinedValue(name.to_string()))?;
+            if expanded {
+                let bits = self.statement_value(statement)?;
+                in_progress.remove(&name);
+                self.values.insert(name, bits);
+            } else {
+                if !in_progress.insert(name.clone()) {
+                    return Err(EquivalenceError::CombinationalLoop(name.to_string()));
+                }
+                stack.push((name, true));
+                for op in Self::operands(statement) {
+                    if !self.values.contains_key(op) {
+                        stack.push((op.clone(), false))
+                    }
+                }
+            }
+        }
+        Ok(self.values[name].clone())
+    }
+
+    fn statement_value(&mut self, statement: &Statement) -> Result<Bits, EquivalenceError> {
+        match statement {
+            Statement::Binding(binding) => {
+                let ops = binding
+                    .operands
+                    .iter()
+                    .map(|op| self.values.get(op).cloned().unwrap_or_default())
+                    .collect::<Vec<_>>();
+                self.binding_value(binding, &ops)
+            }
+            Statement::Constant(_, ty, value) => match value {
+                ConstantValue::Int(value) => Ok(aig::constant(value, width(ty)?)),
+                ConstantValue::Bool(value) => Ok(vec![Lit::constant(*value)]),
+                ConstantValue::HighImp => {
+                    Err(EquivalenceError::Unsupported("HIGHIMP values".to_string()))
+                }
+            },
+            _ => unreachable!("Only bindings and constants define values"),
+        }
+    }
+
+    fn binding_value(&mut self, binding: &Binding, ops: &[Bits]) -> Result<Bits, EquivalenceError> {
+        let w = width(&binding.ty)?;
+        let aig = &mut self.ctx.aig;
+
+        // The width Verilog evaluates an expression in is the widest of the target and
+        // the operands
+        let context_width = |ops: &[&Bits]| ops.iter().map(|op| op.len()).fold(w, usize::max);
+        let arithmetic = |aig: &mut Aig, signed: bool, f: fn(&mut Aig, &[Lit], &[Lit]) -> Bits| {
+            let cw = context_width(&[&ops[0], &ops[1]]);
+            let result = f(
+                aig,
+                &aig::resize(&ops[0], cw, signed),
+                &aig::resize(&ops[1], cw, signed),
+            );
+            aig::resize(&result, w, false)
+        };
+        // Comparisons are evaluated in the width of the operands and give a single bit
+        let compare = |aig: &mut Aig, signed: bool, f: fn(&mut Aig, &[Lit], &[Lit]) -> Lit| {
+            let cw = ops[0].len().max(ops[1].len());
+            let result = f(
+                aig,
+                &aig::resize(&ops[0], cw, signed),
+                &aig::resize(&ops[1], cw, signed),
+            );
+            aig::resize(&[result], w, false)
+        };
+        let shift = |aig: &mut Aig, direction: Shift, signed: bool| {
+            let value = aig::resize(&ops[0], context_width(&[&ops[0]]), signed);
+            let fill = match value.last() {
+                Some(msb) if signed => *msb,
+                _ => Lit::FALSE,
+            };
+            let result = aig::shift(aig, &value, &ops[1], direction, fill);
+            aig::resize(&result, w, false)
+        };
+        let bit = |lit: Lit| aig::resize(&[lit], w, false);
+        let unsupported = |what: &str| Err(EquivalenceError::Unsupported(what.to_string()));
+
+        let result = match &binding.operator {
+            Operator::Add => arithmetic(aig, true, aig::add),
+            Operator::UnsignedAdd => arithmetic(aig, false, aig::add),
+            Operator::Sub => arithmetic(aig, true, aig::sub),
+            Operator::UnsignedSub => arithmetic(aig, false, aig::sub),
+            Operator::Mul => arithmetic(aig, true, aig::mul),
+            Operator::UnsignedMul => arithmetic(aig, false, aig::mul),
+            Operator::Div | Operator::UnsignedDiv => return unsupported("division"),
+            Operator::Mod | Operator::UnsignedMod => return unsupported("modulo"),
+            Operator::Eq => compare(aig, false, aig::eq),
+            Operator::NotEq => compare(aig, false, |aig, a, b| !aig::eq(aig, a, b)),
+            Operator::Gt => compare(aig, true, |aig, a, b| aig::signed_lt(aig, b, a)),
+            Operator::UnsignedGt => compare(aig, false, |aig, a, b| aig::unsigned_lt(aig, b, a)),
+            Operator::Lt => compare(aig, true, aig::signed_lt),
+            Operator::UnsignedLt => compare(aig, false, aig::unsigned_lt),
+            Operator::Ge => compare(aig, true, |aig, a, b| !aig::signed_lt(aig, a, b)),
+            Operator::UnsignedGe => compare(aig, false, |aig, a, b| !aig::unsigned_lt(aig, a, b)),
+            Operator::Le => compare(aig, true, |aig, a, b| !aig::signed_lt(aig, b, a)),
+            Operator::UnsignedLe => compare(aig, false, |aig, a, b| !aig::unsigned_lt(aig, b, a)),
+            Operator::LeftShift => shift(aig, Shift::Left, false),
+            Operator::RightShift => shift(aig, Shift::Right, false),
+            Operator::ArithmeticRightShift => shift(aig, Shift::Right, true),
+            Operator::LogicalAnd => {
+                let a = aig.or_all(ops[0].clone());
+                let b = aig.or_all(ops[1].clone());
+                bit(aig.and(a, b))
+            }
+            Operator::LogicalOr => {
+                let a = aig.or_all(ops[0].clone());
+                let b = aig.or_all(ops[1].clone());
+                bit(aig.or(a, b))
+            }
+            Operator::LogicalXor | Operator::BitwiseXor => {
+                arithmetic(aig, false, |aig, a, b| aig::bitwise(aig, a, b, Aig::xor))
+            }
+            Operator::BitwiseAnd => {
+                arithmetic(aig, false, |aig, a, b| aig::bitwise(aig, a, b, Aig::and))
+            }
+            Operator::BitwiseOr => {
+                arithmetic(aig, false, |aig, a, b| aig::bitwise(aig, a, b, Aig::or))
+            }
+            Operator::LogicalNot | Operator::Not => bit(!aig.or_all(ops[0].clone())),
+            Operator::BitwiseNot => {
+                let value = aig::resize(&ops[0], context_width(&[&ops[0]]), false);
+                aig::resize(&aig::not(&value), w, false)
+            }
+            Operator::USub => {
+                let value = aig::resize(&ops[0], context_width(&[&ops[0]]), false);
+                aig::resize(&aig::negate(aig, &value), w, false)
+            }
+            Operator::ReduceAnd => bit(aig.and_all(ops[0].clone())),
+            Operator::ReduceOr => bit(aig.or_all(ops[0].clone())),
+            Operator::ReduceXor => bit(aig.xor_all(ops[0].clone())),
+            Operator::DivPow2 => {
+                // if (divisor == 0) name = dividend;
+                // else name = $signed($signed(dividend) + $signed(1 << (divisor - 1)))
+                //      >>> $signed(divisor);
+                // where the unsized 1 is 32 bits wide
+                let (dividend, divisor) = (&ops[0], &ops[1]);
+                let cw = context_width(&[dividend]).max(32);
+                let amount_width = divisor.len().max(32);
+                let one = aig::constant(&1.into(), amount_width);
+                let amount = aig::sub(aig, &aig::resize(divisor, amount_width, false), &one);
+                let one = aig::constant(&1.into(), cw);
+                let rounding = aig::shift(aig, &one, &amount, Shift::Left, Lit::FALSE);
+                let sum = aig::add(aig, &aig::resize(dividend, cw, true), &rounding);
+                let fill = sum.last().copied().unwrap_or(Lit::FALSE);
+                let divided = aig::shift(aig, &sum, divisor, Shift::Right, fill);
+                let zero = aig::constant(&0.into(), divisor.len());
+                let is_zero = aig::eq(aig, divisor, &zero);
+                aig::mux(
+                    aig,
+                    is_zero,
+                    &aig::resize(dividend, w, false),
+                    &aig::resize(&divided, w, false),
+                )
+            }
+            Operator::Gray2Bin { num_bits } => {
+                let n = index(num_bits);
+                let mut result = vec![Lit::FALSE; n];
+                for i in (0..n).rev() {
+                    result[i] = if i == n - 1 {
+                        ops[0][i]
+                    } else {
+                        aig.xor(ops[0][i], result[i + 1])
+                    };
+                }
+                aig::resize(&result, w, false)
+            }
+            Operator::SignExtend {
+                extra_bits,
+                operand_size,
+            } => {
+                let extra = index(extra_bits);
+                let extended = if extra == 0 {
+                    ops[0].clone()
+                } else {
+                    let msb = ops[0][index(operand_size) - 1];
+                    aig::concat([vec![msb; extra].as_slice(), &ops[0]])
+                };
+                aig::resize(&extended, w, false)
+            }
+            Operator::ZeroExtend { extra_bits } => {
+                aig::resize(&ops[0], ops[0].len() + index(extra_bits), false)
+            }
+            Operator::Truncate => aig::resize(&ops[0], w, false),
+            Operator::Concat => {
+                aig::resize(&aig::concat(ops.iter().map(|op| op.as_slice())), w, false)
+            }
+            Operator::Select { .. } => {
+                let cond = aig.or_all(ops[0].clone());
+                let cw = context_width(&[&ops[1], &ops[2]]);
+                let result = aig::mux(
+                    aig,
+                    cond,
+                    &aig::resize(&ops[1], cw, false),
+                    &aig::resize(&ops[2], cw, false),
+                );
+                aig::resize(&result, w, false)
+            }
+            Operator::Match { .. } => {
+                // None of the branches matching gives an undefined value, here zero
+                let mut result = vec![Lit::FALSE; w];
+                for branch in ops.chunks(2).rev() {
+                    let cond = aig.or_all(branch[0].clone());
+                    result = aig::mux(aig, cond, &aig::resize(&branch[1], w, false), &result);
+                }
+                result
+            }
+            // The first element is the least significant
+            Operator::ConstructArray => {
+                aig::resize(&ops.iter().flatten().copied().collect::<Vec<_>>(), w, false)
+            }
+            Operator::IndexArray => {
+                // An index outside the array gives an undefined value, here zero
+                let (array, idx) = (&ops[0], &ops[1]);
+                let mut result = vec![Lit::FALSE; w];
+                if w != 0 {
+                    for (i, element) in array.chunks_exact(w).enumerate().rev() {
+                        let selected = aig::is_value(aig, idx, i);
+                        result = aig::mux(aig, selected, element, &result);
+                    }
+                }
+                result
+            }
+            Operator::RangeIndexArray {
+                start,
+                end_exclusive,
+            } => {
+                let member_size = match &binding.ty {
+                    Type::Array { inner, .. } => width(inner)?,
+                    _ => panic!("Range index with non-array output"),
+                };
+                ops[0][index(start) * member_size..index(end_exclusive) * member_size].to_vec()
+            }
+            Operator::RangeIndexBits {
+                start,
+                end_exclusive,
+            } => ops[0][index(start)..index(end_exclusive)].to_vec(),
+            // The first member is the most significant
+            Operator::ConstructTuple => aig::concat(ops.iter().map(|op| op.as_slice())),
+            Operator::ConstructEnum {
+                variant,
+                variant_count,
+            } => {
+                let tag = aig::constant(&(*variant).into(), tag_size(*variant_count));
+                let payload = aig::concat(ops.iter().map(|op| op.as_slice()));
+                // The padding is undefined, here zero
+                let padding = vec![Lit::FALSE; w - tag.len() - payload.len()];
+                aig::concat([tag.as_slice(), &payload, &padding])
+            }
+            Operator::IsEnumVariant { variant, enum_type } => {
+                let tag_size = tag_size(enum_type.assume_enum().len());
+                if tag_size == 0 {
+                    bit(Lit::TRUE)
+                } else {
+                    let total = ops[0].len();
+                    let tag = &ops[0][total - tag_size..];
+                    bit(aig::is_value(aig, tag, *variant))
+                }
+            }
+            Operator::EnumMember {
+                enum_type,
+                variant,
+                member_index,
+            } => {
+                let variants = enum_type.assume_enum();
+                let member_start = tag_size(variants.len())
+                    + variants[*variant][0..*member_index]
+                        .iter()
+                        .map(width)
+                        .sum::<Result<usize, _>>()?;
+                let member_end = member_start + width(&variants[*variant][*member_index])?;
+                let total = ops[0].len();
+                ops[0][total - member_end..total - member_start].to_vec()
+            }
+            Operator::IndexTuple(idx, types) => {
+                let sizes = types.iter().map(width).collect::<Result<Vec<_>, _>>()?;
+                let start = sizes[0..*idx as usize].iter().sum::<usize>();
+                let end = start + sizes[*idx as usize];
+                let total = ops[0].len();
+                ops[0][total - end..total - start].to_vec()
+            }
+            Operator::Bitreverse => {
+                aig::resize(&ops[0].iter().rev().copied().collect::<Vec<_>>(), w, false)
+            }
+            Operator::Alias => aig::resize(&ops[0], w, false),
+            Operator::Nop if w == 0 => vec![],
+            Operator::Instance { .. } => {
+                let key = &self.instance_keys[&binding.name];
+                shared(aig, &mut self.ctx.instances, key, &binding.ty)?.map_err(|reason| {
+                    EquivalenceError::InstanceMismatch {
+                        instance: key.to_string(),
+                        reason,
+                    }
+                })?
+            }
+            Operator::DeclClockedMemory { .. }
+            | Operator::DeclBlockRam { .. }
+            | Operator::IndexMemory => return unsupported("memories"),
+            Operator::ReadPort | Operator::FlipPort | Operator::ReadMutWires | Operator::Nop => {
+                return unsupported("ports")
+            }
+        };
+        Ok(result)
+    }
+}
+
+/// Bit-blasts `entity`, using the inputs in `ctx` as its input ports
+pub fn blast(ctx: &mut Context, entity: &Entity) -> Result<Design, EquivalenceError> {
+    if entity
+        .statements
+        .iter()
+        .any(|s| matches!(s, Statement::Set { .. }))
+    {
+        return Err(EquivalenceError::Unsupported("ports".to_string()));
+    }
+
+    let mut registers = KeyCounter::default();
+    let register_keys = entity
+        .statements
+        .iter()
+        .filter_map(|s| match s {
+            Statement::Register(reg) => Some((registers.next(register_name(&reg.name)), reg)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    // The initial values are computed separately as they have their own statements
+    let mut initial_values = HashMap::new();
+    for (key, reg) in &register_keys {
+        if let Some(initial) = &reg.initial {
+            let last = match initial.last() {
+                Some(Statement::Binding(binding)) => binding.name.clone(),
+                Some(Statement::Constant(id, _, _)) => ValueName::Expr(*id),
+                _ => panic!("The initial value of a register must end in a value"),
+            };
+            let value = Blaster::new(ctx, initial).value(&last)?;
+            initial_values.insert(key.clone(), aig::resize(&value, width(&reg.ty)?, false));
+        }
+    }
+
+    let mut blaster = Blaster::new(ctx, &entity.statements);
+    for (input, bits) in entity.inputs.iter().zip(&blaster.ctx.inputs) {
+        blaster.values.insert(input.val_name.clone(), bits.clone());
+        blaster
+            .types
+            .insert(input.val_name.clone(), input.ty.clone());
+    }
+    for (key, reg) in &register_keys {
+        let state = shared(
+            &mut blaster.ctx.aig,
+            &mut blaster.ctx.registers,
+            key,
+            &reg.ty,
+        )?
+        .map_err(|reason| EquivalenceError::RegisterMismatch {
+            register: key.to_string(),
+            reason,
+        })?;
+        blaster.values.insert(reg.name.clone(), state);
+    }
+
+    let output = blaster.value(&entity.output)?;
+
+    let mut register_logic = BTreeMap::new();
+    for (key, reg) in register_keys {
+        let w = width(&reg.ty)?;
+        let value = aig::resize(&blaster.value(&reg.value)?, w, false);
+        let clock = blaster.value(&reg.clock)?;
+        let (reset, next) = match &reg.reset {
+            Some((trigger, reset_value)) => {
+                let trigger = blaster.value(trigger)?;
+                let reset_value = aig::resize(&blaster.value(reset_value)?, w, false);
+                let aig = &mut blaster.ctx.aig;
+                let asserted = aig.or_all(trigger.clone());
+                let asserted = if reg.reset_style.active_low {
+                    !asserted
+                } else {
+                    asserted
+                };
+                let next = aig::mux(aig, asserted, &reset_value, &value);
+                (Some((trigger, reset_value)), next)
+            }
+            None => (None, value),
+        };
+        register_logic.insert(
+            key.clone(),
+            RegisterLogic {
+                ty: reg.ty.clone(),
+                clock,
+                clock_edge: reg.clock_edge,
+                reset,
+                reset_style: reg.reset_style,
+                initial: initial_values.remove(&key),
+                next,
+            },
+        );
+    }
+
+    let mut instance_logic = BTreeMap::new();
+    for statement in &entity.statements {
+        if let Statement::Binding(Binding {
+            name,
+            operator: Operator::Instance { params, .. },
+            operands,
+            ..
+        }) = statement
+        {
+            let inputs = params
+                .iter()
+                .zip(operands)
+                .map(|(param, op)| {
+                    let ty = blaster
+                        .types
+                        .get(op)
+                        .cloned()
+                        .ok_or_else(|| EquivalenceError::UndefinedValue(op.to_string()))?;
+                    Ok((param.name.clone(), ty, blaster.value(op)?))
+                })
+                .collect::<Result<_, EquivalenceError>>()?;
+            instance_logic.insert(
+                blaster.instance_keys[name].clone(),
+                InstanceLogic { inputs },
+            );
+        }
+    }
+
+    Ok(Design {
+        output,
+        registers: register_logic,
+        instances: instance_logic,
+    })
+}
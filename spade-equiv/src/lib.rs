@@ -0,0 +1,905 @@
+//! Checks whether two flattened MIR entities with the same ports describe the same hardware.
+//!
+//! Both entities are bit-blasted into a shared and-inverter graph in which the input ports,
+//! the current register values and the outputs of instantiated units are free variables
+//! shared between the entities. Registers are matched by name and instances by the unit
+//! they instantiate, in the order they appear in. The entities are equivalent if the
+//! output, the next value, clock and reset of every register and the inputs to every
+//! instance are the same for every assignment of the free variables, which is checked by
+//! asking a SAT solver for an assignment where any of them differ.
+//!
+//! Memories, ports, division and modulo are not supported.
+
+pub mod aig;
+pub mod blast;
+pub mod sat;
+
+use std::collections::{BTreeSet, HashMap};
+
+use num::{BigInt, BigUint, One, Zero};
+use spade_mir::eval::Value;
+use spade_mir::types::Type;
+use spade_mir::Entity;
+use thiserror::Error;
+
+use crate::aig::{bits_value, Aig, Bits, Lit, Node};
+use crate::blast::{blast, width, Context};
+use crate::sat::{SatLit, Solver};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum EquivalenceError {
+    #[error("The entities have different ports: {0}")]
+    PortMismatch(String),
+    #[error("The equivalence checker does not support {0}")]
+    Unsupported(String),
+    #[error("Register {0} only exists in one of the entities")]
+    UnmatchedRegister(String),
+    #[error("Register {register} differs between the entities: {reason}")]
+    RegisterMismatch { register: String, reason: String },
+    #[error("Instance of {0} only exists in one of the entities")]
+    UnmatchedInstance(String),
+    #[error("Instance of {instance} differs between the entities: {reason}")]
+    InstanceMismatch { instance: String, reason: String },
+    #[error("{0} is used but never defined")]
+    UndefinedValue(String),
+    #[error("{0} depends on itself combinationally")]
+    CombinationalLoop(String),
+}
+
+/// The value of a signal in a counterexample
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalValue {
+    pub name: String,
+    pub ty: Type,
+    pub value: Value,
+}
+
+/// A signal which has different values in the two entities
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub signal: String,
+    pub ty: Type,
+    pub left: Value,
+    pub right: Value,
+}
+
+/// An assignment of the inputs, registers and instance outputs for which the entities differ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counterexample {
+    pub inputs: Vec<SignalValue>,
+    pub registers: Vec<SignalValue>,
+    pub instance_outputs: Vec<SignalValue>,
+    pub differences: Vec<Difference>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Equivalent,
+    Different(Counterexample),
+}
+
+/// Integers and bools are shown as such, everything else as its bits
+fn format_value(ty: &Type, value: &Value) -> String {
+    match ty {
+        Type::Int(_) | Type::UInt(_) | Type::Bool => format!("{value}"),
+        _ if value.width().is_zero() => "()".to_string(),
+        _ => format!("0b{}", value.as_string()),
+    }
+}
+
+impl std::fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sections = [
+            ("Inputs", &self.inputs),
+            ("Registers", &self.registers),
+            ("Instance outputs", &self.instance_outputs),
+        ];
+        for (title, signals) in sections {
+            if !signals.is_empty() {
+                writeln!(f, "{title}:")?;
+                for SignalValue { name, ty, value } in signals {
+                    writeln!(f, "  {name} = {}", format_value(ty, value))?;
+                }
+            }
+        }
+        writeln!(f, "Differences:")?;
+        for Difference {
+            signal,
+            ty,
+            left,
+            right,
+        } in &self.differences
+        {
+            writeln!(
+                f,
+                "  {signal}: {} != {}",
+                format_value(ty, left),
+                format_value(ty, right)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The value of `bits` as a value of type `ty`
+fn value_of(ty: &Type, bits: BigUint, width: usize) -> Value {
+    match ty {
+        Type::Bool => Value::Bit(bits == BigUint::one()),
+        Type::Int(size) => {
+            let val = if width != 0 && bits.bit(width as u64 - 1) {
+                BigInt::from(bits) - (BigInt::one() << width)
+            } else {
+                BigInt::from(bits)
+            };
+            Value::Int {
+                size: size.clone(),
+                val,
+            }
+        }
+        _ => Value::UInt {
+            size: width.into(),
+            val: bits,
+        },
+    }
+}
+
+/// Two vectors of bits which must be equal for the entities to be equivalent
+struct Obligation {
+    signal: String,
+    ty: Type,
+    left: Bits,
+    right: Bits,
+}
+
+/// Encodes the gates `root` depends on as clauses, returning the solver variable of each node
+fn encode(aig: &Aig, root: Lit, solver: &mut Solver) -> HashMap<usize, usize> {
+    let mut vars = HashMap::new();
+    let mut stack = vec![root.node()];
+    while let Some(node) = stack.pop() {
+        if vars.contains_key(&node) {
+            continue;
+        }
+        vars.insert(node, solver.new_var());
+        if let Node::And(a, b) = aig.nodes()[node] {
+            stack.push(a.node());
+            stack.push(b.node());
+        }
+    }
+    let lit = |l: Lit| SatLit::new(vars[&l.node()], l.is_negated());
+    for (&node, &var) in &vars {
+        let out = SatLit::new(var, false);
+        match aig.nodes()[node] {
+            Node::False => solver.add_clause([!out]),
+            Node::Input => {}
+            Node::And(a, b) => {
+                solver.add_clause([!out, lit(a)]);
+                solver.add_clause([!out, lit(b)]);
+                solver.add_clause([out, !lit(a), !lit(b)]);
+            }
+        }
+    }
+    vars
+}
+
+/// Checks whether `left` and `right` are equivalent. The entities must have the same port
+/// types, the ports are matched by position
+pub fn check_equivalence(left: &Entity, right: &Entity) -> Result<Outcome, EquivalenceError> {
+    if left.inputs.len() != right.inputs.len() {
+        return Err(EquivalenceError::PortMismatch(format!(
+            "{} has {} inputs but {} has {}",
+            left.name,
+            left.inputs.len(),
+            right.name,
+            right.inputs.len()
+        )));
+    }
+    for (l, r) in left.inputs.iter().zip(&right.inputs) {
+        if l.ty != r.ty {
+            return Err(EquivalenceError::PortMismatch(format!(
+                "{} is {} but {} is {}",
+                l.name, l.ty, r.name, r.ty
+            )));
+        }
+    }
+    if left.output_type != right.output_type {
+        return Err(EquivalenceError::PortMismatch(format!(
+            "The output is {} in {} but {} in {}",
+            left.output_type, left.name, right.output_type, right.name
+        )));
+    }
+
+    let mut ctx = Context::new();
+    for input in &left.inputs {
+        let bits = ctx.aig.inputs(width(&input.ty)?);
+        ctx.inputs.push(bits);
+    }
+    let l = blast(&mut ctx, left)?;
+    let r = blast(&mut ctx, right)?;
+
+    let mut obligations = vec![Obligation {
+        signal: "output".to_string(),
+        ty: left.output_type.clone(),
+        left: l.output,
+        right: r.output,
+    }];
+
+    let register_keys = l
+        .registers
+        .keys()
+        .chain(r.registers.keys())
+        .collect::<BTreeSet<_>>();
+    for key in register_keys {
+        let (Some(lreg), Some(rreg)) = (l.registers.get(key), r.registers.get(key)) else {
+            return Err(EquivalenceError::UnmatchedRegister(key.to_string()));
+        };
+        let mismatch = |reason: String| EquivalenceError::RegisterMismatch {
+            register: key.to_string(),
+            reason,
+        };
+        if lreg.clock_edge != rreg.clock_edge {
+            return Err(mismatch(format!(
+                "It is clocked on the {} edge in one entity and the {} edge in the other",
+                lreg.clock_edge, rreg.clock_edge
+            )));
+        }
+        if lreg.reset.is_some() != rreg.reset.is_some() {
+            return Err(mismatch("Only one of them has a reset".to_string()));
+        }
+        if lreg.reset.is_some() && lreg.reset_style != rreg.reset_style {
+            return Err(mismatch(format!(
+                "The reset is {} in one entity and {} in the other",
+                lreg.reset_style, rreg.reset_style
+            )));
+        }
+        let mut obligation = |signal: &str, ty: &Type, left: &Bits, right: &Bits| {
+            obligations.push(Obligation {
+                signal: format!("{signal} of register {key}"),
+                ty: ty.clone(),
+                left: left.clone(),
+                right: right.clone(),
+            })
+        };
+        obligation("clock", &Type::Bool, &lreg.clock, &rreg.clock);
+        obligation("next value", &lreg.ty, &lreg.next, &rreg.next);
+        if let (Some((ltrigger, _)), Some((rtrigger, _))) = (&lreg.reset, &rreg.reset) {
+            obligation("reset", &Type::Bool, ltrigger, rtrigger);
+        }
+        match (&lreg.initial, &rreg.initial) {
+            (Some(linitial), Some(rinitial)) => {
+                obligation("initial value", &lreg.ty, linitial, rinitial)
+            }
+            (None, None) => {}
+            _ => {
+                return Err(mismatch(
+                    "Only one of them has an initial value".to_string(),
+                ))
+            }
+        }
+    }
+
+    let instance_keys = l
+        .instances
+        .keys()
+        .chain(r.instances.keys())
+        .collect::<BTreeSet<_>>();
+    for key in instance_keys {
+        let (Some(linst), Some(rinst)) = (l.instances.get(key), r.instances.get(key)) else {
+            return Err(EquivalenceError::UnmatchedInstance(key.to_string()));
+        };
+        if linst.inputs.len() != rinst.inputs.len() {
+            return Err(EquivalenceError::InstanceMismatch {
+                instance: key.to_string(),
+                reason: "The number of inputs differ".to_string(),
+            });
+        }
+        for ((name, ty, lbits), (_, _, rbits)) in linst.inputs.iter().zip(&rinst.inputs) {
+            obligations.push(Obligation {
+                signal: format!("input {name} of instance of {key}"),
+                ty: ty.clone(),
+                left: lbits.clone(),
+                right: rbits.clone(),
+            })
+        }
+    }
+
+    let aig = &mut ctx.aig;
+    let mut miter = Lit::FALSE;
+    for obligation in &obligations {
+        if obligation.left.len() != obligation.right.len() {
+            // Only possible for instance inputs, all other types have been checked
+            return Err(EquivalenceError::InstanceMismatch {
+                instance: obligation.signal.clone(),
+                reason: "The input widths differ".to_string(),
+            });
+        }
+        let differences = aig::bitwise(aig, &obligation.left, &obligation.right, Aig::xor);
+        let different = aig.or_all(differences);
+        miter = aig.or(miter, different);
+    }
+    // Structurally identical logic has already been merged
+    if miter == Lit::FALSE {
+        return Ok(Outcome::Equivalent);
+    }
+
+    let mut solver = Solver::new();
+    let vars = encode(aig, miter, &mut solver);
+    solver.add_clause([SatLit::new(vars[&miter.node()], miter.is_negated())]);
+    let Some(model) = solver.solve() else {
+        return Ok(Outcome::Equivalent);
+    };
+
+    // Free variables which the miter does not depend on can have any value
+    let values = aig.simulate(|node| vars.get(&node).map(|v| model[*v]).unwrap_or(false));
+    let signal = |name: String, ty: &Type, bits: &Bits| SignalValue {
+        name,
+        ty: ty.clone(),
+        value: value_of(ty, bits_value(&values, bits), bits.len()),
+    };
+    Ok(Outcome::Different(Counterexample {
+        inputs: left
+            .inputs
+            .iter()
+            .zip(&ctx.inputs)
+            .map(|(input, bits)| signal(input.name.clone(), &input.ty, bits))
+            .collect(),
+        registers: ctx
+            .registers
+            .iter()
+            .map(|(key, (ty, bits))| signal(key.to_string(), ty, bits))
+            .collect(),
+        instance_outputs: ctx
+            .instances
+            .iter()
+            .map(|(key, (ty, bits))| signal(key.to_string(), ty, bits))
+            .collect(),
+        differences: obligations
+            .iter()
+            .filter_map(|o| {
+                let left = bits_value(&values, &o.left);
+                let right = bits_value(&values, &o.right);
+                (left != right).then(|| Difference {
+                    signal: o.signal.clone(),
+                    ty: o.ty.clone(),
+                    left: value_of(&o.ty, left, o.left.len()),
+                    right: value_of(&o.ty, right, o.right.len()),
+                })
+            })
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use itertools::Itertools;
+    use pretty_assertions::assert_eq;
+    use spade_mir::eval::eval_statements;
+    use spade_mir::parser::{parse_entity, parse_statement};
+
+    fn check(left: &str, right: &str) -> Result<Outcome, EquivalenceError> {
+        check_equivalence(&parse_entity(left).unwrap(), &parse_entity(right).unwrap())
+    }
+
+    fn counterexample(left: &str, right: &str) -> Counterexample {
+        match check(left, right).unwrap() {
+            Outcome::Different(counterexample) => counterexample,
+            Outcome::Equivalent => panic!("Expected the entities to differ"),
+        }
+    }
+
+    #[test]
+    fn commuted_addition_is_equivalent() {
+        let left = indoc! {"
+            entity add((a, a, int<8>), (b, b, int<8>)) -> int<9> {
+                let e0: int<9> = Add(a, b)
+            } => e0
+        "};
+        let right = indoc! {"
+            entity add((x, x, int<8>), (y, y, int<8>)) -> int<9> {
+                let e5: int<9> = Add(y, x)
+            } => e5
+        "};
+        assert_eq!(check(left, right), Ok(Outcome::Equivalent));
+    }
+
+    #[test]
+    fn multiplication_by_two_is_a_shift() {
+        let left = indoc! {"
+            entity double((a, a, uint<6>)) -> uint<6> {
+                const e0: uint<6> = 2
+                let e1: uint<6> = UnsignedMul(a, e0)
+            } => e1
+        "};
+        let right = indoc! {"
+            entity double((a, a, uint<6>)) -> uint<6> {
+                const e0: uint<6> = 1
+                let e1: uint<6> = LeftShift(a, e0)
+            } => e1
+        "};
+        assert_eq!(check(left, right), Ok(Outcome::Equivalent));
+    }
+
+    #[test]
+    fn comparisons_can_be_flipped() {
+        let left = indoc! {"
+            entity lt((a, a, int<4>), (b, b, int<4>)) -> bool {
+                let e0: bool = Lt(a, b)
+            } => e0
+        "};
+        let right = indoc! {"
+            entity lt((a, a, int<4>), (b, b, int<4>)) -> bool {
+                let e0: bool = Ge(a, b)
+                let e1: bool = LogicalNot(e0)
+            } => e1
+        "};
+        assert_eq!(check(left, right), Ok(Outcome::Equivalent));
+    }
+
+    #[test]
+    fn counterexample_is_an_input_where_the_outputs_differ() {
+        let left = indoc! {"
+            entity f((a, a, int<8>), (b, b, int<8>)) -> int<8> {
+                let e0: int<9> = Add(a, b)
+                let e1: int<8> = Truncate(e0)
+            } => e1
+        "};
+        let right = indoc! {"
+            entity f((a, a, int<8>), (b, b, int<8>)) -> int<8> {
+                let e0: int<9> = Sub(a, b)
+                let e1: int<8> = Truncate(e0)
+            } => e1
+        "};
+        let result = counterexample(left, right);
+        let [a, b] = [&result.inputs[0].value, &result.inputs[1].value]
+            .map(|v| i64::try_from(v.assume_int()).unwrap());
+        let wrap = |v: i64| (v as i8) as i64;
+        assert_eq!(result.differences.len(), 1);
+        let difference = &result.differences[0];
+        assert_eq!(difference.signal, "output");
+        assert_eq!(difference.left.assume_int(), wrap(a + b).into());
+        assert_eq!(difference.right.assume_int(), wrap(a - b).into());
+        assert_ne!(wrap(a + b), wrap(a - b));
+    }
+
+    const COUNTER: &str = indoc! {"
+        entity counter((clk, clk, bool), (rst, rst, bool)) -> uint<8> {
+            const e0: uint<8> = 0
+            const e1: uint<8> = 1
+            let e2: uint<9> = UnsignedAdd(count, e1)
+            let e3: uint<8> = Truncate(e2)
+            reg(clk) count: uint<8>(rst, e0) = e3
+        } => count
+    "};
+
+    #[test]
+    fn registers_with_the_same_next_value_are_equivalent() {
+        let right = indoc! {"
+            entity counter((clk, clk, bool), (rst, rst, bool)) -> uint<8> {
+                const e10: uint<8> = 1
+                const e11: uint<8> = 0
+                let e12: uint<8> = UnsignedAdd(e10, count_n4)
+                reg(clk) count_n4: uint<8>(rst, e11) = e12
+            } => count_n4
+        "};
+        assert_eq!(check(COUNTER, right), Ok(Outcome::Equivalent));
+    }
+
+    #[test]
+    fn different_reset_values_are_found() {
+        let right = indoc! {"
+            entity counter((clk, clk, bool), (rst, rst, bool)) -> uint<8> {
+                const e0: uint<8> = 5
+                const e1: uint<8> = 1
+                let e2: uint<9> = UnsignedAdd(count, e1)
+                let e3: uint<8> = Truncate(e2)
+                reg(clk) count: uint<8>(rst, e0) = e3
+            } => count
+        "};
+        let result = counterexample(COUNTER, right);
+        assert_eq!(result.inputs[1].value, Value::Bit(true));
+        assert_eq!(
+            result.differences,
+            vec![Difference {
+                signal: "next value of register count".to_string(),
+                ty: Type::uint(8),
+                left: Value::UInt {
+                    size: 8u32.into(),
+                    val: 0u32.into()
+                },
+                right: Value::UInt {
+                    size: 8u32.into(),
+                    val: 5u32.into()
+                },
+            }]
+        );
+        assert!(
+            format!("{result}").contains("next value of register count: uint<8>(0) != uint<8>(5)")
+        );
+    }
+
+    #[test]
+    fn registers_must_exist_in_both_entities() {
+        let right = indoc! {"
+            entity counter((clk, clk, bool), (rst, rst, bool)) -> uint<8> {
+                const e0: uint<8> = 0
+                const e1: uint<8> = 1
+                let e2: uint<9> = UnsignedAdd(other, e1)
+                let e3: uint<8> = Truncate(e2)
+                reg(clk) other: uint<8>(rst, e0) = e3
+            } => other
+        "};
+        assert_eq!(
+            check(COUNTER, right),
+            Err(EquivalenceError::UnmatchedRegister("count".to_string()))
+        );
+    }
+
+    #[test]
+    fn instance_inputs_are_compared() {
+        let left = indoc! {"
+            entity top((a, a, uint<4>), (b, b, uint<4>)) -> uint<4> {
+                let e0: uint<4> = BitwiseAnd(a, b)
+                let e1: uint<4> = Instance(sub; x)(e0)
+            } => e1
+        "};
+        let same = indoc! {"
+            entity top((a, a, uint<4>), (b, b, uint<4>)) -> uint<4> {
+                let e0: uint<4> = BitwiseNot(a)
+                let e1: uint<4> = BitwiseNot(b)
+                let e2: uint<4> = BitwiseOr(e0, e1)
+                let e3: uint<4> = BitwiseNot(e2)
+                let e4: uint<4> = Instance(sub; x)(e3)
+            } => e4
+        "};
+        let different = indoc! {"
+            entity top((a, a, uint<4>), (b, b, uint<4>)) -> uint<4> {
+                let e0: uint<4> = BitwiseOr(a, b)
+                let e1: uint<4> = Instance(sub; x)(e0)
+            } => e1
+        "};
+        assert_eq!(check(left, same), Ok(Outcome::Equivalent));
+        let result = counterexample(left, different);
+        assert_eq!(
+            result
+                .differences
+                .iter()
+                .map(|d| d.signal.as_str())
+                .collect::<Vec<_>>(),
+            vec!["input x of instance of sub"]
+        );
+    }
+
+    #[test]
+    fn enums_and_matches_are_supported() {
+        // match e { Some(x) => x, None => 0 } written as a match and as a select
+        let left = indoc! {"
+            entity f((e, e, enum option [], option [uint<3>])) -> uint<3> {
+                let e0: bool = IsEnumVariant(1, enum option [], option [uint<3>])(e)
+                let e1: uint<3> = EnumMember(1 0, enum option [], option [uint<3>])(e)
+                let e2: bool = IsEnumVariant(0, enum option [], option [uint<3>])(e)
+                const e3: uint<3> = 0
+                let e4: uint<3> = Match(e0, e1, e2, e3)
+            } => e4
+        "};
+        let right = indoc! {"
+            entity f((e, e, enum option [], option [uint<3>])) -> uint<3> {
+                let e0: bool = RangeIndexBits(3, 4)(e)
+                let e1: uint<3> = RangeIndexBits(0, 3)(e)
+                const e3: uint<3> = 0
+                let e4: uint<3> = Select(e0, e1, e3)
+            } => e4
+        "};
+        assert_eq!(check(left, right), Ok(Outcome::Equivalent));
+    }
+
+    fn unsupported(operator: &str) -> Result<Outcome, EquivalenceError> {
+        let left = format!(
+            "entity f((a, a, uint<4>), (b, b, uint<4>)) -> uint<4> {{
+                let e0: uint<4> = {operator}(a, b)
+            }} => e0"
+        );
+        check(&left, &left)
+    }
+
+    #[test]
+    fn division_is_unsupported() {
+        for operator in ["Div", "UnsignedDiv"] {
+            assert_eq!(
+                unsupported(operator),
+                Err(EquivalenceError::Unsupported("division".to_string())),
+                "{operator}"
+            );
+        }
+    }
+
+    #[test]
+    fn modulo_is_unsupported() {
+        for operator in ["Mod", "UnsignedMod"] {
+            assert_eq!(
+                unsupported(operator),
+                Err(EquivalenceError::Unsupported("modulo".to_string())),
+                "{operator}"
+            );
+        }
+    }
+
+    #[test]
+    fn clocked_memories_are_unsupported() {
+        let left = indoc! {"
+            entity f((clk, clk, bool), (a, a, uint<1>)) -> bool {
+                let e0: Memory[bool; 2] = DeclClockedMemory(1, 1, 1, 2)(clk)
+                let e1: bool = IndexMemory(e0, a)
+            } => e1
+        "};
+        assert_eq!(
+            check(left, left),
+            Err(EquivalenceError::Unsupported("memories".to_string()))
+        );
+    }
+
+    #[test]
+    fn block_rams_are_unsupported() {
+        let left = indoc! {"
+            entity f((clk, clk, bool)) -> int<8> {
+                let e0: int<8> = DeclBlockRam(2, 8, 4, mask 2, no_change)(clk)
+            } => e0
+        "};
+        assert_eq!(
+            check(left, left),
+            Err(EquivalenceError::Unsupported("memories".to_string()))
+        );
+    }
+
+    #[test]
+    fn combinational_loops_are_reported() {
+        let left = indoc! {"
+            entity f((a, a, uint<4>)) -> uint<4> {
+                let e0: uint<4> = BitwiseAnd(a, e1)
+                let e1: uint<4> = BitwiseOr(a, e0)
+            } => e0
+        "};
+        assert!(matches!(
+            check(left, left),
+            Err(EquivalenceError::CombinationalLoop(_))
+        ));
+    }
+
+    #[test]
+    fn mismatched_ports_are_reported() {
+        let left = indoc! {"
+            entity f((a, a, uint<4>)) -> uint<4> {
+            } => a
+        "};
+        let right = indoc! {"
+            entity f((a, a, int<4>)) -> uint<4> {
+                let e0: uint<4> = Alias(a)
+            } => e0
+        "};
+        assert_eq!(
+            check(left, right),
+            Err(EquivalenceError::PortMismatch(
+                "a is uint<4> but a is int<4>".to_string()
+            ))
+        );
+    }
+
+    /// The bits of the value of the last statement, computed by bit-blasting
+    fn blasted(statements: &[String]) -> String {
+        let statements = statements
+            .iter()
+            .map(|s| parse_statement(s).unwrap())
+            .collect::<Vec<_>>();
+        let (output, output_type) = match statements.last().unwrap() {
+            spade_mir::Statement::Binding(b) => (b.name.clone(), b.ty.clone()),
+            _ => unreachable!(),
+        };
+        let entity = Entity {
+            name: spade_mir::unit_name::UnitName::_test_from_strs(&["e"]),
+            inputs: vec![],
+            output,
+            output_type,
+            statements,
+            verilog_attrs: vec![],
+        };
+        let mut ctx = Context::new();
+        let design = blast(&mut ctx, &entity).unwrap();
+        let values = ctx.aig.simulate(|_| false);
+        let bits = design.output.iter().rev().map(|bit| {
+            if aig::lit_value(&values, *bit) {
+                '1'
+            } else {
+                '0'
+            }
+        });
+        bits.collect()
+    }
+
+    fn evaluated(statements: &[String]) -> String {
+        let statements = statements
+            .iter()
+            .map(|s| parse_statement(s).unwrap())
+            .collect::<Vec<_>>();
+        eval_statements(&statements).as_string()
+    }
+
+    #[test]
+    fn operators_agree_with_the_evaluator() {
+        let cases: &[&[&str]] = &[
+            &[
+                "const e0: int<4> = -3",
+                "const e1: int<4> = 7",
+                "let e2: int<5> = Add(e0, e1)",
+            ],
+            &[
+                "const e0: int<4> = -8",
+                "const e1: int<4> = 7",
+                "let e2: int<5> = Sub(e0, e1)",
+            ],
+            &["const e0: int<4> = 5", "let e1: int<4> = USub(e0)"],
+            &[
+                "const e0: int<4> = -2",
+                "const e1: bool = true",
+                "let e2: (int<4>, bool) = ConstructTuple(e0, e1)",
+            ],
+            &[
+                "const e0: int<3> = 1",
+                "const e1: int<3> = 2",
+                "let e2: [int<3>; 2] = ConstructArray(e0, e1)",
+            ],
+            &[
+                "const e0: uint<2> = 3",
+                "let e1: enum option [], option [uint<2>], option [] = ConstructEnum(1, 3)(e0)",
+            ],
+            &["const e0: uint<5> = 21", "let e1: uint<5> = Alias(e0)"],
+        ];
+        for case in cases {
+            assert_agrees(&case.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        }
+    }
+
+    /// Checks that bit-blasting the statements gives the value computed by the evaluator.
+    /// Bits which the evaluator considers undefined may have any value
+    fn assert_agrees(statements: &[String]) {
+        let (blasted, evaluated) = (blasted(statements), evaluated(statements));
+        let agrees = blasted.len() == evaluated.len()
+            && blasted
+                .chars()
+                .zip(evaluated.chars())
+                .all(|(b, e)| e == 'X' || b == e);
+        assert!(agrees, "{statements:#?}\n{blasted} != {evaluated}");
+    }
+
+    /// Every value of a type written as `int<N>`, `uint<N>` or `bool`
+    fn all_values(ty: &str) -> Vec<String> {
+        if ty == "bool" {
+            return vec!["false".to_string(), "true".to_string()];
+        }
+        let (signed, width) = match ty.strip_prefix("int<") {
+            Some(rest) => (true, rest),
+            None => (false, ty.strip_prefix("uint<").unwrap()),
+        };
+        let width = width.trim_end_matches('>').parse::<u32>().unwrap();
+        let (min, max) = if signed {
+            (-(1i64 << (width - 1)), 1i64 << (width - 1))
+        } else {
+            (0, 1i64 << width)
+        };
+        (min..max).map(|v| v.to_string()).collect()
+    }
+
+    /// Compares `operator` applied to every combination of values of the `operands` types
+    /// with the evaluator
+    fn check_operator(operator: &str, operands: &[&str], output: &str) {
+        let values = operands.iter().map(|ty| all_values(ty)).collect::<Vec<_>>();
+        for combination in values.iter().multi_cartesian_product() {
+            let mut statements = combination
+                .iter()
+                .zip(operands)
+                .enumerate()
+                .map(|(i, (value, ty))| format!("const e{i}: {ty} = {value}"))
+                .collect::<Vec<_>>();
+            let names = (0..operands.len()).map(|i| format!("e{i}")).join(", ");
+            statements.push(format!(
+                "let e{}: {output} = {operator}({names})",
+                operands.len()
+            ));
+            assert_agrees(&statements);
+        }
+    }
+
+    #[test]
+    fn arithmetic_agrees_with_the_evaluator() {
+        for operator in ["Add", "Sub", "Mul"] {
+            check_operator(operator, &["int<3>", "int<3>"], "int<4>");
+            check_operator(operator, &["int<3>", "int<2>"], "int<6>");
+            check_operator(operator, &["int<3>", "int<3>"], "int<2>");
+        }
+        for operator in ["UnsignedAdd", "UnsignedSub", "UnsignedMul"] {
+            check_operator(operator, &["uint<3>", "uint<3>"], "uint<4>");
+            check_operator(operator, &["uint<3>", "uint<2>"], "uint<6>");
+            check_operator(operator, &["uint<3>", "uint<3>"], "uint<2>");
+        }
+        check_operator("USub", &["int<3>"], "int<3>");
+        check_operator("USub", &["int<3>"], "int<4>");
+    }
+
+    #[test]
+    fn comparisons_agree_with_the_evaluator() {
+        for operator in ["Eq", "NotEq", "Gt", "Lt", "Ge", "Le"] {
+            check_operator(operator, &["int<3>", "int<3>"], "bool");
+            check_operator(operator, &["int<3>", "int<2>"], "bool");
+        }
+        for operator in [
+            "Eq",
+            "NotEq",
+            "UnsignedGt",
+            "UnsignedLt",
+            "UnsignedGe",
+            "UnsignedLe",
+        ] {
+            check_operator(operator, &["uint<3>", "uint<3>"], "bool");
+            check_operator(operator, &["uint<3>", "uint<2>"], "bool");
+        }
+    }
+
+    #[test]
+    fn shifts_agree_with_the_evaluator() {
+        for operator in ["LeftShift", "RightShift"] {
+            check_operator(operator, &["uint<3>", "uint<3>"], "uint<3>");
+            check_operator(operator, &["uint<3>", "uint<2>"], "uint<5>");
+        }
+        check_operator("ArithmeticRightShift", &["int<3>", "uint<3>"], "int<3>");
+        check_operator("ArithmeticRightShift", &["int<3>", "uint<2>"], "int<5>");
+        check_operator("DivPow2", &["int<3>", "uint<3>"], "int<3>");
+        check_operator("DivPow2", &["int<4>", "uint<2>"], "int<4>");
+    }
+
+    #[test]
+    fn logic_agrees_with_the_evaluator() {
+        for operator in ["LogicalAnd", "LogicalOr", "LogicalXor"] {
+            check_operator(operator, &["bool", "bool"], "bool");
+        }
+        for operator in ["LogicalNot", "Not"] {
+            check_operator(operator, &["bool"], "bool");
+        }
+        for operator in ["BitwiseAnd", "BitwiseOr", "BitwiseXor"] {
+            check_operator(operator, &["uint<3>", "uint<3>"], "uint<3>");
+            check_operator(operator, &["uint<3>", "uint<2>"], "uint<4>");
+        }
+        check_operator("BitwiseNot", &["uint<3>"], "uint<3>");
+        check_operator("BitwiseNot", &["uint<3>"], "uint<4>");
+        for operator in ["ReduceAnd", "ReduceOr", "ReduceXor"] {
+            check_operator(operator, &["uint<3>"], "bool");
+        }
+    }
+
+    #[test]
+    fn bit_manipulation_agrees_with_the_evaluator() {
+        check_operator("SignExtend(2, 3)", &["int<3>"], "int<5>");
+        check_operator("ZeroExtend(2)", &["uint<3>"], "uint<5>");
+        check_operator("Truncate", &["uint<3>"], "uint<2>");
+        check_operator("Alias", &["uint<3>"], "uint<3>");
+        check_operator("Concat", &["uint<2>", "uint<2>"], "uint<4>");
+        check_operator("Gray2Bin(3)", &["uint<3>"], "uint<3>");
+        check_operator("Bitreverse", &["uint<3>"], "uint<3>");
+        check_operator("RangeIndexBits(1, 3)", &["uint<3>"], "uint<2>");
+    }
+
+    #[test]
+    fn selection_agrees_with_the_evaluator() {
+        check_operator("Select", &["bool", "uint<2>", "uint<2>"], "uint<2>");
+        check_operator("Match", &["bool", "uint<2>", "bool", "uint<2>"], "uint<2>");
+        // Indices outside the array are undefined
+        for index in all_values("uint<2>") {
+            assert_agrees(&[
+                "const e0: uint<2> = 1".to_string(),
+                "const e1: uint<2> = 2".to_string(),
+                "const e2: uint<2> = 3".to_string(),
+                "let e3: [uint<2>; 3] = ConstructArray(e0, e1, e2)".to_string(),
+                format!("const e4: uint<2> = {index}"),
+                "let e5: uint<2> = IndexArray(e3, e4)".to_string(),
+            ]);
+        }
+    }
+}
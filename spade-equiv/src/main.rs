@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use color_eyre::eyre::{bail, Context, Result};
+
+use spade_equiv::blast::unit_key;
+use spade_equiv::{check_equivalence, Outcome};
+use spade_mir::parser::parse_entities;
+use spade_mir::Entity;
+
+/// Check whether the units in two MIR files, as written by `--mir-output`, describe the
+/// same hardware
+///
+/// Exits with status 1 if any unit is not equivalent, and with status 2 if all checked units
+/// are equivalent but some units were not checked
+#[derive(Parser)]
+#[command(name = "spade-equiv")]
+struct Opt {
+    /// MIR file with the original units
+    left: PathBuf,
+    /// MIR file with the units to compare to the original ones
+    right: PathBuf,
+    /// Only check these units. Can be specified multiple times. By default all units which
+    /// are in both files are checked, skipping and listing the ones which can not be checked
+    #[arg(short = 'u', long = "unit")]
+    units: Vec<String>,
+}
+
+fn read_entities(file: &Path) -> Result<Vec<Entity>> {
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.to_string_lossy()))?;
+    parse_entities(&source).with_context(|| format!("Failed to parse {}", file.to_string_lossy()))
+}
+
+fn find<'a>(entities: &'a [Entity], name: &str) -> Option<&'a Entity> {
+    entities
+        .iter()
+        .find(|e| unit_key(&e.name) == name || e.name.without_escapes() == name)
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let opts = Opt::parse();
+
+    let left = read_entities(&opts.left)?;
+    let right = read_entities(&opts.right)?;
+
+    let explicit = !opts.units.is_empty();
+    // The units which were not checked, along with the reason
+    let mut unchecked = vec![];
+    let units = if explicit {
+        opts.units.clone()
+    } else {
+        for (entities, others, file) in [(&left, &right, "left"), (&right, &left, "right")] {
+            unchecked.extend(
+                entities
+                    .iter()
+                    .map(|e| unit_key(&e.name))
+                    .filter(|name| find(others, name).is_none())
+                    .map(|name| format!("{name}: only in the {file} file")),
+            );
+        }
+        left.iter()
+            .map(|e| unit_key(&e.name))
+            .filter(|name| find(&right, name).is_some())
+            .collect()
+    };
+
+    let mut all_equivalent = true;
+    for unit in units {
+        let (Some(l), Some(r)) = (find(&left, &unit), find(&right, &unit)) else {
+            bail!("No unit named {unit} was found in both files");
+        };
+        match check_equivalence(l, r) {
+            Ok(Outcome::Equivalent) => println!("{unit}: equivalent"),
+            Ok(Outcome::Different(counterexample)) => {
+                all_equivalent = false;
+                println!("{unit}: not equivalent\n{counterexample}")
+            }
+            Err(e) if explicit => {
+                return Err(e).with_context(|| format!("Failed to check {unit}"));
+            }
+            Err(e) => unchecked.push(format!("{unit}: {e}")),
+        }
+    }
+
+    if !unchecked.is_empty() {
+        println!("\n{} units were not checked:", unchecked.len());
+        for reason in &unchecked {
+            println!("  {reason}");
+        }
+    }
+
+    if !all_equivalent {
+        std::process::exit(1)
+    }
+    if !unchecked.is_empty() {
+        std::process::exit(2)
+    }
+    Ok(())
+}
@@ -0,0 +1,456 @@
+//! A small CDCL SAT solver. It uses two watched literals for propagation, learns first
+//! UIP clauses on conflicts, picks decision variables by activity and restarts according
+//! to the Luby sequence. Learned clauses are never deleted, which is fine for the
+//! problem sizes produced by single units.
+
+use std::collections::BinaryHeap;
+
+pub type Var = usize;
+
+/// A variable or its negation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SatLit(usize);
+
+impl SatLit {
+    pub fn new(var: Var, negated: bool) -> Self {
+        SatLit(var * 2 + negated as usize)
+    }
+
+    pub fn var(self) -> Var {
+        self.0 / 2
+    }
+
+    pub fn is_negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl std::ops::Not for SatLit {
+    type Output = SatLit;
+
+    fn not(self) -> SatLit {
+        SatLit(self.0 ^ 1)
+    }
+}
+
+/// The number of conflicts between restarts is this times the next number in the Luby
+/// sequence
+const RESTART_INTERVAL: usize = 100;
+const ACTIVITY_DECAY: f64 = 0.95;
+
+#[derive(Default)]
+pub struct Solver {
+    clauses: Vec<Vec<SatLit>>,
+    /// The clauses watching each literal, indexed by literal
+    watches: Vec<Vec<usize>>,
+    assignment: Vec<Option<bool>>,
+    level: Vec<usize>,
+    /// The clause which implied the assignment of each variable, if any
+    reason: Vec<Option<usize>>,
+    trail: Vec<SatLit>,
+    /// The start of each decision level in the trail
+    trail_limits: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    activity_increment: f64,
+    /// Variables ordered by activity. Entries are not removed when the activity of
+    /// a variable changes, so stale entries are skipped when popping
+    order: BinaryHeap<(u64, Var)>,
+    /// The polarity each variable was last assigned
+    phase: Vec<bool>,
+    /// Set when an empty clause has been added
+    unsatisfiable: bool,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self {
+            activity_increment: 1.,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_var(&mut self) -> Var {
+        let var = self.assignment.len();
+        self.assignment.push(None);
+        self.level.push(0);
+        self.reason.push(None);
+        self.activity.push(0.);
+        self.phase.push(false);
+        self.watches.push(vec![]);
+        self.watches.push(vec![]);
+        self.order.push((0f64.to_bits(), var));
+        var
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.assignment.len()
+    }
+
+    fn value(&self, lit: SatLit) -> Option<bool> {
+        self.assignment[lit.var()].map(|v| v ^ lit.is_negated())
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_limits.len()
+    }
+
+    fn assign(&mut self, lit: SatLit, reason: Option<usize>) {
+        let var = lit.var();
+        self.assignment[var] = Some(!lit.is_negated());
+        self.level[var] = self.decision_level();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Adds a clause. Must be called before [`Solver::solve`]
+    pub fn add_clause(&mut self, lits: impl IntoIterator<Item = SatLit>) {
+        let mut lits = lits.into_iter().collect::<Vec<_>>();
+        lits.sort_by_key(|l| l.index());
+        lits.dedup();
+        if lits.windows(2).any(|w| w[0] == !w[1]) {
+            // Always satisfied
+            return;
+        }
+        lits.retain(|l| self.value(*l) != Some(false));
+        if lits.iter().any(|l| self.value(*l) == Some(true)) {
+            return;
+        }
+        match lits.as_slice() {
+            [] => self.unsatisfiable = true,
+            [lit] => {
+                self.assign(*lit, None);
+                if self.propagate().is_some() {
+                    self.unsatisfiable = true
+                }
+            }
+            _ => {
+                self.attach(lits);
+            }
+        }
+    }
+
+    fn attach(&mut self, lits: Vec<SatLit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[lits[0].index()].push(index);
+        self.watches[lits[1].index()].push(index);
+        self.clauses.push(lits);
+        index
+    }
+
+    /// Propagates the assignments on the trail, returning a conflicting clause if one is found
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let false_lit = !self.trail[self.propagated];
+            self.propagated += 1;
+
+            let watching = std::mem::take(&mut self.watches[false_lit.index()]);
+            let mut kept = Vec::with_capacity(watching.len());
+            let mut conflict = None;
+            for (i, &clause_index) in watching.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watching[i..]);
+                    break;
+                }
+                let clause = &mut self.clauses[clause_index];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if self.assignment[first.var()].map(|v| v ^ first.is_negated()) == Some(true) {
+                    kept.push(clause_index);
+                    continue;
+                }
+                let replacement = (2..clause.len()).find(|&k| {
+                    let lit = clause[k];
+                    self.assignment[lit.var()].map(|v| v ^ lit.is_negated()) != Some(false)
+                });
+                match replacement {
+                    Some(k) => {
+                        clause.swap(1, k);
+                        let new_watch = clause[1];
+                        self.watches[new_watch.index()].push(clause_index);
+                    }
+                    None => {
+                        kept.push(clause_index);
+                        match self.value(first) {
+                            Some(false) => conflict = Some(clause_index),
+                            _ => self.assign(first, Some(clause_index)),
+                        }
+                    }
+                }
+            }
+            self.watches[false_lit.index()] = kept;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, var: Var) {
+        self.activity[var] += self.activity_increment;
+        if self.activity[var] > 1e100 {
+            for activity in &mut self.activity {
+                *activity *= 1e-100;
+            }
+            self.activity_increment *= 1e-100;
+            self.order = (0..self.num_vars())
+                .map(|v| (self.activity[v].to_bits(), v))
+                .collect();
+        } else {
+            self.order.push((self.activity[var].to_bits(), var));
+        }
+    }
+
+    /// Learns a first UIP clause from a conflict. Returns the clause, with the asserting
+    /// literal first, and the level to backtrack to
+    fn analyze(&mut self, conflict: usize) -> (Vec<SatLit>, usize) {
+        let mut seen = vec![false; self.num_vars()];
+        let mut learnt = vec![];
+        let mut pending = 0;
+        let mut clause = conflict;
+        let mut index = self.trail.len();
+        let asserting = loop {
+            for k in 0..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                let var = lit.var();
+                // The implied literal of a reason clause is the one being resolved on
+                if seen[var] || self.value(lit) == Some(true) {
+                    continue;
+                }
+                seen[var] = true;
+                self.bump(var);
+                if self.level[var] == self.decision_level() {
+                    pending += 1;
+                } else if self.level[var] > 0 {
+                    learnt.push(lit);
+                }
+            }
+            let lit = loop {
+                index -= 1;
+                if seen[self.trail[index].var()] {
+                    break self.trail[index];
+                }
+            };
+            pending -= 1;
+            if pending == 0 {
+                break !lit;
+            }
+            clause = self.reason[lit.var()].expect("Implied literal without a reason");
+        };
+        self.activity_increment /= ACTIVITY_DECAY;
+
+        let backtrack_level = learnt
+            .iter()
+            .map(|l| self.level[l.var()])
+            .max()
+            .unwrap_or(0);
+        // The watched literals of the learnt clause must be the asserting literal and a
+        // literal from the backtrack level
+        if let Some(pos) = learnt
+            .iter()
+            .position(|l| self.level[l.var()] == backtrack_level)
+        {
+            learnt.swap(0, pos);
+        }
+        learnt.insert(0, asserting);
+        (learnt, backtrack_level)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let limit = self.trail_limits[level];
+        for lit in self.trail.drain(limit..) {
+            let var = lit.var();
+            self.phase[var] = !lit.is_negated();
+            self.assignment[var] = None;
+            self.reason[var] = None;
+            self.order.push((self.activity[var].to_bits(), var));
+        }
+        self.trail_limits.truncate(level);
+        self.propagated = limit;
+    }
+
+    fn pick_branch_var(&mut self) -> Option<Var> {
+        while let Some((activity, var)) = self.order.pop() {
+            if self.assignment[var].is_none() && activity == self.activity[var].to_bits() {
+                return Some(var);
+            }
+        }
+        // Entries may have been skipped as stale while the variable was assigned
+        (0..self.num_vars()).find(|v| self.assignment[*v].is_none())
+    }
+
+    /// Finds an assignment satisfying all clauses, or returns `None` if there is none
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        if self.unsatisfiable {
+            return None;
+        }
+        let mut restarts = 0;
+        let mut conflicts = 0;
+        let mut restart_limit = RESTART_INTERVAL * luby(restarts);
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.decision_level() == 0 {
+                    self.unsatisfiable = true;
+                    return None;
+                }
+                conflicts += 1;
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                let asserting = learnt[0];
+                if learnt.len() == 1 {
+                    self.assign(asserting, None);
+                } else {
+                    let clause = self.attach(learnt);
+                    self.assign(asserting, Some(clause));
+                }
+            } else {
+                if conflicts >= restart_limit {
+                    restarts += 1;
+                    conflicts = 0;
+                    restart_limit = RESTART_INTERVAL * luby(restarts);
+                    self.backtrack(0);
+                }
+                match self.pick_branch_var() {
+                    Some(var) => {
+                        self.trail_limits.push(self.trail.len());
+                        self.assign(SatLit::new(var, !self.phase[var]), None);
+                    }
+                    None => {
+                        return Some(self.assignment.iter().map(|v| v.unwrap()).collect());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The `i`th element of the Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, ...
+fn luby(i: usize) -> usize {
+    let mut size = 1;
+    let mut power = 1;
+    while size < i + 1 {
+        size = 2 * size + 1;
+        power *= 2;
+    }
+    let mut i = i;
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        power /= 2;
+        i %= size;
+    }
+    power
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(l: i32) -> SatLit {
+        SatLit::new(l.unsigned_abs() as usize - 1, l < 0)
+    }
+
+    fn solver(vars: usize, clauses: &[&[i32]]) -> Solver {
+        let mut solver = Solver::new();
+        for _ in 0..vars {
+            solver.new_var();
+        }
+        for clause in clauses {
+            solver.add_clause(clause.iter().map(|l| lit(*l)));
+        }
+        solver
+    }
+
+    fn satisfies(model: &[bool], clauses: &[&[i32]]) -> bool {
+        clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|l| model[l.unsigned_abs() as usize - 1] == (*l > 0))
+        })
+    }
+
+    #[test]
+    fn luby_sequence() {
+        assert_eq!(
+            (0..15).map(luby).collect::<Vec<_>>(),
+            vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]
+        )
+    }
+
+    #[test]
+    fn satisfiable_problem_gives_model() {
+        let clauses: &[&[i32]] = &[&[1, 2], &[-1, 3], &[-3, -2], &[2, 3, -4], &[4]];
+        let model = solver(4, clauses).solve().unwrap();
+        assert!(satisfies(&model, clauses));
+    }
+
+    #[test]
+    fn contradicting_units_are_unsatisfiable() {
+        assert_eq!(solver(1, &[&[1], &[-1]]).solve(), None);
+    }
+
+    #[test]
+    fn pigeonhole_is_unsatisfiable() {
+        // 5 pigeons in 4 holes. Variable p * 4 + h + 1 is pigeon p in hole h
+        let pigeons = 5;
+        let holes = 4;
+        let var = |p: i32, h: i32| p * holes + h + 1;
+        let mut clauses = vec![];
+        for p in 0..pigeons {
+            clauses.push((0..holes).map(|h| var(p, h)).collect::<Vec<_>>());
+        }
+        for h in 0..holes {
+            for p in 0..pigeons {
+                for q in (p + 1)..pigeons {
+                    clauses.push(vec![-var(p, h), -var(q, h)]);
+                }
+            }
+        }
+        let clauses = clauses.iter().map(|c| c.as_slice()).collect::<Vec<_>>();
+        assert_eq!(solver((pigeons * holes) as usize, &clauses).solve(), None);
+    }
+
+    #[test]
+    fn random_problems_agree_with_brute_force() {
+        // A simple linear congruential generator keeps the test deterministic
+        let mut state = 12345u64;
+        let mut next = |max: u64| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (state >> 33) % max
+        };
+        for _ in 0..200 {
+            let vars = 8;
+            let clauses = (0..35)
+                .map(|_| {
+                    (0..3)
+                        .map(|_| {
+                            let v = next(vars) as i32 + 1;
+                            if next(2) == 0 {
+                                v
+                            } else {
+                                -v
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let clauses = clauses.iter().map(|c| c.as_slice()).collect::<Vec<_>>();
+            let brute_force = (0..1u32 << vars).any(|bits| {
+                let model = (0..vars).map(|v| bits >> v & 1 == 1).collect::<Vec<_>>();
+                satisfies(&model, &clauses)
+            });
+            match solver(vars as usize, &clauses).solve() {
+                Some(model) => assert!(satisfies(&model, &clauses)),
+                None => assert!(!brute_force),
+            }
+        }
+    }
+}
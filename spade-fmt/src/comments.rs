@@ -0,0 +1,191 @@
+//! The lexer used by the parser skips comments. This module builds a token stream
+//! on top of it which keeps the comments, allowing the formatter to put them back
+//! into the formatted code.
+
+use std::ops::Range;
+
+use logos::Logos;
+use spade_common::location_info::{lspan, Loc};
+use spade_diagnostics::Diagnostic;
+use spade_parser::lexer::TokenKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `// ...` or `/// ...`, running to the end of the line
+    Line,
+    /// `/* ... */`
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// The text of the comment including the comment markers, but without the newline
+    /// ending line comments
+    pub text: String,
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lexeme {
+    Token(TokenKind),
+    Comment(Comment),
+}
+
+/// Splits `source` into tokens and comments, in the order they appear in the source
+pub fn lex(source: &str, file_id: usize) -> Result<Vec<(Lexeme, Range<usize>)>, Diagnostic> {
+    let mut result = vec![];
+    let mut lexer = TokenKind::lexer(source);
+    let mut prev_end = 0;
+
+    while let Some(token) = lexer.next() {
+        let span = lexer.span();
+        let kind = token.map_err(|_| {
+            Diagnostic::error(
+                Loc::new((), lspan(span.clone()), file_id),
+                "Lexer error, unexpected symbol",
+            )
+        })?;
+
+        line_comments(source, prev_end..span.start, &mut result);
+
+        if kind == TokenKind::BlockCommentStart {
+            // Comments do not nest, the first `*/` ends the comment
+            let end = loop {
+                match lexer.next() {
+                    Some(Ok(TokenKind::BlockCommentEnd)) => break lexer.span().end,
+                    Some(_) => {}
+                    None => {
+                        return Err(Diagnostic::error(
+                            Loc::new((), lspan(span.clone()), file_id),
+                            "Unterminated block comment",
+                        )
+                        .primary_label("This comment is never closed"))
+                    }
+                }
+            };
+            let comment = Comment {
+                kind: CommentKind::Block,
+                text: source[span.start..end].to_string(),
+                span: span.start..end,
+            };
+            result.push((Lexeme::Comment(comment), span.start..end));
+            prev_end = end;
+        } else if let TokenKind::DocComment(_) = kind {
+            let text = source[span.clone()].trim_end();
+            let end = span.start + text.len();
+            let comment = Comment {
+                kind: CommentKind::Line,
+                text: text.to_string(),
+                span: span.start..end,
+            };
+            result.push((Lexeme::Comment(comment), span.start..end));
+            prev_end = end;
+        } else {
+            result.push((Lexeme::Token(kind), span.clone()));
+            prev_end = span.end;
+        }
+    }
+    line_comments(source, prev_end..source.len(), &mut result);
+
+    Ok(result)
+}
+
+/// Only the comments in `source`, in the order they appear
+pub fn comments(source: &str, file_id: usize) -> Result<Vec<Comment>, Diagnostic> {
+    Ok(lex(source, file_id)?
+        .into_iter()
+        .filter_map(|(lexeme, _)| match lexeme {
+            Lexeme::Comment(comment) => Some(comment),
+            Lexeme::Token(_) => None,
+        })
+        .collect())
+}
+
+/// Finds the line comments in `gap` which is a part of the source skipped by the lexer,
+/// i.e. something which only contains whitespace and line comments
+fn line_comments(source: &str, gap: Range<usize>, result: &mut Vec<(Lexeme, Range<usize>)>) {
+    let mut pos = gap.start;
+    while let Some(offset) = source[pos..gap.end].find("//") {
+        let start = pos + offset;
+        let end = source[start..gap.end]
+            .find('\n')
+            .map(|newline| start + newline)
+            .unwrap_or(gap.end);
+        let comment = Comment {
+            kind: CommentKind::Line,
+            text: source[start..end].trim_end().to_string(),
+            span: start..end,
+        };
+        result.push((Lexeme::Comment(comment), start..end));
+        pos = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn kinds(source: &str) -> Vec<Lexeme> {
+        lex(source, 0)
+            .unwrap()
+            .into_iter()
+            .map(|(lexeme, _)| lexeme)
+            .collect()
+    }
+
+    fn line(text: &str, span: Range<usize>) -> Lexeme {
+        Lexeme::Comment(Comment {
+            kind: CommentKind::Line,
+            text: text.to_string(),
+            span,
+        })
+    }
+
+    #[test]
+    fn line_comments_are_kept() {
+        assert_eq!(
+            kinds("a // b c\n// d\n"),
+            vec![
+                Lexeme::Token(TokenKind::Identifier("a".to_string())),
+                line("// b c", 2..8),
+                line("// d", 9..13),
+            ]
+        )
+    }
+
+    #[test]
+    fn doc_comments_are_line_comments() {
+        assert_eq!(
+            kinds("/// a\n//// b\nc"),
+            vec![
+                line("/// a", 0..5),
+                line("//// b", 6..12),
+                Lexeme::Token(TokenKind::Identifier("c".to_string())),
+            ]
+        )
+    }
+
+    #[test]
+    fn block_comments_are_kept() {
+        assert_eq!(
+            kinds("a /* b\n c */ d"),
+            vec![
+                Lexeme::Token(TokenKind::Identifier("a".to_string())),
+                Lexeme::Comment(Comment {
+                    kind: CommentKind::Block,
+                    text: "/* b\n c */".to_string(),
+                    span: 2..12,
+                }),
+                Lexeme::Token(TokenKind::Identifier("d".to_string())),
+            ]
+        )
+    }
+
+    #[test]
+    fn unterminated_block_comment_is_error() {
+        assert!(lex("a /* b", 0).is_err())
+    }
+}
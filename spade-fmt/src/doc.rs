@@ -0,0 +1,234 @@
+//! A small pretty printer in the style of Wadler's "A prettier printer". The formatter
+//! describes the code as a [`Doc`] and [`Doc::render`] decides where to put line breaks.
+//!
+//! A [`Doc::group`] is printed on a single line if it fits, otherwise all of its
+//! [`Doc::Line`]s are printed as newlines.
+
+use itertools::Itertools;
+
+pub const INDENT: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum Doc {
+    Nil,
+    /// Text which is printed as is. Only contains newlines if it is a block comment
+    Text(String),
+    /// A space, or a newline if the surrounding group is broken
+    Line,
+    /// Nothing, or a newline if the surrounding group is broken
+    SoftLine,
+    /// Always a newline. Forces all surrounding groups to break
+    HardLine,
+    /// Forces all surrounding groups to break without printing anything
+    BreakParent,
+    /// Text which is only printed if the surrounding group is broken
+    IfBreak(String),
+    Concat(Vec<Doc>),
+    /// Indents newlines inside the doc one level further
+    Nest(Box<Doc>),
+    Group {
+        doc: Box<Doc>,
+        /// Set if the doc contains a hard line or break parent
+        must_break: bool,
+    },
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Self {
+        Doc::Text(text.into())
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Self {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    /// Concatenates `docs` with `separator` between each of them
+    pub fn join(docs: impl IntoIterator<Item = Doc>, separator: Doc) -> Self {
+        Doc::Concat(Itertools::intersperse(docs.into_iter(), separator).collect())
+    }
+
+    pub fn nest(self) -> Self {
+        Doc::Nest(Box::new(self))
+    }
+
+    pub fn group(self) -> Self {
+        let must_break = self.forces_break();
+        Doc::Group {
+            doc: Box::new(self),
+            must_break,
+        }
+    }
+
+    fn forces_break(&self) -> bool {
+        match self {
+            Doc::HardLine | Doc::BreakParent => true,
+            Doc::Text(text) => text.contains('\n'),
+            Doc::Nil | Doc::Line | Doc::SoftLine | Doc::IfBreak(_) => false,
+            Doc::Concat(docs) => docs.iter().any(Doc::forces_break),
+            Doc::Nest(doc) => doc.forces_break(),
+            Doc::Group { must_break, .. } => *must_break,
+        }
+    }
+
+    /// Renders the doc, breaking groups which do not fit in `width` columns
+    pub fn render(&self, width: usize) -> String {
+        let mut out = String::new();
+        let mut column = 0;
+        let mut stack = vec![(0, Mode::Break, self)];
+
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Nil | Doc::BreakParent => {}
+                Doc::Text(text) => {
+                    out.push_str(text);
+                    column = match text.rfind('\n') {
+                        // Multi line block comments
+                        Some(newline) => text[newline + 1..].chars().count(),
+                        None => column + text.chars().count(),
+                    };
+                }
+                Doc::IfBreak(text) => {
+                    if mode == Mode::Break {
+                        out.push_str(text);
+                        column += text.chars().count();
+                    }
+                }
+                Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                    if let Doc::Line = doc {
+                        out.push(' ');
+                        column += 1;
+                    }
+                }
+                Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                    let trimmed = out.trim_end_matches(' ').len();
+                    out.truncate(trimmed);
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                    column = indent;
+                }
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|d| (indent, mode, d))),
+                Doc::Nest(doc) => stack.push((indent + INDENT, mode, doc)),
+                Doc::Group { doc, must_break } => {
+                    let mode = if mode == Mode::Flat
+                        || (!must_break && fits(width as isize - column as isize, doc, &stack))
+                    {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                    stack.push((indent, mode, doc))
+                }
+            }
+        }
+
+        let mut lines = out.lines().map(str::trim_end).collect::<Vec<_>>();
+        while lines.last() == Some(&"") {
+            lines.pop();
+        }
+        lines.into_iter().map(|line| format!("{line}\n")).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Returns true if `doc` printed flat, followed by the content of `rest` up to the next
+/// newline, fits in `width` columns
+fn fits(mut width: isize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut rest = rest.iter().rev();
+    let mut stack = vec![(Mode::Flat, doc)];
+
+    loop {
+        if width < 0 {
+            return false;
+        }
+        let (mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some((_, mode, doc)) => (*mode, *doc),
+                None => return true,
+            },
+        };
+
+        match doc {
+            Doc::Nil | Doc::BreakParent => {}
+            Doc::Text(text) => match text.split_once('\n') {
+                Some((first_line, _)) => return width >= first_line.chars().count() as isize,
+                None => width -= text.chars().count() as isize,
+            },
+            Doc::IfBreak(text) => {
+                if mode == Mode::Break {
+                    width -= text.chars().count() as isize
+                }
+            }
+            Doc::Line if mode == Mode::Flat => width -= 1,
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|d| (mode, d))),
+            Doc::Nest(doc) => stack.push((mode, doc)),
+            Doc::Group { doc, must_break } => {
+                let mode = if *must_break { Mode::Break } else { mode };
+                stack.push((mode, doc))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list() -> Doc {
+        Doc::concat([
+            Doc::text("f("),
+            Doc::concat([
+                Doc::SoftLine,
+                Doc::join(
+                    ["aaaa", "bbbb", "cccc"].map(Doc::text),
+                    Doc::concat([Doc::text(","), Doc::Line]),
+                ),
+                Doc::IfBreak(",".to_string()),
+            ])
+            .nest(),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ])
+        .group()
+    }
+
+    #[test]
+    fn groups_which_fit_are_flat() {
+        assert_eq!(list().render(100), "f(aaaa, bbbb, cccc)\n")
+    }
+
+    #[test]
+    fn groups_which_do_not_fit_are_broken() {
+        assert_eq!(
+            list().render(10),
+            "f(\n    aaaa,\n    bbbb,\n    cccc,\n)\n"
+        )
+    }
+
+    #[test]
+    fn hard_lines_break_groups() {
+        let doc = Doc::concat([list(), Doc::text("x"), Doc::HardLine]).group();
+        assert_eq!(
+            Doc::concat([Doc::text("{"), doc]).group().render(100),
+            "{f(aaaa, bbbb, cccc)x\n"
+        );
+        assert_eq!(
+            Doc::concat([
+                Doc::text("g("),
+                Doc::SoftLine,
+                Doc::BreakParent,
+                Doc::text(")")
+            ])
+            .group()
+            .render(100),
+            "g(\n)\n"
+        );
+    }
+}
@@ -0,0 +1,404 @@
+//! A formatter for Spade code. The code is parsed with the normal parser, and the
+//! resulting AST is printed back in a canonical layout. The comments, which the parser
+//! skips, are lexed separately and put back between the nodes they were next to.
+
+mod comments;
+mod doc;
+mod printer;
+
+use codespan::Span;
+use logos::Logos;
+use spade_ast::ModuleBody;
+use spade_common::location_info::Loc;
+use spade_diagnostics::Diagnostic;
+use spade_parser::{lexer::TokenKind, Parser};
+
+/// The line width the formatter tries to stay within
+pub const WIDTH: usize = 100;
+
+fn parse(source: &str, file_id: usize) -> Result<ModuleBody, Diagnostic> {
+    Parser::new(TokenKind::lexer(source), file_id).top_level_module_body()
+}
+
+/// The comments of `source` without trailing whitespace, which the formatter removes
+fn comment_texts(source: &str, file_id: usize) -> Result<Vec<String>, Diagnostic> {
+    Ok(comments::comments(source, file_id)?
+        .into_iter()
+        .map(|comment| {
+            comment
+                .text
+                .lines()
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect())
+}
+
+/// Formats the code in `source`. Returns an error if `source` does not parse.
+///
+/// As a safety net, the formatted code is parsed again and compared to the original
+/// code. If the code or its comments changed, a bug is reported instead of returning
+/// code which does something else
+pub fn format(source: &str, file_id: usize) -> Result<String, Diagnostic> {
+    let comments = comments::comments(source, file_id)?;
+    let ast = parse(source, file_id)?;
+
+    let doc = printer::Printer::new(source, comments).module_body(&ast);
+    let result = doc.render(WIDTH);
+
+    let whole_file = Loc::new((), Span::new(0, source.len() as u32), file_id);
+    match parse(&result, file_id) {
+        Ok(new_ast) if new_ast == ast => {}
+        Ok(_) => {
+            return Err(Diagnostic::bug(
+                whole_file,
+                "Formatting changed the meaning of the code",
+            ))
+        }
+        Err(_) => {
+            return Err(Diagnostic::bug(
+                whole_file,
+                "Formatting produced code which does not parse",
+            ))
+        }
+    }
+    if comment_texts(&result, file_id)? != comment_texts(source, file_id)? {
+        return Err(Diagnostic::bug(
+            whole_file,
+            "Formatting lost or reordered comments",
+        ));
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn check(input: &str, expected: &str) {
+        let formatted = format(input, 0).unwrap_or_else(|e| panic!("{e:?}"));
+        assert_eq!(formatted, expected);
+        let reformatted = format(&formatted, 0).unwrap_or_else(|e| panic!("{e:?}"));
+        assert_eq!(reformatted, formatted, "Formatting is not idempotent");
+    }
+
+    fn check_unchanged(code: &str) {
+        check(code, code)
+    }
+
+    #[test]
+    fn units_are_formatted() {
+        check(
+            "fn add(a:int<8>,b :int<8>)->int<9>{a+b}",
+            indoc! {"
+                fn add(a: int<8>, b: int<8>) -> int<9> {
+                    a + b
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn statements_are_formatted() {
+        check(
+            indoc! {"
+                entity counter(clk: clock, rst: bool) -> uint<8> {
+                    reg(clk) x: uint<8> reset(rst: 0) = trunc(x+1);
+                    let (a,b)=(x,  0x10u8);
+                    decl y;
+                    let y = if a==b {a} else {b};
+                    set p = match y { 0 => true, _=>false };
+                    x
+                }
+            "},
+            indoc! {"
+                entity counter(clk: clock, rst: bool) -> uint<8> {
+                    reg(clk) x: uint<8> reset(rst: 0) = trunc(x + 1);
+                    let (a, b) = (x, 0x10u8);
+                    decl y;
+                    let y = if a == b { a } else { b };
+                    set p = match y {
+                        0 => true,
+                        _ => false,
+                    };
+                    x
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn pipeline_stages_are_indented() {
+        check(
+            indoc! {"
+                pipeline(2) p(clk: clock, x: int<8>) -> int<8> {
+                let a = x;
+                reg;
+                'second
+                let b = stage(-1).a;
+                reg*1;
+                b
+                }
+            "},
+            indoc! {"
+                pipeline(2) p(clk: clock, x: int<8>) -> int<8> {
+                        let a = x;
+                    reg;
+                        'second
+                        let b = stage(-1).a;
+                    reg*1;
+                        b
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn needed_parentheses_are_kept() {
+        check_unchanged(indoc! {"
+            fn f(a: int<8>, b: int<8>) -> int<8> {
+                let x = (a + b) * a;
+                let y = a - (b - a);
+                let z = (if true { a } else { b }) + a;
+                let w = (a `add` b) `add` a;
+                let v = -(a + b);
+                let u = (a + b).x;
+                a
+            }
+        "})
+    }
+
+    #[test]
+    fn redundant_parentheses_are_removed() {
+        check(
+            "fn f(a: int<8>, b: int<8>) -> int<8> { ((a * b)) + ((a)) + ((-a)) }",
+            indoc! {"
+                fn f(a: int<8>, b: int<8>) -> int<8> {
+                    (a * b) + a + -a
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn if_branches_are_broken_together() {
+        check(
+            indoc! {"
+                fn f(a: bool, b: int<8>) -> int<8> {
+                    let x = if a { b } else { some_function_with_a_long_name(b, second_argument_value, third_argument) };
+                    if a {b} else if !a {-b} else {0}
+                }
+            "},
+            indoc! {"
+                fn f(a: bool, b: int<8>) -> int<8> {
+                    let x = if a {
+                        b
+                    } else {
+                        some_function_with_a_long_name(b, second_argument_value, third_argument)
+                    };
+                    if a {
+                        b
+                    } else if !a {
+                        -b
+                    } else {
+                        0
+                    }
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn long_lists_are_broken() {
+        check(
+            indoc! {"
+                fn f() -> int<8> {
+                    some_function_with_a_long_name(first_argument_value, second_argument_value, third_argument_value)
+                }
+            "},
+            indoc! {"
+                fn f() -> int<8> {
+                    some_function_with_a_long_name(
+                        first_argument_value,
+                        second_argument_value,
+                        third_argument_value,
+                    )
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn types_impls_and_traits_are_formatted() {
+        check(
+            indoc! {"
+                struct port P<T> {a: &T, b: &mut T}
+                enum E<T> {A, B{value:T}}
+                trait Tr { fn f(self) -> bool; }
+                impl<T> Tr for E<T> where T: Tr { fn f(self) -> bool { true } }
+                use lib::x as y;
+                mod m { fn g() -> bool __builtin__ }
+                $config X = 5
+            "},
+            indoc! {"
+                struct port P<T> {
+                    a: &T,
+                    b: &mut T,
+                }
+                enum E<T> {
+                    A,
+                    B{value: T},
+                }
+                trait Tr {
+                    fn f(self) -> bool;
+                }
+                impl<T> Tr for E<T>
+                    where T: Tr
+                {
+                    fn f(self) -> bool {
+                        true
+                    }
+                }
+                use lib::x as y;
+                mod m {
+                    fn g() -> bool __builtin__
+                }
+                $config X = 5
+            "},
+        )
+    }
+
+    #[test]
+    fn comptime_blocks_are_formatted() {
+        check(
+            indoc! {"
+                $config A = 1
+                fn f() -> bool {
+                    $if A == 1 { let x = true; } $else { let x = false; }
+                    x
+                }
+            "},
+            indoc! {"
+                $config A = 1
+                fn f() -> bool {
+                    $if A == 1 {
+                        let x = true;
+                    } $else {
+                        let x = false;
+                    }
+                    x
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn attributes_are_formatted() {
+        check_unchanged(indoc! {"
+            #[no_mangle]
+            #[verilog_attrs(keep, max_fanout = 4)]
+            entity e(#[no_mangle] clk: clock) -> bool {
+                #[fsm]
+                reg(clk) x = x;
+                true
+            }
+        "})
+    }
+
+    #[test]
+    fn comments_are_preserved() {
+        check(
+            indoc! {"
+                // A function
+                fn f(
+                    a: bool, // The first argument
+                    b: bool,
+                ) -> bool {
+                    /* before */ let x = a; // after
+
+
+                    // Last
+                    x
+                    // End of block
+                }
+                // End of file
+            "},
+            indoc! {"
+                // A function
+                fn f(
+                    a: bool, // The first argument
+                    b: bool,
+                ) -> bool {
+                    /* before */
+                    let x = a; // after
+
+                    // Last
+                    x
+                    // End of block
+                }
+                // End of file
+            "},
+        )
+    }
+
+    #[test]
+    fn doc_comments_are_preserved() {
+        check_unchanged(indoc! {"
+            /// A struct
+            #[no_mangle]
+            /// with an attribute
+            struct S {
+                /// A field
+                a: bool,
+            }
+            enum E {
+                /// A variant
+                A,
+            }
+            #[no_mangle]
+            /// A function
+            fn f(
+                /// An argument
+                a: bool,
+            ) -> bool {
+                a
+            }
+        "})
+    }
+
+    #[test]
+    fn parse_errors_are_reported() {
+        assert!(format("fn f( -> bool {}", 0).is_err())
+    }
+
+    macro_rules! idempotence_tests {
+        ($($name:ident: $file:expr),* $(,)?) => {
+            $(
+                #[test]
+                fn $name() {
+                    let source = include_str!($file);
+                    let formatted = format(source, 0).unwrap_or_else(|e| panic!("{e:?}"));
+                    let reformatted = format(&formatted, 0).unwrap_or_else(|e| panic!("{e:?}"));
+                    assert_eq!(reformatted, formatted);
+                }
+            )*
+        };
+    }
+
+    idempotence_tests! {
+        prelude_is_idempotent: "../../spade-compiler/prelude/prelude.spade",
+        cdc_is_idempotent: "../../spade-compiler/stdlib/cdc.spade",
+        conv_is_idempotent: "../../spade-compiler/stdlib/conv.spade",
+        io_is_idempotent: "../../spade-compiler/stdlib/io.spade",
+        mem_is_idempotent: "../../spade-compiler/stdlib/mem.spade",
+        ops_is_idempotent: "../../spade-compiler/stdlib/ops.spade",
+        option_is_idempotent: "../../spade-compiler/stdlib/option.spade",
+        ports_is_idempotent: "../../spade-compiler/stdlib/ports.spade",
+        stream_is_idempotent: "../../spade-compiler/stdlib/stream.spade",
+    }
+}
@@ -0,0 +1,1741 @@
+//! Turns the AST of a file back into code. The structure of the code is taken from the
+//! AST, while integer literals are copied from the source to keep their base and size
+//! suffix. Comments are not part of the AST, so they are put back in front of, or after,
+//! the statement, item or list element they are next to in the source.
+
+use std::ops::Range;
+
+use logos::Logos;
+use num::{BigInt, Signed};
+use spade_ast::comptime::{ComptimeCondOp, ComptimeCondition, MaybeComptime};
+use spade_ast::{
+    ArgumentList, ArgumentPattern, Attribute, AttributeList, BinaryOperator, BitLiteral, Block,
+    CallKind, ClockEdge, Enum, Expression, ImplBlock, Implication, Item, MemoryInitFormat, Module,
+    ModuleBody, NamedArgument, NamedTurbofish, Parameter, ParameterList, Pattern,
+    PipelineStageReference, Property, PropertyKind, Register, Statement, Struct, TraitDef,
+    TraitSpec, TurbofishInner, TypeDeclKind, TypeDeclaration, TypeExpression, TypeParam, TypeSpec,
+    UnaryOperator, Unit, UnitHead, UnitKind, VerilogParameterValue, WhereClause,
+};
+use spade_common::location_info::Loc;
+use spade_common::name::Path;
+use spade_parser::lexer::TokenKind;
+use spade_parser::{binop_binding_power, OpBindingPower};
+
+use crate::comments::{Comment, CommentKind};
+use crate::doc::Doc;
+
+fn start<T>(loc: &Loc<T>) -> usize {
+    loc.span.start().to_usize()
+}
+
+fn end<T>(loc: &Loc<T>) -> usize {
+    loc.span.end().to_usize()
+}
+
+fn range<T>(loc: &Loc<T>) -> Range<usize> {
+    start(loc)..end(loc)
+}
+
+/// The position of the closing delimiter of a node whose location includes its delimiters
+fn close<T>(loc: &Loc<T>) -> usize {
+    end(loc).saturating_sub(1)
+}
+
+fn text(text: impl Into<String>) -> Doc {
+    Doc::text(text)
+}
+
+fn parens(doc: Doc) -> Doc {
+    Doc::concat([text("("), doc, text(")")])
+}
+
+/// How tightly an expression holds together, used to decide where parentheses are needed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    /// `$if` expressions, which are only allowed where a full expression is expected
+    Comptime,
+    /// Custom infix operators, ``a `f` b``
+    Infix,
+    Binary(OpBindingPower),
+    Prefix,
+    Atom,
+}
+
+/// The callee, turbofish, left hand side and right hand side of ``a `f` b``
+type InfixCall<'a> = (
+    &'a Loc<Path>,
+    &'a Option<Loc<TurbofishInner>>,
+    &'a Loc<Expression>,
+    &'a Loc<Expression>,
+);
+
+/// Custom infix operators are parsed into calls. They are told apart from normal calls
+/// by the callee being located after the first argument
+fn infix_call(expr: &Expression) -> Option<InfixCall<'_>> {
+    match expr {
+        Expression::Call {
+            kind: CallKind::Function,
+            callee,
+            args,
+            turbofish,
+        } => match &args.inner {
+            ArgumentList::Positional(args)
+                if args.len() == 2 && start(callee) > start(&args[0]) =>
+            {
+                Some((callee, turbofish, &args[0], &args[1]))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn precedence(expr: &Expression) -> Precedence {
+    match expr {
+        Expression::Comptime(_) => Precedence::Comptime,
+        Expression::BinaryOperator(_, op, _) => Precedence::Binary(binop_binding_power(op)),
+        // The operand of `*` is a full expression, so nothing can be in between the two
+        Expression::UnaryOperator(UnaryOperator::Dereference, _) => Precedence::Atom,
+        Expression::UnaryOperator(_, _) => Precedence::Prefix,
+        Expression::IntLiteral(lit) if lit.is_negative() => Precedence::Prefix,
+        _ if infix_call(expr).is_some() => Precedence::Infix,
+        _ => Precedence::Atom,
+    }
+}
+
+/// Returns true if an operator or suffix following `expr` would be parsed as part of its
+/// last operand, like the `+ 1` in `if c {a} else {b} + 1`
+fn is_right_open(expr: &Expression) -> bool {
+    match expr {
+        Expression::If(_, _, _) | Expression::UnaryOperator(UnaryOperator::Dereference, _) => true,
+        Expression::UnaryOperator(_, operand) => is_right_open(operand),
+        Expression::BinaryOperator(_, _, rhs) => is_right_open(rhs),
+        _ => infix_call(expr)
+            .map(|(_, _, _, rhs)| is_right_open(rhs))
+            .unwrap_or(false),
+    }
+}
+
+fn binop_str(op: &BinaryOperator) -> &'static str {
+    match op {
+        BinaryOperator::Add => "+",
+        BinaryOperator::Sub => "-",
+        BinaryOperator::Mul => "*",
+        BinaryOperator::Div => "/",
+        BinaryOperator::Mod => "%",
+        BinaryOperator::Equals => "==",
+        BinaryOperator::NotEquals => "!=",
+        BinaryOperator::Lt => "<",
+        BinaryOperator::Gt => ">",
+        BinaryOperator::Le => "<=",
+        BinaryOperator::Ge => ">=",
+        BinaryOperator::LogicalAnd => "&&",
+        BinaryOperator::LogicalOr => "||",
+        BinaryOperator::LogicalXor => "^^",
+        BinaryOperator::LeftShift => "<<",
+        BinaryOperator::RightShift => ">>",
+        BinaryOperator::ArithmeticRightShift => ">>>",
+        BinaryOperator::BitwiseAnd => "&",
+        BinaryOperator::BitwiseOr => "|",
+        BinaryOperator::BitwiseXor => "^",
+    }
+}
+
+/// The first token of `doc` when it is printed
+fn first_token(doc: &Doc) -> Option<TokenKind> {
+    TokenKind::lexer(&doc.render(1000))
+        .next()
+        .and_then(Result::ok)
+}
+
+fn comment_doc(comment: &Comment) -> Doc {
+    match comment.kind {
+        // Nothing can follow a line comment on the same line
+        CommentKind::Line => Doc::concat([text(&comment.text), Doc::BreakParent]),
+        CommentKind::Block => text(&comment.text),
+    }
+}
+
+pub struct Printer<'a> {
+    source: &'a str,
+    comments: Vec<Comment>,
+    /// The first comment which has not been printed yet
+    next_comment: usize,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(source: &'a str, comments: Vec<Comment>) -> Self {
+        Self {
+            source,
+            comments,
+            next_comment: 0,
+        }
+    }
+
+    fn take_comment_before(&mut self, pos: usize) -> Option<Comment> {
+        let comment = self
+            .comments
+            .get(self.next_comment)
+            .filter(|c| c.span.start < pos)
+            .cloned();
+        if comment.is_some() {
+            self.next_comment += 1;
+        }
+        comment
+    }
+
+    /// Returns true if there is an empty line right before `pos` which is not at the start
+    /// of a block or the file
+    fn blank_line_before(&self, pos: usize) -> bool {
+        let before = &self.source[..pos];
+        let code = before.trim_end();
+        before[code.len()..].matches('\n').count() >= 2
+            && !code.is_empty()
+            && !code.ends_with(['{', '(', '['])
+    }
+
+    /// Comments in front of an element starting at `pos`, each on its own line
+    fn leading_comments(&mut self, pos: usize, keep_blank_lines: bool) -> Doc {
+        let mut result = vec![];
+        while let Some(comment) = self.take_comment_before(pos) {
+            if keep_blank_lines && self.blank_line_before(comment.span.start) {
+                result.push(Doc::HardLine)
+            }
+            result.push(text(comment.text));
+            result.push(Doc::HardLine);
+        }
+        if keep_blank_lines && self.blank_line_before(pos) {
+            result.push(Doc::HardLine)
+        }
+        Doc::concat(result)
+    }
+
+    /// Comments inside an element ending at `end` which have not been printed yet, and
+    /// comments after it on the same line
+    fn trailing_comments(&mut self, end: usize) -> Doc {
+        let mut result = vec![];
+        let mut prev = end;
+        let mut after_line_comment = false;
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            let inside = comment.span.start < end;
+            let same_line = comment.span.start >= prev
+                && self.source[prev..comment.span.start]
+                    .chars()
+                    .all(|c| matches!(c, ' ' | '\t' | ',' | ';'));
+            if (after_line_comment || !same_line) && !inside {
+                break;
+            }
+            if after_line_comment {
+                result.push(Doc::HardLine)
+            }
+            result.push(text(" "));
+            result.push(comment_doc(comment));
+            after_line_comment = comment.kind == CommentKind::Line;
+            prev = prev.max(comment.span.end);
+            self.next_comment += 1;
+        }
+        Doc::concat(result)
+    }
+
+    /// Comments before the closing delimiter at `close`, each on its own line
+    fn dangling_comments(&mut self, close: usize) -> Doc {
+        let mut result = vec![];
+        while let Some(comment) = self.take_comment_before(close) {
+            result.push(Doc::HardLine);
+            if self.blank_line_before(comment.span.start) {
+                result.push(Doc::HardLine)
+            }
+            result.push(comment_doc(&comment));
+        }
+        Doc::concat(result)
+    }
+
+    /// An element on its own line with the comments around it
+    fn line(&mut self, span: Range<usize>, element: impl FnOnce(&mut Self) -> Doc) -> Doc {
+        Doc::concat([
+            Doc::HardLine,
+            self.leading_comments(span.start, true),
+            element(self),
+            self.trailing_comments(span.end),
+        ])
+    }
+
+    /// `{`, followed by `lines` which are expected to start with a newline, and `}`
+    fn braces(&mut self, lines: Vec<Doc>, close: usize) -> Doc {
+        let before = self.next_comment;
+        let dangling = self.dangling_comments(close);
+        if lines.is_empty() && self.next_comment == before {
+            return text("{}");
+        }
+        Doc::concat([
+            text("{"),
+            Doc::concat([Doc::concat(lines), dangling]).nest(),
+            Doc::HardLine,
+            text("}"),
+        ])
+    }
+
+    /// A comma separated list which is printed on one line if it fits, otherwise with one
+    /// element per line and a trailing comma
+    fn list(
+        &mut self,
+        open: &str,
+        spans: Vec<Range<usize>>,
+        mut element: impl FnMut(&mut Self, usize) -> Doc,
+        close: &str,
+        close_pos: Option<usize>,
+    ) -> Doc {
+        let count = spans.len();
+        let mut elements = vec![];
+        for (i, span) in spans.into_iter().enumerate() {
+            elements.push(Doc::concat([
+                self.leading_comments(span.start, false),
+                element(self, i),
+                if i + 1 == count {
+                    Doc::IfBreak(",".to_string())
+                } else {
+                    text(",")
+                },
+                self.trailing_comments(span.end),
+            ]));
+        }
+        let before = self.next_comment;
+        let dangling = close_pos
+            .map(|pos| self.dangling_comments(pos))
+            .unwrap_or(Doc::Nil);
+        if count == 0 && self.next_comment == before {
+            return text(format!("{open}{close}"));
+        }
+        Doc::concat([
+            text(open),
+            Doc::concat([
+                if count == 0 { Doc::Nil } else { Doc::SoftLine },
+                Doc::join(elements, Doc::Line),
+                dangling,
+            ])
+            .nest(),
+            Doc::SoftLine,
+            text(close),
+        ])
+        .group()
+    }
+
+    /// Returns true if the code at `span` is surrounded by a pair of parentheses
+    fn parenthesized(&self, span: Range<usize>) -> bool {
+        let open = self.source[..span.start].trim_end();
+        if !open.ends_with('(') {
+            return false;
+        }
+        let open = open.len() - 1;
+        let mut depth = 0;
+        for (offset, c) in self.source[open..].char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return span.end <= open + offset
+                            && self.source[span.end..open + offset].trim().is_empty();
+                    }
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// An integer literal as written in the source, which keeps its base and size suffix
+    fn int_literal(&self, span: Range<usize>, value: &BigInt) -> Doc {
+        let mut lexer = TokenKind::lexer(&self.source[span]);
+        let mut digits = None;
+        while let Some(token) = lexer.next() {
+            if token.map(|t| t.is_integer()).unwrap_or(false) {
+                digits = Some(lexer.slice())
+            }
+        }
+        match digits {
+            Some(digits) if value.is_negative() => text(format!("-{digits}")),
+            Some(digits) => text(digits),
+            None => text(value.to_string()),
+        }
+    }
+
+    pub fn module_body(&mut self, body: &ModuleBody) -> Doc {
+        let mut result = vec![];
+        for (i, item) in body.members.iter().enumerate() {
+            let mut line = self.line(item_range(item), |s| s.item(item));
+            if i == 0 {
+                // The file should not start with an empty line
+                if let Doc::Concat(docs) = &mut line {
+                    docs.remove(0);
+                }
+            }
+            result.push(line);
+        }
+        while let Some(comment) = self.take_comment_before(self.source.len()) {
+            if !result.is_empty() {
+                result.push(Doc::HardLine);
+                if self.blank_line_before(comment.span.start) {
+                    result.push(Doc::HardLine)
+                }
+            }
+            result.push(comment_doc(&comment));
+        }
+        Doc::concat(result)
+    }
+
+    fn item(&mut self, item: &Item) -> Doc {
+        match item {
+            Item::Unit(unit) => self.unit(unit),
+            Item::TraitDef(def) => self.trait_def(def),
+            Item::Type(decl) => self.type_declaration(decl),
+            Item::Module(module) => self.module(module),
+            Item::Use(u) => Doc::concat([
+                text("use "),
+                text(u.path.to_string()),
+                match &u.alias {
+                    Some(alias) => text(format!(" as {alias}")),
+                    None => Doc::Nil,
+                },
+                text(";"),
+            ]),
+            Item::Config(config) => Doc::concat([
+                text(format!("$config {} = ", config.name)),
+                self.int_literal(range(&config.val), &config.val),
+            ]),
+            Item::ImplBlock(block) => self.impl_block(block),
+        }
+    }
+
+    fn module(&mut self, module: &Loc<Module>) -> Doc {
+        let lines = module
+            .body
+            .members
+            .iter()
+            .map(|item| self.line(item_range(item), |s| s.item(item)))
+            .collect();
+        Doc::concat([
+            text(format!("mod {} ", module.name)),
+            self.braces(lines, close(&module.body)),
+        ])
+    }
+
+    fn unit(&mut self, unit: &Loc<Unit>) -> Doc {
+        let attributes = Doc::concat([
+            self.attribute_lines(&unit.head.attributes),
+            // Doc comments between the attributes and the unit
+            self.leading_comments(start(&unit.head.unit_kind), false),
+        ]);
+        let head = self.unit_head(&unit.head);
+        let where_clauses = self.where_clauses(&unit.head.where_clauses);
+        let has_where_clauses = !unit.head.where_clauses.is_empty();
+
+        let body = match &unit.body {
+            Some(body) => {
+                let body = match &body.inner {
+                    Expression::Block(block) => {
+                        self.block(block, range(body), true, unit.head.unit_kind.is_pipeline())
+                    }
+                    _ => self.expr(body),
+                };
+                let separator = if has_where_clauses {
+                    Doc::HardLine
+                } else {
+                    text(" ")
+                };
+                return Doc::concat([attributes, head, where_clauses, separator, body]);
+            }
+            None => Doc::concat([Doc::Line, text("__builtin__")]).nest(),
+        };
+
+        if has_where_clauses {
+            Doc::concat([
+                attributes,
+                head,
+                where_clauses,
+                Doc::concat([Doc::HardLine, text("__builtin__")]).nest(),
+            ])
+        } else {
+            Doc::concat([attributes, Doc::concat([head, body]).group()])
+        }
+    }
+
+    fn unit_head(&mut self, head: &UnitHead) -> Doc {
+        let kind = match &head.unit_kind.inner {
+            UnitKind::Function => text("fn"),
+            UnitKind::Entity => text("entity"),
+            UnitKind::Pipeline(depth) => Doc::concat([
+                text("pipeline("),
+                self.maybe_comptime_type_expr(depth),
+                text(")"),
+            ]),
+        };
+        Doc::concat([
+            kind,
+            text(format!(" {}", head.name)),
+            self.generics(&head.type_params),
+            self.parameters(&head.inputs),
+            match &head.output_type {
+                Some(ty) => Doc::concat([text(" -> "), self.type_spec(ty)]),
+                None => Doc::Nil,
+            },
+        ])
+    }
+
+    fn parameters(&mut self, params: &Loc<ParameterList>) -> Doc {
+        let self_ = params.self_.iter().map(range);
+        let args = params
+            .args
+            .iter()
+            .map(|(_, attrs, name, ty)| attrs.0.first().map(start).unwrap_or(start(name))..end(ty));
+        let spans = self_.chain(args).collect();
+        let offset = params.self_.iter().count();
+        self.list(
+            "(",
+            spans,
+            |s, i| match i.checked_sub(offset) {
+                Some(i) => s.parameter(&params.args[i]),
+                None => text("self"),
+            },
+            ")",
+            Some(close(params)),
+        )
+    }
+
+    fn parameter(&mut self, (_, attrs, name, ty): &Parameter) -> Doc {
+        let attrs = attrs
+            .0
+            .iter()
+            .map(|attr| Doc::concat([self.attribute(attr), text(" ")]))
+            .collect::<Vec<_>>();
+        Doc::concat([
+            Doc::concat(attrs),
+            text(format!("{name}: ")),
+            self.type_spec(ty),
+        ])
+    }
+
+    fn generics(&mut self, params: &Option<Loc<Vec<Loc<TypeParam>>>>) -> Doc {
+        match params {
+            Some(params) => self.list(
+                "<",
+                params.iter().map(range).collect(),
+                |s, i| s.type_param(&params[i]),
+                ">",
+                Some(close(params)),
+            ),
+            None => Doc::Nil,
+        }
+    }
+
+    fn type_param(&mut self, param: &Loc<TypeParam>) -> Doc {
+        match &param.inner {
+            TypeParam::TypeName { name, traits } if traits.is_empty() => text(name.to_string()),
+            TypeParam::TypeName { name, traits } => {
+                Doc::concat([text(format!("{name}: ")), self.trait_bounds(traits)])
+            }
+            TypeParam::TypeWithMeta { meta, name } => text(format!("#{meta} {name}")),
+        }
+    }
+
+    fn trait_bounds(&mut self, traits: &[Loc<TraitSpec>]) -> Doc {
+        let traits = traits
+            .iter()
+            .map(|t| self.trait_spec(t))
+            .collect::<Vec<_>>();
+        Doc::join(traits, text(" + "))
+    }
+
+    fn trait_spec(&mut self, spec: &TraitSpec) -> Doc {
+        Doc::concat([
+            text(spec.path.to_string()),
+            self.type_args(&spec.type_params),
+        ])
+    }
+
+    fn type_args(&mut self, args: &Option<Loc<Vec<Loc<TypeExpression>>>>) -> Doc {
+        match args {
+            Some(args) => self.list(
+                "<",
+                args.iter().map(range).collect(),
+                |s, i| s.type_expr(&args[i]),
+                ">",
+                Some(close(args)),
+            ),
+            None => Doc::Nil,
+        }
+    }
+
+    /// Where clauses on a line of their own, after the head of a unit, trait or impl
+    fn where_clauses(&mut self, clauses: &[WhereClause]) -> Doc {
+        if clauses.is_empty() {
+            return Doc::Nil;
+        }
+        let clauses = clauses
+            .iter()
+            .map(|clause| match clause {
+                WhereClause::GenericInt { target, expression } => Doc::concat([
+                    text(format!("{target}: {{ ")),
+                    self.expr(expression),
+                    text(" }"),
+                ]),
+                WhereClause::TraitBounds { target, traits } => {
+                    Doc::concat([text(format!("{target}: ")), self.trait_bounds(traits)])
+                }
+            })
+            .collect::<Vec<_>>();
+        Doc::concat([
+            Doc::HardLine,
+            text("where "),
+            Doc::join(clauses, Doc::concat([text(","), Doc::Line]))
+                .group()
+                .nest(),
+        ])
+        .nest()
+    }
+
+    fn trait_def(&mut self, def: &Loc<TraitDef>) -> Doc {
+        let head = Doc::concat([
+            text(format!("trait {}", def.name)),
+            self.generics(&def.type_params),
+            self.where_clauses(&def.where_clauses),
+        ]);
+        let separator = if def.where_clauses.is_empty() {
+            text(" ")
+        } else {
+            Doc::HardLine
+        };
+        let methods = def
+            .methods
+            .iter()
+            .map(|method| {
+                self.line(range(method), |s| {
+                    Doc::concat([s.unit_head(method), text(";")])
+                })
+            })
+            .collect();
+        Doc::concat([head, separator, self.braces(methods, close(def))])
+    }
+
+    fn impl_block(&mut self, block: &Loc<ImplBlock>) -> Doc {
+        let head = Doc::concat([
+            text("impl"),
+            self.generics(&block.type_params),
+            text(" "),
+            match &block.r#trait {
+                Some(t) => Doc::concat([self.trait_spec(t), text(" for ")]),
+                None => Doc::Nil,
+            },
+            self.type_spec(&block.target),
+            self.where_clauses(&block.where_clauses),
+        ]);
+        let separator = if block.where_clauses.is_empty() {
+            text(" ")
+        } else {
+            Doc::HardLine
+        };
+        let units = block
+            .units
+            .iter()
+            .map(|unit| self.line(unit_range(unit), |s| s.unit(unit)))
+            .collect();
+        Doc::concat([head, separator, self.braces(units, close(block))])
+    }
+
+    fn type_declaration(&mut self, decl: &Loc<TypeDeclaration>) -> Doc {
+        match &decl.kind {
+            TypeDeclKind::Enum(e) => self.enum_declaration(e, &decl.generic_args),
+            TypeDeclKind::Struct(s) => self.struct_declaration(s, &decl.generic_args),
+        }
+    }
+
+    fn enum_declaration(
+        &mut self,
+        e: &Loc<Enum>,
+        generics: &Option<Loc<Vec<Loc<TypeParam>>>>,
+    ) -> Doc {
+        let head = Doc::concat([text(format!("enum {}", e.name)), self.generics(generics)]);
+        let variants = e
+            .options
+            .iter()
+            .map(|(_, name, members)| {
+                let span = start(name)..members.as_ref().map(end).unwrap_or(end(name));
+                self.line(span, |s| {
+                    Doc::concat([
+                        text(name.to_string()),
+                        match members {
+                            Some(members) => s.members(members, false),
+                            None => Doc::Nil,
+                        },
+                        text(","),
+                    ])
+                })
+            })
+            .collect();
+        Doc::concat([head, text(" "), self.braces(variants, close(e))])
+    }
+
+    fn struct_declaration(
+        &mut self,
+        s: &Loc<Struct>,
+        generics: &Option<Loc<Vec<Loc<TypeParam>>>>,
+    ) -> Doc {
+        Doc::concat([
+            self.attribute_lines(&s.attributes),
+            self.leading_comments(start(&s.name), false),
+            text("struct "),
+            if s.is_port() { text("port ") } else { Doc::Nil },
+            text(s.name.to_string()),
+            self.generics(generics),
+            text(" "),
+            self.members(&s.members, true),
+        ])
+    }
+
+    /// The `{a: T, b: U}` members of a struct or enum variant
+    fn members(&mut self, members: &Loc<ParameterList>, one_per_line: bool) -> Doc {
+        let spans = members
+            .args
+            .iter()
+            .map(|(_, attrs, name, ty)| attrs.0.first().map(start).unwrap_or(start(name))..end(ty))
+            .collect::<Vec<_>>();
+        if one_per_line {
+            let lines = spans
+                .into_iter()
+                .zip(&members.args)
+                .map(|(span, member)| {
+                    self.line(span, |s| Doc::concat([s.parameter(member), text(",")]))
+                })
+                .collect();
+            self.braces(lines, close(members))
+        } else {
+            self.list(
+                "{",
+                spans,
+                |s, i| s.parameter(&members.args[i]),
+                "}",
+                Some(close(members)),
+            )
+        }
+    }
+
+    /// Attributes in front of a unit or statement, each on its own line
+    fn attribute_lines(&mut self, attributes: &AttributeList) -> Doc {
+        let lines = attributes
+            .0
+            .iter()
+            .map(|attr| {
+                Doc::concat([
+                    self.leading_comments(start(attr), false),
+                    self.attribute(attr),
+                    self.trailing_comments(end(attr)),
+                    Doc::HardLine,
+                ])
+            })
+            .collect::<Vec<_>>();
+        Doc::concat(lines)
+    }
+
+    fn attribute(&mut self, attr: &Loc<Attribute>) -> Doc {
+        fn with_args(name: &str, args: Vec<Doc>) -> Doc {
+            Doc::concat([
+                text(format!("{name}(")),
+                Doc::join(args, text(", ")),
+                text(")"),
+            ])
+        }
+        fn names<T: std::fmt::Display>(names: &[Loc<T>]) -> Vec<Doc> {
+            names.iter().map(|name| text(name.to_string())).collect()
+        }
+
+        let name = attr.name();
+        let inner = match &attr.inner {
+            Attribute::NoMangle | Attribute::CdcPrimitive | Attribute::Retime => text(name),
+            Attribute::Optimize { passes } => with_args(name, names(passes)),
+            Attribute::Fsm { state: None } => text(name),
+            Attribute::Fsm { state: Some(state) } => with_args(name, vec![text(state.to_string())]),
+            Attribute::WalTraceable {
+                suffix,
+                uses_clk,
+                uses_rst,
+            } => {
+                let args = [
+                    suffix.as_ref().map(|suffix| format!("suffix = {suffix}")),
+                    uses_clk.then(|| "uses_clk".to_string()),
+                    uses_rst.then(|| "uses_rst".to_string()),
+                ];
+                with_args(name, args.into_iter().flatten().map(text).collect())
+            }
+            Attribute::WalTrace {
+                clk: None,
+                rst: None,
+            } => text(name),
+            Attribute::WalTrace { clk, rst } => {
+                let mut args = vec![];
+                for (arg, value) in [("clk", clk), ("rst", rst)] {
+                    if let Some(value) = value {
+                        args.push(Doc::concat([text(format!("{arg} = ")), self.expr(value)]))
+                    }
+                }
+                with_args(name, args)
+            }
+            Attribute::WalSuffix { suffix } => {
+                with_args(name, vec![text(format!("suffix = {suffix}"))])
+            }
+            Attribute::Reset {
+                synchronous,
+                active_low,
+            } => {
+                let args = [
+                    synchronous.map(|sync| if sync { "sync" } else { "async" }),
+                    active_low.map(|low| if low { "active_low" } else { "active_high" }),
+                ];
+                with_args(name, args.into_iter().flatten().map(text).collect())
+            }
+            Attribute::ClockEdge { edge } => {
+                let edge = match edge {
+                    ClockEdge::Rising => "rising",
+                    ClockEdge::Falling => "falling",
+                    ClockEdge::Both => "both",
+                };
+                with_args(name, vec![text(edge)])
+            }
+            Attribute::MemoryInit { file, format } => {
+                let mut args = vec![text(format!("file = \"{file}\""))];
+                if let Some(format) = format {
+                    let format = match format.inner {
+                        MemoryInitFormat::Hex => "hex",
+                        MemoryInitFormat::Bin => "bin",
+                        MemoryInitFormat::Raw => "raw",
+                    };
+                    args.push(text(format!("format = {format}")))
+                }
+                with_args(name, args)
+            }
+            Attribute::VerilogParameters { params } => {
+                let args = params
+                    .iter()
+                    .map(|(param, value)| {
+                        Doc::concat([text(format!("{param} = ")), self.verilog_value(value)])
+                    })
+                    .collect();
+                with_args(name, args)
+            }
+            Attribute::VerilogAttrs { attrs } => {
+                let args = attrs
+                    .iter()
+                    .map(|(attr, value)| match value {
+                        Some(value) => {
+                            Doc::concat([text(format!("{attr} = ")), self.verilog_value(value)])
+                        }
+                        None => text(attr.to_string()),
+                    })
+                    .collect();
+                with_args(name, args)
+            }
+            Attribute::LintLevel { level: _, lints } => with_args(name, names(lints)),
+        };
+        Doc::concat([text("#["), inner, text("]")])
+    }
+
+    fn verilog_value(&self, value: &Loc<VerilogParameterValue>) -> Doc {
+        match &value.inner {
+            VerilogParameterValue::Int(val) => self.int_literal(range(value), val),
+            VerilogParameterValue::String(s) => text(format!("\"{s}\"")),
+        }
+    }
+
+    fn type_spec(&mut self, ty: &Loc<TypeSpec>) -> Doc {
+        match &ty.inner {
+            TypeSpec::Tuple(inner) => self.list(
+                "(",
+                inner.iter().map(range).collect(),
+                |s, i| s.type_spec(&inner[i]),
+                ")",
+                Some(close(ty)),
+            ),
+            TypeSpec::Array { inner, size } => Doc::concat([
+                text("["),
+                self.type_spec(inner),
+                text("; "),
+                self.type_expr(size),
+                text("]"),
+            ]),
+            TypeSpec::Named(path, args) => {
+                Doc::concat([text(path.to_string()), self.type_args(args)])
+            }
+            TypeSpec::Unit(_) => text("()"),
+            TypeSpec::Backward(inner) => Doc::concat([text("&mut "), self.type_spec(inner)]),
+            TypeSpec::Inverted(inner) => Doc::concat([text("~"), self.type_spec(inner)]),
+            TypeSpec::Wire(inner) => Doc::concat([text("&"), self.type_spec(inner)]),
+            TypeSpec::Wildcard => text("_"),
+        }
+    }
+
+    fn type_expr(&mut self, expr: &Loc<TypeExpression>) -> Doc {
+        match &expr.inner {
+            TypeExpression::TypeSpec(ty) => self.type_spec(ty),
+            TypeExpression::Integer(val) => self.int_literal(range(expr), val),
+            TypeExpression::ConstGeneric(inner) => {
+                Doc::concat([text("{"), self.expr(inner), text("}")])
+            }
+        }
+    }
+
+    fn maybe_comptime_type_expr(&mut self, expr: &Loc<MaybeComptime<Loc<TypeExpression>>>) -> Doc {
+        match &expr.inner {
+            MaybeComptime::Raw(inner) => self.type_expr(inner),
+            MaybeComptime::Comptime(cond) => {
+                self.comptime_condition(cond, |s, inner| s.type_expr(inner))
+            }
+        }
+    }
+
+    /// `$if N == 1 { a } $else { b }` where the branches are printed by `branch`
+    fn comptime_condition<T>(
+        &mut self,
+        cond: &ComptimeCondition<T>,
+        mut branch: impl FnMut(&mut Self, &T) -> Doc,
+    ) -> Doc {
+        let mut braced = |s: &mut Self, inner: &T| {
+            Doc::concat([
+                text("{"),
+                Doc::concat([Doc::Line, branch(s, inner)]).nest(),
+                Doc::Line,
+                text("}"),
+            ])
+            .group()
+        };
+        Doc::concat([
+            self.comptime_header(cond),
+            braced(self, &cond.on_true),
+            match &cond.on_false {
+                Some(on_false) => Doc::concat([text(" $else "), braced(self, on_false)]),
+                None => Doc::Nil,
+            },
+        ])
+    }
+
+    /// The `$if N == 1 ` of a comptime condition
+    fn comptime_header<T>(&self, cond: &ComptimeCondition<T>) -> Doc {
+        let (name, op, val) = &cond.condition;
+        let op = match op {
+            ComptimeCondOp::Eq => "==",
+            ComptimeCondOp::Lt => "<",
+            ComptimeCondOp::Gt => ">",
+            ComptimeCondOp::Le => "<=",
+            ComptimeCondOp::Ge => ">=",
+        };
+        Doc::concat([
+            text(format!("$if {name} {op} ")),
+            self.int_literal(range(val), val),
+            text(" "),
+        ])
+    }
+
+    fn pattern(&mut self, pattern: &Loc<Pattern>) -> Doc {
+        match &pattern.inner {
+            Pattern::Integer(lit) => self.int_literal(range(pattern), &lit.clone().as_signed()),
+            Pattern::Bool(b) => text(b.to_string()),
+            Pattern::Path(path) => text(path.to_string()),
+            Pattern::Tuple(inner) => self.patterns("(", inner, ")", close(pattern)),
+            Pattern::Array(inner) => self.patterns("[", inner, "]", close(pattern)),
+            Pattern::Type(path, args) => {
+                let args = match &args.inner {
+                    ArgumentPattern::Positional(inner) => {
+                        self.patterns("(", inner, ")", close(args))
+                    }
+                    ArgumentPattern::Named(inner) => {
+                        let spans = inner
+                            .iter()
+                            .map(|(name, pattern)| {
+                                start(name)..pattern.as_ref().map(end).unwrap_or(end(name))
+                            })
+                            .collect();
+                        let list = self.list(
+                            "(",
+                            spans,
+                            |s, i| match &inner[i] {
+                                (name, Some(pattern)) => {
+                                    Doc::concat([text(format!("{name}: ")), s.pattern(pattern)])
+                                }
+                                (name, None) => text(name.to_string()),
+                            },
+                            ")",
+                            Some(close(args)),
+                        );
+                        Doc::concat([text("$"), list])
+                    }
+                };
+                Doc::concat([text(path.to_string()), args])
+            }
+        }
+    }
+
+    fn patterns(
+        &mut self,
+        open: &str,
+        patterns: &[Loc<Pattern>],
+        close: &str,
+        close_pos: usize,
+    ) -> Doc {
+        self.list(
+            open,
+            patterns.iter().map(range).collect(),
+            |s, i| s.pattern(&patterns[i]),
+            close,
+            Some(close_pos),
+        )
+    }
+
+    /// A block, which is kept on one line if it only contains a short result expression
+    /// and `always_break` is not set. The statements of pipelines are indented one level
+    /// further than the `reg` markers separating their stages
+    fn block(
+        &mut self,
+        block: &Block,
+        span: Range<usize>,
+        always_break: bool,
+        pipeline: bool,
+    ) -> Doc {
+        if !always_break {
+            if let Some(doc) = self.short_block(block, span.clone()) {
+                return doc.group();
+            }
+        }
+
+        let close = span.end.saturating_sub(1);
+        let stage_indent = |line: Doc, is_marker: bool| {
+            if pipeline && !is_marker {
+                line.nest()
+            } else {
+                line
+            }
+        };
+        let mut lines = vec![];
+        for statement in &block.statements {
+            let is_marker = matches!(statement.inner, Statement::PipelineRegMarker(_, _));
+            let line = self.line(self.statement_range(statement), |s| s.statement(statement));
+            lines.push(stage_indent(line, is_marker))
+        }
+        if let Some(result) = &block.result {
+            let line = self.line(range(result), |s| match &result.inner {
+                // Branches of `if` expressions ending a block are never put on one line
+                Expression::If(cond, on_true, on_false) => {
+                    Doc::concat([Doc::BreakParent, s.if_chain(cond, on_true, on_false)]).group()
+                }
+                _ => s.block_result(result),
+            });
+            lines.push(stage_indent(line, false))
+        }
+        self.braces(lines, close)
+    }
+
+    /// A block which only contains a result, without a group deciding if it is put on one
+    /// line. Returns `None` if the block contains statements
+    fn short_block(&mut self, block: &Block, span: Range<usize>) -> Option<Doc> {
+        let result = match (block.statements.is_empty(), &block.result) {
+            (true, Some(result)) => result,
+            _ => return None,
+        };
+        Some(Doc::concat([
+            text("{"),
+            Doc::concat([
+                Doc::Line,
+                self.leading_comments(start(result), false),
+                self.block_result(result),
+                self.trailing_comments(end(result)),
+                self.dangling_comments(span.end.saturating_sub(1)),
+            ])
+            .nest(),
+            Doc::Line,
+            text("}"),
+        ]))
+    }
+
+    /// `if c {a} else if d {b} else {c}` without a group. Blocks in the chain are all put
+    /// on one line or all broken, by the group the chain is put in
+    fn if_chain(
+        &mut self,
+        cond: &Loc<Expression>,
+        on_true: &Loc<Expression>,
+        on_false: &Loc<Expression>,
+    ) -> Doc {
+        let branch = |s: &mut Self, branch: &Loc<Expression>| match &branch.inner {
+            Expression::Block(block) => s
+                .short_block(block, range(branch))
+                .unwrap_or_else(|| s.block(block, range(branch), true, false)),
+            Expression::If(cond, on_true, on_false) => s.if_chain(cond, on_true, on_false),
+            _ => s.expr(branch),
+        };
+        Doc::concat([
+            text("if "),
+            self.expr(cond),
+            text(" "),
+            branch(self, on_true),
+            text(" else "),
+            branch(self, on_false),
+        ])
+    }
+
+    fn block_result(&mut self, result: &Loc<Expression>) -> Doc {
+        let needs_parens = precedence(result) == Precedence::Comptime;
+        self.operand(result, needs_parens)
+    }
+
+    /// The span of a statement including its attributes and keyword
+    fn statement_range(&self, statement: &Loc<Statement>) -> Range<usize> {
+        let attrs = match &statement.inner {
+            Statement::Binding(binding) => &binding.attrs,
+            Statement::Register(reg) => &reg.attributes,
+            _ => return range(statement),
+        };
+        let start = match (attrs.0.first(), &statement.inner) {
+            (Some(attr), _) => start(attr),
+            // The location of bindings starts at the pattern rather than at `let`
+            (None, Statement::Binding(_)) => {
+                let before = self.source[..start(statement)].trim_end();
+                match before.strip_suffix("let") {
+                    Some(before_let) => before_let.len(),
+                    None => start(statement),
+                }
+            }
+            (None, _) => start(statement),
+        };
+        start..end(statement)
+    }
+
+    fn statement(&mut self, statement: &Loc<Statement>) -> Doc {
+        match &statement.inner {
+            Statement::Label(name) => text(format!("'{name}")),
+            Statement::Declaration(names) => text(format!(
+                "decl {};",
+                names
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            Statement::Binding(binding) => Doc::concat([
+                self.attribute_lines(&binding.attrs),
+                text("let "),
+                self.pattern(&binding.pattern),
+                match &binding.ty {
+                    Some(ty) => Doc::concat([text(": "), self.type_spec(ty)]),
+                    None => Doc::Nil,
+                },
+                text(" = "),
+                self.expr(&binding.value),
+                text(";"),
+            ]),
+            Statement::PipelineRegMarker(count, cond) => Doc::concat([
+                text("reg"),
+                match cond {
+                    Some(cond) => Doc::concat([text("["), self.expr(cond), text("]")]),
+                    None => Doc::Nil,
+                },
+                match count {
+                    Some(count) => Doc::concat([text("*"), self.type_expr(count)]),
+                    None => Doc::Nil,
+                },
+                text(";"),
+            ]),
+            Statement::Register(reg) => self.register(reg),
+            Statement::Set { target, value } => Doc::concat([
+                text("set "),
+                self.expr(target),
+                text(" = "),
+                self.expr(value),
+                text(";"),
+            ]),
+            Statement::Assert(expr) => {
+                let expr_doc = self.expr(expr);
+                // `assert (a) ...` would be parsed as the clock of a property
+                let expr_doc = if first_token(&expr_doc) == Some(TokenKind::OpenParen) {
+                    parens(expr_doc)
+                } else {
+                    expr_doc
+                };
+                Doc::concat([text("assert "), expr_doc, text(";")])
+            }
+            Statement::Property(property) => self.property(property),
+            Statement::Comptime(cond) => {
+                let on_true_close = match &cond.on_false {
+                    Some(_) => None,
+                    None => Some(close(statement)),
+                };
+                Doc::concat([
+                    self.comptime_header(cond),
+                    self.statements(&cond.on_true, on_true_close),
+                    match &cond.on_false {
+                        Some(on_false) => Doc::concat([
+                            text(" $else "),
+                            self.statements(on_false, Some(close(statement))),
+                        ]),
+                        None => Doc::Nil,
+                    },
+                ])
+            }
+        }
+    }
+
+    /// The braced statements of a comptime statement. `close` is the position of the
+    /// closing brace, if it is known
+    fn statements(&mut self, statements: &[Loc<Statement>], close: Option<usize>) -> Doc {
+        let lines = statements
+            .iter()
+            .map(|statement| self.line(self.statement_range(statement), |s| s.statement(statement)))
+            .collect::<Vec<_>>();
+        match close {
+            Some(close) => self.braces(lines, close),
+            None if lines.is_empty() => text("{}"),
+            None => Doc::concat([
+                text("{"),
+                Doc::concat(lines).nest(),
+                Doc::HardLine,
+                text("}"),
+            ]),
+        }
+    }
+
+    fn register(&mut self, reg: &Loc<Register>) -> Doc {
+        Doc::concat([
+            self.attribute_lines(&reg.attributes),
+            text("reg("),
+            self.expr(&reg.clock),
+            text(") "),
+            self.pattern(&reg.pattern),
+            match &reg.value_type {
+                Some(ty) => Doc::concat([text(": "), self.type_spec(ty)]),
+                None => Doc::Nil,
+            },
+            match &reg.reset {
+                Some((trigger, value)) => Doc::concat([
+                    text(" reset("),
+                    self.expr(trigger),
+                    text(": "),
+                    self.expr(value),
+                    text(")"),
+                ]),
+                None => Doc::Nil,
+            },
+            match &reg.initial {
+                Some(initial) => Doc::concat([text(" initial("), self.expr(initial), text(")")]),
+                None => Doc::Nil,
+            },
+            text(" = "),
+            self.expr(&reg.value),
+            text(";"),
+        ])
+    }
+
+    fn property(&mut self, property: &Property) -> Doc {
+        let keyword = match property.kind {
+            PropertyKind::Assert => "assert",
+            PropertyKind::Assume => "assume",
+            PropertyKind::Cover => "cover",
+        };
+        let clock = Doc::concat([
+            text(format!("{keyword}(")),
+            self.expr(&property.clock),
+            match &property.reset {
+                Some(reset) => Doc::concat([text(", "), self.expr(reset)]),
+                None => Doc::Nil,
+            },
+            text(") "),
+        ]);
+
+        // `assert (clk) -a` is an assertion of `clk - a`, so the body of assert properties
+        // must start with something which can not continue an expression
+        let first = |s: &mut Self, expr: &Loc<Expression>| {
+            let doc = s.expr(expr);
+            let starts_body = first_token(&doc)
+                .map(|t| t.starts_property_body())
+                .unwrap_or(false);
+            if property.kind == PropertyKind::Assert && !starts_body {
+                parens(doc)
+            } else {
+                doc
+            }
+        };
+        let body = match &property.antecedent {
+            Some((antecedent, implication)) => Doc::concat([
+                first(self, antecedent),
+                text(match implication {
+                    Implication::Overlapping => " |-> ",
+                    Implication::NonOverlapping => " |=> ",
+                }),
+                self.expr(&property.consequent),
+            ]),
+            None => first(self, &property.consequent),
+        };
+        Doc::concat([clock, body, text(";")])
+    }
+
+    /// `expr`, surrounded by parentheses if `needs_parens` is set
+    fn operand(&mut self, expr: &Loc<Expression>, needs_parens: bool) -> Doc {
+        let doc = self.expr(expr);
+        if needs_parens {
+            parens(doc)
+        } else {
+            doc
+        }
+    }
+
+    /// The expression before a `.`, `#` or `[`
+    fn suffix_target(&mut self, expr: &Loc<Expression>) -> Doc {
+        let needs_parens = precedence(expr) < Precedence::Atom || is_right_open(expr);
+        self.operand(expr, needs_parens)
+    }
+
+    fn expressions(
+        &mut self,
+        open: &str,
+        exprs: &[Loc<Expression>],
+        close: &str,
+        close_pos: Option<usize>,
+    ) -> Doc {
+        self.list(
+            open,
+            exprs.iter().map(range).collect(),
+            |s, i| s.expr(&exprs[i]),
+            close,
+            close_pos,
+        )
+    }
+
+    fn args(&mut self, args: &Loc<ArgumentList>) -> Doc {
+        match &args.inner {
+            ArgumentList::Positional(exprs) => self.expressions("(", exprs, ")", Some(close(args))),
+            ArgumentList::Named(named) => {
+                let spans = named
+                    .iter()
+                    .map(|arg| match arg {
+                        NamedArgument::Full(name, value) => start(name)..end(value),
+                        NamedArgument::Short(name) => range(name),
+                    })
+                    .collect();
+                let list = self.list(
+                    "(",
+                    spans,
+                    |s, i| match &named[i] {
+                        NamedArgument::Full(name, value) => {
+                            Doc::concat([text(format!("{name}: ")), s.expr(value)])
+                        }
+                        NamedArgument::Short(name) => text(name.to_string()),
+                    },
+                    ")",
+                    Some(close(args)),
+                );
+                Doc::concat([text("$"), list])
+            }
+        }
+    }
+
+    fn turbofish(&mut self, turbofish: &Option<Loc<TurbofishInner>>) -> Doc {
+        let Some(turbofish) = turbofish else {
+            return Doc::Nil;
+        };
+        match &turbofish.inner {
+            TurbofishInner::Positional(params) => {
+                let list = self.list(
+                    "<",
+                    params.iter().map(range).collect(),
+                    |s, i| s.type_expr(&params[i]),
+                    ">",
+                    Some(close(turbofish)),
+                );
+                Doc::concat([text("::"), list])
+            }
+            TurbofishInner::Named(params) => {
+                let list = self.list(
+                    "<",
+                    params.iter().map(range).collect(),
+                    |s, i| match &params[i].inner {
+                        NamedTurbofish::Short(name) => text(name.to_string()),
+                        NamedTurbofish::Full(name, value) => {
+                            Doc::concat([text(format!("{name}: ")), s.type_expr(value)])
+                        }
+                    },
+                    ">",
+                    Some(close(turbofish)),
+                );
+                Doc::concat([text("::$"), list])
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Loc<Expression>) -> Doc {
+        if let Some((callee, turbofish, lhs, rhs)) = infix_call(expr) {
+            let lhs_parens = precedence(lhs) <= Precedence::Infix || is_right_open(lhs);
+            let rhs_parens = precedence(rhs) < Precedence::Infix;
+            return Doc::concat([
+                self.operand(lhs, lhs_parens),
+                text(format!(" `{callee}")),
+                self.turbofish(turbofish),
+                text("`"),
+                Doc::concat([Doc::Line, self.operand(rhs, rhs_parens)]).nest(),
+            ])
+            .group();
+        }
+
+        match &expr.inner {
+            Expression::Identifier(path) => text(path.to_string()),
+            Expression::IntLiteral(lit) => self.int_literal(range(expr), &lit.clone().as_signed()),
+            Expression::BoolLiteral(b) => text(b.to_string()),
+            Expression::BitLiteral(bit) => text(match bit {
+                BitLiteral::Low => "LOW",
+                BitLiteral::High => "HIGH",
+                BitLiteral::HighImp => "HIGHIMP",
+            }),
+            Expression::ArrayLiteral(elems) => self.expressions("[", elems, "]", Some(close(expr))),
+            Expression::ArrayShorthandLiteral(elem, amount) => Doc::concat([
+                text("["),
+                self.expr(elem),
+                text("; "),
+                self.int_literal(range(amount), &BigInt::from(amount.inner.clone())),
+                text("]"),
+            ]),
+            Expression::Index(target, index) => Doc::concat([
+                self.suffix_target(target),
+                text("["),
+                self.expr(index),
+                text("]"),
+            ]),
+            Expression::RangeIndex {
+                target,
+                start: first,
+                end: last,
+            } => Doc::concat([
+                self.suffix_target(target),
+                text("["),
+                self.int_literal(range(first), &BigInt::from(first.inner.clone())),
+                text(":"),
+                self.int_literal(range(last), &BigInt::from(last.inner.clone())),
+                text("]"),
+            ]),
+            // The location of tuples does not include the parentheses
+            Expression::TupleLiteral(elems) => self.expressions("(", elems, ")", None),
+            Expression::TupleIndex(target, index) => Doc::concat([
+                self.suffix_target(target),
+                text("#"),
+                self.int_literal(range(index), &BigInt::from(index.inner)),
+            ]),
+            Expression::FieldAccess(target, field) => {
+                Doc::concat([self.suffix_target(target), text(format!(".{field}"))])
+            }
+            Expression::CreatePorts => text("port"),
+            Expression::Call {
+                kind,
+                callee,
+                args,
+                turbofish,
+            } => Doc::concat([
+                match kind {
+                    CallKind::Function => Doc::Nil,
+                    CallKind::Entity(_) => text("inst "),
+                    CallKind::Pipeline(_, depth) => Doc::concat([
+                        text("inst("),
+                        self.maybe_comptime_type_expr(depth),
+                        text(") "),
+                    ]),
+                },
+                text(callee.to_string()),
+                self.turbofish(turbofish),
+                self.args(args),
+            ]),
+            Expression::MethodCall {
+                target,
+                name,
+                args,
+                kind,
+                turbofish,
+            } => Doc::concat([
+                self.suffix_target(target),
+                text("."),
+                match kind {
+                    CallKind::Function => Doc::Nil,
+                    CallKind::Entity(_) | CallKind::Pipeline(_, _) => text("inst "),
+                },
+                text(name.to_string()),
+                self.turbofish(turbofish),
+                self.args(args),
+            ]),
+            Expression::If(cond, on_true, on_false) => {
+                self.if_chain(cond, on_true, on_false).group()
+            }
+            Expression::Match(scrutinee, arms) => {
+                let head = Doc::concat([text("match "), self.expr(scrutinee), text(" ")]);
+                let lines = arms
+                    .iter()
+                    .map(|(pattern, value)| {
+                        self.line(start(pattern)..end(value), |s| {
+                            Doc::concat([
+                                s.pattern(pattern),
+                                text(" => "),
+                                s.expr(value),
+                                text(","),
+                            ])
+                        })
+                    })
+                    .collect();
+                Doc::concat([head, self.braces(lines, close(arms))])
+            }
+            Expression::UnaryOperator(op, operand) => {
+                let (op, needs_parens) = match op {
+                    // `*` is followed by a full expression
+                    UnaryOperator::Dereference => ("*", false),
+                    // `& &a` must not be printed as `&&a`
+                    UnaryOperator::Reference => (
+                        "&",
+                        precedence(operand) < Precedence::Prefix
+                            || matches!(
+                                operand.inner,
+                                Expression::UnaryOperator(UnaryOperator::Reference, _)
+                            ),
+                    ),
+                    UnaryOperator::Sub => ("-", precedence(operand) < Precedence::Prefix),
+                    UnaryOperator::Not => ("!", precedence(operand) < Precedence::Prefix),
+                    UnaryOperator::BitwiseNot => ("~", precedence(operand) < Precedence::Prefix),
+                };
+                Doc::concat([text(op), self.operand(operand, needs_parens)])
+            }
+            Expression::BinaryOperator(lhs, op, rhs) => {
+                let power = Precedence::Binary(binop_binding_power(op));
+                // Parentheses which clarify the order of operations are kept even if
+                // they are not needed
+                let lhs_parens = precedence(lhs) < power
+                    || is_right_open(lhs)
+                    || (precedence(lhs) < Precedence::Prefix && self.parenthesized(range(lhs)));
+                let rhs_parens = precedence(rhs) <= power
+                    || (precedence(rhs) < Precedence::Prefix && self.parenthesized(range(rhs)));
+                Doc::concat([
+                    self.operand(lhs, lhs_parens),
+                    text(format!(" {}", binop_str(op))),
+                    Doc::concat([Doc::Line, self.operand(rhs, rhs_parens)]).nest(),
+                ])
+                .group()
+            }
+            Expression::Block(block) => self.block(block, range(expr), false, false),
+            Expression::PipelineReference {
+                stage_kw_and_reference_loc: _,
+                stage,
+                name,
+            } => {
+                let stage = match stage {
+                    PipelineStageReference::Relative(offset) => match &offset.inner {
+                        // `stage(-a)` is parsed into a negation of `a`
+                        TypeExpression::ConstGeneric(offset) => match &offset.inner {
+                            Expression::UnaryOperator(UnaryOperator::Sub, inner) => {
+                                Doc::concat([text("-"), self.expr(inner)])
+                            }
+                            _ => Doc::concat([text("+"), self.expr(offset)]),
+                        },
+                        _ => Doc::concat([text("+"), self.type_expr(offset)]),
+                    },
+                    PipelineStageReference::Absolute(label) => text(label.to_string()),
+                };
+                Doc::concat([text("stage("), stage, text(format!(").{name}"))])
+            }
+            Expression::StageValid => text("stage.valid"),
+            Expression::StageReady => text("stage.ready"),
+            Expression::Comptime(cond) => self.comptime_condition(cond, |s, e| s.expr(e)),
+        }
+    }
+}
+
+fn unit_range(unit: &Loc<Unit>) -> Range<usize> {
+    unit.head
+        .attributes
+        .0
+        .first()
+        .map(start)
+        .unwrap_or(start(unit))..end(unit)
+}
+
+/// The span of an item including its attributes
+fn item_range(item: &Item) -> Range<usize> {
+    match item {
+        Item::Unit(unit) => unit_range(unit),
+        Item::Type(decl) => {
+            let attrs = match &decl.kind {
+                TypeDeclKind::Struct(s) => s.attributes.0.first().map(start),
+                TypeDeclKind::Enum(_) => None,
+            };
+            attrs.unwrap_or(start(decl))..end(decl)
+        }
+        Item::TraitDef(def) => range(def),
+        Item::Module(module) => range(module),
+        Item::Use(u) => range(u),
+        Item::Config(config) => range(config),
+        Item::ImplBlock(block) => range(block),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::format;
+
+    fn check(input: &str, expected: &str) {
+        let formatted = format(input, 0).unwrap_or_else(|e| panic!("{e:?}"));
+        assert_eq!(formatted, expected);
+        let reformatted = format(&formatted, 0).unwrap_or_else(|e| panic!("{e:?}"));
+        assert_eq!(reformatted, formatted, "Formatting is not idempotent");
+    }
+
+    #[test]
+    fn every_kind_of_pipeline_stage_is_printed() {
+        check(
+            indoc! {"
+                pipeline(3) p(clk:clock,x:int<8>,en:bool)->int<8>{
+                reg;
+                  let a=x+1;
+                  // Before the stage
+                  reg[en];
+                  let b=stage(-1).a;
+                  reg*1; // After the stage
+                  b
+                }
+            "},
+            indoc! {"
+                pipeline(3) p(clk: clock, x: int<8>, en: bool) -> int<8> {
+                    reg;
+                        let a = x + 1;
+                    // Before the stage
+                    reg[en];
+                        let b = stage(-1).a;
+                    reg*1; // After the stage
+                        b
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn nested_and_empty_comptime_ifs_are_printed() {
+        check(
+            indoc! {"
+                $config N = 1
+                $config M = 2
+                fn f()->bool{
+                $if N==1{let x=true;}
+                $if N == 2 {
+                $if M==1{let y=true;}$else{}
+                }$else{let z=false;}
+                $if M==2{}
+                true
+                }
+            "},
+            indoc! {"
+                $config N = 1
+                $config M = 2
+                fn f() -> bool {
+                    $if N == 1 {
+                        let x = true;
+                    }
+                    $if N == 2 {
+                        $if M == 1 {
+                            let y = true;
+                        } $else {}
+                    } $else {
+                        let z = false;
+                    }
+                    $if M == 2 {}
+                    true
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn impl_blocks_and_generic_traits_are_printed() {
+        check(
+            indoc! {"
+                struct S{a:bool}
+                impl S{}
+                impl S{fn get(self)->bool{self.a}
+                // Between methods
+                fn not(self)->bool{!self.a}}
+                trait T<A,B>{fn first(self,a:A)->A;fn second(self,b:B)->B;}
+                impl<X> T<X,bool> for S{fn first(self,a:X)->X{a}fn second(self,b:bool)->bool{b}}
+            "},
+            indoc! {"
+                struct S {
+                    a: bool,
+                }
+                impl S {}
+                impl S {
+                    fn get(self) -> bool {
+                        self.a
+                    }
+                    // Between methods
+                    fn not(self) -> bool {
+                        !self.a
+                    }
+                }
+                trait T<A, B> {
+                    fn first(self, a: A) -> A;
+                    fn second(self, b: B) -> B;
+                }
+                impl<X> T<X, bool> for S {
+                    fn first(self, a: X) -> X {
+                        a
+                    }
+                    fn second(self, b: bool) -> bool {
+                        b
+                    }
+                }
+            "},
+        )
+    }
+
+    #[test]
+    fn comments_stay_next_to_their_code() {
+        check(
+            indoc! {"
+                fn f(a:bool, // The first argument
+                b:bool)->bool{
+                  // Only a comment
+                  a
+                }
+                // Between units
+
+                /* Block comment */ fn g()->bool{
+                  let x = {
+                    // Dangling
+                    true
+                  };
+                  x
+                }
+                entity e() {
+                  // Empty body
+                }
+            "},
+            indoc! {"
+                fn f(
+                    a: bool, // The first argument
+                    b: bool,
+                ) -> bool {
+                    // Only a comment
+                    a
+                }
+                // Between units
+
+                /* Block comment */
+                fn g() -> bool {
+                    let x = {
+                        // Dangling
+                        true
+                    };
+                    x
+                }
+                entity e() {
+                    // Empty body
+                }
+            "},
+        )
+    }
+}
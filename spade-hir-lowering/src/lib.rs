@@ -1,16 +1,18 @@
 mod attributes;
 pub mod error;
 mod linear_check;
+mod memory_init;
 pub mod monomorphisation;
 pub mod name_map;
 pub mod passes;
 mod pattern;
 pub mod pipelines;
+mod retiming;
 mod statement_list;
 pub mod substitution;
 mod usefulness;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 
 use attributes::AttributeListExt;
 use attributes::LocAttributeExt;
@@ -53,7 +55,7 @@ use spade_common::name::{Identifier, Path};
 use spade_common::num_ext::InfallibleToBigInt;
 use spade_common::num_ext::InfallibleToBigUint;
 use spade_diagnostics::diag_anyhow;
-use spade_diagnostics::{diag_assert, diag_bail, DiagHandler, Diagnostic};
+use spade_diagnostics::{diag_assert, diag_bail, CodeBundle, DiagHandler, Diagnostic, Lint};
 use spade_typeinference::equation::TypeVar;
 use spade_typeinference::equation::TypedExpression;
 use spade_typeinference::GenericListToken;
@@ -70,6 +72,7 @@ use spade_hir as hir;
 use spade_hir::{expression::BinaryOperator, ExprKind, Expression, Statement, Unit};
 use spade_mir as mir;
 use spade_typeinference::TypeState;
+use spade_types::meta_types::MetaType;
 use spade_types::{ConcreteType, PrimitiveType};
 
 pub trait Manglable {
@@ -246,6 +249,7 @@ pub fn all_conditions(ops: Vec<ValueName>, ctx: &mut Context) -> (Vec<mir::State
                 operands: vec![result_name, op.clone()],
                 ty: MirType::Bool,
                 loc: None,
+                verilog_attrs: vec![],
             }));
             result_name = new_name;
         }
@@ -287,6 +291,7 @@ impl PatternLocal for Loc<Pattern> {
                                 .type_of_id(p.id, ctx.symtab.symtab(), &ctx.item_list.types)
                                 .to_mir_type(),
                             loc: None,
+                            verilog_attrs: vec![],
                         }),
                         p,
                     );
@@ -318,6 +323,7 @@ impl PatternLocal for Loc<Pattern> {
                                 .type_of_id(p.id, ctx.symtab.symtab(), &ctx.item_list.types)
                                 .to_mir_type(),
                             loc: None,
+                            verilog_attrs: vec![],
                         }),
                         p,
                     );
@@ -366,6 +372,7 @@ impl PatternLocal for Loc<Pattern> {
                                         )
                                         .to_mir_type(),
                                     loc: None,
+                                    verilog_attrs: vec![],
                                 }),
                                 value,
                             );
@@ -399,6 +406,7 @@ impl PatternLocal for Loc<Pattern> {
                                         )
                                         .to_mir_type(),
                                     loc: None,
+                                    verilog_attrs: vec![],
                                 }),
                                 &p.value,
                             );
@@ -437,6 +445,7 @@ impl PatternLocal for Loc<Pattern> {
                         operator: mir::Operator::Eq,
                         operands: vec![value_name.clone(), ValueName::Expr(const_id)],
                         loc: None,
+                        verilog_attrs: vec![],
                     }),
                 ];
 
@@ -456,6 +465,7 @@ impl PatternLocal for Loc<Pattern> {
                     operator: mir::Operator::LogicalNot,
                     operands: vec![value_name.clone()],
                     loc: None,
+                    verilog_attrs: vec![],
                 })];
 
                 Ok(PatternCondition {
@@ -523,6 +533,7 @@ impl PatternLocal for Loc<Pattern> {
                             operands: vec![value_name.clone()],
                             ty: MirType::Bool,
                             loc: None,
+                            verilog_attrs: vec![],
                         })
                     }
                     PatternableKind::Struct => mir::Statement::Constant(
@@ -710,6 +721,7 @@ pub fn do_wal_trace_lowering(
             operands: vec![main_value_name.clone()],
             ty: flipped_ty.clone(),
             loc: None,
+            verilog_attrs: vec![],
         });
         if !flipped_ty.size().is_zero() {
             result.push_anonymous(flipped_port);
@@ -754,6 +766,7 @@ pub fn do_wal_trace_lowering(
                 operands: vec![operand],
                 ty: mir_ty.clone(),
                 loc: None,
+                verilog_attrs: vec![],
             }));
 
             // Add the wal trace statement
@@ -934,6 +947,25 @@ pub fn lower_wal_trace(
     Ok(())
 }
 
+fn lower_verilog_value(value: &hir::VerilogParameterValue) -> mir::VerilogParameterValue {
+    match value {
+        hir::VerilogParameterValue::Int(v) => mir::VerilogParameterValue::Int(v.clone()),
+        hir::VerilogParameterValue::String(s) => mir::VerilogParameterValue::String(s.clone()),
+    }
+}
+
+fn lower_verilog_attrs(
+    attrs: &[(Loc<Identifier>, Option<Loc<hir::VerilogParameterValue>>)],
+) -> Vec<mir::VerilogAttribute> {
+    attrs
+        .iter()
+        .map(|(name, value)| mir::VerilogAttribute {
+            name: name.to_string(),
+            value: value.as_ref().map(|v| lower_verilog_value(v)),
+        })
+        .collect()
+}
+
 #[local_impl]
 impl StatementLocal for Statement {
     #[tracing::instrument(name = "Statement::lower", level = "trace", skip(self, ctx))]
@@ -945,9 +977,15 @@ impl StatementLocal for Statement {
                 ty: _,
                 value,
                 wal_trace,
+                memory_init,
+                verilog_attrs,
             }) => {
                 result.append(value.lower(ctx)?);
 
+                if let Some(memory_init) = memory_init {
+                    memory_init::apply_memory_init(memory_init, value, &mut result, ctx)?;
+                }
+
                 let refutability = pattern.is_refutable(ctx);
                 if refutability.is_useful() {
                     return Err(refutable_pattern_diagnostic(
@@ -963,6 +1001,31 @@ impl StatementLocal for Statement {
 
                 let mir_ty = concrete_ty.to_mir_type();
 
+                // Aliases of memories are emitted as macros rather than declarations, so
+                // attributes on them are placed on the declaration of the memory instead
+                let mut verilog_attrs = lower_verilog_attrs(verilog_attrs);
+                if !verilog_attrs.is_empty() {
+                    let decl = result.binding_mut(&value.variable(ctx)?).filter(|b| {
+                        matches!(
+                            b.operator,
+                            mir::Operator::DeclClockedMemory { .. }
+                                | mir::Operator::DeclBlockRam { .. }
+                        )
+                    });
+                    match decl {
+                        Some(decl) => decl.verilog_attrs.append(&mut verilog_attrs),
+                        None if matches!(mir_ty, mir::types::Type::Memory { .. }) => {
+                            return Err(Diagnostic::error(
+                                value,
+                                "verilog_attrs on a memory must be where the memory is declared",
+                            )
+                            .primary_label("This does not declare a memory")
+                            .note("Move the attribute to the let binding of the clocked_memory"))
+                        }
+                        None => {}
+                    }
+                }
+
                 result.push_primary(
                     mir::Statement::Binding(mir::Binding {
                         name: pattern.value_name(),
@@ -970,6 +1033,7 @@ impl StatementLocal for Statement {
                         operands: vec![value.variable(ctx)?],
                         ty: mir_ty.clone(),
                         loc: Some(pattern.loc()),
+                        verilog_attrs,
                     }),
                     pattern,
                 );
@@ -983,7 +1047,9 @@ impl StatementLocal for Statement {
             Statement::Register(register) => {
                 let hir::Register {
                     clock,
+                    clock_edge,
                     reset,
+                    reset_style,
                     initial,
                     pattern,
                     value,
@@ -1025,13 +1091,21 @@ impl StatementLocal for Statement {
                 }
 
                 let mut traced = None;
+                let mut verilog_attrs = vec![];
                 attributes.lower(&mut |attr| match &attr.inner {
                     Attribute::Fsm { state } => {
                         traced = Some(state.value_name());
                         Ok(())
                     }
+                    Attribute::VerilogAttrs { attrs } => {
+                        verilog_attrs.extend(lower_verilog_attrs(attrs));
+                        Ok(())
+                    }
                     Attribute::WalTraceable { .. } => Err(attr.report_unused("register")),
-                    Attribute::Optimize { .. } => Err(attr.report_unused("register")),
+                    Attribute::Optimize { .. }
+                    | Attribute::CdcPrimitive
+                    | Attribute::Retime
+                    | Attribute::VerilogParameters { .. } => Err(attr.report_unused("register")),
                 })?;
 
                 let initial = if let Some(init) = initial {
@@ -1060,16 +1134,26 @@ impl StatementLocal for Statement {
                             .type_of_id(pattern.id, ctx.symtab.symtab(), &ctx.item_list.types)
                             .to_mir_type(),
                         clock: clock.variable(ctx)?,
+                        clock_edge: match clock_edge {
+                            hir::ClockEdge::Rising => mir::ClockEdge::Rising,
+                            hir::ClockEdge::Falling => mir::ClockEdge::Falling,
+                            hir::ClockEdge::Both => mir::ClockEdge::Both,
+                        },
                         reset: reset
                             .as_ref()
                             .map::<Result<_>, _>(|(value, trig)| {
                                 Ok((value.variable(ctx)?, trig.variable(ctx)?))
                             })
                             .transpose()?,
+                        reset_style: mir::ResetStyle {
+                            synchronous: reset_style.synchronous,
+                            active_low: reset_style.active_low,
+                        },
                         initial,
                         value: value.variable(ctx)?,
                         loc: Some(pattern.loc()),
                         traced,
+                        verilog_attrs,
                     }),
                     pattern,
                 );
@@ -1080,12 +1164,56 @@ impl StatementLocal for Statement {
             Statement::PipelineRegMarker(_cond) => {
                 // NOTE: Cond is handled by pipeline lowering
                 ctx.subs.current_stage += 1;
+                result.push_stage_boundary();
             }
             Statement::Label(_) => {}
             Statement::Assert(expr) => {
                 result.append(expr.lower(ctx)?);
                 result.push_anonymous(mir::Statement::Assert(expr.variable(ctx)?.at_loc(expr)))
             }
+            Statement::Property(property) => {
+                let hir::Property {
+                    kind,
+                    clock,
+                    reset,
+                    antecedent,
+                    consequent,
+                } = property;
+
+                result.append(clock.lower(ctx)?);
+                if let Some(reset) = reset {
+                    result.append(reset.lower(ctx)?);
+                }
+                if let Some((antecedent, _)) = antecedent {
+                    result.append(antecedent.lower(ctx)?);
+                }
+                result.append(consequent.lower(ctx)?);
+
+                let kind = match kind {
+                    hir::PropertyKind::Assert => mir::PropertyKind::Assert,
+                    hir::PropertyKind::Assume => mir::PropertyKind::Assume,
+                    hir::PropertyKind::Cover => mir::PropertyKind::Cover,
+                };
+                let antecedent = antecedent
+                    .as_ref()
+                    .map(|(antecedent, implication)| -> Result<_> {
+                        let implication = match implication {
+                            hir::Implication::Overlapping => mir::Implication::Overlapping,
+                            hir::Implication::NonOverlapping => mir::Implication::NonOverlapping,
+                        };
+                        Ok((antecedent.variable(ctx)?, implication))
+                    })
+                    .transpose()?;
+
+                result.push_anonymous(mir::Statement::Property(mir::Property {
+                    kind,
+                    clock: clock.variable(ctx)?,
+                    reset: reset.as_ref().map(|r| r.variable(ctx)).transpose()?,
+                    antecedent,
+                    consequent: consequent.variable(ctx)?,
+                    loc: Some(().between_locs(clock, consequent)),
+                }))
+            }
             Statement::WalSuffixed { suffix, target } => {
                 let ty = ctx
                     .types
@@ -1116,6 +1244,12 @@ pub fn expr_to_mir(expr: Loc<Expression>, ctx: &mut Context) -> Result<Statement
     expr.lower(ctx)
 }
 
+/// The name of the value which holds the result of `expr` once it has been lowered by
+/// [expr_to_mir]
+pub fn expr_value_name(expr: &Loc<Expression>, ctx: &Context) -> Result<mir::ValueName> {
+    expr.variable(ctx)
+}
+
 #[local_impl]
 impl ExprLocal for Loc<Expression> {
     /// If the verilog code for this expression is just an alias for another variable
@@ -1292,6 +1426,7 @@ impl ExprLocal for Loc<Expression> {
                                 operands: vec![lhs.variable(ctx)?, rhs.variable(ctx)?],
                                 ty: self_type,
                                 loc: Some(self.loc()),
+                                verilog_attrs: vec![],
                             }),
                             self,
                         );
@@ -1319,6 +1454,7 @@ impl ExprLocal for Loc<Expression> {
                                 operands: vec![lhs.variable(ctx)?, rhs.variable(ctx)?],
                                 ty: self_type,
                                 loc: Some(self.loc()),
+                                verilog_attrs: vec![],
                             }),
                             self,
                         );
@@ -1426,6 +1562,7 @@ impl ExprLocal for Loc<Expression> {
                             operands: vec![operand.variable(ctx)?],
                             ty: self_type,
                             loc: Some(self.loc()),
+                            verilog_attrs: vec![],
                         }),
                         self,
                     );
@@ -1458,6 +1595,7 @@ impl ExprLocal for Loc<Expression> {
                             .collect::<Result<_>>()?,
                         ty: self_type,
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -1482,6 +1620,7 @@ impl ExprLocal for Loc<Expression> {
                         operands: vec![tup.variable(ctx)?],
                         ty: self_type,
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -1541,6 +1680,7 @@ impl ExprLocal for Loc<Expression> {
                             operands: vec![],
                             ty: inner_mir_type,
                             loc: Some(self.loc()),
+                            verilog_attrs: vec![],
                         }),
                         mir::Statement::Binding(mir::Binding {
                             name: rname.clone(),
@@ -1548,6 +1688,7 @@ impl ExprLocal for Loc<Expression> {
                             operands: vec![lname.clone()],
                             ty: right_mir_type,
                             loc: Some(self.loc()),
+                            verilog_attrs: vec![],
                         }),
                     ],
                     self,
@@ -1560,6 +1701,7 @@ impl ExprLocal for Loc<Expression> {
                         operands: vec![lname, rname],
                         ty: self_type,
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -1612,6 +1754,7 @@ impl ExprLocal for Loc<Expression> {
                         operands: vec![target.variable(ctx)?],
                         ty: self_type,
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -1630,6 +1773,7 @@ impl ExprLocal for Loc<Expression> {
                             .collect::<Result<_>>()?,
                         ty: self_type,
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -1657,6 +1801,7 @@ impl ExprLocal for Loc<Expression> {
                             .collect::<Result<_>>()?,
                         ty: self_type,
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -1672,6 +1817,7 @@ impl ExprLocal for Loc<Expression> {
                         operands: vec![target.variable(ctx)?, index.variable(ctx)?],
                         ty: self_type,
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -1716,6 +1862,7 @@ impl ExprLocal for Loc<Expression> {
                         operands: vec![target.variable(ctx)?],
                         ty: self_type,
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -1738,7 +1885,7 @@ impl ExprLocal for Loc<Expression> {
                 result.push_primary(
                     mir::Statement::Binding(mir::Binding {
                         name: self.variable(ctx)?,
-                        operator: mir::Operator::Select,
+                        operator: mir::Operator::Select { from_if: true },
                         operands: vec![
                             cond.variable(ctx)?,
                             on_true.variable(ctx)?,
@@ -1749,6 +1896,7 @@ impl ExprLocal for Loc<Expression> {
                             .expr_type(self, ctx.symtab.symtab(), &ctx.item_list.types)?
                             .to_mir_type(),
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 );
@@ -1781,8 +1929,24 @@ impl ExprLocal for Loc<Expression> {
                     .primary_label(format!("{witnesses} not covered")));
                 }
 
+                // Arms which match no values that are not matched by earlier arms are never taken
+                for (i, (pat, _)) in branches.iter().enumerate() {
+                    let earlier = usefulness::Matrix::new(&pat_stacks[0..i]);
+                    if !is_useful(&pat_stacks[i], &earlier).is_useful() {
+                        ctx.diag_handler.lints.emit(
+                            Lint::UnreachableMatchArm,
+                            Diagnostic::warning(pat, "Unreachable match arm")
+                                .primary_label("This arm is never taken")
+                                .note(
+                                    "All values matching this pattern are matched by earlier arms",
+                                ),
+                        );
+                    }
+                }
+
                 result.append(operand.lower(ctx)?);
                 let mut operands = vec![];
+                let mut arm_locs = vec![];
                 for (pat, result_expr) in branches {
                     result.append(pat.lower(operand.variable(ctx)?, ctx)?);
 
@@ -1793,18 +1957,20 @@ impl ExprLocal for Loc<Expression> {
 
                     operands.push(cond.result_name);
                     operands.push(result_expr.variable(ctx)?);
+                    arm_locs.push(pat.loc());
                 }
 
                 result.push_primary(
                     mir::Statement::Binding(mir::Binding {
                         name: self.variable(ctx)?,
-                        operator: mir::Operator::Match,
+                        operator: mir::Operator::Match { arm_locs },
                         operands,
                         ty: ctx
                             .types
                             .expr_type(self, ctx.symtab.symtab(), &ctx.item_list.types)?
                             .to_mir_type(),
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -1878,6 +2044,7 @@ impl ExprLocal for Loc<Expression> {
                                 operands: vec![signal_name.clone()],
                                 ty: mir::types::Type::Bool,
                                 loc: Some(self.loc()),
+                                verilog_attrs: vec![],
                             }),
                             self,
                         )
@@ -1913,6 +2080,7 @@ impl ExprLocal for Loc<Expression> {
                                 operands: vec![signal_name.clone()],
                                 ty: mir::types::Type::Bool,
                                 loc: Some(self.loc()),
+                                verilog_attrs: vec![],
                             }),
                             self,
                         )
@@ -2007,6 +2175,9 @@ impl ExprLocal for Loc<Expression> {
         handle_special_functions! {
             ["std", "mem", "clocked_memory"] => handle_clocked_memory_decl,
             ["std", "mem", "clocked_memory_init"] => handle_clocked_memory_initial_decl,
+            ["std", "mem", "clocked_memory_masked"] => handle_clocked_memory_decl,
+            ["std", "mem", "single_port_ram"] => handle_single_port_ram,
+            ["std", "mem", "true_dual_port_ram"] => handle_true_dual_port_ram,
             ["std", "mem", "read_memory"] => handle_read_memory,
             ["std", "conv", "trunc"] => handle_trunc,
             ["std", "conv", "sext"] => handle_sext,
@@ -2054,6 +2225,7 @@ impl ExprLocal for Loc<Expression> {
                             .map(|arg| arg.value.variable(ctx))
                             .collect::<Result<_>>()?,
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -2071,6 +2243,7 @@ impl ExprLocal for Loc<Expression> {
                         .map(|arg| arg.value.variable(ctx))
                         .collect::<Result<Vec<_>>>()?,
                     loc: Some(self.loc()),
+                    verilog_attrs: vec![],
                 }),
                 self,
             ),
@@ -2117,6 +2290,7 @@ impl ExprLocal for Loc<Expression> {
                         operator: mir::Operator::Instance {
                             name: instance_name.as_mir(),
                             params,
+                            verilog_params: vec![],
                             loc: Some(self.loc()),
                         },
                         operands: args
@@ -2128,23 +2302,86 @@ impl ExprLocal for Loc<Expression> {
                             .expr_type(self, ctx.symtab.symtab(), &ctx.item_list.types)?
                             .to_mir_type(),
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 );
             }
-            Some(hir::ExecutableItem::BuiltinUnit(name, head)) => {
+            Some(hir::ExecutableItem::BuiltinUnit(name, head, attributes)) => {
                 let (unit_name, type_params) = (name, &head.get_type_params());
 
-                // NOTE: Ideally this check would be done earlier, when defining the generic
-                // builtin. However, at the moment, the compiler does not know if the generic
-                // is an intrinsic until here when it has gone through the list of intrinsics
-                if !type_params.is_empty() {
-                    return Err(Diagnostic::error(
-                        self.loc(),
-                        "Generic builtins cannot be instantiated",
-                    )
-                    .primary_label("Invalid instance")
-                    .secondary_label(head, "Because this is a generic __builtin__"));
+                // Number generics of builtins are passed to the Verilog module as parameters,
+                // followed by any constant parameters listed in the attributes. Generics defined
+                // by where clauses are derived from the others, so they are not passed.
+                let derived = head
+                    .where_clauses
+                    .iter()
+                    .filter_map(|clause| match &clause.inner {
+                        hir::WhereClause::Int { target, .. } => Some(&target.inner),
+                        hir::WhereClause::Type { .. } => None,
+                    })
+                    .collect::<Vec<_>>();
+                let mut verilog_params = vec![];
+                for param in type_params
+                    .iter()
+                    .filter(|param| !derived.contains(&&param.name_id))
+                {
+                    // NOTE: Ideally this check would be done earlier, when defining the generic
+                    // builtin. However, at the moment, the compiler does not know if the generic
+                    // is an intrinsic until here when it has gone through the list of intrinsics
+                    match param.meta {
+                        MetaType::Int | MetaType::Uint | MetaType::Number => {
+                            match TypeState::ungenerify_type(
+                                &instance_list[&param.name_id()],
+                                ctx.symtab.symtab(),
+                                &ctx.item_list.types,
+                            ) {
+                                Some(ConcreteType::Integer(value)) => {
+                                    verilog_params.push(mir::VerilogParameter {
+                                        name: param.ident.to_string(),
+                                        value: mir::VerilogParameterValue::Int(value),
+                                    })
+                                }
+                                _ => {
+                                    return Err(Diagnostic::error(
+                                        self.loc(),
+                                        format!(
+                                            "The value of {} is not known for this instance",
+                                            param.ident
+                                        ),
+                                    )
+                                    .primary_label(format!("Unknown value of {}", param.ident))
+                                    .secondary_label(
+                                        &param.ident,
+                                        format!("{} is passed to the Verilog module", param.ident),
+                                    )
+                                    .help("Specify it with turbofish syntax"))
+                                }
+                            }
+                        }
+                        MetaType::Any | MetaType::Type => {
+                            return Err(Diagnostic::error(
+                                self.loc(),
+                                "Builtins with generic types cannot be instantiated",
+                            )
+                            .primary_label("Invalid instance")
+                            .secondary_label(
+                                &param.ident,
+                                format!("Because {} is a type", param.ident),
+                            )
+                            .note("Only #int and #uint generics can be passed to Verilog modules"))
+                        }
+                    }
+                }
+                for attr in &attributes.0 {
+                    if let hir::Attribute::VerilogParameters { params } = &attr.inner {
+                        verilog_params.extend(params.iter().map(|(name, value)| {
+                            mir::VerilogParameter {
+                                name: name.to_string(),
+                                value: lower_verilog_value(value),
+                            }
+                        }))
+                    }
                 }
 
                 let params = args
@@ -2164,6 +2401,7 @@ impl ExprLocal for Loc<Expression> {
                         operator: mir::Operator::Instance {
                             name: unit_name.as_mir(),
                             params,
+                            verilog_params,
                             loc: Some(self.loc()),
                         },
                         operands: args
@@ -2175,6 +2413,7 @@ impl ExprLocal for Loc<Expression> {
                             .expr_type(self, ctx.symtab.symtab(), &ctx.item_list.types)?
                             .to_mir_type(),
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 );
@@ -2273,8 +2512,10 @@ impl ExprLocal for Loc<Expression> {
                     "Expected exactly 3 types in write port tuple"
                 );
                 let write_ports = size;
+                let mask_w = tup_inner[0].to_mir_type().size();
                 let addr_w = tup_inner[1].to_mir_type().size();
                 let inner_w = tup_inner[2].to_mir_type().size();
+                check_write_mask(&mask_w, &inner_w, args[1].value)?;
 
                 result.push_primary(
                     mir::Statement::Binding(mir::Binding {
@@ -2292,6 +2533,7 @@ impl ExprLocal for Loc<Expression> {
                                 diag_anyhow!(self, "Found negative number of elements for memory")
                             })?,
                             initial,
+                            mask_w,
                         },
                         operands: args
                             .iter()
@@ -2305,6 +2547,7 @@ impl ExprLocal for Loc<Expression> {
                             .expr_type(self, ctx.symtab.symtab(), &ctx.item_list.types)?
                             .to_mir_type(),
                         loc: Some(self.loc()),
+                        verilog_attrs: vec![],
                     }),
                     self,
                 )
@@ -2318,6 +2561,149 @@ impl ExprLocal for Loc<Expression> {
         Ok(result)
     }
 
+    fn handle_single_port_ram(
+        &self,
+        path: &Loc<NameID>,
+        result: StatementList,
+        args: &[Argument<Expression, TypeSpec>],
+        ctx: &mut Context,
+    ) -> Result<StatementList> {
+        self.handle_block_ram(path, result, args, ctx, 1)
+    }
+
+    fn handle_true_dual_port_ram(
+        &self,
+        path: &Loc<NameID>,
+        result: StatementList,
+        args: &[Argument<Expression, TypeSpec>],
+        ctx: &mut Context,
+    ) -> Result<StatementList> {
+        self.handle_block_ram(path, result, args, ctx, 2)
+    }
+
+    /// Block RAMs take a (clock, port) pair for each port, followed by the read during
+    /// write mode
+    fn handle_block_ram(
+        &self,
+        path: &Loc<NameID>,
+        result: StatementList,
+        args: &[Argument<Expression, TypeSpec>],
+        ctx: &mut Context,
+        ports: usize,
+    ) -> Result<StatementList> {
+        // The localimpl macro is a bit stupid
+        let mut result = result;
+
+        // The mode may be any expression which can be evaluated at compile time. The
+        // arguments are already lowered into `result`, so it is evaluated from there
+        let mode_arg = &args[ports * 2];
+        let mode_value = mir::eval::UnitEvaluator::new([])
+            .eval_statements(result.statements_mut(), &mode_arg.value.variable(ctx)?)
+            .map_err(|e| {
+                Diagnostic::error(
+                    mode_arg.value,
+                    "Block RAM read during write mode must be known at compile time",
+                )
+                .primary_label("Value not known at compile time")
+                .note(e.to_string())
+            })?;
+
+        let ConcreteType::Enum { options } =
+            ctx.types
+                .expr_type(mode_arg.value, ctx.symtab.symtab(), &ctx.item_list.types)?
+        else {
+            diag_bail!(
+                mode_arg.value,
+                "Block RAM read during write mode was not an enum"
+            )
+        };
+        // The tag is in the most significant bits of the value
+        let tag_shift = mode_value.width() - mir::enum_util::tag_size(options.len());
+        let variant = (mode_value.assume_uint() >> tag_shift.to_usize().unwrap_or(0))
+            .to_usize()
+            .and_then(|tag| options.get(tag))
+            .map(|(name, _)| name.clone())
+            .ok_or_else(|| diag_anyhow!(mode_arg.value, "Read during write mode had no variant"))?;
+
+        let read_during_write = [
+            ("ReadFirst", mir::ReadDuringWrite::ReadFirst),
+            ("WriteFirst", mir::ReadDuringWrite::WriteFirst),
+            ("NoChange", mir::ReadDuringWrite::NoChange),
+        ]
+        .into_iter()
+        .find(|(name, _)| {
+            let path = Path::from_strs(&["std", "mem", "ReadDuringWrite", name]).nowhere();
+            ctx.symtab.symtab().lookup_id(&path).ok().as_ref() == Some(&variant)
+        })
+        .map(|(_, mode)| mode)
+        .ok_or_else(|| {
+            Diagnostic::error(
+                mode_arg.value,
+                format!("Block RAMs do not support the read during write mode {variant}"),
+            )
+            .primary_label(format!("{variant} is not supported"))
+            .note("Use ReadDuringWrite::ReadFirst, ReadDuringWrite::WriteFirst or ReadDuringWrite::NoChange")
+        })?;
+
+        let port_t =
+            ctx.types
+                .expr_type(args[1].value, ctx.symtab.symtab(), &ctx.item_list.types)?;
+        let ConcreteType::Tuple(port_inner) = port_t else {
+            diag_bail!(args[1].value, "Block RAM port was not a tuple")
+        };
+        let mask_w = port_inner[0].to_mir_type().size();
+        let addr_w = port_inner[1].to_mir_type().size();
+        let inner_w = port_inner[2].to_mir_type().size();
+        check_write_mask(&mask_w, &inner_w, args[1].value)?;
+
+        let elems = match ctx.item_list.executables.get(path) {
+            Some(hir::ExecutableItem::BuiltinUnit(_, head, _)) => {
+                let num_elements = head.get_type_params()[0].name_id();
+                let tok = GenericListToken::Expression(self.id);
+                let instance_list = ctx.types.get_generic_list(&tok);
+                match TypeState::ungenerify_type(
+                    &instance_list[&num_elements],
+                    ctx.symtab.symtab(),
+                    &ctx.item_list.types,
+                ) {
+                    Some(ConcreteType::Integer(size)) => size.to_biguint().ok_or_else(|| {
+                        diag_anyhow!(self, "Found negative number of elements for block RAM")
+                    })?,
+                    _ => diag_bail!(self, "Block RAM size was not an integer"),
+                }
+            }
+            _ => diag_bail!(path, "Block RAM was not a builtin"),
+        };
+
+        result.push_primary(
+            mir::Statement::Binding(mir::Binding {
+                name: self.variable(ctx)?,
+                operator: mir::Operator::DeclBlockRam {
+                    addr_w,
+                    inner_w,
+                    elems,
+                    mask_w,
+                    read_during_write,
+                    initial: None,
+                },
+                operands: args
+                    .iter()
+                    .take(ports * 2)
+                    .map(|arg| arg.value.variable(ctx))
+                    .collect::<Result<Vec<_>>>()?,
+                ty: ctx
+                    .types
+                    .expr_type(self, ctx.symtab.symtab(), &ctx.item_list.types)?
+                    .to_mir_type(),
+                loc: Some(self.loc()),
+                verilog_attrs: vec![],
+            }),
+            self,
+        );
+
+        Ok(result)
+    }
+
     /// Result is the initial statement list to expand and return
     fn handle_read_memory(
         &self,
@@ -2344,6 +2730,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![target.variable(ctx)?, index.variable(ctx)?],
                 ty: self_type,
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2391,6 +2778,7 @@ impl ExprLocal for Loc<Expression> {
                     .expr_type(self, ctx.symtab.symtab(), &ctx.item_list.types)?
                     .to_mir_type(),
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2452,6 +2840,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?],
                 ty: self_type,
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2509,6 +2898,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?],
                 ty: self_type,
                 loc: None,
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2584,6 +2974,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?],
                 ty: self_type,
                 loc: None,
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2614,6 +3005,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?],
                 ty: self_type,
                 loc: None,
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2660,6 +3052,7 @@ impl ExprLocal for Loc<Expression> {
                     operands: vec![args[0].value.variable(ctx)?, args[1].value.variable(ctx)?],
                     ty: self_type,
                     loc: None,
+                    verilog_attrs: vec![],
                 }),
                 self,
             );
@@ -2689,6 +3082,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?, args[1].value.variable(ctx)?],
                 ty: self_type,
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2719,6 +3113,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?],
                 ty: self_type,
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2747,6 +3142,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?],
                 ty: self_type,
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2775,6 +3171,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?],
                 ty: self_type,
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2803,6 +3200,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?],
                 ty: self_type,
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2842,6 +3240,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?, args[1].value.variable(ctx)?],
                 ty: self_type,
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2881,6 +3280,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?, args[1].value.variable(ctx)?],
                 ty: self_type,
                 loc: Some(self.loc()),
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2911,6 +3311,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![],
                 ty: self_type,
                 loc: None,
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2941,6 +3342,7 @@ impl ExprLocal for Loc<Expression> {
                 operands: vec![args[0].value.variable(ctx)?],
                 ty: self_type,
                 loc: None,
+                verilog_attrs: vec![],
             }),
             self,
         );
@@ -2953,6 +3355,22 @@ pub fn bits_str(bits: BigUint) -> String {
     format!("{} bit{}", bits, if bits == One::one() { "" } else { "s" })
 }
 
+/// Memory write masks are either a single write enable, or one enable bit for each byte
+/// of the element
+fn check_write_mask(mask_w: &BigUint, inner_w: &BigUint, port: &Loc<Expression>) -> Result<()> {
+    let bytes = (inner_w + 7u32) / 8u32;
+    if mask_w.is_one() || *mask_w == bytes {
+        Ok(())
+    } else {
+        Err(Diagnostic::error(
+            port,
+            format!("Write mask has {mask_w} bits but the elements have {bytes} bytes"),
+        )
+        .primary_label(format!("Expected a write mask of 1 or {bytes} bits"))
+        .note("Write masks have one bit per byte of the element, or a single bit which writes the whole element"))
+    }
+}
+
 pub struct Context<'a> {
     pub symtab: &'a mut FrozenSymtab,
     pub idtracker: &'a mut ExprIdTracker,
@@ -2963,10 +3381,14 @@ pub struct Context<'a> {
     pub unit_generic_list: &'a Option<GenericListToken>,
     pub subs: &'a mut Substitutions,
     pub diag_handler: &'a mut DiagHandler,
+    /// The source code being compiled, used to find files relative to the code which
+    /// refers to them
+    pub code: &'a CodeBundle,
     pub pipeline_context: &'a mut MaybePipelineContext,
     pub self_mono_item: Option<MonoItem>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn generate_unit<'a>(
     unit: &Unit,
     name: UnitName,
@@ -2979,6 +3401,7 @@ pub fn generate_unit<'a>(
     name_map: &mut BTreeMap<NameID, NameID>,
     mono_state: &mut MonoState,
     diag_handler: &mut DiagHandler,
+    code: &CodeBundle,
     name_source_map: &mut NameSourceMap,
     self_mono_item: Option<MonoItem>,
     opt_passes: &[&dyn MirPass],
@@ -3025,7 +3448,7 @@ pub fn generate_unit<'a>(
                 })
             },
         )
-        .collect::<Result<_>>()?;
+        .collect::<Result<Vec<_>>>()?;
 
     let mut statements = StatementList::new();
     let subs = &mut Substitutions::new();
@@ -3040,25 +3463,30 @@ pub fn generate_unit<'a>(
         unit_generic_list,
         mono_state,
         diag_handler,
+        code,
         pipeline_context,
         self_mono_item,
     };
 
-    if let UnitKind::Pipeline {
+    let pipeline_registers = if let UnitKind::Pipeline {
         depth: _,
         depth_typeexpr_id: _,
     } = unit.head.unit_kind.inner
     {
-        lower_pipeline(
+        Some(lower_pipeline(
             &unit.inputs,
             &unit.body,
             &mut statements,
             &mut ctx,
             name_map,
-        )?;
-    }
+        )?)
+    } else {
+        None
+    };
 
+    let body_start = statements.len();
     statements.append(unit.body.lower(&mut ctx)?);
+    let output = unit.body.variable(&ctx)?;
 
     let output_t = ctx
         .types
@@ -3075,6 +3503,8 @@ pub fn generate_unit<'a>(
 
     let mut local_passes = opt_passes.to_vec();
     let pass_impls = spade_mir::passes::mir_passes();
+    let mut verilog_attrs = vec![];
+    let mut retime = None;
     unit.attributes.lower(&mut |attr| match &attr.inner {
         Attribute::Optimize { passes: new_passes } => {
             for new_pass in new_passes {
@@ -3090,11 +3520,60 @@ pub fn generate_unit<'a>(
             }
             Ok(())
         }
-        Attribute::Fsm { .. } | Attribute::WalTraceable { .. } => Err(attr.report_unused("unit")),
+        // Used by the clock domain crossing check after lowering
+        Attribute::CdcPrimitive => Ok(()),
+        Attribute::VerilogAttrs { attrs } => {
+            verilog_attrs.extend(lower_verilog_attrs(attrs));
+            Ok(())
+        }
+        Attribute::Retime => {
+            retime = Some(attr.loc());
+            Ok(())
+        }
+        Attribute::Fsm { .. }
+        | Attribute::WalTraceable { .. }
+        | Attribute::VerilogParameters { .. } => Err(attr.report_unused("unit")),
     })?;
 
+    // Registers between pipeline stages are only generated for values which are used
+    // in later stages, so unread pipeline registers are not worth reporting
+    let pipeline_register_names = pipeline_registers
+        .iter()
+        .flat_map(|registers| registers.registers.iter().map(|reg| reg.name.clone()))
+        .collect::<HashSet<_>>();
+
+    if let (Some(attr), Some(registers)) = (retime, pipeline_registers) {
+        let stage_boundaries = statements.stage_boundaries().to_vec();
+        let retimed = retiming::retime_pipeline(
+            statements.statements_mut(),
+            body_start,
+            &stage_boundaries,
+            registers,
+            &mir_inputs,
+            &output,
+            ctx.idtracker,
+        );
+        if let Err(reason) = retimed {
+            ctx.diag_handler.lints.emit(
+                Lint::RetimingNotApplied,
+                Diagnostic::warning(attr, "Pipeline was not retimed")
+                    .primary_label(format!("Not retimed because {reason}")),
+            );
+        }
+    }
+
     let mut statements = statements.to_vec(name_source_map);
 
+    for reg in spade_mir::liveness::unread_registers(&statements, &output) {
+        if let (Some(loc), false) = (reg.loc, pipeline_register_names.contains(&reg.name)) {
+            ctx.diag_handler.lints.emit(
+                Lint::UnreadRegister,
+                Diagnostic::warning(loc, "Register is never read")
+                    .primary_label("The value of this register is never used"),
+            );
+        }
+    }
+
     for pass in local_passes.iter().chain(opt_passes) {
         statements = pass.transform_statements(&statements, ctx.idtracker);
     }
@@ -3102,8 +3581,9 @@ pub fn generate_unit<'a>(
     Ok(mir::Entity {
         name: name.as_mir(),
         inputs: mir_inputs,
-        output: unit.body.variable(&ctx)?,
+        output,
         output_type: output_t,
         statements,
+        verilog_attrs,
     })
 }
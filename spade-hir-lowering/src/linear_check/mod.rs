@@ -79,6 +79,8 @@ pub fn visit_statement(
             ty: _,
             value,
             wal_trace: _,
+            memory_init: _,
+            verilog_attrs: _,
         }) => {
             visit_expression(value, linear_state, ctx)?;
             linear_state.consume_expression(value)?;
@@ -89,6 +91,8 @@ pub fn visit_statement(
                 pattern,
                 clock,
                 reset,
+                clock_edge: _,
+                reset_style: _,
                 initial,
                 value,
                 value_type: _,
@@ -128,6 +132,7 @@ pub fn visit_statement(
         },
         Statement::Label(_) => {}
         Statement::Assert(_) => {}
+        Statement::Property(_) => {}
         Statement::WalSuffixed { .. } => {}
         Statement::Set { target, value } => {
             visit_expression(target, linear_state, ctx)?;
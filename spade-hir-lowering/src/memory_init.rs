@@ -0,0 +1,348 @@
+use std::path::{Path, PathBuf};
+
+use num::{BigInt, BigUint, ToPrimitive};
+use spade_common::location_info::Loc;
+use spade_diagnostics::{CodeBundle, Diagnostic};
+use spade_hir::{Expression, MemoryInit, MemoryInitFormat};
+use spade_mir as mir;
+
+use crate::statement_list::StatementList;
+use crate::{Context, ExprLocal, Result};
+
+/// An error found while parsing the content of a memory init file
+#[derive(Debug, PartialEq)]
+pub struct MemoryInitError {
+    /// The line of the file where the error was found. None for raw files
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl MemoryInitError {
+    fn new(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+/// Read the file specified by `init` and use its content as the initial value of
+/// the memory declared by `value`, which must be a memory or block RAM
+pub fn apply_memory_init(
+    init: &Loc<MemoryInit>,
+    value: &Loc<Expression>,
+    result: &mut StatementList,
+    ctx: &mut Context,
+) -> Result<()> {
+    let name = value.variable(ctx)?;
+
+    let (inner_w, elems, initial) = match result.binding_operator_mut(&name) {
+        Some(mir::Operator::DeclClockedMemory {
+            inner_w,
+            elems,
+            initial,
+            ..
+        })
+        | Some(mir::Operator::DeclBlockRam {
+            inner_w,
+            elems,
+            initial,
+            ..
+        }) => (inner_w, elems, initial),
+        _ => {
+            return Err(
+                Diagnostic::error(init, "memory_init can only be used on memories")
+                    .primary_label("memory_init on a non-memory")
+                    .secondary_label(value, "This is not a memory or block RAM"),
+            )
+        }
+    };
+
+    if initial.is_some() {
+        return Err(Diagnostic::error(init, "Memory initialised more than once")
+            .primary_label("memory_init on a memory with initial values")
+            .secondary_label(value, "This memory already has initial values")
+            .note("Use clocked_memory instead of clocked_memory_init"));
+    }
+
+    let path = init_file_path(&init.file, ctx.code);
+    let content = std::fs::read(&path).map_err(|e| {
+        Diagnostic::error(
+            &init.file,
+            format!("Failed to read memory init file {}", path.display()),
+        )
+        .primary_label(format!("{e}"))
+    })?;
+
+    let values = parse_memory_init(&content, init.format, inner_w).map_err(|e| {
+        let position = match e.line {
+            Some(line) => format!("{}:{line}", init.file.inner),
+            None => init.file.inner.clone(),
+        };
+        Diagnostic::error(&init.file, "Invalid memory init file")
+            .primary_label(e.message.clone())
+            .note(format!("At {position}: {}", e.message))
+    })?;
+
+    if BigUint::from(values.len()) != *elems {
+        return Err(Diagnostic::error(
+            &init.file,
+            format!(
+                "Expected {elems} values in memory init file, found {}",
+                values.len()
+            ),
+        )
+        .primary_label(format!("Contains {} values", values.len()))
+        .secondary_label(value, format!("This memory has {elems} elements")));
+    }
+
+    let ty = mir::types::Type::UInt(inner_w.clone());
+    *initial = Some(
+        values
+            .into_iter()
+            .map(|v| {
+                vec![mir::Statement::Constant(
+                    ctx.idtracker.next(),
+                    ty.clone(),
+                    mir::ConstantValue::Int(BigInt::from(v)),
+                )]
+            })
+            .collect(),
+    );
+
+    Ok(())
+}
+
+/// The path of a memory init file. Relative paths are relative to the directory of the
+/// source file containing the attribute
+fn init_file_path(file: &Loc<String>, code: &CodeBundle) -> PathBuf {
+    match code
+        .file_name(file.file_id)
+        .and_then(|source| Path::new(source).parent())
+    {
+        Some(dir) => dir.join(&file.inner),
+        None => PathBuf::from(&file.inner),
+    }
+}
+
+/// Parse the content of a memory init file into one value per memory element. Each value
+/// is checked to fit in `width` bits
+pub fn parse_memory_init(
+    content: &[u8],
+    format: MemoryInitFormat,
+    width: &BigUint,
+) -> std::result::Result<Vec<BigUint>, MemoryInitError> {
+    match format {
+        MemoryInitFormat::Hex => parse_text(content, 16, width),
+        MemoryInitFormat::Bin => parse_text(content, 2, width),
+        MemoryInitFormat::Raw => parse_raw(content, width),
+    }
+}
+
+fn parse_text(
+    content: &[u8],
+    radix: u32,
+    width: &BigUint,
+) -> std::result::Result<Vec<BigUint>, MemoryInitError> {
+    let content = std::str::from_utf8(content)
+        .map_err(|_| MemoryInitError::new(None, "File is not valid UTF-8"))?;
+
+    let mut result = vec![];
+    let mut in_block_comment = false;
+    for (line_idx, line) in content.lines().enumerate() {
+        let line_no = Some(line_idx + 1);
+
+        // Strip comments, which may span several lines in the case of block comments
+        let mut code = String::new();
+        let mut rest = line;
+        loop {
+            if in_block_comment {
+                match rest.find("*/") {
+                    Some(end) => {
+                        in_block_comment = false;
+                        rest = &rest[end + 2..];
+                    }
+                    None => break,
+                }
+            } else {
+                let line_comment = rest.find("//");
+                let block_comment = rest.find("/*");
+                match (line_comment, block_comment) {
+                    (Some(l), Some(b)) if b < l => {
+                        code += &rest[..b];
+                        code.push(' ');
+                        in_block_comment = true;
+                        rest = &rest[b + 2..];
+                    }
+                    (None, Some(b)) => {
+                        code += &rest[..b];
+                        code.push(' ');
+                        in_block_comment = true;
+                        rest = &rest[b + 2..];
+                    }
+                    (Some(l), _) => {
+                        code += &rest[..l];
+                        break;
+                    }
+                    (None, None) => {
+                        code += rest;
+                        break;
+                    }
+                }
+            }
+        }
+
+        for word in code.split_whitespace() {
+            if word.starts_with('@') {
+                return Err(MemoryInitError::new(
+                    line_no,
+                    "Address directives are not supported",
+                ));
+            }
+            let digits = word.replace('_', "");
+            if digits.chars().any(|c| matches!(c, 'x' | 'X' | 'z' | 'Z')) {
+                return Err(MemoryInitError::new(
+                    line_no,
+                    format!("{word} contains undefined bits"),
+                ));
+            }
+            let value = BigUint::parse_bytes(digits.as_bytes(), radix).ok_or_else(|| {
+                MemoryInitError::new(
+                    line_no,
+                    format!(
+                        "{word} is not a {} number",
+                        if radix == 16 { "hexadecimal" } else { "binary" }
+                    ),
+                )
+            })?;
+            check_width(&value, width, line_no)?;
+            result.push(value);
+        }
+    }
+
+    if in_block_comment {
+        return Err(MemoryInitError::new(None, "Unterminated block comment"));
+    }
+
+    Ok(result)
+}
+
+fn parse_raw(
+    content: &[u8],
+    width: &BigUint,
+) -> std::result::Result<Vec<BigUint>, MemoryInitError> {
+    let bytes_per_elem = width
+        .to_usize()
+        .map(|w| ((w + 7) / 8).max(1))
+        .ok_or_else(|| MemoryInitError::new(None, "Memory elements are too wide"))?;
+
+    if content.len() % bytes_per_elem != 0 {
+        return Err(MemoryInitError::new(
+            None,
+            format!(
+                "File size {} is not a multiple of the element size of {bytes_per_elem} bytes",
+                content.len()
+            ),
+        ));
+    }
+
+    content
+        .chunks(bytes_per_elem)
+        .map(|chunk| {
+            let value = BigUint::from_bytes_le(chunk);
+            check_width(&value, width, None)?;
+            Ok(value)
+        })
+        .collect()
+}
+
+fn check_width(
+    value: &BigUint,
+    width: &BigUint,
+    line: Option<usize>,
+) -> std::result::Result<(), MemoryInitError> {
+    if BigUint::from(value.bits()) > *width {
+        Err(MemoryInitError::new(
+            line,
+            format!("{value:#x} does not fit in {width} bits"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(vals: &[u32]) -> Vec<BigUint> {
+        vals.iter().map(|v| BigUint::from(*v)).collect()
+    }
+
+    #[test]
+    fn hex_files_with_comments_parse() {
+        let content = b"// header\n01 ff /* skipped\n 12 */ 1_0\n  a // trailing\n";
+        assert_eq!(
+            parse_memory_init(content, MemoryInitFormat::Hex, &BigUint::from(8u32)),
+            Ok(values(&[0x01, 0xff, 0x10, 0x0a]))
+        );
+    }
+
+    #[test]
+    fn bin_files_parse() {
+        let content = b"0101\n1111 0000\n";
+        assert_eq!(
+            parse_memory_init(content, MemoryInitFormat::Bin, &BigUint::from(4u32)),
+            Ok(values(&[0b0101, 0b1111, 0]))
+        );
+    }
+
+    #[test]
+    fn too_wide_values_are_rejected() {
+        let content = b"00\n100\n";
+        assert_eq!(
+            parse_memory_init(content, MemoryInitFormat::Hex, &BigUint::from(8u32)),
+            Err(MemoryInitError::new(
+                Some(2),
+                "0x100 does not fit in 8 bits"
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_digits_are_rejected() {
+        assert_eq!(
+            parse_memory_init(b"12 2x", MemoryInitFormat::Hex, &BigUint::from(8u32)),
+            Err(MemoryInitError::new(Some(1), "2x contains undefined bits"))
+        );
+        assert_eq!(
+            parse_memory_init(b"012", MemoryInitFormat::Bin, &BigUint::from(8u32)),
+            Err(MemoryInitError::new(Some(1), "012 is not a binary number"))
+        );
+    }
+
+    #[test]
+    fn raw_files_are_little_endian_per_element() {
+        let content = [0x34, 0x02, 0xff, 0x03];
+        assert_eq!(
+            parse_memory_init(&content, MemoryInitFormat::Raw, &BigUint::from(10u32)),
+            Ok(values(&[0x234, 0x3ff]))
+        );
+        let content = [0x34, 0x12];
+        assert_eq!(
+            parse_memory_init(&content, MemoryInitFormat::Raw, &BigUint::from(10u32)),
+            Err(MemoryInitError::new(None, "0x1234 does not fit in 10 bits"))
+        );
+    }
+
+    #[test]
+    fn raw_files_must_contain_whole_elements() {
+        assert_eq!(
+            parse_memory_init(&[1, 2, 3], MemoryInitFormat::Raw, &BigUint::from(16u32)),
+            Err(MemoryInitError::new(
+                None,
+                "File size 3 is not a multiple of the element size of 2 bytes"
+            ))
+        );
+    }
+}
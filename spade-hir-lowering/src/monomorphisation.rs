@@ -5,7 +5,7 @@ use mir::passes::MirPass;
 use spade_common::location_info::Loc;
 use spade_common::{id_tracker::ExprIdTracker, location_info::WithLocation, name::NameID};
 use spade_diagnostics::diagnostic::{Message, Subdiagnostic};
-use spade_diagnostics::{DiagHandler, Diagnostic};
+use spade_diagnostics::{CodeBundle, DiagHandler, Diagnostic};
 use spade_hir::{symbol_table::FrozenSymtab, ExecutableItem, ItemList, UnitName};
 use spade_mir as mir;
 use spade_typeinference::equation::TypeVar;
@@ -21,6 +21,7 @@ use crate::passes::disallow_inout_bindings::InOutChecks;
 use crate::passes::flatten_regs::FlattenRegs;
 use crate::passes::lower_methods::LowerMethods;
 use crate::passes::pass::{Pass, Passable};
+use crate::passes::unused_variables::UnusedVariables;
 
 /// An item to be monomorphised
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -131,8 +132,13 @@ pub struct MirOutput {
     /// Mapping between new names for registers and their previous value. Used
     /// to add type information for registers generated by pipelines
     pub reg_name_map: BTreeMap<NameID, NameID>,
+    /// Diagnostics which do not prevent compilation
+    pub warnings: Vec<Diagnostic>,
+    /// True if the unit is marked `#[cdc_primitive]`
+    pub cdc_primitive: bool,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn compile_items(
     items: &BTreeMap<&NameID, (&ExecutableItem, TypeState)>,
     symtab: &mut FrozenSymtab,
@@ -140,6 +146,7 @@ pub fn compile_items(
     name_source_map: &mut NameSourceMap,
     item_list: &ItemList,
     diag_handler: &mut DiagHandler,
+    code: &CodeBundle,
     wordlength_inference_method: Option<wordlength_inference::InferMethod>,
     opt_passes: &[&dyn MirPass],
 ) -> Vec<Result<MirOutput>> {
@@ -157,10 +164,39 @@ pub fn compile_items(
             }
             ExecutableItem::StructInstance => {}
             ExecutableItem::EnumInstance { .. } => {}
-            ExecutableItem::BuiltinUnit(_, _) => {}
+            ExecutableItem::BuiltinUnit(_, _, _) => {}
         }
     }
 
+    compile_requested_items(
+        state,
+        items,
+        symtab,
+        idtracker,
+        name_source_map,
+        item_list,
+        diag_handler,
+        code,
+        wordlength_inference_method,
+        opt_passes,
+    )
+}
+
+/// Compiles the items which have been requested in `state`, along with the items they
+/// request in turn
+#[allow(clippy::too_many_arguments)]
+pub fn compile_requested_items(
+    mut state: MonoState,
+    items: &BTreeMap<&NameID, (&ExecutableItem, TypeState)>,
+    symtab: &mut FrozenSymtab,
+    idtracker: &mut ExprIdTracker,
+    name_source_map: &mut NameSourceMap,
+    item_list: &ItemList,
+    diag_handler: &mut DiagHandler,
+    code: &CodeBundle,
+    wordlength_inference_method: Option<wordlength_inference::InferMethod>,
+    opt_passes: &[&dyn MirPass],
+) -> Vec<Result<MirOutput>> {
     let mut result = vec![];
     'item_loop: while let Some(item) = state.next_target() {
         let original_item = items.get(&item.source_name.inner);
@@ -216,6 +252,7 @@ pub fn compile_items(
                 // Apply passes to the type checked module
                 let mut u = u.clone();
                 let passes = [
+                    &mut UnusedVariables::new(&mut diag_handler.lints) as &mut dyn Pass,
                     &mut LowerMethods {
                         type_state: &type_state,
                         items: item_list,
@@ -240,6 +277,7 @@ pub fn compile_items(
                     }
                 }
 
+                let mut warnings = vec![];
                 if let Some(method) = wordlength_inference_method {
                     let infer_result = wordlength_inference::infer_and_check(
                         method,
@@ -247,12 +285,21 @@ pub fn compile_items(
                         &u,
                         type_ctx,
                     );
-                    if let Err(e) = infer_result {
-                        result.push(Err(state.add_mono_traceback(e, &item)));
-                        continue;
+                    match infer_result {
+                        Ok(w) => warnings = w,
+                        Err(e) => {
+                            result.push(Err(state.add_mono_traceback(e, &item)));
+                            continue;
+                        }
                     }
                 }
 
+                let cdc_primitive = u
+                    .attributes
+                    .0
+                    .iter()
+                    .any(|attr| matches!(attr.inner, spade_hir::Attribute::CdcPrimitive));
+
                 let self_mono_item = Some(item.clone());
                 let out = generate_unit(
                     &u.inner,
@@ -265,6 +312,7 @@ pub fn compile_items(
                     &mut reg_name_map,
                     &mut state,
                     diag_handler,
+                    code,
                     name_source_map,
                     self_mono_item,
                     opt_passes,
@@ -274,6 +322,8 @@ pub fn compile_items(
                     mir,
                     type_state: type_state.clone(),
                     reg_name_map,
+                    warnings,
+                    cdc_primitive,
                 });
                 result.push(out);
             }
@@ -283,7 +333,7 @@ pub fn compile_items(
             Some((ExecutableItem::EnumInstance { .. }, _)) => {
                 panic!("Requesting compilation of enum instance as module")
             }
-            Some((ExecutableItem::BuiltinUnit(_, _), _)) => {
+            Some((ExecutableItem::BuiltinUnit(_, _, _), _)) => {
                 panic!("Requesting compilation of builtin unit")
             }
             None => {
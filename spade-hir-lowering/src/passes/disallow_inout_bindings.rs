@@ -119,6 +119,7 @@ impl<'a> Pass for InOutChecks<'a> {
                         | spade_hir::Statement::PipelineRegMarker(_)
                         | spade_hir::Statement::Label(_)
                         | spade_hir::Statement::Assert(_)
+                        | spade_hir::Statement::Property(_)
                         | spade_hir::Statement::Set { .. }
                         | spade_hir::Statement::WalSuffixed { .. } => {}
                     }
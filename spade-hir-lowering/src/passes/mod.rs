@@ -2,3 +2,4 @@ pub mod disallow_inout_bindings;
 pub mod flatten_regs;
 pub mod lower_methods;
 pub mod pass;
+pub mod unused_variables;
@@ -1,5 +1,7 @@
 use spade_common::location_info::Loc;
-use spade_hir::{Binding, ExprKind, Expression, PipelineRegMarkerExtra, Register, Statement, Unit};
+use spade_hir::{
+    Binding, ExprKind, Expression, PipelineRegMarkerExtra, Property, Register, Statement, Unit,
+};
 
 use crate::Result;
 
@@ -116,12 +118,16 @@ impl Passable for Loc<Expression> {
                             ty: _,
                             value,
                             wal_trace: _,
+                            memory_init: _,
+                            verilog_attrs: _,
                         }) => value.apply(pass)?,
                         Statement::Register(reg) => {
                             let Register {
                                 pattern: _,
                                 clock,
                                 reset,
+                                clock_edge: _,
+                                reset_style: _,
                                 initial,
                                 value,
                                 value_type: _,
@@ -157,6 +163,21 @@ impl Passable for Loc<Expression> {
                             target: _,
                         } => {}
                         Statement::Assert(expr) => expr.apply(pass)?,
+                        Statement::Property(Property {
+                            kind: _,
+                            clock,
+                            reset,
+                            antecedent,
+                            consequent,
+                        }) => {
+                            subnodes!(clock, consequent);
+                            if let Some(reset) = reset {
+                                subnodes!(reset)
+                            }
+                            if let Some((antecedent, _)) = antecedent {
+                                subnodes!(antecedent)
+                            }
+                        }
                         Statement::Set { target, value } => subnodes!(target, value),
                     }
                 }
@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+
+use spade_common::location_info::Loc;
+use spade_common::name::NameID;
+use spade_diagnostics::{Diagnostic, Lint, Lints};
+use spade_hir::expression::CallKind;
+use spade_hir::{Binding, ExprKind, Expression, Statement, Unit, UnitKind};
+
+use crate::error::Result;
+
+use super::pass::{Pass, Passable};
+
+/// Lints variables which are bound but never read. Variables bound to the output of
+/// an `inst` are reported as unused instance outputs instead. Registers are not
+/// checked here as unread registers are found after lowering to MIR
+pub struct UnusedVariables<'a> {
+    lints: &'a mut Lints,
+    /// Names which are bound, and whether or not they are bound to the output of an `inst`
+    bound: Vec<(Loc<NameID>, bool)>,
+    read: HashSet<NameID>,
+}
+
+impl<'a> UnusedVariables<'a> {
+    pub fn new(lints: &'a mut Lints) -> Self {
+        Self {
+            lints,
+            bound: vec![],
+            read: HashSet::new(),
+        }
+    }
+}
+
+fn is_inst(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExprKind::Call { kind, .. }
+        | ExprKind::MethodCall {
+            call_kind: kind, ..
+        } => {
+            matches!(kind, CallKind::Entity(_) | CallKind::Pipeline { .. })
+        }
+        _ => false,
+    }
+}
+
+impl<'a> Pass for UnusedVariables<'a> {
+    fn visit_expression(&mut self, expression: &mut Loc<Expression>) -> Result<()> {
+        match &expression.kind {
+            ExprKind::Identifier(name) => {
+                self.read.insert(name.clone());
+            }
+            ExprKind::PipelineRef { name, .. } => {
+                self.read.insert(name.inner.clone());
+            }
+            ExprKind::Match(_, branches) => {
+                for (pattern, _) in branches {
+                    self.bound
+                        .extend(pattern.get_names().into_iter().map(|name| (name, false)))
+                }
+            }
+            ExprKind::Block(block) => {
+                for statement in &block.statements {
+                    match &statement.inner {
+                        Statement::Binding(Binding {
+                            pattern,
+                            value,
+                            wal_trace,
+                            ..
+                        }) => {
+                            let inst = is_inst(value);
+                            self.bound
+                                .extend(pattern.get_names().into_iter().map(|name| (name, inst)));
+
+                            // The clock and reset of wal_trace are not visited by passes
+                            if let Some(wal_trace) = wal_trace {
+                                for expr in [&wal_trace.clk, &wal_trace.rst].into_iter().flatten() {
+                                    expr.clone().apply(self)?;
+                                }
+                            }
+                        }
+                        Statement::WalSuffixed { suffix: _, target } => {
+                            self.read.insert(target.inner.clone());
+                        }
+                        Statement::Register(_)
+                        | Statement::Declaration(_)
+                        | Statement::PipelineRegMarker(_)
+                        | Statement::Label(_)
+                        | Statement::Assert(_)
+                        | Statement::Property(_)
+                        | Statement::Set { .. } => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn visit_unit(&mut self, unit: &mut Unit) -> Result<()> {
+        // The clock of a pipeline is used by its pipeline registers
+        let implicitly_used = match unit.head.unit_kind.inner {
+            UnitKind::Pipeline { .. } => 1,
+            UnitKind::Function(_) | UnitKind::Entity => 0,
+        };
+        let inputs = unit
+            .head
+            .inputs
+            .0
+            .iter()
+            .zip(&unit.inputs)
+            .skip(implicitly_used)
+            .filter(|(param, _)| param.no_mangle.is_none() && param.name.inner.0 != "self")
+            .map(|(_, (name, _))| (name.clone(), false))
+            .collect::<Vec<_>>();
+
+        for (name, inst) in inputs.into_iter().chain(self.bound.drain(..)) {
+            let ident = name.1.tail();
+            if self.read.contains(&name.inner) || ident.0.starts_with('_') {
+                continue;
+            }
+
+            let diag = if inst {
+                Diagnostic::warning(
+                    &name,
+                    format!("Output of instance bound to {ident} is unused"),
+                )
+                .primary_label(format!("{ident} is never used"))
+                .note("The instance is still created, but its output is not used")
+                .span_suggest_replace(
+                    "If the output is not needed, discard it",
+                    &name,
+                    "_",
+                )
+            } else {
+                Diagnostic::warning(&name, format!("Unused variable {ident}"))
+                    .primary_label(format!("{ident} is never used"))
+                    .span_suggest_replace(
+                        "If this is intentional, prefix it with an underscore",
+                        &name,
+                        format!("_{ident}"),
+                    )
+            };
+            let lint = if inst {
+                Lint::UnusedInstOutput
+            } else {
+                Lint::UnusedVariable
+            };
+            self.lints.emit(lint, diag);
+        }
+        Ok(())
+    }
+}
@@ -197,7 +197,12 @@ impl Constructor {
     ) -> Vec<Self> {
         match self {
             Self::Wildcard => split_wildcard(ty, other_ctors),
-            Self::IntRange { min, max } => split_int_range(min.clone(), max.clone(), other_ctors),
+            Self::IntRange { min, max } => split_int_range(
+                min.clone(),
+                max.clone(),
+                // Wildcards in the other rows cover the full range of the type
+                other_ctors.flat_map(|ctor| ctor.split(ty, vec![].into_iter())),
+            ),
             _ => vec![self.clone()],
         }
     }
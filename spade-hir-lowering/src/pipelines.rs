@@ -33,6 +33,26 @@ pub struct PipelineContext {
     pub valid_signals: Vec<Option<ValueName>>,
 }
 
+/// A register between two pipeline stages, inserted by [lower_pipeline]
+pub struct PipelineRegister {
+    /// The stage which the register is at the end of
+    pub stage: usize,
+    pub name: ValueName,
+    pub previous: ValueName,
+    /// The binding which selects between the previous and current value of the register
+    /// if the stage has an enable signal
+    pub enable_mux: Option<ValueName>,
+}
+
+/// The pipeline registers generated for a pipeline, along with what is needed
+/// to generate new ones if the registers are moved
+pub struct PipelineRegisters {
+    pub clock: ValueName,
+    /// The enable signal of the registers at the end of each stage, if there is one
+    pub enables: Vec<Option<ValueName>>,
+    pub registers: Vec<PipelineRegister>,
+}
+
 pub enum MaybePipelineContext {
     NotPipeline,
     Pipeline(PipelineContext),
@@ -66,12 +86,15 @@ pub fn handle_statement(
     local_conds: &mut Vec<Option<ValueName>>,
     stage_enable_names: &mut Vec<Option<ValueName>>,
     current_stage: &mut usize,
-) -> Result<()> {
+) -> Result<Vec<PipelineRegister>> {
+    let mut registers = vec![];
     match &statement.inner {
         Statement::Binding(Binding {
             pattern: pat,
             value: expr,
             wal_trace: _,
+            memory_init: _,
+            verilog_attrs: _,
             ty: _,
         }) => {
             let time = expr.inner.kind.available_in(ctx)?;
@@ -127,12 +150,12 @@ pub fn handle_statement(
                     .to_mir_type();
                 // If this stage has an enable signal, generate a mux to optionally select
                 // the previous value, otherwise use the previous value right away
-                let next = if let Some(enable) = &stage_enable_names[*current_stage] {
+                let enable_mux = if let Some(enable) = &stage_enable_names[*current_stage] {
                     let next_name = ValueName::Expr(ctx.idtracker.next());
                     statements.push_secondary(
                         mir::Statement::Binding(mir::Binding {
                             name: next_name.clone(),
-                            operator: mir::Operator::Select,
+                            operator: mir::Operator::Select { from_if: false },
                             operands: vec![
                                 enable.clone(),
                                 reg.previous.value_name(),
@@ -140,31 +163,45 @@ pub fn handle_statement(
                             ],
                             ty: reg_type.clone(),
                             loc: Some(statement.loc()),
+                            verilog_attrs: vec![],
                         }),
                         &reg.original,
                         "Pipeline enable mux",
                     );
-                    next_name
+                    Some(next_name)
                 } else {
-                    reg.previous.value_name()
+                    None
                 };
+                let next = enable_mux
+                    .clone()
+                    .unwrap_or_else(|| reg.previous.value_name());
+                let name = reg
+                    .new
+                    .value_name_with_alternate_source(ValueNameSource::Name(
+                        reg.original.inner.clone(),
+                    ));
+                registers.push(PipelineRegister {
+                    stage: *current_stage,
+                    name: name.clone(),
+                    previous: reg.previous.value_name(),
+                    enable_mux,
+                });
 
                 statements.push_secondary(
                     mir::Statement::Register(mir::Register {
-                        name: reg
-                            .new
-                            .value_name_with_alternate_source(ValueNameSource::Name(
-                                reg.original.inner.clone(),
-                            )),
+                        name,
                         ty: reg_type,
                         clock: clock.value_name(),
                         reset: None,
+                        clock_edge: mir::ClockEdge::Rising,
+                        reset_style: mir::ResetStyle::default(),
                         initial: None,
                         value: next,
                         traced: None,
                         // NOTE: Do we/can we also want to point to the declaration
                         // of the variable?
                         loc: Some(statement.loc()),
+                        verilog_attrs: vec![],
                     }),
                     &reg.original,
                     "Pipelined",
@@ -176,7 +213,7 @@ pub fn handle_statement(
         Statement::Label(_) => {
             // Labels have no effect on codegen
         }
-        Statement::Assert(_) => {
+        Statement::Assert(_) | Statement::Property(_) => {
             // Assertions have no effect on pipeline state
         }
         Statement::WalSuffixed { .. } => {
@@ -186,7 +223,7 @@ pub fn handle_statement(
             // Set have no effect on pipeline state
         }
     }
-    Ok(())
+    Ok(registers)
 }
 
 pub fn lower_pipeline<'a>(
@@ -196,7 +233,7 @@ pub fn lower_pipeline<'a>(
     ctx: &mut Context,
     // Map of names generated by codegen to the original name in the source code.
     name_map: &mut BTreeMap<NameID, NameID>,
-) -> Result<()> {
+) -> Result<PipelineRegisters> {
     let clock = &hir_inputs[0].0;
 
     let (body_statements, _) = if let ExprKind::Block(block) = &body.kind {
@@ -251,8 +288,9 @@ pub fn lower_pipeline<'a>(
 
     let mut current_stage = 0;
     let mut local_conds = vec![];
+    let mut registers = vec![];
     for statement in body_statements {
-        handle_statement(
+        registers.extend(handle_statement(
             statement,
             ctx,
             name_map,
@@ -261,7 +299,7 @@ pub fn lower_pipeline<'a>(
             &mut local_conds,
             &mut stage_enable_names,
             &mut current_stage,
-        )?
+        )?)
     }
 
     // Codegen enable signals for the stages that need them. We need to generate them
@@ -280,6 +318,7 @@ pub fn lower_pipeline<'a>(
                     operands: vec![local.clone()],
                     ty: mir::types::Type::Bool,
                     loc: None,
+                    verilog_attrs: vec![],
                 }));
                 current_enable = Some(name.clone());
             }
@@ -295,6 +334,7 @@ pub fn lower_pipeline<'a>(
                     operands: vec![prev.clone()],
                     ty: mir::types::Type::Bool,
                     loc: None,
+                    verilog_attrs: vec![],
                 }));
                 current_enable = Some(name.clone());
             }
@@ -308,6 +348,7 @@ pub fn lower_pipeline<'a>(
                     operands: vec![local.clone(), prev.clone()],
                     ty: mir::types::Type::Bool,
                     loc: None,
+                    verilog_attrs: vec![],
                 }));
                 current_enable = Some(name.clone());
             }
@@ -315,6 +356,7 @@ pub fn lower_pipeline<'a>(
         }
     }
 
+    let enables = stage_enable_names.clone();
     let mut ready_signals = stage_enable_names.into_iter().collect::<Vec<_>>();
     // NOTE: The last stage needs a ready signal because you *can* use `stage.ready`
     // after the last `reg` in the final output expression, but it will be `None` because
@@ -394,10 +436,13 @@ pub fn lower_pipeline<'a>(
                     ty: mir::types::Type::Bool,
                     clock: clock.value_name(),
                     reset: None,
+                    clock_edge: mir::ClockEdge::Rising,
+                    reset_style: mir::ResetStyle::default(),
                     initial: None,
                     value: next,
                     loc: None,
                     traced: None,
+                    verilog_attrs: vec![],
                 }));
                 prev_valid = Some(valid_name.clone());
                 valid_signals.push(Some(valid_name))
@@ -414,7 +459,11 @@ pub fn lower_pipeline<'a>(
         valid_signals,
     });
 
-    Ok(())
+    Ok(PipelineRegisters {
+        clock: clock.value_name(),
+        enables,
+        registers,
+    })
 }
 
 pub enum MaybeConst {
@@ -468,6 +517,7 @@ pub fn constexpr_and(
                 operands: vec![l.clone(), r.clone()],
                 ty: mir::types::Type::Bool,
                 loc: None,
+                verilog_attrs: vec![],
             }));
 
             MaybeConst::Val(new_name)
@@ -495,10 +545,11 @@ pub fn constexpr_select(
 
             statements.push_anonymous(mir::Statement::Binding(mir::Binding {
                 name: new_name.clone(),
-                operator: mir::Operator::Select,
+                operator: mir::Operator::Select { from_if: false },
                 operands: vec![sel, t, f],
                 ty: mir::types::Type::Bool,
                 loc: None,
+                verilog_attrs: vec![],
             }));
 
             MaybeConst::Val(new_name)
@@ -522,6 +573,7 @@ pub fn constexpr_inv(
                 operands: vec![name],
                 ty: mir::types::Type::Bool,
                 loc: None,
+                verilog_attrs: vec![],
             }));
 
             MaybeConst::Val(new_name)
@@ -0,0 +1,628 @@
+//! Retiming of pipelines marked with `#[retime]`.
+//!
+//! The registers inserted at each `reg` by [crate::pipelines::lower_pipeline] are removed,
+//! and each binding of combinational logic is assigned to a new stage such that the largest
+//! estimated delay of a stage is as small as possible. Registers are then inserted wherever
+//! a value is used in a later stage than the one it is computed in.
+//!
+//! Statements whose timing can be observed are kept in their stage. These are instances,
+//! memories, registers, anything involving ports, and bindings which use `stage.valid`,
+//! `stage.ready` or values from other stages through pipeline references. Everything these
+//! statements use, as well as the output of the pipeline, is still available under the same
+//! name in the same stage, so the latency and the meaning of all names in the pipeline is
+//! preserved.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use num::{BigUint, ToPrimitive, Zero};
+use spade_common::id_tracker::ExprIdTracker;
+use spade_mir::{
+    types::Type, Binding, ClockEdge, MirInput, Operator, Register, ResetStyle, Statement, ValueName,
+};
+
+use crate::pipelines::PipelineRegisters;
+
+/// A value, identified by the name of the statement which computes it, in a pipeline stage
+type Timed = (ValueName, usize);
+
+/// The reason a pipeline was left unchanged by [retime_pipeline]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotRetimed {
+    /// A pipeline register stores a value which is not computed by a statement in the unit
+    UnknownRegisterValue,
+    /// A pipeline register stores a value from another stage than the register, which is
+    /// only supported for the outputs of pipelined instances
+    DelayedRegisterValue,
+    /// The bindings of the pipeline depend on each other in a loop
+    CombinationalLoop,
+    /// The bindings of the pipeline could not be placed in its stages
+    NoSchedule,
+}
+
+impl std::fmt::Display for NotRetimed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotRetimed::UnknownRegisterValue => {
+                write!(
+                    f,
+                    "a pipeline register stores a value which is not computed in the unit"
+                )
+            }
+            NotRetimed::DelayedRegisterValue => {
+                write!(f, "a pipeline register stores a value from another stage")
+            }
+            NotRetimed::CombinationalLoop => {
+                write!(f, "the pipeline contains a combinational loop")
+            }
+            NotRetimed::NoSchedule => {
+                write!(
+                    f,
+                    "the bindings of the pipeline could not be placed in its stages"
+                )
+            }
+        }
+    }
+}
+
+/// Moves the registers of a pipeline to balance the estimated delay of its stages.
+///
+/// `statements` are the statements of the whole unit, where the pipeline body starts
+/// at `body_start`, and the stages of the body start at `stage_boundaries`.
+///
+/// If the pipeline contains something which the retiming does not understand,
+/// the statements are left unchanged and the reason is returned.
+pub fn retime_pipeline(
+    statements: &mut Vec<Statement>,
+    body_start: usize,
+    stage_boundaries: &[usize],
+    registers: PipelineRegisters,
+    inputs: &[MirInput],
+    output: &ValueName,
+    idtracker: &mut ExprIdTracker,
+) -> Result<(), NotRetimed> {
+    let retimer = Retimer::new(
+        statements,
+        body_start,
+        stage_boundaries,
+        &registers,
+        inputs,
+        output,
+    )?;
+
+    let mut best = retimer
+        .schedule(statements, u64::MAX)
+        .ok_or(NotRetimed::NoSchedule)?;
+
+    // Find the smallest stage delay for which there is a valid schedule
+    let mut low = 0;
+    let mut high = best.values().map(|(_, delay)| *delay).max().unwrap_or(0);
+    while low < high {
+        let target = (low + high) / 2;
+        match retimer.schedule(statements, target) {
+            Some(placement) => {
+                high = target;
+                best = placement;
+            }
+            None => low = target + 1,
+        }
+    }
+
+    retimer.apply(statements, body_start, &best, &registers, idtracker);
+    Ok(())
+}
+
+struct Node {
+    index: usize,
+    delay: u64,
+    movable: bool,
+}
+
+struct Retimer {
+    depth: usize,
+    /// The value and stage which each name in the pipeline refers to
+    timing: HashMap<ValueName, Timed>,
+    /// The stage in which each value was computed before retiming
+    stages: HashMap<ValueName, usize>,
+    /// The values in the order they are defined
+    roots: Vec<ValueName>,
+    types: HashMap<ValueName, Type>,
+    /// Bindings in the pipeline body in topological order
+    nodes: Vec<Node>,
+    /// Names used by statements which are not moved
+    fixed_uses: Vec<ValueName>,
+    /// Statements replaced by the retiming
+    removed: HashSet<ValueName>,
+}
+
+impl Retimer {
+    fn new(
+        statements: &[Statement],
+        body_start: usize,
+        stage_boundaries: &[usize],
+        registers: &PipelineRegisters,
+        inputs: &[MirInput],
+        output: &ValueName,
+    ) -> Result<Self, NotRetimed> {
+        let stage_of = |idx: usize| stage_boundaries.iter().filter(|b| **b <= idx).count();
+
+        let mut timing = HashMap::new();
+        let mut stages = HashMap::new();
+        let mut roots = vec![];
+        let mut types = HashMap::new();
+        let mut constants = HashSet::new();
+
+        for input in inputs {
+            timing.insert(input.val_name.clone(), (input.val_name.clone(), 0));
+            stages.insert(input.val_name.clone(), 0);
+            roots.push(input.val_name.clone());
+            types.insert(input.val_name.clone(), input.ty.clone());
+        }
+        for (idx, stmt) in statements.iter().enumerate() {
+            let (name, ty) = match stmt {
+                Statement::Binding(b) => (b.name.clone(), b.ty.clone()),
+                Statement::Register(r) => (r.name.clone(), r.ty.clone()),
+                Statement::Constant(id, ty, _) => {
+                    constants.insert(ValueName::Expr(*id));
+                    types.insert(ValueName::Expr(*id), ty.clone());
+                    continue;
+                }
+                Statement::Assert(_)
+                | Statement::Property(_)
+                | Statement::Set { .. }
+                | Statement::WalTrace { .. } => continue,
+            };
+            if idx >= body_start {
+                let stage = stage_of(idx);
+                timing.insert(name.clone(), (name.clone(), stage));
+                stages.insert(name.clone(), stage);
+                roots.push(name.clone());
+            }
+            types.insert(name, ty);
+        }
+
+        let mut pinned = HashSet::new();
+        for reg in &registers.registers {
+            let (root, previous_stage) = timing
+                .get(&reg.previous)
+                .cloned()
+                .ok_or(NotRetimed::UnknownRegisterValue)?;
+            if previous_stage != reg.stage {
+                // Values computed by a pipelined instance are only registered once they
+                // are available, which is some stages after the instance
+                if root != reg.previous {
+                    return Err(NotRetimed::DelayedRegisterValue);
+                }
+                timing.insert(root.clone(), (root.clone(), reg.stage));
+                stages.insert(root.clone(), reg.stage);
+                pinned.insert(root.clone());
+            }
+            timing.insert(reg.name.clone(), (root, reg.stage + 1));
+        }
+
+        let removed = registers
+            .registers
+            .iter()
+            .flat_map(|reg| [Some(reg.name.clone()), reg.enable_mux.clone()])
+            .flatten()
+            .collect::<HashSet<_>>();
+
+        let can_register = |name: &ValueName| types.get(name).is_some_and(can_register);
+
+        let mut nodes = vec![];
+        let mut fixed_uses = vec![output.clone()];
+        for (idx, stmt) in statements.iter().enumerate() {
+            if let Statement::Binding(b) = stmt {
+                if removed.contains(&b.name) {
+                    continue;
+                }
+                if idx >= body_start {
+                    let delay = estimated_delay(&b.operator, &b.operands, &b.ty, &types);
+                    let stage = stages[&b.name];
+                    let movable = delay.is_some()
+                        && !pinned.contains(&b.name)
+                        && b.verilog_attrs.is_empty()
+                        && can_register(&b.name)
+                        && b.operands.iter().all(|op| {
+                            constants.contains(op)
+                                || (timing.get(op).is_some_and(|(_, t)| *t == stage)
+                                    && can_register(op))
+                        });
+                    nodes.push(Node {
+                        index: idx,
+                        delay: delay.unwrap_or(0),
+                        movable,
+                    });
+                    if movable {
+                        continue;
+                    }
+                }
+            }
+            match stmt {
+                Statement::Register(r) if removed.contains(&r.name) => {}
+                _ => fixed_uses.extend(operands(stmt).into_iter().cloned()),
+            }
+        }
+
+        // Sort the bindings such that the values they use are computed before them
+        let defined_by = nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| match &statements[node.index] {
+                Statement::Binding(b) => Some((b.name.clone(), i)),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        let mut users = vec![vec![]; nodes.len()];
+        let mut unsorted_operands = vec![0; nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            for op in operands(&statements[node.index]) {
+                if let Some(def) = timing.get(op).and_then(|(root, _)| defined_by.get(root)) {
+                    users[*def].push(i);
+                    unsorted_operands[i] += 1;
+                }
+            }
+        }
+        let mut ready = (0..nodes.len())
+            .filter(|i| unsorted_operands[*i] == 0)
+            .collect::<Vec<_>>();
+        let mut order = vec![];
+        while let Some(i) = ready.pop() {
+            order.push(i);
+            for user in &users[i] {
+                unsorted_operands[*user] -= 1;
+                if unsorted_operands[*user] == 0 {
+                    ready.push(*user);
+                }
+            }
+        }
+        if order.len() != nodes.len() {
+            return Err(NotRetimed::CombinationalLoop);
+        }
+        let mut nodes = nodes.into_iter().map(Some).collect::<Vec<_>>();
+        let nodes = order
+            .into_iter()
+            .map(|i| nodes[i].take().unwrap())
+            .collect();
+
+        Ok(Self {
+            depth: stage_boundaries.len(),
+            timing,
+            stages,
+            roots,
+            types,
+            nodes,
+            fixed_uses,
+            removed,
+        })
+    }
+
+    /// Assigns stages to the bindings such that no chain of movable bindings within a
+    /// stage has a delay above `target` unless a single binding does. Bindings are placed
+    /// as early as possible. The result is the stage of each value along with the
+    /// delay until it is computed in that stage, or None if the bindings do not fit
+    /// in the pipeline.
+    fn schedule(
+        &self,
+        statements: &[Statement],
+        target: u64,
+    ) -> Option<HashMap<ValueName, (usize, u64)>> {
+        let mut placed = self
+            .stages
+            .iter()
+            .map(|(name, stage)| (name.clone(), (*stage, 0)))
+            .collect::<HashMap<_, _>>();
+
+        for node in &self.nodes {
+            let Statement::Binding(b) = &statements[node.index] else {
+                unreachable!("Non-binding in retimed nodes")
+            };
+            let operands = b
+                .operands
+                .iter()
+                .filter_map(|op| self.timing.get(op))
+                .map(|(root, t)| Some((placed.get(root)?, *t)))
+                .collect::<Option<Vec<_>>>()?;
+
+            let placement = if node.movable {
+                let stage = operands.iter().map(|((s, _), _)| *s).max().unwrap_or(0);
+                let incoming: u64 = operands
+                    .iter()
+                    .filter(|((s, _), _)| *s == stage)
+                    .map(|((_, delay), _)| *delay)
+                    .max()
+                    .unwrap_or(0);
+                if incoming > 0 && node.delay > 0 && incoming.saturating_add(node.delay) > target {
+                    (stage + 1, node.delay)
+                } else {
+                    (stage, incoming + node.delay)
+                }
+            } else {
+                let stage = self.stages[&b.name];
+                let incoming = operands
+                    .iter()
+                    .filter(|((s, _), t)| *s == stage && *t == stage)
+                    .map(|((_, delay), _)| *delay)
+                    .max()
+                    .unwrap_or(0);
+                (stage, incoming + node.delay)
+            };
+            if placement.0 > self.depth {
+                return None;
+            }
+            placed.insert(b.name.clone(), placement);
+        }
+
+        for name in &self.fixed_uses {
+            if let Some((root, t)) = self.timing.get(name) {
+                if placed.get(root)?.0 > *t {
+                    return None;
+                }
+            }
+        }
+
+        Some(placed)
+    }
+
+    fn apply(
+        &self,
+        statements: &mut Vec<Statement>,
+        body_start: usize,
+        placed: &HashMap<ValueName, (usize, u64)>,
+        registers: &PipelineRegisters,
+        idtracker: &mut ExprIdTracker,
+    ) {
+        // The stages in which each value is used
+        let mut needed: HashMap<&ValueName, BTreeSet<usize>> = HashMap::new();
+        for name in &self.fixed_uses {
+            if let Some((root, t)) = self.timing.get(name) {
+                needed.entry(root).or_default().insert(*t);
+            }
+        }
+        for node in self.nodes.iter().filter(|node| node.movable) {
+            let Statement::Binding(b) = &statements[node.index] else {
+                unreachable!("Non-binding in retimed nodes")
+            };
+            for op in &b.operands {
+                if let Some((root, _)) = self.timing.get(op) {
+                    needed.entry(root).or_default().insert(placed[&b.name].0);
+                }
+            }
+        }
+
+        let original_names = self
+            .timing
+            .iter()
+            .map(|(name, timed)| (timed.clone(), name.clone()))
+            .collect::<HashMap<_, _>>();
+        let locs = statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::Register(r) => Some((r.name.clone(), r.loc)),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut names: HashMap<Timed, ValueName> = HashMap::new();
+        let mut renamed_defs = HashMap::new();
+        let mut new_registers = vec![];
+        for root in &self.roots {
+            let Some(needed) = needed.get(root) else {
+                continue;
+            };
+            let original_stage = self.stages[root];
+            let first = placed[root].0;
+            let last = needed.last().copied().unwrap_or(first).max(first);
+
+            // Names keep referring to the same value in the same stage. If the value
+            // is now computed in an earlier stage, its own name is given to a register
+            let def_name = if first == original_stage {
+                root.clone()
+            } else if let Some(name) = original_names.get(&(root.clone(), first)) {
+                name.clone()
+            } else if needed.contains(&original_stage) {
+                ValueName::Expr(idtracker.next())
+            } else {
+                root.clone()
+            };
+            if &def_name != root {
+                renamed_defs.insert(root.clone(), def_name.clone());
+            }
+            names.insert((root.clone(), first), def_name.clone());
+
+            let ty = &self.types[root];
+            for stage in first + 1..=last {
+                let name = original_names
+                    .get(&(root.clone(), stage))
+                    .filter(|name| **name != def_name)
+                    .cloned()
+                    .unwrap_or_else(|| ValueName::Expr(idtracker.next()));
+                let previous = names[&(root.clone(), stage - 1)].clone();
+
+                let value = if let Some(enable) = &registers.enables[stage - 1] {
+                    let mux = ValueName::Expr(idtracker.next());
+                    new_registers.push(Statement::Binding(Binding {
+                        name: mux.clone(),
+                        operator: Operator::Select { from_if: false },
+                        operands: vec![enable.clone(), previous, name.clone()],
+                        ty: ty.clone(),
+                        loc: None,
+                        verilog_attrs: vec![],
+                    }));
+                    mux
+                } else {
+                    previous
+                };
+
+                new_registers.push(Statement::Register(Register {
+                    name: name.clone(),
+                    ty: ty.clone(),
+                    clock: registers.clock.clone(),
+                    reset: None,
+                    clock_edge: ClockEdge::Rising,
+                    reset_style: ResetStyle::default(),
+                    initial: None,
+                    value,
+                    loc: locs.get(&name).copied().flatten(),
+                    traced: None,
+                    verilog_attrs: vec![],
+                }));
+                names.insert((root.clone(), stage), name);
+            }
+        }
+
+        for node in self.nodes.iter().filter(|node| node.movable) {
+            let Statement::Binding(b) = &mut statements[node.index] else {
+                unreachable!("Non-binding in retimed nodes")
+            };
+            let stage = placed[&b.name].0;
+            for op in &mut b.operands {
+                if let Some((root, _)) = self.timing.get(op) {
+                    *op = names[&(root.clone(), stage)].clone();
+                }
+            }
+            if let Some(new_name) = renamed_defs.get(&b.name) {
+                b.name = new_name.clone();
+            }
+        }
+
+        let body = statements.split_off(body_start);
+        statements.retain(|stmt| match stmt {
+            Statement::Binding(b) => !self.removed.contains(&b.name),
+            Statement::Register(r) => !self.removed.contains(&r.name),
+            _ => true,
+        });
+        statements.extend(new_registers);
+        statements.extend(body);
+    }
+}
+
+fn operands(stmt: &Statement) -> Vec<&ValueName> {
+    match stmt {
+        Statement::Binding(b) => b.operands.iter().collect(),
+        Statement::Register(r) => [Some(&r.clock), Some(&r.value)]
+            .into_iter()
+            .chain(
+                r.reset
+                    .iter()
+                    .flat_map(|(trig, value)| [Some(trig), Some(value)]),
+            )
+            .flatten()
+            .collect(),
+        Statement::Constant(_, _, _) => vec![],
+        Statement::Assert(name) => vec![&name.inner],
+        Statement::Property(p) => [Some(&p.clock), p.reset.as_ref(), Some(&p.consequent)]
+            .into_iter()
+            .chain([p.antecedent.as_ref().map(|(a, _)| a)])
+            .flatten()
+            .collect(),
+        Statement::Set { target, value } => vec![&target.inner, &value.inner],
+        Statement::WalTrace { name, val, .. } => vec![name, val],
+    }
+}
+
+/// Values of types with wires going backwards or memories can not be put in registers
+fn can_register(ty: &Type) -> bool {
+    fn only_forward(ty: &Type) -> bool {
+        match ty {
+            Type::Int(_) | Type::UInt(_) | Type::Bool | Type::Void => true,
+            Type::Tuple(inner) => inner.iter().all(only_forward),
+            Type::Struct(fields) => fields.iter().all(|(_, ty)| only_forward(ty)),
+            Type::Array { inner, .. } => only_forward(inner),
+            Type::Enum(variants) => variants.iter().flatten().all(only_forward),
+            Type::Memory { .. } | Type::Backward(_) | Type::InOut(_) => false,
+        }
+    }
+    ty.size() != BigUint::zero() && only_forward(ty)
+}
+
+fn clog2(value: u64) -> u64 {
+    if value <= 1 {
+        0
+    } else {
+        (64 - (value - 1).leading_zeros()) as u64
+    }
+}
+
+/// A rough estimate of the delay of the logic implementing an operator, in levels of logic.
+/// Operators which are only wiring have no delay.
+///
+/// Operators which can not be moved between pipeline stages have no estimate.
+fn estimated_delay(
+    op: &Operator,
+    operands: &[ValueName],
+    ty: &Type,
+    types: &HashMap<ValueName, Type>,
+) -> Option<u64> {
+    let width = operands
+        .iter()
+        .filter_map(|op| types.get(op))
+        .chain([ty])
+        .map(|ty| ty.size().to_u64().unwrap_or(u64::MAX))
+        .max()
+        .unwrap_or(0);
+    let levels = clog2(width);
+
+    match op {
+        Operator::Alias
+        | Operator::Concat
+        | Operator::ConstructTuple
+        | Operator::ConstructArray
+        | Operator::ConstructEnum { .. }
+        | Operator::EnumMember { .. }
+        | Operator::IndexTuple(_, _)
+        | Operator::Truncate
+        | Operator::ZeroExtend { .. }
+        | Operator::SignExtend { .. }
+        | Operator::RangeIndexArray { .. }
+        | Operator::RangeIndexBits { .. }
+        | Operator::Bitreverse => Some(0),
+        Operator::Not
+        | Operator::LogicalNot
+        | Operator::BitwiseNot
+        | Operator::LogicalAnd
+        | Operator::LogicalOr
+        | Operator::LogicalXor
+        | Operator::BitwiseAnd
+        | Operator::BitwiseOr
+        | Operator::BitwiseXor
+        | Operator::Select { .. } => Some(1),
+        Operator::Match { .. } => Some(1 + clog2(operands.len() as u64 / 2)),
+        Operator::Eq
+        | Operator::NotEq
+        | Operator::IsEnumVariant { .. }
+        | Operator::ReduceAnd
+        | Operator::ReduceOr
+        | Operator::ReduceXor
+        | Operator::LeftShift
+        | Operator::RightShift
+        | Operator::ArithmeticRightShift
+        | Operator::IndexArray => Some(1 + levels),
+        Operator::Add
+        | Operator::UnsignedAdd
+        | Operator::Sub
+        | Operator::UnsignedSub
+        | Operator::USub
+        | Operator::Gt
+        | Operator::UnsignedGt
+        | Operator::Lt
+        | Operator::UnsignedLt
+        | Operator::Ge
+        | Operator::UnsignedGe
+        | Operator::Le
+        | Operator::UnsignedLe
+        | Operator::DivPow2 => Some(2 + levels),
+        Operator::Mul | Operator::UnsignedMul => Some(2 + 3 * levels),
+        Operator::Div | Operator::UnsignedDiv | Operator::Mod | Operator::UnsignedMod => {
+            Some(width.saturating_mul(2 + levels))
+        }
+        Operator::Gray2Bin { num_bits } => Some(num_bits.to_u64().unwrap_or(u64::MAX)),
+        Operator::ReadPort
+        | Operator::ReadMutWires
+        | Operator::FlipPort
+        | Operator::IndexMemory
+        | Operator::DeclClockedMemory { .. }
+        | Operator::DeclBlockRam { .. }
+        | Operator::Instance { .. }
+        | Operator::Nop => None,
+    }
+}
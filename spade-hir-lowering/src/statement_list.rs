@@ -1,4 +1,6 @@
 use itertools::Itertools;
+use spade_mir::Binding;
+use spade_mir::Operator;
 use spade_mir::Statement;
 use spade_mir::ValueName;
 
@@ -8,6 +10,8 @@ use crate::name_map::NameSourceMap;
 pub struct StatementList {
     stmts: Vec<Statement>,
     name_map: NameSourceMap,
+    /// Indices of the first statement after each `reg` in a pipeline body
+    stage_boundaries: Vec<usize>,
 }
 
 impl Default for StatementList {
@@ -21,6 +25,7 @@ impl StatementList {
         Self {
             stmts: vec![],
             name_map: NameSourceMap::new(),
+            stage_boundaries: vec![],
         }
     }
 
@@ -37,6 +42,7 @@ impl StatementList {
             Statement::Register(r) => Some(r.name.clone()),
             Statement::Constant(id, _, _) => Some(ValueName::Expr(*id)),
             Statement::Assert(_) => None,
+            Statement::Property(_) => None,
             Statement::Set { .. } => None,
             Statement::WalTrace { .. } => None,
         };
@@ -60,6 +66,7 @@ impl StatementList {
             Statement::Register(r) => Some(r.name.clone()),
             Statement::Constant(id, _, _) => Some(ValueName::Expr(*id)),
             Statement::Assert(_) => None,
+            Statement::Property(_) => None,
             Statement::Set { .. } => None,
             Statement::WalTrace { .. } => None,
         };
@@ -81,11 +88,51 @@ impl StatementList {
         }
     }
 
+    /// Marks the end of a pipeline stage. Subsequent statements belong to the next stage
+    pub fn push_stage_boundary(&mut self) {
+        self.stage_boundaries.push(self.stmts.len())
+    }
+
+    pub fn stage_boundaries(&self) -> &[usize] {
+        &self.stage_boundaries
+    }
+
+    pub fn len(&self) -> usize {
+        self.stmts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stmts.is_empty()
+    }
+
+    pub fn statements_mut(&mut self) -> &mut Vec<Statement> {
+        &mut self.stmts
+    }
+
     pub fn append(&mut self, mut other: StatementList) {
+        let offset = self.stmts.len();
+        self.stage_boundaries
+            .extend(other.stage_boundaries.iter().map(|b| b + offset));
         self.stmts.append(&mut other.stmts);
         self.name_map.merge(other.name_map)
     }
 
+    /// The operator of the binding which defines `name`, if there is one
+    pub fn binding_operator_mut(&mut self, name: &ValueName) -> Option<&mut Operator> {
+        self.stmts.iter_mut().find_map(|stmt| match stmt {
+            Statement::Binding(b) if &b.name == name => Some(&mut b.operator),
+            _ => None,
+        })
+    }
+
+    /// The binding which defines `name`, if there is one
+    pub fn binding_mut(&mut self, name: &ValueName) -> Option<&mut Binding> {
+        self.stmts.iter_mut().find_map(|stmt| match stmt {
+            Statement::Binding(b) if &b.name == name => Some(b),
+            _ => None,
+        })
+    }
+
     pub fn to_vec(self, name_map: &mut NameSourceMap) -> Vec<Statement> {
         name_map.merge(self.name_map);
         self.stmts
@@ -290,4 +290,29 @@ mod test {
 
         assert!(!is_useful(&pattern, &matrix).is_useful())
     }
+
+    #[test]
+    fn int_pattern_is_not_useful_wrt_wildcard() {
+        let ty = ConcreteType::Single {
+            base: PrimitiveType::Int,
+            params: vec![ConcreteType::Integer(8u32.into())],
+        };
+        let pat = |ctor| {
+            PatStack::new(vec![DeconstructedPattern {
+                ctor,
+                fields: vec![],
+                ty: ty.clone(),
+            }])
+        };
+
+        let pattern = pat(Constructor::IntRange {
+            min: 1.into(),
+            max: 1.into(),
+        });
+        let matrix = Matrix {
+            patterns: vec![pat(Constructor::Wildcard)],
+        };
+
+        assert!(!is_useful(&pattern, &matrix).is_useful())
+    }
 }
@@ -147,8 +147,33 @@ pub struct Binding {
     // Specifies if a wal_trace mir node should be emitted for this struct. If this
     // is present, the type is traceable
     pub wal_trace: Option<Loc<WalTrace>>,
+    /// File to read the initial content of the memory bound by this binding from
+    pub memory_init: Option<Loc<MemoryInit>>,
+    /// Synthesis attributes to emit on the Verilog declaration of the bound value
+    pub verilog_attrs: Vec<(Loc<Identifier>, Option<Loc<VerilogParameterValue>>)>,
 }
 
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum MemoryInitFormat {
+    Hex,
+    Bin,
+    Raw,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum VerilogParameterValue {
+    Int(BigInt),
+    String(String),
+}
+impl WithLocation for VerilogParameterValue {}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryInit {
+    pub file: Loc<String>,
+    pub format: MemoryInitFormat,
+}
+impl WithLocation for MemoryInit {}
+
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum PipelineRegMarkerExtra {
     Condition(Loc<Expression>),
@@ -166,6 +191,7 @@ pub enum Statement {
     PipelineRegMarker(Option<PipelineRegMarkerExtra>),
     Label(Loc<NameID>),
     Assert(Loc<Expression>),
+    Property(Property),
     Set {
         target: Loc<Expression>,
         value: Loc<Expression>,
@@ -185,6 +211,8 @@ impl Statement {
             ty: None,
             value: val.nowhere(),
             wal_trace: None,
+            memory_init: None,
+            verilog_attrs: vec![],
         })
     }
 
@@ -198,15 +226,35 @@ impl Statement {
             ty,
             value,
             wal_trace: None,
+            memory_init: None,
+            verilog_attrs: vec![],
         })
     }
 }
 
+/// How the reset of a register is applied. The default is an asynchronous, active high reset
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ResetStyle {
+    pub synchronous: bool,
+    pub active_low: bool,
+}
+
+/// The edge of the clock on which a register samples its value
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum ClockEdge {
+    #[default]
+    Rising,
+    Falling,
+    Both,
+}
+
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Register {
     pub pattern: Loc<Pattern>,
     pub clock: Loc<Expression>,
+    pub clock_edge: ClockEdge,
     pub reset: Option<(Loc<Expression>, Loc<Expression>)>,
+    pub reset_style: ResetStyle,
     pub initial: Option<Loc<Expression>>,
     pub value: Loc<Expression>,
     pub value_type: Option<Loc<TypeSpec>>,
@@ -214,6 +262,29 @@ pub struct Register {
 }
 impl WithLocation for Register {}
 
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum PropertyKind {
+    Assert,
+    Assume,
+    Cover,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Implication {
+    Overlapping,
+    NonOverlapping,
+}
+
+/// A clocked property which is checked by formal verification tools
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Property {
+    pub kind: PropertyKind,
+    pub clock: Loc<Expression>,
+    pub reset: Option<Loc<Expression>>,
+    pub antecedent: Option<(Loc<Expression>, Implication)>,
+    pub consequent: Loc<Expression>,
+}
+
 #[derive(PartialEq, Debug, Clone, PartialOrd, Eq, Ord, Serialize, Deserialize)]
 pub struct Module {
     pub name: Loc<NameID>,
@@ -631,14 +702,16 @@ impl UnitHead {
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Item {
     Unit(Loc<Unit>),
-    Builtin(UnitName, Loc<UnitHead>),
+    /// A unit implemented outside of spade. The attributes are kept since some of
+    /// them affect how the unit is instantiated
+    Builtin(UnitName, Loc<UnitHead>, AttributeList),
 }
 
 impl Item {
     pub fn assume_unit(&self) -> &Unit {
         match self {
             Item::Unit(u) => &u.inner,
-            Item::Builtin(_, _) => panic!("Expected unit, got builtin"),
+            Item::Builtin(_, _, _) => panic!("Expected unit, got builtin"),
         }
     }
 }
@@ -650,7 +723,7 @@ pub enum ExecutableItem {
     EnumInstance { base_enum: NameID, variant: usize },
     StructInstance,
     Unit(Loc<Unit>),
-    BuiltinUnit(UnitName, Loc<UnitHead>),
+    BuiltinUnit(UnitName, Loc<UnitHead>, AttributeList),
 }
 impl WithLocation for ExecutableItem {}
 
@@ -690,9 +763,28 @@ impl std::fmt::Display for TraitName {
 /// ast node
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Attribute {
-    Optimize { passes: Vec<Loc<String>> },
-    Fsm { state: NameID },
-    WalTraceable { suffix: Identifier },
+    Optimize {
+        passes: Vec<Loc<String>>,
+    },
+    Fsm {
+        state: NameID,
+    },
+    WalTraceable {
+        suffix: Identifier,
+    },
+    /// Crossings between clock domains in the unit are assumed to be safe
+    CdcPrimitive,
+    /// The pipeline registers are placed by the compiler to balance the estimated delay
+    /// of the stages
+    Retime,
+    /// Constant parameter overrides for the Verilog module implementing a __builtin__ unit
+    VerilogParameters {
+        params: Vec<(Loc<Identifier>, Loc<VerilogParameterValue>)>,
+    },
+    /// Synthesis attributes to emit on the Verilog declaration of a register or module
+    VerilogAttrs {
+        attrs: Vec<(Loc<Identifier>, Option<Loc<VerilogParameterValue>>)>,
+    },
 }
 impl Attribute {
     pub fn name(&self) -> &str {
@@ -700,6 +792,10 @@ impl Attribute {
             Attribute::Optimize { passes: _ } => "optimize",
             Attribute::Fsm { state: _ } => "fsm",
             Attribute::WalTraceable { suffix: _ } => "suffix",
+            Attribute::CdcPrimitive => "cdc_primitive",
+            Attribute::Retime => "retime",
+            Attribute::VerilogParameters { .. } => "verilog_parameters",
+            Attribute::VerilogAttrs { .. } => "verilog_attrs",
         }
     }
 }
@@ -751,6 +847,11 @@ pub struct ItemList {
     /// visible to the user.
     pub traits: HashMap<TraitName, TraitDef>,
     pub impls: HashMap<NameID, HashMap<(TraitName, Vec<TypeExpression>), Loc<ImplBlock>>>,
+    /// The `///` documentation of units, types, enum variants, traits and modules
+    pub docs: HashMap<NameID, String>,
+    /// The `///` documentation of struct fields and trait methods, by the name of the
+    /// struct or trait they are a member of
+    pub member_docs: HashMap<(NameID, Identifier), String>,
 }
 
 impl Default for ItemList {
@@ -767,6 +868,8 @@ impl ItemList {
             modules: BTreeMap::new(),
             traits: HashMap::new(),
             impls: HashMap::new(),
+            docs: HashMap::new(),
+            member_docs: HashMap::new(),
         }
     }
 
@@ -822,4 +925,78 @@ impl ItemList {
     pub fn traits(&self) -> &HashMap<TraitName, TraitDef> {
         &self.traits
     }
+
+    /// A copy of the items with names from `first_name` and onwards, along with the impl
+    /// blocks with IDs from `first_impl` and onwards, regardless of which type they are for.
+    pub fn subset(&self, first_name: u64, first_impl: u64) -> Self {
+        let is_new = |name: &NameID| name.0 >= first_name;
+        let is_new_trait = |name: &TraitName| match name {
+            TraitName::Named(name) => is_new(name),
+            TraitName::Anonymous(id) => *id >= first_impl,
+        };
+        Self {
+            executables: self
+                .executables
+                .iter()
+                .filter(|(name, _)| is_new(name))
+                .map(|(name, item)| (name.clone(), item.clone()))
+                .collect(),
+            types: self
+                .types
+                .iter()
+                .filter(|(name, _)| is_new(name))
+                .map(|(name, ty)| (name.clone(), ty.clone()))
+                .collect(),
+            modules: self
+                .modules
+                .iter()
+                .filter(|(name, _)| is_new(name))
+                .map(|(name, module)| (name.clone(), module.clone()))
+                .collect(),
+            traits: self
+                .traits
+                .iter()
+                .filter(|(name, _)| is_new_trait(name))
+                .map(|(name, def)| (name.clone(), def.clone()))
+                .collect(),
+            impls: self
+                .impls
+                .iter()
+                .map(|(target, impls)| {
+                    let impls = impls
+                        .iter()
+                        .filter(|(_, block)| block.id >= first_impl)
+                        .map(|(key, block)| (key.clone(), block.clone()))
+                        .collect::<HashMap<_, _>>();
+                    (target.clone(), impls)
+                })
+                .filter(|(_, impls)| !impls.is_empty())
+                .collect(),
+            docs: self
+                .docs
+                .iter()
+                .filter(|(name, _)| is_new(name))
+                .map(|(name, doc)| (name.clone(), doc.clone()))
+                .collect(),
+            member_docs: self
+                .member_docs
+                .iter()
+                .filter(|((name, _), _)| is_new(name))
+                .map(|(key, doc)| (key.clone(), doc.clone()))
+                .collect(),
+        }
+    }
+
+    /// Adds the items in `other`, which must not overlap with the items in `self`
+    pub fn merge(&mut self, other: Self) {
+        self.executables.extend(other.executables);
+        self.types.extend(other.types);
+        self.modules.extend(other.modules);
+        self.traits.extend(other.traits);
+        for (target, impls) in other.impls {
+            self.impls.entry(target).or_default().extend(impls);
+        }
+        self.docs.extend(other.docs);
+        self.member_docs.extend(other.member_docs);
+    }
 }
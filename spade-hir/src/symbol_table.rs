@@ -566,6 +566,59 @@ impl SymbolTable {
         self.add_thing_with_id_at_offset(offset, id, name, item)
     }
 
+    /// The ID which the next name added to the symtab will get
+    pub fn peek_id(&self) -> u64 {
+        self.id_tracker.peek()
+    }
+
+    /// A copy of the global names with IDs from `first_id` and onwards. Names are only
+    /// added after `first_id` if the ID tracker is further along, so the copy keeps the
+    /// ID tracker state of `self`.
+    pub fn subset(&self, first_id: u64) -> Self {
+        let is_new = |name: &NameID| name.0 >= first_id;
+        Self {
+            symbols: vec![self.symbols[0]
+                .iter()
+                .filter(|(_, name)| is_new(name))
+                .map(|(path, name)| (path.clone(), name.clone()))
+                .collect()],
+            declarations: vec![HashMap::new()],
+            id_tracker: self.id_tracker.make_clone(),
+            types: self
+                .types
+                .iter()
+                .filter(|(name, _)| is_new(name))
+                .map(|(name, ty)| (name.clone(), ty.clone()))
+                .collect(),
+            things: self
+                .things
+                .iter()
+                .filter(|(name, _)| is_new(name))
+                .map(|(name, thing)| (name.clone(), thing.clone()))
+                .collect(),
+            namespace: Path(vec![]),
+            base_namespace: Path(vec![]),
+        }
+    }
+
+    /// Adds the global names in `other`, which must not overlap with the names in `self`,
+    /// and continues from the ID tracker state of `other`
+    pub fn merge(&mut self, other: Self) {
+        let Self {
+            symbols,
+            declarations: _,
+            id_tracker,
+            types,
+            things,
+            namespace: _,
+            base_namespace: _,
+        } = other;
+        self.symbols[0].extend(symbols.into_iter().flatten());
+        self.types.extend(types);
+        self.things.extend(things);
+        self.id_tracker = id_tracker;
+    }
+
     pub fn freeze(self) -> FrozenSymtab {
         let id_tracker = self.id_tracker.make_clone();
         FrozenSymtab {
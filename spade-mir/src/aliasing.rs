@@ -25,7 +25,8 @@ pub fn flatten_aliases(entity: &mut Entity) {
     for stmt in &entity.statements {
         match stmt {
             Statement::Binding(binding) => {
-                if let Operator::Alias = binding.operator {
+                // Attributes apply to the declaration of the name, so it has to be kept
+                if binding.operator == Operator::Alias && binding.verilog_attrs.is_empty() {
                     match (binding.name.clone(), binding.operands[0].clone()) {
                         // Names should only alias expressions, not other names.
                         (ValueName::Named(_, _, _), ValueName::Named(_, _, _)) => {}
@@ -46,6 +47,7 @@ pub fn flatten_aliases(entity: &mut Entity) {
                 unaliasable.insert(ValueName::Expr(*id).clone());
             }
             Statement::Assert(_) => {}
+            Statement::Property(_) => {}
             Statement::Set { .. } => {}
             Statement::WalTrace { .. } => {}
         }
@@ -104,6 +106,16 @@ pub fn flatten_aliases(entity: &mut Entity) {
             }
             Statement::Constant(_, _, _) => {}
             Statement::Assert(_) => {}
+            Statement::Property(property) => {
+                try_rename(&mut property.clock, &aliases);
+                if let Some(rst) = &mut property.reset {
+                    try_rename(rst, &aliases);
+                }
+                if let Some((antecedent, _)) = &mut property.antecedent {
+                    try_rename(antecedent, &aliases);
+                }
+                try_rename(&mut property.consequent, &aliases);
+            }
             Statement::Set { .. } => {}
             Statement::WalTrace {
                 name,
@@ -130,6 +142,26 @@ mod tests {
     use crate::{self as spade_mir, ConstantValue};
     use colored::Colorize;
 
+    #[test]
+    fn aliases_with_verilog_attributes_are_kept() {
+        let mut input = entity!("pong"; ("_i_op", n(0, "op"), Type::int(6)) -> Type::int(6); {
+            (e(0); Type::int(6); Add; n(0, "op"), e(1));
+            (n(0, "a"); Type::int(6); Alias; e(0))
+        } => e(10));
+        if let Statement::Binding(binding) = &mut input.statements[1] {
+            binding.verilog_attrs = vec![crate::VerilogAttribute {
+                name: "keep".to_string(),
+                value: None,
+            }];
+        }
+
+        let expected = input.clone();
+
+        flatten_aliases(&mut input);
+
+        assert_eq!(input, expected);
+    }
+
     #[test]
     fn aliasing_replaces_definitions() {
         let mut input = entity!("pong"; ("_i_op", n(0, "op"), Type::int(6)) -> Type::int(6); {
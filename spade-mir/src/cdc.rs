@@ -0,0 +1,874 @@
+//! Static detection of unsynchronized clock domain crossings. Every value is assigned
+//! the set of clock domains it is derived from, and registers which sample a value
+//! from a domain other than that of their own clock are reported.
+//!
+//! The analysis is modular. Each entity is summarised by the domains of the signals it
+//! drives, expressed in terms of its ports, along with the crossings which can only be
+//! decided once the clocks passed to it are known. Those are resolved by the entities
+//! which instantiate it, or reported if the entity is never instantiated, in which
+//! case all of its clock inputs are assumed to be in different domains.
+//!
+//! Units marked `#[cdc_primitive]`, like the synchronizers in `std::cdc`, are trusted
+//! to cross domains safely. No crossings are reported inside them, but the domains of
+//! their outputs are still tracked.
+
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap};
+use std::rc::Rc;
+
+use spade_common::location_info::Loc;
+use spade_diagnostics::Diagnostic;
+
+use crate::types::Type;
+use crate::unit_name::UnitNameKind;
+use crate::{Binding, Entity, Operator, Register, Statement, ValueName};
+
+/// A register which samples a value produced in another clock domain
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClockDomainCrossing {
+    /// The register, or the instance containing it if it is in an instantiated unit
+    pub at: Loc<()>,
+    /// The register or memory producing the value, if it is known
+    pub origin: Option<Loc<()>>,
+    /// The register in an instantiated unit which samples the value
+    pub register: Option<Loc<()>>,
+}
+
+impl From<ClockDomainCrossing> for Diagnostic {
+    fn from(crossing: ClockDomainCrossing) -> Self {
+        let diag = Diagnostic::warning(crossing.at, "Unsynchronized clock domain crossing");
+        let diag = match crossing.register {
+            Some(register) => diag
+                .primary_label("This instance samples a value from another clock domain")
+                .secondary_label(register, "The value is sampled by this register"),
+            None => diag.primary_label("This register samples a value from another clock domain"),
+        };
+        let diag = match crossing.origin {
+            Some(origin) => diag.secondary_label(origin, "The value is produced in this domain"),
+            None => diag,
+        };
+        diag.help("Cross between the domains using a synchronizer from std::cdc")
+    }
+}
+
+/// A clock in the entity being analysed
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+enum Clock {
+    Node(usize),
+    /// A clock which is internal to an instantiated unit
+    Foreign(usize),
+}
+
+/// A clock referred to by a [Summary]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum ClockRef {
+    /// The clock passed to the specified port
+    Port(usize),
+    /// A clock which does not come from the ports of the unit
+    Internal(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Source<C> {
+    /// Produced by a register clocked by the clock
+    Clock(C),
+    /// Combinationally derived from the specified port
+    Port(usize),
+}
+
+/// The sources of a value along with the register or memory producing it
+type Domains<C> = BTreeMap<Source<C>, Option<Loc<()>>>;
+
+struct DeferredCheck {
+    clock: ClockRef,
+    source: Source<ClockRef>,
+    origin: Option<Loc<()>>,
+    at: Option<Loc<()>>,
+    register: Option<Loc<()>>,
+}
+
+struct Summary {
+    /// The domains of each port, where the ports are the leaves of the inputs followed
+    /// by the leaves of the output. Ports which are not driven by the unit have no
+    /// domains
+    ports: Vec<Domains<ClockRef>>,
+    deferred: Vec<DeferredCheck>,
+}
+
+enum CheckValue {
+    Node(usize),
+    Source(Source<Clock>, Option<Loc<()>>),
+}
+
+struct Check {
+    clock: Clock,
+    value: CheckValue,
+    /// The register, or the instance containing it
+    at: Option<Loc<()>>,
+    /// The register if it is in an instantiated unit
+    register: Option<Loc<()>>,
+}
+
+/// Splits a type into the signals which are tracked separately. Each leaf is true if it
+/// flows backward
+fn leaves(ty: &Type, backward: bool, result: &mut Vec<bool>) {
+    match ty {
+        Type::Tuple(inner) => inner.iter().for_each(|t| leaves(t, backward, result)),
+        Type::Struct(inner) => inner.iter().for_each(|(_, t)| leaves(t, backward, result)),
+        Type::Backward(inner) => leaves(inner, !backward, result),
+        _ => result.push(backward),
+    }
+}
+
+fn leaf_count(ty: &Type) -> usize {
+    let mut result = vec![];
+    leaves(ty, false, &mut result);
+    result.len()
+}
+
+#[derive(Default)]
+struct Graph {
+    /// Union find structure for signals which are the same wire
+    parent: Vec<usize>,
+    values: HashMap<ValueName, Vec<(usize, bool)>>,
+    /// (to, from)
+    edges: Vec<(usize, usize)>,
+    seeds: Vec<(usize, Source<Clock>, Option<Loc<()>>)>,
+    checks: Vec<Check>,
+    foreign: HashMap<(usize, usize), usize>,
+}
+
+impl Graph {
+    fn find(&mut self, node: usize) -> usize {
+        let mut root = node;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        self.parent[node] = root;
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parent[a] = b;
+    }
+
+    fn union_all(&mut self, a: &[usize], b: &[usize]) -> bool {
+        if a.len() != b.len() {
+            return false;
+        }
+        for (a, b) in a.iter().zip(b) {
+            self.union(*a, *b)
+        }
+        true
+    }
+
+    fn leaves(
+        &mut self,
+        name: &ValueName,
+        types: &HashMap<ValueName, &Type>,
+    ) -> Vec<(usize, bool)> {
+        if let Some(leaves) = self.values.get(name) {
+            return leaves.clone();
+        }
+        let mut directions = vec![];
+        match types.get(name) {
+            Some(ty) => leaves(ty, false, &mut directions),
+            None => directions.push(false),
+        }
+        let result = directions
+            .into_iter()
+            .map(|backward| {
+                self.parent.push(self.parent.len());
+                (self.parent.len() - 1, backward)
+            })
+            .collect::<Vec<_>>();
+        self.values.insert(name.clone(), result.clone());
+        result
+    }
+
+    fn nodes(&mut self, name: &ValueName, types: &HashMap<ValueName, &Type>) -> Vec<usize> {
+        self.leaves(name, types)
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect()
+    }
+
+    /// Makes all leaves of `to` depend on all leaves of `from`
+    fn depend_on_all(&mut self, to: &[usize], from: &[usize]) {
+        for to in to {
+            for from in from {
+                self.edges.push((*to, *from))
+            }
+        }
+    }
+
+    fn canonical(&mut self, clock: Clock) -> Clock {
+        match clock {
+            Clock::Node(n) => Clock::Node(self.find(n)),
+            Clock::Foreign(_) => clock,
+        }
+    }
+
+    fn canonical_source(&mut self, source: Source<Clock>) -> Source<Clock> {
+        match source {
+            Source::Clock(c) => Source::Clock(self.canonical(c)),
+            Source::Port(_) => source,
+        }
+    }
+
+    fn import_clock(&mut self, clock: ClockRef, ports: &[usize], instance: usize) -> Clock {
+        match clock {
+            ClockRef::Port(i) => Clock::Node(ports[i]),
+            ClockRef::Internal(i) => {
+                let next = self.foreign.len();
+                Clock::Foreign(*self.foreign.entry((instance, i)).or_insert(next))
+            }
+        }
+    }
+
+    /// Computes the domains of all signals
+    fn domains(&mut self) -> Vec<Domains<Clock>> {
+        let count = self.parent.len();
+        let mut domains = vec![BTreeMap::new(); count];
+        for (node, source, origin) in std::mem::take(&mut self.seeds) {
+            let node = self.find(node);
+            let source = self.canonical_source(source);
+            domains[node].entry(source).or_insert(origin);
+        }
+
+        let mut successors = vec![vec![]; count];
+        for (to, from) in std::mem::take(&mut self.edges) {
+            let (to, from) = (self.find(to), self.find(from));
+            if to != from {
+                successors[from].push(to);
+            }
+        }
+
+        let mut worklist = (0..count).collect::<Vec<_>>();
+        while let Some(from) = worklist.pop() {
+            for &to in &successors[from] {
+                let mut changed = false;
+                for (source, origin) in domains[from].clone() {
+                    if let Entry::Vacant(entry) = domains[to].entry(source) {
+                        entry.insert(origin);
+                        changed = true;
+                    }
+                }
+                if changed {
+                    worklist.push(to);
+                }
+            }
+        }
+        domains
+    }
+}
+
+struct Analysis<'a> {
+    entities: BTreeMap<&'a UnitNameKind, &'a Entity>,
+    primitives: &'a BTreeSet<UnitNameKind>,
+    /// None while the summary is being computed
+    summaries: BTreeMap<&'a UnitNameKind, Option<Rc<Summary>>>,
+    crossings: BTreeMap<(usize, usize, usize), ClockDomainCrossing>,
+}
+
+impl<'a> Analysis<'a> {
+    fn summary(&mut self, name: &UnitNameKind) -> Option<(&'a Entity, Rc<Summary>)> {
+        let (name, entity) = self.entities.get_key_value(name)?;
+        let (name, entity) = (*name, *entity);
+        match self.summaries.get(name) {
+            Some(summary) => summary.clone().map(|s| (entity, s)),
+            None => {
+                self.summaries.insert(name, None);
+                let summary = Rc::new(self.analyse(entity, false));
+                self.summaries.insert(name, Some(summary.clone()));
+                Some((entity, summary))
+            }
+        }
+    }
+
+    fn report(&mut self, check: &Check, origin: Option<Loc<()>>) {
+        if let Some(at) = check.at {
+            let key = (
+                at.file_id,
+                at.span.start().to_usize(),
+                at.span.end().to_usize(),
+            );
+            self.crossings.entry(key).or_insert(ClockDomainCrossing {
+                at,
+                origin,
+                register: check.register,
+            });
+        }
+    }
+
+    fn instance(
+        &mut self,
+        graph: &mut Graph,
+        types: &HashMap<ValueName, &Type>,
+        binding: &Binding,
+        name: &UnitNameKind,
+        params: &[crate::ParamName],
+        instance: usize,
+    ) {
+        if let Some((callee, summary)) = self.summary(name) {
+            let mut ports = vec![];
+            for input in &callee.inputs {
+                if let Some(idx) = params.iter().position(|p| p.name == input.name) {
+                    ports.extend(graph.nodes(&binding.operands[idx], types))
+                }
+            }
+            ports.extend(graph.nodes(&binding.name, types));
+
+            if ports.len() == summary.ports.len() {
+                for (port, domains) in summary.ports.iter().enumerate() {
+                    for (source, origin) in domains {
+                        match source {
+                            Source::Port(from) => graph.edges.push((ports[port], ports[*from])),
+                            Source::Clock(clock) => {
+                                let clock = graph.import_clock(*clock, &ports, instance);
+                                graph
+                                    .seeds
+                                    .push((ports[port], Source::Clock(clock), *origin))
+                            }
+                        }
+                    }
+                }
+                for check in &summary.deferred {
+                    let clock = graph.import_clock(check.clock, &ports, instance);
+                    let value = match check.source {
+                        Source::Port(i) => CheckValue::Node(ports[i]),
+                        Source::Clock(c) => CheckValue::Source(
+                            Source::Clock(graph.import_clock(c, &ports, instance)),
+                            check.origin,
+                        ),
+                    };
+                    // Crossings are reported at the instance, since the register may be
+                    // in code which is not reported on, like the standard library
+                    let (at, register) = match binding.loc {
+                        Some(loc) => (Some(loc), check.register.or(check.at)),
+                        None => (check.at, check.register),
+                    };
+                    graph.checks.push(Check {
+                        clock,
+                        value,
+                        at,
+                        register,
+                    })
+                }
+                return;
+            }
+        }
+
+        // Units without a body are assumed to combinationally drive all outputs
+        // from all inputs
+        let mut incoming = vec![];
+        let mut outgoing = vec![];
+        for operand in &binding.operands {
+            for (node, backward) in graph.leaves(operand, types) {
+                if backward {
+                    outgoing.push(node)
+                } else {
+                    incoming.push(node)
+                }
+            }
+        }
+        for (node, backward) in graph.leaves(&binding.name, types) {
+            if backward {
+                incoming.push(node)
+            } else {
+                outgoing.push(node)
+            }
+        }
+        graph.depend_on_all(&outgoing, &incoming);
+    }
+
+    fn binding(
+        &mut self,
+        graph: &mut Graph,
+        types: &HashMap<ValueName, &Type>,
+        binding: &Binding,
+        statement_idx: usize,
+    ) {
+        let Binding {
+            name,
+            operator,
+            operands,
+            ty: _,
+            loc,
+            verilog_attrs: _,
+        } = binding;
+
+        let result = graph.nodes(name, types);
+        let structural = match operator {
+            Operator::Alias | Operator::FlipPort | Operator::ReadPort => {
+                let operand = graph.nodes(&operands[0], types);
+                graph.union_all(&result, &operand)
+            }
+            Operator::ReadMutWires => {
+                let operand = graph
+                    .leaves(&operands[0], types)
+                    .into_iter()
+                    .filter_map(|(n, backward)| backward.then_some(n))
+                    .collect::<Vec<_>>();
+                graph.union_all(&result, &operand)
+            }
+            Operator::ConstructTuple => {
+                let operands = operands
+                    .iter()
+                    .flat_map(|op| graph.nodes(op, types))
+                    .collect::<Vec<_>>();
+                graph.union_all(&result, &operands)
+            }
+            Operator::IndexTuple(idx, inner) => {
+                let operand = graph.nodes(&operands[0], types);
+                let start = inner
+                    .iter()
+                    .take(*idx as usize)
+                    .map(leaf_count)
+                    .sum::<usize>();
+                match operand.get(start..start + result.len()) {
+                    Some(slice) => graph.union_all(&result, slice),
+                    None => false,
+                }
+            }
+            Operator::Nop => true,
+            Operator::Instance { name, params, .. } => {
+                self.instance(graph, types, binding, &name.kind, params, statement_idx);
+                true
+            }
+            Operator::DeclClockedMemory { .. } => {
+                let clock = graph.nodes(&operands[0], types)[0];
+                for node in &result {
+                    graph
+                        .seeds
+                        .push((*node, Source::Clock(Clock::Node(clock)), *loc));
+                }
+                for node in graph.nodes(&operands[1], types) {
+                    graph.checks.push(Check {
+                        clock: Clock::Node(clock),
+                        value: CheckValue::Node(node),
+                        at: *loc,
+                        register: None,
+                    })
+                }
+                true
+            }
+            // Each port reads and writes in the domain of its own clock
+            Operator::DeclBlockRam { .. } => {
+                let ports = operands.len() / 2;
+                let per_port = result.len() / ports.max(1);
+                for (port, outputs) in result.chunks(per_port.max(1)).enumerate() {
+                    let clock = graph.nodes(&operands[port * 2], types)[0];
+                    for node in outputs {
+                        graph
+                            .seeds
+                            .push((*node, Source::Clock(Clock::Node(clock)), *loc));
+                    }
+                    for node in graph.nodes(&operands[port * 2 + 1], types) {
+                        graph.checks.push(Check {
+                            clock: Clock::Node(clock),
+                            value: CheckValue::Node(node),
+                            at: *loc,
+                            register: None,
+                        })
+                    }
+                }
+                true
+            }
+            _ => false,
+        };
+
+        if !structural {
+            let operands = operands
+                .iter()
+                .flat_map(|op| graph.nodes(op, types))
+                .collect::<Vec<_>>();
+            graph.depend_on_all(&result, &operands)
+        }
+    }
+
+    fn analyse(&mut self, entity: &'a Entity, root: bool) -> Summary {
+        let primitive = self.primitives.contains(&entity.name.kind);
+
+        let mut types = HashMap::new();
+        for input in &entity.inputs {
+            types.insert(input.val_name.clone(), &input.ty);
+        }
+        for statement in &entity.statements {
+            match statement {
+                Statement::Binding(b) => {
+                    types.insert(b.name.clone(), &b.ty);
+                }
+                Statement::Register(r) => {
+                    types.insert(r.name.clone(), &r.ty);
+                }
+                Statement::Constant(id, ty, _) => {
+                    types.insert(ValueName::Expr(*id), ty);
+                }
+                _ => {}
+            }
+        }
+
+        let mut graph = Graph::default();
+
+        // (node, incoming)
+        let mut interface = vec![];
+        for input in &entity.inputs {
+            for (node, backward) in graph.leaves(&input.val_name, &types) {
+                interface.push((node, !backward))
+            }
+        }
+        for (node, backward) in graph.leaves(&entity.output, &types) {
+            interface.push((node, backward))
+        }
+        for (port, (node, incoming)) in interface.iter().enumerate() {
+            if *incoming {
+                graph.seeds.push((*node, Source::Port(port), None))
+            }
+        }
+
+        for (idx, statement) in entity.statements.iter().enumerate() {
+            match statement {
+                Statement::Binding(binding) => self.binding(&mut graph, &types, binding, idx),
+                Statement::Register(Register {
+                    name,
+                    clock,
+                    value,
+                    loc,
+                    ..
+                }) => {
+                    let clock = Clock::Node(graph.nodes(clock, &types)[0]);
+                    for node in graph.nodes(name, &types) {
+                        graph.seeds.push((node, Source::Clock(clock), *loc))
+                    }
+                    for node in graph.nodes(value, &types) {
+                        graph.checks.push(Check {
+                            clock,
+                            value: CheckValue::Node(node),
+                            at: *loc,
+                            register: None,
+                        })
+                    }
+                }
+                Statement::Set { target, value } => {
+                    let target = graph.nodes(target, &types);
+                    let value = graph.nodes(value, &types);
+                    if target.len() == value.len() {
+                        for (t, v) in target.iter().zip(&value) {
+                            graph.edges.push((*t, *v))
+                        }
+                    } else {
+                        graph.depend_on_all(&target, &value)
+                    }
+                }
+                Statement::Constant(..)
+                | Statement::Assert(_)
+                | Statement::Property(_)
+                | Statement::WalTrace { .. } => {}
+            }
+        }
+
+        let domains = graph.domains();
+
+        let mut port_of = HashMap::new();
+        for (port, (node, incoming)) in interface.iter().enumerate() {
+            if *incoming {
+                port_of.entry(graph.find(*node)).or_insert(port);
+            }
+        }
+        let mut internal = HashMap::new();
+        let mut export = |clock: Clock| match clock {
+            Clock::Node(n) if port_of.contains_key(&n) => ClockRef::Port(port_of[&n]),
+            _ => {
+                let next = internal.len();
+                ClockRef::Internal(*internal.entry(clock).or_insert(next))
+            }
+        };
+        let is_port = |clock: &Clock| matches!(clock, Clock::Node(n) if port_of.contains_key(n));
+
+        let mut deferred = vec![];
+        for check in std::mem::take(&mut graph.checks) {
+            if primitive {
+                break;
+            }
+            let clock = graph.canonical(check.clock);
+            let sources = match check.value {
+                CheckValue::Node(node) => domains[graph.find(node)].clone(),
+                CheckValue::Source(source, origin) => {
+                    BTreeMap::from([(graph.canonical_source(source), origin)])
+                }
+            };
+            for (source, origin) in sources {
+                match source {
+                    Source::Port(port) => {
+                        if !root {
+                            deferred.push(DeferredCheck {
+                                clock: export(clock),
+                                source: Source::Port(port),
+                                origin,
+                                at: check.at,
+                                register: check.register,
+                            })
+                        }
+                    }
+                    Source::Clock(other) if other == clock => {}
+                    Source::Clock(other) => {
+                        if !root && (is_port(&clock) || is_port(&other)) {
+                            deferred.push(DeferredCheck {
+                                clock: export(clock),
+                                source: Source::Clock(export(other)),
+                                origin,
+                                at: check.at,
+                                register: check.register,
+                            })
+                        } else {
+                            self.report(&check, origin)
+                        }
+                    }
+                }
+            }
+        }
+
+        let ports = interface
+            .iter()
+            .map(|(node, incoming)| {
+                if *incoming {
+                    return BTreeMap::new();
+                }
+                domains[graph.find(*node)]
+                    .iter()
+                    .map(|(source, origin)| {
+                        let source = match source {
+                            Source::Clock(c) => Source::Clock(export(*c)),
+                            Source::Port(p) => Source::Port(*p),
+                        };
+                        (source, *origin)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Summary { ports, deferred }
+    }
+}
+
+/// Finds registers in `entities` which sample values from other clock domains without
+/// going through a unit in `primitives`
+pub fn clock_domain_crossings(
+    entities: &[&Entity],
+    primitives: &BTreeSet<UnitNameKind>,
+) -> Vec<ClockDomainCrossing> {
+    let instantiated = entities
+        .iter()
+        .flat_map(|e| &e.statements)
+        .filter_map(|s| match s {
+            Statement::Binding(Binding {
+                operator: Operator::Instance { name, .. },
+                ..
+            }) => Some(&name.kind),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+
+    let mut analysis = Analysis {
+        entities: entities.iter().map(|e| (&e.name.kind, *e)).collect(),
+        primitives,
+        summaries: BTreeMap::new(),
+        crossings: BTreeMap::new(),
+    };
+
+    for entity in entities {
+        if instantiated.contains(&entity.name.kind) {
+            analysis.summary(&entity.name.kind);
+        } else {
+            analysis.analyse(entity, true);
+        }
+    }
+
+    analysis.crossings.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::entity;
+    use crate::unit_name::IntoUnitName;
+    use crate::{self as spade_mir};
+    use codespan::Span;
+    use spade_common::location_info::WithLocation;
+
+    fn loc(idx: u32) -> Loc<()> {
+        Loc::new((), Span::new(idx, idx + 1), 0)
+    }
+
+    /// Places each register at the index of its statement
+    fn with_locs(mut entity: Entity) -> Entity {
+        for (idx, statement) in entity.statements.iter_mut().enumerate() {
+            if let Statement::Register(reg) = statement {
+                reg.loc = Some(loc(idx as u32))
+            }
+        }
+        entity
+    }
+
+    fn sampler() -> Entity {
+        with_locs(entity!(&["sampler"]; (
+            "clk", n(0, "clk"), Type::Bool,
+            "x", n(1, "x"), Type::Bool,
+        ) -> Type::Bool; {
+            (reg n(2, "r"); Type::Bool; clock (n(0, "clk")); n(1, "x"))
+        } => n(2, "r")))
+    }
+
+    fn top(sample_clock: ValueName) -> Entity {
+        let mut top = with_locs(entity!(&["top"]; (
+            "clk1", n(0, "clk1"), Type::Bool,
+            "clk2", n(1, "clk2"), Type::Bool,
+            "x", n(2, "x"), Type::Bool,
+        ) -> Type::Bool; {
+            (reg n(3, "a"); Type::Bool; clock (n(0, "clk1")); n(2, "x"));
+            (e(0); Type::Bool; simple_instance((["sampler"]._test_into_unit_name(), vec!["clk", "x"])); n(1, "clk2"), n(3, "a"))
+        } => e(0)));
+        if let Statement::Binding(b) = &mut top.statements[1] {
+            b.operands[0] = sample_clock;
+            b.loc = Some(loc(1));
+        }
+        top
+    }
+
+    #[test]
+    fn registers_in_different_domains_are_reported() {
+        let top = with_locs(entity!(&["top"]; (
+            "clk1", n(0, "clk1"), Type::Bool,
+            "clk2", n(1, "clk2"), Type::Bool,
+            "x", n(2, "x"), Type::int(8),
+        ) -> Type::int(8); {
+            (reg n(3, "a"); Type::int(8); clock (n(0, "clk1")); n(2, "x"));
+            (e(0); Type::int(8); Add; n(3, "a"), n(2, "x"));
+            (reg n(4, "b"); Type::int(8); clock (n(1, "clk2")); e(0));
+            (reg n(5, "c"); Type::int(8); clock (n(0, "clk1")); n(3, "a"))
+        } => n(4, "b")));
+
+        assert_eq!(
+            clock_domain_crossings(&[&top], &BTreeSet::new()),
+            vec![ClockDomainCrossing {
+                at: loc(2),
+                origin: Some(loc(0)),
+                register: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn crossings_into_instances_are_resolved_by_the_caller() {
+        let sampler = sampler();
+
+        let top_same = top(spade_mir::value_name!(n(0, "clk1")));
+        assert_eq!(
+            clock_domain_crossings(&[&top_same, &sampler], &BTreeSet::new()),
+            vec![]
+        );
+
+        let top_other = top(spade_mir::value_name!(n(1, "clk2")));
+        assert_eq!(
+            clock_domain_crossings(&[&top_other, &sampler], &BTreeSet::new()),
+            vec![ClockDomainCrossing {
+                at: loc(1),
+                origin: Some(loc(0)),
+                register: Some(loc(0)),
+            }]
+        );
+    }
+
+    #[test]
+    fn crossings_in_nested_instances_are_reported_at_the_outermost_instance() {
+        let sampler = sampler();
+        let mut middle = entity!(&["middle"]; (
+            "clk", n(0, "clk"), Type::Bool,
+            "x", n(1, "x"), Type::Bool,
+        ) -> Type::Bool; {
+            (e(0); Type::Bool; simple_instance((["sampler"]._test_into_unit_name(), vec!["clk", "x"])); n(0, "clk"), n(1, "x"))
+        } => e(0));
+        if let Statement::Binding(b) = &mut middle.statements[0] {
+            b.loc = Some(loc(10));
+        }
+        let mut top = top(spade_mir::value_name!(n(1, "clk2")));
+        if let Statement::Binding(b) = &mut top.statements[1] {
+            b.operator =
+                Operator::simple_instance(["middle"]._test_into_unit_name(), vec!["clk", "x"]);
+        }
+
+        assert_eq!(
+            clock_domain_crossings(&[&top, &middle, &sampler], &BTreeSet::new()),
+            vec![ClockDomainCrossing {
+                at: loc(1),
+                origin: Some(loc(0)),
+                register: Some(loc(0)),
+            }]
+        );
+    }
+
+    #[test]
+    fn crossings_in_primitives_are_not_reported() {
+        let sampler = sampler();
+        let mut top = top(spade_mir::value_name!(n(1, "clk2")));
+        // The output of the primitive is in the domain of clk2
+        top.statements.push(statement_reg_at(
+            5,
+            spade_mir::statement!(reg n(4, "b"); Type::Bool; clock (n(1, "clk2")); e(0)),
+        ));
+
+        assert_eq!(
+            clock_domain_crossings(
+                &[&top, &sampler],
+                &BTreeSet::from([sampler.name.kind.clone()])
+            ),
+            vec![]
+        );
+    }
+
+    fn statement_reg_at(idx: u32, mut statement: Statement) -> Statement {
+        if let Statement::Register(reg) = &mut statement {
+            reg.loc = Some(loc(idx))
+        }
+        statement
+    }
+
+    #[test]
+    fn domains_are_tracked_through_ports() {
+        let port = Type::Struct(vec![(
+            "a".to_string(),
+            Type::Backward(Box::new(Type::Bool)),
+        )]);
+        let flipped = Type::Struct(vec![("a".to_string(), Type::Bool)]);
+        let driver = with_locs(entity!(&["driver"]; (
+            "clk", n(0, "clk"), Type::Bool,
+            "p", n(1, "p"), port.clone(),
+        ) -> Type::Bool; {
+            (reg n(2, "r"); Type::Bool; clock (n(0, "clk")); n(2, "r"));
+            (e(0); Type::Backward(Box::new(Type::Bool)); IndexTuple((0, vec![Type::Backward(Box::new(Type::Bool))])); n(1, "p"));
+            (set; e(0); n(2, "r"))
+        } => n(2, "r")));
+
+        let top = with_locs(entity!(&["top"]; (
+            "clk1", n(0, "clk1"), Type::Bool,
+            "clk2", n(1, "clk2"), Type::Bool,
+        ) -> Type::Bool; {
+            (e(0); port.clone(); Nop;);
+            (e(1); flipped.clone(); FlipPort; e(0));
+            (e(2); Type::Bool; simple_instance((["driver"]._test_into_unit_name(), vec!["clk", "p"])); n(0, "clk1"), e(0));
+            (e(3); Type::Bool; IndexTuple((0, vec![Type::Bool])); e(1));
+            (reg n(3, "b"); Type::Bool; clock (n(1, "clk2")); e(3))
+        } => n(3, "b")));
+
+        assert_eq!(
+            clock_domain_crossings(&[&top, &driver], &BTreeSet::new()),
+            vec![ClockDomainCrossing {
+                at: loc(4),
+                origin: Some(loc(0)),
+                register: None,
+            }]
+        );
+    }
+}
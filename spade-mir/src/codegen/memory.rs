@@ -0,0 +1,163 @@
+use nesty::{code, Code};
+use num::{BigUint, One, ToPrimitive, Zero};
+
+use crate::eval::eval_statements;
+use crate::{ReadDuringWrite, Statement};
+
+/// Verilog for the bits `[start+width-1:start]` of `op`
+fn bits(op: &str, start: &BigUint, width: &BigUint) -> String {
+    if width.is_one() {
+        format!("{op}[{start}]")
+    } else {
+        format!("{op}[{}:{start}]", start + width - 1u32)
+    }
+}
+
+/// The signals of a memory port, packed as a (write mask, address, data) tuple
+/// starting at bit `start` of `op`
+pub struct MemoryPort<'a> {
+    op: &'a str,
+    data_start: BigUint,
+    addr: String,
+    mask_start: BigUint,
+    inner_w: &'a BigUint,
+    mask_w: &'a BigUint,
+}
+
+impl<'a> MemoryPort<'a> {
+    pub fn new(
+        op: &'a str,
+        start: &BigUint,
+        addr_w: &BigUint,
+        inner_w: &'a BigUint,
+        mask_w: &'a BigUint,
+    ) -> Self {
+        let addr_start = start + inner_w;
+        Self {
+            op,
+            data_start: start.clone(),
+            addr: bits(op, &addr_start, addr_w),
+            mask_start: addr_start + addr_w,
+            inner_w,
+            mask_w,
+        }
+    }
+
+    /// The (offset, width) of the part of the element controlled by each bit of the mask
+    fn lanes(&self) -> Vec<(BigUint, BigUint)> {
+        if self.mask_w.is_one() {
+            vec![(BigUint::zero(), self.inner_w.clone())]
+        } else {
+            let lanes = self.mask_w.to_u64().expect("Write mask is too wide");
+            (0..lanes)
+                .map(|i| {
+                    let lo = BigUint::from(i * 8);
+                    let width = (self.inner_w - &lo).min(BigUint::from(8u32));
+                    (lo, width)
+                })
+                .collect()
+        }
+    }
+
+    fn mask_bit(&self, lane: usize) -> String {
+        format!("{}[{}]", self.op, &self.mask_start + lane)
+    }
+
+    fn data(&self, lo: &BigUint, width: &BigUint) -> String {
+        bits(self.op, &(&self.data_start + lo), width)
+    }
+
+    /// The part of `target` controlled by a mask bit. The whole value if there is a
+    /// single write enable
+    fn lane_of(&self, target: &str, lo: &BigUint, width: &BigUint) -> String {
+        if self.mask_w.is_one() {
+            target.to_string()
+        } else {
+            bits(target, lo, width)
+        }
+    }
+
+    /// Statements writing the data to `mem` for each enabled lane
+    pub fn write(&self, mem: &str) -> Code {
+        let element = format!("{mem}[{}]", self.addr);
+        let writes = self
+            .lanes()
+            .iter()
+            .enumerate()
+            .map(|(i, (lo, width))| {
+                code! {
+                    [0] format!("if ({}) begin", self.mask_bit(i));
+                    [1]     format!(
+                                "{} <= {};",
+                                self.lane_of(&element, lo, width),
+                                self.data(lo, width)
+                            );
+                    [0] "end";
+                }
+                .to_string()
+            })
+            .collect::<Vec<_>>();
+        code! {[0] writes}
+    }
+
+    /// Statements reading the addressed element of `mem` into `q`
+    pub fn read(&self, mem: &str, q: &str, read_during_write: ReadDuringWrite) -> Code {
+        let element = format!("{mem}[{}]", self.addr);
+        match read_during_write {
+            ReadDuringWrite::ReadFirst => code! {
+                [0] format!("{q} <= {element};");
+            },
+            ReadDuringWrite::WriteFirst => {
+                let lanes = self
+                    .lanes()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (lo, width))| {
+                        format!(
+                            "{} <= {} ? {} : {};",
+                            self.lane_of(q, lo, width),
+                            self.mask_bit(i),
+                            self.data(lo, width),
+                            self.lane_of(&element, lo, width),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                code! {[0] lanes}
+            }
+            ReadDuringWrite::NoChange => {
+                let any_write = if self.mask_w.is_one() {
+                    self.mask_bit(0)
+                } else {
+                    format!("(|{})", bits(self.op, &self.mask_start, self.mask_w))
+                };
+                code! {
+                    [0] format!("if (!{any_write}) begin");
+                    [1]     format!("{q} <= {element};");
+                    [0] "end";
+                }
+            }
+        }
+    }
+}
+
+/// An initial block assigning the initial values to the elements of `mem`
+pub fn memory_initial_block(mem: &str, initial: &Option<Vec<Vec<Statement>>>) -> Code {
+    if let Some(vals) = initial {
+        let assignments = vals
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let val = eval_statements(v).as_string();
+
+                format!("{mem}[{i}] = 'b{val};")
+            })
+            .collect::<Vec<_>>();
+        code! {
+            [0] "initial begin";
+            [1]     assignments;
+            [0] "end";
+        }
+    } else {
+        code! {}
+    }
+}
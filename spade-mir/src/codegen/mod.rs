@@ -12,6 +12,7 @@ use spade_diagnostics::{CodeBundle, CompilationError, DiagHandler};
 
 use crate::aliasing::flatten_aliases;
 use crate::assertion_codegen::AssertedExpression;
+use crate::codegen::memory::{memory_initial_block, MemoryPort};
 use crate::eval::eval_statements;
 use crate::renaming::{make_names_predictable, VerilogNameMap};
 use crate::type_list::TypeList;
@@ -20,9 +21,11 @@ use crate::unit_name::{InstanceMap, InstanceNameTracker};
 use crate::verilog::{self, assign, localparam_size_spec, logic, size_spec};
 use crate::wal::insert_wal_signals;
 use crate::{
-    enum_util, Binding, ConstantValue, Entity, MirInput, Operator, ParamName, Statement, ValueName,
+    enum_util, Binding, ConstantValue, Entity, MirInput, Operator, ParamName, Property, ResetStyle,
+    Statement, ValueName, VerilogAttribute,
 };
 
+mod memory;
 pub mod util;
 
 pub use util::{escape_path, mangle_entity, mangle_input, mangle_output, TupleIndex};
@@ -44,6 +47,15 @@ fn source_attribute(loc: &Option<Loc<()>>, code: &Option<CodeBundle>) -> Option<
     }
 }
 
+/// Produces the user specified synthesis attributes of a declaration, if there are any
+fn verilog_attributes(attrs: &[VerilogAttribute]) -> Option<String> {
+    if attrs.is_empty() {
+        None
+    } else {
+        Some(format!("(* {} *)", attrs.iter().join(", ")))
+    }
+}
+
 fn add_to_name_map(name_map: &mut VerilogNameMap, name: &ValueName, ty: &Type) {
     if ty.size() != BigUint::zero() {
         name_map.insert(&name.var_name(), name.verilog_name_source_fwd());
@@ -75,8 +87,14 @@ fn statement_declaration(
                     }
                     _ => logic(&name, &binding.ty.size()),
                 }];
+                // The attributes of block RAMs belong to their internal memory
+                let attributes = match &binding.operator {
+                    Operator::DeclBlockRam { .. } => None,
+                    _ => verilog_attributes(&binding.verilog_attrs),
+                };
                 code![
                     [0] source_attribute(&binding.loc, code);
+                    [0] attributes;
                     [0] inner
                 ]
             } else {
@@ -98,6 +116,28 @@ fn statement_declaration(
                 .map(ValueName::var_name)
                 .collect::<Vec<_>>();
 
+            // Block RAMs store their content and read values in internal signals
+            let internal_declaration = match &binding.operator {
+                Operator::DeclBlockRam { inner_w, elems, .. } => {
+                    let mem = binding.name.aux_var_name("mem");
+                    let mem_declaration = if *inner_w > 1u32.to_biguint() {
+                        format!("logic[{inner_w}-1:0] {mem}[{elems}-1:0];")
+                    } else {
+                        format!("logic {mem}[{elems}-1:0];")
+                    };
+                    let port_count = binding.operands.len() / 2;
+                    let q_declarations = (0..port_count)
+                        .map(|p| logic(&binding.name.aux_var_name(&format!("q{p}")), inner_w))
+                        .collect::<Vec<_>>();
+                    code! {
+                        [0] verilog_attributes(&binding.verilog_attrs);
+                        [0] mem_declaration;
+                        [0] q_declarations;
+                    }
+                }
+                _ => code![],
+            };
+
             // Aliases of memories have to be treated differently because we can't
             // assign them
             let assignment = match &binding.operator {
@@ -113,6 +153,7 @@ fn statement_declaration(
             code! {
                 [0] &forward_declaration;
                 [0] &backward_declaration;
+                [0] &internal_declaration;
                 [0] &assignment;
             }
         }
@@ -126,6 +167,7 @@ fn statement_declaration(
                 let declaration = verilog::reg(&name, &reg.ty.size());
                 code! {
                     [0] source_attribute(&reg.loc, code);
+                    [0] verilog_attributes(&reg.verilog_attrs);
                     [0] &declaration;
                 }
             } else {
@@ -139,6 +181,9 @@ fn statement_declaration(
         Statement::Assert(_) => {
             code! {}
         }
+        Statement::Property(_) => {
+            code! {}
+        }
         Statement::Set { .. } => {
             code! {}
         }
@@ -345,7 +390,7 @@ fn forward_expression_code(binding: &Binding, types: &TypeList, ops: &[ValueName
             Some(0) => op_names[0].to_string(),
             _ => format!("{{{}'b0, {}}}", extra_bits, op_names[0]),
         },
-        Operator::Match => {
+        Operator::Match { .. } => {
             assert!(
                 op_names.len() % 2 == 0,
                 "Match statements must have an even number of operands"
@@ -379,7 +424,7 @@ fn forward_expression_code(binding: &Binding, types: &TypeList, ops: &[ValueName
             )
             .to_string()
         }
-        Operator::Select => {
+        Operator::Select { .. } => {
             assert!(
                 binding.operands.len() == 3,
                 "expected 3 operands to Select operator"
@@ -454,77 +499,65 @@ fn forward_expression_code(binding: &Binding, types: &TypeList, ops: &[ValueName
             inner_w,
             elems: _,
             initial,
+            mask_w,
         } => {
-            let full_port_width = 1u32.to_biguint() + addr_w + inner_w;
-
-            let initial_block = if let Some(vals) = initial {
-                let assignments = vals
-                    .iter()
-                    .enumerate()
-                    .map(|(i, v)| {
-                        let val = eval_statements(v).as_string();
-
-                        format!("{}[{i}] = 'b{val};", name)
-                    })
-                    .collect::<Vec<_>>();
-                code! {
-                    [0] "initial begin";
-                    [1]     assignments;
-                    [0] "end";
-                }
-            } else {
-                code! {}
-            };
+            let full_port_width = mask_w + addr_w + inner_w;
 
             let update_blocks = (0..write_ports.to_usize().expect("Too many write ports"))
                 .map(|port| {
-                    let we_index =
-                        &full_port_width * (port + 1u32.to_biguint()) - 1u32.to_biguint();
-
-                    let addr_start = &full_port_width * port + inner_w;
-                    let addr = if *addr_w == 1u32.to_biguint() {
-                        format!("{}[{}]", op_names[1], addr_start)
-                    } else {
-                        format!(
-                            "{}[{}:{}]",
-                            op_names[1],
-                            &addr_start + addr_w - 1u32.to_biguint(),
-                            addr_start
-                        )
-                    };
-
-                    let write_value_start = port * &full_port_width;
-                    let (write_index, write_value) = if *inner_w == 1u32.to_biguint() {
-                        (
-                            format!("{}[{addr}]", name),
-                            format!("{}[{}]", op_names[1], &write_value_start),
-                        )
-                    } else {
-                        (
-                            format!("{}[{addr}]", name),
-                            format!(
-                                "{}[{end}:{write_value_start}]",
-                                op_names[1],
-                                end = &write_value_start + inner_w - 1u32.to_biguint()
-                            ),
-                        )
-                    };
-                    let we_signal = format!("{}[{we_index}]", op_names[1]);
+                    let port_start = &full_port_width * port;
+                    let port = MemoryPort::new(&op_names[1], &port_start, addr_w, inner_w, mask_w);
+                    port.write(&name).to_string()
+                })
+                .join("\n");
 
+            code! {
+                [0] memory_initial_block(&name, initial);
+                [0] format!("always @(posedge {clk}) begin", clk = op_names[0]);
+                [1]     update_blocks;
+                [0] "end";
+            }
+            .to_string()
+        }
+        Operator::DeclBlockRam {
+            addr_w,
+            inner_w,
+            elems: _,
+            mask_w,
+            read_during_write,
+            initial,
+        } => {
+            let mem = binding.name.aux_var_name("mem");
+            let port_count = op_names.len() / 2;
+
+            let port_blocks = (0..port_count)
+                .map(|p| {
+                    let port = MemoryPort::new(
+                        &op_names[p * 2 + 1],
+                        &BigUint::zero(),
+                        addr_w,
+                        inner_w,
+                        mask_w,
+                    );
+                    let q = binding.name.aux_var_name(&format!("q{p}"));
                     code! {
-                        [0] format!("if ({we_signal}) begin");
-                        [1]     format!("{write_index} <= {write_value};");
-                        [0] "end"
+                        [0] format!("always @(posedge {}) begin", op_names[p * 2]);
+                        [1]     port.write(&mem);
+                        [1]     port.read(&mem, &q, *read_during_write);
+                        [0] "end";
                     }
                     .to_string()
                 })
                 .join("\n");
 
+            let outputs = (0..port_count)
+                .map(|p| binding.name.aux_var_name(&format!("q{p}")))
+                .join(", ");
+
             code! {
-                [0] initial_block;
-                [0] format!("always @(posedge {clk}) begin", clk = op_names[0]);
-                [1]     update_blocks;
-                [0] "end";
+                [0] memory_initial_block(&mem, initial);
+                [0] port_blocks;
+                [0] format!("assign {name} = {{{outputs}}};");
             }
             .to_string()
         }
@@ -699,13 +732,14 @@ fn backward_expression_code(binding: &Binding, types: &TypeList, ops: &[ValueNam
         | Operator::ZeroExtend { .. }
         | Operator::Concat
         | Operator::DeclClockedMemory { .. }
+        | Operator::DeclBlockRam { .. }
         | Operator::ConstructEnum { .. }
         | Operator::IsEnumVariant { .. }
         | Operator::EnumMember { .. }
         | Operator::RangeIndexBits { .. }
         | Operator::IndexMemory
-        | Operator::Select
-        | Operator::Match
+        | Operator::Select { .. }
+        | Operator::Match { .. }
         | Operator::ReadPort
         | Operator::Truncate => panic!(
             "{} cannot be used on types with backward size",
@@ -827,7 +861,7 @@ fn statement_code(statement: &Statement, ctx: &mut Context) -> Code {
 
             // Unless this is a special operator, we just use assign value = expression
             let assignment = match &binding.operator {
-                Operator::Instance{name: module_name, params, loc} => {
+                Operator::Instance{name: module_name, params, verilog_params, loc} => {
                     // Input args
                     let mut args = binding
                         .operands
@@ -869,10 +903,19 @@ fn statement_code(statement: &Statement, ctx: &mut Context) -> Code {
                         ctx.instance_names
                     );
 
+                    let parameters = if verilog_params.is_empty() {
+                        String::new()
+                    } else {
+                        format!(
+                            " #({})",
+                            verilog_params.iter().map(|p| p.to_string()).join(", ")
+                        )
+                    };
+
                     code!{
                         [0] source_attribute(loc, ctx.source_code);
                         [0] format!(
-                            "{} {}({});",
+                            "{}{parameters} {}({});",
                             &module_name.as_verilog(),
                             instance_name,
                             args.join(", ")
@@ -889,7 +932,7 @@ fn statement_code(statement: &Statement, ctx: &mut Context) -> Code {
                         [0] backward_expression.map(|_| format!("assign {} = {};", back_ops[0], back_name));
                     }.to_string()
                 },
-                Operator::Match => forward_expression.unwrap(),
+                Operator::Match { .. } => forward_expression.unwrap(),
                 Operator::DivPow2 => forward_expression.unwrap(),
                 Operator::Gray2Bin{..} => forward_expression.unwrap(),
                 Operator::Nop => String::new(),
@@ -910,7 +953,9 @@ fn statement_code(statement: &Statement, ctx: &mut Context) -> Code {
                     }
                     .to_string()
                 }
-                Operator::DeclClockedMemory { .. } => forward_expression.unwrap(),
+                Operator::DeclClockedMemory { .. } | Operator::DeclBlockRam { .. } => {
+                    forward_expression.unwrap()
+                }
                 Operator::Bitreverse => {
                     let genvar = format!("{}_i", name);
                     let type_size = binding.ty.size();
@@ -935,9 +980,24 @@ fn statement_code(statement: &Statement, ctx: &mut Context) -> Code {
         Statement::Register(reg) => {
             let name = reg.name.var_name();
             let main_body = if let Some((rst_trig, rst_val)) = &reg.reset {
+                let ResetStyle {
+                    synchronous,
+                    active_low,
+                } = reg.reset_style;
+                let clock = reg.clock_edge.event(&reg.clock.var_name());
+                let sensitivity = match (synchronous, active_low) {
+                    (true, _) => clock,
+                    (false, false) => format!("{clock}, posedge {}", rst_trig.var_name()),
+                    (false, true) => format!("{clock}, negedge {}", rst_trig.var_name()),
+                };
+                let condition = if active_low {
+                    format!("!{}", rst_trig.var_name())
+                } else {
+                    rst_trig.var_name()
+                };
                 code! {
-                    [0] &format!("always @(posedge {}, posedge {}) begin", reg.clock.var_name(), rst_trig.var_name());
-                    [1]     &format!("if ({}) begin", rst_trig.var_name());
+                    [0] &format!("always @({sensitivity}) begin");
+                    [1]     &format!("if ({condition}) begin");
                     [2]         &format!("{} <= {};", name, rst_val.var_name());
                     [1]     &"end";
                     [1]     &"else begin";
@@ -947,7 +1007,7 @@ fn statement_code(statement: &Statement, ctx: &mut Context) -> Code {
                 }
             } else {
                 code! {
-                    [0] &format!("always @(posedge {}) begin", reg.clock.var_name());
+                    [0] &format!("always @({}) begin", reg.clock_edge.event(&reg.clock.var_name()));
                     [1]     &format!("{} <= {};", name, reg.value.var_name());
                     [0] &"end"
                 }
@@ -1046,6 +1106,35 @@ fn statement_code(statement: &Statement, ctx: &mut Context) -> Code {
                 [0] format!("`endif")
             }
         }
+        Statement::Property(Property {
+            kind,
+            clock,
+            reset,
+            antecedent,
+            consequent,
+            loc: _,
+        }) => {
+            let disable = reset
+                .as_ref()
+                .map(|rst| format!(" disable iff ({})", rst.var_name()))
+                .unwrap_or_default();
+            let antecedent = antecedent
+                .as_ref()
+                .map(|(val, implication)| format!("{} {implication} ", val.var_name()))
+                .unwrap_or_default();
+
+            // Concurrent properties are only understood by formal tools, so they are
+            // hidden from simulators and synthesis
+            code! {
+                [0] "`ifdef FORMAL";
+                [0] format!(
+                    "{kind} property (@(posedge {clk}){disable} {antecedent}{consequent});",
+                    clk = clock.var_name(),
+                    consequent = consequent.var_name()
+                );
+                [0] "`endif"
+            }
+        }
         Statement::Set { target, value } => {
             let assignment = format!(
                 "assign {} = {};",
@@ -1253,6 +1342,7 @@ pub fn entity_code(
         .join(",\n");
 
     let code = code! {
+        [0] verilog_attributes(&entity.verilog_attrs);
         [0] &format!("module {} (", entity_name);
                 [2] &port_definitions;
             [1] &");";
@@ -1304,7 +1394,10 @@ mod tests {
     use spade_common::name::Path;
 
     use crate as spade_mir;
-    use crate::{entity, statement, types::Type};
+    use crate::{
+        entity, statement, types::Type, ClockEdge, VerilogAttribute, VerilogParameter,
+        VerilogParameterValue,
+    };
 
     use indoc::indoc;
 
@@ -1401,6 +1494,170 @@ mod tests {
         );
     }
 
+    #[test]
+    fn registers_with_active_low_async_reset_work() {
+        let mut reg =
+            statement!(reg n(0, "r"); Type::int(7); clock (e(0)); reset (e(2), e(3)); e(1));
+        if let Statement::Register(reg) = &mut reg {
+            reg.reset_style = ResetStyle {
+                synchronous: false,
+                active_low: true,
+            };
+        }
+
+        let expected = indoc!(
+            r#"
+                reg[6:0] \r ;
+                always @(posedge _e_0, negedge _e_2) begin
+                    if (!_e_2) begin
+                        \r  <= _e_3;
+                    end
+                    else begin
+                        \r  <= _e_1;
+                    end
+                end"#
+        );
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &reg,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn registers_with_sync_reset_work() {
+        let mut reg =
+            statement!(reg n(0, "r"); Type::int(7); clock (e(0)); reset (e(2), e(3)); e(1));
+        if let Statement::Register(reg) = &mut reg {
+            reg.reset_style = ResetStyle {
+                synchronous: true,
+                active_low: false,
+            };
+        }
+
+        let expected = indoc!(
+            r#"
+                reg[6:0] \r ;
+                always @(posedge _e_0) begin
+                    if (_e_2) begin
+                        \r  <= _e_3;
+                    end
+                    else begin
+                        \r  <= _e_1;
+                    end
+                end"#
+        );
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &reg,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn falling_edge_registers_work() {
+        let mut reg =
+            statement!(reg n(0, "r"); Type::int(7); clock (e(0)); reset (e(2), e(3)); e(1));
+        if let Statement::Register(reg) = &mut reg {
+            reg.clock_edge = ClockEdge::Falling;
+        }
+
+        let expected = indoc!(
+            r#"
+                reg[6:0] \r ;
+                always @(negedge _e_0, posedge _e_2) begin
+                    if (_e_2) begin
+                        \r  <= _e_3;
+                    end
+                    else begin
+                        \r  <= _e_1;
+                    end
+                end"#
+        );
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &reg,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn registers_with_verilog_attributes_work() {
+        let mut reg = statement!(reg n(0, "r"); Type::int(7); clock (e(0)); e(1));
+        if let Statement::Register(reg) = &mut reg {
+            reg.verilog_attrs = vec![
+                VerilogAttribute {
+                    name: "ASYNC_REG".to_string(),
+                    value: Some(VerilogParameterValue::String("TRUE".to_string())),
+                },
+                VerilogAttribute {
+                    name: "keep".to_string(),
+                    value: None,
+                },
+            ];
+        }
+
+        let expected = indoc!(
+            r#"
+                (* ASYNC_REG = "TRUE", keep *)
+                reg[6:0] \r ;
+                always @(posedge _e_0) begin
+                    \r  <= _e_1;
+                end"#
+        );
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &reg,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn dual_edge_registers_work() {
+        let mut reg = statement!(reg n(0, "r"); Type::int(7); clock (e(0)); e(1));
+        if let Statement::Register(reg) = &mut reg {
+            reg.clock_edge = ClockEdge::Both;
+        }
+
+        let expected = indoc!(
+            r#"
+                reg[6:0] \r ;
+                always @(posedge _e_0, negedge _e_0) begin
+                    \r  <= _e_1;
+                end"#
+        );
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &reg,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
     #[test]
     fn registers_with_initial_values_work() {
         let initial_value = vec![
@@ -1487,6 +1744,7 @@ mod tests {
             output: ValueName::Expr(0),
             output_type: Type::Bool,
             statements: vec![],
+            verilog_attrs: vec![],
         };
 
         let expected = indoc!(
@@ -1534,6 +1792,7 @@ mod tests {
             output: ValueName::Expr(0),
             output_type: Type::Bool,
             statements: vec![],
+            verilog_attrs: vec![],
         };
 
         let expected = indoc!(
@@ -1712,6 +1971,41 @@ mod tests {
         )
     }
 
+    #[test]
+    fn instance_with_verilog_parameters_works() {
+        let input = statement!(e(0); Type::int(16); Instance({
+            name: spade_mir::UnitName::_test_from_strs(&["fifo"]),
+            params: vec![],
+            verilog_params: vec![
+                VerilogParameter {
+                    name: "WIDTH".to_string(),
+                    value: VerilogParameterValue::Int(16.into()),
+                },
+                VerilogParameter {
+                    name: "MODE".to_string(),
+                    value: VerilogParameterValue::String("FAST".to_string()),
+                },
+            ],
+            loc: None
+        }););
+
+        let expected = indoc!(
+            r#"
+            logic[15:0] _e_0;
+            \fifo  #(.WIDTH(16), .MODE("FAST")) fifo_0(.output__(_e_0));"#
+        );
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &input,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
     #[test]
     fn pipeline_with_stage_references_codegens_correctly() {
         let inst_name = spade_mir::UnitName::_test_from_strs(&["A"]);
@@ -1837,6 +2131,7 @@ mod tests {
                         ParamName{name: "a".to_string(), no_mangle: None},
                         ParamName{name: "b".to_string(), no_mangle: None},
                     ],
+                    verilog_params: vec![],
                     loc: None
                 }););
                 (e(0); Type::int(16); Instance({
@@ -1845,6 +2140,7 @@ mod tests {
                         ParamName{name: "a".to_string(), no_mangle: None},
                         ParamName{name: "b".to_string(), no_mangle: None},
                     ],
+                    verilog_params: vec![],
                     loc: None
                 }););
                 (n(0, "x_"); Type::int(16); Alias; e(0));
@@ -2047,7 +2343,10 @@ mod expression_tests {
     use spade_common::location_info::WithLocation;
     use spade_common::num_ext::InfallibleToBigInt;
 
-    use crate::{self as spade_mir, value_name, UnitName};
+    use crate::{
+        self as spade_mir, value_name, Implication, PropertyKind, ReadDuringWrite, UnitName,
+        VerilogAttribute, VerilogParameterValue,
+    };
     use crate::{statement, types::Type};
 
     use indoc::{formatdoc, indoc};
@@ -2142,7 +2441,7 @@ mod expression_tests {
 
     #[test]
     fn select_operator_works() {
-        let stmt = statement!(e(0); Type::int(2); Select; e(1), e(2), e(3));
+        let stmt = statement!(e(0); Type::int(2); Select({from_if: false}); e(1), e(2), e(3));
 
         let expected = indoc!(
             r#"
@@ -2184,7 +2483,8 @@ mod expression_tests {
 
     #[test]
     fn match_operator_works() {
-        let stmt = statement!(e(0); Type::int(2); Match; e(1), e(2), e(3), e(4));
+        let stmt =
+            statement!(e(0); Type::int(2); Match({arm_locs: vec![]}); e(1), e(2), e(3), e(4));
 
         let expected = indoc!(
             r#"
@@ -2529,6 +2829,7 @@ mod expression_tests {
                     ParamName{name: "a".to_string(), no_mangle: None},
                     ParamName{name: "b".to_string(), no_mangle: None},
                 ],
+                verilog_params: vec![],
                 loc: None
             });
             e(1),
@@ -2564,6 +2865,7 @@ mod expression_tests {
                 ParamName{name: "a".to_string(), no_mangle: None},
                 ParamName{name: "b".to_string(), no_mangle: None},
             ],
+            verilog_params: vec![],
             loc: None
         }); e(1), e(2));
 
@@ -2596,6 +2898,7 @@ mod expression_tests {
                 ParamName{name: "a".to_string(), no_mangle: None},
                 ParamName{name: "b".to_string(), no_mangle: None},
             ],
+            verilog_params: vec![],
             loc: None
         }); e(1), e(2));
 
@@ -2627,6 +2930,7 @@ mod expression_tests {
                 ParamName{name: "a".to_string(), no_mangle: None},
                 ParamName{name: "b".to_string(), no_mangle: None},
             ],
+            verilog_params: vec![],
             loc: None
         }); e(1), e(2));
 
@@ -2661,7 +2965,8 @@ mod expression_tests {
             addr_w: 4u32.to_biguint(),
             inner_w: 6u32.to_biguint(),
             elems: 16u32.to_biguint(),
-            initial: None
+            initial: None,
+            mask_w: 1u32.to_biguint()
         }); e(1), e(2));
 
         // Total write array length: 2 * (1 + 4 + 6)
@@ -2701,7 +3006,8 @@ mod expression_tests {
             addr_w: 1u32.to_biguint(),
             inner_w: 6u32.to_biguint(),
             elems: 16u32.to_biguint(),
-            initial: None
+            initial: None,
+            mask_w: 1u32.to_biguint()
         }); e(1), e(2));
 
         let expected = indoc!(
@@ -2736,7 +3042,8 @@ mod expression_tests {
             addr_w: 4u32.to_biguint(),
             inner_w: 1u32.to_biguint(),
             elems: 16u32.to_biguint(),
-            initial: None
+            initial: None,
+            mask_w: 1u32.to_biguint()
         }); e(1), e(2));
 
         // Total write array length: 2 * (1 + 4 + 6)
@@ -2776,7 +3083,8 @@ mod expression_tests {
             initial: Some(vec![
                 vec![statement!(const 10; Type::Int(6u32.to_biguint()); ConstantValue::Int(10.to_bigint()))],
                 vec![statement!(const 10; Type::Int(6u32.to_biguint()); ConstantValue::Int(5.to_bigint()))],
-            ])
+            ]),
+            mask_w: 1u32.to_biguint()
         }); e(1), e(2));
 
         // Total write array length: 2 * (1 + 4 + 6)
@@ -2809,6 +3117,164 @@ mod expression_tests {
         );
     }
 
+    #[test]
+    fn decl_clocked_memory_with_byte_enables_works() {
+        let t = Type::Array {
+            inner: Box::new(Type::int(16)),
+            length: 16u32.to_biguint(),
+        };
+        let stmt = statement!(e(0); t; DeclClockedMemory({
+            write_ports: 1u32.to_biguint(),
+            addr_w: 4u32.to_biguint(),
+            inner_w: 16u32.to_biguint(),
+            elems: 16u32.to_biguint(),
+            initial: None,
+            mask_w: 2u32.to_biguint()
+        }); e(1), e(2));
+
+        let expected = indoc!(
+            r#"
+            logic[255:0] _e_0;
+            always @(posedge _e_1) begin
+                if (_e_2[20]) begin
+                    _e_0[_e_2[19:16]][7:0] <= _e_2[7:0];
+                end
+                if (_e_2[21]) begin
+                    _e_0[_e_2[19:16]][15:8] <= _e_2[15:8];
+                end
+            end"#
+        );
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &stmt,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn true_dual_port_write_first_block_ram_works() {
+        let t = Type::Tuple(vec![Type::int(8), Type::int(8)]);
+        let stmt = statement!(e(0); t; DeclBlockRam({
+            addr_w: 4u32.to_biguint(),
+            inner_w: 8u32.to_biguint(),
+            elems: 16u32.to_biguint(),
+            mask_w: 1u32.to_biguint(),
+            read_during_write: ReadDuringWrite::WriteFirst,
+            initial: None
+        }); e(1), e(2), e(3), e(4));
+
+        let expected = indoc!(
+            r#"
+            logic[15:0] _e_0;
+            logic[8-1:0] _e_0_mem[16-1:0];
+            logic[7:0] _e_0_q0;
+            logic[7:0] _e_0_q1;
+            always @(posedge _e_1) begin
+                if (_e_2[12]) begin
+                    _e_0_mem[_e_2[11:8]] <= _e_2[7:0];
+                end
+                _e_0_q0 <= _e_2[12] ? _e_2[7:0] : _e_0_mem[_e_2[11:8]];
+            end
+            always @(posedge _e_3) begin
+                if (_e_4[12]) begin
+                    _e_0_mem[_e_4[11:8]] <= _e_4[7:0];
+                end
+                _e_0_q1 <= _e_4[12] ? _e_4[7:0] : _e_0_mem[_e_4[11:8]];
+            end
+            assign _e_0 = {_e_0_q0, _e_0_q1};"#
+        );
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &stmt,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn no_change_block_ram_with_byte_enables_works() {
+        let stmt = statement!(e(0); Type::int(16); DeclBlockRam({
+            addr_w: 4u32.to_biguint(),
+            inner_w: 16u32.to_biguint(),
+            elems: 16u32.to_biguint(),
+            mask_w: 2u32.to_biguint(),
+            read_during_write: ReadDuringWrite::NoChange,
+            initial: None
+        }); e(1), e(2));
+
+        let expected = indoc!(
+            r#"
+            logic[15:0] _e_0;
+            logic[16-1:0] _e_0_mem[16-1:0];
+            logic[15:0] _e_0_q0;
+            always @(posedge _e_1) begin
+                if (_e_2[20]) begin
+                    _e_0_mem[_e_2[19:16]][7:0] <= _e_2[7:0];
+                end
+                if (_e_2[21]) begin
+                    _e_0_mem[_e_2[19:16]][15:8] <= _e_2[15:8];
+                end
+                if (!(|_e_2[21:20])) begin
+                    _e_0_q0 <= _e_0_mem[_e_2[19:16]];
+                end
+            end
+            assign _e_0 = {_e_0_q0};"#
+        );
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &stmt,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn block_ram_attributes_are_on_the_memory() {
+        let mut stmt = statement!(e(0); Type::int(16); DeclBlockRam({
+            addr_w: 4u32.to_biguint(),
+            inner_w: 16u32.to_biguint(),
+            elems: 16u32.to_biguint(),
+            mask_w: 1u32.to_biguint(),
+            read_during_write: ReadDuringWrite::ReadFirst,
+            initial: None
+        }); e(1), e(2));
+        if let Statement::Binding(binding) = &mut stmt {
+            binding.verilog_attrs = vec![VerilogAttribute {
+                name: "ram_style".to_string(),
+                value: Some(VerilogParameterValue::String("block".to_string())),
+            }];
+        }
+
+        let expected = indoc!(
+            r#"
+            logic[15:0] _e_0;
+            (* ram_style = "block" *)
+            logic[16-1:0] _e_0_mem[16-1:0];
+            logic[15:0] _e_0_q0;"#
+        );
+
+        let code = statement_code_and_declaration(
+            &stmt,
+            &TypeList::empty(),
+            &CodeBundle::new("".to_string()),
+        )
+        .to_string();
+        assert!(code.starts_with(expected), "{code}");
+    }
+
     #[test]
     fn truncate_works() {
         let stmt = statement!(e(0); Type::int(5); Truncate; e(1));
@@ -3041,6 +3507,93 @@ mod expression_tests {
         );
     }
 
+    #[test]
+    fn property_codegen_works() {
+        let stmt = Statement::Property(Property {
+            kind: PropertyKind::Assert,
+            clock: value_name!(e(0)),
+            reset: Some(value_name!(e(1))),
+            antecedent: Some((value_name!(e(2)), Implication::NonOverlapping)),
+            consequent: value_name!(e(3)),
+            loc: None,
+        });
+
+        let expected = indoc! {
+            r#"
+            `ifdef FORMAL
+            assert property (@(posedge _e_0) disable iff (_e_1) _e_2 |=> _e_3);
+            `endif"#
+        };
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &stmt,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn cover_property_codegen_works() {
+        let stmt = Statement::Property(Property {
+            kind: PropertyKind::Cover,
+            clock: value_name!(e(0)),
+            reset: None,
+            antecedent: None,
+            consequent: value_name!(e(1)),
+            loc: None,
+        });
+
+        let expected = indoc! {
+            r#"
+            `ifdef FORMAL
+            cover property (@(posedge _e_0) _e_1);
+            `endif"#
+        };
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &stmt,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
+    #[test]
+    fn assume_property_codegen_works() {
+        let stmt = Statement::Property(Property {
+            kind: PropertyKind::Assume,
+            clock: value_name!(e(0)),
+            reset: None,
+            antecedent: Some((value_name!(e(1)), Implication::Overlapping)),
+            consequent: value_name!(e(2)),
+            loc: None,
+        });
+
+        let expected = indoc! {
+            r#"
+            `ifdef FORMAL
+            assume property (@(posedge _e_0) _e_1 |-> _e_2);
+            `endif"#
+        };
+
+        assert_same_code!(
+            &statement_code_and_declaration(
+                &stmt,
+                &TypeList::empty(),
+                &CodeBundle::new("".to_string())
+            )
+            .to_string(),
+            expected
+        );
+    }
+
     #[test]
     fn set_codegen_works() {
         let stmt = Statement::Set {
@@ -3136,6 +3689,7 @@ mod expression_tests {
             output: ValueName::Expr(0),
             output_type: Type::Void,
             statements: vec![],
+            verilog_attrs: vec![],
         };
 
         let expected = indoc!(
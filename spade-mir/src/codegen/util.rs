@@ -29,6 +29,23 @@ impl ValueName {
         }
     }
 
+    /// The name of an auxiliary variable belonging to this value, for operators
+    /// which need internal signals
+    pub fn aux_var_name(&self, suffix: &str) -> String {
+        match self {
+            ValueName::Named(id, name, _) => {
+                if *id == 0 {
+                    format!("\\{name}_{suffix} ")
+                } else {
+                    format!("{name}_n{id}_{suffix}")
+                }
+            }
+            ValueName::Expr(id) => {
+                format!("_e_{id}_{suffix}")
+            }
+        }
+    }
+
     pub fn backward_var_name(&self) -> String {
         match self {
             ValueName::Named(id, name, _) => {
@@ -0,0 +1,266 @@
+//! Functional coverage instrumentation. Like the signals inserted by
+//! [`crate::wal::insert_wal_signals`], coverage signals are aliases with predictable
+//! names which external tools can find in a VCD file. Every inserted signal is
+//! described by a [CoveragePoint] which maps it back to the source code.
+
+use serde::{Deserialize, Serialize};
+use spade_common::id_tracker::NameIdTracker;
+use spade_common::location_info::Loc;
+
+use crate::types::Type;
+use crate::wal::wal_alias;
+use crate::{Binding, Entity, Operator, Register, Statement, ValueName};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CoverageKind {
+    /// The condition of arm `arm` of a `match` with `arms` arms. An arm is taken when
+    /// its condition is high and the conditions of all previous arms are low
+    MatchArm { arm: usize, arms: usize },
+    /// The condition of an `if`. The true branch is taken when the signal is high,
+    /// the false branch when it is low
+    If,
+    /// A register containing an enum with `variants` variants. The current variant
+    /// is the tag in the most significant bits of the signal
+    EnumRegister { variants: usize },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CoveragePoint {
+    /// The unescaped verilog name of the inserted signal
+    pub signal: String,
+    pub kind: CoverageKind,
+    pub loc: Loc<()>,
+}
+
+// NOTE: Like insert_wal_signals, this pass must run after flatten_aliases and
+// make_names_predictable.
+// NOTE: idtracker must be set to None for *codegen* to be correct, but it can be set
+// to an ID tracker for testing purposes, for mir diffing to work
+pub fn insert_coverage_signals(
+    entity: &mut Entity,
+    name_idtracker: &mut Option<&mut NameIdTracker>,
+) -> Vec<CoveragePoint> {
+    let mut points = vec![];
+    let mut new_statements = vec![];
+
+    let mut alias = |new_statements: &mut Vec<Statement>,
+                     source: &ValueName,
+                     prefix: &str,
+                     suffix: &str,
+                     ty: &Type,
+                     kind: CoverageKind,
+                     loc: &Loc<()>| {
+        let stmt = wal_alias(source, prefix, suffix, ty, name_idtracker);
+        if let Statement::Binding(Binding { name, .. }) = &stmt {
+            points.push(CoveragePoint {
+                signal: name.unescaped_var_name(),
+                kind,
+                loc: *loc,
+            });
+        }
+        new_statements.push(stmt);
+    };
+
+    for stmt in &entity.statements {
+        new_statements.push(stmt.clone());
+        match stmt {
+            Statement::Binding(Binding {
+                name,
+                operator: Operator::Match { arm_locs },
+                operands,
+                ty: _,
+                loc: Some(loc),
+                verilog_attrs: _,
+            }) => {
+                let prefix = name.unescaped_var_name();
+                let arms = operands.len() / 2;
+                for (arm, cond) in operands.iter().step_by(2).enumerate() {
+                    alias(
+                        &mut new_statements,
+                        cond,
+                        &prefix,
+                        &format!("__cov_arm{arm}"),
+                        &Type::Bool,
+                        CoverageKind::MatchArm { arm, arms },
+                        // Matches built without arm locations, like those in parsed
+                        // MIR, are attributed to the match as a whole
+                        arm_locs.get(arm).unwrap_or(loc),
+                    );
+                }
+            }
+            Statement::Binding(Binding {
+                name,
+                operator: Operator::Select { from_if: true },
+                operands,
+                ty: _,
+                loc: Some(loc),
+                verilog_attrs: _,
+            }) => alias(
+                &mut new_statements,
+                &operands[0],
+                &name.unescaped_var_name(),
+                "__cov_if",
+                &Type::Bool,
+                CoverageKind::If,
+                loc,
+            ),
+            Statement::Register(Register {
+                name,
+                ty: ty @ Type::Enum(variants),
+                loc: Some(loc),
+                ..
+            }) if variants.len() > 1 => alias(
+                &mut new_statements,
+                name,
+                &name.unescaped_var_name(),
+                "__cov_state",
+                ty,
+                CoverageKind::EnumRegister {
+                    variants: variants.len(),
+                },
+                loc,
+            ),
+            _ => {}
+        }
+    }
+    entity.statements = new_statements;
+    points
+}
+
+#[cfg(test)]
+mod test {
+    use spade_common::id_tracker::NameIdTracker;
+    use spade_common::location_info::{Loc, WithLocation};
+
+    use crate::{self as spade_mir, assert_same_mir, Statement};
+    use crate::{entity, types::Type};
+
+    use super::*;
+    use colored::Colorize;
+
+    fn with_loc(mut entity: Entity) -> Entity {
+        for stmt in &mut entity.statements {
+            match stmt {
+                Statement::Binding(b) => b.loc = Some(().nowhere()),
+                Statement::Register(r) => r.loc = Some(().nowhere()),
+                _ => {}
+            }
+        }
+        entity
+    }
+
+    #[test]
+    fn match_arms_get_coverage_signals() {
+        let mut input = with_loc(entity!(&["name"]; (
+            "a", n(0, "a"), Type::Bool,
+            "b", n(1, "b"), Type::Bool,
+        ) -> Type::int(8); {
+            (e(0); Type::int(8); Match({arm_locs: vec![]}); n(0, "a"), n(0, "a"), n(1, "b"), n(1, "b"));
+        } => e(0)));
+
+        let expected = with_loc(entity!(&["name"]; (
+            "a", n(0, "a"), Type::Bool,
+            "b", n(1, "b"), Type::Bool,
+        ) -> Type::int(8); {
+            (e(0); Type::int(8); Match({arm_locs: vec![]}); n(0, "a"), n(0, "a"), n(1, "b"), n(1, "b"));
+            (n(10, "_e_0__cov_arm0"); Type::Bool; Alias; n(0, "a"));
+            (n(11, "_e_0__cov_arm1"); Type::Bool; Alias; n(1, "b"));
+        } => e(0)));
+
+        let points =
+            insert_coverage_signals(&mut input, &mut Some(&mut NameIdTracker::new_at(100)));
+
+        assert_same_mir!(&input, &expected);
+        assert_eq!(
+            points.iter().map(|p| p.kind.clone()).collect::<Vec<_>>(),
+            vec![
+                CoverageKind::MatchArm { arm: 0, arms: 2 },
+                CoverageKind::MatchArm { arm: 1, arms: 2 }
+            ]
+        );
+    }
+
+    #[test]
+    fn match_arm_coverage_points_are_at_their_patterns() {
+        let arm_locs = vec![
+            ().at(0, &codespan::Span::new(10, 20)),
+            ().at(0, &codespan::Span::new(30, 40)),
+        ];
+        let mut input = with_loc(entity!(&["name"]; (
+            "a", n(0, "a"), Type::Bool,
+            "b", n(1, "b"), Type::Bool,
+        ) -> Type::int(8); {
+            (e(0); Type::int(8); Match({arm_locs: arm_locs.clone()}); n(0, "a"), n(0, "a"), n(1, "b"), n(1, "b"));
+        } => e(0)));
+
+        let points =
+            insert_coverage_signals(&mut input, &mut Some(&mut NameIdTracker::new_at(100)));
+
+        // Locs compare equal regardless of where they are, so the spans are compared
+        assert_eq!(
+            points.iter().map(|p| p.loc.span).collect::<Vec<_>>(),
+            arm_locs.iter().map(|l| l.span).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn only_selects_from_ifs_get_coverage_signals() {
+        let mut input = with_loc(entity!(&["name"]; (
+            "a", n(0, "a"), Type::Bool,
+            "b", n(1, "b"), Type::Bool,
+        ) -> Type::Bool; {
+            (e(0); Type::Bool; Select({from_if: true}); n(0, "a"), n(0, "a"), n(1, "b"));
+            (e(1); Type::Bool; Select({from_if: false}); n(1, "b"), e(0), n(0, "a"));
+        } => e(1)));
+
+        let expected = with_loc(entity!(&["name"]; (
+            "a", n(0, "a"), Type::Bool,
+            "b", n(1, "b"), Type::Bool,
+        ) -> Type::Bool; {
+            (e(0); Type::Bool; Select({from_if: true}); n(0, "a"), n(0, "a"), n(1, "b"));
+            (n(10, "_e_0__cov_if"); Type::Bool; Alias; n(0, "a"));
+            (e(1); Type::Bool; Select({from_if: false}); n(1, "b"), e(0), n(0, "a"));
+        } => e(1)));
+
+        let points =
+            insert_coverage_signals(&mut input, &mut Some(&mut NameIdTracker::new_at(100)));
+
+        assert_same_mir!(&input, &expected);
+        assert_eq!(
+            points.iter().map(|p| p.kind.clone()).collect::<Vec<_>>(),
+            vec![CoverageKind::If]
+        );
+    }
+
+    #[test]
+    fn enum_registers_get_coverage_signals() {
+        let ty = Type::Enum(vec![vec![], vec![Type::int(4)]]);
+        let mut input = with_loc(entity!(&["name"]; (
+            "clk", n(0, "clk"), Type::Bool,
+            "x", n(1, "x"), ty.clone(),
+        ) -> ty.clone(); {
+            (reg n(2, "state"); ty.clone(); clock(n(0, "clk")); n(1, "x"));
+        } => n(2, "state")));
+
+        let expected = with_loc(entity!(&["name"]; (
+            "clk", n(0, "clk"), Type::Bool,
+            "x", n(1, "x"), ty.clone(),
+        ) -> ty.clone(); {
+            (reg n(2, "state"); ty.clone(); clock(n(0, "clk")); n(1, "x"));
+            (n(10, "state_n2__cov_state"); ty.clone(); Alias; n(2, "state"));
+        } => n(2, "state")));
+
+        let points =
+            insert_coverage_signals(&mut input, &mut Some(&mut NameIdTracker::new_at(100)));
+
+        assert_same_mir!(&input, &expected);
+        assert_eq!(
+            points,
+            vec![CoveragePoint {
+                signal: "state_n2__cov_state_n100".to_string(),
+                kind: CoverageKind::EnumRegister { variants: 2 },
+                loc: Loc::nowhere(())
+            }]
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::{Entity, MirInput, Register, Statement, ValueName};
+use crate::{Entity, MirInput, Property, Register, Statement, ValueName};
 
 macro_rules! check {
     ($cond:expr) => {
@@ -128,23 +128,29 @@ fn compare_statements(s1: &Statement, s2: &Statement, var_map: &mut VarMap) -> b
                 name: _,
                 ty: ty1,
                 clock: clock1,
+                clock_edge: edge1,
                 reset: reset1,
+                reset_style: style1,
                 initial: initial1,
                 value: value1,
                 loc: _,
                 traced: _,
+                verilog_attrs: _,
             } = &r1;
             let Register {
                 name: _,
                 ty: ty2,
                 clock: clock2,
+                clock_edge: edge2,
                 reset: reset2,
+                reset_style: style2,
                 initial: initial2,
                 value: value2,
                 loc: _,
                 traced: _,
+                verilog_attrs: _,
             } = &r2;
-            if ty1 != ty2 {
+            if ty1 != ty2 || style1 != style2 || edge1 != edge2 {
                 return false;
             }
 
@@ -185,6 +191,48 @@ fn compare_statements(s1: &Statement, s2: &Statement, var_map: &mut VarMap) -> b
             check_name!(v1, v2);
             true
         }
+        (Statement::Property(p1), Statement::Property(p2)) => {
+            let Property {
+                kind: kind1,
+                clock: clock1,
+                reset: reset1,
+                antecedent: antecedent1,
+                consequent: consequent1,
+                loc: _,
+            } = p1;
+            let Property {
+                kind: kind2,
+                clock: clock2,
+                reset: reset2,
+                antecedent: antecedent2,
+                consequent: consequent2,
+                loc: _,
+            } = p2;
+            if kind1 != kind2 {
+                return false;
+            }
+
+            check_name!(clock1, clock2);
+            check_name!(consequent1, consequent2);
+
+            match (reset1, reset2) {
+                (Some(r1), Some(r2)) => check_name!(r1, r2),
+                (None, None) => {}
+                _ => return false,
+            }
+
+            match (antecedent1, antecedent2) {
+                (Some((a1, i1)), Some((a2, i2))) => {
+                    check_name!(a1, a2);
+                    if i1 != i2 {
+                        return false;
+                    }
+                }
+                (None, None) => {}
+                _ => return false,
+            }
+            true
+        }
         (
             Statement::Set {
                 target: tl,
@@ -249,6 +297,7 @@ fn populate_var_map(
             }
             (Statement::WalTrace { .. }, Statement::WalTrace { .. }) => Ok(()),
             (Statement::Assert(_), Statement::Assert(_)) => Ok(()),
+            (Statement::Property(_), Statement::Property(_)) => Ok(()),
             (Statement::Set { .. }, Statement::Set { .. }) => Ok(()),
             _ => Err(()),
         })
@@ -369,7 +418,7 @@ mod statement_comparison_tests {
         map.map_expr(2, 2);
 
         let lhs = statement!(e(0); Type::int(5); Add; e(1), e(2));
-        let rhs = statement!(e(3); Type::int(5); Select; e(1), e(2));
+        let rhs = statement!(e(3); Type::int(5); Select({from_if: false}); e(1), e(2));
 
         populate_var_map(&vec![lhs.clone()], &vec![rhs.clone()], &mut map).unwrap();
 
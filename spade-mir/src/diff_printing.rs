@@ -9,7 +9,7 @@ use std::collections::HashMap;
 use itertools::Itertools;
 
 use crate::{diff::VarMap, Entity};
-use crate::{Binding, MirInput, Register, Statement, ValueName};
+use crate::{Binding, ClockEdge, MirInput, Property, Register, ResetStyle, Statement, ValueName};
 
 pub fn translate_expr(
     name: u64,
@@ -103,6 +103,7 @@ where
             operands,
             ty,
             loc: _,
+            verilog_attrs: _,
         }) => {
             let name = translate_val_name(name, lhs_trans, rhs_trans);
             let operands = operands
@@ -117,20 +118,33 @@ where
             name,
             ty,
             clock,
+            clock_edge,
             reset,
+            reset_style,
             initial,
             value,
             loc: _,
             traced,
+            verilog_attrs: _,
         }) => {
             let name = translate_val_name(name, lhs_trans, rhs_trans);
-            let clock = translate_val_name(clock, lhs_trans, rhs_trans);
+            let clock = match clock_edge {
+                ClockEdge::Rising => translate_val_name(clock, lhs_trans, rhs_trans),
+                other => format!(
+                    "{other} {}",
+                    translate_val_name(clock, lhs_trans, rhs_trans)
+                ),
+            };
             let reset = reset
                 .as_ref()
                 .map(|(trig, val)| {
                     let trig = translate_val_name(trig, lhs_trans, rhs_trans);
                     let val = translate_val_name(val, lhs_trans, rhs_trans);
-                    format!(" reset ({}, {})", trig, val)
+                    if *reset_style == ResetStyle::default() {
+                        format!(" reset ({}, {})", trig, val)
+                    } else {
+                        format!(" reset ({}, {}; {})", trig, val, reset_style)
+                    }
                 })
                 .unwrap_or_else(|| "".to_string());
             let initial = initial
@@ -163,6 +177,31 @@ where
             let value = translate_val_name(value, lhs_trans, rhs_trans);
             format!("assert {value}")
         }
+        Statement::Property(Property {
+            kind,
+            clock,
+            reset,
+            antecedent,
+            consequent,
+            loc: _,
+        }) => {
+            let clock = translate_val_name(clock, lhs_trans, rhs_trans);
+            let reset = reset
+                .as_ref()
+                .map(|rst| format!(", {}", translate_val_name(rst, lhs_trans, rhs_trans)))
+                .unwrap_or_default();
+            let antecedent = antecedent
+                .as_ref()
+                .map(|(val, implication)| {
+                    format!(
+                        "{} {implication} ",
+                        translate_val_name(val, lhs_trans, rhs_trans)
+                    )
+                })
+                .unwrap_or_default();
+            let consequent = translate_val_name(consequent, lhs_trans, rhs_trans);
+            format!("{kind}({clock}{reset}) {antecedent}{consequent}")
+        }
         Statement::Set { target, value } => {
             let value = translate_val_name(value, lhs_trans, rhs_trans);
             let target = translate_val_name(target, lhs_trans, rhs_trans);
@@ -198,6 +237,7 @@ where
         output,
         output_type,
         statements,
+        verilog_attrs: _,
     } = entity;
 
     let inputs = inputs
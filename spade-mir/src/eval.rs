@@ -1,14 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use itertools::Itertools;
 use num::range;
 use num::BigInt;
 use num::BigUint;
+use num::Integer;
+use num::One;
 use num::ToPrimitive;
 use num::Zero;
 use spade_common::num_ext::InfallibleToBigUint;
 
-use crate::{enum_util, types::Type, Binding, Operator, Statement, ValueName};
+use crate::unit_name::{UnitName, UnitNameKind};
+use crate::{
+    enum_util, types::Type, Binding, ConstantValue, Entity, Operator, Statement, ValueName,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Value {
@@ -199,173 +204,588 @@ impl Value {
             val: val.into(),
         }
     }
-
-    fn undef(size: u64) -> Self {
-        Self::Undef(size.into())
-    }
 }
 
 /// Evaluates a list of statements, returning the value of the final statement in the
-/// list. Panics if the list of statements is empty
+/// list. Bits which depend on undefined values, like the padding of enums, are `X`.
+/// Panics if the list of statements is empty or can not be evaluated
 pub fn eval_statements(statements: &[Statement]) -> Value {
-    let mut name_vals: HashMap<ValueName, Value> = HashMap::new();
-    let mut name_types: HashMap<ValueName, Type> = HashMap::new();
-
-    let mut last_value = None;
-    for stmt in statements {
-        let (n, v) = match stmt {
-            Statement::Binding(b) => {
-                let Binding {
-                    name,
-                    operator,
-                    operands: ops,
-                    ty,
-                    loc: _,
-                } = b;
-
-                name_types.insert(name.clone(), ty.clone());
-
-                let val = match operator {
-                    Operator::Add => Value::Int {
-                        size: ty.size(),
-                        val: name_vals[&ops[0]].assume_int() + name_vals[&ops[1]].assume_int(),
-                    },
-                    Operator::UnsignedAdd => Value::UInt {
-                        size: ty.size(),
-                        val: name_vals[&ops[0]].assume_uint() + name_vals[&ops[1]].assume_uint(),
-                    },
-                    Operator::Sub => Value::Int {
-                        size: ty.size(),
-                        val: name_vals[&ops[0]].assume_int() - name_vals[&ops[1]].assume_int(),
-                    },
-                    Operator::UnsignedSub => Value::UInt {
-                        size: ty.size(),
-                        val: name_vals[&ops[0]].assume_uint() - name_vals[&ops[1]].assume_uint(),
-                    },
-                    Operator::Mul => todo!(),
-                    Operator::UnsignedMul => todo!(),
-                    Operator::Div => todo!(),
-                    Operator::UnsignedDiv => todo!(),
-                    Operator::Mod => todo!(),
-                    Operator::UnsignedMod => todo!(),
-                    Operator::Eq => todo!(),
-                    Operator::NotEq => todo!(),
-                    Operator::Gt => todo!(),
-                    Operator::Lt => todo!(),
-                    Operator::Ge => todo!(),
-                    Operator::Le => todo!(),
-                    Operator::UnsignedGt => todo!(),
-                    Operator::UnsignedLt => todo!(),
-                    Operator::UnsignedGe => todo!(),
-                    Operator::UnsignedLe => todo!(),
-                    Operator::LeftShift => todo!(),
-                    Operator::RightShift => todo!(),
-                    Operator::ArithmeticRightShift => todo!(),
-                    Operator::LogicalAnd => todo!(),
-                    Operator::LogicalOr => todo!(),
-                    Operator::LogicalXor => todo!(),
-                    Operator::LogicalNot => todo!(),
-                    Operator::BitwiseAnd => todo!(),
-                    Operator::BitwiseOr => todo!(),
-                    Operator::BitwiseXor => todo!(),
-                    Operator::Bitreverse => todo!(),
-                    Operator::USub => Value::Int {
-                        size: ty.size(),
-                        val: -name_vals[&ops[0]].assume_int(),
-                    },
-                    Operator::Not => todo!(),
-                    Operator::BitwiseNot => todo!(),
-                    Operator::DivPow2 => todo!(),
-                    Operator::Gray2Bin { .. } => todo!(),
-                    Operator::ReduceAnd { .. } => todo!(),
-                    Operator::ReduceOr { .. } => todo!(),
-                    Operator::ReduceXor { .. } => todo!(),
-                    Operator::SignExtend { .. } => todo!(),
-                    Operator::ZeroExtend { .. } => todo!(),
-                    Operator::Truncate => todo!(),
-                    Operator::Concat => todo!(),
-                    Operator::Select => todo!(),
-                    Operator::Match => todo!(),
-                    Operator::ConstructArray => {
-                        Value::Concat(ops.iter().rev().map(|op| name_vals[op].clone()).collect())
-                    }
-                    Operator::DeclClockedMemory { .. } => todo!(),
-                    Operator::IndexArray => todo!(),
-                    Operator::IndexMemory => todo!(),
-                    Operator::RangeIndexArray { .. } => todo!(),
-                    Operator::RangeIndexBits { .. } => todo!(),
-                    Operator::ConstructTuple => {
-                        Value::Concat(ops.iter().map(|op| name_vals[op].clone()).collect())
+    let output = match statements.last() {
+        Some(Statement::Binding(binding)) => binding.name.clone(),
+        Some(Statement::Constant(id, _, _)) => ValueName::Expr(*id),
+        Some(other) => panic!("Trying to evaluate {other}"),
+        None => panic!("Trying to evaluate empty statement list"),
+    };
+
+    // The bits which depend on undefined values are those which differ when the
+    // undefined values are all zeros and all ones
+    let [zeros, ones] = [false, true].map(|undefined| {
+        let evaluator = UnitEvaluator {
+            units: BTreeMap::new(),
+            undefined,
+        };
+        let mut frame = Frame::new(&evaluator, "the statements".to_string(), statements, 0);
+        frame.value(&output).unwrap_or_else(|e| panic!("{e}"))
+    });
+    if zeros == ones {
+        bits_value(&zeros)
+    } else {
+        Value::Concat(
+            zeros
+                .iter()
+                .zip(&ones)
+                .rev()
+                .map(|(zero, one)| {
+                    if zero == one {
+                        Value::Bit(*zero)
+                    } else {
+                        Value::Undef(1u32.into())
                     }
-                    Operator::ConstructEnum {
-                        variant,
-                        variant_count,
-                    } => {
-                        let tag_size = BigUint::from(enum_util::tag_size(*variant_count));
-
-                        let mut to_concat = if *variant_count == 1 {
-                            vec![]
-                        } else {
-                            vec![Value::UInt {
-                                size: tag_size.clone(),
-                                val: (*variant).into(),
-                            }]
-                        };
-                        to_concat.append(
-                            &mut ops
-                                .iter()
-                                .map(|op| {
-                                    let val = &name_vals[op];
-
-                                    val.clone()
-                                })
-                                .collect(),
-                        );
-                        let variant_member_size =
-                            ops.iter().map(|op| name_types[op].size()).sum::<BigUint>();
-                        let padding_size = ty.size() - tag_size - variant_member_size;
-                        if padding_size != BigUint::zero() {
-                            to_concat.push(Value::Undef(padding_size))
-                        }
-
-                        Value::Concat(to_concat)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The bits of a value, least significant bit first
+type Bits = Vec<bool>;
+
+/// Instances nested deeper than this are assumed to recurse forever
+const MAX_DEPTH: usize = 1024;
+
+/// The reason a unit could not be evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// The unit uses something which only has a meaning in hardware, like a register
+    Unsupported {
+        unit: String,
+        what: String,
+    },
+    /// An instantiated unit has no MIR
+    UnknownUnit(String),
+    DivisionByZero {
+        unit: String,
+    },
+    UndefinedValue {
+        unit: String,
+        name: String,
+    },
+    TooDeep {
+        unit: String,
+    },
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::Unsupported { unit, what } => {
+                write!(f, "{unit} cannot be evaluated because it uses {what}")
+            }
+            EvalError::UnknownUnit(unit) => write!(f, "{unit} is instantiated but has no code"),
+            EvalError::DivisionByZero { unit } => write!(f, "Division by zero in {unit}"),
+            EvalError::UndefinedValue { unit, name } => {
+                write!(f, "{name} is used in {unit} but never defined")
+            }
+            EvalError::TooDeep { unit } => write!(
+                f,
+                "Units are instantiated more than {MAX_DEPTH} levels deep when evaluating {unit}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+fn unit_display(name: &UnitName) -> String {
+    match &name.kind {
+        UnitNameKind::Unescaped(name) => name.clone(),
+        UnitNameKind::Escaped { path, .. } => path.join("::"),
+    }
+}
+
+fn index(value: &BigUint) -> usize {
+    value.to_usize().expect("Index does not fit in a usize")
+}
+
+fn bits_of(value: &BigInt, width: usize) -> Bits {
+    let value = value.mod_floor(&(BigInt::one() << width));
+    (0..width).map(|i| value.bit(i as u64)).collect()
+}
+
+fn unsigned(bits: &[bool]) -> BigInt {
+    bits.iter()
+        .rev()
+        .fold(BigInt::zero(), |acc, bit| (acc << 1) + u32::from(*bit))
+}
+
+fn signed(bits: &[bool]) -> BigInt {
+    match bits.last() {
+        Some(true) => unsigned(bits) - (BigInt::one() << bits.len()),
+        _ => unsigned(bits),
+    }
+}
+
+fn resize(bits: &[bool], width: usize, signed: bool) -> Bits {
+    let fill = signed && bits.last() == Some(&true);
+    (0..width)
+        .map(|i| bits.get(i).copied().unwrap_or(fill))
+        .collect()
+}
+
+/// Builds a vector from parts given most significant part first, like a Verilog
+/// concatenation
+fn concat<'b>(parts: impl IntoIterator<Item = &'b [bool]>) -> Bits {
+    let parts = parts.into_iter().collect::<Vec<_>>();
+    parts.into_iter().rev().flatten().copied().collect()
+}
+
+fn value_bits(value: &Value) -> Bits {
+    value.as_string().chars().rev().map(|c| c == '1').collect()
+}
+
+fn bits_value(bits: &[bool]) -> Value {
+    Value::UInt {
+        size: bits.len().into(),
+        val: unsigned(bits)
+            .to_biguint()
+            .expect("Unsigned values are positive"),
+    }
+}
+
+/// Evaluates units by evaluating the units they instantiate, with the semantics of the
+/// generated Verilog. Only combinational units can be evaluated
+pub struct UnitEvaluator<'a> {
+    units: BTreeMap<&'a UnitName, &'a Entity>,
+    /// The value of undefined bits, like the padding of enums
+    undefined: bool,
+}
+
+impl<'a> UnitEvaluator<'a> {
+    pub fn new(entities: impl IntoIterator<Item = &'a Entity>) -> Self {
+        Self {
+            units: entities.into_iter().map(|e| (&e.name, e)).collect(),
+            undefined: false,
+        }
+    }
+
+    /// Evaluates the unit `name` with `inputs` as its inputs. The output is returned as an
+    /// unsigned integer holding the bits of the output
+    pub fn eval_unit(&self, name: &UnitName, inputs: &[Value]) -> Result<Value, EvalError> {
+        let inputs = inputs.iter().map(value_bits).collect();
+        Ok(bits_value(&self.unit(name, inputs, 0)?))
+    }
+
+    /// Evaluates `output` in a list of statements which may instantiate the units known to
+    /// the evaluator. The value is returned as an unsigned integer holding its bits
+    pub fn eval_statements(
+        &self,
+        statements: &[Statement],
+        output: &ValueName,
+    ) -> Result<Value, EvalError> {
+        let mut frame = Frame::new(self, "the expression".to_string(), statements, 0);
+        Ok(bits_value(&frame.value(output)?))
+    }
+
+    fn unit(&self, name: &UnitName, inputs: Vec<Bits>, depth: usize) -> Result<Bits, EvalError> {
+        let unit = unit_display(name);
+        if depth > MAX_DEPTH {
+            return Err(EvalError::TooDeep { unit });
+        }
+        let entity = self
+            .units
+            .get(name)
+            .ok_or_else(|| EvalError::UnknownUnit(unit.clone()))?;
+        let mut frame = Frame::new(self, unit, &entity.statements, depth);
+        for (input, bits) in entity.inputs.iter().zip(inputs) {
+            let w = frame.width(&input.ty)?;
+            frame
+                .values
+                .insert(input.val_name.clone(), resize(&bits, w, false));
+        }
+        let w = frame.width(&entity.output_type)?;
+        let output = frame.value(&entity.output)?;
+        Ok(resize(&output, w, false))
+    }
+}
+
+/// The values computed while evaluating one list of statements
+struct Frame<'s> {
+    evaluator: &'s UnitEvaluator<'s>,
+    unit: String,
+    depth: usize,
+    definitions: HashMap<ValueName, &'s Statement>,
+    values: HashMap<ValueName, Bits>,
+}
+
+impl<'s> Frame<'s> {
+    fn new(
+        evaluator: &'s UnitEvaluator<'s>,
+        unit: String,
+        statements: &'s [Statement],
+        depth: usize,
+    ) -> Self {
+        let mut definitions = HashMap::new();
+        for statement in statements {
+            match statement {
+                Statement::Binding(binding) => {
+                    definitions.insert(binding.name.clone(), statement);
+                }
+                Statement::Constant(id, _, _) => {
+                    definitions.insert(ValueName::Expr(*id), statement);
+                }
+                Statement::Register(reg) => {
+                    definitions.insert(reg.name.clone(), statement);
+                }
+                // These have no effect on the value
+                Statement::Assert(_)
+                | Statement::Property(_)
+                | Statement::WalTrace { .. }
+                | Statement::Set { .. } => {}
+            }
+        }
+        Self {
+            evaluator,
+            unit,
+            depth,
+            definitions,
+            values: HashMap::new(),
+        }
+    }
+
+    fn unsupported<T>(&self, what: &str) -> Result<T, EvalError> {
+        Err(EvalError::Unsupported {
+            unit: self.unit.clone(),
+            what: what.to_string(),
+        })
+    }
+
+    fn width(&self, ty: &Type) -> Result<usize, EvalError> {
+        if !ty.backward_size().is_zero() {
+            return self.unsupported("ports");
+        }
+        match ty {
+            Type::Memory { .. } => self.unsupported("memories"),
+            Type::InOut(_) => self.unsupported("inout values"),
+            _ => match ty.size().to_usize() {
+                Some(w) => Ok(w),
+                None => self.unsupported(&format!("values as large as {ty}")),
+            },
+        }
+    }
+
+    fn operands(statement: &Statement) -> &[ValueName] {
+        match statement {
+            Statement::Binding(binding) => &binding.operands,
+            _ => &[],
+        }
+    }
+
+    /// The bits of `name`, computing the values it depends on first. The dependencies
+    /// are visited with an explicit stack as the statement lists of large units are too
+    /// deep to recurse through
+    fn value(&mut self, name: &ValueName) -> Result<Bits, EvalError> {
+        let mut stack = vec![(name.clone(), false)];
+        while let Some((name, expanded)) = stack.pop() {
+            if self.values.contains_key(&name) {
+                continue;
+            }
+            let statement =
+                *self
+                    .definitions
+                    .get(&name)
+                    .ok_or_else(|| EvalError::UndefinedValue {
+                        unit: self.unit.clone(),
+                        name: name.to_string(),
+                    })?;
+            if expanded {
+                let bits = self.statement_value(statement)?;
+                self.values.insert(name, bits);
+            } else {
+                stack.push((name, true));
+                for op in Self::operands(statement) {
+                    if !self.values.contains_key(op) {
+                        stack.push((op.clone(), false))
                     }
-                    Operator::IsEnumVariant { .. } => todo!(),
-                    Operator::EnumMember { .. } => todo!(),
-                    Operator::IndexTuple(_, _) => todo!(),
-                    Operator::ReadPort => todo!(),
-                    Operator::FlipPort => todo!(),
-                    Operator::ReadMutWires => todo!(),
-                    Operator::Instance { .. } => todo!(),
-                    Operator::Alias => name_vals[&ops[0]].clone(),
-                    Operator::Nop => todo!(),
-                };
+                }
+            }
+        }
+        Ok(self.values[name].clone())
+    }
 
-                (name.clone(), val)
-            }
-            Statement::Register(_) => panic!("trying to evaluate a register"),
-            Statement::Constant(id, ty, val) => {
-                let val = match val {
-                    crate::ConstantValue::Int(i) => Value::Int {
-                        size: ty.size(),
-                        val: i.clone(),
-                    },
-                    crate::ConstantValue::Bool(v) => Value::Bit(*v),
-                    crate::ConstantValue::HighImp => todo!(),
+    fn statement_value(&self, statement: &Statement) -> Result<Bits, EvalError> {
+        match statement {
+            Statement::Binding(binding) => {
+                let ops = binding
+                    .operands
+                    .iter()
+                    .map(|op| self.values.get(op).cloned().unwrap_or_default())
+                    .collect::<Vec<_>>();
+                self.binding_value(binding, &ops)
+            }
+            Statement::Constant(_, ty, value) => match value {
+                ConstantValue::Int(value) => Ok(bits_of(value, self.width(ty)?)),
+                ConstantValue::Bool(value) => Ok(vec![*value]),
+                ConstantValue::HighImp => self.unsupported("HIGHIMP values"),
+            },
+            Statement::Register(_) => self.unsupported("registers"),
+            _ => unreachable!("Only bindings, constants and registers define values"),
+        }
+    }
+
+    fn binding_value(&self, binding: &Binding, ops: &[Bits]) -> Result<Bits, EvalError> {
+        let w = self.width(&binding.ty)?;
+
+        // The width Verilog evaluates an expression in is the widest of the target and
+        // the operands
+        let context_width = |ops: &[&Bits]| ops.iter().map(|op| op.len()).fold(w, usize::max);
+        let operand_values = |signed: bool| {
+            let cw = context_width(&[&ops[0], &ops[1]]);
+            let value = |op: &Bits| match signed {
+                true => self::signed(&resize(op, cw, true)),
+                false => unsigned(&resize(op, cw, false)),
+            };
+            (value(&ops[0]), value(&ops[1]))
+        };
+        let arithmetic = |signed: bool, f: fn(BigInt, BigInt) -> BigInt| {
+            let (a, b) = operand_values(signed);
+            bits_of(&f(a, b), w)
+        };
+        // Division truncates towards zero like in Verilog
+        let division = |signed: bool, f: fn(BigInt, BigInt) -> BigInt| {
+            let (a, b) = operand_values(signed);
+            if b.is_zero() {
+                return Err(EvalError::DivisionByZero {
+                    unit: self.unit.clone(),
+                });
+            }
+            Ok(bits_of(&f(a, b), w))
+        };
+        // Comparisons are evaluated in the width of the operands and give a single bit
+        let compare = |signed: bool, f: fn(&BigInt, &BigInt) -> bool| {
+            let cw = ops[0].len().max(ops[1].len());
+            let value = |op: &Bits| match signed {
+                true => self::signed(&resize(op, cw, true)),
+                false => unsigned(&resize(op, cw, false)),
+            };
+            resize(&[f(&value(&ops[0]), &value(&ops[1]))], w, false)
+        };
+        let shift = |left: bool, signed: bool| {
+            let value = resize(&ops[0], context_width(&[&ops[0]]), signed);
+            let fill = signed && value.last() == Some(&true);
+            let amount = unsigned(&ops[1]).to_usize().unwrap_or(usize::MAX);
+            let result = (0..value.len())
+                .map(|i| {
+                    let from = if left {
+                        i.checked_sub(amount)
+                    } else {
+                        i.checked_add(amount)
+                    };
+                    from.and_then(|from| value.get(from).copied())
+                        .unwrap_or(if left { false } else { fill })
+                })
+                .collect::<Vec<_>>();
+            resize(&result, w, false)
+        };
+        let bitwise = |f: fn(bool, bool) -> bool| {
+            let cw = context_width(&[&ops[0], &ops[1]]);
+            let (a, b) = (resize(&ops[0], cw, false), resize(&ops[1], cw, false));
+            let result = a.iter().zip(&b).map(|(a, b)| f(*a, *b)).collect::<Vec<_>>();
+            resize(&result, w, false)
+        };
+        let bit = |value: bool| resize(&[value], w, false);
+        let any = |bits: &Bits| bits.iter().any(|b| *b);
+        let undefined = |width: usize| vec![self.evaluator.undefined; width];
+
+        let result = match &binding.operator {
+            Operator::Add => arithmetic(true, |a, b| a + b),
+            Operator::UnsignedAdd => arithmetic(false, |a, b| a + b),
+            Operator::Sub => arithmetic(true, |a, b| a - b),
+            Operator::UnsignedSub => arithmetic(false, |a, b| a - b),
+            Operator::Mul => arithmetic(true, |a, b| a * b),
+            Operator::UnsignedMul => arithmetic(false, |a, b| a * b),
+            Operator::Div => division(true, |a, b| a / b)?,
+            Operator::UnsignedDiv => division(false, |a, b| a / b)?,
+            Operator::Mod => division(true, |a, b| a % b)?,
+            Operator::UnsignedMod => division(false, |a, b| a % b)?,
+            Operator::Eq => compare(false, |a, b| a == b),
+            Operator::NotEq => compare(false, |a, b| a != b),
+            Operator::Gt => compare(true, |a, b| a > b),
+            Operator::UnsignedGt => compare(false, |a, b| a > b),
+            Operator::Lt => compare(true, |a, b| a < b),
+            Operator::UnsignedLt => compare(false, |a, b| a < b),
+            Operator::Ge => compare(true, |a, b| a >= b),
+            Operator::UnsignedGe => compare(false, |a, b| a >= b),
+            Operator::Le => compare(true, |a, b| a <= b),
+            Operator::UnsignedLe => compare(false, |a, b| a <= b),
+            Operator::LeftShift => shift(true, false),
+            Operator::RightShift => shift(false, false),
+            Operator::ArithmeticRightShift => shift(false, true),
+            Operator::LogicalAnd => bit(any(&ops[0]) && any(&ops[1])),
+            Operator::LogicalOr => bit(any(&ops[0]) || any(&ops[1])),
+            Operator::LogicalXor | Operator::BitwiseXor => bitwise(|a, b| a ^ b),
+            Operator::BitwiseAnd => bitwise(|a, b| a && b),
+            Operator::BitwiseOr => bitwise(|a, b| a || b),
+            Operator::LogicalNot | Operator::Not => bit(!any(&ops[0])),
+            Operator::BitwiseNot => {
+                let value = resize(&ops[0], context_width(&[&ops[0]]), false);
+                resize(&value.iter().map(|b| !b).collect::<Vec<_>>(), w, false)
+            }
+            Operator::USub => bits_of(&-unsigned(&ops[0]), w),
+            Operator::ReduceAnd => bit(ops[0].iter().all(|b| *b)),
+            Operator::ReduceOr => bit(any(&ops[0])),
+            Operator::ReduceXor => bit(ops[0].iter().filter(|b| **b).count() % 2 == 1),
+            Operator::DivPow2 => {
+                // if (divisor == 0) name = dividend;
+                // else name = $signed($signed(dividend) + $signed(1 << (divisor - 1)))
+                //      >>> $signed(divisor);
+                // where the unsized 1 is 32 bits wide
+                let (dividend, divisor) = (&ops[0], unsigned(&ops[1]));
+                if divisor.is_zero() {
+                    resize(dividend, w, false)
+                } else {
+                    let cw = context_width(&[dividend]).max(32);
+                    let rounding = match (divisor.clone() - 1u32).to_usize() {
+                        Some(amount) if amount < cw => BigInt::one() << amount,
+                        _ => BigInt::zero(),
+                    };
+                    let sum = bits_of(&(signed(&resize(dividend, cw, true)) + rounding), cw);
+                    let amount = divisor.to_usize().unwrap_or(usize::MAX).min(cw);
+                    bits_of(&(signed(&sum) >> amount), w)
+                }
+            }
+            Operator::Gray2Bin { num_bits } => {
+                let n = index(num_bits);
+                let mut result = vec![false; n];
+                for i in (0..n).rev() {
+                    result[i] = if i == n - 1 {
+                        ops[0][i]
+                    } else {
+                        ops[0][i] ^ result[i + 1]
+                    };
+                }
+                resize(&result, w, false)
+            }
+            Operator::SignExtend {
+                extra_bits,
+                operand_size,
+            } => {
+                let extra = index(extra_bits);
+                let extended = if extra == 0 {
+                    ops[0].clone()
+                } else {
+                    let msb = ops[0][index(operand_size) - 1];
+                    concat([vec![msb; extra].as_slice(), &ops[0]])
                 };
-                let name = ValueName::Expr(*id);
-                name_types.insert(name.clone(), ty.clone());
-                (name, val)
+                resize(&extended, w, false)
+            }
+            Operator::ZeroExtend { extra_bits } => {
+                resize(&ops[0], ops[0].len() + index(extra_bits), false)
+            }
+            Operator::Truncate | Operator::Alias => resize(&ops[0], w, false),
+            Operator::Concat => resize(&concat(ops.iter().map(|op| op.as_slice())), w, false),
+            Operator::Select { .. } => {
+                let chosen = if any(&ops[0]) { &ops[1] } else { &ops[2] };
+                resize(chosen, w, false)
+            }
+            Operator::Match { .. } => {
+                // None of the branches matching gives an undefined value
+                ops.chunks(2)
+                    .find(|branch| any(&branch[0]))
+                    .map(|branch| resize(&branch[1], w, false))
+                    .unwrap_or_else(|| undefined(w))
+            }
+            // The first element is the least significant
+            Operator::ConstructArray => {
+                resize(&ops.iter().flatten().copied().collect::<Vec<_>>(), w, false)
+            }
+            Operator::IndexArray => {
+                // An index outside the array gives an undefined value
+                let idx = unsigned(&ops[1]).to_usize();
+                match idx {
+                    Some(idx) if w != 0 => ops[0]
+                        .chunks_exact(w)
+                        .nth(idx)
+                        .map(|element| element.to_vec())
+                        .unwrap_or_else(|| undefined(w)),
+                    _ => undefined(w),
+                }
+            }
+            Operator::RangeIndexArray {
+                start,
+                end_exclusive,
+            } => {
+                let member_size = match &binding.ty {
+                    Type::Array { inner, .. } => self.width(inner)?,
+                    _ => panic!("Range index with non-array output"),
+                };
+                ops[0][index(start) * member_size..index(end_exclusive) * member_size].to_vec()
+            }
+            Operator::RangeIndexBits {
+                start,
+                end_exclusive,
+            } => ops[0][index(start)..index(end_exclusive)].to_vec(),
+            // The first member is the most significant
+            Operator::ConstructTuple => concat(ops.iter().map(|op| op.as_slice())),
+            Operator::ConstructEnum {
+                variant,
+                variant_count,
+            } => {
+                let tag = bits_of(&(*variant).into(), enum_util::tag_size(*variant_count));
+                let payload = concat(ops.iter().map(|op| op.as_slice()));
+                let padding = undefined(w - tag.len() - payload.len());
+                concat([tag.as_slice(), &payload, &padding])
+            }
+            Operator::IsEnumVariant { variant, enum_type } => {
+                let tag_size = enum_util::tag_size(enum_type.assume_enum().len());
+                if tag_size == 0 {
+                    bit(true)
+                } else {
+                    let total = ops[0].len();
+                    bit(unsigned(&ops[0][total - tag_size..]) == BigInt::from(*variant))
+                }
+            }
+            Operator::EnumMember {
+                enum_type,
+                variant,
+                member_index,
+            } => {
+                let variants = enum_type.assume_enum();
+                let member_start = enum_util::tag_size(variants.len())
+                    + variants[*variant][0..*member_index]
+                        .iter()
+                        .map(|ty| self.width(ty))
+                        .sum::<Result<usize, _>>()?;
+                let member_end = member_start + self.width(&variants[*variant][*member_index])?;
+                let total = ops[0].len();
+                ops[0][total - member_end..total - member_start].to_vec()
+            }
+            Operator::IndexTuple(idx, types) => {
+                let sizes = types
+                    .iter()
+                    .map(|ty| self.width(ty))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let start = sizes[0..*idx as usize].iter().sum::<usize>();
+                let end = start + sizes[*idx as usize];
+                let total = ops[0].len();
+                ops[0][total - end..total - start].to_vec()
+            }
+            Operator::Bitreverse => {
+                resize(&ops[0].iter().rev().copied().collect::<Vec<_>>(), w, false)
+            }
+            Operator::Nop if w == 0 => vec![],
+            Operator::Instance { name, .. } => {
+                let output = self.evaluator.unit(name, ops.to_vec(), self.depth + 1)?;
+                resize(&output, w, false)
+            }
+            Operator::DeclClockedMemory { .. }
+            | Operator::DeclBlockRam { .. }
+            | Operator::IndexMemory => return self.unsupported("memories"),
+            Operator::ReadPort | Operator::FlipPort | Operator::ReadMutWires | Operator::Nop => {
+                return self.unsupported("ports")
             }
-            Statement::Assert(_) => panic!("trying to evaluate an assert statement"),
-            Statement::Set { .. } => panic!("trying to evaluate a `set` statement"),
-            Statement::WalTrace { .. } => panic!("trying to evaluate a `wal_trace`"),
         };
-
-        name_vals.insert(n, v.clone());
-        last_value = Some(v);
+        Ok(result)
     }
-    last_value.expect("Trying to evaluate empty statement list")
 }
 
 #[cfg(test)]
@@ -446,7 +866,7 @@ mod test {
 
         let result = eval_statements(&mir);
 
-        assert_eq!(result, Value::int(16, 15));
+        assert_eq!(result, Value::uint(16, 15));
     }
 
     #[test]
@@ -460,10 +880,7 @@ mod test {
 
         let result = eval_statements(&mir);
 
-        assert_eq!(
-            result,
-            Value::Concat(vec![Value::uint(2, 1), Value::int(16, 5),])
-        )
+        assert_eq!(result.as_string(), "010000000000000101")
     }
 
     #[test]
@@ -477,10 +894,7 @@ mod test {
 
         let result = eval_statements(&mir);
 
-        assert_eq!(
-            result,
-            Value::Concat(vec![Value::uint(2, 1), Value::int(3, 5), Value::undef(13)])
-        )
+        assert_eq!(result.as_string(), "01101XXXXXXXXXXXXX")
     }
 
     #[test]
@@ -495,6 +909,18 @@ mod test {
         assert_eq!("000001010", eval_statements(&mir).as_string())
     }
 
+    #[test]
+    fn only_bits_depending_on_undefined_values_are_undefined() {
+        let mir = vec![
+            statement!(const 0; Type::Bool; ConstantValue::Bool(false)),
+            statement!(const 1; Type::uint(4); ConstantValue::int(3)),
+            statement!(e(2); Type::uint(4); Match({arm_locs: vec![]}); e(0), e(1)),
+            statement!(e(3); Type::uint(4); BitwiseAnd; e(1), e(2)),
+        ];
+
+        assert_eq!("00XX", eval_statements(&mir).as_string())
+    }
+
     #[test]
     fn as_u64_works_for_bits() {
         assert_eq!(Value::Bit(false).as_u64(), 0);
@@ -626,3 +1052,128 @@ mod test {
         0b1111_1111_1000_0000_0000_0011_1u64
     }
 }
+
+#[cfg(test)]
+mod unit_evaluator_tests {
+    use crate as spade_mir;
+    use crate::{entity, statement, types::Type, value_name, ConstantValue};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn instance(name: &UnitName) -> Operator {
+        Operator::Instance {
+            name: name.clone(),
+            params: vec![],
+            verilog_params: vec![],
+            loc: None,
+        }
+    }
+
+    #[test]
+    fn signed_operands_are_sign_extended() {
+        let mir = vec![
+            statement!(const 0; Type::int(8); ConstantValue::int(-7)),
+            statement!(const 1; Type::int(8); ConstantValue::int(2)),
+            statement!(e(2); Type::int(9); Add; e(0), e(1)),
+        ];
+
+        let result = UnitEvaluator::new([]).eval_statements(&mir, &value_name!(e(2)));
+
+        assert_eq!(result, Ok(Value::uint(9, 0b1_1111_1011)));
+    }
+
+    #[test]
+    fn signed_division_truncates_towards_zero() {
+        let mir = vec![
+            statement!(const 0; Type::int(8); ConstantValue::int(-7)),
+            statement!(const 1; Type::int(8); ConstantValue::int(2)),
+            statement!(e(2); Type::int(8); Div; e(0), e(1)),
+            statement!(e(3); Type::int(8); Mod; e(0), e(1)),
+            statement!(e(4); Type::Tuple(vec![Type::int(8), Type::int(8)]); ConstructTuple; e(2), e(3)),
+        ];
+
+        let result = UnitEvaluator::new([]).eval_statements(&mir, &value_name!(e(4)));
+
+        assert_eq!(result, Ok(Value::uint(16, 0xfd_ff)));
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        let mir = vec![
+            statement!(const 0; Type::uint(8); ConstantValue::int(7)),
+            statement!(const 1; Type::uint(8); ConstantValue::int(0)),
+            statement!(e(2); Type::uint(8); UnsignedDiv; e(0), e(1)),
+        ];
+
+        let result = UnitEvaluator::new([]).eval_statements(&mir, &value_name!(e(2)));
+
+        assert_eq!(
+            result,
+            Err(EvalError::DivisionByZero {
+                unit: "the expression".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn instances_are_evaluated() {
+        let double = entity!("double"; ("x", n(0, "x"), Type::uint(8)) -> Type::uint(9); {
+            (e(0); Type::uint(9); UnsignedAdd; n(0, "x"), n(0, "x"))
+        } => e(0));
+        let mut top = entity!("top"; ("y", n(0, "y"), Type::uint(8)) -> Type::uint(9); {
+            (e(1); Type::uint(9); Alias; e(0))
+        } => e(1));
+        top.statements.push(Statement::Binding(Binding {
+            name: value_name!(e(0)),
+            operator: instance(&double.name),
+            operands: vec![value_name!(n(0, "y"))],
+            ty: Type::uint(9),
+            loc: None,
+            verilog_attrs: vec![],
+        }));
+
+        let evaluator = UnitEvaluator::new([&double, &top]);
+
+        assert_eq!(
+            evaluator.eval_unit(&top.name, &[Value::uint(8, 200)]),
+            Ok(Value::uint(9, 400))
+        );
+    }
+
+    #[test]
+    fn registers_are_unsupported() {
+        let counter = entity!("counter"; ("clk", n(0, "clk"), Type::Bool) -> Type::uint(8); {
+            (const 0; Type::uint(8); ConstantValue::int(1));
+            (e(1); Type::uint(8); UnsignedAdd; n(1, "count"), e(0));
+            (reg n(1, "count"); Type::uint(8); clock (n(0, "clk")); e(1))
+        } => n(1, "count"));
+
+        let result = UnitEvaluator::new([&counter]).eval_unit(&counter.name, &[Value::Bit(true)]);
+
+        assert_eq!(
+            result,
+            Err(EvalError::Unsupported {
+                unit: "counter".to_string(),
+                what: "registers".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn missing_units_are_reported() {
+        let missing = UnitName::_test_from_strs(&["missing"]);
+        let mir = vec![Statement::Binding(Binding {
+            name: value_name!(e(0)),
+            operator: instance(&missing),
+            operands: vec![],
+            ty: Type::Bool,
+            loc: None,
+            verilog_attrs: vec![],
+        })];
+
+        let result = UnitEvaluator::new([]).eval_statements(&mir, &value_name!(e(0)));
+
+        assert_eq!(result, Err(EvalError::UnknownUnit("missing".to_string())));
+    }
+}
@@ -0,0 +1,213 @@
+//! This module generates SymbiYosys job files for the units which contain
+//! clocked properties, i.e. `assert(clk)`, `assume(clk)` and `cover(clk)`
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use nesty::{code, Code};
+
+use crate::{Entity, Operator, Statement};
+
+/// The number of cycles SymbiYosys unrolls the design for when proving or
+/// covering properties
+pub const DEFAULT_DEPTH: usize = 20;
+
+fn instantiated_units(entity: &Entity) -> impl Iterator<Item = String> + '_ {
+    entity.statements.iter().filter_map(|stmt| match stmt {
+        Statement::Binding(b) => match &b.operator {
+            Operator::Instance { name, .. } => Some(name.as_verilog()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn has_properties(entity: &Entity) -> bool {
+    entity
+        .statements
+        .iter()
+        .any(|stmt| matches!(stmt, Statement::Property(_)))
+}
+
+/// Returns the entities which should be the top of a formal verification job.
+/// These are the entities which (transitively) contain properties, but which are
+/// not instantiated by another entity containing properties.
+pub fn formal_tops<'a>(entities: &[&'a Entity]) -> Vec<&'a Entity> {
+    let by_name = entities
+        .iter()
+        .map(|e| (e.name.as_verilog(), *e))
+        .collect::<HashMap<_, _>>();
+
+    fn contains_properties(
+        entity: &Entity,
+        by_name: &HashMap<String, &Entity>,
+        visited: &mut HashSet<String>,
+    ) -> bool {
+        if !visited.insert(entity.name.as_verilog()) {
+            return false;
+        }
+        has_properties(entity)
+            || instantiated_units(entity).any(|name| {
+                by_name
+                    .get(&name)
+                    .map(|inner| contains_properties(inner, by_name, visited))
+                    .unwrap_or(false)
+            })
+    }
+
+    let with_properties = entities
+        .iter()
+        .filter(|e| contains_properties(e, &by_name, &mut HashSet::new()))
+        .collect::<Vec<_>>();
+
+    let instantiated = with_properties
+        .iter()
+        .flat_map(|e| instantiated_units(e))
+        .collect::<HashSet<_>>();
+
+    with_properties
+        .into_iter()
+        .filter(|e| !instantiated.contains(&e.name.as_verilog()))
+        .copied()
+        .collect()
+}
+
+/// A name for the job file of `top` which is safe to use as a file name
+pub fn sby_file_name(top: &Entity) -> String {
+    let name = top
+        .name
+        .without_escapes()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    format!("{name}.sby")
+}
+
+/// Generates a SymbiYosys job with a `prove` and a `cover` task for `top` which
+/// is defined in `verilog_file`
+pub fn sby_file(top: &Entity, verilog_file: &Path, depth: usize) -> String {
+    let file_name = verilog_file
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    // Yosys scripts refer to escaped modules with the leading \ of the Verilog name,
+    // but without the space which ends the identifier
+    let verilog_name = top.name.as_verilog();
+    let top_name = verilog_name.trim_end();
+
+    code! {
+        [0] "[tasks]";
+        [0] "prove";
+        [0] "cover";
+        [0] "";
+        [0] "[options]";
+        [0] "prove: mode prove";
+        [0] "cover: mode cover";
+        [0] format!("depth {depth}");
+        [0] "";
+        [0] "[engines]";
+        [0] "smtbmc";
+        [0] "";
+        [0] "[script]";
+        [0] format!("read -formal {file_name}");
+        [0] format!("prep -top {top_name}");
+        [0] "";
+        [0] "[files]";
+        [0] format!("{}", verilog_file.to_string_lossy());
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate as spade_mir;
+    use crate::{entity, types::Type, Implication, Property, PropertyKind, UnitName};
+    use crate::{value_name, Binding};
+
+    fn clocked(name: &str) -> Entity {
+        entity!(name; ("clk", n(0, "clk"), Type::Bool) -> Type::Bool; {} => n(0, "clk"))
+    }
+
+    fn with_property(mut entity: Entity) -> Entity {
+        entity.statements.push(Statement::Property(Property {
+            kind: PropertyKind::Assert,
+            clock: value_name!(n(0, "clk")),
+            reset: None,
+            antecedent: Some((value_name!(n(0, "clk")), Implication::Overlapping)),
+            consequent: value_name!(n(0, "clk")),
+            loc: None,
+        }));
+        entity
+    }
+
+    fn instantiating(mut entity: Entity, inner: &Entity) -> Entity {
+        entity.statements.push(Statement::Binding(Binding {
+            name: value_name!(e(10)),
+            operator: Operator::Instance {
+                name: inner.name.clone(),
+                params: vec![],
+                verilog_params: vec![],
+                loc: None,
+            },
+            operands: vec![],
+            ty: Type::Bool,
+            loc: None,
+            verilog_attrs: vec![],
+        }));
+        entity
+    }
+
+    #[test]
+    fn only_outermost_entities_with_properties_are_tops() {
+        let checked = with_property(clocked("checked"));
+        let wrapper = instantiating(clocked("wrapper"), &checked);
+        let unrelated = clocked("unrelated");
+
+        let tops = formal_tops(&[&checked, &wrapper, &unrelated])
+            .into_iter()
+            .map(|e| e.name.as_verilog())
+            .collect::<Vec<_>>();
+
+        assert_eq!(tops, vec!["wrapper".to_string()]);
+    }
+
+    #[test]
+    fn sby_file_is_correct() {
+        let checked = with_property(clocked("checked"));
+
+        let expected = indoc::indoc! {"
+            [tasks]
+            prove
+            cover
+
+            [options]
+            prove: mode prove
+            cover: mode cover
+            depth 5
+
+            [engines]
+            smtbmc
+
+            [script]
+            read -formal out.sv
+            prep -top checked
+
+            [files]
+            /build/out.sv"
+        };
+
+        assert_eq!(sby_file(&checked, Path::new("/build/out.sv"), 5), expected);
+    }
+
+    #[test]
+    fn sby_file_uses_the_escaped_name_of_namespaced_tops() {
+        let mut checked = with_property(clocked("checked"));
+        checked.name = UnitName::_test_from_strs(&["lib", "checked"]);
+
+        let sby = sby_file(&checked, Path::new("/build/out.sv"), 5);
+        assert!(sby.contains("\nprep -top \\lib::checked\n"), "{sby}");
+        assert_eq!(sby_file_name(&checked), "lib__checked.sby");
+    }
+}
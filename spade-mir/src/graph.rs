@@ -0,0 +1,599 @@
+//! Export of the structure of the generated hardware as graphs, for use by external
+//! visualisation tools. Two kinds of graphs are supported: the instance hierarchy of a top
+//! unit, and the dataflow graph of a single unit. Both can be written in Graphviz DOT or
+//! JSON.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use itertools::Itertools;
+use serde::Serialize;
+use spade_common::name::NameID;
+
+use crate::unit_name::InstanceMap;
+use crate::{Entity, Operator, Register, Statement, ValueName};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+
+impl GraphFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GraphFormat::Dot => "dot",
+            GraphFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Input,
+    Output,
+    Constant,
+    Operator,
+    Register,
+    Instance,
+    Memory,
+}
+
+impl NodeKind {
+    fn dot_shape(&self) -> &'static str {
+        match self {
+            NodeKind::Input => "invhouse",
+            NodeKind::Output => "house",
+            NodeKind::Constant => "plain",
+            NodeKind::Operator => "ellipse",
+            NodeKind::Register => "box",
+            NodeKind::Instance => "box3d",
+            NodeKind::Memory => "cylinder",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Node {
+    pub id: String,
+    pub kind: NodeKind,
+    /// What the node computes, i.e. the operator, constant value or instantiated unit
+    pub label: String,
+    /// The name of the value in the Spade source code, if it is known
+    pub source_name: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub label: Option<String>,
+}
+
+/// The dataflow graph of a unit. Edges go from the values which are used to the
+/// values computed from them
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Graph {
+    pub name: String,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+/// Escapes a string for use inside a quoted DOT identifier
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    // None of the graph types contain anything which can fail to serialize
+    serde_json::to_string_pretty(value).expect("Failed to serialize graph")
+}
+
+impl Graph {
+    pub fn to_dot(&self) -> String {
+        let nodes = self.nodes.iter().map(|node| {
+            let label = match &node.source_name {
+                Some(name) => format!("{name}\n{}", node.label),
+                None => node.label.clone(),
+            };
+            format!(
+                "    \"{}\" [label=\"{}\", shape={}];",
+                dot_escape(&node.id),
+                dot_escape(&label),
+                node.kind.dot_shape()
+            )
+        });
+        let edges = self.edges.iter().map(|edge| {
+            let label = match &edge.label {
+                Some(label) => format!(" [label=\"{}\"]", dot_escape(label)),
+                None => String::new(),
+            };
+            format!(
+                "    \"{}\" -> \"{}\"{label};",
+                dot_escape(&edge.from),
+                dot_escape(&edge.to)
+            )
+        });
+        format!(
+            "digraph \"{}\" {{\n{}\n}}\n",
+            dot_escape(&self.name),
+            nodes.chain(edges).join("\n")
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        to_json(self)
+    }
+
+    pub fn write(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+}
+
+struct DataflowBuilder<'a> {
+    source_name: &'a dyn Fn(&ValueName) -> Option<String>,
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+impl<'a> DataflowBuilder<'a> {
+    fn node(&mut self, name: &ValueName, kind: NodeKind, label: String) {
+        self.nodes.push(Node {
+            id: name.unescaped_var_name(),
+            kind,
+            label,
+            source_name: (self.source_name)(name),
+        })
+    }
+
+    fn edge(&mut self, from: &ValueName, to: &ValueName, label: Option<&str>) {
+        self.edges.push(Edge {
+            from: from.unescaped_var_name(),
+            to: to.unescaped_var_name(),
+            label: label.map(|l| l.to_string()),
+        })
+    }
+
+    fn register(&mut self, reg: &Register) {
+        let Register {
+            name,
+            clock,
+            reset,
+            value,
+            ..
+        } = reg;
+        self.node(name, NodeKind::Register, "reg".to_string());
+        self.edge(clock, name, Some("clock"));
+        if let Some((trigger, reset_value)) = reset {
+            self.edge(trigger, name, Some("reset"));
+            self.edge(reset_value, name, Some("reset value"));
+        }
+        self.edge(value, name, Some("value"));
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Binding(binding) => {
+                let (kind, label) = match &binding.operator {
+                    Operator::Instance { name, .. } => {
+                        (NodeKind::Instance, name.without_escapes().to_string())
+                    }
+                    Operator::DeclClockedMemory { .. } => (NodeKind::Memory, "memory".to_string()),
+                    Operator::DeclBlockRam { .. } => (NodeKind::Memory, "block ram".to_string()),
+                    other => (NodeKind::Operator, format!("{other}")),
+                };
+                self.node(&binding.name, kind, label);
+                for (i, operand) in binding.operands.iter().enumerate() {
+                    let label = match &binding.operator {
+                        Operator::Instance { params, .. } => params.get(i).map(|p| &p.name),
+                        _ => None,
+                    };
+                    self.edge(operand, &binding.name, label.map(|l| l.as_str()));
+                }
+            }
+            Statement::Register(reg) => self.register(reg),
+            Statement::Constant(id, _, value) => self.node(
+                &ValueName::Expr(*id),
+                NodeKind::Constant,
+                format!("{value}"),
+            ),
+            Statement::Set { target, value } => self.edge(value, target, Some("set")),
+            Statement::Assert(_) | Statement::Property(_) | Statement::WalTrace { .. } => {}
+        }
+    }
+}
+
+/// Builds the dataflow graph of a flattened entity. `source_name` is used to look up the
+/// Spade name of the values in the entity
+pub fn dataflow_graph(
+    entity: &Entity,
+    source_name: &dyn Fn(&ValueName) -> Option<String>,
+) -> Graph {
+    let mut builder = DataflowBuilder {
+        source_name,
+        nodes: vec![],
+        edges: vec![],
+    };
+
+    for input in &entity.inputs {
+        builder.node(&input.val_name, NodeKind::Input, input.name.clone());
+    }
+    for statement in &entity.statements {
+        builder.statement(statement)
+    }
+
+    let output_id = "output".to_string();
+    builder.nodes.push(Node {
+        id: output_id.clone(),
+        kind: NodeKind::Output,
+        label: "output".to_string(),
+        source_name: None,
+    });
+    builder.edges.push(Edge {
+        from: entity.output.unescaped_var_name(),
+        to: output_id,
+        label: None,
+    });
+
+    Graph {
+        name: entity.name.without_escapes().to_string(),
+        nodes: builder.nodes,
+        edges: builder.edges,
+    }
+}
+
+/// A unit in the instance hierarchy along with all the units it instantiates
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HierarchyNode {
+    /// The name of the instance in the generated verilog. None for the top unit
+    pub instance: Option<String>,
+    /// The monomorphised name of the unit
+    pub unit: String,
+    pub children: Vec<HierarchyNode>,
+}
+
+impl HierarchyNode {
+    fn dot_lines(&self, id: &str, result: &mut Vec<String>) {
+        let label = match &self.instance {
+            Some(instance) => format!("{instance}\n{}", self.unit),
+            None => self.unit.clone(),
+        };
+        result.push(format!(
+            "    \"{}\" [label=\"{}\", shape=box];",
+            dot_escape(id),
+            dot_escape(&label)
+        ));
+        for child in &self.children {
+            let child_id = format!("{id}.{}", child.instance.as_deref().unwrap_or_default());
+            result.push(format!(
+                "    \"{}\" -> \"{}\";",
+                dot_escape(id),
+                dot_escape(&child_id)
+            ));
+            child.dot_lines(&child_id, result);
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec![];
+        self.dot_lines(&self.unit, &mut lines);
+        format!(
+            "digraph \"{}\" {{\n{}\n}}\n",
+            dot_escape(&self.unit),
+            lines.join("\n")
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        to_json(self)
+    }
+}
+
+/// Writes the hierarchies below several top units to a single file. In JSON, this is an
+/// array of hierarchies, in DOT it is one graph per hierarchy
+pub fn write_hierarchies(roots: &[HierarchyNode], format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Dot => roots.iter().map(HierarchyNode::to_dot).join("\n"),
+        GraphFormat::Json => to_json(&roots),
+    }
+}
+
+struct HierarchyBuilder<'a> {
+    instance_map: &'a InstanceMap,
+    unit_names: HashMap<&'a NameID, &'a str>,
+    /// The units which are currently being expanded, to guard against recursive
+    /// instantiation
+    stack: Vec<&'a NameID>,
+}
+
+impl<'a> HierarchyBuilder<'a> {
+    fn unit_name(&self, unit: &NameID) -> String {
+        self.unit_names
+            .get(unit)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("{}", unit.1))
+    }
+
+    fn build(&mut self, instance: Option<&str>, unit: &'a NameID) -> HierarchyNode {
+        let mut children = vec![];
+        if !self.stack.contains(&unit) {
+            self.stack.push(unit);
+            let instances = self
+                .instance_map
+                .inner
+                .get(unit)
+                .map(|instances| instances.iter().collect::<Vec<_>>())
+                .unwrap_or_default();
+            for (instance, child) in instances {
+                children.push(self.build(Some(instance), child))
+            }
+            self.stack.pop();
+        }
+        HierarchyNode {
+            instance: instance.map(|i| i.to_string()),
+            unit: self.unit_name(unit),
+            children,
+        }
+    }
+}
+
+/// Builds the instance hierarchy of the specified entities. If `top` is given, only the
+/// hierarchy below the entity with that name is built, otherwise one hierarchy is built for
+/// each entity which is not instantiated anywhere. Returns None if there is no entity named
+/// `top`
+pub fn instance_hierarchy(
+    entities: &[&Entity],
+    instance_map: &InstanceMap,
+    top: Option<&str>,
+) -> Option<Vec<HierarchyNode>> {
+    let mut builder = HierarchyBuilder {
+        instance_map,
+        unit_names: entities
+            .iter()
+            .map(|e| (&e.name.source, e.name.without_escapes()))
+            .collect(),
+        stack: vec![],
+    };
+
+    let roots = match top {
+        Some(top) => vec![entities.iter().find(|e| e.name.without_escapes() == top)?],
+        None => {
+            let instantiated = instance_map
+                .inner
+                .values()
+                .flat_map(BTreeMap::values)
+                .collect::<HashSet<_>>();
+            entities
+                .iter()
+                .filter(|e| !instantiated.contains(&e.name.source))
+                .collect()
+        }
+    };
+
+    Some(
+        roots
+            .into_iter()
+            .map(|root| builder.build(None, &root.name.source))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use spade_common::name::Path;
+
+    use crate::parser::parse_entities;
+    use crate::unit_name::{IntoUnitName, UnitName};
+
+    fn entity(code: &str) -> Entity {
+        parse_entities(code).unwrap().remove(0)
+    }
+
+    #[test]
+    fn dataflow_graph_has_a_node_per_value() {
+        let e = entity(indoc! {"
+            entity counter((clk, clk, bool), (rst, rst, bool)) -> int<8> {
+                const e0: int<8> = 1
+                let e1: int<8> = Add(x, e0)
+                reg(clk) x: int<8>(rst, e0) = e1
+            } => x
+        "});
+
+        let graph = dataflow_graph(&e, &|name| match name {
+            ValueName::Named(_, name, _) => Some(format!("source {name}")),
+            ValueName::Expr(_) => None,
+        });
+
+        let kinds = graph
+            .nodes
+            .iter()
+            .map(|n| (n.id.as_str(), n.kind, n.label.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                ("clk", NodeKind::Input, "clk"),
+                ("rst", NodeKind::Input, "rst"),
+                ("_e_0", NodeKind::Constant, "1"),
+                ("_e_1", NodeKind::Operator, "Add"),
+                ("x", NodeKind::Register, "reg"),
+                ("output", NodeKind::Output, "output"),
+            ]
+        );
+        assert_eq!(graph.nodes[4].source_name, Some("source x".to_string()));
+        assert_eq!(graph.nodes[2].source_name, None);
+
+        let edges = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.label.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![
+                ("x", "_e_1", None),
+                ("_e_0", "_e_1", None),
+                ("clk", "x", Some("clock")),
+                ("rst", "x", Some("reset")),
+                ("_e_0", "x", Some("reset value")),
+                ("_e_1", "x", Some("value")),
+                ("x", "output", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn instances_are_labelled_by_unit_and_params() {
+        let e = entity(indoc! {"
+            entity top((a, a, int<8>)) -> int<8> {
+                let inner: int<8> = Instance(sub; x)(a)
+            } => inner
+        "});
+
+        let graph = dataflow_graph(&e, &|_| None);
+        assert_eq!(graph.nodes[1].kind, NodeKind::Instance);
+        assert_eq!(graph.nodes[1].label, "sub");
+        assert_eq!(graph.edges[0].label, Some("x".to_string()));
+    }
+
+    #[test]
+    fn dot_output_is_escaped() {
+        let graph = Graph {
+            name: "a::b".to_string(),
+            nodes: vec![Node {
+                id: "x".to_string(),
+                kind: NodeKind::Input,
+                label: "say \"hi\"".to_string(),
+                source_name: Some("x".to_string()),
+            }],
+            edges: vec![Edge {
+                from: "x".to_string(),
+                to: "output".to_string(),
+                label: Some("l".to_string()),
+            }],
+        };
+
+        let expected = indoc! {r#"
+            digraph "a::b" {
+                "x" [label="x\nsay \"hi\"", shape=invhouse];
+                "x" -> "output" [label="l"];
+            }
+        "#};
+        assert_eq!(graph.to_dot(), expected);
+    }
+
+    fn unit(name: &str, id: u64) -> UnitName {
+        UnitName {
+            source: NameID(id, Path::from_strs(&[name])),
+            ..name._test_into_unit_name()
+        }
+    }
+
+    fn empty_entity(name: UnitName) -> Entity {
+        Entity {
+            name,
+            inputs: vec![],
+            output: ValueName::Expr(0),
+            output_type: crate::types::Type::Bool,
+            statements: vec![],
+            verilog_attrs: vec![],
+        }
+    }
+
+    #[test]
+    fn hierarchy_starts_at_uninstantiated_units() {
+        let top = empty_entity(unit("top", 0));
+        let mid = empty_entity(unit("mid", 1));
+        let leaf = empty_entity(unit("leaf", 2));
+
+        let mut instance_map = InstanceMap::new();
+        instance_map.inner.insert(
+            top.name.source.clone(),
+            [
+                ("leaf_0".to_string(), leaf.name.source.clone()),
+                ("mid_0".to_string(), mid.name.source.clone()),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        instance_map.inner.insert(
+            mid.name.source.clone(),
+            [("leaf_0".to_string(), leaf.name.source.clone())]
+                .into_iter()
+                .collect(),
+        );
+
+        let leaf_node = |instance: &str| HierarchyNode {
+            instance: Some(instance.to_string()),
+            unit: "leaf".to_string(),
+            children: vec![],
+        };
+        let expected = HierarchyNode {
+            instance: None,
+            unit: "top".to_string(),
+            children: vec![
+                leaf_node("leaf_0"),
+                HierarchyNode {
+                    instance: Some("mid_0".to_string()),
+                    unit: "mid".to_string(),
+                    children: vec![leaf_node("leaf_0")],
+                },
+            ],
+        };
+
+        let entities = [&top, &mid, &leaf];
+        assert_eq!(
+            instance_hierarchy(&entities, &instance_map, None),
+            Some(vec![expected.clone()])
+        );
+        assert_eq!(
+            instance_hierarchy(&entities, &instance_map, Some("mid")),
+            Some(vec![HierarchyNode {
+                instance: None,
+                ..expected.children[1].clone()
+            }])
+        );
+        assert_eq!(
+            instance_hierarchy(&entities, &instance_map, Some("missing")),
+            None
+        );
+
+        let expected_dot = indoc! {r#"
+            digraph "top" {
+                "top" [label="top", shape=box];
+                "top" -> "top.leaf_0";
+                "top.leaf_0" [label="leaf_0\nleaf", shape=box];
+                "top" -> "top.mid_0";
+                "top.mid_0" [label="mid_0\nmid", shape=box];
+                "top.mid_0" -> "top.mid_0.leaf_0";
+                "top.mid_0.leaf_0" [label="leaf_0\nleaf", shape=box];
+            }
+        "#};
+        assert_eq!(expected.to_dot(), expected_dot);
+    }
+
+    #[test]
+    fn recursive_instantiation_terminates() {
+        let top = empty_entity(unit("top", 0));
+        let mut instance_map = InstanceMap::new();
+        instance_map.inner.insert(
+            top.name.source.clone(),
+            [("top_0".to_string(), top.name.source.clone())]
+                .into_iter()
+                .collect(),
+        );
+
+        let result = instance_hierarchy(&[&top], &instance_map, Some("top")).unwrap();
+        assert_eq!(result[0].children.len(), 1);
+        assert_eq!(result[0].children[0].children, vec![]);
+    }
+}
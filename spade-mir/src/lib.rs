@@ -1,11 +1,17 @@
 mod aliasing;
 mod assertion_codegen;
+pub mod cdc;
 pub mod codegen;
+pub mod coverage;
 pub mod diff;
 pub mod diff_printing;
-mod enum_util;
+pub mod enum_util;
 pub mod eval;
+pub mod formal;
+pub mod graph;
+pub mod liveness;
 pub mod macros;
+pub mod parser;
 pub mod passes;
 pub mod renaming;
 mod type_list;
@@ -17,7 +23,7 @@ mod wal;
 
 use derive_where::derive_where;
 use itertools::Itertools;
-use num::{BigInt, BigUint};
+use num::{BigInt, BigUint, One};
 use renaming::VerilogNameSource;
 use serde::{Deserialize, Serialize};
 use types::Type;
@@ -160,6 +166,65 @@ pub struct ParamName {
     pub no_mangle: Option<Loc<()>>,
 }
 
+impl std::fmt::Display for ParamName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ParamName { name, no_mangle } = self;
+        write!(
+            f,
+            "{}{name}",
+            no_mangle.map(|_| "#[no_mangle]").unwrap_or("")
+        )
+    }
+}
+
+/// A parameter override passed to a Verilog module when it is instantiated
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerilogParameter {
+    pub name: String,
+    pub value: VerilogParameterValue,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VerilogParameterValue {
+    Int(BigInt),
+    String(String),
+}
+
+/// A synthesis attribute emitted as `(* name = value *)` on a Verilog declaration
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerilogAttribute {
+    pub name: String,
+    pub value: Option<VerilogParameterValue>,
+}
+
+impl std::fmt::Display for VerilogAttribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            None => write!(f, "{}", self.name),
+            Some(VerilogParameterValue::Int(v)) => write!(f, "{} = {v}", self.name),
+            Some(VerilogParameterValue::String(s)) => write!(f, "{} = \"{s}\"", self.name),
+        }
+    }
+}
+
+/// Formats a list of attributes as `(* a, b = 1 *) `, or an empty string if there are none
+fn fmt_verilog_attrs(attrs: &[VerilogAttribute]) -> String {
+    if attrs.is_empty() {
+        String::new()
+    } else {
+        format!("(* {} *) ", attrs.iter().join(", "))
+    }
+}
+
+impl std::fmt::Display for VerilogParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            VerilogParameterValue::Int(v) => write!(f, ".{}({v})", self.name),
+            VerilogParameterValue::String(s) => write!(f, ".{}(\"{s}\")", self.name),
+        }
+    }
+}
+
 #[derive_where(PartialEq)]
 #[derive(Clone, Debug)]
 pub enum Operator {
@@ -222,13 +287,21 @@ pub enum Operator {
     /// Concatenate the bits of all input operands
     Concat,
     /// Select [1] if [0] else [2]
-    Select,
+    Select {
+        /// True if this was lowered from an `if` in the source code rather than inserted
+        /// by the compiler. Only these are coverage points
+        from_if: bool,
+    },
     /// Corresponds to a match statement. If value [0] is true, select [1], if [2] holds, select
     /// [3] and so on. Values are priorotized in order, i.e. if both [0] and [2] hold, [1] is
     /// selected
     // NOTE: We may want to add a MatchUnique for cases where we can guarantee uniqueness,
     // typically match statements with no wildcards
-    Match,
+    Match {
+        /// The location of the pattern of each arm
+        #[derive_where(skip)]
+        arm_locs: Vec<Loc<()>>,
+    },
     /// Construct an array from the operand expressions
     ConstructArray,
     /// Create a mutable array which is modified on the rising edge of the first argument.
@@ -244,6 +317,27 @@ pub enum Operator {
         elems: BigUint,
         /// Initial values for the memory. Must be const evaluatable
         initial: Option<Vec<Vec<Statement>>>,
+        /// Width of the write enable of each write port. 1 for a single write enable,
+        /// otherwise one enable bit for each byte of the element
+        mask_w: BigUint,
+    },
+    /// A block RAM with one or more ports, each of which writes and reads the same
+    /// address on the rising edge of its own clock. The operands are pairs of a clock
+    /// and a (write mask, address, data) tuple, one per port. The result is the registered
+    /// read value of the port if there is one port, otherwise a tuple with the read value
+    /// of each port.
+    DeclBlockRam {
+        /// Width of the addresses
+        addr_w: BigUint,
+        /// Width of each element
+        inner_w: BigUint,
+        /// Number of elements in the RAM
+        elems: BigUint,
+        /// Width of the write mask, see `DeclClockedMemory`
+        mask_w: BigUint,
+        read_during_write: ReadDuringWrite,
+        /// Initial values for the memory. Must be const evaluatable
+        initial: Option<Vec<Vec<Statement>>>,
     },
     /// Index an array with elements of the specified size
     IndexArray,
@@ -306,6 +400,8 @@ pub enum Operator {
         name: UnitName,
         /// The names of the parameters in the same order as the operands.
         params: Vec<ParamName>,
+        /// Parameter overrides for the instantiated Verilog module
+        verilog_params: Vec<VerilogParameter>,
         #[derive_where(skip)]
         loc: Option<Loc<()>>,
     },
@@ -326,6 +422,7 @@ impl Operator {
                     no_mangle: None,
                 })
                 .collect(),
+            verilog_params: vec![],
             loc: None,
         }
     }
@@ -370,8 +467,8 @@ impl std::fmt::Display for Operator {
             Operator::Bitreverse => write!(f, "Bitreverse"),
             Operator::USub => write!(f, "USub"),
             Operator::Not => write!(f, "Not"),
-            Operator::Select => write!(f, "Select"),
-            Operator::Match => write!(f, "Match"),
+            Operator::Select { .. } => write!(f, "Select"),
+            Operator::Match { .. } => write!(f, "Match"),
             Operator::LeftShift => write!(f, "LeftShift"),
             Operator::DivPow2 => write!(f, "DivPow2"),
             Operator::Gray2Bin { num_bits } => write!(f, "Gray2Bin({num_bits})"),
@@ -386,15 +483,14 @@ impl std::fmt::Display for Operator {
                 variant,
                 variant_count,
             } => write!(f, "ConstructEnum({}, {})", variant, variant_count),
-            Operator::IsEnumVariant {
-                variant,
-                enum_type: _,
-            } => write!(f, "IsEnumVariant({})", variant),
+            Operator::IsEnumVariant { variant, enum_type } => {
+                write!(f, "IsEnumVariant({}, {})", variant, enum_type)
+            }
             Operator::EnumMember {
                 variant,
                 member_index,
-                enum_type: _,
-            } => write!(f, "EnumMember({} {})", variant, member_index),
+                enum_type,
+            } => write!(f, "EnumMember({} {}, {})", variant, member_index, enum_type),
             Operator::ConstructTuple => write!(f, "ConstructTuple"),
             Operator::ConstructArray => write!(f, "ConstructArray"),
             Operator::DeclClockedMemory {
@@ -403,23 +499,33 @@ impl std::fmt::Display for Operator {
                 inner_w,
                 elems,
                 initial,
+                mask_w,
             } => write!(
                 f,
-                "DeclClockedMemory({write_ports}, {addr_w}, {inner_w}, {elems}{})",
-                if let Some(values) = initial {
-                    format!(
-                        ", [{}]",
-                        values
-                            .iter()
-                            .map(|v| format!("[{}]", v.iter().map(|v| format!("{v}")).join(", ")))
-                            .join(", ")
-                    )
+                "DeclClockedMemory({write_ports}, {addr_w}, {inner_w}, {elems}{}{})",
+                if *mask_w != BigUint::one() {
+                    format!(", mask {mask_w}")
                 } else {
                     String::new()
-                }
+                },
+                fmt_memory_initial(initial)
+            ),
+            Operator::DeclBlockRam {
+                addr_w,
+                inner_w,
+                elems,
+                mask_w,
+                read_during_write,
+                initial,
+            } => write!(
+                f,
+                "DeclBlockRam({addr_w}, {inner_w}, {elems}, mask {mask_w}, {read_during_write}{})",
+                fmt_memory_initial(initial)
             ),
             Operator::IndexArray => write!(f, "IndexArray"),
-            Operator::IndexTuple(idx, ty) => write!(f, "IndexTuple({}, {ty:?})", idx),
+            Operator::IndexTuple(idx, ty) => {
+                write!(f, "IndexTuple({}, ({}))", idx, ty.iter().join(", "))
+            }
             Operator::RangeIndexArray {
                 start,
                 end_exclusive: end,
@@ -431,9 +537,23 @@ impl std::fmt::Display for Operator {
             Operator::IndexMemory => write!(f, "IndexMemory"),
             Operator::Instance {
                 name,
-                params: _,
+                params,
+                verilog_params,
                 loc: _,
-            } => write!(f, "Instance({})", name.as_verilog()),
+            } => {
+                write!(f, "Instance({}", name.as_verilog())?;
+                if !verilog_params.is_empty() {
+                    write!(
+                        f,
+                        " #({})",
+                        verilog_params.iter().map(|p| p.to_string()).join(", ")
+                    )?;
+                }
+                if !params.is_empty() {
+                    write!(f, "; {}", params.iter().join(", "))?;
+                }
+                write!(f, ")")
+            }
             Operator::Alias => write!(f, "Alias"),
             Operator::FlipPort => write!(f, "FlipPort"),
             Operator::ReadMutWires => write!(f, "ReadMutWires"),
@@ -450,6 +570,8 @@ pub struct Binding {
     pub operands: Vec<ValueName>,
     pub ty: Type,
     pub loc: Option<Loc<()>>,
+    /// Synthesis attributes emitted on the Verilog declaration of the value
+    pub verilog_attrs: Vec<VerilogAttribute>,
 }
 
 impl std::fmt::Display for Binding {
@@ -460,27 +582,130 @@ impl std::fmt::Display for Binding {
             operands,
             ty,
             loc: _,
+            verilog_attrs,
         } = self;
         write!(
             f,
-            "let {name}: {ty} = {operator}({})",
+            "{}let {name}: {ty} = {operator}({})",
+            fmt_verilog_attrs(verilog_attrs),
             operands.iter().map(|op| format!("{op}")).join(", ")
         )
     }
 }
 
+fn fmt_memory_initial(initial: &Option<Vec<Vec<Statement>>>) -> String {
+    if let Some(values) = initial {
+        format!(
+            ", [{}]",
+            values
+                .iter()
+                .map(|v| format!("[{}]", v.iter().map(|v| format!("{v}")).join(", ")))
+                .join(", ")
+        )
+    } else {
+        String::new()
+    }
+}
+
+/// What a block RAM port reads when it writes to the address it is reading
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReadDuringWrite {
+    /// The value stored before the write
+    ReadFirst,
+    /// The value being written
+    WriteFirst,
+    /// The read output keeps its previous value
+    NoChange,
+}
+
+impl std::fmt::Display for ReadDuringWrite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadDuringWrite::ReadFirst => write!(f, "read_first"),
+            ReadDuringWrite::WriteFirst => write!(f, "write_first"),
+            ReadDuringWrite::NoChange => write!(f, "no_change"),
+        }
+    }
+}
+
+/// The reset style of a register, lowered from `spade_hir::ResetStyle`. Decides whether the
+/// reset signal is in the sensitivity list of the generated `always` block and whether it is
+/// inverted in the reset condition
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ResetStyle {
+    /// The reset signal is left out of the sensitivity list, so it is only sampled on the clock
+    /// edge
+    pub synchronous: bool,
+    /// The reset triggers on `negedge` and the condition is `!rst`
+    pub active_low: bool,
+}
+
+impl std::fmt::Display for ResetStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ResetStyle {
+            synchronous,
+            active_low,
+        } = self;
+        write!(
+            f,
+            "{}, {}",
+            if *synchronous { "sync" } else { "async" },
+            if *active_low {
+                "active_low"
+            } else {
+                "active_high"
+            }
+        )
+    }
+}
+
+/// The edge of the clock on which a register samples its value
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ClockEdge {
+    #[default]
+    Rising,
+    Falling,
+    /// Both edges. This is not synthesizable and intended for simulation models
+    Both,
+}
+
+impl ClockEdge {
+    /// The verilog event expression for the edge of `clock`
+    pub fn event(&self, clock: &str) -> String {
+        match self {
+            ClockEdge::Rising => format!("posedge {clock}"),
+            ClockEdge::Falling => format!("negedge {clock}"),
+            ClockEdge::Both => format!("posedge {clock}, negedge {clock}"),
+        }
+    }
+}
+
+impl std::fmt::Display for ClockEdge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClockEdge::Rising => write!(f, "rising"),
+            ClockEdge::Falling => write!(f, "falling"),
+            ClockEdge::Both => write!(f, "both"),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Register {
     pub name: ValueName,
     pub ty: Type,
     pub clock: ValueName,
+    pub clock_edge: ClockEdge,
     pub reset: Option<(ValueName, ValueName)>,
+    pub reset_style: ResetStyle,
     pub initial: Option<Vec<Statement>>,
     pub value: ValueName,
     pub loc: Option<Loc<()>>,
     /// True if this register corresponds to an fsm with the specified ValueName
     /// as the actual state
     pub traced: Option<ValueName>,
+    /// Synthesis attributes emitted on the Verilog declaration of the register
+    pub verilog_attrs: Vec<VerilogAttribute>,
 }
 
 impl std::fmt::Display for Register {
@@ -489,16 +714,35 @@ impl std::fmt::Display for Register {
             name,
             ty,
             clock,
+            clock_edge,
             reset,
+            reset_style,
             initial,
             value,
             loc: _,
-            traced: _,
+            traced,
+            verilog_attrs,
         } = self;
 
+        let traced = traced
+            .as_ref()
+            .map(|t| format!("traced({t}) "))
+            .unwrap_or_else(String::new);
+
+        let clock = match clock_edge {
+            ClockEdge::Rising => format!("{clock}"),
+            other => format!("{other} {clock}"),
+        };
+
         let reset = reset
             .as_ref()
-            .map(|(trig, val)| format!("({trig}, {val})"))
+            .map(|(trig, val)| {
+                if *reset_style == ResetStyle::default() {
+                    format!("({trig}, {val})")
+                } else {
+                    format!("({trig}, {val}; {reset_style})")
+                }
+            })
             .unwrap_or_else(String::new);
 
         let initial = initial
@@ -506,7 +750,80 @@ impl std::fmt::Display for Register {
             .map(|i| format!("initial({})", i.iter().map(|s| format!("{s}")).join("; ")))
             .unwrap_or_else(String::new);
 
-        write!(f, "reg({clock}) {name}: {ty}{reset}{initial} = {value}")
+        write!(
+            f,
+            "{}{traced}reg({clock}) {name}: {ty}{reset}{initial} = {value}",
+            fmt_verilog_attrs(verilog_attrs)
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum PropertyKind {
+    Assert,
+    Assume,
+    Cover,
+}
+
+impl std::fmt::Display for PropertyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropertyKind::Assert => write!(f, "assert"),
+            PropertyKind::Assume => write!(f, "assume"),
+            PropertyKind::Cover => write!(f, "cover"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Implication {
+    Overlapping,
+    NonOverlapping,
+}
+
+impl std::fmt::Display for Implication {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Implication::Overlapping => write!(f, "|->"),
+            Implication::NonOverlapping => write!(f, "|=>"),
+        }
+    }
+}
+
+/// A concurrent property which is checked on every rising edge of `clock` by
+/// formal verification tools. The property is not checked while `reset` is high
+#[derive(Clone, PartialEq, Debug)]
+pub struct Property {
+    pub kind: PropertyKind,
+    pub clock: ValueName,
+    pub reset: Option<ValueName>,
+    pub antecedent: Option<(ValueName, Implication)>,
+    pub consequent: ValueName,
+    pub loc: Option<Loc<()>>,
+}
+
+impl std::fmt::Display for Property {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Property {
+            kind,
+            clock,
+            reset,
+            antecedent,
+            consequent,
+            loc: _,
+        } = self;
+
+        let reset = reset
+            .as_ref()
+            .map(|rst| format!(", {rst}"))
+            .unwrap_or_else(String::new);
+
+        let antecedent = antecedent
+            .as_ref()
+            .map(|(val, implication)| format!("{val} {implication} "))
+            .unwrap_or_else(String::new);
+
+        write!(f, "{kind}({clock}{reset}) {antecedent}{consequent}")
     }
 }
 
@@ -517,6 +834,7 @@ pub enum Statement {
     /// A constant expression with the specified ID and value
     Constant(u64, Type, ConstantValue),
     Assert(Loc<ValueName>),
+    Property(Property),
     Set {
         target: Loc<ValueName>,
         value: Loc<ValueName>,
@@ -551,13 +869,14 @@ impl std::fmt::Display for Statement {
             Statement::Register(r) => write!(f, "{r}"),
             Statement::Constant(id, ty, val) => write!(f, "const e{id}: {ty} = {val}"),
             Statement::Assert(val) => write!(f, "assert {val}"),
+            Statement::Property(p) => write!(f, "{p}"),
             Statement::Set { target, value } => write!(f, "set {target} = {value}"),
             Statement::WalTrace {
                 name,
                 val,
                 suffix,
-                ty: _,
-            } => write!(f, "wal_trace({name}, {val}, {suffix})"),
+                ty,
+            } => write!(f, "wal_trace({name}, {val}, {suffix}, {ty})"),
         }
     }
 }
@@ -578,6 +897,8 @@ pub struct Entity {
     pub output: ValueName,
     pub output_type: Type,
     pub statements: Vec<Statement>,
+    /// Synthesis attributes emitted on the Verilog module
+    pub verilog_attrs: Vec<VerilogAttribute>,
 }
 
 impl std::fmt::Display for Entity {
@@ -588,6 +909,7 @@ impl std::fmt::Display for Entity {
             output,
             output_type,
             statements,
+            verilog_attrs,
         } = self;
 
         let inputs = inputs
@@ -611,7 +933,8 @@ impl std::fmt::Display for Entity {
 
         writeln!(
             f,
-            "entity {name}({inputs}) -> {output_type} {{",
+            "{}entity {name}({inputs}) -> {output_type} {{",
+            fmt_verilog_attrs(verilog_attrs),
             name = name.as_verilog()
         )?;
         write!(f, "{statements}")?;
@@ -0,0 +1,153 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{Binding, Operator, Property, Register, Statement, ValueName};
+
+/// The values read by `statement`
+fn reads(statement: &Statement) -> Vec<&ValueName> {
+    match statement {
+        Statement::Binding(Binding { operands, .. }) => operands.iter().collect(),
+        Statement::Register(Register {
+            clock,
+            reset,
+            initial,
+            value,
+            ..
+        }) => {
+            let mut result = vec![clock, value];
+            if let Some((trig, val)) = reset {
+                result.push(trig);
+                result.push(val);
+            }
+            for statement in initial.iter().flatten() {
+                result.extend(reads(statement));
+            }
+            result
+        }
+        Statement::Constant(..) => vec![],
+        Statement::Assert(val) => vec![&val.inner],
+        Statement::Property(Property {
+            clock,
+            reset,
+            antecedent,
+            consequent,
+            ..
+        }) => [
+            Some(clock),
+            reset.as_ref(),
+            antecedent.as_ref().map(|(a, _)| a),
+        ]
+        .into_iter()
+        .flatten()
+        .chain([consequent])
+        .collect(),
+        Statement::Set { target, value } => vec![&target.inner, &value.inner],
+        Statement::WalTrace { name, val, .. } => vec![name, val],
+    }
+}
+
+/// Statements which have an effect even if their value is never read. Everything they
+/// read is live
+fn is_root(statement: &Statement) -> bool {
+    match statement {
+        Statement::Binding(Binding { operator, .. }) => {
+            matches!(operator, Operator::Instance { .. })
+        }
+        Statement::Register(_) | Statement::Constant(..) => false,
+        Statement::Assert(_)
+        | Statement::Property(_)
+        | Statement::Set { .. }
+        | Statement::WalTrace { .. } => true,
+    }
+}
+
+/// Returns the registers in `statements` whose value never affects `output`, an instance
+/// or any other statement with side effects. A register which is only read by the logic
+/// computing its own next value is unread
+pub fn unread_registers<'a>(statements: &'a [Statement], output: &ValueName) -> Vec<&'a Register> {
+    let definitions = statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Binding(Binding { name, .. })
+            | Statement::Register(Register { name, .. }) => Some((name, statement)),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut live = HashSet::new();
+    let mut to_visit = vec![output];
+    to_visit.extend(statements.iter().filter(|s| is_root(s)).flat_map(reads));
+
+    while let Some(name) = to_visit.pop() {
+        if live.insert(name) {
+            if let Some(statement) = definitions.get(name) {
+                to_visit.extend(reads(statement))
+            }
+        }
+    }
+
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Register(reg) if !live.contains(&reg.name) => Some(reg),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::types::Type;
+    use crate::unit_name::IntoUnitName;
+    use crate::{self as spade_mir, statement};
+
+    fn names(registers: Vec<&Register>) -> Vec<ValueName> {
+        registers.into_iter().map(|r| r.name.clone()).collect()
+    }
+
+    #[test]
+    fn registers_reaching_the_output_are_read() {
+        let statements = vec![
+            statement!(reg n(1, "a"); Type::int(8); clock (n(0, "clk")); n(2, "x")),
+            statement!(e(0); Type::int(8); Add; n(1, "a"), n(2, "x")),
+        ];
+
+        assert_eq!(
+            names(unread_registers(&statements, &spade_mir::value_name!(e(0)))),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn registers_only_feeding_themselves_are_unread() {
+        let statements = vec![
+            statement!(reg n(1, "a"); Type::int(8); clock (n(0, "clk")); e(0)),
+            statement!(e(0); Type::int(8); Add; n(1, "a"), n(2, "x")),
+        ];
+
+        assert_eq!(
+            names(unread_registers(
+                &statements,
+                &spade_mir::value_name!(n(2, "x"))
+            )),
+            vec![spade_mir::value_name!(n(1, "a"))]
+        );
+    }
+
+    #[test]
+    fn registers_read_by_instances_are_read() {
+        let statements = vec![
+            statement!(reg n(1, "a"); Type::int(8); clock (n(0, "clk")); n(2, "x")),
+            statement!(e(0); Type::Bool; simple_instance((["sink"]._test_into_unit_name(), vec!["a"])); n(1, "a")),
+        ];
+
+        assert_eq!(
+            names(unread_registers(
+                &statements,
+                &spade_mir::value_name!(n(2, "x"))
+            )),
+            vec![]
+        );
+    }
+}
@@ -61,6 +61,7 @@ macro_rules! statement {
             ],
             ty: $type,
             loc: None,
+            verilog_attrs: vec![],
         })
     };
     //register with async reset
@@ -77,11 +78,14 @@ macro_rules! statement {
             name: spade_mir::value_name!($name_kind $name),
             ty: $type,
             clock: spade_mir::value_name!($clk_name_kind $clk_name),
+            clock_edge: spade_mir::ClockEdge::Rising,
             reset: spade_mir::optional_reset!($($reset)?),
+            reset_style: spade_mir::ResetStyle::default(),
             initial: spade_mir::optional_initial!($($initial)?),
             value: spade_mir::value_name!($val_kind $val_name),
             loc: None,
-            traced: spade_mir::if_tracing!($($traced_kind $traced_name)?)
+            traced: spade_mir::if_tracing!($($traced_kind $traced_name)?),
+            verilog_attrs: vec![],
         })
     };
     // Register without reset
@@ -96,11 +100,14 @@ macro_rules! statement {
             name: spade_mir::value_name!($name_kind $name),
             ty: $type,
             clock: spade_mir::value_name!($clk_name_kind $clk_name),
+            clock_edge: spade_mir::ClockEdge::Rising,
             reset: None,
+            reset_style: spade_mir::ResetStyle::default(),
             initial: None,
             value: spade_mir::value_name!($val_kind $val_name),
             loc: None,
-            traced: spade_mir::if_tracing!($($traced_kind $traced_name)?)
+            traced: spade_mir::if_tracing!($($traced_kind $traced_name)?),
+            verilog_attrs: vec![],
         })
     };
     // Set statement
@@ -162,6 +169,7 @@ macro_rules! entity {
             statements: vec![
                 $( spade_mir::statement! $statement ),*
             ],
+            verilog_attrs: vec![],
         }
     }
 }
@@ -198,7 +206,10 @@ mod tests {
     use spade_mir::unit_name::UnitNameKind;
 
     use crate::{self as spade_mir, MirInput, UnitName};
-    use crate::{types::Type, Binding, ConstantValue, Operator, Register, Statement, ValueName};
+    use crate::{
+        types::Type, Binding, ClockEdge, ConstantValue, Operator, Register, ResetStyle, Statement,
+        ValueName,
+    };
 
     #[test]
     fn value_name_parsing_works() {
@@ -220,6 +231,7 @@ mod tests {
             ],
             ty: Type::Bool,
             loc: None,
+            verilog_attrs: vec![],
         });
 
         assert_eq!(
@@ -239,6 +251,7 @@ mod tests {
             ],
             ty: Type::Bool,
             loc: None,
+            verilog_attrs: vec![],
         });
 
         assert_eq!(
@@ -253,11 +266,14 @@ mod tests {
             name: ValueName::_test_named(0, "test".into()),
             ty: Type::int(5),
             clock: ValueName::_test_named(1, "clk".into()),
+            clock_edge: ClockEdge::Rising,
             reset: None,
+            reset_style: ResetStyle::default(),
             initial: None,
             value: ValueName::Expr(0),
             loc: None,
             traced: Some(ValueName::Expr(2)),
+            verilog_attrs: vec![],
         });
 
         assert_eq!(
@@ -272,11 +288,14 @@ mod tests {
             name: ValueName::_test_named(0, "test".into()),
             ty: Type::int(5),
             clock: ValueName::_test_named(1, "clk".into()),
+            clock_edge: ClockEdge::Rising,
             reset: Some((ValueName::Expr(1), ValueName::Expr(2))),
+            reset_style: ResetStyle::default(),
             initial: None,
             value: ValueName::Expr(0),
             loc: None,
             traced: None,
+            verilog_attrs: vec![],
         });
 
         assert_eq!(
@@ -307,6 +326,7 @@ mod tests {
                 statement!(e(0); Type::int(6); Add; n(1, "value")),
                 statement!(reg n(1, "value"); Type::int(6); clock (n(0, "clk")); e(0)),
             ],
+            verilog_attrs: vec![],
         };
 
         let result = entity!(&["pong"]; ("_i_clk", n(0, "clk"), Type::Bool) -> Type::int(6); {
@@ -0,0 +1,1152 @@
+//! A parser for the textual MIR format printed by the `Display` impls of [`Entity`] and
+//! [`Statement`], which is also what `--mir-output` writes. This makes it possible to write
+//! MIR by hand, for example to feed it directly to the MIR passes and codegen in tests.
+//!
+//! The format is whitespace insensitive and `//` starts a comment which runs to the end
+//! of the line.
+//!
+//! Value names are parsed back from how they are printed: `e{id}` is an expression, `{name}_n{id}`
+//! is a named value with a non-zero id and any other name is a named value with id 0. A named
+//! value which looks like one of the other kinds, like a variable called `e1`, therefore does not
+//! survive a round trip, but it will still be given the same name in the generated Verilog.
+//! Locations and the sources of names are not part of the format.
+
+use std::str::FromStr;
+
+use num::{BigInt, BigUint};
+use spade_common::location_info::WithLocation;
+use spade_common::name::{NameID, Path};
+
+use crate::types::Type;
+use crate::unit_name::UnitNameKind;
+use crate::{
+    Binding, ClockEdge, ConstantValue, Entity, Implication, MirInput, Operator, ParamName,
+    Property, PropertyKind, ReadDuringWrite, Register, ResetStyle, Statement, UnitName, ValueName,
+    ValueNameSource, VerilogAttribute, VerilogParameter, VerilogParameterValue,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+/// Parse all entities in `source`, for example a file written by `--mir-output`
+pub fn parse_entities(source: &str) -> Result<Vec<Entity>> {
+    let mut parser = Parser::new(source);
+    let mut result = vec![];
+    while !parser.at_end() {
+        result.push(parser.entity()?);
+    }
+    Ok(result)
+}
+
+/// Parse a single entity
+pub fn parse_entity(source: &str) -> Result<Entity> {
+    Parser::new(source).complete(Parser::entity)
+}
+
+/// Parse a single statement
+pub fn parse_statement(source: &str) -> Result<Statement> {
+    Parser::new(source).complete(Parser::statement)
+}
+
+pub fn parse_type(source: &str) -> Result<Type> {
+    Parser::new(source).complete(Parser::ty)
+}
+
+/// Converts a printed value name back into a value name. See the module documentation
+/// for the cases where this is ambiguous
+pub fn value_name(name: &str) -> ValueName {
+    if let Some(id) = name.strip_prefix('e').and_then(|id| id.parse().ok()) {
+        return ValueName::Expr(id);
+    }
+    let (id, name) = name
+        .rsplit_once("_n")
+        .and_then(|(base, id)| Some((id.parse().ok()?, base)))
+        .filter(|(_, base)| !base.is_empty())
+        .unwrap_or((0, name));
+    ValueName::Named(
+        id,
+        name.to_string(),
+        ValueNameSource::Name(NameID(id, Path::from_strs(&[name]))),
+    )
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError::new(self.line, message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.chars.get(self.pos) {
+            if *c == '\n' {
+                self.line += 1;
+            } else if *c == '/' && self.chars.get(self.pos + 1) == Some(&'/') {
+                while self.chars.get(self.pos).is_some_and(|c| *c != '\n') {
+                    self.pos += 1;
+                }
+                continue;
+            } else if !c.is_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.pos >= self.chars.len()
+    }
+
+    /// Runs `f` and requires that nothing but whitespace follows
+    fn complete<T>(mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let result = f(&mut self)?;
+        if !self.at_end() {
+            return Err(self.unexpected("end of input"));
+        }
+        Ok(result)
+    }
+
+    /// Runs `f`, restoring the position if it fails
+    fn try_parse<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Option<T> {
+        let (pos, line) = (self.pos, self.line);
+        let result = f(self).ok();
+        if result.is_none() {
+            self.pos = pos;
+            self.line = line;
+        }
+        result
+    }
+
+    fn unexpected(&mut self, expected: &str) -> ParseError {
+        self.skip_whitespace();
+        match self.chars.get(self.pos) {
+            Some(c) => self.error(format!("Expected {expected}, got `{c}`")),
+            None => self.error(format!("Expected {expected}, got end of input")),
+        }
+    }
+
+    fn peek_char(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        self.chars.get(self.pos) == Some(&c)
+    }
+
+    fn eat_if(&mut self, s: &str) -> bool {
+        self.skip_whitespace();
+        let matches = s
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+        if matches {
+            self.pos += s.chars().count();
+        }
+        matches
+    }
+
+    fn eat(&mut self, s: &str) -> Result<()> {
+        if self.eat_if(s) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{s}`")))
+        }
+    }
+
+    fn take_while(&mut self, cond: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| cond(*c)) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn word(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let word = self.take_while(is_word_char);
+        if word.is_empty() {
+            Err(self.unexpected("a name"))
+        } else {
+            Ok(word)
+        }
+    }
+
+    fn peek_word(&mut self) -> Option<String> {
+        let (pos, line) = (self.pos, self.line);
+        let word = self.word().ok();
+        self.pos = pos;
+        self.line = line;
+        word
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<()> {
+        match self.peek_word() {
+            Some(word) if word == keyword => {
+                self.word()?;
+                Ok(())
+            }
+            _ => Err(self.unexpected(&format!("`{keyword}`"))),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if self.chars.get(self.pos) == Some(&'-') {
+            self.pos += 1;
+        }
+        self.take_while(|c| c.is_ascii_digit());
+        let text = self.chars[start..self.pos].iter().collect::<String>();
+        text.parse().map_err(|_| {
+            self.pos = start;
+            self.unexpected("a number")
+        })
+    }
+
+    /// Items separated by `,` up to and including `close`
+    fn list_until<T>(
+        &mut self,
+        close: &str,
+        separator: &str,
+        mut item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut result = vec![];
+        if self.eat_if(close) {
+            return Ok(result);
+        }
+        loop {
+            result.push(item(self)?);
+            if self.eat_if(close) {
+                return Ok(result);
+            }
+            self.eat(separator)?;
+        }
+    }
+
+    fn list<T>(
+        &mut self,
+        open: &str,
+        close: &str,
+        item: impl FnMut(&mut Self) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        self.eat(open)?;
+        self.list_until(close, ",", item)
+    }
+
+    fn value_name(&mut self) -> Result<ValueName> {
+        Ok(value_name(&self.word()?))
+    }
+
+    fn expr_id(&mut self) -> Result<u64> {
+        match self.value_name()? {
+            ValueName::Expr(id) => Ok(id),
+            ValueName::Named(_, _, _) => Err(self.error("Expected an expression name like `e0`")),
+        }
+    }
+
+    fn unit_name(&mut self) -> Result<UnitName> {
+        if self.eat_if("\\") {
+            let name = self.take_while(|c| !c.is_whitespace());
+            // Names of units with IDs are printed as `path[id]`
+            let (base, id) = name
+                .strip_suffix(']')
+                .and_then(|rest| rest.rsplit_once('['))
+                .and_then(|(base, id)| Some((base, id.parse().ok()?)))
+                .unwrap_or((name.as_str(), 0));
+            let path = base.split("::").collect::<Vec<_>>();
+            Ok(UnitName {
+                source: NameID(id, Path::from_strs(&path)),
+                kind: UnitNameKind::Escaped {
+                    path: path.iter().map(|s| s.to_string()).collect(),
+                    name,
+                },
+            })
+        } else {
+            let name = self.word()?;
+            Ok(UnitName {
+                source: NameID(0, Path::from_strs(&[&name])),
+                kind: UnitNameKind::Unescaped(name),
+            })
+        }
+    }
+
+    fn ty(&mut self) -> Result<Type> {
+        self.skip_whitespace();
+        if self.peek_char('(') {
+            return Ok(Type::Tuple(self.list("(", ")", Self::ty)?));
+        }
+        if self.peek_char('{') {
+            let members = self.list("{", "}", |p| {
+                let name = p.word()?;
+                p.eat(":")?;
+                Ok((name, p.ty()?))
+            })?;
+            return Ok(Type::Struct(members));
+        }
+        if self.peek_char('[') {
+            let (inner, length) = self.array_type()?;
+            return Ok(Type::Array { inner, length });
+        }
+        if self.eat_if("&") {
+            self.keyword("mut")?;
+            self.eat("(")?;
+            let inner = self.ty()?;
+            self.eat(")")?;
+            return Ok(Type::Backward(Box::new(inner)));
+        }
+
+        let name = self.word().map_err(|_| self.unexpected("a type"))?;
+        match name.as_str() {
+            "int" | "uint" => {
+                self.eat("<")?;
+                let size = self.number()?;
+                self.eat(">")?;
+                Ok(if name == "int" {
+                    Type::Int(size)
+                } else {
+                    Type::UInt(size)
+                })
+            }
+            "bool" => Ok(Type::Bool),
+            "void" => Ok(Type::Void),
+            "Memory" => {
+                let (inner, length) = self.array_type()?;
+                Ok(Type::Memory { inner, length })
+            }
+            "enum" => {
+                let mut variants = vec![];
+                if self.peek_word().as_deref() == Some("option") {
+                    self.keyword("option")?;
+                    variants.push(self.list("[", "]", Self::ty)?);
+                    // The variants are separated by `,` just like the items of the list
+                    // the enum may be in
+                    while let Some(variant) = self.try_parse(|p| {
+                        p.eat(",")?;
+                        p.keyword("option")?;
+                        p.list("[", "]", Self::ty)
+                    }) {
+                        variants.push(variant)
+                    }
+                }
+                Ok(Type::Enum(variants))
+            }
+            "inout" => {
+                self.eat("<")?;
+                let inner = self.ty()?;
+                self.eat(">")?;
+                Ok(Type::InOut(Box::new(inner)))
+            }
+            other => Err(self.error(format!("Unknown type `{other}`"))),
+        }
+    }
+
+    /// `[inner; length]`
+    fn array_type(&mut self) -> Result<(Box<Type>, BigUint)> {
+        self.eat("[")?;
+        let inner = self.ty()?;
+        self.eat(";")?;
+        let length = self.number()?;
+        self.eat("]")?;
+        Ok((Box::new(inner), length))
+    }
+
+    fn verilog_value(&mut self) -> Result<VerilogParameterValue> {
+        if self.eat_if("\"") {
+            let value = self.take_while(|c| c != '"');
+            self.eat("\"")?;
+            Ok(VerilogParameterValue::String(value))
+        } else {
+            Ok(VerilogParameterValue::Int(self.number()?))
+        }
+    }
+
+    /// `(* name, name = value *)`, or nothing
+    fn verilog_attrs(&mut self) -> Result<Vec<VerilogAttribute>> {
+        if !self.eat_if("(*") {
+            return Ok(vec![]);
+        }
+        self.list_until("*)", ",", |p| {
+            let name = p.word()?;
+            let value = if p.eat_if("=") {
+                Some(p.verilog_value()?)
+            } else {
+                None
+            };
+            Ok(VerilogAttribute { name, value })
+        })
+    }
+
+    /// `(a, b)`, the arguments of an operator
+    fn args<const N: usize>(&mut self) -> Result<[BigUint; N]> {
+        let args = self.list("(", ")", Self::number)?;
+        let count = args.len();
+        args.try_into()
+            .map_err(|_| self.error(format!("Expected {N} arguments, got {count}")))
+    }
+
+    fn memory_initial(&mut self) -> Result<Vec<Vec<Statement>>> {
+        self.list("[", "]", |p| p.list("[", "]", Self::statement))
+    }
+
+    fn operator(&mut self) -> Result<Operator> {
+        let name = self.word()?;
+        let operator = match name.as_str() {
+            "Add" => Operator::Add,
+            "UnsignedAdd" => Operator::UnsignedAdd,
+            "Sub" => Operator::Sub,
+            "UnsignedSub" => Operator::UnsignedSub,
+            "Mul" => Operator::Mul,
+            "UnsignedMul" => Operator::UnsignedMul,
+            "Div" => Operator::Div,
+            "UnsignedDiv" => Operator::UnsignedDiv,
+            "Mod" => Operator::Mod,
+            "UnsignedMod" => Operator::UnsignedMod,
+            "Eq" => Operator::Eq,
+            "NotEq" => Operator::NotEq,
+            "Gt" => Operator::Gt,
+            "UnsignedGt" => Operator::UnsignedGt,
+            "Lt" => Operator::Lt,
+            "UnsignedLt" => Operator::UnsignedLt,
+            "Ge" => Operator::Ge,
+            "UnsignedGe" => Operator::UnsignedGe,
+            "Le" => Operator::Le,
+            "UnsignedLe" => Operator::UnsignedLe,
+            "LeftShift" => Operator::LeftShift,
+            "RightShift" => Operator::RightShift,
+            "ArithmeticRightShift" => Operator::ArithmeticRightShift,
+            "LogicalAnd" => Operator::LogicalAnd,
+            "LogicalOr" => Operator::LogicalOr,
+            "LogicalXor" => Operator::LogicalXor,
+            "LogicalNot" => Operator::LogicalNot,
+            "BitwiseAnd" => Operator::BitwiseAnd,
+            "BitwiseOr" => Operator::BitwiseOr,
+            "BitwiseXor" => Operator::BitwiseXor,
+            "BitwiseNot" => Operator::BitwiseNot,
+            "ReduceAnd" => Operator::ReduceAnd,
+            "ReduceOr" => Operator::ReduceOr,
+            "ReduceXor" => Operator::ReduceXor,
+            "USub" => Operator::USub,
+            "Not" => Operator::Not,
+            "ReadPort" => Operator::ReadPort,
+            "Bitreverse" => Operator::Bitreverse,
+            "DivPow2" => Operator::DivPow2,
+            "Truncate" => Operator::Truncate,
+            "Concat" => Operator::Concat,
+            "Select" => Operator::Select { from_if: false },
+            "Match" => Operator::Match { arm_locs: vec![] },
+            "ConstructArray" => Operator::ConstructArray,
+            "IndexArray" => Operator::IndexArray,
+            "IndexMemory" => Operator::IndexMemory,
+            "ConstructTuple" => Operator::ConstructTuple,
+            "FlipPort" => Operator::FlipPort,
+            "ReadMutWires" => Operator::ReadMutWires,
+            "Alias" => Operator::Alias,
+            "Nop" => Operator::Nop,
+            "Gray2Bin" => {
+                let [num_bits] = self.args()?;
+                Operator::Gray2Bin { num_bits }
+            }
+            "SignExtend" => {
+                let [extra_bits, operand_size] = self.args()?;
+                Operator::SignExtend {
+                    extra_bits,
+                    operand_size,
+                }
+            }
+            "ZeroExtend" => {
+                let [extra_bits] = self.args()?;
+                Operator::ZeroExtend { extra_bits }
+            }
+            "RangeIndexArray" => {
+                let [start, end_exclusive] = self.args()?;
+                Operator::RangeIndexArray {
+                    start,
+                    end_exclusive,
+                }
+            }
+            "RangeIndexBits" => {
+                let [start, end_exclusive] = self.args()?;
+                Operator::RangeIndexBits {
+                    start,
+                    end_exclusive,
+                }
+            }
+            "ConstructEnum" => {
+                self.eat("(")?;
+                let variant = self.number()?;
+                self.eat(",")?;
+                let variant_count = self.number()?;
+                self.eat(")")?;
+                Operator::ConstructEnum {
+                    variant,
+                    variant_count,
+                }
+            }
+            "IsEnumVariant" => {
+                self.eat("(")?;
+                let variant = self.number()?;
+                self.eat(",")?;
+                let enum_type = self.ty()?;
+                self.eat(")")?;
+                Operator::IsEnumVariant { variant, enum_type }
+            }
+            "EnumMember" => {
+                self.eat("(")?;
+                let variant = self.number()?;
+                let member_index = self.number()?;
+                self.eat(",")?;
+                let enum_type = self.ty()?;
+                self.eat(")")?;
+                Operator::EnumMember {
+                    enum_type,
+                    variant,
+                    member_index,
+                }
+            }
+            "IndexTuple" => {
+                self.eat("(")?;
+                let index = self.number()?;
+                self.eat(",")?;
+                let types = self.list("(", ")", Self::ty)?;
+                self.eat(")")?;
+                Operator::IndexTuple(index, types)
+            }
+            "DeclClockedMemory" => {
+                self.eat("(")?;
+                let write_ports = self.number()?;
+                self.eat(",")?;
+                let addr_w = self.number()?;
+                self.eat(",")?;
+                let inner_w = self.number()?;
+                self.eat(",")?;
+                let elems = self.number()?;
+                let mut mask_w = 1u32.into();
+                let mut initial = None;
+                while self.eat_if(",") {
+                    if self.peek_char('[') {
+                        initial = Some(self.memory_initial()?);
+                    } else {
+                        self.keyword("mask")?;
+                        mask_w = self.number()?;
+                    }
+                }
+                self.eat(")")?;
+                Operator::DeclClockedMemory {
+                    write_ports,
+                    addr_w,
+                    inner_w,
+                    elems,
+                    initial,
+                    mask_w,
+                }
+            }
+            "DeclBlockRam" => {
+                self.eat("(")?;
+                let addr_w = self.number()?;
+                self.eat(",")?;
+                let inner_w = self.number()?;
+                self.eat(",")?;
+                let elems = self.number()?;
+                self.eat(",")?;
+                self.keyword("mask")?;
+                let mask_w = self.number()?;
+                self.eat(",")?;
+                let read_during_write = match self.word()?.as_str() {
+                    "read_first" => ReadDuringWrite::ReadFirst,
+                    "write_first" => ReadDuringWrite::WriteFirst,
+                    "no_change" => ReadDuringWrite::NoChange,
+                    other => {
+                        return Err(self.error(format!("Unknown read during write mode `{other}`")))
+                    }
+                };
+                let initial = if self.eat_if(",") {
+                    Some(self.memory_initial()?)
+                } else {
+                    None
+                };
+                self.eat(")")?;
+                Operator::DeclBlockRam {
+                    addr_w,
+                    inner_w,
+                    elems,
+                    mask_w,
+                    read_during_write,
+                    initial,
+                }
+            }
+            "Instance" => {
+                self.eat("(")?;
+                let name = self.unit_name()?;
+                let verilog_params = if self.eat_if("#") {
+                    self.list("(", ")", |p| {
+                        p.eat(".")?;
+                        let name = p.word()?;
+                        p.eat("(")?;
+                        let value = p.verilog_value()?;
+                        p.eat(")")?;
+                        Ok(VerilogParameter { name, value })
+                    })?
+                } else {
+                    vec![]
+                };
+                let params = if self.eat_if(";") {
+                    self.list_until(")", ",", |p| {
+                        let no_mangle = p.eat_if("#[no_mangle]").then(|| ().nowhere());
+                        Ok(ParamName {
+                            name: p.word()?,
+                            no_mangle,
+                        })
+                    })?
+                } else {
+                    self.eat(")")?;
+                    vec![]
+                };
+                Operator::Instance {
+                    name,
+                    params,
+                    verilog_params,
+                    loc: None,
+                }
+            }
+            other => return Err(self.error(format!("Unknown operator `{other}`"))),
+        };
+        Ok(operator)
+    }
+
+    fn constant_value(&mut self) -> Result<ConstantValue> {
+        match self.peek_word().as_deref() {
+            Some("true") => {
+                self.word()?;
+                Ok(ConstantValue::Bool(true))
+            }
+            Some("false") => {
+                self.word()?;
+                Ok(ConstantValue::Bool(false))
+            }
+            Some("HIGHIMP") => {
+                self.word()?;
+                Ok(ConstantValue::HighImp)
+            }
+            _ => Ok(ConstantValue::Int(self.number::<BigInt>()?)),
+        }
+    }
+
+    fn register(
+        &mut self,
+        traced: Option<ValueName>,
+        verilog_attrs: Vec<VerilogAttribute>,
+    ) -> Result<Register> {
+        self.eat("(")?;
+        let first = self.word()?;
+        let (clock_edge, clock) = if self.peek_char(')') {
+            (ClockEdge::Rising, value_name(&first))
+        } else {
+            let edge = match first.as_str() {
+                "rising" => ClockEdge::Rising,
+                "falling" => ClockEdge::Falling,
+                "both" => ClockEdge::Both,
+                other => return Err(self.error(format!("Unknown clock edge `{other}`"))),
+            };
+            (edge, self.value_name()?)
+        };
+        self.eat(")")?;
+
+        let name = self.value_name()?;
+        self.eat(":")?;
+        let ty = self.ty()?;
+
+        let mut reset_style = ResetStyle::default();
+        let reset = if self.eat_if("(") {
+            let trigger = self.value_name()?;
+            self.eat(",")?;
+            let value = self.value_name()?;
+            if self.eat_if(";") {
+                reset_style.synchronous = match self.word()?.as_str() {
+                    "sync" => true,
+                    "async" => false,
+                    other => return Err(self.error(format!("Unknown reset kind `{other}`"))),
+                };
+                self.eat(",")?;
+                reset_style.active_low = match self.word()?.as_str() {
+                    "active_low" => true,
+                    "active_high" => false,
+                    other => return Err(self.error(format!("Unknown reset polarity `{other}`"))),
+                };
+            }
+            self.eat(")")?;
+            Some((trigger, value))
+        } else {
+            None
+        };
+
+        let initial = if self.peek_word().as_deref() == Some("initial") {
+            self.keyword("initial")?;
+            self.eat("(")?;
+            Some(self.list_until(")", ";", Self::statement)?)
+        } else {
+            None
+        };
+
+        self.eat("=")?;
+        let value = self.value_name()?;
+
+        Ok(Register {
+            name,
+            ty,
+            clock,
+            clock_edge,
+            reset,
+            reset_style,
+            initial,
+            value,
+            loc: None,
+            traced,
+            verilog_attrs,
+        })
+    }
+
+    fn property(&mut self, kind: PropertyKind) -> Result<Property> {
+        self.eat("(")?;
+        let clock = self.value_name()?;
+        let reset = if self.eat_if(",") {
+            Some(self.value_name()?)
+        } else {
+            None
+        };
+        self.eat(")")?;
+
+        let first = self.value_name()?;
+        let implication = if self.eat_if("|->") {
+            Some(Implication::Overlapping)
+        } else if self.eat_if("|=>") {
+            Some(Implication::NonOverlapping)
+        } else {
+            None
+        };
+        let (antecedent, consequent) = match implication {
+            Some(implication) => (Some((first, implication)), self.value_name()?),
+            None => (None, first),
+        };
+
+        Ok(Property {
+            kind,
+            clock,
+            reset,
+            antecedent,
+            consequent,
+            loc: None,
+        })
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        let verilog_attrs = self.verilog_attrs()?;
+        let keyword = self.word()?;
+        if !verilog_attrs.is_empty() && !["let", "reg", "traced"].contains(&keyword.as_str()) {
+            return Err(self.error("Only bindings and registers can have verilog attributes"));
+        }
+
+        let statement = match keyword.as_str() {
+            "let" => {
+                let name = self.value_name()?;
+                self.eat(":")?;
+                let ty = self.ty()?;
+                self.eat("=")?;
+                let operator = self.operator()?;
+                let operands = self.list("(", ")", Self::value_name)?;
+                Statement::Binding(Binding {
+                    name,
+                    operator,
+                    operands,
+                    ty,
+                    loc: None,
+                    verilog_attrs,
+                })
+            }
+            "reg" => Statement::Register(self.register(None, verilog_attrs)?),
+            "traced" => {
+                self.eat("(")?;
+                let traced = self.value_name()?;
+                self.eat(")")?;
+                self.keyword("reg")?;
+                Statement::Register(self.register(Some(traced), verilog_attrs)?)
+            }
+            "const" => {
+                let id = self.expr_id()?;
+                self.eat(":")?;
+                let ty = self.ty()?;
+                self.eat("=")?;
+                Statement::Constant(id, ty, self.constant_value()?)
+            }
+            "assert" if self.peek_char('(') => {
+                Statement::Property(self.property(PropertyKind::Assert)?)
+            }
+            "assert" => Statement::Assert(self.value_name()?.nowhere()),
+            "assume" => Statement::Property(self.property(PropertyKind::Assume)?),
+            "cover" => Statement::Property(self.property(PropertyKind::Cover)?),
+            "set" => {
+                let target = self.value_name()?.nowhere();
+                self.eat("=")?;
+                let value = self.value_name()?.nowhere();
+                Statement::Set { target, value }
+            }
+            "wal_trace" => {
+                self.eat("(")?;
+                let name = self.value_name()?;
+                self.eat(",")?;
+                let val = self.value_name()?;
+                self.eat(",")?;
+                let suffix = self.word()?;
+                self.eat(",")?;
+                let ty = self.ty()?;
+                self.eat(")")?;
+                Statement::WalTrace {
+                    name,
+                    val,
+                    suffix,
+                    ty,
+                }
+            }
+            other => return Err(self.error(format!("Expected a statement, got `{other}`"))),
+        };
+        Ok(statement)
+    }
+
+    fn input(&mut self) -> Result<MirInput> {
+        self.eat("(")?;
+        let no_mangle = self.eat_if("#[no_mangle]").then(|| ().nowhere());
+        let name = self.word()?;
+        self.eat(",")?;
+        let val_name = self.value_name()?;
+        self.eat(",")?;
+        let ty = self.ty()?;
+        self.eat(")")?;
+        Ok(MirInput {
+            name,
+            val_name,
+            ty,
+            no_mangle,
+        })
+    }
+
+    fn entity(&mut self) -> Result<Entity> {
+        let verilog_attrs = self.verilog_attrs()?;
+        self.keyword("entity")?;
+        let name = self.unit_name()?;
+        let inputs = self.list("(", ")", Self::input)?;
+        self.eat("->")?;
+        let output_type = self.ty()?;
+
+        self.eat("{")?;
+        let mut statements = vec![];
+        while !self.eat_if("}") {
+            statements.push(self.statement()?);
+        }
+        self.eat("=>")?;
+        let output = self.value_name()?;
+
+        Ok(Entity {
+            name,
+            inputs,
+            output,
+            output_type,
+            statements,
+            verilog_attrs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use colored::Colorize;
+    use indoc::indoc;
+    use spade_common::id_tracker::ExprIdTracker;
+    use spade_common::location_info::WithLocation;
+
+    use super::*;
+    use crate::codegen::{entity_code, prepare_codegen};
+    use crate::passes::mir_passes;
+    use crate::unit_name::{InstanceMap, IntoUnitName};
+    use crate::{self as spade_mir, assert_same_mir, entity, statement};
+
+    /// Prints `entity`, parses it and checks that the result is the same entity
+    fn assert_round_trips(entity: &Entity) {
+        let printed = format!("{entity}");
+        let parsed = match parse_entity(&printed) {
+            Ok(parsed) => parsed,
+            Err(e) => panic!("Failed to parse\n{printed}\n{e}"),
+        };
+        assert_same_mir!(&parsed, entity);
+        assert_eq!(parsed.name, entity.name);
+        assert_eq!(parsed.verilog_attrs, entity.verilog_attrs);
+        assert_eq!(format!("{parsed}"), printed);
+    }
+
+    #[test]
+    fn value_names_are_parsed_from_how_they_are_printed() {
+        assert_eq!(value_name("e12"), ValueName::Expr(12));
+        assert_eq!(value_name("x"), ValueName::_test_named(0, "x".to_string()));
+        assert_eq!(
+            value_name("new_n0"),
+            ValueName::_test_named(0, "new".to_string())
+        );
+        assert_eq!(
+            value_name("a_n_n3"),
+            ValueName::_test_named(3, "a_n".to_string())
+        );
+        assert_eq!(
+            value_name("state"),
+            ValueName::_test_named(0, "state".to_string())
+        );
+    }
+
+    #[test]
+    fn types_round_trip() {
+        let types = [
+            Type::int(8),
+            Type::uint(3),
+            Type::Bool,
+            Type::Void,
+            Type::Tuple(vec![Type::Bool, Type::Tuple(vec![])]),
+            Type::Struct(vec![
+                ("a".to_string(), Type::Bool),
+                (
+                    "option".to_string(),
+                    Type::Enum(vec![vec![], vec![Type::int(2)]]),
+                ),
+            ]),
+            Type::Array {
+                inner: Box::new(Type::Bool),
+                length: 4u32.into(),
+            },
+            Type::Memory {
+                inner: Box::new(Type::int(4)),
+                length: 16u32.into(),
+            },
+            Type::Enum(vec![
+                vec![Type::int(8), Type::Enum(vec![vec![], vec![]])],
+                vec![],
+            ]),
+            Type::backward(Type::Tuple(vec![Type::Bool, Type::int(2)])),
+            Type::InOut(Box::new(Type::int(2))),
+        ];
+        for ty in types {
+            assert_eq!(parse_type(&format!("{ty}")), Ok(ty));
+        }
+    }
+
+    #[test]
+    fn entity_with_every_statement_kind_round_trips() {
+        let enum_type = Type::Enum(vec![vec![Type::int(4)], vec![]]);
+        let mut input = entity!(&["a", "b"]; (
+            "clk", n(0, "clk"), Type::Bool,
+            "rst", n(1, "rst"), Type::Bool,
+            "x", n(2, "x"), enum_type.clone(),
+        ) -> Type::int(4); {
+            (const 0; Type::int(4); ConstantValue::int(-3));
+            (const 1; Type::Bool; ConstantValue::Bool(true));
+            (e(2); Type::Bool; IsEnumVariant({variant: 0, enum_type: enum_type.clone()}); n(2, "x"));
+            (n(3, "y"); Type::int(4); EnumMember({variant: 0, member_index: 0, enum_type: enum_type.clone()}); n(2, "x"));
+            (e(4); Type::int(4); Add; e(0), n(3, "y"));
+            (e(5); Type::Tuple(vec![Type::int(4), Type::Bool]); ConstructTuple; e(4), e(2));
+            (e(6); Type::Bool; IndexTuple((1, vec![Type::int(4), Type::Bool])); e(5));
+            (e(7); Type::int(6); SignExtend({extra_bits: 2u32.into(), operand_size: 4u32.into()}); e(4));
+            (e(8); Type::int(4); Instance({
+                name: ["a", "c"]._test_into_unit_name(),
+                params: vec![ParamName{name: "p".to_string(), no_mangle: Some(().nowhere())}],
+                verilog_params: vec![VerilogParameter {
+                    name: "W".to_string(),
+                    value: VerilogParameterValue::String("x".to_string())
+                }],
+                loc: None
+            }); e(4));
+            (reg n(4, "r"); Type::int(4); clock (n(0, "clk")); reset (n(1, "rst"), e(0)); e(8));
+            (traced(n(3, "y")) reg n(5, "s"); Type::int(4); clock (n(0, "clk")); n(3, "y"));
+            (assert; e(6));
+            (set; e(2); e(6));
+            (wal_trace (n(3, "y"), e(4), "_suffix", Type::int(4)))
+        } => n(4, "r"));
+
+        input.verilog_attrs = vec![VerilogAttribute {
+            name: "keep_hierarchy".to_string(),
+            value: None,
+        }];
+        input.inputs[0].no_mangle = Some(().nowhere());
+        if let Statement::Register(reg) = &mut input.statements[9] {
+            reg.reset_style = ResetStyle {
+                synchronous: true,
+                active_low: true,
+            };
+        }
+        if let Statement::Register(reg) = &mut input.statements[10] {
+            reg.clock_edge = ClockEdge::Falling;
+            reg.initial = Some(vec![
+                statement!(const 9; Type::int(4); ConstantValue::int(1)),
+            ]);
+            reg.verilog_attrs = vec![VerilogAttribute {
+                name: "ram_style".to_string(),
+                value: Some(VerilogParameterValue::String("block".to_string())),
+            }];
+        }
+        input.statements.push(Statement::Property(Property {
+            kind: PropertyKind::Cover,
+            clock: ValueName::_test_named(0, "clk".to_string()),
+            reset: Some(ValueName::_test_named(1, "rst".to_string())),
+            antecedent: Some((ValueName::Expr(2), Implication::NonOverlapping)),
+            consequent: ValueName::Expr(6),
+            loc: None,
+        }));
+        input.statements.push(Statement::Property(Property {
+            kind: PropertyKind::Assert,
+            clock: ValueName::_test_named(0, "clk".to_string()),
+            reset: None,
+            antecedent: None,
+            consequent: ValueName::Expr(6),
+            loc: None,
+        }));
+
+        assert_round_trips(&input);
+    }
+
+    #[test]
+    fn memories_round_trip() {
+        let input = entity!("mem"; ("clk", n(0, "clk"), Type::Bool) -> Type::Bool; {
+            (e(0); Type::Memory{inner: Box::new(Type::Bool), length: 2u32.into()}; DeclClockedMemory({
+                write_ports: 1u32.into(),
+                addr_w: 1u32.into(),
+                inner_w: 1u32.into(),
+                elems: 2u32.into(),
+                initial: Some(vec![
+                    vec![statement!(const 1; Type::Bool; ConstantValue::Bool(false))],
+                    vec![statement!(const 2; Type::Bool; ConstantValue::Bool(true))],
+                ]),
+                mask_w: 1u32.into(),
+            }); n(0, "clk"));
+            (e(3); Type::int(8); DeclBlockRam({
+                addr_w: 2u32.into(),
+                inner_w: 8u32.into(),
+                elems: 4u32.into(),
+                mask_w: 2u32.into(),
+                read_during_write: ReadDuringWrite::NoChange,
+                initial: None,
+            }); n(0, "clk"));
+            (e(4); Type::Bool; RangeIndexBits({start: 0u32.into(), end_exclusive: 1u32.into()}); e(3))
+        } => e(4));
+
+        assert_round_trips(&input);
+    }
+
+    #[test]
+    fn entities_in_mir_output_are_separated() {
+        let source = indoc! {r"
+            entity first((a, a, bool)) -> bool {
+            } => a
+
+            // A comment
+            entity \lib::second[3] ((b, b, int<2>)) -> int<2> {
+                let e0: int<2> = USub(b) // Another comment
+            } => e0
+        "};
+        let entities = parse_entities(source).unwrap();
+        assert_eq!(entities.len(), 2);
+        assert_eq!(
+            entities[1].name.kind,
+            UnitNameKind::Escaped {
+                name: "lib::second[3]".to_string(),
+                path: vec!["lib".to_string(), "second".to_string()]
+            }
+        );
+        assert_eq!(entities[1].name.source.0, 3);
+    }
+
+    #[test]
+    fn errors_point_to_the_line() {
+        let source = indoc! {"
+            entity e() -> bool {
+                let e0: bool = Frobnicate()
+            } => e0
+        "};
+        assert_eq!(
+            parse_entity(source),
+            Err(ParseError::new(2, "Unknown operator `Frobnicate`"))
+        );
+        assert_eq!(
+            parse_statement("let x: int<> = Add(a, b)"),
+            Err(ParseError::new(1, "Expected a number, got `>`"))
+        );
+    }
+
+    #[test]
+    fn hand_written_mir_generates_the_same_code_as_mir_from_macros() {
+        let source = indoc! {"
+            entity counter((clk, clk, bool), (rst, rst, bool)) -> int<8> {
+                const e0: int<8> = 0
+                const e1: int<8> = 1
+                let e2: int<9> = Add(count, e1)
+                let e3: int<8> = Truncate(e2)
+                reg(clk) count: int<8>(rst, e0) = e3
+            } => count
+        "};
+        let expected = entity!("counter"; (
+            "clk", n(0, "clk"), Type::Bool,
+            "rst", n(0, "rst"), Type::Bool,
+        ) -> Type::int(8); {
+            (const 0; Type::int(8); ConstantValue::int(0));
+            (const 1; Type::int(8); ConstantValue::int(1));
+            (e(2); Type::int(9); Add; n(0, "count"), e(1));
+            (e(3); Type::int(8); Truncate; e(2));
+            (reg n(0, "count"); Type::int(8); clock (n(0, "clk")); reset (n(0, "rst"), e(0)); e(3))
+        } => n(0, "count"));
+
+        let parsed = parse_entity(source).unwrap();
+        assert_same_mir!(&parsed, &expected);
+
+        let code = |entity: Entity| {
+            let mut idtracker = ExprIdTracker::new_at(100);
+            let mut entity = prepare_codegen(entity, &mut idtracker);
+            for pass in mir_passes().values() {
+                entity.0.statements =
+                    pass.transform_statements(&entity.0.statements, &mut idtracker);
+            }
+            entity_code(&entity, &mut InstanceMap::new(), &None)
+                .0
+                .to_string()
+        };
+        assert_eq!(code(parsed), code(expected));
+    }
+}
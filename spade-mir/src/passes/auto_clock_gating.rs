@@ -27,6 +27,7 @@ fn split_trivial_tag_value(
         operands: vec![value.clone()],
         ty: Type::Bool,
         loc: *loc,
+        verilog_attrs: vec![],
     }));
     statements.push(Statement::Binding(Binding {
         name: payload_name.clone(),
@@ -37,6 +38,7 @@ fn split_trivial_tag_value(
         operands: vec![value.clone()],
         ty: Type::Tuple(variants[1].clone()),
         loc: *loc,
+        verilog_attrs: vec![],
     }));
 
     (tag_name, payload_name)
@@ -88,16 +90,19 @@ impl Register {
                         name: tag_reg_name.clone(),
                         ty: Type::Bool,
                         clock: self.clock.clone(),
+                        clock_edge: self.clock_edge,
                         reset: reset_tag,
+                        reset_style: self.reset_style,
                         initial: self.initial.as_ref().map(|_| panic!("Had initial")),
                         value: value_tag.clone(),
                         loc: self.loc,
                         // FIXME: wal-tracing breaks with this change
                         traced: None,
+                        verilog_attrs: self.verilog_attrs.clone(),
                     }));
                     new_statements.push(Statement::Binding(Binding {
                         name: payload_reg_value_name.clone(),
-                        operator: Operator::Select,
+                        operator: Operator::Select { from_if: false },
                         operands: vec![
                             value_tag.clone(),
                             value_payload.clone(),
@@ -105,17 +110,21 @@ impl Register {
                         ],
                         ty: payload_type.clone(),
                         loc: self.loc,
+                        verilog_attrs: vec![],
                     }));
                     new_statements.push(Statement::Register(Register {
                         name: payload_reg_name.clone(),
                         ty: payload_type,
                         clock: self.clock.clone(),
+                        clock_edge: self.clock_edge,
                         reset: reset_payload,
+                        reset_style: self.reset_style,
                         initial: self.initial.as_ref().map(|_| panic!("Had initial")),
                         value: payload_reg_value_name,
                         loc: self.loc,
                         // FIXME: wal-tracing breaks with this change
                         traced: None,
+                        verilog_attrs: self.verilog_attrs.clone(),
                     }));
                     new_statements.push(Statement::Binding(Binding {
                         name: self.name.clone(),
@@ -123,6 +132,7 @@ impl Register {
                         operands: vec![tag_reg_name.clone(), payload_reg_name.clone()],
                         ty: self.ty.clone(),
                         loc: self.loc,
+                        verilog_attrs: vec![],
                     }));
 
                     Some(new_statements)
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use spade_common::name::NameID;
 
-use crate::{Binding, Entity, MirInput, Register, ValueName};
+use crate::{Binding, Entity, MirInput, Property, Register, ValueName};
 
 /// Mapping from verilog name back to the corresponding NameID
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -106,6 +106,7 @@ pub fn make_names_predictable(e: &mut Entity) -> NameState {
             output: _,
             output_type: _,
             statements,
+            verilog_attrs: _,
         } = e;
 
         for input in inputs {
@@ -120,19 +121,24 @@ pub fn make_names_predictable(e: &mut Entity) -> NameState {
                     operands: _,
                     ty: _,
                     loc: _,
+                    verilog_attrs: _,
                 }) => state.push(name),
                 crate::Statement::Register(Register {
                     name,
                     ty: _,
                     clock: _,
+                    clock_edge: _,
                     reset: _,
+                    reset_style: _,
                     initial: _,
                     value: _,
                     loc: _,
                     traced: _,
+                    verilog_attrs: _,
                 }) => state.push(name),
                 crate::Statement::Constant(_, _, _) => {}
                 crate::Statement::Assert(_) => {}
+                crate::Statement::Property(_) => {}
                 crate::Statement::Set {
                     target: _,
                     value: _,
@@ -150,6 +156,7 @@ pub fn make_names_predictable(e: &mut Entity) -> NameState {
             output,
             output_type: _,
             statements,
+            verilog_attrs: _,
         } = e;
 
         for MirInput {
@@ -172,6 +179,7 @@ pub fn make_names_predictable(e: &mut Entity) -> NameState {
                     operands,
                     ty: _,
                     loc: _,
+                    verilog_attrs: _,
                 }) => {
                     *name = state.get(name);
 
@@ -183,11 +191,14 @@ pub fn make_names_predictable(e: &mut Entity) -> NameState {
                     name,
                     ty: _,
                     clock,
+                    clock_edge: _,
                     reset,
+                    reset_style: _,
                     initial: _,
                     value,
                     loc: _,
                     traced,
+                    verilog_attrs: _,
                 }) => {
                     *name = state.get(name);
                     *clock = state.get(clock);
@@ -200,6 +211,23 @@ pub fn make_names_predictable(e: &mut Entity) -> NameState {
                 }
                 crate::Statement::Constant(_, _, _) => {}
                 crate::Statement::Assert(val) => val.inner = state.get(val),
+                crate::Statement::Property(Property {
+                    kind: _,
+                    clock,
+                    reset,
+                    antecedent,
+                    consequent,
+                    loc: _,
+                }) => {
+                    *clock = state.get(clock);
+                    if let Some(rst) = reset {
+                        *rst = state.get(rst);
+                    }
+                    if let Some((val, _)) = antecedent {
+                        *val = state.get(val);
+                    }
+                    *consequent = state.get(consequent);
+                }
                 crate::Statement::Set { target, value } => {
                     target.inner = state.get(target);
                     value.inner = state.get(value);
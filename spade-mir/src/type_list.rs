@@ -44,6 +44,7 @@ impl TypeList {
                     self.inner.insert(ValueName::Expr(*idx), ty.clone());
                 }
                 Statement::Assert(_) => {}
+                Statement::Property(_) => {}
                 Statement::Set { .. } => {
                     // No new types introduced
                 }
@@ -3,10 +3,83 @@
 
 use itertools::Itertools;
 use nesty::{code, Code};
-use num::ToPrimitive;
+use num::BigUint;
 use spade_common::num_ext::InfallibleToBigUint;
 
-use crate::{codegen::mangle_input, types::Type, unit_name::UnitNameKind, Entity};
+use crate::{
+    codegen::{mangle_input, mangle_output},
+    types::Type,
+    unit_name::{UnitName, UnitNameKind},
+    Entity,
+};
+
+/// The name of the C++ member verilator generates for the verilog identifier `name`.
+/// Mirrors `AstNode::encodeName` in verilator
+pub fn verilator_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut chars = name.chars().peekable();
+    let mut first = true;
+    while let Some(c) = chars.next() {
+        if (first && c.is_ascii_alphabetic()) || (!first && c.is_ascii_alphanumeric()) {
+            result.push(c);
+        } else if c == '_' {
+            if chars.peek() == Some(&'_') {
+                chars.next();
+                result.push_str("___05F");
+            } else {
+                result.push(c);
+            }
+        } else {
+            result.push_str(&format!("__0{:02x}", c as u32));
+        }
+        first = false;
+    }
+    result
+}
+
+/// The name of the unit in the wrapper class names. The encoding is injective, so units
+/// with different verilog names get different wrappers. It leaves most unescaped names
+/// unchanged, but units whose wrapper name differs from their verilog name must be
+/// verilated with `--prefix V<wrapper name>`
+fn wrapper_name(name: &UnitName) -> String {
+    verilator_name(name.without_escapes())
+}
+
+/// The name used to look up the unit in the compiler state from spade-cxx
+fn spade_name(name: &UnitName) -> String {
+    match &name.kind {
+        UnitNameKind::Unescaped(_) => format!("{}", name.source.1),
+        UnitNameKind::Escaped { name, path: _ } => name.clone(),
+    }
+}
+
+fn cpp_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Code which assigns `value`, a spade::SignalValue, to the verilator port `port`
+fn port_assignment(port: &str, size: &BigUint) -> String {
+    if *size <= 64u32.to_biguint() {
+        format!("{port} = value->as_u64();")
+    } else {
+        code! {
+            [0] "auto words = value->as_u32_chunks();";
+            [0] "for (std::size_t i = 0; i < words.size(); i++) {";
+            [1]     format!("{port}[i] = words[i];");
+            [0] "}";
+        }
+        .to_string()
+    }
+}
+
+/// Code which returns the value of the verilator port `port` as a string of bits
+fn port_bits(port: &str, size: &BigUint) -> String {
+    if *size <= 64u32.to_biguint() {
+        format!("return spade_verilator::bits_from_u64({port}, {size});")
+    } else {
+        format!("return spade_verilator::bits_from_words(&{port}[0], {size});")
+    }
+}
 
 impl Type {
     fn output_wrappers(
@@ -99,29 +172,48 @@ impl Entity {
         let (constructor_calls, fields_in_parent, field_classes): (Vec<_>, Vec<_>, Vec<_>) = self
             .inputs
             .iter()
-            .filter(|f| f.ty.size() != 0u32.to_biguint())
+            .filter(|f| f.ty.size() != 0u32.to_biguint() || matches!(f.ty, Type::Backward(_)))
             .map(|f| {
                 let field_name = &f.name;
-                let field_name_mangled = mangle_input(&f.no_mangle, &f.name);
                 let field_class_name = format!("{class_name}_{field_name}");
 
-                let assignment = if f.ty.size() <= 64u32.to_biguint() {
-                    format!("parent.top->{field_name_mangled} = value->as_u64();")
-                } else {
-                    let size_u64 = f.ty.size().to_u64().expect("Input size does not fit in u64");
-                    code! {
-                        [0] "auto value_split = value->as_u32_chunks();";
-                        [0] (0..(size_u64 / 32))
-                            .map(|i| format!("parent.top->{field_name_mangled}[{i}] = value_split[{i}];"))
-                            .join("\n");
-                        [0] if size_u64 % 32 != 0 {
-                                let idx = size_u64 / 32;
-                                vec![format!("parent.top->{field_name_mangled}[{idx}] = value_split[{idx}];")]
-                            } else {
-                                vec![]
-                            }
-                    }.to_string()
-                };
+                // &mut ports are outputs of the verilated module, so they are read rather than
+                // assigned
+                if let Type::Backward(_) = &f.ty {
+                    let class = code! {
+                        [0] format!("class {field_class_name} {{");
+                        [1]     "public:";
+                        [2]         format!("{field_class_name}({parent_class_name}& parent)");
+                        [3]             ": parent(parent)";
+                        [2]         "{}";
+                        [2]         "bool operator==(std::string const& other) const {";
+                        [3]             format!(r#"auto field = parent.s_ext->backward_port_field("{field_name}");"#);
+                        [3]             format!("auto val = spade::new_bit_string(parent.{field_name}_string_fn());");
+                        [3]             "return parent.s_ext->compare_field(*field, other, *val)->matches();";
+                        [2]         "}";
+                        [2]         "void assert_eq(std::string const& expected, std::string const& source_loc) {";
+                        [3]             format!(r#"auto field = parent.s_ext->backward_port_field("{field_name}");"#);
+                        [3]             format!("auto val = spade::new_bit_string(parent.{field_name}_string_fn());");
+                        [3]             "parent.s_ext->assert_eq(*field, expected, *val, source_loc);";
+                        [2]         "}";
+                        [2]         "std::string spade_repr() {";
+                        [3]             format!(r#"auto field = parent.s_ext->backward_port_field("{field_name}");"#);
+                        [3]             format!("auto val = spade::new_bit_string(parent.{field_name}_string_fn());");
+                        [3]             "return std::string(parent.s_ext->field_value(*field, *val));";
+                        [2]         "}";
+                        [1]     "private:";
+                        [2]         format!("{parent_class_name}& parent;");
+                        [0] "};"
+                    }.to_string();
+
+                    let constructor_call = format!(", {field_name}(new {field_class_name}(parent))");
+                    let field = format!("{field_class_name}* {field_name};");
+                    return (constructor_call, field, class);
+                }
+
+                let field_name_mangled = verilator_name(&mangle_input(&f.no_mangle, &f.name));
+                let assignment =
+                    port_assignment(&format!("parent.top->{field_name_mangled}"), &f.ty.size());
 
                 let class = code! {
                     [0] format!("class {field_class_name} {{");
@@ -179,13 +271,9 @@ impl Entity {
         (pre_declaration, implementation)
     }
 
-    pub fn verilator_wrapper(&self) -> Option<String> {
-        // Units which are mangled have no stable name in verilator, so we won't generate
-        // them
-        let name = match &self.name.kind {
-            UnitNameKind::Unescaped(name) => name,
-            UnitNameKind::Escaped { .. } => return None,
-        };
+    pub fn verilator_wrapper(&self) -> String {
+        let name = wrapper_name(&self.name);
+        let spade_name = cpp_string(&spade_name(&self.name));
 
         let class_name = format!("{name}_spade_t");
         let output_class_name = format!("{class_name}_o");
@@ -200,64 +288,46 @@ impl Entity {
             [1]     if has_output {format!(", o(init_{class_name}_o(this))")} else {String::new()};
             [0]  "{";
             [0] "}";
+            [0] format!("{class_name}(std::string spade_state, V{name}* top)");
+            [1]     format!(": {class_name}(spade_state, {spade_name}, top)");
+            [0] "{}";
         };
 
         let (output_declaration, output_impl) =
             self.output_type
                 .output_wrappers(&class_name, vec![], &output_class_name);
 
-        let size = self.output_type.size();
-        let size_u64 = self
-            .output_type
-            .size()
-            .to_u64()
-            .expect("Output size does not fit in 64 bits");
         let output_string_generator = if !has_output {
             code! {}
-        } else if size <= 64u32.to_biguint() {
-            code! {
-                [0] format!("std::bitset<{size}> bits = this->top->output___05F;");
-                [0] "std::stringstream ss;";
-                [0] "ss << bits;";
-                [0] "return ss.str();";
-            }
         } else {
             code! {
-                [0] "std::bitset<32> bits;";
-                [0] "std::stringstream ss;";
-                [0] if size_u64 % 32 != 0 {
-                        code!{
-                            [0] format!("std::bitset<{}> bits_;", size_u64 % 32);
-                            [0] format!("bits_ = this->top->output___05F[{}];", size_u64 / 32);
-                            [0] format!("ss << bits_;")
-                        }
-                    }
-                    else {
-                        code!{}
-                    };
-                [0] (0..(size / 32u32).to_u64().unwrap())
-                    .rev()
-                    .map(|i| {
-                        code! {
-                            [0] format!("bits = this->top->output___05F[{i}];");
-                            [0] format!("ss << bits;")
-                        }.to_string()
-                    })
-                    .join("\n");
-                [0] "return ss.str();";
+                [0] port_bits(&format!("this->top->{}", verilator_name("output__")), &self.output_type.size())
             }
         };
 
         let output_string_fn = code! {
             [0] "std::string output_string_fn() {";
-            [0]     output_string_generator;
+            [1]     output_string_generator;
             [0] "}";
         };
 
+        let backward_string_fns = self
+            .inputs
+            .iter()
+            .filter(|f| matches!(f.ty, Type::Backward(_)))
+            .map(|f| {
+                let port = verilator_name(&mangle_output(&f.no_mangle, &f.name));
+                code! {
+                    [0] format!("std::string {}_string_fn() {{", f.name);
+                    [1]     port_bits(&format!("this->top->{port}"), &f.ty.backward_size());
+                    [0] "}";
+                }
+                .to_string()
+            })
+            .collect::<Vec<_>>();
+
         let (input_pre, input_impl) = self.input_wrapper(&class_name);
         let class = code! {
-            [0] "#include <sstream>";
-            [0] "#include <bitset>";
             [0] format!("#if __has_include(<V{name}.h>)");
             [0] format!(r#"#include <V{name}.h>"#);
             [0] format!("class {class_name};");
@@ -275,26 +345,116 @@ impl Entity {
             [2]         "rust::Box<spade::SimulationExt> s_ext;";
             [2]         format!("V{name}* top;");
             [2]         output_string_fn;
+            [2]         backward_string_fns;
             [0] "};";
             [0] input_impl;
             [0] output_impl;
             [0] "#endif";
         };
 
-        Some(class.to_string())
+        class.to_string()
+    }
+}
+
+/// Helpers shared by all wrappers for converting between verilator ports and strings of
+/// bits. Wide ports are arrays of 32 bit words with the least significant word first
+fn port_helpers() -> Code {
+    code! {
+        [0] "namespace spade_verilator {";
+        [1]     "inline std::string bits_from_words(const uint32_t* words, std::size_t width) {";
+        [2]         "std::string result;";
+        [2]         "for (std::size_t i = width; i > 0; i--) {";
+        [3]             "result.push_back(((words[(i - 1) / 32] >> ((i - 1) % 32)) & 1) ? '1' : '0');";
+        [2]         "}";
+        [2]         "return result;";
+        [1]     "}";
+        [1]     "inline std::string bits_from_u64(uint64_t value, std::size_t width) {";
+        [2]         "uint32_t words[2] = {uint32_t(value), uint32_t(value >> 32)};";
+        [2]         "return bits_from_words(words, width);";
+        [1]     "}";
+        [0] "}";
+    }
+}
+
+/// A table of the units with wrappers. `spade_name` is the name to pass to
+/// `spade::setup_spade` and `prefix` is the class name verilator must generate for the unit,
+/// i.e. the units must be verilated with `--top-module <verilog_name> --prefix <prefix>`
+fn name_table(entities: &[&Entity]) -> Code {
+    if entities.is_empty() {
+        return code! {};
+    }
+    let entries = entities
+        .iter()
+        .map(|e| {
+            format!(
+                "{{{}, {}, {}}},",
+                cpp_string(&spade_name(&e.name)),
+                cpp_string(e.name.without_escapes()),
+                cpp_string(&format!("V{}", wrapper_name(&e.name)))
+            )
+        })
+        .collect::<Vec<_>>();
+
+    code! {
+        [0] "struct spade_unit_name {";
+        [1]     "const char* spade_name;";
+        [1]     "const char* verilog_name;";
+        [1]     "const char* prefix;";
+        [0] "};";
+        [0] "static const spade_unit_name spade_unit_names[] = {";
+        [1]     entries;
+        [0] "};";
     }
 }
 
 pub fn verilator_wrappers(entities: &[&Entity]) -> String {
     let inner = entities
         .iter()
-        .filter_map(|e| Entity::verilator_wrapper(e))
+        .map(|e| Entity::verilator_wrapper(e))
         .collect::<Vec<_>>();
 
     code! {
         [0] "#pragma once";
         [0] "#include <string>";
+        [0] "#include <cstdint>";
+        [0] port_helpers();
+        [0] name_table(entities);
         [0] inner
     }
     .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unit_name::IntoUnitName;
+
+    #[test]
+    fn verilator_names_are_encoded() {
+        assert_eq!(verilator_name("output__"), "output___05F");
+        assert_eq!(verilator_name("a_i"), "a_i");
+        assert_eq!(verilator_name("a__b_i"), "a___05Fb_i");
+        assert_eq!(verilator_name("a::b"), "a__03a__03ab");
+    }
+
+    #[test]
+    fn escaped_unit_names_are_valid_identifiers() {
+        let name = UnitName::_test_from_strs(&["lib", "unit[12]"]);
+        assert_eq!(wrapper_name(&name), "lib__03a__03aunit__05b12__05d");
+        assert_eq!(spade_name(&name), "lib::unit[12]");
+    }
+
+    #[test]
+    fn escaped_and_unescaped_wrapper_names_do_not_collide() {
+        let pairs = [
+            (UnitName::_test_from_strs(&["a", "b"]), "a__b"),
+            (UnitName::_test_from_strs(&["foo[1]"]), "foo_1"),
+        ];
+        for (escaped, unescaped) in pairs {
+            assert_ne!(
+                wrapper_name(&escaped),
+                wrapper_name(&unescaped._test_into_unit_name())
+            );
+        }
+    }
+}
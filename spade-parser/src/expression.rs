@@ -7,8 +7,8 @@ use spade_macros::trace_parser;
 use crate::error::{ExpectedArgumentList, Result, UnexpectedToken};
 use crate::{lexer::TokenKind, ParseStackEntry, Parser};
 
-#[derive(PartialEq, PartialOrd, Eq, Ord)]
-enum OpBindingPower {
+#[derive(PartialEq, PartialOrd, Eq, Ord, Debug, Clone, Copy)]
+pub enum OpBindingPower {
     None,
     LogicalOr,
     LogicalAnd,
@@ -26,7 +26,9 @@ enum OpBindingPower {
     PrefixUnary,
 }
 
-fn binop_binding_power(op: &BinaryOperator) -> OpBindingPower {
+/// How strongly `op` binds its operands. Operators with higher binding power are applied
+/// before those with lower, and operators with the same binding power are left associative
+pub fn binop_binding_power(op: &BinaryOperator) -> OpBindingPower {
     match op {
         BinaryOperator::Add => OpBindingPower::AddLike,
         BinaryOperator::Sub => OpBindingPower::AddLike,
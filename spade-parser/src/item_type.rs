@@ -59,6 +59,19 @@ impl UnitKindLocal for Option<Loc<UnitKind>> {
         }
     }
 
+    fn allows_property(&self, at: Loc<()>) -> Result<(), Diagnostic> {
+        match self.as_ref().map(|x| x.split_loc_ref()) {
+            Some((UnitKind::Function, kw_loc)) => Err(not_allowed_in_function(
+                "clocked property in function",
+                at,
+                "clocked property",
+                kw_loc,
+            )),
+            Some((UnitKind::Entity | UnitKind::Pipeline(_), _)) => Ok(()),
+            None => Err(bug_no_item_context(at)),
+        }
+    }
+
     fn allows_pipeline_ref(&self, at: Loc<()>) -> Result<(), Diagnostic> {
         match self.as_ref().map(|x| x.split_loc_ref()) {
             Some((UnitKind::Function, kw_loc)) => Err(stage_ref_in("function", at, kw_loc)),
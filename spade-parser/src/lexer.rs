@@ -1,4 +1,4 @@
-use logos::Logos;
+use logos::{Filter, Logos};
 
 use num::BigUint;
 
@@ -53,6 +53,13 @@ pub enum TokenKind {
     })]
     BinInteger((BigUint, LiteralKind)),
 
+    /// A string literal. Escape sequences are not supported
+    #[regex(r#""[^"\n]*""#, |lex| {
+        let slice = lex.slice();
+        slice[1..slice.len()-1].to_string()
+    })]
+    String(String),
+
     #[token("true")]
     True,
     #[token("false")]
@@ -115,6 +122,10 @@ pub enum TokenKind {
     As,
     #[token("assert")]
     Assert,
+    #[token("assume")]
+    Assume,
+    #[token("cover")]
+    Cover,
     #[token("mut")]
     Mut,
     #[token("where")]
@@ -200,6 +211,10 @@ pub enum TokenKind {
     FatArrow,
     #[token("->")]
     SlimArrow,
+    #[token("|->")]
+    OverlappingImplication,
+    #[token("|=>")]
+    NonOverlappingImplication,
     #[token(",")]
     Comma,
     #[token(".")]
@@ -225,6 +240,20 @@ pub enum TokenKind {
     #[regex("//[^\n]*\n", logos::skip)]
     Comment,
 
+    /// A `///` comment documenting the item following it. The text does not include the
+    /// `///`, the space following it or trailing whitespace. Comments starting with `////`
+    /// are normal comments
+    #[regex("///[^\n]*\n?", priority = 10, callback = |lex| {
+        let text = lex.slice().trim_end();
+        if text.starts_with("////") {
+            Filter::Skip
+        } else {
+            let text = &text[3..];
+            Filter::Emit(text.strip_prefix(' ').unwrap_or(text).to_string())
+        }
+    })]
+    DocComment(String),
+
     #[token("/*")]
     BlockCommentStart,
     #[token("*/")]
@@ -240,6 +269,7 @@ impl TokenKind {
             TokenKind::Integer(_) => "integer",
             TokenKind::HexInteger(_) => "hexadecimal integer",
             TokenKind::BinInteger(_) => "binary integer",
+            TokenKind::String(_) => "string",
             TokenKind::True => "true",
             TokenKind::False => "false",
             TokenKind::Low => "LOW",
@@ -269,6 +299,8 @@ impl TokenKind {
             TokenKind::As => "as",
             TokenKind::Use => "use",
             TokenKind::Assert => "assert",
+            TokenKind::Assume => "assume",
+            TokenKind::Cover => "cover",
             TokenKind::Set => "set",
             TokenKind::Mut => "mut",
             TokenKind::Where => "where",
@@ -311,6 +343,8 @@ impl TokenKind {
 
             TokenKind::FatArrow => "=>",
             TokenKind::SlimArrow => "->",
+            TokenKind::OverlappingImplication => "|->",
+            TokenKind::NonOverlappingImplication => "|=>",
             TokenKind::Semi => ";",
             TokenKind::Colon => ":",
             TokenKind::Comma => ",",
@@ -327,12 +361,34 @@ impl TokenKind {
 
             TokenKind::Whitespace => "whitespace",
             TokenKind::Comment => "comment",
+            TokenKind::DocComment(_) => "doc comment",
 
             TokenKind::BlockCommentStart => "/*",
             TokenKind::BlockCommentEnd => "*/",
         }
     }
 
+    /// True if this token can start an expression, but cannot continue one. Used to
+    /// tell the clock of a property apart from a parenthesised expression
+    pub fn starts_property_body(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Identifier(_)
+                | TokenKind::Integer(_)
+                | TokenKind::HexInteger(_)
+                | TokenKind::BinInteger(_)
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Not
+                | TokenKind::Tilde
+                | TokenKind::OpenParen
+                | TokenKind::OpenBrace
+                | TokenKind::If
+                | TokenKind::Match
+                | TokenKind::Instance
+        )
+    }
+
     pub fn is_identifier(&self) -> bool {
         matches!(self, TokenKind::Identifier(_))
     }
@@ -369,6 +425,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn string_literals_work() {
+        let mut lex = TokenKind::lexer(r#""rom/boot.hex""#);
+
+        assert_eq!(
+            lex.next(),
+            Some(Ok(TokenKind::String("rom/boot.hex".to_string())))
+        );
+        assert_eq!(lex.next(), None);
+    }
+
     #[test]
     fn integer_literals_work() {
         let mut lex = TokenKind::lexer("123");
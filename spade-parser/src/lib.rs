@@ -4,6 +4,8 @@ mod expression;
 pub mod item_type;
 pub mod lexer;
 
+pub use expression::{binop_binding_power, OpBindingPower};
+
 use colored::*;
 use itertools::Itertools;
 use local_impl::local_impl;
@@ -12,10 +14,12 @@ use tracing::{debug, event, Level};
 
 use spade_ast::{
     ArgumentList, ArgumentPattern, Attribute, AttributeList, Binding, BitLiteral, Block, CallKind,
-    ComptimeConfig, Enum, Expression, ImplBlock, IntLiteral, Item, Module, ModuleBody,
-    NamedArgument, NamedTurbofish, ParameterList, Pattern, PipelineStageReference, Register,
-    Statement, Struct, TraitDef, TraitSpec, TurbofishInner, TypeDeclKind, TypeDeclaration,
-    TypeExpression, TypeParam, TypeSpec, Unit, UnitHead, UnitKind, UseStatement, WhereClause,
+    ClockEdge, ComptimeConfig, DocComment, Enum, EnumOption, Expression, ImplBlock, Implication,
+    IntLiteral, Item, LintLevel, MemoryInitFormat, Module, ModuleBody, NamedArgument,
+    NamedTurbofish, Parameter, ParameterList, Pattern, PipelineStageReference, Property,
+    PropertyKind, Register, Statement, Struct, TraitDef, TraitSpec, TurbofishInner, TypeDeclKind,
+    TypeDeclaration, TypeExpression, TypeParam, TypeSpec, Unit, UnitHead, UnitKind, UseStatement,
+    VerilogParameterValue, WhereClause,
 };
 use spade_common::location_info::{lspan, AsLabel, FullSpan, HasCodespan, Loc, WithLocation};
 use spade_common::name::{Identifier, Path};
@@ -85,6 +89,9 @@ pub struct Parser<'a> {
     pub parse_stack: Vec<ParseStackEntry>,
     file_id: usize,
     unit_context: Option<Loc<UnitKind>>,
+    /// The `///` comments in front of the next token. Cleared when a token is eaten, so
+    /// doc comments in places where they are not expected are ignored
+    doc_comments: Vec<Loc<String>>,
 }
 
 impl<'a> Parser<'a> {
@@ -96,6 +103,7 @@ impl<'a> Parser<'a> {
             parse_stack: vec![],
             file_id,
             unit_context: None,
+            doc_comments: vec![],
         }
     }
 }
@@ -126,6 +134,17 @@ impl<'a> Parser<'a> {
         }
     }
 
+    #[trace_parser]
+    pub fn string_literal(&mut self) -> Result<Loc<String>> {
+        let token = self.eat_cond(|t| matches!(t, TokenKind::String(_)), "string")?;
+
+        if let TokenKind::String(value) = token.kind {
+            Ok(value.at(self.file_id, &token.span))
+        } else {
+            unreachable!("eat_cond should have checked this");
+        }
+    }
+
     #[trace_parser]
     pub fn path(&mut self) -> Result<Loc<Path>> {
         let mut result = vec![];
@@ -1152,6 +1171,26 @@ impl<'a> Parser<'a> {
         let tok = peek_for!(self, &TokenKind::Assert);
         self.disallow_attributes(attrs, &tok)?;
 
+        // `assert (x) ...` is ambiguous between a clocked property and an immediate
+        // assertion of a parenthesised expression. It is only a property if the
+        // parenthesis is followed by something which can start an expression
+        // but not continue one.
+        if self.peek_kind(&TokenKind::OpenParen)? {
+            let mut speculative = self.clone();
+            if let Ok((clock, reset)) = speculative.property_clock() {
+                let starts_body = speculative
+                    .peek()
+                    .map(|t| t.kind.starts_property_body())
+                    .unwrap_or(false);
+                if starts_body {
+                    *self = speculative;
+                    return self
+                        .property_body(&tok, PropertyKind::Assert, clock, reset)
+                        .map(Some);
+                }
+            }
+        }
+
         let expr = self.expression()?;
 
         Ok(Some(Statement::Assert(expr.clone()).between(
@@ -1161,6 +1200,91 @@ impl<'a> Parser<'a> {
         )))
     }
 
+    #[trace_parser]
+    pub fn assume(&mut self, attrs: &AttributeList) -> Result<Option<Loc<Statement>>> {
+        let tok = peek_for!(self, &TokenKind::Assume);
+        self.disallow_attributes(attrs, &tok)?;
+
+        let (clock, reset) = self.property_clock()?;
+        self.property_body(&tok, PropertyKind::Assume, clock, reset)
+            .map(Some)
+    }
+
+    #[trace_parser]
+    pub fn cover(&mut self, attrs: &AttributeList) -> Result<Option<Loc<Statement>>> {
+        let tok = peek_for!(self, &TokenKind::Cover);
+        self.disallow_attributes(attrs, &tok)?;
+
+        let (clock, reset) = self.property_clock()?;
+        self.property_body(&tok, PropertyKind::Cover, clock, reset)
+            .map(Some)
+    }
+
+    /// Parses the `(clk)` or `(clk, rst)` which precedes the body of a clocked property
+    #[trace_parser]
+    fn property_clock(&mut self) -> Result<(Loc<Expression>, Option<Loc<Expression>>)> {
+        let (result, _) = self.surrounded(
+            &TokenKind::OpenParen,
+            |s| {
+                let clock = s.expression()?;
+                let reset = if s.peek_and_eat(&TokenKind::Comma)?.is_some() {
+                    Some(s.expression()?)
+                } else {
+                    None
+                };
+                Ok((clock, reset))
+            },
+            &TokenKind::CloseParen,
+        )?;
+        Ok(result)
+    }
+
+    /// Parses the body of a clocked property, i.e. `a`, `a |-> b` or `a |=> b`
+    #[trace_parser]
+    fn property_body(
+        &mut self,
+        start_token: &Token,
+        kind: PropertyKind,
+        clock: Loc<Expression>,
+        reset: Option<Loc<Expression>>,
+    ) -> Result<Loc<Statement>> {
+        self.unit_context
+            .allows_property(().at(self.file_id, &start_token.span()))?;
+
+        let first = self.expression()?;
+        let implication = if self
+            .peek_and_eat(&TokenKind::OverlappingImplication)?
+            .is_some()
+        {
+            Some(Implication::Overlapping)
+        } else if self
+            .peek_and_eat(&TokenKind::NonOverlappingImplication)?
+            .is_some()
+        {
+            Some(Implication::NonOverlapping)
+        } else {
+            None
+        };
+
+        let (antecedent, consequent) = match implication {
+            Some(implication) => (Some((first, implication)), self.expression()?),
+            None => (None, first),
+        };
+
+        let loc = ().between(self.file_id, &start_token.span, &consequent);
+        Ok(Statement::Property(
+            Property {
+                kind,
+                clock,
+                reset,
+                antecedent,
+                consequent,
+            }
+            .at_loc(&loc),
+        )
+        .at_loc(&loc))
+    }
+
     #[trace_parser]
     pub fn comptime_statement(&mut self, allow_stages: bool) -> Result<Option<Loc<Statement>>> {
         let inner = |s: &mut Self| s.exhaustive_statements(allow_stages, &TokenKind::CloseBrace);
@@ -1202,6 +1326,8 @@ impl<'a> Parser<'a> {
             &|s| s.declaration(&attrs),
             &|s| s.label(&attrs),
             &|s| s.assert(&attrs),
+            &|s| s.assume(&attrs),
+            &|s| s.cover(&attrs),
             &|s| s.set(&attrs),
             &|s| s.comptime_statement(allow_stages),
         ])?;
@@ -1264,10 +1390,11 @@ impl<'a> Parser<'a> {
     }
 
     #[trace_parser]
-    pub fn parameter(&mut self) -> Result<(AttributeList, Loc<Identifier>, Loc<TypeSpec>)> {
+    pub fn parameter(&mut self) -> Result<Parameter> {
+        let doc = self.doc_comment()?;
         let attrs = self.attributes()?;
         let (name, ty) = self.name_and_type()?;
-        Ok((attrs, name, ty))
+        Ok((doc, attrs, name, ty))
     }
 
     #[trace_parser]
@@ -1293,10 +1420,17 @@ impl<'a> Parser<'a> {
 
     #[tracing::instrument(skip(self))]
     pub fn type_parameter_list(&mut self) -> Result<ParameterList> {
-        Ok(ParameterList::without_self(
-            self.comma_separated(Self::name_and_type, &TokenKind::CloseBrace)
+        let member = |s: &mut Self| {
+            let doc = s.doc_comment()?;
+            let (name, ty) = s.name_and_type()?;
+            Ok((doc, AttributeList::empty(), name, ty))
+        };
+        Ok(ParameterList {
+            self_: None,
+            args: self
+                .comma_separated(member, &TokenKind::CloseBrace)
                 .no_context()?,
-        ))
+        })
     }
 
     #[trace_parser]
@@ -1456,6 +1590,7 @@ impl<'a> Parser<'a> {
 
         Ok(Some(
             UnitHead {
+                doc: None,
                 attributes: attributes.clone(),
                 unit_kind,
                 name,
@@ -1471,7 +1606,7 @@ impl<'a> Parser<'a> {
     fn where_clauses(&mut self) -> Result<Vec<WhereClause>> {
         if let Some(where_kw) = self.peek_and_eat(&TokenKind::Where)? {
             let clauses = self
-                .comma_separated(
+                .token_separated(
                     |s| {
                         if s.peek_cond(|t| matches!(t, &TokenKind::Identifier(_)), "identifier")? {
                             let name = s.path()?;
@@ -1495,7 +1630,11 @@ impl<'a> Parser<'a> {
                                     .token_separated(
                                         Self::path_with_generic_spec,
                                         &TokenKind::Plus,
-                                        vec![TokenKind::Comma, TokenKind::OpenBrace],
+                                        vec![
+                                            TokenKind::Comma,
+                                            TokenKind::OpenBrace,
+                                            TokenKind::Builtin,
+                                        ],
                                     )
                                     .extra_expected(vec!["identifier"])?
                                     .into_iter()
@@ -1516,10 +1655,9 @@ impl<'a> Parser<'a> {
                                 "Comma separated should not show this error",
                             ))
                         }
-                        // NOTE: With this ending symbol we won't support __builtin__ with where
-                        // clauses.
                     },
-                    &TokenKind::OpenBrace,
+                    &TokenKind::Comma,
+                    vec![TokenKind::OpenBrace, TokenKind::Builtin],
                 )
                 .extra_expected(vec!["identifier"])?;
 
@@ -1586,6 +1724,7 @@ impl<'a> Parser<'a> {
         let where_clauses = self.where_clauses()?;
 
         let mut result = TraitDef {
+            doc: None,
             name,
             type_params,
             where_clauses,
@@ -1594,9 +1733,12 @@ impl<'a> Parser<'a> {
 
         self.eat(&TokenKind::OpenBrace)?;
 
-        while let Some(decl) = self.unit_head(&AttributeList::empty())? {
+        let mut doc = self.doc_comment()?;
+        while let Some(mut decl) = self.unit_head(&AttributeList::empty())? {
+            decl.doc = doc;
             result.methods.push(decl);
             self.eat(&TokenKind::Semi)?;
+            doc = self.doc_comment()?;
         }
         let end_token = self.eat(&TokenKind::CloseBrace)?;
 
@@ -1651,6 +1793,7 @@ impl<'a> Parser<'a> {
 
         Ok(Some(
             ImplBlock {
+                doc: None,
                 r#trait,
                 type_params,
                 where_clauses,
@@ -1664,7 +1807,9 @@ impl<'a> Parser<'a> {
     #[trace_parser]
     pub fn impl_body(&mut self) -> Result<Vec<Loc<Unit>>> {
         let mut result = vec![];
-        while let Some(u) = self.unit(&AttributeList::empty())? {
+        let mut doc = self.doc_comment()?;
+        while let Some(mut u) = self.unit(&AttributeList::empty())? {
+            u.head.doc = doc;
             if u.head.unit_kind.is_pipeline() {
                 return Err(Diagnostic::error(
                     u.head.unit_kind.loc(),
@@ -1676,6 +1821,7 @@ impl<'a> Parser<'a> {
             }
 
             result.push(u);
+            doc = self.doc_comment()?;
         }
 
         Ok(result)
@@ -1683,7 +1829,8 @@ impl<'a> Parser<'a> {
 
     #[trace_parser]
     #[tracing::instrument(level = "debug", skip(self))]
-    pub fn enum_option(&mut self) -> Result<(Loc<Identifier>, Option<Loc<ParameterList>>)> {
+    pub fn enum_option(&mut self) -> Result<EnumOption> {
+        let doc = self.doc_comment()?;
         let name = self.identifier()?;
 
         let args = if let Some(start) = self.peek_and_eat(&TokenKind::OpenBrace)? {
@@ -1701,7 +1848,7 @@ impl<'a> Parser<'a> {
             return Err(err);
         };
 
-        Ok((name, args))
+        Ok((doc, name, args))
     }
 
     fn maybe_suggest_brace_enum_variant(&mut self, err: &mut Diagnostic) -> Result<bool> {
@@ -1750,6 +1897,7 @@ impl<'a> Parser<'a> {
         )?;
 
         let result = TypeDeclaration {
+            doc: None,
             name: name.clone(),
             kind: TypeDeclKind::Enum(Enum { name, options }.between(
                 self.file_id,
@@ -1787,6 +1935,7 @@ impl<'a> Parser<'a> {
         let members = members.at_loc(&members_loc);
 
         let result = TypeDeclaration {
+            doc: None,
             name: name.clone(),
             kind: TypeDeclKind::Struct(
                 Struct {
@@ -1834,6 +1983,7 @@ impl<'a> Parser<'a> {
 
         Ok(Some(
             Module {
+                doc: None,
                 name,
                 body: body.between(self.file_id, &open_brace.span, &end.span),
             }
@@ -2040,6 +2190,26 @@ impl<'a> Parser<'a> {
 
         match start.inner.0.as_str() {
             "no_mangle" => Ok(Attribute::NoMangle),
+            "cdc_primitive" => Ok(Attribute::CdcPrimitive),
+            "retime" => Ok(Attribute::Retime),
+            "clock_edge" => {
+                let (edge, _) = self.surrounded(
+                    &TokenKind::OpenParen,
+                    Self::identifier,
+                    &TokenKind::CloseParen,
+                )?;
+                let edge = match edge.inner.0.as_str() {
+                    "rising" => ClockEdge::Rising,
+                    "falling" => ClockEdge::Falling,
+                    "both" => ClockEdge::Both,
+                    _ => {
+                        return Err(Diagnostic::error(&edge, "Invalid parameter for clock_edge")
+                            .primary_label("Invalid parameter")
+                            .note("clock_edge only takes the parameters rising, falling or both"))
+                    }
+                };
+                Ok(Attribute::ClockEdge { edge })
+            }
             "fsm" => {
                 if self.peek_kind(&TokenKind::OpenParen)? {
                     let (state, _) = self.surrounded(
@@ -2100,6 +2270,117 @@ impl<'a> Parser<'a> {
             "wal_suffix" => Ok(attribute_arg_parser!(start, self, s, Attribute::WalSuffix {
                 suffix [required]: {s.identifier()}
             })),
+            "memory_init" => Ok(
+                attribute_arg_parser!(start, self, s, Attribute::MemoryInit {
+                    file [required]: {s.string_literal()},
+                    format: {s.memory_init_format()}
+                }),
+            ),
+            // The parameter names are chosen by the user, so this can not use attribute_arg_parser
+            "verilog_parameters" => {
+                let (params, _) = self.surrounded(
+                    &TokenKind::OpenParen,
+                    |s| {
+                        s.comma_separated(
+                            |s| {
+                                let name = s.identifier()?;
+                                s.eat(&TokenKind::Assignment)?;
+                                let value = s.verilog_parameter_value()?;
+                                Ok((name, value))
+                            },
+                            &TokenKind::CloseParen,
+                        )
+                        .no_context()
+                    },
+                    &TokenKind::CloseParen,
+                )?;
+
+                Ok(Attribute::VerilogParameters { params })
+            }
+            "verilog_attrs" => {
+                let (attrs, _) = self.surrounded(
+                    &TokenKind::OpenParen,
+                    |s| {
+                        s.comma_separated(
+                            |s| {
+                                let name = s.identifier()?;
+                                let value = if s.peek_and_eat(&TokenKind::Assignment)?.is_some() {
+                                    Some(s.verilog_parameter_value()?)
+                                } else {
+                                    None
+                                };
+                                Ok((name, value))
+                            },
+                            &TokenKind::CloseParen,
+                        )
+                        .no_context()
+                    },
+                    &TokenKind::CloseParen,
+                )?;
+
+                Ok(Attribute::VerilogAttrs { attrs })
+            }
+            // The lint names are not known by the parser, so this can not use attribute_arg_parser
+            level @ ("allow" | "warn" | "deny") => {
+                let (lints, _) = self.surrounded(
+                    &TokenKind::OpenParen,
+                    |s| {
+                        s.comma_separated(|s| s.identifier(), &TokenKind::CloseParen)
+                            .no_context()
+                    },
+                    &TokenKind::CloseParen,
+                )?;
+
+                let level = match level {
+                    "allow" => LintLevel::Allow,
+                    "warn" => LintLevel::Warn,
+                    _ => LintLevel::Deny,
+                };
+                Ok(Attribute::LintLevel { level, lints })
+            }
+            // `async` is a rust keyword, so this can not use attribute_arg_parser
+            "reset_style" => {
+                let (flags, _) = self.surrounded(
+                    &TokenKind::OpenParen,
+                    |s| {
+                        s.comma_separated(|s| s.identifier(), &TokenKind::CloseParen)
+                            .no_context()
+                    },
+                    &TokenKind::CloseParen,
+                )?;
+
+                let mut synchronous: Option<Loc<bool>> = None;
+                let mut active_low: Option<Loc<bool>> = None;
+                for flag in flags {
+                    let (field, value) = match flag.inner.0.as_str() {
+                        "sync" => (&mut synchronous, true),
+                        "async" => (&mut synchronous, false),
+                        "active_high" => (&mut active_low, false),
+                        "active_low" => (&mut active_low, true),
+                        _ => {
+                            return Err(Diagnostic::error(&flag, "Invalid parameter for reset_style")
+                                .primary_label("Invalid parameter")
+                                .note(
+                                    "reset_style only takes the parameters sync, async, active_high or active_low",
+                                ))
+                        }
+                    };
+                    if let Some(prev) = field {
+                        return Err(Diagnostic::error(
+                            &flag,
+                            format!("Conflicting reset style {}", flag.inner),
+                        )
+                        .primary_label("Reset style specified more than once")
+                        .secondary_label(*prev, "Previously specified here"));
+                    }
+                    *field = Some(value.at_loc(&flag));
+                }
+
+                Ok(Attribute::Reset {
+                    synchronous: synchronous.map(|s| s.inner),
+                    active_low: active_low.map(|a| a.inner),
+                })
+            }
             other => Err(
                 Diagnostic::error(&start, format!("Unknown attribute '{other}'"))
                     .primary_label("Unrecognised attribute"),
@@ -2107,6 +2388,34 @@ impl<'a> Parser<'a> {
         }
     }
 
+    #[trace_parser]
+    pub fn memory_init_format(&mut self) -> Result<Loc<MemoryInitFormat>> {
+        let format = self.identifier()?;
+        match format.inner.0.as_str() {
+            "hex" => Ok(MemoryInitFormat::Hex.at_loc(&format)),
+            "bin" => Ok(MemoryInitFormat::Bin.at_loc(&format)),
+            "raw" => Ok(MemoryInitFormat::Raw.at_loc(&format)),
+            _ => Err(Diagnostic::error(&format, "Unknown memory init format")
+                .primary_label("Unknown format")
+                .note("Supported formats are hex, bin and raw")),
+        }
+    }
+
+    #[trace_parser]
+    pub fn verilog_parameter_value(&mut self) -> Result<Loc<VerilogParameterValue>> {
+        if let Some(int) = self.int_literal()? {
+            Ok(int.map(|i| VerilogParameterValue::Int(i.as_signed())))
+        } else if self.peek_cond(|t| matches!(t, TokenKind::String(_)), "string")? {
+            Ok(self.string_literal()?.map(VerilogParameterValue::String))
+        } else {
+            let got = self.peek()?;
+            Err(Diagnostic::from(UnexpectedToken {
+                got,
+                expected: vec!["integer", "string"],
+            }))
+        }
+    }
+
     #[trace_parser]
     pub fn attributes(&mut self) -> Result<AttributeList> {
         // peek_for!(self, &TokenKind::Hash)
@@ -2126,8 +2435,15 @@ impl<'a> Parser<'a> {
     #[trace_parser]
     #[tracing::instrument(skip(self))]
     pub fn item(&mut self) -> Result<Option<Item>> {
+        let doc = self.doc_comment()?;
         let attrs = self.attributes()?;
-        self.first_successful(vec![
+        let doc = match (doc, self.doc_comment()?) {
+            (Some(before), Some(after)) => {
+                Some(format!("{}\n{}", before.inner, after.inner).between_locs(&before, &after))
+            }
+            (before, after) => before.or(after),
+        };
+        let item = self.first_successful(vec![
             &|s: &mut Self| s.unit(&attrs).map(|e| e.map(Item::Unit)),
             &|s: &mut Self| s.trait_def(&attrs).map(|e| e.map(Item::TraitDef)),
             &|s: &mut Self| s.impl_block(&attrs).map(|e| e.map(Item::ImplBlock)),
@@ -2135,7 +2451,38 @@ impl<'a> Parser<'a> {
             &|s: &mut Self| s.module(&attrs).map(|e| e.map(Item::Module)),
             &|s: &mut Self| s.r#use(&attrs).map(|e| e.map(Item::Use)),
             &|s: &mut Self| s.comptime_item(&attrs).map(|e| e.map(Item::Config)),
-        ])
+        ])?;
+
+        Ok(item.map(|mut item| {
+            match &mut item {
+                Item::Unit(u) => u.head.doc = doc,
+                Item::TraitDef(t) => t.doc = doc,
+                Item::ImplBlock(i) => i.doc = doc,
+                Item::Type(t) => t.doc = doc,
+                Item::Module(m) => m.doc = doc,
+                // Doc comments on these are ignored like in any other place where they
+                // have no meaning
+                Item::Use(_) | Item::Config(_) => {}
+            }
+            item
+        }))
+    }
+
+    /// The `///` comments in front of the next token, joined into one string
+    #[trace_parser]
+    pub fn doc_comment(&mut self) -> Result<Option<DocComment>> {
+        // Peeking lexes the doc comments in front of the next token
+        self.peek()?;
+        let docs = std::mem::take(&mut self.doc_comments);
+        Ok(match (docs.first(), docs.last()) {
+            (Some(first), Some(last)) => Some(
+                docs.iter()
+                    .map(|doc| doc.inner.as_str())
+                    .join("\n")
+                    .between_locs(first, last),
+            ),
+            _ => None,
+        })
     }
 
     #[trace_parser]
@@ -2370,6 +2717,7 @@ impl<'a> Parser<'a> {
 
         self.parse_stack.push(ParseStackEntry::Ate(food.clone()));
         self.last_token = Some(food.clone());
+        self.doc_comments.clear();
         Ok(food)
     }
 
@@ -2451,10 +2799,18 @@ impl<'a> Parser<'a> {
         }?;
 
         match out.kind {
+            TokenKind::DocComment(text) => {
+                self.doc_comments.push(text.at(self.file_id, &out.span));
+                self.next_token()
+            }
             TokenKind::BlockCommentStart => loop {
                 let next = self.next_token()?;
                 match next.kind {
-                    TokenKind::BlockCommentEnd => break self.next_token(),
+                    TokenKind::BlockCommentEnd => {
+                        // Doc comments inside block comments are not doc comments
+                        self.doc_comments.clear();
+                        break self.next_token();
+                    }
                     TokenKind::Eof => {
                         break Err(Diagnostic::error(next, "Unterminated block comment")
                             .primary_label("Expected */")
@@ -2687,6 +3043,7 @@ mod tests {
         let code = include_str!("../parser_test_code/entity_without_inputs.sp");
         let expected = Unit {
             head: UnitHead {
+                doc: None,
                 attributes: AttributeList::empty(),
                 unit_kind: UnitKind::Entity.nowhere(),
                 name: Identifier("no_inputs".to_string()).nowhere(),
@@ -2726,6 +3083,7 @@ mod tests {
         let code = include_str!("../parser_test_code/entity_with_inputs.sp");
         let expected = Unit {
             head: UnitHead {
+                doc: None,
                 attributes: AttributeList::empty(),
                 unit_kind: UnitKind::Entity.nowhere(),
                 name: ast_ident("with_inputs"),
@@ -2902,6 +3260,7 @@ mod tests {
 
         let e1 = Unit {
             head: UnitHead {
+                doc: None,
                 attributes: AttributeList::empty(),
                 unit_kind: UnitKind::Entity.nowhere(),
                 name: Identifier("e1".to_string()).nowhere(),
@@ -2922,6 +3281,7 @@ mod tests {
 
         let e2 = Unit {
             head: UnitHead {
+                doc: None,
                 attributes: AttributeList::empty(),
                 unit_kind: UnitKind::Entity.nowhere(),
                 name: Identifier("e2".to_string()).nowhere(),
@@ -2956,12 +3316,14 @@ mod tests {
         "#;
 
         let expected = ImplBlock {
+            doc: None,
             r#trait: None,
             type_params: None,
             where_clauses: vec![],
             target: ast_type_spec("SomeType"),
             units: vec![Unit {
                 head: UnitHead {
+                    doc: None,
                     attributes: AttributeList::empty(),
                     unit_kind: UnitKind::Function.nowhere(),
                     name: ast_ident("some_fn"),
@@ -2992,12 +3354,14 @@ mod tests {
         "#;
 
         let expected = ImplBlock {
+            doc: None,
             r#trait: Some(ast_trait_spec("SomeTrait", None)),
             type_params: None,
             where_clauses: vec![],
             target: ast_type_spec("SomeType"),
             units: vec![Unit {
                 head: UnitHead {
+                    doc: None,
                     attributes: AttributeList::empty(),
                     unit_kind: UnitKind::Function.nowhere(),
                     name: ast_ident("some_fn"),
@@ -3028,6 +3392,7 @@ mod tests {
         "#;
 
         let expected = ImplBlock {
+            doc: None,
             r#trait: Some(ast_trait_spec(
                 "SomeTrait",
                 Some(vec![ast_type_expr("SomeTypeParam")]),
@@ -3037,6 +3402,7 @@ mod tests {
             target: ast_type_spec("SomeType"),
             units: vec![Unit {
                 head: UnitHead {
+                    doc: None,
                     attributes: AttributeList::empty(),
                     unit_kind: UnitKind::Function.nowhere(),
                     name: ast_ident("some_fn"),
@@ -3145,6 +3511,7 @@ mod tests {
         let expected = Some(
             Unit {
                 head: UnitHead {
+                    doc: None,
                     attributes: AttributeList::empty(),
                     unit_kind: UnitKind::Entity.nowhere(),
                     name: ast_ident("X"),
@@ -3168,6 +3535,7 @@ mod tests {
         let expected = Some(
             Unit {
                 head: UnitHead {
+                    doc: None,
                     attributes: AttributeList::empty(),
                     unit_kind: UnitKind::Function.nowhere(),
                     name: ast_ident("X"),
@@ -3193,6 +3561,7 @@ mod tests {
         let expected = Some(Item::Unit(
             Unit {
                 head: UnitHead {
+                    doc: None,
                     attributes: AttributeList(vec![Attribute::NoMangle.nowhere()]),
                     unit_kind: UnitKind::Function.nowhere(),
                     name: ast_ident("X"),
@@ -3218,6 +3587,7 @@ mod tests {
         let expected = Some(Item::Unit(
             Unit {
                 head: UnitHead {
+                    doc: None,
                     attributes: AttributeList(vec![Attribute::NoMangle.nowhere()]),
                     unit_kind: UnitKind::Entity.nowhere(),
                     name: ast_ident("X"),
@@ -3234,6 +3604,118 @@ mod tests {
         check_parse!(code, item, Ok(expected));
     }
 
+    #[test]
+    fn doc_comments_are_attached_to_units() {
+        let code = r#"
+            /// Does
+            ///  nothing
+            #[no_mangle]
+            /// at all
+            fn X(
+                /// The input
+                a: bool
+            ) __builtin__"#;
+
+        let expected = Some(Item::Unit(
+            Unit {
+                head: UnitHead {
+                    doc: Some("Does\n nothing\nat all".to_string().nowhere()),
+                    attributes: AttributeList(vec![Attribute::NoMangle.nowhere()]),
+                    unit_kind: UnitKind::Function.nowhere(),
+                    name: ast_ident("X"),
+                    inputs: ParameterList {
+                        self_: None,
+                        args: vec![(
+                            Some("The input".to_string().nowhere()),
+                            AttributeList::empty(),
+                            ast_ident("a"),
+                            tspec!("bool"),
+                        )],
+                    }
+                    .nowhere(),
+                    output_type: None,
+                    type_params: None,
+                    where_clauses: vec![],
+                },
+                body: None,
+            }
+            .nowhere(),
+        ));
+
+        check_parse!(code, item, Ok(expected));
+    }
+
+    #[test]
+    fn doc_comments_are_attached_to_fields_and_variants() {
+        let code = r#"
+            /// A struct
+            struct S {
+                /// A field
+                a: bool,
+                b: bool,
+            }
+            enum E {
+                /// A variant
+                A,
+                B,
+            }
+        "#;
+
+        let body = crate::Parser::new(TokenKind::lexer(code), 0)
+            .module_body()
+            .unwrap();
+        let Item::Type(s) = &body.members[0] else {
+            panic!("Expected a struct")
+        };
+        assert_eq!(s.doc, Some("A struct".to_string().nowhere()));
+        let TypeDeclKind::Struct(s) = &s.kind else {
+            panic!("Expected a struct")
+        };
+        let field_docs = s
+            .members
+            .args
+            .iter()
+            .map(|arg| arg.0.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            field_docs,
+            vec![Some("A field".to_string().nowhere()), None]
+        );
+
+        let Item::Type(e) = &body.members[1] else {
+            panic!("Expected an enum")
+        };
+        assert_eq!(e.doc, None);
+        let TypeDeclKind::Enum(e) = &e.kind else {
+            panic!("Expected an enum")
+        };
+        let variant_docs = e
+            .options
+            .iter()
+            .map(|opt| opt.0.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            variant_docs,
+            vec![Some("A variant".to_string().nowhere()), None]
+        );
+    }
+
+    #[test]
+    fn comments_which_are_not_doc_comments_are_ignored() {
+        let code = r#"
+            //// Not a doc comment
+            /*
+            /// Not a doc comment
+            */
+            fn X() __builtin__"#;
+
+        let Ok(Some(Item::Unit(unit))) = crate::Parser::new(TokenKind::lexer(code), 0).item()
+        else {
+            panic!("Expected a unit")
+        };
+        assert_eq!(unit.head.doc, None);
+    }
+
     #[test]
     fn reg_has_fsm_attribute() {
         let code = r#"
@@ -3246,6 +3728,7 @@ mod tests {
         let expected = Some(Item::Unit(
             Unit {
                 head: UnitHead {
+                    doc: None,
                     attributes: AttributeList::empty(),
                     unit_kind: UnitKind::Entity.nowhere(),
                     name: ast_ident("X"),
@@ -3283,6 +3766,129 @@ mod tests {
         check_parse!(code, item, Ok(expected));
     }
 
+    #[test]
+    fn reset_attribute_parses() {
+        check_parse!(
+            "reset_style(sync, active_low)",
+            attribute_inner,
+            Ok(Attribute::Reset {
+                synchronous: Some(true),
+                active_low: Some(true),
+            })
+        );
+        check_parse!(
+            "reset_style(async)",
+            attribute_inner,
+            Ok(Attribute::Reset {
+                synchronous: Some(false),
+                active_low: None,
+            })
+        );
+    }
+
+    #[test]
+    fn memory_init_attribute_parses() {
+        check_parse!(
+            r#"memory_init(file = "boot.bin", format = raw)"#,
+            attribute_inner,
+            Ok(Attribute::MemoryInit {
+                file: "boot.bin".to_string().nowhere(),
+                format: Some(MemoryInitFormat::Raw.nowhere()),
+            })
+        );
+        check_parse!(
+            r#"memory_init(file = "boot.hex")"#,
+            attribute_inner,
+            Ok(Attribute::MemoryInit {
+                file: "boot.hex".to_string().nowhere(),
+                format: None,
+            })
+        );
+    }
+
+    #[test]
+    fn verilog_parameters_attribute_parses() {
+        check_parse!(
+            r#"verilog_parameters(DEPTH = 512, OFFSET = -3, MODE = "FAST",)"#,
+            attribute_inner,
+            Ok(Attribute::VerilogParameters {
+                params: vec![
+                    (
+                        ast_ident("DEPTH"),
+                        VerilogParameterValue::Int(512.to_bigint()).nowhere()
+                    ),
+                    (
+                        ast_ident("OFFSET"),
+                        VerilogParameterValue::Int((-3).to_bigint()).nowhere()
+                    ),
+                    (
+                        ast_ident("MODE"),
+                        VerilogParameterValue::String("FAST".to_string()).nowhere()
+                    ),
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn verilog_attrs_attribute_parses() {
+        check_parse!(
+            r#"verilog_attrs(keep, ASYNC_REG = "TRUE", max_fanout = 4)"#,
+            attribute_inner,
+            Ok(Attribute::VerilogAttrs {
+                attrs: vec![
+                    (ast_ident("keep"), None),
+                    (
+                        ast_ident("ASYNC_REG"),
+                        Some(VerilogParameterValue::String("TRUE".to_string()).nowhere())
+                    ),
+                    (
+                        ast_ident("max_fanout"),
+                        Some(VerilogParameterValue::Int(4.to_bigint()).nowhere())
+                    ),
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn lint_level_attributes_parse() {
+        check_parse!(
+            "allow(unused_variable, shadowed_binding)",
+            attribute_inner,
+            Ok(Attribute::LintLevel {
+                level: LintLevel::Allow,
+                lints: vec![ast_ident("unused_variable"), ast_ident("shadowed_binding")]
+            })
+        );
+        check_parse!(
+            "deny(unread_register)",
+            attribute_inner,
+            Ok(Attribute::LintLevel {
+                level: LintLevel::Deny,
+                lints: vec![ast_ident("unread_register")]
+            })
+        );
+    }
+
+    #[test]
+    fn clock_edge_attribute_parses() {
+        check_parse!(
+            "clock_edge(falling)",
+            attribute_inner,
+            Ok(Attribute::ClockEdge {
+                edge: ClockEdge::Falling
+            })
+        );
+        check_parse!(
+            "clock_edge(both)",
+            attribute_inner,
+            Ok(Attribute::ClockEdge {
+                edge: ClockEdge::Both
+            })
+        );
+    }
+
     #[test]
     fn entity_instantiation() {
         let code = "inst some_entity(x, y, z)";
@@ -3353,14 +3959,20 @@ mod tests {
 
         let expected = Item::Type(
             TypeDeclaration {
+                doc: None,
                 name: ast_ident("State"),
                 kind: TypeDeclKind::Enum(
                     Enum {
                         name: ast_ident("State"),
                         options: vec![
-                            (ast_ident("First"), None),
-                            (ast_ident("Second"), Some(aparams![("a", tspec!("bool")),])),
+                            (None, ast_ident("First"), None),
+                            (
+                                None,
+                                ast_ident("Second"),
+                                Some(aparams![("a", tspec!("bool")),]),
+                            ),
                             (
+                                None,
                                 ast_ident("Third"),
                                 Some(aparams![("a", tspec!("bool")), ("b", tspec!("bool"))]),
                             ),
@@ -3382,6 +3994,7 @@ mod tests {
 
         let expected = Item::Type(
             TypeDeclaration {
+                doc: None,
                 name: ast_ident("State"),
                 kind: TypeDeclKind::Struct(
                     Struct {
@@ -3406,6 +4019,7 @@ mod tests {
 
         let expected = Item::Type(
             TypeDeclaration {
+                doc: None,
                 name: ast_ident("State"),
                 kind: TypeDeclKind::Struct(
                     Struct {
@@ -3506,6 +4120,7 @@ mod tests {
         let expected = ModuleBody {
             members: vec![Item::Module(
                 Module {
+                    doc: None,
                     name: ast_ident("X"),
                     body: ModuleBody { members: vec![] }.nowhere(),
                 }
@@ -3523,10 +4138,12 @@ mod tests {
         let expected = ModuleBody {
             members: vec![Item::Module(
                 Module {
+                    doc: None,
                     name: ast_ident("X"),
                     body: ModuleBody {
                         members: vec![Item::Module(
                             Module {
+                                doc: None,
                                 name: ast_ident("Y"),
                                 body: ModuleBody { members: vec![] }.nowhere(),
                             }
@@ -3581,6 +4198,101 @@ mod tests {
         check_parse!(code, statement(false), Ok(Some(expected)));
     }
 
+    #[test]
+    fn parenthesised_immediate_assertions_parse() {
+        let code = r#"assert (x) == y;"#;
+
+        let expected = Statement::Assert(
+            Expression::BinaryOperator(
+                Box::new(Expression::Identifier(ast_path("x")).nowhere()),
+                BinaryOperator::Equals.nowhere(),
+                Box::new(Expression::Identifier(ast_path("y")).nowhere()),
+            )
+            .nowhere(),
+        )
+        .nowhere();
+
+        check_parse!(code, statement(false), Ok(Some(expected)));
+    }
+
+    #[test]
+    fn clocked_assertions_parse() {
+        let code = r#"assert(clk, rst) a |=> b;"#;
+
+        let expected = Statement::Property(
+            Property {
+                kind: PropertyKind::Assert,
+                clock: Expression::Identifier(ast_path("clk")).nowhere(),
+                reset: Some(Expression::Identifier(ast_path("rst")).nowhere()),
+                antecedent: Some((
+                    Expression::Identifier(ast_path("a")).nowhere(),
+                    Implication::NonOverlapping,
+                )),
+                consequent: Expression::Identifier(ast_path("b")).nowhere(),
+            }
+            .nowhere(),
+        )
+        .nowhere();
+
+        check_parse!(
+            code,
+            statement(false),
+            Ok(Some(expected)),
+            Parser::set_parsing_entity
+        );
+    }
+
+    #[test]
+    fn cover_without_implication_parses() {
+        let code = r#"cover(clk) a;"#;
+
+        let expected = Statement::Property(
+            Property {
+                kind: PropertyKind::Cover,
+                clock: Expression::Identifier(ast_path("clk")).nowhere(),
+                reset: None,
+                antecedent: None,
+                consequent: Expression::Identifier(ast_path("a")).nowhere(),
+            }
+            .nowhere(),
+        )
+        .nowhere();
+
+        check_parse!(
+            code,
+            statement(false),
+            Ok(Some(expected)),
+            Parser::set_parsing_entity
+        );
+    }
+
+    #[test]
+    fn assumptions_with_overlapping_implication_parse() {
+        let code = r#"assume(clk) a |-> b;"#;
+
+        let expected = Statement::Property(
+            Property {
+                kind: PropertyKind::Assume,
+                clock: Expression::Identifier(ast_path("clk")).nowhere(),
+                reset: None,
+                antecedent: Some((
+                    Expression::Identifier(ast_path("a")).nowhere(),
+                    Implication::Overlapping,
+                )),
+                consequent: Expression::Identifier(ast_path("b")).nowhere(),
+            }
+            .nowhere(),
+        )
+        .nowhere();
+
+        check_parse!(
+            code,
+            statement(false),
+            Ok(Some(expected)),
+            Parser::set_parsing_entity
+        );
+    }
+
     #[test]
     fn config_define_works() {
         let code = r#"$config A = 5"#;
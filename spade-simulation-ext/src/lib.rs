@@ -25,14 +25,15 @@ use spade_hir_lowering::monomorphisation::MonoState;
 use spade_hir_lowering::pipelines::MaybePipelineContext;
 use spade_hir_lowering::substitution::Substitutions;
 use spade_hir_lowering::{expr_to_mir, MirLowerable};
-use spade_mir::codegen::mangle_input;
+use spade_mir::codegen::{mangle_input, mangle_output};
 use spade_mir::eval::{eval_statements, Value};
+use spade_mir::renaming::VerilogNameSource;
 use spade_mir::unit_name::InstanceMap;
 use spade_parser::lexer;
 use spade_parser::Parser;
 use spade_typeinference::equation::{TypeVar, TypedExpression};
 use spade_typeinference::traits::TraitImplList;
-use spade_typeinference::{GenericListSource, HasType, TypeState};
+use spade_typeinference::{GenericListSource, GenericListToken, HasType, TypeState};
 use spade_types::ConcreteType;
 use vcd_translate::translation::{self, inner_translate_value};
 
@@ -165,6 +166,9 @@ pub struct Spade {
     /// Type state used for new code written into the context of this struct.
     type_state: TypeState,
     uut_head: UnitHead,
+    /// The generic list used to convert the types of the ports of uut. If uut is generic,
+    /// the generics are bound to the types of the monomorphised instance being tested
+    uut_generic_list: GenericListToken,
     uut_nameid: NameID,
     instance_map: InstanceMap,
     mir_context: HashMap<NameID, MirContext>,
@@ -188,11 +192,24 @@ impl Spade {
         let mut error_buffer = Buffer::ansi();
         let mut diag_handler = DiagHandler::new(Box::new(CodespanEmitter));
 
+        // Monomorphised instances of generic units are referred to by the name of their
+        // verilog module, i.e. `path::to::unit[<id>]`
+        let (uut_path, mono_id) = match uut_name.strip_suffix(']').and_then(|s| s.rsplit_once('['))
+        {
+            Some((path, id)) => {
+                let id = id
+                    .parse::<u64>()
+                    .with_context(|| format!("{uut_name} is not a valid unit name"))?;
+                (path.to_string(), Some(id))
+            }
+            None => (uut_name.clone(), None),
+        };
+
         let file_id = code
             .write()
             .unwrap()
-            .add_file("dut".to_string(), uut_name.clone());
-        let mut parser = Parser::new(lexer::TokenKind::lexer(&uut_name), file_id);
+            .add_file("dut".to_string(), uut_path.clone());
+        let mut parser = Parser::new(lexer::TokenKind::lexer(&uut_path), file_id);
         let uut = parser.path().report_and_convert(
             &mut error_buffer,
             &code.read().unwrap(),
@@ -203,11 +220,31 @@ impl Spade {
             .map_err(Diagnostic::from)
             .report_and_convert(&mut error_buffer, &code.read().unwrap(), &mut diag_handler)?;
 
-        if !uut_head.get_type_params().is_empty() {
-            return Err(anyhow!(
-                "Testing units with generics is currently unsupported"
-            ))?;
-        }
+        let mut type_state = TypeState::new();
+        let (uut_nameid, uut_generic_list) = match mono_id {
+            Some(id) => {
+                let mono_nameid = NameID(id, uut_nameid.1.clone());
+                let generic_list =
+                    Self::mono_generic_list(&mut type_state, &mono_nameid, &uut_head, &state)?;
+                (mono_nameid, generic_list)
+            }
+            None => {
+                if !uut_head.get_type_params().is_empty() {
+                    return Err(anyhow!(
+                        "{uut_name} is generic. Use the name of a monomorphised instance, \
+                        i.e. the name of its verilog module such as {uut_name}[<id>]"
+                    ))?;
+                }
+                let generic_list = type_state.create_generic_list(
+                    GenericListSource::Anonymous,
+                    &[],
+                    &[],
+                    None,
+                    &[],
+                )?;
+                (uut_nameid, generic_list)
+            }
+        };
 
         // Set the namespace of the module
         let namespace = uut.prelude();
@@ -223,7 +260,7 @@ impl Spade {
             code,
             error_buffer,
             diag_handler,
-            type_state: TypeState::new(),
+            type_state,
             owned: Some(OwnedState {
                 symtab,
                 item_list: state.item_list,
@@ -232,6 +269,7 @@ impl Spade {
                 impl_idtracker: state.impl_idtracker,
             }),
             uut_head,
+            uut_generic_list,
             uut_nameid,
             instance_map: state.instance_map,
             mir_context: state.mir_context,
@@ -319,17 +357,12 @@ impl Spade {
             Some(t) => t,
             None => return Ok(None),
         };
-        let generic_list = self.type_state.create_generic_list(
-            GenericListSource::Anonymous,
-            &[],
-            &[],
-            None,
-            &[],
-        )?;
 
-        let ty =
-            self.type_state
-                .type_var_from_hir(output_type.loc(), &output_type, &generic_list)?;
+        let ty = self.type_state.type_var_from_hir(
+            output_type.loc(),
+            &output_type,
+            &self.uut_generic_list,
+        )?;
 
         let owned_state = self.owned.as_ref().unwrap();
         let concrete = TypeState::ungenerify_type(
@@ -374,16 +407,11 @@ impl Spade {
         symtab.new_scope();
         let o_name = symtab.add_local_variable(Identifier("o".to_string()).nowhere());
 
-        let generic_list = self.type_state.create_generic_list(
-            GenericListSource::Anonymous,
-            &[],
-            &[],
-            None,
-            &[],
+        let ty = self.type_state.type_var_from_hir(
+            output_type.loc(),
+            &output_type,
+            &self.uut_generic_list,
         )?;
-        let ty =
-            self.type_state
-                .type_var_from_hir(output_type.loc(), &output_type, &generic_list)?;
 
         // NOTE: safe unwrap, o_name is something we just created, so it can be any type
         let g = self.type_state.new_generic_any();
@@ -419,6 +447,8 @@ impl Spade {
             impl_idtracker: owned_state.impl_idtracker,
             pipeline_ctx: None,
             self_ctx: SelfContext::FreeStanding,
+            reset_style: Default::default(),
+            lints: Default::default(),
         };
         let hir = spade_ast_lowering::visit_expression(&ast, &mut ast_ctx)
             .report_and_convert(&mut self.error_buffer, &self.code, &mut self.diag_handler)?
@@ -490,6 +520,8 @@ impl Spade {
             impl_idtracker,
             pipeline_ctx: _,
             self_ctx: _,
+            reset_style: _,
+            lints: _,
         } = ast_ctx;
 
         self.return_owned(OwnedState {
@@ -515,6 +547,39 @@ impl Spade {
         Ok(result)
     }
 
+    /// Access the value of a `&mut` input port of the DUT. The bits of the field are
+    /// the bits of the corresponding verilog output port
+    pub fn backward_port_field(&mut self, port: &str) -> Result<FieldRef> {
+        let (_, port_ty) = self.get_port(port.into())?;
+        let inner = match &port_ty.inner {
+            TypeSpec::Backward(inner) => inner,
+            _ => return Err(anyhow!("{port} is not a &mut port of {}", self.uut)),
+        };
+
+        let ty = self
+            .type_state
+            .type_var_from_hir(inner.loc(), inner, &self.uut_generic_list)?
+            .get_type(&self.type_state)?;
+
+        let owned_state = self.owned.as_ref().unwrap();
+        let concrete = TypeState::ungenerify_type(
+            &ty,
+            owned_state.symtab.symtab(),
+            &owned_state.item_list.types,
+        )
+        .ok_or_else(|| anyhow!("The type of {port} is not fully known"))?;
+
+        let size = concrete.to_mir_type().size();
+        Ok(FieldRef {
+            range: (
+                0,
+                size.to_u64()
+                    .ok_or(anyhow!("Field index exceeds {} bits", usize::MAX))?,
+            ),
+            ty,
+        })
+    }
+
     // Translate a value from a verilog instance path into a string value
     pub fn translate_value(&self, path: &str, value: &str) -> Result<String> {
         let owned_state = self.owned.as_ref().unwrap();
@@ -549,15 +614,72 @@ impl Spade {
     ) -> Result<(String, spade_mir::eval::Value)> {
         let (port_name, port_ty) = self.get_port(port.into())?;
 
-        let mut type_state = TypeState::new();
-        let generic_list =
-            type_state.create_generic_list(GenericListSource::Anonymous, &[], &[], None, &[])?;
-        let ty = type_state.type_var_from_hir(port_ty.loc(), &port_ty, &generic_list)?;
+        let ty =
+            self.type_state
+                .type_var_from_hir(port_ty.loc(), &port_ty, &self.uut_generic_list)?;
 
         let val = self.compile_expr(expr, &ty)?;
         Ok((port_name, val))
     }
 
+    /// Creates a generic list for the type parameters of `head` where each parameter
+    /// has the type it has in the monomorphised instance `mono_name`. The types are found
+    /// by unifying the ports of `head` with the ports of the instance
+    fn mono_generic_list(
+        type_state: &mut TypeState,
+        mono_name: &NameID,
+        head: &UnitHead,
+        state: &CompilerState,
+    ) -> Result<GenericListToken> {
+        let mir_ctx = state
+            .mir_context
+            .get(mono_name)
+            .ok_or_else(|| anyhow!("Did not find a monomorphised unit named {mono_name}"))?;
+
+        let generic_list = type_state.create_generic_list(
+            GenericListSource::Anonymous,
+            &head.unit_type_params,
+            &head.scope_type_params,
+            None,
+            &[],
+        )?;
+
+        let trait_impls = TraitImplList::new();
+        let ctx = spade_typeinference::Context {
+            symtab: state.symtab.symtab(),
+            items: &state.item_list,
+            trait_impls: &trait_impls,
+        };
+
+        for Parameter {
+            name,
+            ty,
+            no_mangle,
+        } in &head.inputs.0
+        {
+            // Ports with only backward parts have no forward name
+            let source = [
+                mangle_input(no_mangle, &name.0),
+                mangle_output(no_mangle, &name.0),
+            ]
+            .iter()
+            .find_map(|port| mir_ctx.verilog_name_map.lookup_name(port));
+            let mono_ty = match source {
+                Some(VerilogNameSource::ForwardName(n) | VerilogNameSource::BackwardName(n)) => {
+                    mir_ctx.type_map.type_of(&TypedExpression::Name(n.clone()))
+                }
+                _ => None,
+            };
+
+            if let Some(mono_ty) = mono_ty {
+                let port_ty = type_state.type_var_from_hir(ty.loc(), ty, &generic_list)?;
+                type_state.unify(&port_ty, mono_ty, &ctx)?;
+            }
+        }
+
+        Ok(generic_list)
+    }
+
     #[tracing::instrument(level = "trace", skip(symtab, name))]
     fn lookup_function_like(
         name: &Loc<SpadePath>,
@@ -640,6 +762,8 @@ impl Spade {
             impl_idtracker,
             pipeline_ctx: None,
             self_ctx: SelfContext::FreeStanding,
+            reset_style: Default::default(),
+            lints: Default::default(),
         };
 
         let hir = spade_ast_lowering::visit_expression(&ast, &mut ast_ctx)
@@ -653,6 +777,8 @@ impl Spade {
             impl_idtracker,
             pipeline_ctx: _,
             self_ctx: _,
+            reset_style: _,
+            lints: _,
         } = ast_ctx;
 
         let mut symtab = symtab.freeze();
@@ -704,6 +830,7 @@ impl Spade {
             mono_state: &mut MonoState::new(),
             subs: &mut Substitutions::new(),
             diag_handler: &mut self.diag_handler,
+            code: &self.code,
             pipeline_context: &mut MaybePipelineContext::NotPipeline,
             self_mono_item: None,
         };
@@ -1,4 +1,6 @@
-use crate::{build_items, build_items_with_stdlib, code_compiles, snapshot_error};
+use crate::{
+    build_items, build_items_with_stdlib, code_compiles, snapshot_error, snapshot_inference_error,
+};
 
 snapshot_error! {
     impl_method_generic_args_length_does_not_match_trait_method_generic_args_length,
@@ -153,8 +155,9 @@ code_compiles! {
     }"
 }
 
-snapshot_error! {
+snapshot_inference_error! {
     single_identifier_enum_lookups_pass_compiler,
+    "ONE",
     "
     enum X {
         A{x: int<5>},
@@ -582,7 +585,9 @@ snapshot_error! {
     no_mangle_generics,
     "
     #[no_mangle]
-    fn mangling_time<#uint N>() -> int<N> __builtin__
+    fn mangling_time<#uint N>(x: int<N>) -> int<N> {
+        x
+    }
     "
 }
 
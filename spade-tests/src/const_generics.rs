@@ -1,4 +1,4 @@
-use crate::{build_items, snapshot_error};
+use crate::{build_items, snapshot_error, snapshot_inference_error};
 
 #[test]
 fn constrained_ints_in_where_clause_compiles() {
@@ -278,8 +278,9 @@ snapshot_error! {
     "
 }
 
-snapshot_error! {
+snapshot_inference_error! {
     simple_unsatisfied_int_constraint_in_where_clause_errors,
+    "ONE",
     "
         fn add_one<#uint N, #uint O>(in: int<N>) -> int<O>
             where O: { N + 2 }
@@ -324,8 +325,9 @@ snapshot_error! {
     "
 }
 
-snapshot_error! {
+snapshot_inference_error! {
     simple_unsatisfied_int_constraint_in_where_clause_on_impl_block_errors,
+    "ONE",
     "
         impl <#uint N, #uint O> int<N>
         where O: { N + 2 }
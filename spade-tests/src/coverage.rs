@@ -0,0 +1,164 @@
+use spade::compiler_state::CompilerState;
+use spade_common::location_info::WithLocation;
+use spade_common::name::{NameID, Path};
+use spade_mir::coverage::CoverageKind;
+use vcd_translate::coverage::{coverage_from_vcd, CoverageEntry};
+
+use crate::compile_code;
+
+/// Compiles `code` with coverage enabled and returns the compiler state along with
+/// the `main` unit
+fn compile_with_coverage(code: &str) -> (CompilerState, NameID) {
+    let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+    let opts = spade::Opt {
+        coverage: true,
+        ..spade::Opt::new(&mut buffer)
+    };
+    let artefacts = compile_code(&[], code, false, opts)
+        .unwrap_or_else(|| panic!("{}", String::from_utf8_lossy(buffer.as_slice())));
+
+    let (top, _) = artefacts
+        .state
+        .symtab
+        .symtab()
+        .lookup_unit(&Path::from_strs(&["main"]).nowhere())
+        .unwrap();
+    (artefacts.state, top)
+}
+
+/// Writes a VCD of a simulation of `top` with one time step per element of `steps`.
+/// Each step contains the value of the clock `clk` and of the coverage signals
+fn vcd_with_clock(state: &CompilerState, top: &NameID, steps: &[(bool, Vec<bool>)]) -> Vec<u8> {
+    let mut result = vec![];
+    let mut writer = vcd::Writer::new(&mut result);
+    writer.timescale(1, vcd::TimescaleUnit::NS).unwrap();
+    writer.add_module("main").unwrap();
+    let clk = writer.add_wire(1, "clk").unwrap();
+    let ids = state.mir_context[top]
+        .coverage_points
+        .iter()
+        .map(|point| writer.add_wire(1, &point.signal).unwrap())
+        .collect::<Vec<_>>();
+    writer.upscope().unwrap();
+    writer.enddefinitions().unwrap();
+
+    for (time, (clock, values)) in steps.iter().enumerate() {
+        writer.timestamp(time as u64).unwrap();
+        for (id, value) in ids.iter().zip(values) {
+            writer.change_scalar(*id, *value).unwrap();
+        }
+        writer.change_scalar(clk, *clock).unwrap();
+    }
+
+    result
+}
+
+/// Writes a VCD of a simulation of `top` in which the coverage signals have the
+/// values in `samples`, one clock cycle per sample
+fn vcd_of(state: &CompilerState, top: &NameID, samples: &[Vec<bool>]) -> Vec<u8> {
+    let steps = samples
+        .iter()
+        .flat_map(|values| [(false, values.clone()), (true, values.clone())])
+        .collect::<Vec<_>>();
+    vcd_with_clock(state, top, &steps)
+}
+
+#[test]
+fn match_arms_are_reported_on_their_own_lines() {
+    let code = "
+        entity main(x: uint<2>) -> uint<8> {
+            match x {
+                0 => 1,
+                1 => 2,
+                _ => 3,
+            }
+        }
+    ";
+    let (state, top) = compile_with_coverage(code);
+    assert_eq!(state.mir_context[&top].coverage_points.len(), 3);
+
+    // Arm 0 is taken once and the wildcard arm twice
+    let vcd = vcd_of(
+        &state,
+        &top,
+        &[
+            vec![true, false, true],
+            vec![false, false, true],
+            vec![false, false, true],
+        ],
+    );
+    let report = coverage_from_vcd(vcd.as_slice(), &top, "clk", &state).unwrap();
+
+    let entry = |line, arm: &str, cycles| CoverageEntry {
+        file: "testinput".to_string(),
+        line,
+        description: "match",
+        branches: vec![(arm.to_string(), cycles)],
+    };
+    assert_eq!(
+        report,
+        vec![
+            entry(3, "arm 0", 1),
+            entry(4, "arm 1", 0),
+            entry(5, "arm 2", 2)
+        ]
+    );
+}
+
+#[test]
+fn pipeline_enable_muxes_are_not_coverage_points() {
+    let code = "
+        pipeline(2) main(clk: clock, en: bool, x: bool) -> bool {
+                let y = if x { en } else { false };
+            reg[en];
+            reg;
+                y
+        }
+    ";
+    let (state, top) = compile_with_coverage(code);
+
+    let points = &state.mir_context[&top].coverage_points;
+    assert_eq!(
+        points.iter().map(|p| p.kind.clone()).collect::<Vec<_>>(),
+        vec![CoverageKind::If]
+    );
+}
+
+#[test]
+fn branches_are_only_counted_at_rising_clock_edges() {
+    let code = "
+        entity main(x: bool, a: uint<8>, b: uint<8>) -> uint<8> {
+            if x { a } else { b }
+        }
+    ";
+    let (state, top) = compile_with_coverage(code);
+    assert_eq!(state.mir_context[&top].coverage_points.len(), 1);
+
+    let vcd = vcd_with_clock(
+        &state,
+        &top,
+        &[
+            (false, vec![false]),
+            (true, vec![false]),
+            // High after the falling edge, but low again before the next rising edge
+            (false, vec![true]),
+            (false, vec![false]),
+            (false, vec![false]),
+            (true, vec![false]),
+            // Changes at a rising edge are only seen in the next cycle
+            (false, vec![true]),
+            (true, vec![false]),
+        ],
+    );
+    let report = coverage_from_vcd(vcd.as_slice(), &top, "clk", &state).unwrap();
+
+    assert_eq!(
+        report,
+        vec![CoverageEntry {
+            file: "testinput".to_string(),
+            line: 2,
+            description: "if",
+            branches: vec![("true".to_string(), 1), ("false".to_string(), 2)],
+        }]
+    );
+}
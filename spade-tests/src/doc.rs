@@ -0,0 +1,123 @@
+use spade_doc::{generate, Format};
+use spade_hir::ItemList;
+
+use crate::build_artifacts;
+
+const CODE: &str = "
+    /// A module
+    mod m {
+        /// A struct
+        struct S {
+            /// A field
+            a: bool,
+        }
+
+        /// An enum
+        enum E {
+            /// A variant
+            A,
+            B{b: bool},
+        }
+
+        /// A trait
+        trait T {
+            /// A method
+            fn method(self) -> bool;
+        }
+
+        impl T for S {
+            /// The implementation
+            fn method(self) -> bool {
+                self.a
+            }
+        }
+    }
+
+    /// Takes an `S`
+    ///
+    /// and returns a bool
+    fn f(s: m::S) -> bool {
+        s.a
+    }
+";
+
+fn doc_of<'a>(item_list: &'a ItemList, path: &str) -> Option<&'a str> {
+    item_list
+        .docs
+        .iter()
+        .find(|(name, _)| name.1.to_string() == path)
+        .map(|(_, doc)| doc.as_str())
+}
+
+fn member_doc_of<'a>(item_list: &'a ItemList, path: &str, member: &str) -> Option<&'a str> {
+    item_list
+        .member_docs
+        .iter()
+        .find(|((name, m), _)| name.1.to_string() == path && m.0 == member)
+        .map(|(_, doc)| doc.as_str())
+}
+
+fn page<'a>(pages: &'a [(std::path::PathBuf, String)], name: &str) -> &'a str {
+    pages
+        .iter()
+        .find(|(file, _)| file.to_string_lossy() == name)
+        .map(|(_, content)| content.as_str())
+        .unwrap_or_else(|| panic!("No page called {name}"))
+}
+
+#[test]
+fn doc_comments_are_carried_into_the_item_list() {
+    let item_list = build_artifacts(CODE, false).item_list;
+
+    assert_eq!(doc_of(&item_list, "m"), Some("A module"));
+    assert_eq!(doc_of(&item_list, "m::S"), Some("A struct"));
+    assert_eq!(doc_of(&item_list, "m::E"), Some("An enum"));
+    assert_eq!(doc_of(&item_list, "m::E::A"), Some("A variant"));
+    assert_eq!(doc_of(&item_list, "m::E::B"), None);
+    assert_eq!(doc_of(&item_list, "m::T"), Some("A trait"));
+    assert_eq!(
+        doc_of(&item_list, "f"),
+        Some("Takes an `S`\n\nand returns a bool")
+    );
+    assert_eq!(member_doc_of(&item_list, "m::S", "a"), Some("A field"));
+    assert_eq!(
+        member_doc_of(&item_list, "m::T", "method"),
+        Some("A method")
+    );
+}
+
+#[test]
+fn html_documentation_links_between_pages() {
+    let item_list = build_artifacts(CODE, false).item_list;
+    let pages = generate(&item_list, Format::Html);
+
+    let index = page(&pages, "index.html");
+    assert!(index.contains("<a href=\"m.html\">m</a> - A module"));
+    assert!(index.contains("fn f(s: <a href=\"m.html#type.S\">S</a>) -&gt; bool"));
+    assert!(index.contains("<p>Takes an <code>S</code></p>\n<p>and returns a bool</p>"));
+
+    let m = page(&pages, "m.html");
+    assert!(m.contains("<section id=\"type.S\">"));
+    assert!(m.contains("<p>A field</p>"));
+    assert!(m.contains("<p>A variant</p>"));
+    assert!(m.contains("B{b: bool}"));
+    assert!(m.contains("impl <a href=\"m.html#trait.T\">T</a> for <a href=\"m.html#type.S\">S</a>"));
+    assert!(m.contains("<p>The implementation</p>"));
+    assert!(m.contains("<p>A method</p>"));
+}
+
+#[test]
+fn markdown_documentation_links_between_pages() {
+    let item_list = build_artifacts(CODE, false).item_list;
+    let pages = generate(&item_list, Format::Markdown);
+
+    let index = page(&pages, "index.md");
+    assert!(index.contains("- [m](m.md) - A module"));
+    assert!(index.contains("fn f(s: <a href=\"m.md#type.S\">S</a>) -&gt; bool"));
+    assert!(index.contains("Takes an `S`\n\nand returns a bool"));
+
+    let m = page(&pages, "m.md");
+    assert!(m.contains("[crate root](index.md)"));
+    assert!(m.contains("<a id=\"type.S\"></a>\n\n### S"));
+    assert!(m.contains("- <code>a: bool</code>\n\n  A field"));
+}
@@ -0,0 +1,89 @@
+use spade_equiv::blast::unit_key;
+use spade_equiv::{check_equivalence, Outcome};
+use spade_mir::codegen::Codegenable;
+use spade_mir::eval::Value;
+use spade_mir::Entity;
+
+use crate::build_artifacts;
+
+fn top(code: &str) -> Entity {
+    build_artifacts(code, true)
+        .flat_mir_entities
+        .into_iter()
+        .map(|Codegenable(entity)| entity)
+        .find(|entity| unit_key(&entity.name) == "top")
+        .expect("No unit named top")
+}
+
+fn check(left: &str, right: &str) -> Outcome {
+    check_equivalence(&top(left), &top(right)).unwrap()
+}
+
+#[test]
+fn reordered_accumulator_is_equivalent() {
+    let left = r#"
+        entity top(clk: clock, rst: bool, x: uint<8>) -> uint<8> {
+            reg(clk) acc reset(rst: 0) = trunc(acc + x);
+            acc
+        }
+    "#;
+    let right = r#"
+        entity top(clk: clock, rst: bool, x: uint<8>) -> uint<8> {
+            decl acc;
+            let sum = x + acc;
+            reg(clk) acc reset(rst: 0) = trunc(sum);
+            acc
+        }
+    "#;
+    assert_eq!(check(left, right), Outcome::Equivalent);
+}
+
+#[test]
+fn reordered_match_arms_are_equivalent() {
+    let left = r#"
+        enum E { A{x: uint<4>}, B }
+        fn top(e: E) -> uint<4> {
+            match e {
+                E::A(x) => x,
+                E::B => 0,
+            }
+        }
+    "#;
+    let right = r#"
+        enum E { A{x: uint<4>}, B }
+        fn top(e: E) -> uint<4> {
+            match e {
+                E::B => 0,
+                E::A(x) => x,
+            }
+        }
+    "#;
+    assert_eq!(check(left, right), Outcome::Equivalent);
+}
+
+#[test]
+fn off_by_one_comparison_has_counterexample() {
+    let left = r#"
+        fn top(a: int<8>, b: int<8>) -> bool {
+            a < b
+        }
+    "#;
+    let same = r#"
+        fn top(a: int<8>, b: int<8>) -> bool {
+            b > a
+        }
+    "#;
+    let different = r#"
+        fn top(a: int<8>, b: int<8>) -> bool {
+            a <= b
+        }
+    "#;
+    assert_eq!(check(left, same), Outcome::Equivalent);
+    let Outcome::Different(counterexample) = check(left, different) else {
+        panic!("Expected a counterexample")
+    };
+    let inputs = &counterexample.inputs;
+    assert_eq!(inputs[0].value, inputs[1].value, "{counterexample}");
+    assert_eq!(counterexample.differences[0].left, Value::Bit(false));
+    assert_eq!(counterexample.differences[0].right, Value::Bit(true));
+}
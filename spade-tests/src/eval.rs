@@ -0,0 +1,135 @@
+use spade::eval::{EvalFailure, Evaluator};
+use spade_common::name::Path;
+use spade_diagnostics::emitter::CodespanEmitter;
+use spade_diagnostics::DiagHandler;
+
+use crate::compile_code;
+
+/// Compiles `code` in the `proj` namespace and evaluates `expr`, returning the evaluated
+/// value or the error report
+fn eval(code: &str, expr: &str) -> Result<String, String> {
+    let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+    let artefacts = compile_code(&["proj"], code, true, spade::Opt::new(&mut buffer))
+        .unwrap_or_else(|| panic!("{}", String::from_utf8_lossy(buffer.as_slice())));
+
+    let mut evaluator = Evaluator::new(
+        artefacts,
+        Path::from_strs(&["proj"]),
+        DiagHandler::new(Box::new(CodespanEmitter)),
+    );
+    match evaluator.eval(expr, &mut buffer) {
+        Ok(result) => Ok(result.to_string()),
+        Err(EvalFailure::Reported) => Err(String::from_utf8_lossy(buffer.as_slice()).to_string()),
+        Err(EvalFailure::Eval(e)) => Err(e.to_string()),
+    }
+}
+
+const CRC: &str = "
+    fn crc_shift(x: uint<8>) -> uint<8> {
+        if (x & 0x80) != 0 {
+            trunc(x << 1) ^ 0x07
+        } else {
+            trunc(x << 1)
+        }
+    }
+
+    fn crc_step(crc: uint<8>, byte: uint<8>) -> uint<8> {
+        let x2 = crc_shift(crc_shift(crc ^ byte));
+        let x4 = crc_shift(crc_shift(x2));
+        let x6 = crc_shift(crc_shift(x4));
+        crc_shift(crc_shift(x6))
+    }
+
+    fn crc8(bytes: [uint<8>; 3]) -> uint<8> {
+        crc_step(crc_step(crc_step(0, bytes[0]), bytes[1]), bytes[2])
+    }
+
+    fn pick<T>(first: bool, a: T, b: T) -> T {
+        if first {
+            a
+        } else {
+            b
+        }
+    }
+";
+
+#[test]
+fn calls_to_non_generic_functions_are_evaluated() {
+    // CRC-8 with the polynomial 0x07 of [1, 2, 3]
+    assert_eq!(
+        eval(CRC, "lib::crc8([1, 2, 3])"),
+        Ok("72: uint<8>".to_string())
+    );
+}
+
+#[test]
+fn calls_to_turbofished_generic_functions_are_evaluated() {
+    assert_eq!(
+        eval(CRC, "lib::pick::<int<4>>(false, 1, -3)"),
+        Ok("-3: int<4>".to_string())
+    );
+}
+
+#[test]
+fn signed_division_rounds_towards_zero() {
+    let code = "
+        fn half(x: int<8>) -> int<8> {
+            x / 2
+        }
+    ";
+    assert_eq!(eval(code, "proj::half(-7)"), Ok("-3: int<8>".to_string()));
+}
+
+#[test]
+fn compound_values_are_written_as_spade_code() {
+    let code = "
+        struct Point {
+            x: int<8>,
+            y: uint<4>,
+        }
+
+        fn point(x: int<8>) -> (Point, [bool; 2]) {
+            (Point$(x: x, y: 3), [true, false])
+        }
+
+        fn maybe(x: uint<8>) -> Option<uint<8>> {
+            if x > 3 { Some(x) } else { None }
+        }
+    ";
+    assert_eq!(
+        eval(code, "lib::point(-5)"),
+        Ok("(Point$(x: -5, y: 3), [true, false]): (proj::Point, [bool; 2])".to_string())
+    );
+    assert_eq!(
+        eval(code, "lib::maybe(5)"),
+        Ok("Some(5): std::option::Option<uint<8>>".to_string())
+    );
+    assert_eq!(
+        eval(code, "lib::maybe(2)"),
+        Ok("None: std::option::Option<uint<8>>".to_string())
+    );
+}
+
+#[test]
+fn division_by_zero_is_reported() {
+    let code = "
+        fn div(x: uint<8>, y: uint<8>) -> uint<8> {
+            std::ops::comb_div(x, y)
+        }
+    ";
+    assert_eq!(
+        eval(code, "lib::div(1, 0)"),
+        Err("Division by zero in proj::div".to_string())
+    );
+}
+
+#[test]
+fn generic_parameters_must_be_known() {
+    let code = "
+        fn first<T, #uint N>(xs: [T; N]) -> T {
+            xs[0]
+        }
+    ";
+    let report = eval(code, "lib::first([1, 2])").unwrap_err();
+    insta::assert_snapshot!(report);
+}
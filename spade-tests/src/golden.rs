@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+
+use spade::golden::{self, GoldenError, Snapshot};
+
+/// Creates an empty directory containing `files`
+fn golden_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("spade_golden_{name}"));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, content) in files {
+        std::fs::write(dir.join(file), unindent::unindent(content)).unwrap();
+    }
+    dir
+}
+
+const ADDER: &str = "
+    entity adder(a: uint<8>, b: uint<8>) -> uint<9> {
+        a + b
+    }
+";
+
+#[test]
+fn blessed_outputs_match() {
+    let dir = golden_dir("blessed_outputs_match", &[("adder.spade", ADDER)]);
+
+    let blessed = golden::run(&dir, true).unwrap();
+    assert_eq!(blessed.blessed.len(), 3);
+    assert!(dir.join("adder.v").exists());
+    assert!(dir.join("adder.mir").exists());
+    assert!(dir.join("adder.items.ron").exists());
+    assert!(!dir.join("adder.stderr").exists());
+
+    let report = golden::run(&dir, false).unwrap();
+    assert!(report.passed(), "{:?}", report.mismatches);
+    assert_eq!(report.inputs, vec![dir.join("adder.spade")]);
+}
+
+#[test]
+fn snapshots_are_the_compiler_output_files() {
+    let dir = golden_dir("snapshots_are_the_compiler_output_files", &[]);
+    let (verilog, mir) = (dir.join("out.sv"), dir.join("out.mir"));
+    let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+    let opts = spade::Opt {
+        outfile: Some(verilog.clone()),
+        mir_output: Some(mir.clone()),
+        ..spade::Opt::new(&mut buffer)
+    };
+    crate::compile_code(&[], ADDER, true, opts).expect("Failed to compile");
+
+    // The output files also contain the units of the stdlib, which are not snapshotted
+    let outputs = golden::outputs("testinput", &unindent::unindent(ADDER)).unwrap();
+    assert!(std::fs::read_to_string(verilog)
+        .unwrap()
+        .contains(&outputs[&Snapshot::Verilog]));
+    assert!(std::fs::read_to_string(mir)
+        .unwrap()
+        .contains(&outputs[&Snapshot::Mir]));
+}
+
+#[test]
+fn snapshots_only_contain_the_input_file() {
+    let code = "
+        struct Pair { a: uint<8>, b: uint<8> }
+
+        entity adder(p: Pair) -> uint<9> {
+            std::conv::trunc(p.a + p.b + 0)
+        }
+    ";
+    let outputs = golden::outputs("testinput", &unindent::unindent(code)).unwrap();
+
+    for snapshot in [Snapshot::Verilog, Snapshot::Mir, Snapshot::ItemList] {
+        assert!(
+            !outputs[&snapshot].contains("std"),
+            "{snapshot:?} contains the stdlib:\n{}",
+            outputs[&snapshot]
+        );
+    }
+    assert!(outputs[&Snapshot::Verilog].starts_with("module \\adder "));
+    assert!(outputs[&Snapshot::ItemList].contains("\"Pair\""));
+}
+
+#[test]
+fn changed_hardware_is_reported() {
+    let dir = golden_dir("changed_hardware_is_reported", &[("adder.spade", ADDER)]);
+    golden::run(&dir, true).unwrap();
+
+    std::fs::write(
+        dir.join("adder.spade"),
+        "entity adder(a: uint<8>, b: uint<8>) -> uint<9> { a - b }",
+    )
+    .unwrap();
+
+    let report = golden::run(&dir, false).unwrap();
+    let snapshots = report
+        .mismatches
+        .iter()
+        .map(|m| m.snapshot.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(snapshots, vec![dir.join("adder.v"), dir.join("adder.mir")]);
+    let diff = &report.mismatches[0].diff;
+    assert!(diff
+        .lines()
+        .any(|l| l.starts_with('-') && l.contains(r"\a  + \b")));
+    assert!(diff
+        .lines()
+        .any(|l| l.starts_with('+') && l.contains(r"\a  - \b")));
+}
+
+#[test]
+fn diagnostics_are_snapshotted() {
+    let dir = golden_dir(
+        "diagnostics_are_snapshotted",
+        &[(
+            "broken.spade",
+            "
+            fn broken(a: bool) -> uint<8> {
+                a
+            }
+            ",
+        )],
+    );
+
+    golden::run(&dir, true).unwrap();
+    assert!(!dir.join("broken.v").exists());
+    insta::assert_snapshot!(std::fs::read_to_string(dir.join("broken.stderr")).unwrap());
+}
+
+#[test]
+fn fixed_errors_remove_stale_snapshots() {
+    let dir = golden_dir(
+        "fixed_errors_remove_stale_snapshots",
+        &[("adder.spade", "entity adder() -> uint<8> { x }")],
+    );
+    golden::run(&dir, true).unwrap();
+    assert!(dir.join("adder.stderr").exists());
+
+    std::fs::write(dir.join("adder.spade"), unindent::unindent(ADDER)).unwrap();
+    let report = golden::run(&dir, false).unwrap();
+    let missing = report
+        .mismatches
+        .iter()
+        .find(|m| m.snapshot == Snapshot::Diagnostics.path_for(&dir.join("adder.spade")))
+        .expect("Expected the stale diagnostics to be reported");
+    assert!(missing.diff.lines().all(|l| !l.starts_with('+')));
+
+    golden::run(&dir, true).unwrap();
+    assert!(!dir.join("adder.stderr").exists());
+    assert!(golden::run(&dir, false).unwrap().passed());
+}
+
+#[test]
+fn directory_without_inputs_is_an_error() {
+    let dir = golden_dir("directory_without_inputs_is_an_error", &[]);
+    assert!(matches!(
+        golden::run(&dir, false),
+        Err(GoldenError::NoInputs(_))
+    ));
+}
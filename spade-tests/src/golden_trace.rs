@@ -0,0 +1,86 @@
+use spade_common::location_info::WithLocation;
+use spade_common::name::Path;
+use vcd::Value::{V0, V1};
+use vcd_translate::golden::{record_vcd, TraceValue};
+
+use crate::compile_code;
+
+fn value(s: &str) -> TraceValue {
+    TraceValue::Value(s.to_string())
+}
+
+#[test]
+fn trace_is_recorded_at_rising_clock_edges() {
+    let code = "
+        struct P {
+            a: uint<4>,
+            b: bool,
+        }
+
+        entity main(clk: clock, x: uint<4>) -> P {
+            reg(clk) count: uint<4> = x;
+            P$(a: count, b: true)
+        }
+    ";
+    let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+    let artefacts = compile_code(&[], code, false, spade::Opt::new(&mut buffer))
+        .unwrap_or_else(|| panic!("{}", String::from_utf8_lossy(buffer.as_slice())));
+    let state = artefacts.state;
+    let (top, head) = state
+        .symtab
+        .symtab()
+        .lookup_unit(&Path::from_strs(&["main"]).nowhere())
+        .unwrap();
+
+    let register = state.mir_context[&top].registers[0].clone();
+
+    // A simulation in which x is 3 and then 5 at the two rising edges of the clock
+    let mut vcd = vec![];
+    let mut writer = vcd::Writer::new(&mut vcd);
+    writer.timescale(1, vcd::TimescaleUnit::NS).unwrap();
+    writer.add_module("main").unwrap();
+    let clk = writer.add_wire(1, "clk_i").unwrap();
+    let x = writer.add_wire(4, "x_i").unwrap();
+    let count = writer.add_wire(4, &register).unwrap();
+    let output = writer.add_wire(5, "output__").unwrap();
+    writer.upscope().unwrap();
+    writer.enddefinitions().unwrap();
+
+    writer.timestamp(0).unwrap();
+    writer.change_scalar(clk, V0).unwrap();
+    writer.change_vector(x, &[V0, V0, V1, V1]).unwrap();
+    writer.change_vector(count, &[V0]).unwrap();
+    writer.change_vector(output, &[V1]).unwrap();
+    writer.timestamp(1).unwrap();
+    writer.change_scalar(clk, V1).unwrap();
+    writer.change_vector(count, &[V1, V1]).unwrap();
+    writer.change_vector(output, &[V1, V1, V1]).unwrap();
+    writer.timestamp(2).unwrap();
+    writer.change_scalar(clk, V0).unwrap();
+    writer.change_vector(x, &[V0, V1, V0, V1]).unwrap();
+    writer.timestamp(3).unwrap();
+    writer.change_scalar(clk, V1).unwrap();
+    writer.change_vector(count, &[V0, V1, V0, V1]).unwrap();
+    writer.change_vector(output, &[V0, V1, V0, V1, V1]).unwrap();
+
+    let trace = record_vcd(vcd.as_slice(), &top, &head, "clk", &state).unwrap();
+
+    assert_eq!(
+        trace.signals,
+        vec![register, "output__".to_string(), "x_i".to_string()]
+    );
+    let output = |a: &str| {
+        TraceValue::Struct(vec![
+            ("a".to_string(), value(a)),
+            ("b".to_string(), value("true")),
+        ])
+    };
+    // Values are sampled before the changes caused by the clock edge
+    assert_eq!(
+        trace.cycles,
+        vec![
+            vec![value("0"), output("0"), value("3")],
+            vec![value("3"), output("3"), value("5")],
+        ]
+    );
+}
@@ -0,0 +1,96 @@
+use spade_mir::codegen::Codegenable;
+use spade_mir::graph::{dataflow_graph, instance_hierarchy, GraphFormat, NodeKind};
+use spade_mir::Entity;
+
+use crate::build_artifacts;
+
+fn unit<'a>(entities: &'a [&Entity], name: &str) -> &'a Entity {
+    entities
+        .iter()
+        .find(|e| e.name.without_escapes() == name)
+        .unwrap_or_else(|| panic!("No unit named {name}"))
+}
+
+const CODE: &str = r#"
+    entity counter<#uint N>(clk: clock, rst: bool) -> uint<N> {
+        reg(clk) count reset(rst: 0) = trunc(count + 1);
+        count
+    }
+
+    entity top(clk: clock, rst: bool) -> uint<8> {
+        let small = inst counter::<4>(clk, rst);
+        let big = inst counter::<8>(clk, rst);
+        big
+    }
+"#;
+
+#[test]
+fn hierarchy_contains_monomorphised_units() {
+    let artefacts = build_artifacts(CODE, true);
+    let entities = artefacts
+        .flat_mir_entities
+        .iter()
+        .map(|Codegenable(e)| e)
+        .collect::<Vec<_>>();
+
+    let roots = instance_hierarchy(&entities, &artefacts.state.instance_map, Some("top"))
+        .expect("No top unit");
+
+    assert_eq!(roots[0].unit, "top");
+    let children = &roots[0].children;
+    assert_eq!(
+        children
+            .iter()
+            .map(|child| child.instance.as_deref())
+            .collect::<Vec<_>>(),
+        vec![Some("counter_0"), Some("counter_1")]
+    );
+    // Each instance is of its own monomorphised version of counter
+    assert!(children.iter().all(|c| c.unit.starts_with("counter[")));
+    assert_ne!(children[0].unit, children[1].unit);
+}
+
+#[test]
+fn missing_hierarchy_top_is_reported_as_an_error() {
+    let file = std::env::temp_dir().join("spade_missing_hierarchy_top.dot");
+    let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+    let opts = spade::Opt {
+        hierarchy_output: Some((file, GraphFormat::Dot)),
+        hierarchy_top: Some("does_not_exist".to_string()),
+        ..spade::Opt::new(&mut buffer)
+    };
+
+    assert!(crate::compile_code(&[], CODE, true, opts).is_none());
+    let output = std::str::from_utf8(buffer.as_slice()).unwrap();
+    assert!(
+        output.ends_with("Found no unit named does_not_exist to use as the top of the hierarchy\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn dataflow_nodes_are_labelled_with_source_names() {
+    let artefacts = build_artifacts(CODE, true);
+    let entities = artefacts
+        .flat_mir_entities
+        .iter()
+        .map(|Codegenable(e)| e)
+        .collect::<Vec<_>>();
+    let state = &artefacts.state;
+
+    let graph = dataflow_graph(unit(&entities, "top"), &|name| {
+        state.demangle_value_name(name)
+    });
+    let instances = graph
+        .nodes
+        .iter()
+        .filter(|n| n.kind == NodeKind::Instance)
+        .collect::<Vec<_>>();
+    assert_eq!(instances.len(), 2);
+    assert!(
+        instances
+            .iter()
+            .any(|n| n.source_name.as_deref() == Some("big")),
+        "{instances:#?}"
+    );
+}
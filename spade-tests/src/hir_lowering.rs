@@ -3,8 +3,8 @@ use crate::snapshot_error;
 #[cfg(test)]
 mod tests {
     use crate::{
-        build_and_compare_entities, build_entity, build_items, build_items_with_stdlib,
-        snapshot_error,
+        build_and_compare_entities, build_artifacts, build_entity, build_items,
+        build_items_with_stdlib, snapshot_error,
     };
     use colored::Colorize;
     use spade_common::{
@@ -49,7 +49,7 @@ mod tests {
                 "a", n(1, "a"), Type::int(16),
                 "b", n(2, "b"), Type::int(16)
             ) -> Type::int(16); {
-                (e(0); Type::int(16); Select; n(0, "c"), n(1, "a"), n(2, "b"))
+                (e(0); Type::int(16); Select({from_if: true}); n(0, "c"), n(1, "a"), n(2, "b"))
             } => e(0)
         };
 
@@ -549,6 +549,68 @@ mod tests {
         assert_same_mir!(&build_entity!(code), &expected);
     }
 
+    #[test]
+    fn register_reset_style_is_inherited_from_unit() {
+        let code = r#"
+        #[reset_style(sync)]
+        entity name(clk: clock, rst: bool, a: int<16>) -> int<16> {
+            #[reset_style(active_low)]
+            reg(clk) res reset (rst: 0) = a;
+            res
+        }
+        "#;
+
+        let mut expected = entity! {&["name"]; (
+                "clk", n(0, "clk"), Type::Bool,
+                "rst", n(2, "rst"), Type::Bool,
+                "a", n(1, "a"), Type::int(16),
+            ) -> Type::int(16); {
+                (const 0; Type::int(16); ConstantValue::int(0));
+                (reg n(0, "res");
+                    Type::int(16);
+                    clock(n(0, "clk"));
+                    reset (n(2, "rst"), e(0));
+                    n(1, "a"))
+            } => n(0, "res")
+        };
+        for statement in &mut expected.statements {
+            if let spade_mir::Statement::Register(reg) = statement {
+                reg.reset_style = spade_mir::ResetStyle {
+                    synchronous: true,
+                    active_low: true,
+                };
+            }
+        }
+
+        assert_same_mir!(&build_entity!(code), &expected);
+    }
+
+    #[test]
+    fn register_clock_edge_attribute_is_lowered() {
+        let code = r#"
+        entity name(clk: clock, a: int<16>) -> int<16> {
+            #[clock_edge(falling)]
+            reg(clk) res = a;
+            res
+        }
+        "#;
+
+        let mut expected = entity! {&["name"]; (
+                "clk", n(0, "clk"), Type::Bool,
+                "a", n(1, "a"), Type::int(16),
+            ) -> Type::int(16); {
+                (reg n(0, "res"); Type::int(16); clock(n(0, "clk")); n(1, "a"))
+            } => n(0, "res")
+        };
+        for statement in &mut expected.statements {
+            if let spade_mir::Statement::Register(reg) = statement {
+                reg.clock_edge = spade_mir::ClockEdge::Falling;
+            }
+        }
+
+        assert_same_mir!(&build_entity!(code), &expected);
+    }
+
     #[test]
     fn registers_with_initial_work() {
         let code = r#"
@@ -998,6 +1060,87 @@ mod tests {
         assert_same_mir!(&result, &expected);
     }
 
+    #[test]
+    fn retimed_pipelines_balance_stages() {
+        let code = r#"
+            #[retime]
+            pipeline(2) pl(clk: clock, a: int<8>, b: int<8>, c: int<9>) -> int<10> {
+                    let x = a + b;
+                    let y = x + c;
+                reg*2;
+                    y
+            }
+        "#;
+
+        let expected = entity!(&["pl"]; (
+                "clk", n(3, "clk"), Type::Bool,
+                "a", n(0, "a"), Type::int(8),
+                "b", n(1, "b"), Type::int(8),
+                "c", n(2, "c"), Type::int(9),
+            ) -> Type::int(10); {
+                (reg n(12, "s1_c"); Type::int(9); clock(n(3, "clk")); n(2, "c"));
+                (reg n(13, "s1_x"); Type::int(9); clock(n(3, "clk")); n(10, "x"));
+                (reg n(24, "s2_y"); Type::int(10); clock(n(3, "clk")); n(14, "s1_y"));
+                // Stage 0
+                (e(0); Type::int(9); Add; n(0, "a"), n(1, "b"));
+                (n(10, "x"); Type::int(9); Alias; e(0));
+                // Stage 1
+                (e(1); Type::int(10); Add; n(13, "s1_x"), n(12, "s1_c"));
+                (n(14, "s1_y"); Type::int(10); Alias; e(1));
+            } => n(24, "s2_y")
+        );
+
+        let result = build_entity!(code);
+
+        assert_same_mir!(&result, &expected);
+    }
+
+    #[test]
+    fn retiming_keeps_pipeline_references_and_instances_in_place() {
+        let code = r#"
+            pipeline(1) sub(clk: clock, a: int<8>) -> int<8> __builtin__
+
+            #[retime]
+            pipeline(2) pl(clk: clock, a: int<8>, b: int<8>, c: int<9>) -> (int<10>, int<8>, int<8>) {
+                    let x = a + b;
+                    let y = x + c;
+                    let s = inst(1) sub(clk, a);
+                reg*2;
+                    (y, stage(-1).a, s)
+            }
+        "#;
+
+        let inst_name = spade_mir::UnitName::_test_from_strs(&["sub"]);
+        let expected = entity!(&["pl"]; (
+                "clk", n(3, "clk"), Type::Bool,
+                "a", n(0, "a"), Type::int(8),
+                "b", n(1, "b"), Type::int(8),
+                "c", n(2, "c"), Type::int(9),
+            ) -> Type::Tuple(vec![Type::int(10), Type::int(8), Type::int(8)]); {
+                (reg n(11, "s1_a"); Type::int(8); clock(n(3, "clk")); n(0, "a"));
+                (reg n(12, "s1_c"); Type::int(9); clock(n(3, "clk")); n(2, "c"));
+                (reg n(13, "s1_x"); Type::int(9); clock(n(3, "clk")); n(10, "x"));
+                (reg n(24, "s2_y"); Type::int(10); clock(n(3, "clk")); n(14, "s1_y"));
+                (reg n(25, "s2_s"); Type::int(8); clock(n(3, "clk")); n(15, "s"));
+                // Stage 0
+                (e(0); Type::int(9); Add; n(0, "a"), n(1, "b"));
+                (n(10, "x"); Type::int(9); Alias; e(0));
+                // Stage 1
+                (e(1); Type::int(10); Add; n(13, "s1_x"), n(12, "s1_c"));
+                (n(14, "s1_y"); Type::int(10); Alias; e(1));
+                // Stage 0, available in stage 1
+                (e(2); Type::int(8); simple_instance((inst_name, vec!["clk", "a"])); n(3, "clk"), n(0, "a"));
+                (n(15, "s"); Type::int(8); Alias; e(2));
+                // Stage 2
+                (e(3); Type::Tuple(vec![Type::int(10), Type::int(8), Type::int(8)]); ConstructTuple; n(24, "s2_y"), n(11, "s1_a"), n(25, "s2_s"));
+            } => e(3)
+        );
+
+        let result = build_entity!(code);
+
+        assert_same_mir!(&result, &expected);
+    }
+
     #[test]
     fn correct_codegen_for_forward_references() {
         let code = r#"
@@ -1201,7 +1344,7 @@ mod tests {
                 (e(11); Type::Bool; LogicalAnd; e(2), e(10));
                 (const 3; Type::Bool; ConstantValue::Bool(true));
                 (const 5; Type::int(16); ConstantValue::int(0));
-                (e(6); Type::int(16); Match; e(11), n(1, "x"), e(3), e(5));
+                (e(6); Type::int(16); Match({arm_locs: vec![]}); e(11), n(1, "x"), e(3), e(5));
             } => e(6)},
         ];
 
@@ -1225,7 +1368,7 @@ mod tests {
                 (const 3; Type::Bool; ConstantValue::Bool(false));
                 (e(2); Type::Bool; LogicalNot; n(0, "e"));
                 (const 4; Type::Bool; ConstantValue::Bool(true));
-                (e(6); Type::Bool; Match; n(0, "e"), e(3), e(2), e(4));
+                (e(6); Type::Bool; Match({arm_locs: vec![]}); n(0, "e"), e(3), e(2), e(4));
             } => e(6)},
         ];
 
@@ -1251,7 +1394,7 @@ mod tests {
                 (const 4; Type::Bool; ConstantValue::Bool(true));
                 (const 5; Type::Bool; ConstantValue::Bool(true));
                 (const 6; Type::Bool; ConstantValue::Bool(false));
-                (e(6); Type::Bool; Match; e(2), e(4), e(5), e(6));
+                (e(6); Type::Bool; Match({arm_locs: vec![]}); e(2), e(4), e(5), e(6));
             } => e(6)},
         ];
 
@@ -1287,7 +1430,7 @@ mod tests {
                 (const 12; Type::Bool; ConstantValue::Bool(true));
                 (const 13; Type::int(16); ConstantValue::int(2));
                 // Condition for branch 1
-                (e(6); Type::int(16); Match; e(3), e(10), e(5), e(11), e(12), e(13))
+                (e(6); Type::int(16); Match({arm_locs: vec![]}); e(3), e(10), e(5), e(11), e(12), e(13))
             } => e(6)
         };
 
@@ -1326,7 +1469,7 @@ mod tests {
 
                 (const 21; Type::Bool; ConstantValue::Bool(true));
                 (const 5; Type::int(16); ConstantValue::int(0));
-                (e(6); Type::int(16); Match; e(14), e(13), e(20), n(1, "x"), e(21), e(5));
+                (e(6); Type::int(16); Match({arm_locs: vec![]}); e(14), e(13), e(20), n(1, "x"), e(21), e(5));
             } => e(6)},
         ];
 
@@ -1358,7 +1501,7 @@ mod tests {
                 (const 0; Type::int(10); ConstantValue::int(10));
                 (const 4; Type::Bool; ConstantValue::Bool(true));
                 (const 2; Type::int(10); ConstantValue::int(0));
-                (e(3); Type::int(10); Match; e(11), e(0), e(4), e(2));
+                (e(3); Type::int(10); Match({arm_locs: vec![]}); e(11), e(0), e(4), e(2));
             } => e(3)},
         ];
 
@@ -1503,6 +1646,38 @@ mod tests {
         build_and_compare_entities!(code, expected);
     }
 
+    #[test]
+    fn property_statements_lower_correctly() {
+        let code = r#"entity name(clk: clock, x: bool, y: bool) -> bool {
+            assert(clk) x |=> y;
+            x
+        }"#;
+
+        let mut expected = entity! {&["name"]; (
+            "clk", n(0, "clk"), Type::Bool,
+            "x", n(1, "x"), Type::Bool,
+            "y", n(2, "y"), Type::Bool,
+        ) -> Type::Bool; {
+            } => n(1, "x")
+        };
+        expected
+            .statements
+            .push(spade_mir::Statement::Property(spade_mir::Property {
+                kind: spade_mir::PropertyKind::Assert,
+                clock: spade_mir::value_name!(n(0, "clk")),
+                reset: None,
+                antecedent: Some((
+                    spade_mir::value_name!(n(1, "x")),
+                    spade_mir::Implication::NonOverlapping,
+                )),
+                consequent: spade_mir::value_name!(n(2, "y")),
+                loc: None,
+            }));
+
+        let expected = vec![expected];
+        build_and_compare_entities!(code, expected);
+    }
+
     #[test]
     fn div_pow2_works() {
         let code = r#"
@@ -1718,6 +1893,208 @@ mod tests {
         "
     }
 
+    snapshot_error! {
+        builtin_with_unknown_number_generic_is_error,
+        "
+            entity counter<#uint N, #uint W>(clk: clock, x: uint<W>) __builtin__
+
+            entity main(clk: clock) {
+                let _ = inst counter(clk, 0u8);
+            }
+        "
+    }
+
+    #[test]
+    fn verilog_attrs_are_lowered_to_declarations() {
+        let code = r#"
+            use std::mem::clocked_memory;
+
+            #[verilog_attrs(keep_hierarchy)]
+            entity x(clk: clock, a: bool) -> bool {
+                #[verilog_attrs(ASYNC_REG = "TRUE")]
+                reg(clk) r = a;
+                #[verilog_attrs(mark_debug)]
+                let b = !r;
+                #[verilog_attrs(ram_style = "block")]
+                let mem: Memory<bool, 4> = inst clocked_memory(clk, [(false, 0u2, b)]);
+                b
+            }
+        "#;
+
+        let attr = |name: &str, value: Option<&str>| spade_mir::VerilogAttribute {
+            name: name.to_string(),
+            value: value.map(|v| spade_mir::VerilogParameterValue::String(v.to_string())),
+        };
+
+        let result = build_items_with_stdlib(code);
+        let entity = result
+            .iter()
+            .find(|e| e.name.without_escapes() == "x")
+            .unwrap();
+        assert_eq!(entity.verilog_attrs, vec![attr("keep_hierarchy", None)]);
+
+        let attrs = entity
+            .statements
+            .iter()
+            .filter_map(|s| match s {
+                spade_mir::Statement::Binding(b) if !b.verilog_attrs.is_empty() => {
+                    Some((b.operator.to_string(), b.verilog_attrs.clone()))
+                }
+                spade_mir::Statement::Register(r) if !r.verilog_attrs.is_empty() => {
+                    Some(("reg".to_string(), r.verilog_attrs.clone()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            attrs,
+            vec![
+                ("reg".to_string(), vec![attr("ASYNC_REG", Some("TRUE"))]),
+                ("Alias".to_string(), vec![attr("mark_debug", None)]),
+                (
+                    "DeclClockedMemory(1, 2, 1, 4)".to_string(),
+                    vec![attr("ram_style", Some("block"))]
+                ),
+            ]
+        );
+    }
+
+    snapshot_error! {
+        verilog_attrs_on_builtin_is_error,
+        "
+            #[verilog_attrs(keep)]
+            entity x(clk: clock) -> bool __builtin__
+        "
+    }
+
+    snapshot_error! {
+        retime_on_entity_is_error,
+        "
+            #[retime]
+            entity x(clk: clock) -> bool {
+                false
+            }
+        "
+    }
+
+    snapshot_error! {
+        verilog_attrs_on_memory_alias_is_error,
+        "
+            use std::mem::clocked_memory;
+
+            entity x(clk: clock) -> bool {
+                let mem: Memory<bool, 4> = inst clocked_memory(clk, [(false, 0u2, false)]);
+                #[verilog_attrs(ram_style = \"block\")]
+                let alias = mem;
+                true
+            }
+        "
+    }
+
+    #[test]
+    fn builtin_number_generics_are_verilog_parameters() {
+        let code = r#"
+            #[no_mangle]
+            #[verilog_parameters(MODE = "FAST", SEED = -1)]
+            entity fifo<#uint WIDTH>(#[no_mangle] clk: clock) -> uint<WIDTH> __builtin__
+
+            entity x(clk: clock) -> uint<16> {
+                inst fifo::<16>(clk)
+            }
+        "#;
+
+        let inst_name = spade_mir::UnitName {
+            kind: UnitNameKind::Unescaped("fifo".to_string()),
+            source: NameID(0, Path::from_strs(&["fifo"])),
+        };
+
+        let expected = vec![entity! {&["x"]; (
+            "clk", n(0, "clk"), Type::Bool,
+        ) -> Type::uint(16); {
+            (e(0); Type::uint(16); Instance({
+                name: inst_name,
+                params: vec![spade_mir::ParamName {
+                    name: "clk".to_string(),
+                    no_mangle: Some(().nowhere()),
+                }],
+                verilog_params: vec![
+                    spade_mir::VerilogParameter {
+                        name: "WIDTH".to_string(),
+                        value: spade_mir::VerilogParameterValue::Int(16.to_bigint()),
+                    },
+                    spade_mir::VerilogParameter {
+                        name: "MODE".to_string(),
+                        value: spade_mir::VerilogParameterValue::String("FAST".to_string()),
+                    },
+                    spade_mir::VerilogParameter {
+                        name: "SEED".to_string(),
+                        value: spade_mir::VerilogParameterValue::Int((-1).to_bigint()),
+                    },
+                ],
+                loc: None
+            }); n(0, "clk"))
+        } => e(0)}];
+
+        build_and_compare_entities!(code, expected, no_stdlib);
+    }
+
+    #[test]
+    fn builtin_generics_defined_by_where_clauses_are_not_verilog_parameters() {
+        let code = r#"
+            #[no_mangle]
+            entity counter<#uint N, #uint W>(#[no_mangle] clk: clock) -> uint<W>
+                where W: { N + 1 }
+            __builtin__
+
+            entity x(clk: clock) -> uint<5> {
+                inst counter::<4, 5>(clk)
+            }
+        "#;
+
+        let inst_name = spade_mir::UnitName {
+            kind: UnitNameKind::Unescaped("counter".to_string()),
+            source: NameID(0, Path::from_strs(&["counter"])),
+        };
+
+        let expected = vec![entity! {&["x"]; (
+            "clk", n(0, "clk"), Type::Bool,
+        ) -> Type::uint(5); {
+            (e(0); Type::uint(5); Instance({
+                name: inst_name,
+                params: vec![spade_mir::ParamName {
+                    name: "clk".to_string(),
+                    no_mangle: Some(().nowhere()),
+                }],
+                verilog_params: vec![spade_mir::VerilogParameter {
+                    name: "N".to_string(),
+                    value: spade_mir::VerilogParameterValue::Int(4.to_bigint()),
+                }],
+                loc: None
+            }); n(0, "clk"))
+        } => e(0)}];
+
+        build_and_compare_entities!(code, expected, no_stdlib);
+    }
+
+    snapshot_error! {
+        verilog_parameters_on_unit_with_body_is_error,
+        r#"
+            #[verilog_parameters(DEPTH = 4)]
+            entity x() -> bool {
+                true
+            }
+        "#
+    }
+
+    snapshot_error! {
+        verilog_parameter_with_generic_name_is_error,
+        r#"
+            #[verilog_parameters(WIDTH = 4)]
+            entity x<#uint WIDTH>() -> uint<WIDTH> __builtin__
+        "#
+    }
+
     #[test]
     fn instantiating_builtin_generic_pipeline_which_is_non_intrinsic_is_error() {
         let code = "
@@ -2190,6 +2567,210 @@ mod tests {
         "
     }
 
+    #[test]
+    fn memory_init_reads_values_from_file() {
+        let file = std::env::temp_dir().join("spade_memory_init_reads_values_from_file.hex");
+        std::fs::write(&file, "// lookup table\n01 02\n7f_ff\n").unwrap();
+
+        let code = format!(
+            r#"
+            use std::mem::clocked_memory;
+            use std::mem::read_memory;
+
+            entity test(clk: clock, idx: uint<2>) -> uint<16> {{
+                #[memory_init(file = "{}", format = hex)]
+                let mem: Memory<uint<16>, 3> = inst clocked_memory(clk, [(false, idx, 0)]);
+                inst read_memory(mem, idx)
+            }}
+            "#,
+            file.display()
+        );
+
+        let result = build_items_with_stdlib(&code);
+        let initial = result
+            .iter()
+            .flat_map(|e| &e.statements)
+            .find_map(|s| match s {
+                spade_mir::Statement::Binding(spade_mir::Binding {
+                    operator: spade_mir::Operator::DeclClockedMemory { initial, .. },
+                    ..
+                }) => initial.clone(),
+                _ => None,
+            })
+            .expect("Found no initialised memory");
+
+        let values = initial
+            .iter()
+            .map(|v| spade_mir::eval::eval_statements(v).as_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec!["0000000000000001", "0000000000000010", "0111111111111111"]
+        );
+    }
+
+    #[test]
+    fn memory_init_files_are_relative_to_the_source_file() {
+        let dir = std::env::temp_dir().join("spade_memory_init_files_are_relative");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("values.hex"), "01\n02\n").unwrap();
+
+        let code = r#"
+            use std::mem::clocked_memory;
+            use std::mem::read_memory;
+
+            entity test(clk: clock, idx: uint<1>) -> uint<8> {
+                #[memory_init(file = "values.hex", format = hex)]
+                let mem: Memory<uint<8>, 2> = inst clocked_memory(clk, [(false, idx, 0)]);
+                inst read_memory(mem, idx)
+            }
+        "#;
+        let files = vec![(
+            spade::ModuleNamespace {
+                namespace: Path(vec![]),
+                base_namespace: Path(vec![]),
+            },
+            dir.join("top.spade").display().to_string(),
+            unindent::unindent(code),
+        )];
+
+        // The tests run in the directory of the crate, not the directory of the source file
+        let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+        let result = spade::compile(
+            files,
+            true,
+            spade::Opt::new(&mut buffer),
+            spade_diagnostics::DiagHandler::new(Box::new(
+                spade_diagnostics::emitter::CodespanEmitter,
+            )),
+        );
+        assert!(
+            result.is_ok(),
+            "{}",
+            String::from_utf8_lossy(buffer.as_slice())
+        );
+    }
+
+    snapshot_error! {
+        memory_init_with_missing_file_is_error,
+        r#"
+            use std::mem::clocked_memory;
+            use std::mem::read_memory;
+
+            entity test(clk: clock, idx: uint<2>) -> uint<8> {
+                #[memory_init(file = "this_file_does_not_exist.hex")]
+                let mem: Memory<uint<8>, 4> = inst clocked_memory(clk, [(false, idx, 0)]);
+                inst read_memory(mem, idx)
+            }
+        "#
+    }
+
+    snapshot_error! {
+        memory_init_on_non_memory_is_error,
+        r#"
+            entity test(a: uint<8>) -> uint<8> {
+                #[memory_init(file = "this_file_does_not_exist.hex")]
+                let x = a;
+                x
+            }
+        "#
+    }
+
+    snapshot_error! {
+        block_ram_with_bad_write_mask_width_is_error,
+        r#"
+            use std::mem::single_port_ram;
+            use std::mem::ReadDuringWrite;
+
+            entity test(clk: clock, we: uint<3>, addr: uint<4>, data: uint<16>) -> uint<16> {
+                inst single_port_ram::<16, 4, 3, uint<16>>(clk, (we, addr, data), ReadDuringWrite::ReadFirst)
+            }
+        "#
+    }
+
+    /// The Verilog generated for the unit `test` in `code`
+    fn verilog_of_test(code: &str) -> String {
+        build_artifacts(code, true)
+            .module_code
+            .into_iter()
+            .find(|m| m.starts_with("module \\test "))
+            .expect("Found no module for test")
+    }
+
+    #[test]
+    fn clocked_memory_masked_writes_masked_bytes() {
+        let code = r#"
+            use std::mem::clocked_memory_masked;
+            use std::mem::read_memory;
+
+            entity test(clk: clock, we: uint<2>, addr: uint<2>, data: uint<16>) -> uint<16> {
+                let mem: Memory<uint<16>, 4> = inst clocked_memory_masked(clk, [(we, addr, data)]);
+                inst read_memory(mem, addr)
+            }
+        "#;
+
+        insta::assert_snapshot!(verilog_of_test(code));
+    }
+
+    #[test]
+    fn single_port_ram_generates_block_ram() {
+        let code = r#"
+            use std::mem::single_port_ram;
+            use std::mem::ReadDuringWrite;
+
+            entity test(clk: clock, we: uint<2>, addr: uint<4>, data: uint<16>) -> uint<16> {
+                inst single_port_ram::<16, 4, 2, uint<16>>(clk, (we, addr, data), ReadDuringWrite::ReadFirst)
+            }
+        "#;
+
+        insta::assert_snapshot!(verilog_of_test(code));
+    }
+
+    #[test]
+    fn block_ram_mode_can_be_computed_at_compile_time() {
+        let code = r#"
+            use std::mem::single_port_ram;
+            use std::mem::ReadDuringWrite;
+
+            entity test(clk: clock, we: uint<1>, addr: uint<4>, data: uint<16>) -> uint<16> {
+                inst single_port_ram::<16, 4, 1, uint<16>>(
+                    clk,
+                    (we, addr, data),
+                    if true { ReadDuringWrite::NoChange } else { ReadDuringWrite::ReadFirst }
+                )
+            }
+        "#;
+
+        // Only reads in no change mode are conditional
+        assert!(verilog_of_test(code).contains("if (!"));
+        assert!(!verilog_of_test(&code.replace("if true", "if false")).contains("if (!"));
+    }
+
+    #[test]
+    fn true_dual_port_ram_generates_block_ram() {
+        let code = r#"
+            use std::mem::true_dual_port_ram;
+            use std::mem::ReadDuringWrite;
+
+            entity test(
+                clk_a: clock,
+                access_a: (uint<1>, uint<4>, uint<8>),
+                clk_b: clock,
+                access_b: (uint<1>, uint<4>, uint<8>),
+            ) -> (uint<8>, uint<8>) {
+                inst true_dual_port_ram::<16, 4, 1, uint<8>>(
+                    clk_a,
+                    access_a,
+                    clk_b,
+                    access_b,
+                    ReadDuringWrite::WriteFirst
+                )
+            }
+        "#;
+
+        insta::assert_snapshot!(verilog_of_test(code));
+    }
+
     #[test]
     fn port_pair_creation_works() {
         let code = "
@@ -13,18 +13,40 @@ mod compiler_state;
 #[cfg(test)]
 mod const_generics;
 #[cfg(test)]
+mod coverage;
+#[cfg(test)]
+mod doc;
+#[cfg(test)]
+mod equivalence;
+#[cfg(test)]
+mod eval;
+#[cfg(test)]
+mod golden;
+#[cfg(test)]
+mod golden_trace;
+#[cfg(test)]
+mod graph_export;
+#[cfg(test)]
 mod hir_lowering;
 #[cfg(test)]
 mod integration;
 #[cfg(test)]
+mod library;
+#[cfg(test)]
 mod linear_check;
 #[cfg(test)]
+mod lints;
+#[cfg(test)]
+mod mir_parser;
+#[cfg(test)]
 mod parser;
 #[cfg(test)]
 mod ports_integration;
 #[cfg(test)]
 mod suggestions;
 #[cfg(test)]
+mod type_error_chain;
+#[cfg(test)]
 mod typeinference;
 #[cfg(test)]
 mod usefulness;
@@ -93,35 +115,12 @@ macro_rules! snapshot_error {
             let source = unindent::unindent($src);
             let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
             let opts = spade::Opt {
-                error_buffer: &mut buffer,
-                outfile: None,
-                mir_output: None,
-                verilator_wrapper_output: None,
-                state_dump_file: None,
-                item_list_file: None,
                 print_type_traceback: std::env::var("SPADE_TRACE_TYPEINFERENCE").is_ok(),
-                print_parse_traceback: false,
-                wl_infer_method: None,
-                opt_passes: vec![]
+                wl_infer_method: $crate::wl_infer_method_from_env(),
+                ..spade::Opt::new(&mut buffer)
             };
 
-            let files = vec![(
-                    spade::ModuleNamespace {
-                        namespace: spade_common::name::Path(vec![]),
-                        base_namespace: spade_common::name::Path(vec![]),
-                    },
-                    "testinput".to_string(),
-                    source.to_string(),
-                )];
-
-            let _ = spade::compile(
-                files,
-                $include_stdlib,
-                opts,
-                spade_diagnostics::DiagHandler::new(Box::new(
-                    spade_diagnostics::emitter::CodespanEmitter,
-                )),
-            );
+            let _ = $crate::compile_code(&[], &source, $include_stdlib, opts);
 
             insta::with_settings!({
                 // FIXME: Why can't we set 'description => source' here?
@@ -170,40 +169,17 @@ macro_rules! snapshot_inference_error {
             let source = unindent::unindent($src);
             let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
             let opts = spade::Opt {
-                error_buffer: &mut buffer,
-                outfile: None,
-                mir_output: None,
-                verilator_wrapper_output: None,
-                state_dump_file: None,
-                item_list_file: None,
-                print_type_traceback: false,
-                print_parse_traceback: false,
                 wl_infer_method: match $kind {
-                    "AA" => Some(InferMethod::AA),
-                    "IA" => Some(InferMethod::IA),
-                    "AAIA" => Some(InferMethod::AAIA),
+                    "AA" => Some(spade_wordlength_inference::InferMethod::AA),
+                    "IA" => Some(spade_wordlength_inference::InferMethod::IA),
+                    "AAIA" => Some(spade_wordlength_inference::InferMethod::AAIA),
                     "ONE" => None,
                     _ => panic!("Not a valid inference kind: {:?}", $kind),
                 },
+                ..spade::Opt::new(&mut buffer)
             };
 
-            let files = vec![(
-                    spade::ModuleNamespace {
-                        namespace: spade_common::name::Path(vec![]),
-                        base_namespace: spade_common::name::Path(vec![]),
-                    },
-                    "testinput".to_string(),
-                    source.to_string(),
-                )];
-
-            let _ = spade::compile(
-                files,
-                true,
-                opts,
-                spade_diagnostics::DiagHandler::new(Box::new(
-                    spade_diagnostics::emitter::CodespanEmitter,
-                )),
-            );
+            let _ = $crate::compile_code(&[], &source, true, opts);
 
             insta::with_settings!({
                 // FIXME: Why can't we set 'description => source' here?
@@ -245,42 +221,54 @@ fn build_items_inner(code: &str, with_stdlib: bool) -> Vec<spade_mir::Entity> {
     build_artifacts(code, with_stdlib).bumpy_mir_entities
 }
 
-pub fn build_artifacts(code: &str, with_stdlib: bool) -> Artefacts {
-    let source = unindent::unindent(code);
-    let mut buffer = codespan_reporting::term::termcolor::BufferWriter::stdout(
-        codespan_reporting::term::termcolor::ColorChoice::Never,
-    )
-    .buffer();
-    let opts = spade::Opt {
-        error_buffer: &mut buffer,
-        outfile: None,
-        mir_output: None,
-        verilator_wrapper_output: None,
-        state_dump_file: None,
-        item_list_file: None,
-        print_type_traceback: std::env::var("SPADE_TRACE_TYPEINFERENCE").is_ok(),
-        print_parse_traceback: false,
-        wl_infer_method: None,
-        opt_passes: vec![],
-    };
+/// The word length inference method set by `SPADE_INFER_METHOD`, like in the compiler.
+/// This allows running the snapshot tests with word length inference enabled. Tests of
+/// behaviour which word length inference changes use `snapshot_inference_error` instead
+pub fn wl_infer_method_from_env() -> Option<spade_wordlength_inference::InferMethod> {
+    std::env::var("SPADE_INFER_METHOD")
+        .ok()
+        .map(|method| spade::wordlength_inference_method(&method).unwrap())
+}
 
+/// Compiles `code` as the file `testinput` in `namespace`. Returns None if the compilation
+/// fails, in which case the errors are reported to the error buffer of `opts`
+pub fn compile_code(
+    namespace: &[&str],
+    code: &str,
+    with_stdlib: bool,
+    opts: spade::Opt,
+) -> Option<Artefacts> {
     let files = vec![(
         spade::ModuleNamespace {
-            namespace: spade_common::name::Path(vec![]),
-            base_namespace: spade_common::name::Path(vec![]),
+            namespace: spade_common::name::Path::from_strs(namespace),
+            base_namespace: spade_common::name::Path::from_strs(namespace),
         },
         "testinput".to_string(),
-        source,
+        unindent::unindent(code),
     )];
 
-    match spade::compile(
+    spade::compile(
         files,
         with_stdlib,
         opts,
-        spade_diagnostics::DiagHandler::new(Box::new(spade_diagnostics::emitter::CodespanEmitter)),
-    ) {
-        Ok(artefacts) => artefacts,
-        Err(_) => {
+        DiagHandler::new(Box::new(CodespanEmitter)),
+    )
+    .ok()
+}
+
+pub fn build_artifacts(code: &str, with_stdlib: bool) -> Artefacts {
+    let mut buffer = codespan_reporting::term::termcolor::BufferWriter::stdout(
+        codespan_reporting::term::termcolor::ColorChoice::Never,
+    )
+    .buffer();
+    let opts = spade::Opt {
+        print_type_traceback: std::env::var("SPADE_TRACE_TYPEINFERENCE").is_ok(),
+        ..spade::Opt::new(&mut buffer)
+    };
+
+    match compile_code(&[], code, with_stdlib, opts) {
+        Some(artefacts) => artefacts,
+        None => {
             // I'm not 100% sure why this is needed. The bufferwriter should output
             // to stdout and buffer.flush() should be enough. Unfortunately, that does
             // not seem to be the case
@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+
+use spade::library::Library;
+use spade::Artefacts;
+use spade_common::name::Path;
+use spade_mir::codegen::Codegenable;
+
+use crate::compile_code;
+
+fn compile(
+    namespace: &[&str],
+    code: &str,
+    with_stdlib: bool,
+    library: Vec<Library>,
+    library_namespace: Option<&str>,
+    library_output: Option<PathBuf>,
+) -> Option<Artefacts> {
+    let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+    let opts = spade::Opt {
+        library,
+        library_namespace: library_namespace.map(|n| n.to_string()),
+        library_output,
+        ..spade::Opt::new(&mut buffer)
+    };
+
+    let result = compile_code(namespace, code, with_stdlib, opts);
+    if result.is_none() {
+        println!("{}", String::from_utf8_lossy(buffer.as_slice()));
+    }
+    result
+}
+
+/// Builds `code` in the `namespace` namespace as a library with `libraries` linked,
+/// and reads it back
+fn build_library_in(
+    name: &str,
+    namespace: &str,
+    code: &str,
+    with_stdlib: bool,
+    libraries: Vec<Library>,
+) -> Library {
+    let file = std::env::temp_dir().join(format!("spade_library_{name}.spadelib"));
+    compile(
+        &[namespace],
+        code,
+        with_stdlib,
+        libraries,
+        None,
+        Some(file.clone()),
+    )
+    .expect("Failed to build library");
+    Library::read(&file).unwrap()
+}
+
+/// Builds `code` in the `mylib` namespace as a library and reads it back
+fn build_library(name: &str, code: &str, with_stdlib: bool) -> Library {
+    build_library_in(name, "mylib", code, with_stdlib, vec![])
+}
+
+fn has_unit(artefacts: &Artefacts, name: &str) -> bool {
+    artefacts
+        .flat_mir_entities
+        .iter()
+        .any(|Codegenable(e)| e.name.without_escapes() == name)
+}
+
+const LIBRARY: &str = r#"
+    fn double(x: uint<8>) -> uint<9> {
+        x + x
+    }
+"#;
+
+#[test]
+fn linked_libraries_can_be_used_under_a_chosen_namespace() {
+    let library = build_library("namespace", LIBRARY, true);
+    assert!(library.includes_stdlib);
+    assert_eq!(library.namespace, Path::from_strs(&["mylib"]));
+
+    let code = r#"
+        entity top(clk: clock, a: uint<8>) -> uint<9> {
+            let x = dep::double(a);
+            let y = mylib::double(a);
+            reg(clk) r = trunc(x + y);
+            r
+        }
+    "#;
+    let artefacts = compile(&[], code, true, vec![library], Some("dep"), None)
+        .expect("Failed to compile with library");
+    assert!(has_unit(&artefacts, "top"));
+    assert!(has_unit(&artefacts, "mylib::double"));
+}
+
+#[test]
+fn stdlib_can_be_added_to_libraries_built_without_it() {
+    let library = build_library("no_stdlib", LIBRARY, false);
+    assert!(!library.includes_stdlib);
+
+    let code = r#"
+        fn top(a: uint<8>) -> uint<8> {
+            std::conv::trunc(mylib::double(a))
+        }
+    "#;
+    assert!(compile(&[], code, true, vec![library], None, None).is_some());
+}
+
+#[test]
+fn library_namespace_can_not_shadow_existing_names() {
+    let library = build_library("shadow", LIBRARY, true);
+    assert!(compile(&[], "", true, vec![library], Some("trunc"), None).is_none());
+}
+
+#[test]
+fn library_namespace_without_library_is_reported_as_an_error() {
+    let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+    let opts = spade::Opt {
+        library_namespace: Some("mylib".to_string()),
+        ..spade::Opt::new(&mut buffer)
+    };
+
+    assert!(compile_code(&[], "", true, opts).is_none());
+    let output = std::str::from_utf8(buffer.as_slice()).unwrap();
+    assert!(
+        output.ends_with("A library namespace was specified without linking a library\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn lints_allowed_in_libraries_are_not_reported_when_linked() {
+    let library = build_library(
+        "allowed_lints",
+        r#"
+            #[allow(unused_variable)]
+            fn with_unused(x: uint<8>) -> uint<8> {
+                let unused = x;
+                x
+            }
+        "#,
+        true,
+    );
+
+    let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+    let opts = spade::Opt {
+        library: vec![library],
+        ..spade::Opt::new(&mut buffer)
+    };
+    let code = r#"
+        fn top(a: uint<8>) -> uint<8> {
+            mylib::with_unused(a)
+        }
+    "#;
+    assert!(compile_code(&[], code, true, opts).is_some());
+    assert_eq!(String::from_utf8_lossy(buffer.as_slice()), "");
+}
+
+const DEPENDENT_LIBRARY: &str = r#"
+    fn quadruple(x: uint<8>) -> uint<10> {
+        let d = mylib::double(x);
+        d + d
+    }
+"#;
+
+#[test]
+fn libraries_only_contain_their_own_code() {
+    let base = build_library("own_code_base", LIBRARY, true);
+    let dependent = build_library_in(
+        "own_code_dependent",
+        "otherlib",
+        DEPENDENT_LIBRARY,
+        true,
+        vec![build_library("own_code_base_dep", LIBRARY, true)],
+    );
+    assert!(base.includes_stdlib);
+    assert!(!dependent.includes_stdlib);
+
+    let code = r#"
+        fn top(a: uint<8>) -> uint<10> {
+            otherlib::quadruple(a)
+        }
+    "#;
+    let artefacts = compile(&[], code, true, vec![dependent, base], None, None)
+        .expect("Failed to compile with libraries");
+    assert!(has_unit(&artefacts, "otherlib::quadruple"));
+    assert!(has_unit(&artefacts, "mylib::double"));
+}
+
+#[test]
+fn libraries_can_not_be_linked_without_their_dependencies() {
+    let dependent = build_library_in(
+        "missing_dependency",
+        "otherlib",
+        DEPENDENT_LIBRARY,
+        true,
+        vec![build_library("missing_dependency_base", LIBRARY, true)],
+    );
+    assert!(compile(&[], "", true, vec![dependent], None, None).is_none());
+}
+
+#[test]
+fn separately_built_libraries_can_not_be_linked_together() {
+    let first = build_library("separate_first", LIBRARY, true);
+    let second = build_library_in(
+        "separate_second",
+        "otherlib",
+        "fn triple(x: uint<8>) -> uint<10> { zext(x) + zext(x) + zext(x) }",
+        true,
+        vec![],
+    );
+    assert!(compile(&[], "", true, vec![first, second], None, None).is_none());
+}
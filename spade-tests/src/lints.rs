@@ -0,0 +1,264 @@
+#[cfg(test)]
+mod tests {
+    use spade_wordlength_inference::InferMethod;
+
+    use crate::{compile_code, snapshot_error};
+
+    /// Compiles `code` with word length inference and returns the reported diagnostics
+    fn wordlength_diagnostics(code: &str) -> String {
+        let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+        let opts = spade::Opt {
+            wl_infer_method: Some(InferMethod::IA),
+            ..spade::Opt::new(&mut buffer)
+        };
+        let _ = compile_code(&[], code, true, opts);
+        String::from_utf8(buffer.into_inner()).expect("error contains invalid utf-8")
+    }
+
+    snapshot_error! {
+        unused_variables_are_linted,
+        "
+        fn f(a: bool, _b: bool) -> bool {
+            let x = true;
+            let _y = true;
+            false
+        }
+        "
+    }
+
+    snapshot_error! {
+        unused_match_bindings_are_linted,
+        "
+        fn f(x: Option<bool>) -> bool {
+            match x {
+                Some(inner) => true,
+                None => false,
+            }
+        }
+        "
+    }
+
+    snapshot_error! {
+        unused_inst_outputs_are_linted,
+        "
+        entity sub(clk: clock) -> bool {
+            reg(clk) x = x;
+            x
+        }
+
+        entity top(clk: clock) -> bool {
+            let out = inst sub(clk);
+            let _discarded = inst sub(clk);
+            true
+        }
+        "
+    }
+
+    snapshot_error! {
+        unread_registers_are_linted,
+        "
+        entity counter(clk: clock, rst: bool) -> uint<8> {
+            reg(clk) unread: uint<8> reset(rst: 0) = trunc(unread + 1);
+            reg(clk) read: uint<8> reset(rst: 0) = trunc(read + 1);
+            read
+        }
+        "
+    }
+
+    snapshot_error! {
+        pipeline_registers_are_not_unread_registers,
+        "
+        pipeline(2) pipe(clk: clock, a: bool) -> bool {
+                let b = a;
+            reg;
+                let c = b;
+            reg;
+                c
+        }
+        "
+    }
+
+    snapshot_error! {
+        shadowed_bindings_are_linted,
+        "
+        fn f(a: bool) -> bool {
+            let x = a;
+            let x = !x;
+            x
+        }
+        "
+    }
+
+    snapshot_error! {
+        unreachable_match_arms_are_linted,
+        "
+        fn f(a: uint<8>) -> uint<8> {
+            match a {
+                0 => 1,
+                _ => 2,
+                1 => 3,
+            }
+        }
+        "
+    }
+
+    snapshot_error! {
+        allow_silences_lints,
+        "
+        #[allow(unused_variable, shadowed_binding)]
+        fn f(a: bool) -> bool {
+            let x = true;
+            let x = true;
+            true
+        }
+        "
+    }
+
+    snapshot_error! {
+        deny_turns_lints_into_errors,
+        "
+        #[deny(unused_variable)]
+        fn f(a: bool) -> bool {
+            #[allow(unused_variable)]
+            let x = true;
+            let y = true;
+            a
+        }
+        "
+    }
+
+    snapshot_error! {
+        unknown_lints_are_errors,
+        "
+        #[allow(unused_variables)]
+        fn f(a: bool) -> bool {
+            a
+        }
+        "
+    }
+
+    #[test]
+    fn oversized_wordlengths_are_linted() {
+        insta::assert_snapshot!(wordlength_diagnostics(
+            "
+            fn f(a: int<4>, b: int<4>) -> int<16> {
+                sext(a + b)
+            }
+            "
+        ));
+    }
+
+    #[test]
+    fn oversized_wordlengths_can_be_allowed() {
+        let report = wordlength_diagnostics(
+            "
+            #[allow(oversized_wordlength)]
+            fn f(a: int<4>, b: int<4>) -> int<16> {
+                sext(a + b)
+            }
+            ",
+        );
+        assert_eq!(report, "");
+    }
+
+    #[test]
+    fn comparisons_bound_values_in_if_branches() {
+        insta::assert_snapshot!(wordlength_diagnostics(
+            "
+            fn f(a: int<8>) -> int<16> {
+                sext(if a < 0 { 0 } else if 10 <= a { 10 } else { a })
+            }
+            "
+        ));
+    }
+
+    #[test]
+    fn if_branches_are_merged_in_wordlength_inference() {
+        insta::assert_snapshot!(wordlength_diagnostics(
+            "
+            fn f(q: bool) -> int<16> {
+                sext(if q { 3 } else { -2 })
+            }
+            "
+        ));
+    }
+
+    #[test]
+    fn match_arms_are_merged_in_wordlength_inference() {
+        insta::assert_snapshot!(wordlength_diagnostics(
+            "
+            fn f(q: uint<2>) -> int<16> {
+                sext(match q {
+                    0 => 1,
+                    1 => -4,
+                    _ => 2,
+                })
+            }
+            "
+        ));
+    }
+
+    snapshot_error! {
+        clock_domain_crossings_are_linted,
+        "
+        entity main(clk_a: clock, clk_b: clock, x: bool) -> bool {
+            reg(clk_a) a = x;
+            reg(clk_b) b = a;
+            b
+        }
+        "
+    }
+
+    snapshot_error! {
+        clock_domain_crossings_can_be_allowed,
+        "
+        #[allow(clock_domain_crossing)]
+        entity main(clk_a: clock, clk_b: clock, x: bool) -> bool {
+            reg(clk_a) a = x;
+            reg(clk_b) b = a;
+            b
+        }
+        "
+    }
+
+    snapshot_error! {
+        clock_domain_crossings_into_stdlib_units_are_linted,
+        "
+        entity main(clk_a: clock, clk_b: clock, x: bool) -> bool {
+            reg(clk_a) a = x;
+            inst std::io::rising_edge(clk_b, a)
+        }
+        "
+    }
+
+    snapshot_error! {
+        pipelines_which_cannot_be_retimed_are_linted,
+        "
+        #[retime]
+        pipeline(2) pl(clk: clock, a: int<8>) -> int<8> {
+                let x = a;
+            reg;
+                let z = trunc(stage(+1).w + x);
+            reg;
+                let w = z;
+                w
+        }
+        "
+    }
+
+    snapshot_error! {
+        retiming_not_applied_can_be_allowed,
+        "
+        #[retime]
+        #[allow(retiming_not_applied)]
+        pipeline(2) pl(clk: clock, a: int<8>) -> int<8> {
+                let x = a;
+            reg;
+                let z = trunc(stage(+1).w + x);
+            reg;
+                let w = z;
+                w
+        }
+        "
+    }
+}
@@ -0,0 +1,166 @@
+use spade_common::id_tracker::ExprIdTracker;
+use spade_mir::codegen::{entity_code, Codegenable};
+use spade_mir::diff::{compare_entity, VarMap};
+use spade_mir::parser::{parse_entities, parse_entity};
+use spade_mir::passes::mir_passes;
+use spade_mir::unit_name::InstanceMap;
+use spade_mir::{Entity, Statement};
+
+use crate::build_artifacts;
+
+const CODE: &str = r#"
+    use std::mem::clocked_memory;
+
+    struct S { a: int<8>, b: bool }
+    enum E { A{x: int<8>}, B }
+
+    fn f(s: S, e: E) -> int<8> {
+        match e {
+            E::A(x) => trunc(x + s.a),
+            E::B => s.a,
+        }
+    }
+
+    #[verilog_attrs(keep_hierarchy)]
+    entity counter(clk: clock, rst: bool, x: int<8>) -> int<8> {
+        reg(clk) r reset(rst: 0) = trunc(x + r);
+        let t = (r, true);
+        let arr = [r, x];
+        #[verilog_attrs(ram_style = "block")]
+        let mem: Memory<bool, 4> = inst clocked_memory(clk, [(t#1, 0u2, false)]);
+        f(S(arr[1], t#1), E::A(r))
+    }
+
+    pipeline(2) p(clk: clock, #[no_mangle] x: int<8>) -> int<8> {
+            let new = x;
+        reg;
+            let y = inst counter(clk, false, new);
+        reg;
+            stage(-1).y
+    }
+"#;
+
+fn assert_round_trips(entity: &Entity) {
+    let printed = format!("{entity}");
+    let parsed = parse_entity(&printed).unwrap_or_else(|e| panic!("{printed}\n{e}"));
+    assert!(
+        compare_entity(&parsed, entity, &mut VarMap::new()),
+        "Parsed MIR differs from\n{printed}"
+    );
+    assert_eq!(format!("{parsed}"), printed);
+}
+
+#[test]
+fn mir_from_the_compiler_round_trips() {
+    let artefacts = build_artifacts(CODE, true);
+
+    for entity in &artefacts.bumpy_mir_entities {
+        assert_round_trips(entity)
+    }
+    for Codegenable(entity) in &artefacts.flat_mir_entities {
+        assert_round_trips(entity)
+    }
+}
+
+#[test]
+fn parsed_mir_output_generates_the_same_verilog() {
+    let artefacts = build_artifacts(CODE, true);
+
+    let mir_output = artefacts
+        .flat_mir_entities
+        .iter()
+        .map(|e| format!("{}", e.0))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let parsed = parse_entities(&mir_output).unwrap();
+    assert_eq!(parsed.len(), artefacts.flat_mir_entities.len());
+
+    let code = |entity: &Codegenable| {
+        entity_code(entity, &mut InstanceMap::new(), &None)
+            .0
+            .to_string()
+    };
+    for (parsed, original) in parsed.into_iter().zip(&artefacts.flat_mir_entities) {
+        assert_eq!(code(&Codegenable(parsed)), code(original));
+    }
+}
+
+#[test]
+fn passes_transform_handwritten_mir() {
+    let input = parse_entity(
+        r"
+        entity \e ((clk, clk, bool), (rst, rst, bool), (x, x, enum option [], option [int<8>]))
+            -> enum option [], option [int<8>] {
+            let e0: enum option [], option [int<8>] = ConstructEnum(0, 2)()
+            reg(clk) r: enum option [], option [int<8>](rst, e0) = x
+        } => r
+        ",
+    )
+    .unwrap();
+
+    // The tag and payload of the register are stored in separate registers, and the
+    // payload is only updated when the new value has one
+    let expected = parse_entity(
+        r"
+        entity \e ((clk, clk, bool), (rst, rst, bool), (x, x, enum option [], option [int<8>]))
+            -> enum option [], option [int<8>] {
+            let e0: enum option [], option [int<8>] = ConstructEnum(0, 2)()
+            let e13: bool = RangeIndexBits(8, 9)(x)
+            let e14: (int<8>) = RangeIndexBits(0, 8)(x)
+            let e15: bool = RangeIndexBits(8, 9)(e0)
+            let e16: (int<8>) = RangeIndexBits(0, 8)(e0)
+            reg(clk) e10: bool(rst, e15) = e13
+            let e12: (int<8>) = Select(e13, e14, e11)
+            reg(clk) e11: (int<8>)(rst, e16) = e12
+            let r: enum option [], option [int<8>] = Concat(e10, e11)
+        } => r
+        ",
+    )
+    .unwrap();
+
+    let passes = mir_passes();
+    let statements = passes["enum_clock_gating"]
+        .transform_statements(&input.statements, &mut ExprIdTracker::new_at(10));
+    let result = Entity {
+        statements,
+        ..input
+    };
+
+    assert!(
+        compare_entity(&result, &expected, &mut VarMap::new()),
+        "Expected\n{expected}\nGot\n{result}"
+    );
+}
+
+#[test]
+fn gated_registers_keep_their_verilog_attributes() {
+    let input = parse_entity(
+        r"
+        entity \e ((clk, clk, bool), (x, x, enum option [], option [int<8>]))
+            -> enum option [], option [int<8>] {
+            (* keep, max_fanout = 4 *)
+            reg(clk) r: enum option [], option [int<8>] = x
+        } => r
+        ",
+    )
+    .unwrap();
+    let Statement::Register(original) = &input.statements[0] else {
+        panic!("Expected a register")
+    };
+
+    let passes = mir_passes();
+    let statements = passes["enum_clock_gating"]
+        .transform_statements(&input.statements, &mut ExprIdTracker::new_at(10));
+
+    let attrs = statements
+        .iter()
+        .filter_map(|s| match s {
+            Statement::Register(reg) => Some(&reg.verilog_attrs),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        attrs,
+        vec![&original.verilog_attrs, &original.verilog_attrs]
+    );
+}
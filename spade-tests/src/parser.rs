@@ -717,3 +717,22 @@ snapshot_error! {
         }
     "
 }
+
+snapshot_error! {
+    assume_is_not_an_identifier,
+    "
+        entity test(clk: clock) -> bool {
+            let assume = true;
+            assume
+        }
+    "
+}
+
+snapshot_error! {
+    cover_is_not_an_identifier,
+    "
+        fn test(cover: bool) -> bool {
+            cover
+        }
+    "
+}
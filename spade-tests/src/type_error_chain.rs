@@ -0,0 +1,110 @@
+use spade_mir::codegen::Codegenable;
+use spade_typeinference::unification_chain::TypeErrorVerbosity;
+
+use crate::compile_code;
+
+/// Compiles `code`, which is expected to fail, and returns the error report
+fn type_error(code: &str, type_error_verbosity: TypeErrorVerbosity) -> String {
+    let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+    let opts = spade::Opt {
+        type_error_verbosity,
+        ..spade::Opt::new(&mut buffer)
+    };
+
+    let result = compile_code(&[], code, true, opts);
+    assert!(result.is_none(), "Expected a type error");
+    String::from_utf8(buffer.into_inner()).expect("error contains invalid utf-8")
+}
+
+const PROPAGATED_THROUGH_LETS: &str = "
+    fn takes_bool(x: bool) -> bool {
+        x
+    }
+
+    entity main(a: uint<8>) -> bool {
+        let b = a;
+        let c = b;
+        let d = c;
+        let e = d;
+        takes_bool(e)
+    }
+";
+
+#[test]
+fn short_type_errors_do_not_include_the_chain() {
+    let report = type_error(PROPAGATED_THROUGH_LETS, TypeErrorVerbosity::Short);
+    assert!(!report.contains("propagated through"), "{report}");
+}
+
+#[test]
+fn chain_type_errors_include_the_closest_values() {
+    insta::assert_snapshot!(type_error(
+        PROPAGATED_THROUGH_LETS,
+        TypeErrorVerbosity::Chain
+    ));
+}
+
+#[test]
+fn full_type_errors_include_every_value() {
+    insta::assert_snapshot!(type_error(
+        PROPAGATED_THROUGH_LETS,
+        TypeErrorVerbosity::Full
+    ));
+}
+
+#[test]
+fn chains_go_through_generic_arguments() {
+    insta::assert_snapshot!(type_error(
+        "
+        fn identity<T>(x: T) -> T {
+            x
+        }
+
+        fn takes_bool(x: bool) -> bool {
+            x
+        }
+
+        entity main(a: uint<8>) -> bool {
+            let b = identity(a);
+            takes_bool(b)
+        }
+        ",
+        TypeErrorVerbosity::Full
+    ));
+}
+
+#[test]
+fn verbosity_does_not_change_the_inferred_types() {
+    let code = "
+        fn identity<T>(x: T) -> T {
+            x
+        }
+
+        fn first<A, B>(a: A, b: B) -> A {
+            a
+        }
+
+        entity main(a: uint<8>, b: bool) -> uint<8> {
+            let x = identity(a);
+            let y = identity(first(b, x));
+            if y { x } else { first(identity(x), y) }
+        }
+    ";
+    let mir = |type_error_verbosity| {
+        let mut buffer = codespan_reporting::term::termcolor::Buffer::no_color();
+        let opts = spade::Opt {
+            type_error_verbosity,
+            ..spade::Opt::new(&mut buffer)
+        };
+        compile_code(&[], code, true, opts)
+            .unwrap_or_else(|| panic!("{}", String::from_utf8_lossy(buffer.as_slice())))
+            .flat_mir_entities
+            .iter()
+            .map(|Codegenable(e)| e.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let short = mir(TypeErrorVerbosity::Short);
+    assert_eq!(mir(TypeErrorVerbosity::Chain), short);
+    assert_eq!(mir(TypeErrorVerbosity::Full), short);
+}
@@ -1,4 +1,6 @@
-use crate::{build_items, build_items_with_stdlib, code_compiles, snapshot_error};
+use crate::{
+    build_items, build_items_with_stdlib, code_compiles, snapshot_error, snapshot_inference_error,
+};
 
 #[test]
 fn visit_unary_operator_works_for_tilde_int() {
@@ -215,8 +217,9 @@ snapshot_error!(
     "#
 );
 
-snapshot_error!(
+snapshot_inference_error!(
     type_error_when_overflow_is_possible,
+    "ONE",
     "
     entity main(a: int<16>, b: int<16>) -> int<16> {
         a + b
@@ -224,8 +227,9 @@ snapshot_error!(
     "
 );
 
-snapshot_error! {
+snapshot_inference_error! {
     multiplication_errors_if_overflow,
+    "ONE",
     "
     entity main(a: int<14>, b: int<16>) -> int<32> {
         a * b
@@ -233,8 +237,9 @@ snapshot_error! {
     "
 }
 
-snapshot_error! {
+snapshot_inference_error! {
     int_addition_produces_one_more_bit,
+    "ONE",
     "
         fn add(a: int<8>, b: int<8>) -> int<10> {
             let x = a + b;
@@ -1159,8 +1164,9 @@ snapshot_error! {
     "
 }
 
-snapshot_error! {
+snapshot_inference_error! {
     int_add_produces_int,
+    "ONE",
     "
         fn test(x: int<8>, y: int<8>) -> uint<9> {
             x + y
@@ -7,7 +7,7 @@ macro_rules! snapshot_verilator_wrapper {
         fn $name() {
             let e = build_entity!($code);
 
-            let code = e.verilator_wrapper().unwrap_or_default();
+            let code = e.verilator_wrapper();
 
             insta::with_settings!({
                 // FIXME: Why can't we set 'description => source' here?
@@ -22,7 +22,7 @@ macro_rules! snapshot_verilator_wrapper {
     }
 }
 
-snapshot_verilator_wrapper! {no_mangle_unit_has_no_wrapper,
+snapshot_verilator_wrapper! {mangled_unit_wrapper_works,
     r#"
         fn mangle() {}
     "#
@@ -41,3 +41,55 @@ snapshot_verilator_wrapper! {wrapper_with_input_works,
         fn no_mangle(short: int<32>, wide: int<128>) {}
     "#
 }
+
+snapshot_verilator_wrapper! {wrapper_with_wide_output_works,
+    r#"
+        #[no_mangle]
+        fn no_mangle(a: uint<100>) -> uint<100> {
+            a
+        }
+    "#
+}
+
+snapshot_verilator_wrapper! {wrapper_with_backward_port_works,
+    r#"
+        #[no_mangle]
+        entity no_mangle(a: int<8>, b: &mut int<8>) {
+            set b = a;
+        }
+    "#
+}
+
+#[test]
+fn generic_unit_wrapper_works() {
+    let entities = build_items(
+        r#"
+        fn generic<#uint N>(a: uint<N>) -> uint<N> {
+            a
+        }
+
+        #[no_mangle]
+        fn user(a: uint<8>) -> uint<8> {
+            generic(a)
+        }
+    "#,
+    );
+    let e = entities
+        .iter()
+        .find(|e| e.name.without_escapes().starts_with("generic["))
+        .expect("Found no monomorphised instance of generic");
+
+    // The id of the instance depends on the compilation order, so it is replaced to keep
+    // the snapshot stable
+    let id = e.name.source.0;
+    let code = e
+        .verilator_wrapper()
+        .replace(&format!("generic[{id}]"), "generic[<id>]")
+        .replace(&format!("__05b{id}__05d"), "__05b<id>__05d");
+
+    insta::with_settings!({
+        omit_expression => true,
+    }, {
+        insta::assert_snapshot!(code);
+    });
+}
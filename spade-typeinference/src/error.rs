@@ -5,6 +5,7 @@ use spade_common::location_info::{FullSpan, Loc, WithLocation};
 use spade_diagnostics::Diagnostic;
 
 use crate::constraints::ConstraintSource;
+use crate::unification_chain::{type_var_loc, UnificationChain};
 
 use super::equation::{TraitReq, TypeVar};
 
@@ -18,6 +19,9 @@ use super::equation::{TraitReq, TypeVar};
 pub struct UnificationTrace {
     pub failing: TypeVar,
     pub inside: Option<TypeVar>,
+    /// The values through which the type was propagated to the unification point. Only
+    /// computed if the type error verbosity asks for it
+    pub chain: UnificationChain,
 }
 impl WithLocation for UnificationTrace {}
 impl std::fmt::Display for UnificationTrace {
@@ -31,6 +35,7 @@ impl UnificationTrace {
         Self {
             failing,
             inside: None,
+            chain: UnificationChain::default(),
         }
     }
 
@@ -181,18 +186,24 @@ impl<T> UnificationErrorExt<T> for std::result::Result<T, UnificationError> {
                     );
 
                     let diag = if !omit_expected_source {
-                        add_known_type_context(
+                        let diag = add_known_type_context(
                             diag,
                             unification_point.clone(),
                             &e.failing,
                             display_meta,
-                        )
+                        );
+                        add_unification_chain(diag, unification_point.clone(), &e, display_meta)
                     } else {
                         diag
                     };
 
-                    let diag =
-                        add_known_type_context(diag, unification_point, &g.failing, display_meta);
+                    let diag = add_known_type_context(
+                        diag,
+                        unification_point.clone(),
+                        &g.failing,
+                        display_meta,
+                    );
+                    let diag = add_unification_chain(diag, unification_point, &g, display_meta);
                     diag.type_error(
                         format!("{}", e.failing.display_with_meta(display_meta)),
                         e.inside.map(|o| o.display_with_meta(display_meta)),
@@ -304,6 +315,34 @@ impl<T> UnificationErrorExt<T> for std::result::Result<T, UnificationError> {
     }
 }
 
+/// Points out the values through which the type in `trace` was propagated to the unification
+/// point, skipping the places which are already labeled
+fn add_unification_chain(
+    diag: Diagnostic,
+    unification_point: impl Into<FullSpan>,
+    trace: &UnificationTrace,
+    meta: bool,
+) -> Diagnostic {
+    let unification_point = unification_point.into();
+    let labeled = [type_var_loc(&trace.failing), type_var_loc(trace.outer())];
+    let (steps, omitted) = trace.chain.shown_steps(|step| {
+        FullSpan::from(step) == unification_point || labeled.iter().any(|l| l.is_same_loc(step))
+    });
+
+    let ty = trace.display_with_meta(meta);
+    let diag = steps.iter().fold(diag, |diag, step| {
+        diag.secondary_label(step, format!("Type {ty} propagated through here"))
+    });
+    if omitted > 0 {
+        diag.note(format!(
+            "Type {ty} was propagated through {omitted} more {}. Increase the type error verbosity to show all of them",
+            if omitted == 1 { "value" } else { "values" }
+        ))
+    } else {
+        diag
+    }
+}
+
 fn add_known_type_context(
     diag: Diagnostic,
     unification_point: impl Into<FullSpan> + Clone,
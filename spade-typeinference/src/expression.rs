@@ -13,7 +13,7 @@ use spade_types::KnownType;
 use crate::constraints::{bits_to_store, ce_int, ce_var, ConstraintExpr, ConstraintSource};
 use crate::equation::{TypeVar, TypedExpression};
 use crate::error::{TypeMismatch as Tm, UnificationErrorExt};
-use crate::fixed_types::{t_bit, t_bool, t_void};
+use crate::fixed_types::{t_bit, t_bool, t_int, t_void};
 use crate::requirements::{ConstantInt, Requirement};
 use crate::{Context, GenericListToken, HasType, Result, TraceStackEntry, TypeState};
 
@@ -662,6 +662,13 @@ impl TypeState {
         Ok(())
     }
 
+    fn is_known_int(&self, expr: &Loc<Expression>, ctx: &Context) -> bool {
+        matches!(
+            expr.get_type(self),
+            Ok(TypeVar::Known(_, t, _)) if t == t_int(ctx.symtab)
+        )
+    }
+
     #[trace_typechecker]
     #[tracing::instrument(level = "trace", skip_all)]
     pub fn visit_binary_operator(
@@ -674,8 +681,11 @@ impl TypeState {
             self.visit_expression(lhs, ctx, generic_list)?;
             self.visit_expression(rhs, ctx, generic_list)?;
             match op.inner {
+                // Word length inference only tracks signed integers, unsigned arithmetic
+                // is constrained as usual
                 BinaryOperator::Add
-                | BinaryOperator::Sub | BinaryOperator::Mul if self.use_wordlenght_inference => {
+                | BinaryOperator::Sub
+                | BinaryOperator::Mul if self.use_wordlenght_inference && self.is_known_int(lhs, ctx) => {
                     let lhs_t = self.new_generic_int(expression.loc(), ctx.symtab);
                     self.unify_expression_generic_error(lhs, &lhs_t, ctx)?;
                     let rhs_t = self.new_generic_int(expression.loc(), ctx.symtab);
@@ -32,8 +32,8 @@ use spade_hir as hir;
 use spade_hir::param_util::{match_args_with_params, Argument};
 use spade_hir::symbol_table::{Patternable, PatternableKind, SymbolTable, TypeSymbol};
 use spade_hir::{
-    ArgumentList, Block, ExprKind, Expression, ItemList, Pattern, PatternArgument, Register,
-    Statement, TraitName, TraitSpec, TypeParam, Unit,
+    ArgumentList, Block, ExprKind, Expression, ItemList, Pattern, PatternArgument, Property,
+    Register, Statement, TraitName, TraitSpec, TypeParam, Unit,
 };
 use spade_types::KnownType;
 
@@ -52,6 +52,7 @@ use crate::error::TypeMismatch as Tm;
 use crate::fixed_types::t_void;
 use crate::requirements::ConstantInt;
 use crate::traits::{TraitImpl, TraitImplList};
+use crate::unification_chain::{type_var_loc, TypeErrorVerbosity, UnificationLinks};
 
 mod constraints;
 pub mod dump;
@@ -65,6 +66,7 @@ mod requirements;
 pub mod testutil;
 pub mod trace_stack;
 pub mod traits;
+pub mod unification_chain;
 
 pub struct Context<'a> {
     pub symtab: &'a SymbolTable,
@@ -149,6 +151,9 @@ pub struct TypeState {
     /// (Experimental) Use Affine- or Interval-Arithmetic to bounds check integers in a separate
     /// module.
     pub use_wordlenght_inference: bool,
+
+    /// Records why type variables were unified, to explain type mismatches
+    unification_links: UnificationLinks,
 }
 
 impl Default for TypeState {
@@ -170,6 +175,7 @@ impl TypeState {
             trait_impls: TraitImplList::new(),
             pipeline_state: None,
             use_wordlenght_inference: false,
+            unification_links: UnificationLinks::default(),
         }
     }
 
@@ -180,6 +186,11 @@ impl TypeState {
         }
     }
 
+    pub fn set_type_error_verbosity(mut self, verbosity: TypeErrorVerbosity) -> Self {
+        self.unification_links.verbosity = verbosity;
+        self
+    }
+
     pub fn get_equations(&self) -> &TypeEquations {
         &self.equations
     }
@@ -228,6 +239,35 @@ impl TypeState {
         Ok(tvar)
     }
 
+    /// The type variable of `hir_type` before any unification, if it is a generic parameter
+    /// and type mismatches are being explained
+    fn original_generic_type(
+        &self,
+        hir_type: &hir::TypeSpec,
+        generic_list_token: &GenericListToken,
+    ) -> Option<TypeVar> {
+        match hir_type {
+            hir::TypeSpec::Generic(name) => self
+                .unification_links
+                .original_generic(generic_list_token, &name.inner),
+            _ => None,
+        }
+    }
+
+    /// The type of a parameter or output in a unit head, along with its type variable
+    /// before any unification if it is generic
+    fn unit_head_type(
+        &mut self,
+        loc: Loc<()>,
+        hir_type: &hir::TypeSpec,
+        generic_list_token: &GenericListToken,
+    ) -> Result<UnitHeadType> {
+        Ok(UnitHeadType {
+            current: self.type_var_from_hir(loc, hir_type, generic_list_token)?,
+            original: self.original_generic_type(hir_type, generic_list_token),
+        })
+    }
+
     #[tracing::instrument(level = "trace", skip_all, fields(%hir_type))]
     pub fn type_var_from_hir<'a>(
         &'a mut self,
@@ -553,7 +593,7 @@ impl TypeState {
             kind,
         } in args.iter()
         {
-            let target_type = self.type_var_from_hir(value.loc(), target_type, generic_list)?;
+            let target_type = self.unit_head_type(value.loc(), target_type, generic_list)?;
 
             let loc = match kind {
                 hir::param_util::ArgumentKind::Positional => value.loc(),
@@ -811,6 +851,24 @@ impl TypeState {
 
                 self.handle_clocked_memory(num_elements, addr_size, &matched_args, ctx)?
             },
+            ["std", "mem", "clocked_memory_masked"]  => {
+                let num_elements = generic_arg!(0);
+                let addr_size = generic_arg!(2);
+
+                self.handle_clocked_memory(num_elements, addr_size, &matched_args, ctx)?
+            },
+            ["std", "mem", "single_port_ram"]  => {
+                let num_elements = generic_arg!(0);
+                let addr_size = generic_arg!(1);
+
+                self.handle_block_ram(num_elements, addr_size, 1, &matched_args, ctx)?
+            },
+            ["std", "mem", "true_dual_port_ram"]  => {
+                let num_elements = generic_arg!(0);
+                let addr_size = generic_arg!(1);
+
+                self.handle_block_ram(num_elements, addr_size, 2, &matched_args, ctx)?
+            },
             ["std", "mem", "read_memory"]  => {
                 let addr_size = generic_arg!(0);
                 let num_elements = generic_arg!(2);
@@ -825,9 +883,12 @@ impl TypeState {
         let return_type = head
             .output_type
             .as_ref()
-            .map(|o| self.type_var_from_hir(expression_id.loc(), o, &generic_list))
+            .map(|o| self.unit_head_type(expression_id.loc(), o, &generic_list))
             .transpose()?
-            .unwrap_or_else(|| TypeVar::Known(expression_id.loc(), t_void(ctx.symtab), vec![]));
+            .unwrap_or_else(|| UnitHeadType {
+                current: TypeVar::Known(expression_id.loc(), t_void(ctx.symtab), vec![]),
+                original: None,
+            });
 
         self.unify(expression_type, &return_type, ctx)
             .into_default_diagnostic(expression_id.loc())?;
@@ -958,6 +1019,44 @@ impl TypeState {
         Ok(())
     }
 
+    /// Constrains the address width of each port of a block RAM with `ports` (clock, port)
+    /// argument pairs
+    pub fn handle_block_ram(
+        &mut self,
+        num_elements: TypeVar,
+        addr_size_arg: TypeVar,
+        ports: usize,
+        args: &[Argument<Expression, TypeSpec>],
+        ctx: &Context,
+    ) -> Result<()> {
+        for port in (0..ports).map(|p| &args[p * 2 + 1]) {
+            let loc = port.value.loc();
+            let (addr_type, addr_size) = self.new_split_generic_uint(loc, ctx.symtab);
+            let port_type = TypeVar::tuple(
+                loc,
+                vec![
+                    self.new_generic_type(loc),
+                    addr_type,
+                    self.new_generic_type(loc),
+                ],
+            );
+
+            self.add_constraint(
+                addr_size.clone(),
+                bits_to_store(ce_var(&num_elements) - ce_int(1.to_bigint())),
+                loc,
+                &port_type,
+                ConstraintSource::MemoryIndexing,
+            );
+
+            // NOTE: Unwrap is safe, size is still generic at this point
+            self.unify(&addr_size, &addr_size_arg, ctx).unwrap();
+            self.unify_expression_generic_error(port.value, &port_type, ctx)?;
+        }
+
+        Ok(())
+    }
+
     pub fn handle_read_memory(
         &mut self,
         num_elements: TypeVar,
@@ -1157,6 +1256,8 @@ impl TypeState {
             GenericListSource::Expression(id) => GenericListToken::Expression(id),
         };
 
+        self.unification_links
+            .add_generic_list(&reference, &mapping);
         if self
             .generic_lists
             .insert(reference.clone(), mapping)
@@ -1443,6 +1544,8 @@ impl TypeState {
                 ty,
                 value,
                 wal_trace,
+                memory_init: _,
+                verilog_attrs: _,
             }) => {
                 trace!("Visiting `let {} = ..`", pattern.kind);
                 self.visit_expression(value, ctx, generic_list)?;
@@ -1574,6 +1677,7 @@ impl TypeState {
                 self.unify_expression_generic_error(expr, &t_bool(ctx.symtab).at_loc(stmt), ctx)?;
                 Ok(())
             }
+            Statement::Property(property) => self.visit_property(property, ctx, generic_list),
             Statement::Set { target, value } => {
                 self.visit_expression(target, ctx, generic_list)?;
                 self.visit_expression(value, ctx, generic_list)?;
@@ -1588,6 +1692,47 @@ impl TypeState {
         }
     }
 
+    #[trace_typechecker]
+    pub fn visit_property(
+        &mut self,
+        property: &Property,
+        ctx: &Context,
+        generic_list: &GenericListToken,
+    ) -> Result<()> {
+        let Property {
+            kind: _,
+            clock,
+            reset,
+            antecedent,
+            consequent,
+        } = property;
+
+        self.visit_expression(clock, ctx, generic_list)?;
+        self.unify(clock, &t_clock(ctx.symtab).at_loc(clock), ctx)
+            .into_diagnostic(
+                clock.loc(),
+                |diag,
+                 Tm {
+                     g: got,
+                     e: _expected,
+                 }| {
+                    diag.message(format!("Expected clock, got {got}"))
+                        .primary_label("expected clock")
+                },
+            )?;
+
+        let bools = reset
+            .iter()
+            .chain(antecedent.iter().map(|(expr, _)| expr))
+            .chain(std::iter::once(consequent));
+        for expr in bools {
+            self.visit_expression(expr, ctx, generic_list)?;
+            self.unify_expression_generic_error(expr, &t_bool(ctx.symtab).at_loc(expr), ctx)?;
+        }
+
+        Ok(())
+    }
+
     #[trace_typechecker]
     pub fn visit_register(
         &mut self,
@@ -1903,6 +2048,7 @@ impl TypeState {
             expression.clone(),
             var.clone(),
         ));
+        self.unification_links.add_equation(&expression, &var);
         if let Some(prev) = self.equations.insert(expression.clone(), var.clone()) {
             let var = var.clone();
             let expr = expression.clone();
@@ -2321,6 +2467,18 @@ impl TypeState {
 
         let (new_type, replaced_types) = result?;
 
+        if self.unification_links.enabled() {
+            for replaced_type in &replaced_types {
+                let (at, other) = if replaced_type == &v1cpy {
+                    (e2.value_loc(), e2.original_type(self))
+                } else {
+                    (e1.value_loc(), e1.original_type(self))
+                };
+                self.unification_links
+                    .add_link(replaced_type, &new_type, at, other);
+            }
+        }
+
         self.trace_stack.push(TraceStackEntry::Unified(
             v1cpy,
             v2cpy,
@@ -2387,6 +2545,28 @@ impl TypeState {
         Ok(new_type)
     }
 
+    /// Adds the chain of values through which each side of a type mismatch got its type
+    fn add_unification_chains(
+        &self,
+        err: UnificationError,
+        e1: &impl HasType,
+        e2: &impl HasType,
+    ) -> UnificationError {
+        if !self.unification_links.enabled() {
+            return err;
+        }
+        let add_chains = |Tm { mut e, mut g }| {
+            g.chain = self.unification_links.chain(e1.original_type(self));
+            e.chain = self.unification_links.chain(e2.original_type(self));
+            Tm { e, g }
+        };
+        match err {
+            UnificationError::Normal(tm) => UnificationError::Normal(add_chains(tm)),
+            UnificationError::MetaMismatch(tm) => UnificationError::MetaMismatch(add_chains(tm)),
+            other => other,
+        }
+    }
+
     #[tracing::instrument(level = "trace", skip_all)]
     pub fn unify(
         &mut self,
@@ -2394,7 +2574,9 @@ impl TypeState {
         e2: &impl HasType,
         ctx: &Context,
     ) -> std::result::Result<TypeVar, UnificationError> {
-        let new_type = self.unify_inner(e1, e2, ctx)?;
+        let new_type = self
+            .unify_inner(e1, e2, ctx)
+            .map_err(|err| self.add_unification_chains(err, e1, e2))?;
 
         // With replacement done, some of our constraints may have been updated to provide
         // more type inference information. Try to do unification of those new constraints too
@@ -2738,52 +2920,138 @@ impl TypeState {
 
 pub trait HasType: std::fmt::Debug {
     fn get_type(&self, state: &TypeState) -> Result<TypeVar>;
+
+    /// The location of the value, if known. Used to explain type mismatches
+    fn value_loc(&self) -> Option<Loc<()>> {
+        None
+    }
+
+    /// The type the value had before any unification. Used to explain type mismatches
+    fn original_type(&self, _state: &TypeState) -> Option<TypeVar> {
+        None
+    }
 }
 
 impl HasType for TypeVar {
     fn get_type(&self, state: &TypeState) -> Result<TypeVar> {
         Ok(state.check_var_for_replacement(self.clone()))
     }
+
+    fn value_loc(&self) -> Option<Loc<()>> {
+        Some(type_var_loc(self))
+    }
+
+    fn original_type(&self, _state: &TypeState) -> Option<TypeVar> {
+        Some(self.clone())
+    }
 }
 impl HasType for Loc<TypeVar> {
     fn get_type(&self, state: &TypeState) -> Result<TypeVar> {
         self.inner.get_type(state)
     }
+
+    fn value_loc(&self) -> Option<Loc<()>> {
+        Some(self.loc())
+    }
+
+    fn original_type(&self, _state: &TypeState) -> Option<TypeVar> {
+        Some(self.inner.clone())
+    }
 }
 impl HasType for TypedExpression {
     fn get_type(&self, state: &TypeState) -> Result<TypeVar> {
         state.type_of(self)
     }
+
+    fn original_type(&self, state: &TypeState) -> Option<TypeVar> {
+        state.unification_links.original_type(self)
+    }
 }
 impl HasType for Expression {
     fn get_type(&self, state: &TypeState) -> Result<TypeVar> {
         state.type_of(&TypedExpression::Id(self.id))
     }
+
+    fn original_type(&self, state: &TypeState) -> Option<TypeVar> {
+        TypedExpression::Id(self.id).original_type(state)
+    }
 }
 impl HasType for Loc<Expression> {
     fn get_type(&self, state: &TypeState) -> Result<TypeVar> {
         state.type_of(&TypedExpression::Id(self.inner.id))
     }
+
+    fn value_loc(&self) -> Option<Loc<()>> {
+        Some(self.loc())
+    }
+
+    fn original_type(&self, state: &TypeState) -> Option<TypeVar> {
+        self.inner.original_type(state)
+    }
 }
 impl HasType for Pattern {
     fn get_type(&self, state: &TypeState) -> Result<TypeVar> {
         state.type_of(&TypedExpression::Id(self.id))
     }
+
+    fn original_type(&self, state: &TypeState) -> Option<TypeVar> {
+        TypedExpression::Id(self.id).original_type(state)
+    }
 }
 impl HasType for Loc<Pattern> {
     fn get_type(&self, state: &TypeState) -> Result<TypeVar> {
         state.type_of(&TypedExpression::Id(self.inner.id))
     }
+
+    fn value_loc(&self) -> Option<Loc<()>> {
+        Some(self.loc())
+    }
+
+    fn original_type(&self, state: &TypeState) -> Option<TypeVar> {
+        self.inner.original_type(state)
+    }
+}
+
+/// The type of a parameter or output in a unit head. Unified as the current type, but type
+/// mismatches are explained from the type variable a generic had before unification, which
+/// shows how the generic got its type
+#[derive(Debug)]
+struct UnitHeadType {
+    current: TypeVar,
+    original: Option<TypeVar>,
+}
+
+impl HasType for UnitHeadType {
+    fn get_type(&self, state: &TypeState) -> Result<TypeVar> {
+        self.current.get_type(state)
+    }
+
+    fn value_loc(&self) -> Option<Loc<()>> {
+        self.original.as_ref().unwrap_or(&self.current).value_loc()
+    }
+
+    fn original_type(&self, _state: &TypeState) -> Option<TypeVar> {
+        Some(self.original.as_ref().unwrap_or(&self.current).clone())
+    }
 }
+
 impl HasType for Loc<KnownType> {
     fn get_type(&self, _state: &TypeState) -> Result<TypeVar> {
         Ok(TypeVar::Known(self.loc(), self.inner.clone(), vec![]))
     }
+
+    fn value_loc(&self) -> Option<Loc<()>> {
+        Some(self.loc())
+    }
 }
 impl HasType for NameID {
     fn get_type(&self, state: &TypeState) -> Result<TypeVar> {
         state.type_of(&TypedExpression::Name(self.clone()))
     }
+
+    fn original_type(&self, state: &TypeState) -> Option<TypeVar> {
+        TypedExpression::Name(self.clone()).original_type(state)
+    }
 }
 
 /// Mapping between names and concrete type used for lookup, without being
@@ -0,0 +1,184 @@
+//! Bookkeeping for explaining type mismatches. Whenever an unknown type variable is replaced
+//! during unification, the value it was unified with is recorded along with the type that
+//! value had before unification. Following these links from the type of an expression gives
+//! the chain of values through which the expression was forced to its type, which is shown
+//! as part of type mismatch diagnostics.
+
+use std::collections::{HashMap, HashSet};
+
+use spade_common::location_info::Loc;
+
+use spade_common::name::NameID;
+
+use crate::equation::{TypeVar, TypedExpression};
+use crate::GenericListToken;
+
+/// How much of the unification chain to include in type mismatch diagnostics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TypeErrorVerbosity {
+    /// Only point out where the mismatching types were inferred
+    #[default]
+    Short,
+    /// Also point out the last few values through which each type was inferred
+    Chain,
+    /// Point out every value through which each type was inferred
+    Full,
+}
+
+impl TypeErrorVerbosity {
+    /// The maximum number of steps shown for each side of a type mismatch
+    fn max_steps(self) -> Option<usize> {
+        match self {
+            TypeErrorVerbosity::Short => Some(0),
+            TypeErrorVerbosity::Chain => Some(3),
+            TypeErrorVerbosity::Full => None,
+        }
+    }
+}
+
+/// The values through which a type was propagated to the point of a type error, starting at
+/// the value closest to the error
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UnificationChain {
+    pub steps: Vec<Loc<()>>,
+    /// The number of steps to show in diagnostics. `None` if all steps should be shown
+    pub max_steps: Option<usize>,
+}
+
+impl UnificationChain {
+    /// Splits the steps which are not hidden into those which should be shown, and the
+    /// number of steps which were left out
+    pub fn shown_steps(&self, is_hidden: impl Fn(&Loc<()>) -> bool) -> (Vec<Loc<()>>, usize) {
+        let steps = self
+            .steps
+            .iter()
+            .filter(|step| !is_hidden(step))
+            .cloned()
+            .collect::<Vec<_>>();
+        match self.max_steps {
+            Some(max) if steps.len() > max => {
+                let omitted = steps.len() - max;
+                (steps.into_iter().take(max).collect(), omitted)
+            }
+            _ => (steps, 0),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Link {
+    /// The value the type variable was unified with
+    at: Loc<()>,
+    /// The type variable which replaced it
+    replaced_by: TypeVar,
+    /// The type of the value it was unified with, before unification
+    other: Option<TypeVar>,
+}
+
+#[derive(Clone, Default)]
+pub struct UnificationLinks {
+    pub verbosity: TypeErrorVerbosity,
+    original_types: HashMap<TypedExpression, TypeVar>,
+    /// The type variables of generic parameters before any unification. Generic lists are
+    /// updated in place during unification, so the original variables are kept here
+    original_generics: HashMap<GenericListToken, HashMap<NameID, TypeVar>>,
+    links: HashMap<u64, Link>,
+}
+
+impl UnificationLinks {
+    /// Links are only recorded if they are going to be shown, since the bookkeeping is
+    /// not free
+    pub fn enabled(&self) -> bool {
+        self.verbosity != TypeErrorVerbosity::Short
+    }
+
+    pub fn add_equation(&mut self, expression: &TypedExpression, var: &TypeVar) {
+        if self.enabled() {
+            self.original_types
+                .entry(expression.clone())
+                .or_insert_with(|| var.clone());
+        }
+    }
+
+    pub fn original_type(&self, expression: &TypedExpression) -> Option<TypeVar> {
+        self.original_types.get(expression).cloned()
+    }
+
+    pub fn add_generic_list(
+        &mut self,
+        token: &GenericListToken,
+        mapping: &HashMap<NameID, TypeVar>,
+    ) {
+        if self.enabled() {
+            self.original_generics
+                .entry(token.clone())
+                .or_insert_with(|| mapping.clone());
+        }
+    }
+
+    pub fn original_generic(&self, token: &GenericListToken, name: &NameID) -> Option<TypeVar> {
+        self.original_generics.get(token)?.get(name).cloned()
+    }
+
+    /// Records that `replaced` was replaced by `replaced_by` because it was unified with the
+    /// value at `at` whose type was `other`. If the location of the value is not known, the
+    /// location of its type is used instead
+    pub fn add_link(
+        &mut self,
+        replaced: &TypeVar,
+        replaced_by: &TypeVar,
+        at: Option<Loc<()>>,
+        other: Option<TypeVar>,
+    ) {
+        if !self.enabled() {
+            return;
+        }
+        let TypeVar::Unknown(_, id, _, _) = replaced else {
+            return;
+        };
+        let Some(at) = at.or_else(|| other.as_ref().map(type_var_loc)) else {
+            return;
+        };
+        self.links.entry(*id).or_insert_with(|| Link {
+            at,
+            replaced_by: replaced_by.clone(),
+            other,
+        });
+    }
+
+    /// The chain of values through which `start` was forced to its current type
+    pub fn chain(&self, start: Option<TypeVar>) -> UnificationChain {
+        let mut steps: Vec<Loc<()>> = vec![];
+        let mut visited = HashSet::new();
+        let mut current = start;
+        while let Some(TypeVar::Unknown(_, id, _, _)) = &current {
+            if !visited.insert(*id) {
+                break;
+            }
+            let Some(link) = self.links.get(id) else {
+                break;
+            };
+            if !steps.iter().any(|step| step.is_same_loc(&link.at)) {
+                steps.push(link.at);
+            }
+            // If the variable was merged with another unknown variable, the type came from
+            // wherever that variable got its type. Otherwise it came from the value it was
+            // unified with
+            current = match &link.replaced_by {
+                unknown @ TypeVar::Unknown(..) => Some(unknown.clone()),
+                TypeVar::Known(..) => link.other.clone(),
+            };
+        }
+        UnificationChain {
+            steps,
+            max_steps: self.verbosity.max_steps(),
+        }
+    }
+}
+
+pub fn type_var_loc(var: &TypeVar) -> Loc<()> {
+    match var {
+        TypeVar::Known(loc, _, _) => *loc,
+        TypeVar::Unknown(loc, _, _, _) => *loc,
+    }
+}
@@ -0,0 +1,273 @@
+use crate::ImportError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// Identifiers and keywords, including system functions like `$clog2`
+    Identifier(String),
+    /// Numbers as written in the source, including based literals like `8'hff`
+    Number(String),
+    String(String),
+    Symbol(char),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+}
+
+impl Token {
+    pub fn is_symbol(&self, c: char) -> bool {
+        self.kind == TokenKind::Symbol(c)
+    }
+
+    pub fn is_identifier(&self, name: &str) -> bool {
+        matches!(&self.kind, TokenKind::Identifier(i) if i == name)
+    }
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Identifier(s) | TokenKind::Number(s) => write!(f, "{s}"),
+            TokenKind::String(s) => write!(f, "\"{s}\""),
+            TokenKind::Symbol(c) => write!(f, "{c}"),
+        }
+    }
+}
+
+/// Compiler directives which are skipped along with the rest of their line
+const LINE_DIRECTIVES: &[&str] = &[
+    "timescale",
+    "define",
+    "undef",
+    "ifdef",
+    "ifndef",
+    "elsif",
+    "else",
+    "endif",
+    "include",
+    "default_nettype",
+    "resetall",
+    "celldefine",
+    "endcelldefine",
+];
+
+/// Split Verilog source code into tokens, skipping comments, attributes and compiler
+/// directives
+pub fn tokenize(source: &str) -> Result<Vec<Token>, ImportError> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut result = vec![];
+    let mut line = 1;
+    let mut i = 0;
+
+    let take_while = |i: &mut usize, cond: &dyn Fn(char) -> bool| {
+        let start = *i;
+        while *i < chars.len() && cond(chars[*i]) {
+            *i += 1;
+        }
+        chars[start..*i].iter().collect::<String>()
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            '/' if next == Some('/') => {
+                take_while(&mut i, &|c| c != '\n');
+            }
+            '/' if next == Some('*') => {
+                let start_line = line;
+                i += 2;
+                loop {
+                    match (chars.get(i), chars.get(i + 1)) {
+                        (Some('*'), Some('/')) => {
+                            i += 2;
+                            break;
+                        }
+                        (Some(c), _) => {
+                            if *c == '\n' {
+                                line += 1;
+                            }
+                            i += 1;
+                        }
+                        (None, _) => {
+                            return Err(ImportError::new(start_line, "Unterminated comment"))
+                        }
+                    }
+                }
+            }
+            // Attributes, but not the `(*)` of `@(*)`
+            '(' if next == Some('*') && chars.get(i + 2) != Some(&')') => {
+                let start_line = line;
+                i += 2;
+                loop {
+                    match (chars.get(i), chars.get(i + 1)) {
+                        (Some('*'), Some(')')) => {
+                            i += 2;
+                            break;
+                        }
+                        (Some(c), _) => {
+                            if *c == '\n' {
+                                line += 1;
+                            }
+                            i += 1;
+                        }
+                        (None, _) => {
+                            return Err(ImportError::new(start_line, "Unterminated attribute"))
+                        }
+                    }
+                }
+            }
+            '`' => {
+                i += 1;
+                let directive = take_while(&mut i, &|c| c.is_alphanumeric() || c == '_');
+                if LINE_DIRECTIVES.contains(&directive.as_str()) {
+                    // Macro definitions may continue on the next line
+                    loop {
+                        let rest = take_while(&mut i, &|c| c != '\n');
+                        if rest.ends_with('\\') && i < chars.len() {
+                            line += 1;
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                } else {
+                    result.push(Token {
+                        kind: TokenKind::Identifier(format!("`{directive}")),
+                        line,
+                    })
+                }
+            }
+            '"' => {
+                i += 1;
+                let mut value = String::new();
+                loop {
+                    match chars.get(i) {
+                        Some('"') => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') => {
+                            value.push('\\');
+                            if let Some(escaped) = chars.get(i + 1) {
+                                value.push(*escaped);
+                            }
+                            i += 2;
+                        }
+                        Some('\n') | None => {
+                            return Err(ImportError::new(line, "Unterminated string"))
+                        }
+                        Some(c) => {
+                            value.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                result.push(Token {
+                    kind: TokenKind::String(value),
+                    line,
+                })
+            }
+            '\\' => {
+                let name = take_while(&mut i, &|c| !c.is_whitespace());
+                return Err(ImportError::new(
+                    line,
+                    format!("Escaped identifier {name} is not supported"),
+                ));
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let ident = take_while(&mut i, &|c| c.is_alphanumeric() || c == '_' || c == '$');
+                result.push(Token {
+                    kind: TokenKind::Identifier(ident),
+                    line,
+                })
+            }
+            c if c.is_ascii_digit() || (c == '\'' && next.is_some_and(is_base_char)) => {
+                let mut number = take_while(&mut i, &|c| c.is_ascii_digit() || c == '_');
+                if chars.get(i) == Some(&'.') {
+                    number += &take_while(&mut i, &|c| {
+                        c.is_ascii_digit() || c == '.' || c == '_' || c == 'e' || c == 'E'
+                    });
+                } else if chars.get(i) == Some(&'\'') {
+                    i += 1;
+                    number.push('\'');
+                    number += &take_while(&mut i, &|c| c == 's' || c == 'S');
+                    number += &take_while(&mut i, &is_base_char);
+                    number += &take_while(&mut i, &|c| {
+                        c.is_ascii_hexdigit() || matches!(c, '_' | 'x' | 'X' | 'z' | 'Z' | '?')
+                    });
+                }
+                result.push(Token {
+                    kind: TokenKind::Number(number),
+                    line,
+                })
+            }
+            c => {
+                result.push(Token {
+                    kind: TokenKind::Symbol(c),
+                    line,
+                });
+                i += 1;
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+fn is_base_char(c: char) -> bool {
+    matches!(c, 'b' | 'B' | 'o' | 'O' | 'd' | 'D' | 'h' | 'H' | '0' | '1')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.kind)
+            .collect()
+    }
+
+    fn ident(s: &str) -> TokenKind {
+        TokenKind::Identifier(s.to_string())
+    }
+
+    #[test]
+    fn comments_attributes_and_directives_are_skipped() {
+        let source = "`timescale 1ns/1ps\n(* keep *) wire /* x */ a; // b\n`define X \\\n 5\n";
+        assert_eq!(
+            kinds(source),
+            vec![ident("wire"), ident("a"), TokenKind::Symbol(';')]
+        );
+    }
+
+    #[test]
+    fn numbers_are_kept_as_written() {
+        assert_eq!(
+            kinds("8'hFF 'b1 12 4'sd3"),
+            vec![
+                TokenKind::Number("8'hFF".to_string()),
+                TokenKind::Number("'b1".to_string()),
+                TokenKind::Number("12".to_string()),
+                TokenKind::Number("4'sd3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_are_tracked() {
+        let tokens = tokenize("a\n/*\n*/ b").unwrap();
+        assert_eq!(tokens[0].line, 1);
+        assert_eq!(tokens[1].line, 3);
+    }
+}
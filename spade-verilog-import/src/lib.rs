@@ -0,0 +1,180 @@
+//! Generates spade `__builtin__` entities from the headers of Verilog and SystemVerilog
+//! modules, to make existing Verilog code usable from spade without hand writing the
+//! port lists.
+
+pub mod lexer;
+pub mod parser;
+pub mod stub;
+
+use itertools::Itertools;
+use thiserror::Error;
+
+pub use parser::Module;
+
+#[derive(Debug, Error, PartialEq)]
+pub struct ImportError {
+    /// The line of the Verilog source the error is at. None for errors which are not
+    /// caused by a specific line, such as requests for modules which do not exist
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ImportError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(line),
+            message: message.into(),
+        }
+    }
+
+    pub fn without_line(message: impl Into<String>) -> Self {
+        Self {
+            line: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Parse the module headers in a Verilog file
+pub fn parse_modules(source: &str) -> Result<Vec<Module>, ImportError> {
+    parser::parse_modules(&lexer::tokenize(source)?)
+}
+
+/// Generate spade stubs for the modules in a Verilog file. If `only` is non-empty,
+/// only stubs for the modules in it are generated
+pub fn import_verilog(source: &str, only: &[String]) -> Result<String, ImportError> {
+    generate_stubs(&parse_modules(source)?, only)
+}
+
+/// Generate spade stubs for `modules`. If `only` is non-empty, only stubs for the modules
+/// in it are generated, and all of them must be in `modules`
+pub fn generate_stubs(modules: &[Module], only: &[String]) -> Result<String, ImportError> {
+    if let Some(missing) = only.iter().find(|m| !modules.iter().any(|o| &o.name == *m)) {
+        return Err(ImportError::without_line(format!(
+            "No module named {missing} was found"
+        )));
+    }
+
+    Ok(modules
+        .iter()
+        .filter(|m| only.is_empty() || only.contains(&m.name))
+        .map(stub::generate_stub)
+        .collect::<Result<Vec<_>, _>>()?
+        .iter()
+        .join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use spade_parser::{lexer::TokenKind, Parser};
+
+    use logos::Logos;
+
+    fn check_parses(spade: &str) {
+        let mut parser = Parser::new(TokenKind::lexer(spade), 0);
+        let body = parser.top_level_module_body();
+        assert!(body.is_ok(), "Generated code does not parse:\n{spade}");
+    }
+
+    #[test]
+    fn ansi_module_imports() {
+        let verilog = indoc!(
+            r#"
+            `timescale 1ns / 1ps
+            module fifo #(
+                parameter WIDTH = 8,
+                parameter DEPTH = 16,
+                parameter MODE = "FAST"
+            ) (
+                input wire clk,
+                input wire rst_n,
+                input wire [WIDTH-1:0] din,
+                input wire write,
+                output reg [WIDTH-1:0] dout,
+                output wire [$clog2(DEPTH):0] count,
+                inout wire sda
+            );
+            endmodule
+        "#
+        );
+
+        let expected = indoc!(
+            r#"
+            // Generated from the Verilog module fifo
+            // WIDTH defaults to 8
+            // DEPTH defaults to 16
+            #[no_mangle]
+            #[verilog_parameters(MODE = "FAST")]
+            entity fifo<#uint WIDTH, #uint DEPTH, #uint COUNT_WIDTH>(
+                #[no_mangle] clk: clock,
+                #[no_mangle] rst_n: bool,
+                #[no_mangle] din: uint<WIDTH>,
+                #[no_mangle] write: bool,
+                #[no_mangle] dout: &mut uint<WIDTH>,
+                #[no_mangle] count: &mut uint<COUNT_WIDTH>,
+                #[no_mangle] sda: inout<bool>,
+            )
+                where COUNT_WIDTH: { (uint_bits_to_fit((2 * DEPTH) - 1) - 1) + 1 }
+            __builtin__"#
+        );
+
+        let result = import_verilog(verilog, &[]).unwrap();
+        assert_eq!(result, expected);
+        check_parses(&result);
+    }
+
+    #[test]
+    fn non_ansi_module_with_output_imports() {
+        let verilog = indoc!(
+            r#"
+            module adder(a, b, output__);
+                input signed [15:0] a, b;
+                output signed [16:0] output__;
+                assign output__ = a + b;
+            endmodule
+
+            module other(input x); endmodule
+        "#
+        );
+
+        let expected = indoc!(
+            r#"
+            // Generated from the Verilog module adder
+            #[no_mangle]
+            entity adder(
+                #[no_mangle] a: int<16>,
+                #[no_mangle] b: int<16>,
+            ) -> int<17> __builtin__"#
+        );
+
+        let result = import_verilog(verilog, &["adder".to_string()]).unwrap();
+        assert_eq!(result, expected);
+        check_parses(&result);
+    }
+
+    #[test]
+    fn missing_modules_are_errors() {
+        assert_eq!(
+            import_verilog("module a; endmodule", &["b".to_string()]),
+            Err(ImportError::without_line("No module named b was found"))
+        );
+        assert_eq!(
+            import_verilog("module a; endmodule", &["b".to_string()])
+                .unwrap_err()
+                .to_string(),
+            "No module named b was found"
+        );
+    }
+}
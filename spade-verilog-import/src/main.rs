@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use color_eyre::eyre::{Context, Result};
+
+use spade_verilog_import::{generate_stubs, parse_modules};
+
+/// Generate spade `__builtin__` entities for the modules in Verilog or SystemVerilog files
+#[derive(Parser)]
+#[command(name = "spade-verilog-import")]
+struct Opt {
+    /// Verilog files to import
+    #[arg(required = true)]
+    infiles: Vec<PathBuf>,
+    /// File to write the spade code to. Written to stdout if not specified
+    #[arg(short = 'o')]
+    outfile: Option<PathBuf>,
+    /// Only generate stubs for these modules. Can be specified multiple times
+    #[arg(short = 'm', long = "module")]
+    modules: Vec<String>,
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let opts = Opt::parse();
+
+    let mut modules = vec![];
+    for infile in &opts.infiles {
+        let source = std::fs::read_to_string(infile)
+            .with_context(|| format!("Failed to read {}", infile.to_string_lossy()))?;
+
+        modules.extend(
+            parse_modules(&source)
+                .with_context(|| format!("Failed to parse {}", infile.to_string_lossy()))?,
+        );
+    }
+
+    let stubs = generate_stubs(&modules, &opts.modules).context("Failed to import modules")?;
+
+    let output = format!("{stubs}\n");
+    match &opts.outfile {
+        Some(outfile) => std::fs::write(outfile, output)
+            .with_context(|| format!("Failed to write {}", outfile.to_string_lossy()))?,
+        None => print!("{output}"),
+    }
+
+    Ok(())
+}
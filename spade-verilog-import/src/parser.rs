@@ -0,0 +1,714 @@
+use crate::lexer::{Token, TokenKind};
+use crate::ImportError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Input,
+    Output,
+    Inout,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+/// The constant expressions that can appear in the ranges of ports
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Int(u64),
+    Parameter(String),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+    Clog2(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub msb: Expr,
+    pub lsb: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Port {
+    pub name: String,
+    pub direction: Direction,
+    pub signed: bool,
+    pub range: Option<Range>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterKind {
+    Int { signed: bool },
+    String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub kind: ParameterKind,
+    /// The default value as written in the Verilog source
+    pub default: Option<String>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub ports: Vec<Port>,
+}
+
+/// Data types which may appear between the direction and name of a port
+const PORT_TYPES: &[&str] = &[
+    "wire", "reg", "logic", "var", "bit", "tri", "uwire", "unsigned",
+];
+/// Data types of parameters which are integers
+const INT_PARAMETER_TYPES: &[&str] = &[
+    "int", "integer", "logic", "bit", "reg", "byte", "shortint", "longint", "unsigned",
+];
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn line(&self) -> usize {
+        self.peek()
+            .or(self.tokens.last())
+            .map(|t| t.line)
+            .unwrap_or(1)
+    }
+
+    fn eat(&mut self) -> Result<&'a Token, ImportError> {
+        let token = self
+            .peek()
+            .ok_or_else(|| ImportError::new(self.line(), "Unexpected end of file"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn peek_symbol(&self, c: char) -> bool {
+        self.peek().map(|t| t.is_symbol(c)).unwrap_or(false)
+    }
+
+    fn peek_identifier(&self, name: &str) -> bool {
+        self.peek().map(|t| t.is_identifier(name)).unwrap_or(false)
+    }
+
+    fn eat_symbol(&mut self, c: char) -> Result<(), ImportError> {
+        let token = self.eat()?;
+        if token.is_symbol(c) {
+            Ok(())
+        } else {
+            Err(ImportError::new(
+                token.line,
+                format!("Expected `{c}`, got `{}`", token.kind),
+            ))
+        }
+    }
+
+    fn eat_if_identifier(&mut self, names: &[&str]) -> Option<&'a str> {
+        match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Identifier(i)) if names.contains(&i.as_str()) => {
+                self.pos += 1;
+                Some(i)
+            }
+            _ => None,
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, ImportError> {
+        let token = self.eat()?;
+        match &token.kind {
+            TokenKind::Identifier(i) => Ok(i.clone()),
+            other => Err(ImportError::new(
+                token.line,
+                format!("Expected an identifier, got `{other}`"),
+            )),
+        }
+    }
+
+    /// Collect the tokens up to the next `,` or `;` or closing bracket which is not nested
+    fn until_separator(&mut self) -> Vec<&'a Token> {
+        let mut depth = 0;
+        let mut result = vec![];
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::Symbol('(' | '[' | '{') => depth += 1,
+                TokenKind::Symbol(')' | ']' | '}') if depth == 0 => break,
+                TokenKind::Symbol(')' | ']' | '}') => depth -= 1,
+                TokenKind::Symbol(',' | ';') if depth == 0 => break,
+                _ => {}
+            }
+            result.push(token);
+            self.pos += 1;
+        }
+        result
+    }
+
+    fn expr(&mut self) -> Result<Expr, ImportError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.peek_symbol('+') {
+                BinOp::Add
+            } else if self.peek_symbol('-') {
+                BinOp::Sub
+            } else {
+                return Ok(lhs);
+            };
+            self.pos += 1;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(self.term()?))
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ImportError> {
+        let mut lhs = self.atom()?;
+        // `**` is exponentiation, which is not supported
+        while self.peek_symbol('*')
+            && !self
+                .tokens
+                .get(self.pos + 1)
+                .map(|t| t.is_symbol('*'))
+                .unwrap_or(false)
+        {
+            self.pos += 1;
+            lhs = Expr::Binary(Box::new(lhs), BinOp::Mul, Box::new(self.atom()?))
+        }
+        Ok(lhs)
+    }
+
+    fn atom(&mut self) -> Result<Expr, ImportError> {
+        let token = self.eat()?;
+        match &token.kind {
+            TokenKind::Number(n) => n
+                .replace('_', "")
+                .parse()
+                .map(Expr::Int)
+                .map_err(|_| ImportError::new(token.line, format!("Unsupported number {n}"))),
+            TokenKind::Identifier(name) if name == "$clog2" => {
+                self.eat_symbol('(')?;
+                let inner = self.expr()?;
+                self.eat_symbol(')')?;
+                Ok(Expr::Clog2(Box::new(inner)))
+            }
+            TokenKind::Identifier(name) if !name.starts_with(['$', '`']) => {
+                Ok(Expr::Parameter(name.clone()))
+            }
+            TokenKind::Symbol('(') => {
+                let inner = self.expr()?;
+                self.eat_symbol(')')?;
+                Ok(inner)
+            }
+            other => Err(ImportError::new(
+                token.line,
+                format!("Unsupported `{other}` in port width"),
+            )),
+        }
+    }
+
+    fn range(&mut self) -> Result<Option<Range>, ImportError> {
+        if !self.peek_symbol('[') {
+            return Ok(None);
+        }
+        self.eat_symbol('[')?;
+        let msb = self.expr()?;
+        self.eat_symbol(':')?;
+        let lsb = self.expr()?;
+        self.eat_symbol(']')?;
+        if self.peek_symbol('[') {
+            return Err(ImportError::new(
+                self.line(),
+                "Multi dimensional ports are not supported",
+            ));
+        }
+        Ok(Some(Range { msb, lsb }))
+    }
+
+    /// The list of parameters in `#( ... )`
+    fn parameter_list(&mut self) -> Result<Vec<Parameter>, ImportError> {
+        self.eat_symbol('(')?;
+        let mut result = vec![];
+        let mut previous = (false, None);
+        while !self.peek_symbol(')') {
+            let (local, declared_type, parameter) = self.parameter_declaration(previous)?;
+            if !local {
+                result.push(parameter);
+            }
+            previous = (local, declared_type);
+            if !self.peek_symbol(')') {
+                self.eat_symbol(',')?;
+            }
+        }
+        self.eat_symbol(')')?;
+        Ok(result)
+    }
+
+    /// A single parameter. Parameters without a `parameter` or `localparam` keyword inherit
+    /// whether they are local and their declared type from the previous one. Returns
+    /// whether the parameter is local and its declared type along with the parameter
+    fn parameter_declaration(
+        &mut self,
+        previous: (bool, Option<ParameterKind>),
+    ) -> Result<(bool, Option<ParameterKind>, Parameter), ImportError> {
+        let (local, mut declared_type) = match self.eat_if_identifier(&["parameter", "localparam"])
+        {
+            Some(keyword) => (keyword == "localparam", None),
+            None => previous,
+        };
+
+        let line = self.line();
+        if self.eat_if_identifier(&["type"]).is_some() {
+            return Err(ImportError::new(line, "Type parameters are not supported"));
+        }
+        if let Some(ty) = self.eat_if_identifier(INT_PARAMETER_TYPES) {
+            let signed = matches!(ty, "int" | "integer" | "byte" | "shortint" | "longint");
+            declared_type = Some(ParameterKind::Int { signed });
+        } else if self.eat_if_identifier(&["string"]).is_some() {
+            declared_type = Some(ParameterKind::String)
+        } else if let Some(ty) = self.eat_if_identifier(&["real", "realtime", "time"]) {
+            return Err(ImportError::new(
+                line,
+                format!("{ty} parameters are not supported"),
+            ));
+        }
+        if self.eat_if_identifier(&["signed"]).is_some() {
+            declared_type = Some(ParameterKind::Int { signed: true });
+        }
+        // The width of a parameter does not affect how it is passed
+        self.range()?;
+
+        let name = self.identifier()?;
+
+        let default = if self.peek_symbol('=') {
+            self.eat_symbol('=')?;
+            Some(self.until_separator())
+        } else {
+            None
+        };
+
+        // Without a declared type, the type of the default value is used
+        let kind = declared_type.clone().unwrap_or_else(|| {
+            match default.as_ref().and_then(|d| d.first()).map(|t| &t.kind) {
+                Some(TokenKind::String(_)) => ParameterKind::String,
+                Some(TokenKind::Symbol('-')) => ParameterKind::Int { signed: true },
+                _ => ParameterKind::Int { signed: false },
+            }
+        });
+
+        Ok((
+            local,
+            declared_type,
+            Parameter {
+                name,
+                kind,
+                default: default.map(|tokens| tokens.iter().map(|t| t.kind.to_string()).collect()),
+                line,
+            },
+        ))
+    }
+
+    /// Ports declared in the header of ANSI style modules. Direction and type are inherited
+    /// from the previous port unless they are specified
+    fn ansi_ports(&mut self) -> Result<Vec<Port>, ImportError> {
+        let mut result: Vec<Port> = vec![];
+        while !self.peek_symbol(')') {
+            let line = self.line();
+            let direction = match self.eat_if_identifier(&["input", "output", "inout"]) {
+                Some("input") => Some(Direction::Input),
+                Some("output") => Some(Direction::Output),
+                Some("inout") => Some(Direction::Inout),
+                _ => None,
+            };
+            let explicit_type = self.port_type();
+            let signed = self.eat_if_identifier(&["signed"]).is_some();
+            let range = self.range()?;
+            let name = self.identifier()?;
+            if self.peek_symbol('[') {
+                return Err(ImportError::new(
+                    line,
+                    format!("Unpacked array port {name} is not supported"),
+                ));
+            }
+            if self.peek_symbol('.') {
+                return Err(ImportError::new(
+                    line,
+                    format!("Interface port {name} is not supported"),
+                ));
+            }
+
+            let port = match (direction, result.last()) {
+                (Some(direction), _) => Port {
+                    name,
+                    direction,
+                    signed,
+                    range,
+                    line,
+                },
+                // Inherit everything from the previous port if only the name is given
+                (None, Some(prev)) if !explicit_type && !signed && range.is_none() => Port {
+                    name,
+                    line,
+                    ..prev.clone()
+                },
+                (None, Some(prev)) => Port {
+                    name,
+                    direction: prev.direction,
+                    signed,
+                    range,
+                    line,
+                },
+                (None, None) => {
+                    return Err(ImportError::new(
+                        line,
+                        format!("Port {name} has no direction"),
+                    ))
+                }
+            };
+            result.push(port);
+
+            // Default values of ports are not part of the interface
+            self.until_separator();
+            if !self.peek_symbol(')') {
+                self.eat_symbol(',')?;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Skip a net or variable type, returning true if there was one
+    fn port_type(&mut self) -> bool {
+        let mut found = false;
+        while self.eat_if_identifier(PORT_TYPES).is_some() {
+            found = true
+        }
+        found
+    }
+
+    /// Declarations of ports and parameters in the body of non-ANSI style modules
+    fn body_declarations(
+        &mut self,
+        names: &[String],
+        parameters: &mut Vec<Parameter>,
+    ) -> Result<Vec<Port>, ImportError> {
+        let mut ports = vec![];
+        while !self.peek_identifier("endmodule") {
+            let line = self.line();
+            match self.eat_if_identifier(&["input", "output", "inout", "parameter"]) {
+                Some("parameter") => {
+                    self.pos -= 1;
+                    let mut previous = (false, None);
+                    loop {
+                        let (local, declared_type, parameter) =
+                            self.parameter_declaration(previous)?;
+                        previous = (local, declared_type);
+                        parameters.push(parameter);
+                        if self.peek_symbol(',') {
+                            self.eat_symbol(',')?;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                Some(direction) => {
+                    let direction = match direction {
+                        "input" => Direction::Input,
+                        "output" => Direction::Output,
+                        _ => Direction::Inout,
+                    };
+                    self.port_type();
+                    let signed = self.eat_if_identifier(&["signed"]).is_some();
+                    let range = self.range()?;
+                    loop {
+                        let name = self.identifier()?;
+                        ports.push(Port {
+                            name,
+                            direction,
+                            signed,
+                            range: range.clone(),
+                            line,
+                        });
+                        if self.peek_symbol(',') {
+                            self.eat_symbol(',')?;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                None => {
+                    if self.peek().is_none() {
+                        return Err(ImportError::new(line, "Missing endmodule"));
+                    }
+                    self.pos += 1;
+                    continue;
+                }
+            }
+            self.eat_symbol(';')?;
+        }
+
+        // Ports are passed by name, but keep them in the order of the header
+        names
+            .iter()
+            .map(|name| {
+                ports
+                    .iter()
+                    .find(|p| &p.name == name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        ImportError::new(self.line(), format!("Port {name} is never declared"))
+                    })
+            })
+            .collect()
+    }
+
+    fn module(&mut self) -> Result<Module, ImportError> {
+        let name = self.identifier()?;
+        // Skip lifetimes and package imports
+        self.eat_if_identifier(&["automatic", "static"]);
+        while self.eat_if_identifier(&["import"]).is_some() {
+            self.until_separator();
+            self.eat_symbol(';')?;
+        }
+
+        let mut parameters = if self.peek_symbol('#') {
+            self.eat_symbol('#')?;
+            Some(self.parameter_list()?)
+        } else {
+            None
+        };
+
+        let ports = if self.peek_symbol('(') {
+            self.eat_symbol('(')?;
+            let ansi = self.peek_symbol(')')
+                || self
+                    .peek()
+                    .map(|t| {
+                        matches!(&t.kind, TokenKind::Identifier(i)
+                            if matches!(i.as_str(), "input" | "output" | "inout"))
+                    })
+                    .unwrap_or(false);
+            if ansi {
+                let ports = self.ansi_ports()?;
+                self.eat_symbol(')')?;
+                self.eat_symbol(';')?;
+                Some(ports)
+            } else {
+                let mut names = vec![];
+                while !self.peek_symbol(')') {
+                    names.push(self.identifier()?);
+                    if !self.peek_symbol(')') {
+                        self.eat_symbol(',')?;
+                    }
+                }
+                self.eat_symbol(')')?;
+                self.eat_symbol(';')?;
+                // In non-ANSI modules, the ports are declared in the body. Parameters in
+                // the body can only be overridden if there is no parameter list
+                let mut body_parameters = vec![];
+                let ports = self.body_declarations(&names, &mut body_parameters)?;
+                if parameters.is_none() {
+                    parameters = Some(body_parameters);
+                }
+                Some(ports)
+            }
+        } else {
+            self.eat_symbol(';')?;
+            None
+        };
+
+        // Skip the rest of the module
+        while !self.peek_identifier("endmodule") {
+            if self.peek().is_none() {
+                return Err(ImportError::new(self.line(), "Missing endmodule"));
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+
+        Ok(Module {
+            name,
+            parameters: parameters.unwrap_or_default(),
+            ports: ports.unwrap_or_default(),
+        })
+    }
+}
+
+/// Parse the headers of all modules in the tokens of a Verilog file
+pub fn parse_modules(tokens: &[Token]) -> Result<Vec<Module>, ImportError> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let mut result = vec![];
+    while let Some(token) = parser.peek() {
+        parser.pos += 1;
+        if token.is_identifier("module") || token.is_identifier("macromodule") {
+            result.push(parser.module()?);
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use pretty_assertions::assert_eq;
+
+    fn parse(source: &str) -> Vec<Module> {
+        parse_modules(&tokenize(source).unwrap()).unwrap()
+    }
+
+    fn param(name: &str) -> Box<Expr> {
+        Box::new(Expr::Parameter(name.to_string()))
+    }
+
+    #[test]
+    fn ansi_headers_parse() {
+        let modules = parse(
+            "module fifo #(parameter WIDTH = 8, DEPTH = 4, parameter MODE = \"FAST\")
+                (input wire clk, input [WIDTH-1:0] din, b, output reg signed [7:0] q);
+                assign q = 0;
+             endmodule",
+        );
+        assert_eq!(
+            modules,
+            vec![Module {
+                name: "fifo".to_string(),
+                parameters: vec![
+                    Parameter {
+                        name: "WIDTH".to_string(),
+                        kind: ParameterKind::Int { signed: false },
+                        default: Some("8".to_string()),
+                        line: 1
+                    },
+                    Parameter {
+                        name: "DEPTH".to_string(),
+                        kind: ParameterKind::Int { signed: false },
+                        default: Some("4".to_string()),
+                        line: 1
+                    },
+                    Parameter {
+                        name: "MODE".to_string(),
+                        kind: ParameterKind::String,
+                        default: Some("\"FAST\"".to_string()),
+                        line: 1
+                    },
+                ],
+                ports: vec![
+                    Port {
+                        name: "clk".to_string(),
+                        direction: Direction::Input,
+                        signed: false,
+                        range: None,
+                        line: 2
+                    },
+                    Port {
+                        name: "din".to_string(),
+                        direction: Direction::Input,
+                        signed: false,
+                        range: Some(Range {
+                            msb: Expr::Binary(param("WIDTH"), BinOp::Sub, Box::new(Expr::Int(1))),
+                            lsb: Expr::Int(0)
+                        }),
+                        line: 2
+                    },
+                    Port {
+                        name: "b".to_string(),
+                        direction: Direction::Input,
+                        signed: false,
+                        range: Some(Range {
+                            msb: Expr::Binary(param("WIDTH"), BinOp::Sub, Box::new(Expr::Int(1))),
+                            lsb: Expr::Int(0)
+                        }),
+                        line: 2
+                    },
+                    Port {
+                        name: "q".to_string(),
+                        direction: Direction::Output,
+                        signed: true,
+                        range: Some(Range {
+                            msb: Expr::Int(7),
+                            lsb: Expr::Int(0)
+                        }),
+                        line: 2
+                    },
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn non_ansi_headers_parse() {
+        let modules = parse(
+            "module counter(clk, q);
+                parameter integer N = 4;
+                input clk;
+                output [$clog2(N):0] q;
+                reg x;
+             endmodule",
+        );
+        assert_eq!(
+            modules,
+            vec![Module {
+                name: "counter".to_string(),
+                parameters: vec![Parameter {
+                    name: "N".to_string(),
+                    kind: ParameterKind::Int { signed: true },
+                    default: Some("4".to_string()),
+                    line: 2
+                }],
+                ports: vec![
+                    Port {
+                        name: "clk".to_string(),
+                        direction: Direction::Input,
+                        signed: false,
+                        range: None,
+                        line: 3
+                    },
+                    Port {
+                        name: "q".to_string(),
+                        direction: Direction::Output,
+                        signed: false,
+                        range: Some(Range {
+                            msb: Expr::Clog2(param("N")),
+                            lsb: Expr::Int(0)
+                        }),
+                        line: 4
+                    },
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn local_parameters_are_skipped() {
+        let modules = parse(
+            "module a #(parameter A = 1, localparam B = A * 2) (); endmodule
+             module b; endmodule",
+        );
+        assert_eq!(
+            modules
+                .iter()
+                .map(|m| (m.name.as_str(), m.parameters.len()))
+                .collect::<Vec<_>>(),
+            vec![("a", 1), ("b", 0)]
+        );
+    }
+
+    #[test]
+    fn unsupported_ports_are_errors() {
+        let tokens = tokenize("module a(input [7:0] x [3:0]); endmodule").unwrap();
+        assert_eq!(
+            parse_modules(&tokens),
+            Err(ImportError::new(
+                1,
+                "Unpacked array port x is not supported"
+            ))
+        );
+    }
+}
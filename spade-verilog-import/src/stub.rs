@@ -0,0 +1,401 @@
+use itertools::Itertools;
+use logos::Logos;
+use spade_parser::lexer::TokenKind as SpadeToken;
+
+use crate::parser::{BinOp, Direction, Expr, Module, ParameterKind, Port};
+use crate::ImportError;
+
+/// Verilog ports and parameters are bound by name, so their names must be usable as
+/// spade identifiers without renaming
+fn check_identifier(name: &str, what: &str, line: usize) -> Result<(), ImportError> {
+    let mut lexer = SpadeToken::lexer(name);
+    match (lexer.next(), lexer.next()) {
+        (Some(Ok(SpadeToken::Identifier(_))), None) => Ok(()),
+        _ => Err(ImportError::new(
+            line,
+            format!("The {what} {name} is not a valid spade identifier"),
+        )),
+    }
+}
+
+impl Expr {
+    /// The value of the expression if it is a constant which fits in an i128
+    fn eval(&self) -> Option<i128> {
+        match self {
+            Expr::Int(i) => Some(*i as i128),
+            Expr::Parameter(_) => None,
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval()?, rhs.eval()?);
+                match op {
+                    BinOp::Add => lhs.checked_add(rhs),
+                    BinOp::Sub => lhs.checked_sub(rhs),
+                    BinOp::Mul => lhs.checked_mul(rhs),
+                }
+            }
+            Expr::Clog2(inner) => {
+                let inner = inner.eval()?;
+                let mut bits = 0;
+                // 1 << 127 is negative, so the shift has overflowed if the result is not positive
+                while 1i128.checked_shl(bits).filter(|v| *v > 0)? < inner {
+                    bits += 1
+                }
+                Some(bits as i128)
+            }
+        }
+    }
+
+    /// The expression as a spade type expression. Compound expressions are not wrapped
+    /// in `{}`
+    fn to_spade(&self) -> String {
+        match self {
+            Expr::Int(i) => i.to_string(),
+            Expr::Parameter(p) => p.clone(),
+            Expr::Binary(lhs, op, rhs) => {
+                let op = match op {
+                    BinOp::Add => "+",
+                    BinOp::Sub => "-",
+                    BinOp::Mul => "*",
+                };
+                let operand = |e: &Expr| match e {
+                    Expr::Binary(..) => format!("({})", e.to_spade()),
+                    Expr::Clog2(..) if e.eval().is_none() => format!("({})", e.to_spade()),
+                    _ => e.to_spade(),
+                };
+                format!("{} {op} {}", operand(lhs), operand(rhs))
+            }
+            // $clog2(x) is the number of bits needed to store x-1, which is one less than
+            // the number of bits needed to store 2x-1. Unlike x-1, 2x-1 is not 0 for x = 1,
+            // where uint_bits_to_fit would give 1 rather than 0
+            Expr::Clog2(inner) => match self.eval() {
+                Some(value) => Expr::Int(value as u64).to_spade(),
+                None => {
+                    let double = Expr::Binary(Box::new(Expr::Int(2)), BinOp::Mul, inner.clone());
+                    format!(
+                        "uint_bits_to_fit({}) - 1",
+                        Expr::Binary(Box::new(double), BinOp::Sub, Box::new(Expr::Int(1)))
+                            .to_spade()
+                    )
+                }
+            },
+        }
+    }
+}
+
+/// The width of a port with a range
+#[derive(Debug, PartialEq)]
+enum Width {
+    /// A number or a single parameter, which can be used directly in a type
+    Simple(String),
+    /// An expression of parameters. Spade does not allow these in argument types, so they
+    /// have to be given a name by a `where` clause
+    Derived(String),
+}
+
+fn port_width(port: &Port) -> Option<Width> {
+    let range = port.range.as_ref()?;
+
+    if let (Some(msb), Some(lsb)) = (range.msb.eval(), range.lsb.eval()) {
+        return Some(Width::Simple(((msb - lsb).abs() + 1).to_string()));
+    }
+
+    // Ranges are almost always [N-1:0] or [N:0], in which case the width is N or N+1.
+    // Otherwise, the width is computed as msb - lsb + 1 which only works for descending
+    // ranges, since the parameter values are not known
+    let (high, low) = match (range.msb.eval(), range.lsb.eval()) {
+        (Some(_), None) => (&range.lsb, &range.msb),
+        _ => (&range.msb, &range.lsb),
+    };
+    let width = match (high, low.eval()) {
+        (Expr::Binary(n, BinOp::Sub, one), Some(0)) if one.eval() == Some(1) => (**n).clone(),
+        (_, Some(0)) => Expr::Binary(Box::new(high.clone()), BinOp::Add, Box::new(Expr::Int(1))),
+        _ => Expr::Binary(
+            Box::new(Expr::Binary(
+                Box::new(high.clone()),
+                BinOp::Sub,
+                Box::new(low.clone()),
+            )),
+            BinOp::Add,
+            Box::new(Expr::Int(1)),
+        ),
+    };
+
+    Some(match width {
+        Expr::Int(_) | Expr::Parameter(_) => Width::Simple(width.to_spade()),
+        _ => match width.eval() {
+            Some(value) => Width::Simple(value.to_string()),
+            None => Width::Derived(width.to_spade()),
+        },
+    })
+}
+
+fn is_clock_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    matches!(name.as_str(), "clk" | "clock")
+        || ["clk_", "clock_"].iter().any(|p| name.starts_with(p))
+        || ["_clk", "_clock"].iter().any(|s| name.ends_with(s))
+}
+
+/// The spade type of the value carried by a port with the specified width
+fn port_type(port: &Port, width: Option<&str>) -> String {
+    match width {
+        None if port.direction == Direction::Input && is_clock_name(&port.name) => {
+            "clock".to_string()
+        }
+        None => "bool".to_string(),
+        Some(width) if port.signed => format!("int<{width}>"),
+        Some(width) => format!("uint<{width}>"),
+    }
+}
+
+/// Generate a `#[no_mangle]` `__builtin__` entity which instantiates the Verilog module.
+///
+/// Inputs are passed as values, inouts as `inout<T>` and outputs as `&mut T` since the
+/// return value of an entity is always bound to the `output__` port. An output named
+/// `output__` is therefore used as the return type. Integer parameters become generics
+/// and string parameters are passed with their default values. Widths which depend on
+/// several parameters get a generic of their own, defined by a `where` clause
+pub fn generate_stub(module: &Module) -> Result<String, ImportError> {
+    check_identifier(&module.name, "module name", 1)?;
+
+    let mut result = vec![];
+    result.push(format!(
+        "// Generated from the Verilog module {}",
+        module.name
+    ));
+
+    let mut generics = vec![];
+    let mut constants = vec![];
+    for param in &module.parameters {
+        check_identifier(&param.name, "parameter", param.line)?;
+        match &param.kind {
+            ParameterKind::Int { signed } => {
+                let meta = if *signed { "#int" } else { "#uint" };
+                if let Some(default) = &param.default {
+                    result.push(format!("// {} defaults to {default}", param.name));
+                }
+                generics.push(format!("{meta} {}", param.name))
+            }
+            ParameterKind::String => match &param.default {
+                Some(default) => constants.push(format!("{} = {default}", param.name)),
+                None => {
+                    return Err(ImportError::new(
+                        param.line,
+                        format!("String parameter {} has no default value", param.name),
+                    ))
+                }
+            },
+        }
+    }
+
+    result.push("#[no_mangle]".to_string());
+    if !constants.is_empty() {
+        result.push(format!(
+            "#[verilog_parameters({})]",
+            constants.iter().join(", ")
+        ));
+    }
+
+    let mut inputs = vec![];
+    let mut output = None;
+    let mut where_clauses = vec![];
+    for port in &module.ports {
+        check_identifier(&port.name, "port", port.line)?;
+        let width = match port_width(port) {
+            Some(Width::Simple(width)) => Some(width),
+            Some(Width::Derived(expr)) => {
+                let mut name = format!("{}_WIDTH", port.name.to_uppercase());
+                while module.parameters.iter().any(|p| p.name == name) {
+                    name.push('_');
+                }
+                generics.push(format!("#uint {name}"));
+                where_clauses.push(format!("{name}: {{ {expr} }}"));
+                Some(name)
+            }
+            None => None,
+        };
+        let ty = port_type(port, width.as_deref());
+        match port.direction {
+            Direction::Output if port.name == "output__" => output = Some(ty),
+            Direction::Output => inputs.push(format!("#[no_mangle] {}: &mut {ty}", port.name)),
+            Direction::Input => inputs.push(format!("#[no_mangle] {}: {ty}", port.name)),
+            Direction::Inout => inputs.push(format!("#[no_mangle] {}: inout<{ty}>", port.name)),
+        }
+    }
+
+    let generics = if generics.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", generics.join(", "))
+    };
+    let inputs = if inputs.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "\n{}\n",
+            inputs.iter().map(|i| format!("    {i},")).join("\n")
+        )
+    };
+    let output = output.map(|o| format!(" -> {o}")).unwrap_or_default();
+    let where_clauses = if where_clauses.is_empty() {
+        String::new()
+    } else {
+        format!("\n    where {}\n", where_clauses.join(",\n        "))
+    };
+    let builtin = if where_clauses.is_empty() {
+        " __builtin__"
+    } else {
+        "__builtin__"
+    };
+
+    result.push(format!(
+        "entity {}{generics}({inputs}){output}{where_clauses}{builtin}",
+        module.name
+    ));
+
+    Ok(result.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Range;
+
+    fn port(msb: Expr, lsb: Expr) -> Port {
+        Port {
+            name: "p".to_string(),
+            direction: Direction::Input,
+            signed: false,
+            range: Some(Range { msb, lsb }),
+            line: 1,
+        }
+    }
+
+    fn param(name: &str) -> Expr {
+        Expr::Parameter(name.to_string())
+    }
+
+    fn bin(lhs: Expr, op: BinOp, rhs: Expr) -> Expr {
+        Expr::Binary(Box::new(lhs), op, Box::new(rhs))
+    }
+
+    fn simple(w: &str) -> Width {
+        Width::Simple(w.to_string())
+    }
+
+    fn derived(w: &str) -> Width {
+        Width::Derived(w.to_string())
+    }
+
+    #[test]
+    fn widths_are_simplified() {
+        let cases = [
+            (port(Expr::Int(7), Expr::Int(0)), simple("8")),
+            (port(Expr::Int(0), Expr::Int(7)), simple("8")),
+            (
+                port(bin(param("W"), BinOp::Sub, Expr::Int(1)), Expr::Int(0)),
+                simple("W"),
+            ),
+            (port(param("N"), Expr::Int(0)), derived("N + 1")),
+            (
+                port(
+                    bin(
+                        bin(Expr::Int(2), BinOp::Mul, param("W")),
+                        BinOp::Sub,
+                        Expr::Int(1),
+                    ),
+                    Expr::Int(0),
+                ),
+                derived("2 * W"),
+            ),
+            (
+                port(
+                    bin(Expr::Clog2(Box::new(param("D"))), BinOp::Sub, Expr::Int(1)),
+                    Expr::Int(0),
+                ),
+                derived("uint_bits_to_fit((2 * D) - 1) - 1"),
+            ),
+            (
+                port(Expr::Clog2(Box::new(param("D"))), Expr::Int(0)),
+                derived("(uint_bits_to_fit((2 * D) - 1) - 1) + 1"),
+            ),
+            // $clog2(1) and $clog2(0) are both 0
+            (
+                port(Expr::Clog2(Box::new(Expr::Int(1))), Expr::Int(0)),
+                simple("1"),
+            ),
+            (
+                port(Expr::Clog2(Box::new(Expr::Int(0))), Expr::Int(0)),
+                simple("1"),
+            ),
+            (
+                port(
+                    bin(
+                        Expr::Clog2(Box::new(Expr::Int(17))),
+                        BinOp::Sub,
+                        Expr::Int(1),
+                    ),
+                    Expr::Int(0),
+                ),
+                simple("5"),
+            ),
+            (port(param("H"), param("L")), derived("(H - L) + 1")),
+            (
+                port(
+                    bin(Expr::Clog2(Box::new(Expr::Int(17))), BinOp::Mul, param("W")),
+                    Expr::Int(0),
+                ),
+                derived("(5 * W) + 1"),
+            ),
+            // Constants which overflow are kept as expressions
+            (
+                port(
+                    bin(
+                        Expr::Int(u64::MAX),
+                        BinOp::Mul,
+                        bin(Expr::Int(u64::MAX), BinOp::Mul, Expr::Int(2)),
+                    ),
+                    Expr::Int(0),
+                ),
+                derived("(18446744073709551615 * (18446744073709551615 * 2)) + 1"),
+            ),
+            (
+                port(
+                    Expr::Clog2(Box::new(bin(
+                        Expr::Int(u64::MAX),
+                        BinOp::Mul,
+                        Expr::Int(u64::MAX >> 1),
+                    ))),
+                    Expr::Int(0),
+                ),
+                derived(
+                    "(uint_bits_to_fit((2 * (18446744073709551615 * 9223372036854775807)) - 1) - 1) + 1",
+                ),
+            ),
+        ];
+        for (port, expected) in cases {
+            assert_eq!(port_width(&port), Some(expected), "{port:?}");
+        }
+    }
+
+    #[test]
+    fn single_bit_clock_inputs_are_clocks() {
+        for name in ["clk", "CLK", "clk_i", "sys_clk", "clock_fast"] {
+            assert!(is_clock_name(name), "{name}");
+        }
+        for name in ["clkdiv", "block"] {
+            assert!(!is_clock_name(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn keywords_are_not_valid_names() {
+        assert!(check_identifier("data", "port", 1).is_ok());
+        assert_eq!(
+            check_identifier("reg", "port", 3),
+            Err(ImportError::new(
+                3,
+                "The port reg is not a valid spade identifier"
+            ))
+        );
+    }
+}
@@ -155,6 +155,25 @@ impl AAForm {
         )
     }
 
+    /// The smallest integer range containing all values the form can take
+    fn to_range(&self) -> Range {
+        let mid = self.mid();
+        let rad = self.rad();
+        Range::new(
+            (mid.clone() - rad.clone()).floor().to_integer(),
+            (mid + rad).ceil().to_integer(),
+        )
+    }
+
+    fn scale(&self, factor: &BigRational) -> AAForm {
+        AAForm(
+            self.0
+                .iter()
+                .map(|(v, s)| (*v, s.clone() * factor.clone()))
+                .collect(),
+        )
+    }
+
     // Operations
 
     fn mul(&self, tracker: &mut AAVarTracker, other: &Self) -> Self {
@@ -235,6 +254,38 @@ impl AAForm {
     fn neg(&self) -> Self {
         AAForm(self.0.clone().into_iter().map(|(v, s)| (v, -s)).collect())
     }
+
+    fn shl(&self, amount: u32) -> Self {
+        self.scale(&BigRational::from_integer(BigInt::from(1) << amount))
+    }
+
+    fn concat(&self, tracker: &mut AAVarTracker, lsb: &Self, lsb_width: u32) -> Self {
+        // A non-negative lsb is added as is, which keeps the correlation between the two parts
+        let lsb_range = lsb.to_range();
+        if lsb_range.lo().is_negative() {
+            let unsigned = Range::new(BigInt::zero(), (BigInt::from(1) << lsb_width) - 1);
+            self.shl(lsb_width)
+                .add(&AAForm::from_range(tracker, unsigned))
+        } else {
+            self.shl(lsb_width).add(lsb)
+        }
+    }
+
+    fn wrap(&self, tracker: &mut AAVarTracker, width: u32) -> Self {
+        self.map_range(tracker, |range| range.wrap(width))
+    }
+
+    /// Applies `f` to the range of the form. The correlations are kept if the range is
+    /// unchanged
+    fn map_range(&self, tracker: &mut AAVarTracker, f: impl FnOnce(&Range) -> Range) -> Self {
+        let range = self.to_range();
+        let mapped = f(&range);
+        if mapped == range {
+            self.clone()
+        } else {
+            AAForm::from_range(tracker, mapped)
+        }
+    }
 }
 
 fn evaluate_aa(
@@ -286,6 +337,44 @@ fn evaluate_aa(
             (Some(a), Some(b)) => Some(a.union(tracker, &b)),
             _ => None,
         },
+        Equation::Shl(a, amount) => evaluate_aa(tracker, a, known).map(|x| x.shl(*amount)),
+        // Rounding is not affine, so these go through the range of the operand
+        Equation::Shr(a, amount, width) => evaluate_aa(tracker, a, known)
+            .map(|x| AAForm::from_range(tracker, x.to_range().shr(*amount, *width))),
+        Equation::AShr(a, amount) => evaluate_aa(tracker, a, known)
+            .map(|x| AAForm::from_range(tracker, x.to_range().ashr(*amount))),
+        Equation::DivPow2(a, pow) => match (
+            evaluate_aa(tracker, a, known),
+            evaluate_aa(tracker, pow, known),
+        ) {
+            (Some(a), Some(pow)) => Some(AAForm::from_range(
+                tracker,
+                a.to_range().div_pow2(&pow.to_range()),
+            )),
+            _ => None,
+        },
+        Equation::Concat(a, b, b_width) => match (
+            evaluate_aa(tracker, a, known),
+            evaluate_aa(tracker, b, known),
+        ) {
+            (Some(a), Some(b)) => Some(a.concat(tracker, &b, *b_width)),
+            _ => None,
+        },
+        Equation::Wrap(a, width) => evaluate_aa(tracker, a, known).map(|x| x.wrap(tracker, *width)),
+        Equation::AtMost(a, bound) => match (
+            evaluate_aa(tracker, a, known),
+            evaluate_aa(tracker, bound, known),
+        ) {
+            (Some(a), Some(bound)) => Some(a.map_range(tracker, |r| r.at_most(&bound.to_range()))),
+            _ => None,
+        },
+        Equation::AtLeast(a, bound) => match (
+            evaluate_aa(tracker, a, known),
+            evaluate_aa(tracker, bound, known),
+        ) {
+            (Some(a), Some(bound)) => Some(a.map_range(tracker, |r| r.at_least(&bound.to_range()))),
+            _ => None,
+        },
     }
 }
 
@@ -293,14 +382,5 @@ pub fn evaluate_aa_and_simplify_to_range(
     body: &Equation,
     known: &BTreeMap<Var, Range>,
 ) -> Option<Range> {
-    if let Some(aa_expr) = evaluate_aa(&mut AAVarTracker::new(), body, known) {
-        let mid = aa_expr.mid();
-        let rad = aa_expr.rad();
-        Some(Range::new(
-            BigRational::to_integer(&(mid.clone() - rad.clone()).floor()),
-            BigRational::to_integer(&(mid + rad).ceil()),
-        ))
-    } else {
-        None
-    }
+    evaluate_aa(&mut AAVarTracker::new(), body, known).map(|aa_expr| aa_expr.to_range())
 }
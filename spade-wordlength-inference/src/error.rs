@@ -2,6 +2,8 @@ use spade_common::location_info::Loc;
 use spade_diagnostics::Diagnostic;
 use spade_macros::IntoDiagnostic;
 
+use crate::range::Range;
+
 #[derive(IntoDiagnostic)]
 #[diagnostic(
     error,
@@ -12,13 +14,73 @@ pub struct UnificationError {
     pub at: Loc<()>,
 }
 
-#[derive(IntoDiagnostic)]
-#[diagnostic(error, "Word length mismatch. Got {} bits but expected {} bits", diag.inferred, diag.typechecked)]
+/// A value which does not fit in its type
 pub struct WordlengthMismatch {
-    #[diagnostic(primary, "Got {} bits, expected {}", diag.inferred, diag.typechecked)]
     pub inferred_at: Loc<()>,
+    /// The location of the type of the value, if it is known
+    pub annotation: Option<Loc<()>>,
+    pub range: Range,
     pub inferred: u32,
     pub typechecked: u32,
 }
 
+impl From<WordlengthMismatch> for Diagnostic {
+    fn from(diag: WordlengthMismatch) -> Self {
+        let result = Diagnostic::error(
+            diag.inferred_at,
+            format!(
+                "Word length mismatch. Got {} bits but expected {} bits",
+                diag.inferred, diag.typechecked
+            ),
+        )
+        .primary_label(format!(
+            "This value is in {} which needs {} bits",
+            diag.range, diag.inferred
+        ));
+        with_annotation(result, &diag.inferred_at, diag.annotation, diag.typechecked)
+    }
+}
+
+/// A type which is wider than any value it holds
+pub struct OversizedWordlength {
+    pub at: Loc<()>,
+    pub annotation: Option<Loc<()>>,
+    pub range: Range,
+    pub inferred: u32,
+    pub typechecked: u32,
+}
+
+impl From<OversizedWordlength> for Diagnostic {
+    fn from(diag: OversizedWordlength) -> Self {
+        let result = Diagnostic::warning(
+            diag.at,
+            format!(
+                "Word length is larger than required. Got {} bits but {} bits are enough",
+                diag.typechecked, diag.inferred
+            ),
+        )
+        .primary_label(format!(
+            "This value is in {} which needs {} bits",
+            diag.range, diag.inferred
+        ));
+        with_annotation(result, &diag.at, diag.annotation, diag.typechecked)
+    }
+}
+
+fn with_annotation(
+    diag: Diagnostic,
+    value: &Loc<()>,
+    annotation: Option<Loc<()>>,
+    typechecked: u32,
+) -> Diagnostic {
+    match annotation {
+        Some(annotation)
+            if (annotation.file_id, annotation.span) != (value.file_id, value.span) =>
+        {
+            diag.secondary_label(annotation, format!("The type has {typechecked} bits"))
+        }
+        _ => diag,
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Diagnostic>;
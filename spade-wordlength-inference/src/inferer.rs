@@ -1,7 +1,9 @@
 use std::collections::btree_map::Entry;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
+use num::ToPrimitive;
 use spade_common::location_info::{Loc, WithLocation};
+use spade_common::name::{Identifier, NameID, Path};
 use spade_hir::expression::NamedArgument;
 use spade_hir::symbol_table::SymbolTable;
 use spade_hir::{
@@ -10,6 +12,7 @@ use spade_hir::{
 };
 use spade_hir::{ArgumentList, Expression};
 use spade_typeinference::{equation::TypeVar, fixed_types::t_int, HasType, TypeState};
+use spade_types::KnownType;
 
 use crate::range::Range;
 use crate::{error, InferMethod, Res};
@@ -28,8 +31,34 @@ pub enum Equation {
     Neg(Box<Equation>),
     BitManipMax(Box<Equation>, Box<Equation>),
     Union(Box<Equation>, Box<Equation>),
+    /// Multiplication by 2^amount. Overflow must be handled by wrapping the result
+    Shl(Box<Equation>, u32),
+    /// Logical right shift by a constant of a value which is the given number of bits wide
+    Shr(Box<Equation>, u32, u32),
+    /// Arithmetic right shift by a constant
+    AShr(Box<Equation>, u32),
+    /// Division by 2^pow rounding towards zero
+    DivPow2(Box<Equation>, Box<Equation>),
+    /// Concatenation of the bits of the first value with the bits of the second value which is
+    /// the given number of bits wide
+    Concat(Box<Equation>, Box<Equation>, u32),
+    /// Truncation to the given number of bits
+    Wrap(Box<Equation>, u32),
+    /// The first value where it is known to be at most the second value
+    AtMost(Box<Equation>, Box<Equation>),
+    /// The first value where it is known to be at least the second value
+    AtLeast(Box<Equation>, Box<Equation>),
 }
 
+/// A bound on a variable in a branch of an `if` whose condition compares the variable
+#[derive(Debug, Clone)]
+enum Bound {
+    AtMost(Equation),
+    AtLeast(Equation),
+}
+
+type Bounds = Vec<(NameID, Bound)>;
+
 impl WithLocation for Equation {}
 
 pub struct Inferer<'a> {
@@ -40,6 +69,8 @@ pub struct Inferer<'a> {
     pub(crate) var_counter: usize,
     pub(crate) symtab: &'a SymbolTable,
     pub(crate) type_state: &'a mut TypeState,
+    /// The bounds on variables in the branches currently being visited
+    bounds: Bounds,
 }
 impl<'a> Inferer<'a> {
     pub fn new(type_state: &'a mut TypeState, symtab: &'a SymbolTable) -> Self {
@@ -50,6 +81,7 @@ impl<'a> Inferer<'a> {
             var_counter: 0,
             symtab,
             type_state,
+            bounds: vec![],
         }
     }
 
@@ -82,6 +114,19 @@ impl<'a> Inferer<'a> {
         }
     }
 
+    /// The number of bits of `thing` if it is an integer of known size
+    fn int_width(&self, thing: &Loc<Expression>) -> Option<u32> {
+        match thing.get_type(self.type_state) {
+            Ok(TypeVar::Known(_, t, params)) if t == t_int(self.symtab) => {
+                match params.as_slice() {
+                    [TypeVar::Known(_, KnownType::Integer(size), _)] => size.to_u32(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
     fn maybe_add_equation(&mut self, thing: &Loc<Expression>, maybe_eq: Option<Equation>) {
         if let (Some(var), Some(eq)) = (self.find_or_create(thing), maybe_eq) {
             self.equations.push((var, eq.at_loc(thing)))
@@ -90,13 +135,16 @@ impl<'a> Inferer<'a> {
 
     pub fn expression(&mut self, expr: &Loc<Expression>) -> Res {
         let maybe_eq = match &expr.inner.kind {
-            ExprKind::Identifier(_) => self.find_or_create(expr).map(Equation::V),
+            ExprKind::Identifier(name) => self
+                .find_or_create(expr)
+                .map(|var| self.bounded(name, Equation::V(var))),
             ExprKind::IntLiteral(literal, _) => Some(Equation::Constant(Range::new(
                 // FIXME: Use size
                 literal.clone(),
                 literal.clone(),
             ))),
-            ExprKind::TypeLevelInteger(_) => todo!(),
+            // The value of a type level integer is not known until monomorphisation
+            ExprKind::TypeLevelInteger(_) => None,
 
             ExprKind::BinaryOperator(lhs, op, rhs) => self.binary_operator(lhs, op.inner, rhs)?,
             ExprKind::UnaryOperator(op, v) => self.unary_operator(*op, v)?,
@@ -141,10 +189,7 @@ impl<'a> Inferer<'a> {
                 self.expression(target)?;
                 None
             }
-            ExprKind::Call { args, .. } => {
-                self.visit_args(args)?;
-                None
-            }
+            ExprKind::Call { callee, args, .. } => self.call(expr, callee, args)?,
             ExprKind::MethodCall { target, args, .. } => {
                 self.expression(target)?;
                 self.visit_args(args)?;
@@ -188,6 +233,8 @@ impl<'a> Inferer<'a> {
                 ty: _,
                 value,
                 wal_trace,
+                memory_init: _,
+                verilog_attrs: _,
             }) => {
                 if let Some(wal_trace) = wal_trace {
                     if let Some(expr) = &wal_trace.rst {
@@ -202,6 +249,16 @@ impl<'a> Inferer<'a> {
             Statement::Assert(expr) => {
                 self.expression(expr)?;
             }
+            Statement::Property(property) => {
+                self.expression(&property.clock)?;
+                if let Some(reset) = &property.reset {
+                    self.expression(reset)?;
+                }
+                if let Some((antecedent, _)) = &property.antecedent {
+                    self.expression(antecedent)?;
+                }
+                self.expression(&property.consequent)?;
+            }
 
             Statement::Set { target, value } => {
                 self.expression(target)?;
@@ -236,17 +293,19 @@ impl<'a> Inferer<'a> {
     ) -> Res {
         // NOTE: Conditions can contain integer operations
         self.expression(value)?;
-        // NOTE: This unification works if the range contains zero - which it kinda always does
-        // here, but it can cause overestimations!
         // NOTE: It's fine that we don't visit the pattern since there cannot be an expression in
         // it.
-        let mut eq = Equation::Constant(Range::zero());
+        let mut eq: Option<Option<Equation>> = None;
         for (_, body) in patterns {
-            if let Some(b) = self.expression(body)? {
-                eq = Equation::Union(Box::new(eq), Box::new(b));
-            }
+            let b = self.expression(body)?;
+            // If any arm is opaque, so is the result of the match
+            eq = Some(match (eq, b) {
+                (None, b) => b,
+                (Some(Some(eq)), Some(b)) => Some(Equation::Union(Box::new(eq), Box::new(b))),
+                (Some(_), _) => None,
+            });
         }
-        Ok(Some(eq))
+        Ok(eq.flatten())
     }
 
     fn if_(
@@ -255,14 +314,69 @@ impl<'a> Inferer<'a> {
         true_: &Loc<Expression>,
         false_: &Loc<Expression>,
     ) -> Res {
-        // NOTE: Conditions can contain integer operations
-        self.expression(value)?;
-        Ok(match (self.expression(true_)?, self.expression(false_)?) {
+        let (true_bounds, false_bounds) = self.condition(value)?;
+        let true_ = self.with_bounds(true_bounds, |s| s.expression(true_))?;
+        let false_ = self.with_bounds(false_bounds, |s| s.expression(false_))?;
+        Ok(match (true_, false_) {
             (Some(true_), Some(false_)) => Some(Equation::Union(Box::new(true_), Box::new(false_))),
             _ => None,
         })
     }
 
+    /// Visits the condition of an `if` and returns the bounds on variables which hold when
+    /// it is true and when it is false. Only comparisons give bounds
+    fn condition(&mut self, cond: &Loc<Expression>) -> error::Result<(Bounds, Bounds)> {
+        let ExprKind::BinaryOperator(lhs, op, rhs) = &cond.inner.kind else {
+            // NOTE: Conditions can contain integer operations
+            self.expression(cond)?;
+            return Ok((vec![], vec![]));
+        };
+        // The condition itself is a bool and needs no equation, so visiting the operands
+        // is all that visiting the condition would do
+        let (Some(lhs_t), Some(rhs_t)) = (self.expression(lhs)?, self.expression(rhs)?) else {
+            return Ok((vec![], vec![]));
+        };
+        let l = Operand {
+            expr: lhs,
+            eq: lhs_t,
+        };
+        let r = Operand {
+            expr: rhs,
+            eq: rhs_t,
+        };
+
+        let (when_true, when_false) = match op.inner {
+            BinaryOperator::Lt => (l.at_most(&r, -1), r.at_most(&l, 0)),
+            BinaryOperator::Le => (l.at_most(&r, 0), r.at_most(&l, -1)),
+            BinaryOperator::Gt => (r.at_most(&l, -1), l.at_most(&r, 0)),
+            BinaryOperator::Ge => (r.at_most(&l, 0), l.at_most(&r, -1)),
+            BinaryOperator::Eq => ([l.at_most(&r, 0), r.at_most(&l, 0)].concat(), vec![]),
+            BinaryOperator::NotEq => (vec![], [l.at_most(&r, 0), r.at_most(&l, 0)].concat()),
+            _ => (vec![], vec![]),
+        };
+        Ok((when_true, when_false))
+    }
+
+    /// Runs `f` with `bounds` holding in addition to the current bounds
+    fn with_bounds(&mut self, bounds: Bounds, f: impl FnOnce(&mut Self) -> Res) -> Res {
+        let outer = self.bounds.len();
+        self.bounds.extend(bounds);
+        let result = f(self);
+        self.bounds.truncate(outer);
+        result
+    }
+
+    /// `value` narrowed by the bounds on `name`
+    fn bounded(&self, name: &NameID, value: Equation) -> Equation {
+        self.bounds
+            .iter()
+            .filter(|(n, _)| n == name)
+            .fold(value, |value, (_, bound)| match bound {
+                Bound::AtMost(b) => Equation::AtMost(Box::new(value), Box::new(b.clone())),
+                Bound::AtLeast(b) => Equation::AtLeast(Box::new(value), Box::new(b.clone())),
+            })
+    }
+
     fn binary_operator(
         &mut self,
         lhs: &Loc<Expression>,
@@ -285,19 +399,41 @@ impl<'a> Inferer<'a> {
             (BinaryOperator::Mul, Some(lhs_t), Some(rhs_t)) => {
                 Some(Equation::Mul(Box::new(lhs_t), Box::new(rhs_t)))
             }
-            (BinaryOperator::Div, _, _) => todo!(),
-            (BinaryOperator::Mod, _, _) => todo!(),
+            // The magnitude of the quotient and the remainder are both bounded by the magnitude
+            // of the dividend
+            (BinaryOperator::Div | BinaryOperator::Mod, Some(lhs_t), _) => {
+                Some(Equation::BitManpi(Box::new(lhs_t)))
+            }
 
+            // Shifts by constants are tracked exactly, the left value is the one being shifted
+            (BinaryOperator::LeftShift, Some(v), Some(amount)) => {
+                match (constant_amount(&amount), self.int_width(lhs)) {
+                    (Some(amount), Some(width)) => Some(Equation::Wrap(
+                        Box::new(Equation::Shl(Box::new(v), amount)),
+                        width,
+                    )),
+                    _ => Some(Equation::BitManpi(Box::new(v))),
+                }
+            }
+            (BinaryOperator::RightShift, Some(v), Some(amount)) => {
+                match (constant_amount(&amount), self.int_width(lhs)) {
+                    (Some(amount), Some(width)) => Some(Equation::Shr(Box::new(v), amount, width)),
+                    _ => Some(Equation::BitManpi(Box::new(v))),
+                }
+            }
+            (BinaryOperator::ArithmeticRightShift, Some(v), Some(amount)) => {
+                match constant_amount(&amount) {
+                    Some(amount) => Some(Equation::AShr(Box::new(v), amount)),
+                    None => Some(Equation::BitManpi(Box::new(v))),
+                }
+            }
             (
                 BinaryOperator::LeftShift
                 | BinaryOperator::RightShift
                 | BinaryOperator::ArithmeticRightShift,
                 Some(v),
-                _,
-            ) => {
-                // The left value is the one being shifted
-                Some(Equation::BitManpi(Box::new(v)))
-            }
+                None,
+            ) => Some(Equation::BitManpi(Box::new(v))),
 
             (
                 BinaryOperator::BitwiseOr
@@ -324,11 +460,15 @@ impl<'a> Inferer<'a> {
                 | BinaryOperator::ArithmeticRightShift
                 | BinaryOperator::Add
                 | BinaryOperator::Sub
-                | BinaryOperator::Mul,
+                | BinaryOperator::Mul
+                | BinaryOperator::Div
+                | BinaryOperator::Mod,
                 _,
                 _,
             ) => None,
 
+            // The result of a comparison is a bool. Both operands share a type and are
+            // tracked by the type checker, the operands themselves have been visited above.
             (
                 BinaryOperator::Eq
                 | BinaryOperator::NotEq
@@ -342,6 +482,67 @@ impl<'a> Inferer<'a> {
         })
     }
 
+    /// Standard library functions which are lowered to operators that the inference tracks
+    fn builtin(&self, callee: &NameID) -> Option<Builtin> {
+        [
+            (["std", "conv", "trunc"], Builtin::Trunc),
+            (["std", "conv", "sext"], Builtin::Extend),
+            (["std", "conv", "zext"], Builtin::Extend),
+            (["std", "conv", "concat"], Builtin::Concat),
+            (["std", "ops", "div_pow2"], Builtin::DivPow2),
+        ]
+        .into_iter()
+        .find(|(path, _)| {
+            let path = Path(
+                path.iter()
+                    .map(|p| Identifier(p.to_string()).nowhere())
+                    .collect(),
+            )
+            .nowhere();
+            self.symtab.try_lookup_final_id(&path).as_ref() == Some(callee)
+        })
+        .map(|(_, builtin)| builtin)
+    }
+
+    fn call(
+        &mut self,
+        expr: &Loc<Expression>,
+        callee: &Loc<NameID>,
+        args: &Loc<ArgumentList<Expression>>,
+    ) -> Res {
+        let Some(builtin) = self.builtin(callee) else {
+            return self.visit_args(args);
+        };
+        let params: &[&str] = match builtin {
+            Builtin::Trunc | Builtin::Extend => &["x"],
+            Builtin::Concat => &["x", "y"],
+            Builtin::DivPow2 => &["x", "pow"],
+        };
+        let Some(arg_exprs) = ordered_args(args, params) else {
+            return self.visit_args(args);
+        };
+        let mut arg_eqs = vec![];
+        for arg in &arg_exprs {
+            arg_eqs.push(self.expression(arg)?);
+        }
+
+        Ok(match (builtin, arg_eqs.as_slice()) {
+            (Builtin::Trunc, [Some(x)]) => self
+                .int_width(expr)
+                .map(|width| Equation::Wrap(Box::new(x.clone()), width)),
+            // Extension does not change the value
+            (Builtin::Extend, [Some(x)]) => Some(x.clone()),
+            (Builtin::Concat, [Some(x), Some(y)]) => self
+                .int_width(&arg_exprs[1])
+                .map(|y_width| Equation::Concat(Box::new(x.clone()), Box::new(y.clone()), y_width)),
+            (Builtin::DivPow2, [Some(x), Some(pow)]) => Some(Equation::DivPow2(
+                Box::new(x.clone()),
+                Box::new(pow.clone()),
+            )),
+            _ => None,
+        })
+    }
+
     fn unary_operator(&mut self, op: UnaryOperator, v: &Loc<Expression>) -> Res {
         let v_t = self.expression(v)?;
         Ok(match (op, v_t) {
@@ -360,45 +561,61 @@ impl<'a> Inferer<'a> {
         })
     }
 
+    /// Computes the range of `body` given the ranges of the variables in `known`
+    pub fn evaluate(
+        wl_infer_method: InferMethod,
+        body: &Equation,
+        known: &BTreeMap<Var, Range>,
+    ) -> Option<Range> {
+        match wl_infer_method {
+            // There's a third method one could take here:
+            // Run both and take the smaller one
+            //
+            // In theory it's the best approach and isn't that expensive. One could maybe
+            // even extend it to automatically swapping between them when analyzing the
+            // syntax tree - but that could get exponentially more expensive (but might
+            // give a larger improvement on the results). Implementing this is left as an
+            // exercise to the examiner.
+            InferMethod::IA => crate::range::evaluate_ia(body, known),
+            InferMethod::AA => crate::affine::evaluate_aa_and_simplify_to_range(body, known),
+            InferMethod::AAIA => match (
+                crate::range::evaluate_ia(body, known),
+                crate::affine::evaluate_aa_and_simplify_to_range(body, known),
+            ) {
+                (Some(a), Some(b)) => Some(a.subset(&b)),
+                (Some(a), None) | (None, Some(a)) => Some(a),
+                (None, None) => None,
+            },
+        }
+    }
+
+    /// Infers the ranges of all variables which are not in `known`, and checks that the
+    /// variables in `known` are large enough to hold the values assigned to them.
+    /// `annotations` are the locations of the types of the variables, if any.
     pub fn infer(
         wl_infer_method: InferMethod,
         equations: &Vec<(Var, Loc<Equation>)>,
         mut known: BTreeMap<Var, Range>,
-        locs: &BTreeMap<Var, Loc<()>>,
+        annotations: &BTreeMap<Var, Loc<()>>,
     ) -> error::Result<BTreeMap<Var, Range>> {
+        // The sizes of these come from the type checker, the remaining variables hold the
+        // values of all of their equations
+        let typechecked = known.keys().cloned().collect::<BTreeSet<_>>();
         // worst-case: The equations are all in reverse order and we can solve one new
         // variable per run, but maybe this is untrue and we can grantee something like
         // finishes in a fixed number of cycles?
         for _ in 0..equations.len() {
             let known_at_start = known.clone();
             for (var, body) in equations.iter() {
-                let loc = locs.get(var).cloned().unwrap_or(Loc::nowhere(()));
-                if let Some(infer) = match wl_infer_method {
-                    // There's a third method one could take here:
-                    // Run both and take the smaller one
-                    //
-                    // In theory it's the best approach and isn't that expensive. One could maybe
-                    // even extend it to automatically swapping between them when analyzing the
-                    // syntax tree - but that could get exponentially more expensive (but might
-                    // give a larger improvement on the results). Implementing this is left as an
-                    // exercise to the examiner.
-                    InferMethod::IA => crate::range::evaluate_ia(body, &known),
-                    InferMethod::AA => {
-                        crate::affine::evaluate_aa_and_simplify_to_range(body, &known)
-                    }
-                    InferMethod::AAIA => match (
-                        crate::range::evaluate_ia(body, &known),
-                        crate::affine::evaluate_aa_and_simplify_to_range(body, &known),
-                    ) {
-                        (Some(a), Some(b)) => Some(a.subset(&b)),
-                        (Some(a), None) | (None, Some(a)) => Some(a),
-                        (None, None) => None,
-                    },
-                } {
+                if let Some(infer) = Self::evaluate(wl_infer_method, body, &known) {
                     match known.entry(*var) {
                         Entry::Vacant(v) => {
                             v.insert(infer);
                         }
+                        Entry::Occupied(mut v) if !typechecked.contains(var) => {
+                            let union = v.get().union(&infer);
+                            v.insert(union);
+                        }
                         Entry::Occupied(v) => {
                             match (v.get().to_wordlength(), infer.to_wordlength()) {
                                 // NOTE: I had to weaken this check to `<` (from `!=`) since it gave false
@@ -412,7 +629,9 @@ impl<'a> Inferer<'a> {
                                     return Err(error::WordlengthMismatch {
                                         typechecked: typecheck_wl,
                                         inferred: infer_wl,
-                                        inferred_at: loc,
+                                        range: infer,
+                                        inferred_at: body.loc(),
+                                        annotation: annotations.get(var).cloned(),
                                     }
                                     .into());
                                 }
@@ -457,6 +676,72 @@ impl<'a> Inferer<'a> {
     }
 }
 
+/// An operand of a comparison in the condition of an `if`
+struct Operand<'a> {
+    expr: &'a Loc<Expression>,
+    eq: Equation,
+}
+
+impl Operand<'_> {
+    /// The bounds which hold when `self <= other + offset`
+    fn at_most(&self, other: &Operand, offset: i32) -> Bounds {
+        let plus = |eq: &Equation, k: i32| {
+            Equation::Add(
+                Box::new(eq.clone()),
+                Box::new(Equation::Constant(Range::new(k.into(), k.into()))),
+            )
+        };
+        let mut bounds = vec![];
+        if let ExprKind::Identifier(name) = &self.expr.inner.kind {
+            bounds.push((name.clone(), Bound::AtMost(plus(&other.eq, offset))));
+        }
+        if let ExprKind::Identifier(name) = &other.expr.inner.kind {
+            bounds.push((name.clone(), Bound::AtLeast(plus(&self.eq, -offset))));
+        }
+        bounds
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Builtin {
+    Trunc,
+    Extend,
+    Concat,
+    DivPow2,
+}
+
+/// The value of a shift amount if it is a known constant
+fn constant_amount(amount: &Equation) -> Option<u32> {
+    match amount {
+        Equation::Constant(range) if range.lo() == range.hi() => range.lo().to_u32(),
+        _ => None,
+    }
+}
+
+/// The arguments of a call in the order of `params`, or None if they can't be matched up
+fn ordered_args(
+    args: &Loc<ArgumentList<Expression>>,
+    params: &[&str],
+) -> Option<Vec<Loc<Expression>>> {
+    match &args.inner {
+        ArgumentList::Positional(exprs) if exprs.len() == params.len() => Some(exprs.clone()),
+        ArgumentList::Positional(_) => None,
+        ArgumentList::Named(named) => params
+            .iter()
+            .map(|param| {
+                named.iter().find_map(|arg| match arg {
+                    NamedArgument::Full(name, expr) | NamedArgument::Short(name, expr)
+                        if name.inner.0 == *param =>
+                    {
+                        Some(expr.clone())
+                    }
+                    _ => None,
+                })
+            })
+            .collect(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::{BTreeMap, BTreeSet};
@@ -474,11 +759,6 @@ mod tests {
         equations: Vec<(Var, Equation)>,
         expected: Vec<(Var, Range)>,
     ) {
-        let vars = equations
-            .clone()
-            .into_iter()
-            .map(|(v, _)| (v, Loc::nowhere(())))
-            .collect();
         let inferred = Inferer::infer(
             wl_infer_method,
             &equations
@@ -487,7 +767,7 @@ mod tests {
                 .map(|(v, e)| (v, Loc::nowhere(e)))
                 .collect(),
             BTreeMap::new(),
-            &vars,
+            &BTreeMap::new(),
         )
         .map(|e| e.into_iter().collect::<BTreeSet<(Var, Range)>>());
         let expected = Ok(expected.into_iter().collect::<BTreeSet<(Var, Range)>>());
@@ -524,6 +804,30 @@ mod tests {
     fn u(a: Equation, b: Equation) -> Equation {
         Equation::Union(Box::new(a), Box::new(b))
     }
+    fn shl(a: Equation, amount: u32) -> Equation {
+        Equation::Shl(Box::new(a), amount)
+    }
+    fn shr(a: Equation, amount: u32, width: u32) -> Equation {
+        Equation::Shr(Box::new(a), amount, width)
+    }
+    fn ashr(a: Equation, amount: u32) -> Equation {
+        Equation::AShr(Box::new(a), amount)
+    }
+    fn div_pow2(a: Equation, pow: Equation) -> Equation {
+        Equation::DivPow2(Box::new(a), Box::new(pow))
+    }
+    fn concat(a: Equation, b: Equation, b_width: u32) -> Equation {
+        Equation::Concat(Box::new(a), Box::new(b), b_width)
+    }
+    fn wrap(a: Equation, width: u32) -> Equation {
+        Equation::Wrap(Box::new(a), width)
+    }
+    fn at_most(a: Equation, bound: Equation) -> Equation {
+        Equation::AtMost(Box::new(a), Box::new(bound))
+    }
+    fn at_least(a: Equation, bound: Equation) -> Equation {
+        Equation::AtLeast(Box::new(a), Box::new(bound))
+    }
 
     // AA
     #[test]
@@ -839,4 +1143,155 @@ mod tests {
             vec![(Var(0), r(0, 0))],
         )
     }
+
+    #[test]
+    fn shl_ia() {
+        check_infer(
+            InferMethod::IA,
+            vec![(Var(0), shl(c(-3, 5), 2))],
+            vec![(Var(0), r(-12, 20))],
+        )
+    }
+
+    #[test]
+    fn shl_aa() {
+        // AA knows that the shifted value is correlated with the original value
+        check_infer(
+            InferMethod::AA,
+            vec![(Var(0), c(0, 10)), (Var(1), sub(shl(v(0), 1), v(0)))],
+            vec![(Var(0), r(0, 10)), (Var(1), r(0, 10))],
+        )
+    }
+
+    #[test]
+    fn overflowing_shl_wraps_ia() {
+        check_infer(
+            InferMethod::IA,
+            vec![(Var(0), wrap(shl(c(0, 10), 2), 5))],
+            vec![(Var(0), r(-16, 15))],
+        )
+    }
+
+    #[test]
+    fn non_overflowing_wrap_is_exact_aa() {
+        check_infer(
+            InferMethod::AA,
+            vec![(Var(0), c(0, 10)), (Var(1), sub(wrap(v(0), 5), v(0)))],
+            vec![(Var(0), r(0, 10)), (Var(1), r(0, 0))],
+        )
+    }
+
+    #[test]
+    fn arithmetic_right_shift_rounds_down_ia() {
+        check_infer(
+            InferMethod::IA,
+            vec![(Var(0), ashr(c(-5, 5), 1))],
+            vec![(Var(0), r(-3, 2))],
+        )
+    }
+
+    #[test]
+    fn logical_right_shift_ia() {
+        check_infer(
+            InferMethod::IA,
+            vec![
+                (Var(0), shr(c(3, 12), 2, 8)),
+                (Var(1), shr(c(-5, 5), 1, 8)),
+                (Var(2), shr(c(-5, 5), 8, 8)),
+            ],
+            // Negative values become large positive values
+            vec![(Var(0), r(0, 3)), (Var(1), r(0, 127)), (Var(2), r(0, 0))],
+        )
+    }
+
+    #[test]
+    fn div_pow2_rounds_towards_zero_aaia() {
+        check_infer(
+            InferMethod::AAIA,
+            vec![
+                (Var(0), div_pow2(c(-7, 9), c(1, 1))),
+                (Var(1), div_pow2(c(-7, 9), c(1, 2))),
+            ],
+            vec![(Var(0), r(-3, 4)), (Var(1), r(-3, 4))],
+        )
+    }
+
+    #[test]
+    fn concat_ia() {
+        check_infer(
+            InferMethod::IA,
+            vec![
+                (Var(0), concat(c(1, 2), c(0, 3), 2)),
+                // The bits of negative values are unsigned in the result
+                (Var(1), concat(c(1, 2), c(-1, 1), 2)),
+            ],
+            vec![(Var(0), r(4, 11)), (Var(1), r(4, 11))],
+        )
+    }
+
+    #[test]
+    fn concat_aa() {
+        check_infer(
+            InferMethod::AA,
+            vec![(Var(0), c(0, 10)), (Var(1), concat(v(0), v(0), 4))],
+            vec![(Var(0), r(0, 10)), (Var(1), r(0, 170))],
+        )
+    }
+
+    #[test]
+    fn bounds_narrow_values_ia() {
+        check_infer(
+            InferMethod::IA,
+            vec![
+                (Var(0), c(-100, 100)),
+                (Var(1), at_least(at_most(v(0), c(10, 10)), c(0, 0))),
+            ],
+            vec![(Var(0), r(-100, 100)), (Var(1), r(0, 10))],
+        )
+    }
+
+    #[test]
+    fn unchanged_bounded_values_keep_their_correlation_aa() {
+        check_infer(
+            InferMethod::AA,
+            vec![
+                (Var(0), c(0, 10)),
+                (Var(1), sub(at_most(v(0), c(20, 20)), v(0))),
+            ],
+            vec![(Var(0), r(0, 10)), (Var(1), r(0, 0))],
+        )
+    }
+
+    #[test]
+    fn variables_without_a_type_hold_the_values_of_all_equations_ia() {
+        check_infer(
+            InferMethod::IA,
+            vec![(Var(0), c(1, 1)), (Var(0), c(-4, -4))],
+            vec![(Var(0), r(-4, 1))],
+        )
+    }
+
+    #[test]
+    fn undersized_values_are_errors() {
+        let known = [(Var(0), Range::signed(4))].into_iter().collect();
+        let result = Inferer::infer(
+            InferMethod::IA,
+            &vec![(Var(0), Loc::nowhere(shl(c(0, 10), 1)))],
+            known,
+            &BTreeMap::new(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn values_which_fit_are_not_errors() {
+        let known = [(Var(0), Range::signed(5))].into_iter().collect();
+        let result = Inferer::infer(
+            InferMethod::IA,
+            &vec![(Var(0), Loc::nowhere(shl(c(-4, 7), 1)))],
+            known,
+            &BTreeMap::new(),
+        );
+        assert_eq!(result, Ok([(Var(0), r(-16, 15))].into_iter().collect()));
+    }
 }
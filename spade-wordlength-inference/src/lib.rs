@@ -1,9 +1,10 @@
 use std::collections::BTreeMap;
 
-use inferer::{Equation, Inferer};
-use num::{BigInt, ToPrimitive};
+use inferer::{Equation, Inferer, Var};
+use num::ToPrimitive;
 use range::Range;
 use spade_common::location_info::{Loc, WithLocation};
+use spade_diagnostics::Diagnostic;
 use spade_hir::Unit;
 use spade_typeinference::{equation::TypeVar, TypeState};
 use spade_types::KnownType;
@@ -19,45 +20,69 @@ pub mod error;
 
 mod affine;
 mod inferer;
-mod range;
+pub mod range;
 
 pub type Res = error::Result<Option<Equation>>;
 
+/// Infers the word lengths of integers whose size is unknown, and checks that the sizes of
+/// the remaining integers match the values assigned to them. Values which do not fit in their
+/// type are errors. The returned diagnostics are warnings about types which are wider than
+/// required.
 pub fn infer_and_check(
     wl_infer_method: InferMethod,
     type_state: &mut TypeState,
     unit: &Unit,
     ctx: &spade_typeinference::Context,
-) -> error::Result<()> {
+) -> error::Result<Vec<Diagnostic>> {
     let mut inferer = inferer::Inferer::new(type_state, ctx.symtab);
     inferer.expression(&unit.body)?;
 
     let mut known = BTreeMap::new();
-    //
+    let mut sizes = BTreeMap::new();
+    let mut annotations = BTreeMap::new();
     for (ty, var) in inferer.mappings.iter() {
         match &ty.inner {
-            TypeVar::Known(_, KnownType::Integer(size), _) => {
-                let x: u32 = size
-                    .to_u128()
-                    .unwrap()
-                    .saturating_sub(1)
-                    .try_into()
-                    .unwrap(); // This is assumed to be small
-                let bound: BigInt = BigInt::from(1) << x;
-                known.insert(*var, Range::new(1 - bound.clone(), bound - 2));
+            TypeVar::Known(loc, KnownType::Integer(size), _) => {
+                // This is assumed to be small
+                let size = size.to_u32().unwrap();
+                known.insert(*var, Range::signed(size));
+                sizes.insert(*var, size);
+                if (loc.file_id, loc.span) != (0, Loc::nowhere(()).span) {
+                    annotations.insert(*var, *loc);
+                }
             }
             TypeVar::Known(_, KnownType::Named(n), _) => panic!("How do I handle a type? {:?}", n),
-            TypeVar::Unknown(_, _, _, _) => {
-                // known.insert(var, Range { lo: 0, hi: 0 });
-            }
+            TypeVar::Unknown(_, _, _, _) => {}
 
             TypeVar::Known(_, other, params) => panic!("Wat? {:?} {:?}", other, params),
         }
     }
 
-    let known = Inferer::infer(wl_infer_method, &inferer.equations, known, &inferer.locs)?;
+    let known = Inferer::infer(wl_infer_method, &inferer.equations, known, &annotations)?;
+
+    let mut warnings = vec![];
+    for (var, typechecker_wl) in sizes {
+        if let Some(range) = computed_range(wl_infer_method, &inferer.equations, &known, var) {
+            match range.to_wordlength() {
+                Some(inferred_wl) if inferred_wl < typechecker_wl => warnings.push(
+                    error::OversizedWordlength {
+                        at: inferer.locs.get(&var).cloned().unwrap_or(Loc::nowhere(())),
+                        annotation: annotations.get(&var).cloned(),
+                        range,
+                        inferred: inferred_wl,
+                        typechecked: typechecker_wl,
+                    }
+                    .into(),
+                ),
+                _ => {}
+            }
+        }
+    }
 
     for (ty, var) in inferer.mappings.iter() {
+        if !matches!(ty.inner, TypeVar::Unknown(..)) {
+            continue;
+        }
         // None errors are checked when mir-lowering, this isn't necessarily an error
         let inferred_wl =
             if let Some(inferred_wl) = known.get(var).and_then(|guess| guess.to_wordlength()) {
@@ -65,25 +90,7 @@ pub fn infer_and_check(
             } else {
                 continue;
             };
-        let typechecker_wl =
-            if let TypeVar::Known(_, KnownType::Integer(typechecker_wl), _) = &ty.inner {
-                // 2^32 bits should be enough for anyone - right?
-                typechecker_wl.to_u32().unwrap()
-            } else {
-                continue;
-            };
         let loc = inferer.locs.get(var).cloned().unwrap_or(Loc::nowhere(()));
-        if typechecker_wl != inferred_wl {
-            // NOTE: To make these types better, the known types need to have a Loc on
-            // them, something I really don't feel like doing right now.
-            // NOTE: Printing the actual ranges of values would be nice!
-            return Err(error::WordlengthMismatch {
-                typechecked: typechecker_wl,
-                inferred: inferred_wl,
-                inferred_at: loc,
-            }
-            .into());
-        }
         to_wordlength_error(
             inferer.type_state.unify(
                 ty,
@@ -99,7 +106,26 @@ pub fn infer_and_check(
         )?;
     }
 
-    Ok(())
+    Ok(warnings)
+}
+
+/// The union of the values of all equations for `var`. None if any equation is opaque or
+/// if there are no equations for the variable
+fn computed_range(
+    wl_infer_method: InferMethod,
+    equations: &[(Var, Loc<Equation>)],
+    known: &BTreeMap<Var, Range>,
+    var: Var,
+) -> Option<Range> {
+    equations
+        .iter()
+        .filter(|(v, _)| *v == var)
+        .map(|(_, body)| Inferer::evaluate(wl_infer_method, body, known))
+        .reduce(|a, b| match (a, b) {
+            (Some(a), Some(b)) => Some(a.union(&b)),
+            _ => None,
+        })
+        .flatten()
 }
 
 fn to_wordlength_error<A>(
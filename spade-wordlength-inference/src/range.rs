@@ -1,9 +1,13 @@
 use crate::inferer::{Equation, Var};
 use num::BigInt;
 use num::Signed;
+use num::ToPrimitive;
 use num::Zero;
 use std::collections::BTreeMap;
 
+/// The largest word length the inference will consider
+const MAX_WORDLENGTH: u32 = 2048;
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Range {
     lo: BigInt,
@@ -25,6 +29,15 @@ impl Range {
         &self.hi
     }
 
+    /// The range of values representable by a signed integer of `width` bits
+    pub fn signed(width: u32) -> Self {
+        if width == 0 {
+            return Self::zero();
+        }
+        let bound = BigInt::from(1) << (width - 1);
+        Self::new(-bound.clone(), bound - 1)
+    }
+
     pub fn add(&self, b: &Self) -> Self {
         let a = self;
         Self::new(a.lo.clone() + b.lo.clone(), a.hi.clone() + b.hi.clone())
@@ -80,25 +93,92 @@ impl Range {
 
     pub fn bit_manip(&self) -> Option<Self> {
         // This signed integers
-        self.to_wordlength().map(|wl| {
-            let bound = BigInt::from(1) << (wl - 1);
-            let a = -bound.clone();
-            let b = bound - BigInt::from(1);
+        self.to_wordlength().map(Self::signed)
+    }
 
-            Self::new(a.clone().min(b.clone()), a.max(b))
-        })
+    /// Multiplication by `2^amount`
+    pub fn shl(&self, amount: u32) -> Self {
+        Self::new(self.lo.clone() << amount, self.hi.clone() << amount)
     }
 
-    pub fn to_wordlength(&self) -> Option<u32> {
-        // NOTE: This can be considerably more fancy, taking into account the range and working
-        // from there - but I'm keeping things simple for now.
-        for i in 1..2048 {
-            let n = BigInt::from(1) << i;
-            if self.hi.abs() < n && self.lo.abs() < n + BigInt::from(1) {
-                return Some(i + 1);
-            }
+    /// Logical right shift of a value which is `width` bits wide. Negative values become large
+    /// positive values, of which only the `width - amount` low bits can be set
+    pub fn shr(&self, amount: u32, width: u32) -> Self {
+        if amount == 0 {
+            self.clone()
+        } else if self.lo.is_negative() {
+            Self::new(
+                BigInt::zero(),
+                (BigInt::from(1) << width.saturating_sub(amount)) - 1,
+            )
+        } else {
+            self.ashr(amount)
         }
-        None
+    }
+
+    /// The values of `self` which are at most `bound`. If there are none, the value can not
+    /// occur, and the range is narrowed to the single value `bound.hi` to keep it non-empty
+    pub fn at_most(&self, bound: &Range) -> Self {
+        let hi = self.hi.clone().min(bound.hi.clone());
+        Self::new(self.lo.clone().min(hi.clone()), hi)
+    }
+
+    /// The values of `self` which are at least `bound`. If there are none, the range is
+    /// narrowed to the single value `bound.lo`, see [Range::at_most]
+    pub fn at_least(&self, bound: &Range) -> Self {
+        let lo = self.lo.clone().max(bound.lo.clone());
+        Self::new(lo.clone(), self.hi.clone().max(lo))
+    }
+
+    /// Arithmetic right shift, i.e. division by `2^amount` rounding towards negative infinity
+    pub fn ashr(&self, amount: u32) -> Self {
+        Self::new(self.lo.clone() >> amount, self.hi.clone() >> amount)
+    }
+
+    /// Division by `2^pow` rounding towards zero. Both ends of the range move towards zero as
+    /// `pow` grows, so the result is spanned by the smallest and largest divisor.
+    pub fn div_pow2(&self, pow: &Range) -> Self {
+        let div = |p: &BigInt| {
+            // Shifting by more bits than any value we can represent makes no difference
+            let p = p
+                .clone()
+                .clamp(BigInt::zero(), BigInt::from(MAX_WORDLENGTH));
+            let divisor = BigInt::from(1) << p.to_u32().unwrap();
+            Self::new(
+                self.lo.clone() / divisor.clone(),
+                self.hi.clone() / divisor.clone(),
+            )
+        };
+        div(&pow.lo).union(&div(&pow.hi))
+    }
+
+    /// Concatenation of the bits of `self` with the `lsb_width` bits of `lsb`.
+    pub fn concat(&self, lsb: &Self, lsb_width: u32) -> Self {
+        // The bits of a negative lsb are reinterpreted as an unsigned value
+        let lsb = if lsb.lo.is_negative() {
+            Self::new(BigInt::zero(), (BigInt::from(1) << lsb_width) - 1)
+        } else {
+            lsb.clone()
+        };
+        self.shl(lsb_width).add(&lsb)
+    }
+
+    /// The value after truncation to `width` bits. Values which fit are unchanged, but if
+    /// any value overflows, the result can be anything representable in `width` bits.
+    pub fn wrap(&self, width: u32) -> Self {
+        match self.to_wordlength() {
+            Some(wl) if wl <= width => self.clone(),
+            _ => Self::signed(width),
+        }
+    }
+
+    /// The smallest number of bits required to represent all values in the range as a signed
+    /// integer
+    pub fn to_wordlength(&self) -> Option<u32> {
+        (1..MAX_WORDLENGTH).find(|wl| {
+            let range = Self::signed(*wl);
+            range.lo <= self.lo && self.hi <= range.hi
+        })
     }
 
     pub fn zero() -> Range {
@@ -106,6 +186,12 @@ impl Range {
     }
 }
 
+impl std::fmt::Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
 pub fn evaluate_ia(body: &Equation, known: &BTreeMap<Var, Range>) -> Option<Range> {
     match &body {
         Equation::V(var) => known.get(var).cloned(),
@@ -135,5 +221,70 @@ pub fn evaluate_ia(body: &Equation, known: &BTreeMap<Var, Range>) -> Option<Rang
             (Some(a), Some(b)) => Some(a.union(&b)),
             _ => None,
         },
+        Equation::Shl(a, amount) => evaluate_ia(a, known).map(|x| x.shl(*amount)),
+        Equation::Shr(a, amount, width) => evaluate_ia(a, known).map(|x| x.shr(*amount, *width)),
+        Equation::AShr(a, amount) => evaluate_ia(a, known).map(|x| x.ashr(*amount)),
+        Equation::DivPow2(a, pow) => match (evaluate_ia(a, known), evaluate_ia(pow, known)) {
+            (Some(a), Some(pow)) => Some(a.div_pow2(&pow)),
+            _ => None,
+        },
+        Equation::Concat(a, b, b_width) => match (evaluate_ia(a, known), evaluate_ia(b, known)) {
+            (Some(a), Some(b)) => Some(a.concat(&b, *b_width)),
+            _ => None,
+        },
+        Equation::Wrap(a, width) => evaluate_ia(a, known).map(|x| x.wrap(*width)),
+        Equation::AtMost(a, bound) => match (evaluate_ia(a, known), evaluate_ia(bound, known)) {
+            (Some(a), Some(bound)) => Some(a.at_most(&bound)),
+            _ => None,
+        },
+        Equation::AtLeast(a, bound) => match (evaluate_ia(a, known), evaluate_ia(bound, known)) {
+            (Some(a), Some(bound)) => Some(a.at_least(&bound)),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(lo: i128, hi: i128) -> Range {
+        Range::new(BigInt::from(lo), BigInt::from(hi))
+    }
+
+    #[test]
+    fn wordlengths_of_signed_ranges_are_exact() {
+        assert_eq!(r(-8, 7).to_wordlength(), Some(4));
+        assert_eq!(r(-9, 7).to_wordlength(), Some(5));
+        assert_eq!(r(-8, 8).to_wordlength(), Some(5));
+        assert_eq!(r(-1, 0).to_wordlength(), Some(1));
+        assert_eq!(r(0, 1).to_wordlength(), Some(2));
+    }
+
+    #[test]
+    fn logical_right_shift_by_zero_is_unchanged() {
+        assert_eq!(r(-5, 5).shr(0, 8), r(-5, 5));
+    }
+
+    #[test]
+    fn bounds_narrow_ranges() {
+        assert_eq!(r(-5, 20).at_most(&r(0, 10)), r(-5, 10));
+        assert_eq!(r(-5, 20).at_least(&r(0, 10)), r(0, 20));
+    }
+
+    #[test]
+    fn unsatisfiable_bounds_leave_the_value_of_the_bound() {
+        assert_eq!(r(5, 20).at_most(&r(-3, 0)), r(0, 0));
+        assert_eq!(r(5, 20).at_least(&r(30, 40)), r(30, 30));
+        // A bound of a single value which is just out of reach
+        assert_eq!(r(5, 20).at_most(&r(4, 4)), r(4, 4));
+        assert_eq!(r(5, 20).at_least(&r(21, 21)), r(21, 21));
+    }
+
+    #[test]
+    fn signed_range_round_trips() {
+        for width in 1..10 {
+            assert_eq!(Range::signed(width).to_wordlength(), Some(width));
+        }
     }
 }
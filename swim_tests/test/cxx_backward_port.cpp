@@ -0,0 +1,18 @@
+// top=cxx::top::backward_port
+
+#include <cassert>
+#define TOP backward_port
+
+#include <verilator_util.hpp>
+
+TEST_CASE(it_works, {
+    s.i->a = "5";
+
+    ctx->timeInc(1);
+    dut->eval();
+
+    ASSERT_EQ(s.i->b, "5");
+    return 0;
+})
+
+MAIN
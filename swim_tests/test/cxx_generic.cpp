@@ -0,0 +1,38 @@
+// top=cxx::top::generic_identity_u8
+
+#include <cassert>
+#include <cstdlib>
+#include <string>
+#define TOP generic_identity_u8
+
+#include <verilator_util.hpp>
+
+// The id of the monomorphised instance depends on the rest of the project, so its
+// name is looked up in the table of units with wrappers
+std::string generic_instance_name() {
+    for (auto const& unit : spade_unit_names) {
+        if (std::string(unit.spade_name).rfind("cxx::top::generic_identity[", 0) == 0) {
+            return unit.spade_name;
+        }
+    }
+    return "";
+}
+
+TEST_CASE(it_works, {
+    auto name = generic_instance_name();
+    assert(!name.empty());
+
+    // The instance has the same ports as the unit which instantiates it, so its
+    // ports can be driven through the verilated module of that unit
+    generic_identity_u8_spade_t g(std::getenv("SWIM_SPADE_STATE"), name, &*dut);
+
+    g.i->a = "5";
+
+    ctx->timeInc(1);
+    dut->eval();
+
+    ASSERT_EQ(g.o, "5");
+    return 0;
+})
+
+MAIN
@@ -0,0 +1,92 @@
+#top = stdlib::stream::arbiter_harness
+
+from cocotb.clock import Clock
+from spade import FallingEdge, SpadeExt
+from cocotb import cocotb
+from cocotb.triggers import Timer
+
+async def setup(s, clk):
+    await cocotb.start(Clock(clk, period=10, units='ns').start())
+    s.i.rst = True
+    s.i.a = "None"
+    s.i.b = "None"
+    s.i.ready = False
+    await FallingEdge(clk)
+    s.i.rst = False
+
+
+@cocotb.test()
+async def inputs_take_turns(dut):
+    s = SpadeExt(dut)
+    clk = dut.clk_i
+    await setup(s, clk)
+
+    s.i.a = "Some(1)"
+    s.i.b = "Some(2)"
+    s.i.ready = True
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("Some(1)")
+    s.o.a_ready.assert_eq(True)
+    s.o.b_ready.assert_eq(False)
+
+    await FallingEdge(clk)
+    s.i.a = "Some(3)"
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("Some(2)")
+    s.o.a_ready.assert_eq(False)
+    s.o.b_ready.assert_eq(True)
+
+    await FallingEdge(clk)
+    s.i.b = "None"
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("Some(3)")
+    s.o.a_ready.assert_eq(True)
+
+    await FallingEdge(clk)
+    s.i.a = "None"
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("None")
+
+
+@cocotb.test()
+async def a_single_input_is_passed_on_every_clock_cycle(dut):
+    s = SpadeExt(dut)
+    clk = dut.clk_i
+    await setup(s, clk)
+
+    s.i.ready = True
+    for i in range(0, 4):
+        s.i.b = f"Some({i})"
+        await Timer(1, units='ns')
+        s.o.out.assert_eq(f"Some({i})")
+        s.o.b_ready.assert_eq(True)
+        await FallingEdge(clk)
+    s.i.b = "None"
+
+
+@cocotb.test()
+async def selection_is_kept_until_the_value_is_transferred(dut):
+    s = SpadeExt(dut)
+    clk = dut.clk_i
+    await setup(s, clk)
+
+    s.i.b = "Some(2)"
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("Some(2)")
+
+    await FallingEdge(clk)
+    # a has priority when both inputs have values, but b was selected before a had a value
+    s.i.a = "Some(1)"
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("Some(2)")
+    s.o.a_ready.assert_eq(False)
+
+    s.i.ready = True
+    await Timer(1, units='ns')
+    s.o.b_ready.assert_eq(True)
+
+    await FallingEdge(clk)
+    s.i.b = "None"
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("Some(1)")
+    s.o.a_ready.assert_eq(True)
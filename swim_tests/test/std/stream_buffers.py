@@ -0,0 +1,96 @@
+#top = stdlib::stream::buffers_harness
+
+from cocotb.clock import Clock
+from spade import FallingEdge, SpadeExt
+from cocotb import cocotb, random
+from cocotb.triggers import Timer
+
+async def setup(s, clk):
+    await cocotb.start(Clock(clk, period=10, units='ns').start())
+    s.i.rst = True
+    s.i.data = "None"
+    s.i.ready = False
+    await FallingEdge(clk)
+    s.i.rst = False
+
+
+async def run(s, clk, seq, send_probability, ready_probability):
+    """Sends `seq` through the buffers and returns the values received at the output"""
+    received = []
+    sent = 0
+    offering = False
+    for _ in range(0, 20 * len(seq)):
+        await FallingEdge(clk)
+        # Values which are offered must be kept until they are transferred
+        offering = sent < len(seq) and (offering or random.random() < send_probability)
+        s.i.data = f"Some({seq[sent]})" if offering else "None"
+        ready = random.random() < ready_probability
+        s.i.ready = ready
+        await Timer(1, units='ns')
+
+        if offering and s.o.in_ready.is_eq(True):
+            sent += 1
+            offering = False
+        out = s.o.out.value()
+        if ready and out != "None":
+            received.append(out)
+        if len(received) == len(seq):
+            break
+    return received
+
+
+@cocotb.test()
+async def values_pass_through_at_full_rate(dut):
+    s = SpadeExt(dut)
+    clk = dut.clk_i
+    await setup(s, clk)
+
+    s.i.ready = True
+    for i in range(0, 8):
+        s.i.data = f"Some({i})"
+        await Timer(1, units='ns')
+        s.o.in_ready.assert_eq(True)
+        await FallingEdge(clk)
+        # Each value is stored in the skid buffer on the first clock edge and in the
+        # register slice on the second
+        if i >= 1:
+            s.o.out.assert_eq(f"Some({i - 1})")
+    s.i.data = "None"
+
+
+@cocotb.test()
+async def values_are_kept_while_the_output_is_not_ready(dut):
+    s = SpadeExt(dut)
+    clk = dut.clk_i
+    await setup(s, clk)
+
+    for i in range(0, 4):
+        s.i.data = f"Some({i})"
+        await FallingEdge(clk)
+    s.i.data = "None"
+    # The register slice, the skid buffer's output register and skid register hold one value each
+    s.o.in_ready.assert_eq(False)
+    s.o.out.assert_eq("Some(0)")
+
+    await FallingEdge(clk)
+    s.o.out.assert_eq("Some(0)")
+
+    s.i.ready = True
+    for i in range(0, 3):
+        await Timer(1, units='ns')
+        s.o.out.assert_eq(f"Some({i})")
+        await FallingEdge(clk)
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("None")
+
+
+@cocotb.test()
+async def no_values_are_lost_or_duplicated(dut):
+    random.seed(0)
+    s = SpadeExt(dut)
+    clk = dut.clk_i
+    await setup(s, clk)
+
+    seq = list(map(lambda _: random.randint(0, 100), range(0, 100)))
+    received = await run(s, clk, seq, 0.7, 0.5)
+    assert received == list(map(lambda v: f"Some({v})", seq))
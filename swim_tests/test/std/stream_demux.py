@@ -0,0 +1,30 @@
+#top = stdlib::stream::demux_harness
+
+from spade import SpadeExt
+from cocotb import cocotb
+from cocotb.triggers import Timer
+
+@cocotb.test()
+async def values_are_routed_to_the_selected_output(dut):
+    s = SpadeExt(dut)
+
+    s.i.data = "Some(5)"
+    s.i.a_ready = True
+    s.i.b_ready = False
+
+    s.i.select = False
+    await Timer(1, units='ns')
+    s.o.a.assert_eq("Some(5)")
+    s.o.b.assert_eq("None")
+    s.o.in_ready.assert_eq(True)
+
+    s.i.select = True
+    await Timer(1, units='ns')
+    s.o.a.assert_eq("None")
+    s.o.b.assert_eq("None")
+    s.o.in_ready.assert_eq(False)
+
+    s.i.b_ready = True
+    await Timer(1, units='ns')
+    s.o.b.assert_eq("Some(5)")
+    s.o.in_ready.assert_eq(True)
@@ -0,0 +1,63 @@
+#top = stdlib::stream::fifo_harness
+
+from typing import List
+from cocotb.clock import Clock
+from spade import FallingEdge, SpadeExt
+from cocotb import cocotb, random
+from cocotb.triggers import Timer
+
+async def setup(s, write_clk, read_clk):
+    await cocotb.start(Clock(write_clk, period=10, units='ns').start())
+    await cocotb.start(Clock(read_clk, period=11, units='ns').start())
+
+    s.i.write_rst = True
+    s.i.read_rst = True
+    s.i.data = "None"
+    s.i.ready = False
+    for _ in range(0, 4):
+        await FallingEdge(read_clk)
+    s.i.write_rst = False
+    s.i.read_rst = False
+
+
+async def write_side(s, write_clk, seq: List[int]):
+    for elem in seq:
+        await FallingEdge(write_clk)
+        s.i.data = f"Some({elem})"
+        await Timer(1, units='ns')
+        while s.o.in_ready.is_eq(False):
+            await FallingEdge(write_clk)
+            await Timer(1, units='ns')
+    await FallingEdge(write_clk)
+    s.i.data = "None"
+
+
+async def read_side(s, read_clk, count: int, ready_probability: float) -> List[str]:
+    received = []
+    while len(received) < count:
+        await FallingEdge(read_clk)
+        ready = random.random() < ready_probability
+        s.i.ready = ready
+        await Timer(1, units='ns')
+        out = s.o.out.value()
+        if ready and out != "None":
+            received.append(out)
+    return received
+
+
+@cocotb.test()
+async def values_are_passed_through_the_fifo(dut):
+    random.seed(0)
+    s = SpadeExt(dut)
+    write_clk = dut.write_clk_i
+    read_clk = dut.read_clk_i
+
+    await setup(s, write_clk, read_clk)
+
+    seq = list(map(lambda _: random.randint(0, 100), range(0, 50)))
+    read = cocotb.start_soon(read_side(s, read_clk, len(seq), 0.3))
+    write = cocotb.start_soon(write_side(s, write_clk, seq))
+
+    await write
+    received = await read
+    assert received == list(map(lambda v: f"Some({v})", seq))
@@ -0,0 +1,61 @@
+#top = stdlib::stream::fork_harness
+
+from cocotb.clock import Clock
+from spade import FallingEdge, SpadeExt
+from cocotb import cocotb
+from cocotb.triggers import Timer
+
+async def setup(s, clk):
+    await cocotb.start(Clock(clk, period=10, units='ns').start())
+    s.i.rst = True
+    s.i.data = "None"
+    s.i.a_ready = False
+    s.i.b_ready = False
+    await FallingEdge(clk)
+    s.i.rst = False
+
+
+@cocotb.test()
+async def values_are_copied_to_both_outputs(dut):
+    s = SpadeExt(dut)
+    clk = dut.clk_i
+    await setup(s, clk)
+
+    s.i.a_ready = True
+    s.i.b_ready = True
+    for i in range(0, 4):
+        s.i.data = f"Some({i})"
+        await Timer(1, units='ns')
+        s.o.a.assert_eq(f"Some({i})")
+        s.o.b.assert_eq(f"Some({i})")
+        s.o.in_ready.assert_eq(True)
+        await FallingEdge(clk)
+
+
+@cocotb.test()
+async def outputs_can_receive_values_in_different_clock_cycles(dut):
+    s = SpadeExt(dut)
+    clk = dut.clk_i
+    await setup(s, clk)
+
+    s.i.data = "Some(1)"
+    s.i.a_ready = True
+    await Timer(1, units='ns')
+    s.o.a.assert_eq("Some(1)")
+    s.o.in_ready.assert_eq(False)
+
+    await FallingEdge(clk)
+    # a has received the value, so it is not offered to it again
+    s.o.a.assert_eq("None")
+    s.o.in_ready.assert_eq(False)
+
+    s.i.b_ready = True
+    await Timer(1, units='ns')
+    s.o.b.assert_eq("Some(1)")
+    s.o.in_ready.assert_eq(True)
+
+    await FallingEdge(clk)
+    s.i.data = "Some(2)"
+    await Timer(1, units='ns')
+    s.o.a.assert_eq("Some(2)")
+    s.o.b.assert_eq("Some(2)")
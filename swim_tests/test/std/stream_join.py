@@ -0,0 +1,27 @@
+#top = stdlib::stream::join_harness
+
+from spade import SpadeExt
+from cocotb import cocotb
+from cocotb.triggers import Timer
+
+@cocotb.test()
+async def values_are_paired(dut):
+    s = SpadeExt(dut)
+
+    s.i.a = "Some(1)"
+    s.i.b = "None"
+    s.i.ready = True
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("None")
+    s.o.a_ready.assert_eq(False)
+
+    s.i.b = "Some(true)"
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("Some((1, true))")
+    s.o.a_ready.assert_eq(True)
+    s.o.b_ready.assert_eq(True)
+
+    s.i.ready = False
+    await Timer(1, units='ns')
+    s.o.a_ready.assert_eq(False)
+    s.o.b_ready.assert_eq(False)
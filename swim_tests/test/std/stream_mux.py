@@ -0,0 +1,31 @@
+#top = stdlib::stream::mux_harness
+
+from spade import SpadeExt
+from cocotb import cocotb
+from cocotb.triggers import Timer
+
+@cocotb.test()
+async def selected_input_is_passed_on(dut):
+    s = SpadeExt(dut)
+
+    s.i.a = "Some(1)"
+    s.i.b = "Some(2)"
+    s.i.ready = True
+
+    s.i.select = False
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("Some(1)")
+    s.o.a_ready.assert_eq(True)
+    s.o.b_ready.assert_eq(False)
+
+    s.i.select = True
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("Some(2)")
+    s.o.a_ready.assert_eq(False)
+    s.o.b_ready.assert_eq(True)
+
+    s.i.ready = False
+    await Timer(1, units='ns')
+    s.o.out.assert_eq("None")
+    s.o.a_ready.assert_eq(False)
+    s.o.b_ready.assert_eq(False)
@@ -0,0 +1,76 @@
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use clap::Parser;
+use color_eyre::{
+    eyre::{anyhow, Context},
+    Result,
+};
+use spade::compiler_state::CompilerState;
+use spade_common::{location_info::WithLocation, name::Path};
+use vcd_translate::coverage::coverage_from_vcd;
+
+/// Reports which match arms, if branches and enum register variants were reached
+/// in a simulation of a design compiled with `--coverage`
+#[derive(clap::Parser)]
+struct CliArgs {
+    infile: PathBuf,
+    #[clap(short)]
+    state_file: PathBuf,
+    #[clap(short = 't')]
+    top: String,
+    /// The name of the clock port of the top unit. Branches are counted at each of
+    /// its rising edges
+    #[clap(short = 'c', default_value = "clk")]
+    clock: String,
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+
+    let args = CliArgs::parse();
+
+    let reader = BufReader::new(
+        File::open(&args.infile).with_context(|| format!("Failed to open {:?}", args.infile))?,
+    );
+
+    let state_file = std::fs::read_to_string(&args.state_file)
+        .with_context(|| format!("Failed to read state file {:?}", args.state_file))?;
+
+    let compiler_state: CompilerState = ron::from_str(&state_file)
+        .with_context(|| format!("failed to decode compiler state in {:?}", args.state_file))?;
+
+    let top_path = Path::from_strs(&args.top.split("::").collect::<Vec<_>>()).nowhere();
+    let (top, _) = compiler_state
+        .symtab
+        .symtab()
+        .lookup_unit(&top_path)
+        .map_err(|_e| anyhow!("Did not find a unit named {}", args.top))?;
+
+    let report = coverage_from_vcd(reader, &top, &args.clock, &compiler_state)
+        .with_context(|| format!("Failed to read {:?}", args.infile))?;
+    let mut covered = 0;
+    let mut total = 0;
+    for entry in &report {
+        println!("{}:{}: {}", entry.file, entry.line, entry.description);
+        for (branch, cycles) in &entry.branches {
+            total += 1;
+            if *cycles == 0 {
+                println!("    {branch}: NOT COVERED");
+            } else {
+                covered += 1;
+                println!("    {branch}: {cycles} cycles");
+            }
+        }
+    }
+
+    if total == 0 {
+        println!("No coverage signals found. Was the design compiled with --coverage?");
+    } else {
+        println!(
+            "{covered} of {total} branches covered ({:.1}%)",
+            100. * covered as f64 / total as f64
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use clap::Parser;
+use color_eyre::{
+    eyre::{anyhow, bail, Context},
+    Result,
+};
+use spade::compiler_state::CompilerState;
+use spade_common::{location_info::WithLocation, name::Path};
+use vcd_translate::golden::{compare, record_vcd, Trace};
+
+#[derive(clap::Args)]
+struct TraceArgs {
+    /// The VCD file of the simulation
+    infile: PathBuf,
+    #[clap(short)]
+    state_file: PathBuf,
+    #[clap(short = 't')]
+    top: String,
+    /// The name of the clock port of the top unit
+    #[clap(short = 'c', default_value = "clk")]
+    clock: String,
+}
+
+/// Records the values of the ports and registers of a unit at every clock cycle and
+/// compares them against previously recorded golden traces
+#[derive(clap::Parser)]
+enum CliArgs {
+    /// Record a golden trace
+    Record {
+        #[clap(flatten)]
+        args: TraceArgs,
+        #[clap(short = 'o', default_value = "trace.json")]
+        outfile: PathBuf,
+    },
+    /// Compare a simulation against a golden trace
+    Compare {
+        #[clap(flatten)]
+        args: TraceArgs,
+        #[clap(short = 'g')]
+        golden: PathBuf,
+    },
+}
+
+fn record(args: &TraceArgs) -> Result<Trace> {
+    let reader = BufReader::new(
+        File::open(&args.infile).with_context(|| format!("Failed to open {:?}", args.infile))?,
+    );
+
+    let state_file = std::fs::read_to_string(&args.state_file)
+        .with_context(|| format!("Failed to read state file {:?}", args.state_file))?;
+
+    let compiler_state: CompilerState = ron::from_str(&state_file)
+        .with_context(|| format!("failed to decode compiler state in {:?}", args.state_file))?;
+
+    let top_path = Path::from_strs(&args.top.split("::").collect::<Vec<_>>()).nowhere();
+    let (top, head) = compiler_state
+        .symtab
+        .symtab()
+        .lookup_unit(&top_path)
+        .map_err(|_e| anyhow!("Did not find a unit named {}", args.top))?;
+
+    record_vcd(reader, &top, &head, &args.clock, &compiler_state)
+        .with_context(|| format!("Failed to record a trace from {:?}", args.infile))
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+
+    match CliArgs::parse() {
+        CliArgs::Record { args, outfile } => {
+            let trace = record(&args)?;
+            std::fs::write(&outfile, serde_json::to_string_pretty(&trace)?)
+                .with_context(|| format!("Failed to write trace to {outfile:?}"))?;
+        }
+        CliArgs::Compare { args, golden } => {
+            let golden_trace: Trace = serde_json::from_str(
+                &std::fs::read_to_string(&golden)
+                    .with_context(|| format!("Failed to read golden trace {golden:?}"))?,
+            )
+            .with_context(|| format!("Failed to decode golden trace {golden:?}"))?;
+
+            let trace = record(&args)?;
+            if let Some(divergence) = compare(&golden_trace, &trace)? {
+                print!("{divergence}");
+                bail!("{} diverges from {golden:?}", args.infile.to_string_lossy());
+            }
+            println!("{} cycles match {golden:?}", trace.cycles.len());
+        }
+    }
+
+    Ok(())
+}
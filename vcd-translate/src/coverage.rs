@@ -0,0 +1,392 @@
+//! Computes functional coverage from a VCD file of a design compiled with
+//! `--coverage`. The coverage signals inserted by the compiler are found in the VCD
+//! using the coverage points stored in the compiler state, and the value of every
+//! signal is sampled at each rising edge of the clock of the top unit.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+
+use color_eyre::eyre::Result;
+use spade::compiler_state::CompilerState;
+use spade_common::{location_info::Loc, name::NameID};
+use spade_mir::{
+    coverage::{CoverageKind, CoveragePoint},
+    enum_util::tag_size,
+};
+use spade_types::ConcreteType;
+use vcd::{IdCode, ScopeItem, Value};
+
+use crate::sampling::{extend, normalize_name, top_scope, ClockSampler};
+
+#[derive(Debug, Clone)]
+enum GroupKind {
+    /// The signals of each arm of a match. Arms without a signal in the VCD are None
+    Match {
+        arms: Vec<Option<IdCode>>,
+    },
+    If {
+        cond: IdCode,
+    },
+    Enum {
+        state: IdCode,
+        size: usize,
+        variants: Vec<String>,
+    },
+}
+
+/// The coverage signals of a single `match`, `if` or register in a single instance
+#[derive(Debug, Clone)]
+struct Group {
+    /// The location of each branch. The arms of a match are at their patterns, the
+    /// branches of other groups are all at the same location
+    locs: Vec<Loc<()>>,
+    kind: GroupKind,
+    cycles: Vec<u64>,
+}
+
+impl Group {
+    fn new(loc: Loc<()>, kind: GroupKind) -> Self {
+        let branches = match &kind {
+            GroupKind::Match { arms } => arms.len(),
+            GroupKind::If { .. } => 2,
+            GroupKind::Enum { variants, .. } => variants.len(),
+        };
+        Self::with_locs(vec![loc; branches], kind)
+    }
+
+    fn with_locs(locs: Vec<Loc<()>>, kind: GroupKind) -> Self {
+        Self {
+            cycles: vec![0; locs.len()],
+            locs,
+            kind,
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.kind {
+            GroupKind::Match { .. } => "match",
+            GroupKind::If { .. } => "if",
+            GroupKind::Enum { .. } => "register",
+        }
+    }
+
+    fn branch_names(&self) -> Vec<String> {
+        match &self.kind {
+            GroupKind::Match { arms } => (0..arms.len()).map(|i| format!("arm {i}")).collect(),
+            GroupKind::If { .. } => vec!["true".to_string(), "false".to_string()],
+            GroupKind::Enum { variants, .. } => variants.clone(),
+        }
+    }
+}
+
+fn is_high(value: Option<&Vec<Value>>) -> Option<bool> {
+    match value.map(|v| v.as_slice()) {
+        Some([Value::V1]) => Some(true),
+        Some([Value::V0]) => Some(false),
+        _ => None,
+    }
+}
+
+/// The arm taken by a match is the first arm whose condition is high. If the condition
+/// of an earlier arm is unknown, so is the arm that was taken
+pub fn selected_arm(conditions: &[Option<bool>]) -> Option<usize> {
+    for (i, condition) in conditions.iter().enumerate() {
+        match condition {
+            Some(true) => return Some(i),
+            Some(false) => {}
+            None => return None,
+        }
+    }
+    None
+}
+
+/// Returns the variant of an enum with `variants` variants stored in a `size` bit wide
+/// signal with the value `value`
+pub fn enum_variant(value: &[Value], size: usize, variants: usize) -> Option<usize> {
+    let mut tag = 0;
+    for bit in extend(value, size).iter().take(tag_size(variants)) {
+        tag = match bit {
+            Value::V0 => tag << 1,
+            Value::V1 => (tag << 1) | 1,
+            Value::X | Value::Z => return None,
+        };
+    }
+    (tag < variants).then_some(tag)
+}
+
+/// A source code location along with the number of clock cycles in which each of its
+/// branches was taken
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageEntry {
+    pub file: String,
+    pub line: usize,
+    pub description: &'static str,
+    /// (branch, number of cycles)
+    pub branches: Vec<(String, u64)>,
+}
+
+pub struct CoverageTracker {
+    groups: Vec<Group>,
+    sampler: ClockSampler,
+}
+
+impl CoverageTracker {
+    /// Finds the coverage signals in the VCD scopes `items` of a simulation of `top`
+    /// clocked by `clock`. The outermost scope is assumed to be the top module
+    pub fn new(
+        top: &NameID,
+        clock: &str,
+        items: &[ScopeItem],
+        state: &CompilerState,
+    ) -> Result<Self> {
+        let top_scope = top_scope(items)?;
+        let sampler = ClockSampler::new(top, clock, top_scope)?;
+
+        let mut groups = vec![];
+        collect_groups(
+            top,
+            &mut groups,
+            &top_scope.children,
+            std::slice::from_ref(&top_scope.identifier),
+            top,
+            state,
+        );
+        Ok(Self { groups, sampler })
+    }
+
+    pub fn change(&mut self, id: IdCode, value: Vec<Value>) {
+        if self.sampler.change(id, value) {
+            self.sample();
+        }
+    }
+
+    pub fn timestamp(&mut self) {
+        self.sampler.timestamp();
+    }
+
+    /// The branch of `group` taken in the clock cycle which ends at the current rising edge
+    fn taken_branch(&self, group: &Group) -> Option<usize> {
+        match &group.kind {
+            GroupKind::Match { arms } => selected_arm(
+                &arms
+                    .iter()
+                    .map(|arm| arm.and_then(|id| is_high(self.sampler.value(&id))))
+                    .collect::<Vec<_>>(),
+            ),
+            GroupKind::If { cond } => {
+                is_high(self.sampler.value(cond)).map(|high| if high { 0 } else { 1 })
+            }
+            GroupKind::Enum {
+                state,
+                size,
+                variants,
+            } => self
+                .sampler
+                .value(state)
+                .and_then(|value| enum_variant(value, *size, variants.len())),
+        }
+    }
+
+    fn sample(&mut self) {
+        let taken = self
+            .groups
+            .iter()
+            .map(|group| self.taken_branch(group))
+            .collect::<Vec<_>>();
+        for (group, taken) in self.groups.iter_mut().zip(taken) {
+            if let Some(taken) = taken {
+                group.cycles[taken] += 1;
+            }
+        }
+    }
+
+    /// Combines the cycle counts of all instances of each coverage point and sorts them
+    /// by their location in the source code
+    pub fn report(&self, state: &CompilerState) -> Vec<CoverageEntry> {
+        let mut entries: BTreeMap<_, CoverageEntry> = BTreeMap::new();
+        for group in &self.groups {
+            let branches = group
+                .locs
+                .iter()
+                .zip(group.branch_names())
+                .zip(&group.cycles);
+            for ((loc, name), cycles) in branches {
+                let (file, content) = &state.code[loc.file_id];
+                let start = loc.span.start().to_usize();
+                let line = content[..start].matches('\n').count() + 1;
+
+                let entry = entries
+                    .entry((file.clone(), line, start, group.description()))
+                    .or_insert_with(|| CoverageEntry {
+                        file: file.clone(),
+                        line,
+                        description: group.description(),
+                        branches: vec![],
+                    });
+                match entry.branches.iter_mut().find(|(n, _)| *n == name) {
+                    Some((_, total)) => *total += cycles,
+                    None => entry.branches.push((name, *cycles)),
+                }
+            }
+        }
+        entries.into_values().collect()
+    }
+}
+
+/// Reads the VCD of a simulation of `top` clocked by `clock` from `reader` and reports
+/// the coverage of the design
+pub fn coverage_from_vcd(
+    reader: impl BufRead,
+    top: &NameID,
+    clock: &str,
+    state: &CompilerState,
+) -> Result<Vec<CoverageEntry>> {
+    let mut parser = vcd::Parser::new(reader);
+    let header = parser.parse_header()?;
+
+    let mut tracker = CoverageTracker::new(top, clock, &header.items, state)?;
+    for command_result in parser {
+        use vcd::Command::*;
+        match command_result? {
+            Timestamp(_) => tracker.timestamp(),
+            ChangeScalar(id, value) => tracker.change(id, vec![value]),
+            ChangeVector(id, value) => tracker.change(id, value),
+            _ => {}
+        }
+    }
+
+    Ok(tracker.report(state))
+}
+
+fn variant_names(
+    top: &NameID,
+    hierarchy: &[String],
+    signal: &str,
+    variants: usize,
+    state: &CompilerState,
+) -> Vec<String> {
+    // The type of the coverage signal is not known to the compiler, but the type of
+    // the register it aliases is
+    let register = signal.trim_end_matches("__cov_state");
+    let mut full_path = Vec::from(&hierarchy[1..]);
+    full_path.push(register.to_string());
+
+    match state.type_of_hierarchical_value(top, &full_path) {
+        Ok(ConcreteType::Enum { options }) if options.len() == variants => options
+            .iter()
+            .map(|(name, _)| format!("{}", name.1))
+            .collect(),
+        _ => (0..variants).map(|i| format!("variant {i}")).collect(),
+    }
+}
+
+/// The location of the pattern of an arm, and its signal if it is in the VCD
+type MatchArm = (Loc<()>, Option<IdCode>);
+
+fn collect_groups(
+    top: &NameID,
+    groups: &mut Vec<Group>,
+    items: &[ScopeItem],
+    hierarchy: &[String],
+    unit: &NameID,
+    state: &CompilerState,
+) {
+    let points = state
+        .mir_context
+        .get(unit)
+        .map(|ctx| {
+            ctx.coverage_points
+                .iter()
+                .map(|p| (p.signal.as_str(), p))
+                .collect::<HashMap<_, _>>()
+        })
+        .unwrap_or_default();
+
+    // Arms of the same match are grouped by the name of the match
+    let mut matches: BTreeMap<String, Vec<MatchArm>> = BTreeMap::new();
+
+    for item in items {
+        match item {
+            ScopeItem::Scope(scope) => {
+                let inner = state
+                    .instance_map
+                    .inner
+                    .get(unit)
+                    .and_then(|instances| instances.get(normalize_name(&scope.identifier)));
+                if let Some(inner) = inner {
+                    let mut new_path = Vec::from(hierarchy);
+                    new_path.push(scope.identifier.clone());
+                    collect_groups(top, groups, &scope.children, &new_path, inner, state);
+                }
+            }
+            ScopeItem::Var(var) => {
+                let name = normalize_name(&var.reference);
+                let Some(CoveragePoint { signal, kind, loc }) = points.get(name) else {
+                    continue;
+                };
+                match kind {
+                    CoverageKind::MatchArm { arm, arms } => {
+                        let prefix = signal.split("__cov_arm").next().unwrap_or_default();
+                        let signals = matches
+                            .entry(prefix.to_string())
+                            .or_insert_with(|| vec![(*loc, None); *arms]);
+                        signals[*arm] = (*loc, Some(var.code));
+                    }
+                    CoverageKind::If => {
+                        groups.push(Group::new(*loc, GroupKind::If { cond: var.code }))
+                    }
+                    CoverageKind::EnumRegister { variants } => groups.push(Group::new(
+                        *loc,
+                        GroupKind::Enum {
+                            state: var.code,
+                            size: var.size as usize,
+                            variants: variant_names(top, hierarchy, signal, *variants, state),
+                        },
+                    )),
+                }
+            }
+        }
+    }
+
+    groups.extend(matches.into_values().map(|arms| {
+        let (locs, arms) = arms.into_iter().unzip();
+        Group::with_locs(locs, GroupKind::Match { arms })
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_high_arm_is_selected() {
+        assert_eq!(
+            selected_arm(&[Some(false), Some(false), Some(true)]),
+            Some(2)
+        );
+        assert_eq!(selected_arm(&[Some(true), Some(true)]), Some(0));
+        assert_eq!(selected_arm(&[Some(false), Some(false)]), None);
+    }
+
+    #[test]
+    fn unknown_earlier_arm_makes_the_selected_arm_unknown() {
+        assert_eq!(selected_arm(&[Some(false), None, Some(true)]), None);
+        assert_eq!(selected_arm(&[Some(true), None]), Some(0));
+    }
+
+    #[test]
+    fn enum_variant_is_read_from_msbs() {
+        use Value::*;
+        // 3 variants means a 2 bit tag
+        assert_eq!(enum_variant(&[V1, V0, V1, V1], 4, 3), Some(2));
+        assert_eq!(enum_variant(&[V0, V1, V1, V1], 4, 3), Some(1));
+        assert_eq!(enum_variant(&[V1, V1, V0, V0], 4, 3), None);
+    }
+
+    #[test]
+    fn shortened_enum_values_are_extended() {
+        use Value::*;
+        assert_eq!(enum_variant(&[V1, V1], 4, 3), Some(0));
+        assert_eq!(enum_variant(&[X, V1], 4, 3), None);
+    }
+}
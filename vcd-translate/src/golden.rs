@@ -0,0 +1,405 @@
+//! Recording and comparison of golden traces. A trace contains the Spade values of
+//! the ports and registers of a unit at every rising edge of its clock, which allows
+//! checking that a refactored unit behaves exactly like the original one.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use color_eyre::eyre::{anyhow, Result};
+use num::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use spade::compiler_state::CompilerState;
+use spade_common::name::NameID;
+use spade_hir::{Parameter, UnitHead};
+use spade_hir_lowering::MirLowerable;
+use spade_mir::codegen::{mangle_input, mangle_output};
+use spade_types::ConcreteType;
+use vcd::{IdCode, ScopeItem, Value};
+
+use crate::sampling::{extend, normalize_name, top_scope, ClockSampler};
+use crate::translation::translate_value;
+
+/// A value in a trace. Structs are split into their fields in order to report which
+/// fields differ between two traces
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum TraceValue {
+    Value(String),
+    Struct(Vec<(String, TraceValue)>),
+}
+
+impl TraceValue {
+    fn from_bits(ty: &ConcreteType, bits: &[Value]) -> Self {
+        match ty {
+            ConcreteType::Struct { name: _, members } => {
+                let mut offset = 0;
+                let fields = members
+                    .iter()
+                    .map(|(name, ty)| {
+                        let end = offset + bit_count(ty);
+                        let value = TraceValue::from_bits(ty, &bits[offset..end]);
+                        offset = end;
+                        (name.0.clone(), value)
+                    })
+                    .collect();
+                TraceValue::Struct(fields)
+            }
+            _ => TraceValue::Value(translate_value(ty, bits)),
+        }
+    }
+
+    fn differences(&self, other: &TraceValue, path: &str, result: &mut Vec<Difference>) {
+        match (self, other) {
+            (TraceValue::Struct(expected), TraceValue::Struct(got))
+                if expected
+                    .iter()
+                    .map(|(n, _)| n)
+                    .eq(got.iter().map(|(n, _)| n)) =>
+            {
+                for ((name, expected), (_, got)) in expected.iter().zip(got) {
+                    expected.differences(got, &format!("{path}.{name}"), result)
+                }
+            }
+            (expected, got) => {
+                if expected != got {
+                    result.push(Difference {
+                        path: path.to_string(),
+                        expected: expected.to_string(),
+                        got: got.to_string(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TraceValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceValue::Value(v) => write!(f, "{v}"),
+            TraceValue::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, value)| format!("{name}: {value}"))
+                    .collect::<Vec<_>>();
+                write!(f, "{{{}}}", fields.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub top: String,
+    /// The verilog names of the signals in the trace
+    pub signals: Vec<String>,
+    /// The values of the signals at each rising edge of the clock
+    pub cycles: Vec<Vec<TraceValue>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// The signal, followed by the names of the struct fields which differ
+    pub path: String,
+    pub expected: String,
+    pub got: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub cycle: usize,
+    pub differences: Vec<Difference>,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "First divergence in cycle {}", self.cycle)?;
+        for Difference {
+            path,
+            expected,
+            got,
+        } in &self.differences
+        {
+            writeln!(f, "    {path}: expected {expected}, got {got}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares `new` against the golden trace `golden` and returns the first cycle in
+/// which they differ
+pub fn compare(golden: &Trace, new: &Trace) -> Result<Option<Divergence>> {
+    let new_indices = new
+        .signals
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name, i))
+        .collect::<HashMap<_, _>>();
+
+    let indices = golden
+        .signals
+        .iter()
+        .map(|name| {
+            new_indices
+                .get(name)
+                .copied()
+                .ok_or_else(|| anyhow!("{name} is in the golden trace but not in the new trace"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    for (cycle, (expected, got)) in golden.cycles.iter().zip(&new.cycles).enumerate() {
+        let mut differences = vec![];
+        for (name, (expected, new_idx)) in golden.signals.iter().zip(expected.iter().zip(&indices))
+        {
+            expected.differences(&got[*new_idx], name, &mut differences);
+        }
+        if !differences.is_empty() {
+            return Ok(Some(Divergence { cycle, differences }));
+        }
+    }
+
+    if golden.cycles.len() != new.cycles.len() {
+        return Ok(Some(Divergence {
+            cycle: golden.cycles.len().min(new.cycles.len()),
+            differences: vec![Difference {
+                path: "trace length".to_string(),
+                expected: format!("{} cycles", golden.cycles.len()),
+                got: format!("{} cycles", new.cycles.len()),
+            }],
+        }));
+    }
+
+    Ok(None)
+}
+
+fn bit_count(ty: &ConcreteType) -> usize {
+    ty.to_mir_type()
+        .size()
+        .to_usize()
+        .unwrap_or_else(|| panic!("Value is wider than {} bits", usize::MAX))
+}
+
+struct Signal {
+    name: String,
+    id: IdCode,
+    size: usize,
+    ty: ConcreteType,
+}
+
+/// Records a trace from the changes of a VCD file
+pub struct TraceRecorder {
+    top: String,
+    sampler: ClockSampler,
+    signals: Vec<Signal>,
+    cycles: Vec<Vec<TraceValue>>,
+}
+
+impl TraceRecorder {
+    /// Finds the ports and registers of `top` in the VCD scopes `items`. The outermost
+    /// scope is assumed to be the top module
+    pub fn new(
+        top: &NameID,
+        head: &UnitHead,
+        clock: &str,
+        items: &[ScopeItem],
+        state: &CompilerState,
+    ) -> Result<Self> {
+        let top_scope = top_scope(items)?;
+        let sampler = ClockSampler::new(top, clock, top_scope)?;
+
+        let mut ports = head
+            .inputs
+            .0
+            .iter()
+            .flat_map(
+                |Parameter {
+                     name,
+                     ty: _,
+                     no_mangle,
+                 }| {
+                    [
+                        mangle_input(no_mangle, &name.0),
+                        mangle_output(no_mangle, &name.0),
+                    ]
+                },
+            )
+            .collect::<Vec<_>>();
+        ports.push("output__".to_string());
+
+        let registers = state
+            .mir_context
+            .get(top)
+            .map(|ctx| ctx.registers.clone())
+            .unwrap_or_default();
+
+        let mut signals = vec![];
+        for item in &top_scope.children {
+            let ScopeItem::Var(var) = item else { continue };
+            let name = normalize_name(&var.reference);
+
+            if var.code != sampler.clock() && ports.iter().chain(&registers).any(|n| n == name) {
+                // Values without a spade type, such as values of zero size, are skipped
+                if let Ok(ty) = state.type_of_hierarchical_value(top, &[name.to_string()]) {
+                    signals.push(Signal {
+                        name: name.to_string(),
+                        id: var.code,
+                        size: var.size as usize,
+                        ty,
+                    })
+                }
+            }
+        }
+        signals.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self {
+            top: format!("{}", top.1),
+            sampler,
+            signals,
+            cycles: vec![],
+        })
+    }
+
+    pub fn change(&mut self, id: IdCode, value: Vec<Value>) {
+        if self.sampler.change(id, value) {
+            self.record_cycle();
+        }
+    }
+
+    pub fn timestamp(&mut self) {
+        self.sampler.timestamp();
+    }
+
+    /// Samples the values from before the clock edge, i.e. before any changes
+    /// in the current timestamp
+    fn record_cycle(&mut self) {
+        let cycle = self
+            .signals
+            .iter()
+            .map(|signal| {
+                let value = self
+                    .sampler
+                    .value(&signal.id)
+                    .cloned()
+                    .unwrap_or_else(|| vec![Value::X]);
+                let bits = extend(&value, signal.size);
+                TraceValue::from_bits(&signal.ty, &bits)
+            })
+            .collect();
+        self.cycles.push(cycle)
+    }
+
+    pub fn finish(self) -> Trace {
+        Trace {
+            top: self.top,
+            signals: self.signals.into_iter().map(|s| s.name).collect(),
+            cycles: self.cycles,
+        }
+    }
+}
+
+/// Records a trace of `top` from the VCD read from `reader`
+pub fn record_vcd(
+    reader: impl BufRead,
+    top: &NameID,
+    head: &UnitHead,
+    clock: &str,
+    state: &CompilerState,
+) -> Result<Trace> {
+    let mut parser = vcd::Parser::new(reader);
+    let header = parser.parse_header()?;
+
+    let mut recorder = TraceRecorder::new(top, head, clock, &header.items, state)?;
+    for command_result in parser {
+        use vcd::Command::*;
+        match command_result? {
+            Timestamp(_) => recorder.timestamp(),
+            ChangeScalar(id, value) => recorder.change(id, vec![value]),
+            ChangeVector(id, value) => recorder.change(id, value),
+            _ => {}
+        }
+    }
+
+    Ok(recorder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(s: &str) -> TraceValue {
+        TraceValue::Value(s.to_string())
+    }
+
+    fn trace(signals: &[&str], cycles: Vec<Vec<TraceValue>>) -> Trace {
+        Trace {
+            top: "top".to_string(),
+            signals: signals.iter().map(|s| s.to_string()).collect(),
+            cycles,
+        }
+    }
+
+    #[test]
+    fn identical_traces_do_not_diverge() {
+        let golden = trace(&["a"], vec![vec![value("1")], vec![value("2")]]);
+        assert_eq!(compare(&golden, &golden.clone()).unwrap(), None);
+    }
+
+    #[test]
+    fn first_divergent_cycle_is_reported_with_struct_fields() {
+        let s = |x: &str, y: &str| {
+            TraceValue::Struct(vec![
+                ("x".to_string(), value(x)),
+                ("y".to_string(), value(y)),
+            ])
+        };
+        let golden = trace(
+            &["a", "s"],
+            vec![
+                vec![value("1"), s("1", "2")],
+                vec![value("1"), s("1", "3")],
+                vec![value("2"), s("1", "4")],
+            ],
+        );
+        // Signal order in the traces does not matter
+        let new = trace(
+            &["s", "a"],
+            vec![
+                vec![s("1", "2"), value("1")],
+                vec![s("1", "4"), value("1")],
+                vec![s("1", "5"), value("3")],
+            ],
+        );
+
+        assert_eq!(
+            compare(&golden, &new).unwrap(),
+            Some(Divergence {
+                cycle: 1,
+                differences: vec![Difference {
+                    path: "s.y".to_string(),
+                    expected: "3".to_string(),
+                    got: "4".to_string()
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn shorter_trace_diverges() {
+        let golden = trace(&["a"], vec![vec![value("1")], vec![value("2")]]);
+        let new = trace(&["a"], vec![vec![value("1")]]);
+        assert_eq!(compare(&golden, &new).unwrap().map(|d| d.cycle), Some(1));
+    }
+
+    #[test]
+    fn trace_json_round_trips() {
+        let golden = trace(
+            &["a", "s"],
+            vec![vec![
+                value("1"),
+                TraceValue::Struct(vec![("x".to_string(), value("Option::Some(1)"))]),
+            ]],
+        );
+        let json = serde_json::to_string(&golden).unwrap();
+        assert_eq!(serde_json::from_str::<Trace>(&json).unwrap(), golden);
+    }
+}
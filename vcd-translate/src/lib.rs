@@ -1 +1,4 @@
+pub mod coverage;
+pub mod golden;
+pub mod sampling;
 pub mod translation;
@@ -0,0 +1,133 @@
+//! Sampling of the signals in a VCD at the rising edges of a clock. Used by both the
+//! coverage report and golden traces, which must agree on what a clock cycle is.
+
+use std::collections::HashMap;
+
+use color_eyre::eyre::{anyhow, Result};
+use spade_common::name::NameID;
+use spade_mir::codegen::mangle_input;
+use vcd::{IdCode, Scope, ScopeItem, Value};
+
+/// The name of a VCD variable without the escaping of escaped Verilog identifiers
+pub fn normalize_name(name: &str) -> &str {
+    name.trim_start_matches('\\').trim_end()
+}
+
+/// Extends a VCD value to `size` bits. VCD files may omit leading bits of vectors, these
+/// are extended according to the VCD rules
+pub fn extend(value: &[Value], size: usize) -> Vec<Value> {
+    let fill = match value.first() {
+        Some(Value::V1) | None => Value::V0,
+        Some(other) => *other,
+    };
+    std::iter::repeat(fill)
+        .take(size.saturating_sub(value.len()))
+        .chain(value.iter().cloned())
+        .collect()
+}
+
+/// The outermost scope of the VCD scopes `items`, which is assumed to be the top module
+pub fn top_scope(items: &[ScopeItem]) -> Result<&Scope> {
+    items
+        .iter()
+        .find_map(|item| match item {
+            ScopeItem::Scope(scope) => Some(scope),
+            ScopeItem::Var(_) => None,
+        })
+        .ok_or_else(|| anyhow!("Found no modules in the VCD file"))
+}
+
+/// Tracks the values of the signals in a VCD and detects the rising edges of a clock
+pub struct ClockSampler {
+    clock: IdCode,
+    values: HashMap<IdCode, Vec<Value>>,
+    /// The values of the signals which changed in the current timestamp from before
+    /// they changed. None if the signal had no value before
+    previous: HashMap<IdCode, Option<Vec<Value>>>,
+}
+
+impl ClockSampler {
+    /// Samples at the rising edges of the clock port `clock` of `top`, which is found in
+    /// the scope of the top module
+    pub fn new(top: &NameID, clock: &str, top_scope: &Scope) -> Result<Self> {
+        let clock_id = top_scope
+            .children
+            .iter()
+            .find_map(|item| match item {
+                ScopeItem::Var(var) => {
+                    let name = normalize_name(&var.reference);
+                    (name == clock || name == mangle_input(&None, clock)).then_some(var.code)
+                }
+                ScopeItem::Scope(_) => None,
+            })
+            .ok_or_else(|| anyhow!("Did not find a clock named {clock} in {top}"))?;
+
+        Ok(Self {
+            clock: clock_id,
+            values: HashMap::new(),
+            previous: HashMap::new(),
+        })
+    }
+
+    /// Records a change of the signal `id`. Returns true if the change is a rising edge of
+    /// the clock, in which case [ClockSampler::value] gives the values of the clock cycle
+    /// which ends at the edge
+    pub fn change(&mut self, id: IdCode, value: Vec<Value>) -> bool {
+        let old = self.values.insert(id, value.clone());
+        let rising_edge =
+            id == self.clock && value == [Value::V1] && old.as_deref() != Some(&[Value::V1][..]);
+        self.previous.entry(id).or_insert(old);
+        rising_edge
+    }
+
+    pub fn clock(&self) -> IdCode {
+        self.clock
+    }
+
+    pub fn timestamp(&mut self) {
+        self.previous.clear();
+    }
+
+    /// The value of a signal from before any changes in the current timestamp
+    pub fn value(&self, id: &IdCode) -> Option<&Vec<Value>> {
+        match self.previous.get(id) {
+            Some(old) => old.as_ref(),
+            None => self.values.get(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_sampled_from_before_the_rising_edge() {
+        let (clk, x) = (IdCode::from(0u32), IdCode::from(1u32));
+        let mut sampler = ClockSampler {
+            clock: clk,
+            values: HashMap::new(),
+            previous: HashMap::new(),
+        };
+
+        assert!(!sampler.change(clk, vec![Value::V0]));
+        assert!(!sampler.change(x, vec![Value::V0]));
+        sampler.timestamp();
+        // x changes in the same timestamp as the edge, but after it
+        assert!(!sampler.change(x, vec![Value::V1]));
+        assert!(sampler.change(clk, vec![Value::V1]));
+        assert_eq!(sampler.value(&x), Some(&vec![Value::V0]));
+        sampler.timestamp();
+        // Repeated high values are not edges
+        assert!(!sampler.change(clk, vec![Value::V1]));
+        assert_eq!(sampler.value(&x), Some(&vec![Value::V1]));
+    }
+
+    #[test]
+    fn shortened_values_are_extended() {
+        use Value::*;
+        assert_eq!(extend(&[V1, V0], 4), vec![V0, V0, V1, V0]);
+        assert_eq!(extend(&[X, V1], 3), vec![X, X, V1]);
+        assert_eq!(extend(&[V1, V0], 2), vec![V1, V0]);
+    }
+}